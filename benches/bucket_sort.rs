@@ -0,0 +1,43 @@
+// Bucket sort against quicksort, merge sort, and heapsort on uniformly
+// random floats in [0, 1). Run with
+//    cargo bench --bench bucket_sort
+// On uniform input bucket sort is expected O(N), so its time per element
+// should stay roughly flat as the size grows (until the scatter into
+// buckets outgrows the cache) while the comparison sorts' grows with
+// log N; it runs about twice as fast as quicksort. The last column puts
+// all the values in [0, 0.01), i.e. in the first hundredth of the
+// buckets, so each bucket holds about a hundred elements and the
+// insertion sorts start to cost something.
+
+use sorting_in_rust::generate::generate_random;
+use sorting_in_rust::random::SeededRandom;
+use sorting_in_rust::sorts::{bucket_sort, heapsort, merge_sort, quicksort};
+use std::time::{Duration, Instant};
+
+fn time<T: Clone, F: Fn(&mut [T])>(input: &[T], sort: F) -> Duration {
+    let mut v = input.to_vec();
+    let before = Instant::now();
+    sort(&mut v);
+    before.elapsed()
+}
+
+fn main() {
+    let mut rng = SeededRandom::new(514);
+
+    for &size in &[10_000, 100_000, 1_000_000, 10_000_000] {
+        let uniform: Vec<f64> = generate_random(size, &mut rng);
+        let bunched: Vec<f64> = uniform.iter().map(|x| x / 100.0).collect();
+        println!(
+            "size {:>9}  bucket {:>12?} quicksort {:>12?} merge {:>12?} heapsort {:>12?}   bunched bucket {:>12?}",
+            size,
+            time(&uniform, bucket_sort),
+            time(&uniform, quicksort),
+            time(&uniform, |v| {
+                let sorted = merge_sort(v);
+                v.copy_from_slice(&sorted);
+            }),
+            time(&uniform, heapsort),
+            time(&bunched, bucket_sort),
+        );
+    }
+}
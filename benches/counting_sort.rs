@@ -0,0 +1,56 @@
+// Counting sort versus comparison sorts on byte and u16 keys, and on
+// i32s from `generate_random_array` in 0..N. Run with
+//    cargo bench --bench counting_sort
+// Counting sort is linear, so its time per element should stay flat as
+// the size grows. For u8 it should win at every size; for u16, clearing
+// the 65,536 counts dominates for small inputs, which is why `auto_sort`
+// only uses it from `COUNTING_SORT_U16_THRESHOLD` elements up. For the
+// i32s there are as many counts as values, so it's O(2N), and should
+// beat every O(N log N) sort by more and more as N grows.
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use sorting_in_rust::auto::auto_sort;
+use sorting_in_rust::generate::generate_random_array;
+use sorting_in_rust::random::SeededRandom;
+use sorting_in_rust::sorts::{counting_sort, heapsort, merge_sort, quicksort};
+use std::time::{Duration, Instant};
+
+fn time<T: Clone, F: Fn(&mut [T])>(input: &[T], sort: F) -> Duration {
+    let mut v = input.to_vec();
+    let before = Instant::now();
+    sort(&mut v);
+    before.elapsed()
+}
+
+fn main() {
+    let mut rng = StdRng::seed_from_u64(220);
+
+    for &size in &[1_000, 10_000, 100_000, 1_000_000] {
+        let bytes: Vec<u8> = (0..size).map(|_| rng.gen()).collect();
+        let shorts: Vec<u16> = (0..size).map(|_| rng.gen()).collect();
+        println!(
+            "size {:>8}  u8: auto_sort {:>12?} heapsort {:>12?}   u16: auto_sort {:>12?} heapsort {:>12?}",
+            size,
+            time(&bytes, auto_sort),
+            time(&bytes, heapsort),
+            time(&shorts, auto_sort),
+            time(&shorts, heapsort),
+        );
+    }
+
+    println!();
+    for &size in &[1_000, 10_000, 100_000, 1_000_000] {
+        let ints = generate_random_array(size, 0, size, &mut SeededRandom::new(511));
+        println!(
+            "size {:>8}  i32: counting {:>12?} quicksort {:>12?} merge {:>12?} heapsort {:>12?}",
+            size,
+            time(&ints, |v| counting_sort(v, 0, size)),
+            time(&ints, quicksort),
+            time(&ints, |v| {
+                let sorted = merge_sort(v);
+                v.copy_from_slice(&sorted);
+            }),
+            time(&ints, heapsort),
+        );
+    }
+}
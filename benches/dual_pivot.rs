@@ -0,0 +1,54 @@
+// Single- versus dual-pivot quicksort, and three-way partitioning, on
+// random values and on values with lots of duplicates. Run with
+//    cargo bench --bench dual_pivot
+// The single-pivot sorts are the lab's `quicksort` (first element as
+// pivot) and `introsort` (median-of-three, so the same partitioning as
+// `try_quicksort` with `Pivot::MedianOfThree`). On duplicates, the lab's
+// quicksort goes quadratic and overflows the stack, so it only runs on
+// the uniform values there.
+//
+// On uniform values the two-way sorts take about the same time.
+// `quicksort_3way` is the slowest there, because most elements need two
+// comparisons (< pivot? if not, > pivot?). With only ten different
+// values it comes into its own, finishing off every copy of its pivot
+// value in one go, and dual-pivot quicksort, which skips sorting the
+// middle part whenever its pivots are equal, does about as well; both
+// take about half as long as introsort. The standard library's
+// pattern-defeating quicksort is several times faster than any of ours
+// on both.
+
+use sorting_in_rust::benchmark::Distribution;
+use sorting_in_rust::sorts::{dual_pivot_quicksort, introsort, quicksort, quicksort_3way};
+use std::time::{Duration, Instant};
+
+fn time<F: Fn(&mut [i32])>(input: &[i32], sort: F) -> Duration {
+    let mut v = input.to_vec();
+    let before = Instant::now();
+    sort(&mut v);
+    before.elapsed()
+}
+
+fn main() {
+    for &size in &[10_000, 100_000, 1_000_000, 10_000_000] {
+        let uniform = Distribution::Uniform.generate(size, 516);
+        println!(
+            "uniform    size {:>9}  dual-pivot {:>12?}  3-way {:>12?}  introsort {:>12?}  quicksort {:>12?}  std unstable {:>12?}",
+            size,
+            time(&uniform, dual_pivot_quicksort),
+            time(&uniform, quicksort_3way),
+            time(&uniform, introsort),
+            time(&uniform, quicksort),
+            time(&uniform, |v| v.sort_unstable()),
+        );
+        let few = Distribution::FewUnique.generate(size, 516);
+        println!(
+            "few-unique size {:>9}  dual-pivot {:>12?}  3-way {:>12?}  introsort {:>12?}  {:>22}  std unstable {:>12?}",
+            size,
+            time(&few, dual_pivot_quicksort),
+            time(&few, quicksort_3way),
+            time(&few, introsort),
+            "",
+            time(&few, |v| v.sort_unstable()),
+        );
+    }
+}
@@ -0,0 +1,49 @@
+// Bitonic sort on the GPU against the same network on the CPU, and
+// against radix sort and the standard library's sort, on random u32s and
+// f32s. Run with
+//    cargo bench --features gpu --bench gpu
+// Before timing each size we check the GPU's answer against the CPU
+// network's (`gpu::verify_u32`). The GPU times include copying the data
+// over and back, which on a real GPU is most of the cost at small sizes.
+// With a software adapter like llvmpipe the "GPU" is the CPU running
+// shader code, so don't expect it to win there.
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use sorting_in_rust::gpu::{verify_u32, GpuSorter};
+use sorting_in_rust::sorts::{bitonic_sort, radix_sort_lsd};
+use std::time::{Duration, Instant};
+
+fn time<T: Clone, F: FnMut(&mut [T])>(input: &[T], mut sort: F) -> Duration {
+    let mut v = input.to_vec();
+    let before = Instant::now();
+    sort(&mut v);
+    before.elapsed()
+}
+
+fn main() {
+    let gpu = match GpuSorter::new() {
+        Ok(gpu) => gpu,
+        Err(e) => {
+            println!("{}", e);
+            return;
+        }
+    };
+    println!("adapter: {}", gpu.adapter());
+    let mut rng = StdRng::seed_from_u64(234);
+
+    for &size in &[1 << 10, 1 << 14, 1 << 18, 1 << 20] {
+        let ints: Vec<u32> = (0..size).map(|_| rng.gen()).collect();
+        let floats: Vec<f32> = (0..size).map(|_| rng.gen_range(-1e6, 1e6)).collect();
+        verify_u32(&gpu, &ints).expect("the GPU and CPU networks disagree");
+        println!(
+            "size {:>8}  u32: gpu bitonic {:>12?} cpu bitonic {:>12?} radix {:>12?} std unstable {:>12?}   f32: gpu bitonic {:>12?} std {:>12?}",
+            size,
+            time(&ints, |v| gpu.sort_u32(v).unwrap()),
+            time(&ints, bitonic_sort),
+            time(&ints, radix_sort_lsd),
+            time(&ints, |v| v.sort_unstable()),
+            time(&floats, |v| gpu.sort_f32(v).unwrap()),
+            time(&floats, |v| v.sort_unstable_by(|a, b| a.total_cmp(b))),
+        );
+    }
+}
@@ -0,0 +1,58 @@
+// Compare heapsort and the k-way merge across heap arities. Run with
+//    cargo bench --bench heap_arity
+// Since this is just timing with `Instant` (like `main.rs` does), you'll
+// want to run it a few times and not take any single number too
+// seriously.
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use sorting_in_rust::merge::kway_merge_d;
+use sorting_in_rust::sorts::heapsort_d;
+use std::time::{Duration, Instant};
+
+const ARITIES: [usize; 3] = [2, 4, 8];
+const TRIALS: u32 = 5;
+
+fn random_vec(rng: &mut StdRng, len: usize) -> Vec<u64> {
+    (0..len).map(|_| rng.gen()).collect()
+}
+
+// Time `f` on a fresh copy of `input` for each trial and return the
+// fastest time, which is the least noisy estimate we have.
+fn best_of<F: FnMut(Vec<u64>)>(input: &[u64], mut f: F) -> Duration {
+    (0..TRIALS)
+        .map(|_| {
+            let v = input.to_vec();
+            let before = Instant::now();
+            f(v);
+            before.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+fn main() {
+    let mut rng = StdRng::seed_from_u64(206);
+
+    for &size in &[10_000, 100_000, 1_000_000] {
+        let input = random_vec(&mut rng, size);
+        for &arity in &ARITIES {
+            let elapsed = best_of(&input, |mut v| heapsort_d(&mut v, arity));
+            println!("heapsort      size {:>8} arity {}: {:?}", size, arity, elapsed);
+        }
+    }
+
+    for &lists in &[16, 256, 4096] {
+        let mut input = random_vec(&mut rng, 1_000_000);
+        let chunk = input.len() / lists;
+        for part in input.chunks_mut(chunk) {
+            part.sort_unstable();
+        }
+        for &arity in &ARITIES {
+            let elapsed = best_of(&input, |v| {
+                let parts = v.chunks(chunk).map(|c| c.to_vec()).collect();
+                kway_merge_d(parts, arity);
+            });
+            println!("k-way merge  lists {:>8} arity {}: {:?}", lists, arity, elapsed);
+        }
+    }
+}
@@ -0,0 +1,52 @@
+// Compare the two ways of building a heap: Floyd's bottom-up
+// construction (O(N)) and repeated insertion (O(N log N)). Run with
+//    cargo bench --bench heapify
+// We report both comparison counts (which don't depend on the machine)
+// and times (which do). The "per N" column makes the difference in
+// growth rates easy to see: it stays flat for Floyd, but grows with
+// log N for insertion.
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use sorting_in_rust::heap::{heapify_by_insertion, heapify_floyd};
+use sorting_in_rust::instrument::{count_comparisons, counted};
+use std::time::Instant;
+
+fn main() {
+    let mut rng = StdRng::seed_from_u64(207);
+
+    for &(name, increasing) in &[("random", false), ("increasing", true)] {
+        println!("{} input:", name);
+        for &size in &[1_000, 10_000, 100_000, 1_000_000] {
+            let input: Vec<u64> = if increasing {
+                (0..size).collect()
+            } else {
+                (0..size).map(|_| rng.gen()).collect()
+            };
+
+            let mut v = counted(&input);
+            let (_, floyd) = count_comparisons(|| heapify_floyd(&mut v));
+            let mut v = counted(&input);
+            let (_, insertion) = count_comparisons(|| heapify_by_insertion(&mut v));
+
+            let mut v = input.clone();
+            let before = Instant::now();
+            heapify_floyd(&mut v);
+            let floyd_time = before.elapsed();
+            let mut v = input.clone();
+            let before = Instant::now();
+            heapify_by_insertion(&mut v);
+            let insertion_time = before.elapsed();
+
+            println!(
+                "  size {:>8}  floyd {:>9} comparisons ({:.2} per N, {:?})  insertion {:>9} comparisons ({:.2} per N, {:?})",
+                size,
+                floyd,
+                floyd as f64 / size as f64,
+                floyd_time,
+                insertion,
+                insertion as f64 / size as f64,
+                insertion_time
+            );
+        }
+    }
+}
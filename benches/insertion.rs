@@ -0,0 +1,92 @@
+// Compare insertion sort and binary insertion sort. Run with
+//    cargo bench --bench insertion
+// Binary insertion sort makes O(N log N) comparisons instead of O(N^2),
+// but both do the same number of moves (one per inversion), so both are
+// still O(N^2). Watch the "per N" columns: comparisons per N stays
+// roughly flat (growing with log N) for binary insertion sort, while
+// moves per N doubles every time N doubles for both.
+//
+// The second part sorts a big batch of small arrays, which is where the
+// insertion sorts actually get used in practice (e.g., as the base case
+// of a hybrid sort), to compare their constant factors.
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use sorting_in_rust::instrument::{count_comparisons, counted, inversions};
+use sorting_in_rust::sorts::{
+    binary_insertion_sort, insertion_sort, pair_insertion_sort, sentinel_insertion_sort,
+};
+use std::time::{Duration, Instant};
+
+const BATCH_SIZE: usize = 100_000;
+
+type Sort = fn(&mut [u64]);
+
+// Sort every array in the batch (on a fresh copy) and return the total
+// time.
+fn time_batch(batch: &[Vec<u64>], sort: Sort) -> Duration {
+    let mut copies = batch.to_vec();
+    let before = Instant::now();
+    for v in copies.iter_mut() {
+        sort(v);
+    }
+    before.elapsed()
+}
+
+fn main() {
+    let mut rng = StdRng::seed_from_u64(209);
+
+    for &size in &[1_000, 2_000, 4_000, 8_000, 16_000] {
+        let input: Vec<u64> = (0..size).map(|_| rng.gen()).collect();
+        let moves = inversions(&input);
+
+        let mut v = counted(&input);
+        let (_, plain) = count_comparisons(|| insertion_sort(&mut v));
+        let mut v = counted(&input);
+        let (_, binary) = count_comparisons(|| binary_insertion_sort(&mut v));
+
+        let mut v = input.clone();
+        let before = Instant::now();
+        insertion_sort(&mut v);
+        let plain_time = before.elapsed();
+        let mut v = input.clone();
+        let before = Instant::now();
+        binary_insertion_sort(&mut v);
+        let binary_time = before.elapsed();
+
+        let per_n = |count: u64| count as f64 / size as f64;
+        println!(
+            "size {:>6}  moves {:>10} ({:>8.1} per N)  insertion {:>10} comparisons ({:>8.1} per N, {:?})  binary {:>8} comparisons ({:>5.1} per N, {:?})",
+            size,
+            moves,
+            per_n(moves),
+            plain,
+            per_n(plain),
+            plain_time,
+            binary,
+            per_n(binary),
+            binary_time
+        );
+    }
+
+    println!();
+    let sorts: [(&str, Sort); 4] = [
+        ("insertion", insertion_sort),
+        ("sentinel insertion", sentinel_insertion_sort),
+        ("binary insertion", binary_insertion_sort),
+        ("pair insertion", pair_insertion_sort),
+    ];
+    for &length in &[8, 16, 32, 64] {
+        let batch: Vec<Vec<u64>> = (0..BATCH_SIZE)
+            .map(|_| (0..length).map(|_| rng.gen()).collect())
+            .collect();
+        for &(name, sort) in &sorts {
+            println!(
+                "{} arrays of length {:>2}  {:<18} {:?}",
+                BATCH_SIZE,
+                length,
+                name,
+                time_batch(&batch, sort)
+            );
+        }
+    }
+}
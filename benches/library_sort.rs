@@ -0,0 +1,56 @@
+// Library sort with different gap factors on different kinds of input.
+// Run with
+//    cargo bench --bench library_sort
+// The "extra memory" column is the size of the final gapped array,
+// (1 + gap_factor)·N slots of `Option<u64>`, compared to the N·8 bytes
+// of the input itself. Compare the random and sorted rows to see how
+// (un)adaptive library sort is: sorted input is its worst case.
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use sorting_in_rust::sorts::{binary_insertion_sort, library_sort};
+use std::mem::size_of;
+use std::time::Instant;
+
+fn main() {
+    let mut rng = StdRng::seed_from_u64(212);
+    let size = 100_000;
+
+    let random: Vec<u64> = (0..size).map(|_| rng.gen()).collect();
+    let sorted: Vec<u64> = (0..size as u64).collect();
+    // Sorted, but with 1% of the elements swapped with random partners.
+    let mut nearly_sorted = sorted.clone();
+    for _ in 0..size / 100 {
+        let i = rng.gen_range(0, size);
+        let j = rng.gen_range(0, size);
+        nearly_sorted.swap(i, j);
+    }
+    let inputs = [
+        ("random", random),
+        ("sorted", sorted),
+        ("nearly sorted", nearly_sorted),
+    ];
+
+    for (name, input) in inputs.iter() {
+        println!("{} input, size {}:", name, size);
+        for &gap_factor in &[0.1, 0.5, 1.0, 2.0] {
+            let before = Instant::now();
+            let result = library_sort(input, gap_factor);
+            let elapsed = before.elapsed();
+            assert_eq!(size, result.len());
+
+            let slots = ((1.0 + gap_factor) * size as f64).ceil() as usize;
+            let extra = slots * size_of::<Option<u64>>();
+            println!(
+                "  gap factor {:>3}: {:>12?}  extra memory {:>8} bytes ({:.1}× the input)",
+                gap_factor,
+                elapsed,
+                extra,
+                extra as f64 / (size * size_of::<u64>()) as f64
+            );
+        }
+        let mut v = input.clone();
+        let before = Instant::now();
+        binary_insertion_sort(&mut v);
+        println!("  binary insertion sort: {:?}", before.elapsed());
+    }
+}
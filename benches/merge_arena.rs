@@ -0,0 +1,90 @@
+// Merge sort with and without a `MergeArena`, on a batch workload: lots
+// of small arrays, sorted by several threads at once. Run with
+//    cargo bench --bench merge_arena
+// `merge_sort` allocates about two vectors per element; `merge_sort_in`
+// with an arena kept across the batch allocates nothing after the first
+// array. A fresh arena per array is in between: one allocation per
+// array.
+//
+// Either arena version takes a bit over half as long as `merge_sort`.
+// Most of that comes from doing one allocation per array rather than
+// thousands. Reusing the arena as well only helps noticeably on the
+// smallest arrays, where that one allocation is a bigger share of the
+// work. (Those numbers are from a single-core machine, so the "several
+// threads" are taking turns. With real cores competing for the
+// allocator, expect `merge_sort` to fall further behind.)
+//
+// With `--features bump-arena` there's also a row for
+// `merge_sort_in_bump`, with each thread resetting its own bumpalo
+// arena after every array. It runs about as fast as a reused
+// `MergeArena`: either way there's no allocation after the first array.
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+#[cfg(feature = "bump-arena")]
+use sorting_in_rust::sorts::merge_sort_in_bump;
+use sorting_in_rust::sorts::{merge_sort, merge_sort_in, MergeArena};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const ARRAYS_PER_THREAD: usize = 2000;
+
+// Have `threads` threads each sort their own batch of arrays with `sort`,
+// and return how long it took for all of them to finish.
+fn time_batch<F: Fn(&mut Vec<Vec<u32>>) + Sync>(batches: &[Vec<Vec<u32>>], sort: F) -> Duration {
+    let before = Instant::now();
+    thread::scope(|scope| {
+        for batch in batches {
+            let sort = &sort;
+            scope.spawn(move || sort(&mut batch.clone()));
+        }
+    });
+    before.elapsed()
+}
+
+fn main() {
+    let mut rng = StdRng::seed_from_u64(260);
+    let cores = thread::available_parallelism().map_or(1, |n| n.get());
+    for &threads in &[1, cores.max(2)] {
+        for &size in &[100, 1000, 10_000] {
+            let batches: Vec<Vec<Vec<u32>>> = (0..threads)
+                .map(|_| {
+                    (0..ARRAYS_PER_THREAD * 100 / size)
+                        .map(|_| (0..size).map(|_| rng.gen()).collect())
+                        .collect()
+                })
+                .collect();
+
+            let plain = time_batch(&batches, |batch| {
+                for v in batch.iter_mut() {
+                    *v = merge_sort(v);
+                }
+            });
+            let fresh = time_batch(&batches, |batch| {
+                for v in batch.iter_mut() {
+                    merge_sort_in(&mut MergeArena::new(), v);
+                }
+            });
+            let reused = time_batch(&batches, |batch| {
+                let mut arena = MergeArena::new();
+                for v in batch.iter_mut() {
+                    merge_sort_in(&mut arena, v);
+                }
+            });
+            println!(
+                "threads {:>2} size {:>6}  merge_sort {:>12?}  fresh arena {:>12?}  reused arena {:>12?}",
+                threads, size, plain, fresh, reused
+            );
+            #[cfg(feature = "bump-arena")]
+            {
+                let bumped = time_batch(&batches, |batch| {
+                    let mut bump = bumpalo::Bump::new();
+                    for v in batch.iter_mut() {
+                        merge_sort_in_bump(&bump, v);
+                        bump.reset();
+                    }
+                });
+                println!("threads {:>2} size {:>6}  bump arena {:>12?}", threads, size, bumped);
+            }
+        }
+    }
+}
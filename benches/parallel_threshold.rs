@@ -0,0 +1,84 @@
+// Find how big an input has to be before parallel merge sort beats the
+// sequential one, for the `par_threshold_bytes` setting in `tuned.toml`.
+// Run with
+//    cargo bench --bench parallel_threshold
+// For each input size we time `merge_sort` against `parallel_merge_sort`
+// forced to use every thread (with one chunk per thread), on `i32`s and
+// on 32-byte records, and report the smallest size from which the
+// parallel sort wins at every size after it. Measured in bytes the
+// crossovers for the two element types should be roughly the same,
+// which is why `tuned.toml` stores bytes rather than elements.
+//
+// With a single core there's nothing to tune: the parallel sort does the
+// same work plus the cost of starting threads, so it never wins.
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use sorting_in_rust::sorts::{merge_sort, parallel_merge_sort, ParallelConfig};
+use std::fmt::Debug;
+use std::time::{Duration, Instant};
+
+const TRIALS: u32 = 5;
+
+fn best_of<F: FnMut()>(mut f: F) -> Duration {
+    (0..TRIALS)
+        .map(|_| {
+            let before = Instant::now();
+            f();
+            before.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+// The smallest size (in bytes) from which the parallel sort always won,
+// if it did.
+fn crossover<T, G>(name: &str, mut generate: G) -> Option<usize>
+where
+    T: PartialOrd + Copy + Debug + Send + Sync,
+    G: FnMut() -> T,
+{
+    let threads = ParallelConfig::default().threads;
+    let mut crossover = None;
+    for shift in 10..=20 {
+        let size = 1 << shift;
+        let input: Vec<T> = (0..size).map(|_| generate()).collect();
+        let config = ParallelConfig {
+            chunk_size: (size / threads).max(1),
+            threshold: Some(0),
+            ..ParallelConfig::default()
+        };
+        let sequential = best_of(|| {
+            merge_sort(&input);
+        });
+        let parallel = best_of(|| {
+            parallel_merge_sort(&input, &config);
+        });
+        println!(
+            "{:<8} size {:>8}  merge sort {:>12?}  parallel ({} threads) {:>12?}",
+            name, size, sequential, threads, parallel
+        );
+        if parallel < sequential {
+            crossover = crossover.or(Some(size * std::mem::size_of::<T>()));
+        } else {
+            crossover = None;
+        }
+    }
+    crossover
+}
+
+fn main() {
+    if ParallelConfig::default().threads < 2 {
+        println!("only one core, so the parallel sort can't win; nothing to tune");
+        return;
+    }
+    let mut rng = StdRng::seed_from_u64(258);
+    let ints = crossover("i32", || rng.gen::<i32>());
+    let records = crossover("[u64; 4]", || [rng.gen::<u64>(), 0, 0, 0]);
+    match (ints, records) {
+        (Some(ints), Some(records)) => println!(
+            "suggested setting for tuned.toml:\npar_threshold_bytes = {}",
+            ints.max(records)
+        ),
+        _ => println!("the parallel sort never reliably won; keep par_threshold_bytes as it is"),
+    }
+}
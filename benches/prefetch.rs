@@ -0,0 +1,43 @@
+// The effect of software prefetching on the heap-based algorithms. Run
+// this twice and compare:
+//    cargo bench --bench prefetch
+//    cargo bench --bench prefetch --features prefetch
+// Prefetching can only help once the heap is too big for the cache, so
+// expect no difference at the smallest size and (possibly) a noticeable
+// one at the largest. The k-way merge rows go through the same
+// sift-down, in the `MinHeap` that holds the head of each list.
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use sorting_in_rust::merge::kway_merge_d;
+use sorting_in_rust::sorts::heapsort_d;
+use std::time::Instant;
+
+fn main() {
+    println!("prefetching {}", if cfg!(feature = "prefetch") { "on" } else { "off" });
+    let mut rng = StdRng::seed_from_u64(233);
+
+    for &size in &[10_000, 1_000_000, 10_000_000] {
+        let input: Vec<u64> = (0..size).map(|_| rng.gen()).collect();
+        for &arity in &[2, 4] {
+            let mut v = input.clone();
+            let before = Instant::now();
+            heapsort_d(&mut v, arity);
+            println!("heapsort    arity {} size {:>9}: {:?}", arity, size, before.elapsed());
+        }
+    }
+
+    // Lots of lists means a big heap in the merge.
+    for &lists in &[16, 1 << 12, 1 << 16] {
+        let total = 4_000_000;
+        let input: Vec<Vec<u64>> = (0..lists)
+            .map(|_| {
+                let mut list: Vec<u64> = (0..total / lists).map(|_| rng.gen()).collect();
+                list.sort();
+                list
+            })
+            .collect();
+        let before = Instant::now();
+        kway_merge_d(input, 4);
+        println!("kway_merge  arity 4 lists {:>8}: {:?}", lists, before.elapsed());
+    }
+}
@@ -0,0 +1,37 @@
+// LSD radix sort against quicksort and heapsort on big arrays of random
+// u32s and u64s. Run with
+//    cargo bench --bench radix_sort
+// Radix sort makes a fixed number of passes (4 for u32, 8 for u64), so
+// its time per element stays flat while the comparison sorts' grows
+// with log N; u64s take it twice the passes, but the comparison sorts
+// hardly notice the difference.
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use sorting_in_rust::sorts::{heapsort, quicksort, radix_sort_lsd};
+use std::time::{Duration, Instant};
+
+fn time<T: Clone, F: Fn(&mut [T])>(input: &[T], sort: F) -> Duration {
+    let mut v = input.to_vec();
+    let before = Instant::now();
+    sort(&mut v);
+    before.elapsed()
+}
+
+fn main() {
+    let mut rng = StdRng::seed_from_u64(512);
+
+    for &size in &[10_000, 100_000, 1_000_000, 10_000_000] {
+        let small: Vec<u32> = (0..size).map(|_| rng.gen()).collect();
+        let big: Vec<u64> = (0..size).map(|_| rng.gen()).collect();
+        println!(
+            "size {:>9}  u32: radix {:>12?} quicksort {:>12?} heapsort {:>12?}   u64: radix {:>12?} quicksort {:>12?} heapsort {:>12?}",
+            size,
+            time(&small, radix_sort_lsd),
+            time(&small, quicksort),
+            time(&small, heapsort),
+            time(&big, radix_sort_lsd),
+            time(&big, quicksort),
+            time(&big, heapsort),
+        );
+    }
+}
@@ -0,0 +1,38 @@
+// How much time rough sorting saves. Run with
+//    cargo bench --bench rough_sort
+// ε = 0 is a full sort (the same partitioning all the way down), so the
+// other columns show what stopping at blocks of εN buys: each factor of
+// 10 in ε skips about log2(10) ≈ 3.3 levels of partitioning, out of
+// log2(N) in all. The displacement column checks the guarantee.
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use sorting_in_rust::sorts::{max_displacement, rough_sort};
+use std::time::{Duration, Instant};
+
+fn time_rough_sort(input: &[u64], epsilon: f64) -> (Duration, usize) {
+    let mut v = input.to_vec();
+    let before = Instant::now();
+    rough_sort(&mut v, epsilon);
+    (before.elapsed(), max_displacement(&v))
+}
+
+fn main() {
+    let mut rng = StdRng::seed_from_u64(283);
+
+    for &size in &[100_000, 1_000_000, 10_000_000] {
+        let input: Vec<u64> = (0..size).map(|_| rng.gen()).collect();
+        let (full, _) = time_rough_sort(&input, 0.0);
+        println!("size {:>9}  full sort {:>12?}", size, full);
+        for &epsilon in &[0.0001, 0.001, 0.01, 0.1] {
+            let (rough, displacement) = time_rough_sort(&input, epsilon);
+            println!(
+                "    ε = {:<7}  {:>12?} ({:>5.1}% of full)  max displacement {:>8} (≤ {:.0})",
+                epsilon,
+                rough,
+                100.0 * rough.as_secs_f64() / full.as_secs_f64(),
+                displacement,
+                epsilon * size as f64
+            );
+        }
+    }
+}
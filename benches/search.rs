@@ -0,0 +1,55 @@
+// Binary search versus interpolation search. Run with
+//    cargo bench --bench search
+// On uniform data interpolation search needs only a handful of probes
+// (O(log log N)) and should beat binary search. On skewed data (here,
+// the cubes of uniform values, so most of the values are bunched up near
+// zero) its guesses are poor and it can be much slower.
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use sorting_in_rust::search::{binary_search, interpolation_search};
+use std::time::{Duration, Instant};
+
+const QUERIES: usize = 100_000;
+
+fn time<F: Fn(u64) -> Result<usize, usize>>(queries: &[u64], search: F) -> Duration {
+    let before = Instant::now();
+    let mut found = 0;
+    for &q in queries {
+        if search(q).is_ok() {
+            found += 1;
+        }
+    }
+    // Use the result so the searches can't be optimized away.
+    assert!(found <= queries.len());
+    before.elapsed()
+}
+
+fn main() {
+    let mut rng = StdRng::seed_from_u64(222);
+
+    for &size in &[1_000u64, 100_000, 1_000_000] {
+        let mut uniform: Vec<u64> = (0..size).map(|_| rng.gen_range(0, 1 << 40)).collect();
+        let mut skewed: Vec<u64> = (0..size)
+            .map(|_| {
+                let x: u64 = rng.gen_range(0, 1 << 20);
+                x * x * x
+            })
+            .collect();
+        uniform.sort();
+        skewed.sort();
+        for (name, v) in [("uniform", &uniform), ("skewed", &skewed)].iter() {
+            // Look up values that are there, so both searches do the
+            // same work.
+            let queries: Vec<u64> = (0..QUERIES)
+                .map(|_| v[rng.gen_range(0, v.len())])
+                .collect();
+            println!(
+                "{:<8} size {:>9}  binary {:>12?}  interpolation {:>12?}",
+                name,
+                size,
+                time(&queries, |q| binary_search(v, &q)),
+                time(&queries, |q| interpolation_search(v, q)),
+            );
+        }
+    }
+}
@@ -0,0 +1,51 @@
+// How the gap sequence changes Shell sort's growth. Run with
+//    cargo bench --bench shell_sort
+// For each sequence we sort random inputs of growing size and print the
+// time and comparisons, along with the exponent k that the comparisons
+// grew by since the last size (if they went up by 4^k when the size went
+// up by 4, the sort looks like O(N^k) there). Insertion sort would be
+// 2; an O(N log N) sort a little over 1. Shell's gaps come out worst,
+// Knuth's better, and Ciura's best. (Random input isn't Shell's gaps'
+// quadratic worst case; the tests in `sorts/shell_sort.rs` build that.)
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use sorting_in_rust::instrument::{count_comparisons, counted};
+use sorting_in_rust::sorts::{shell_sort, GapSequence};
+use std::time::Instant;
+
+fn main() {
+    let mut rng = StdRng::seed_from_u64(509);
+    let sizes = [10_000, 40_000, 160_000, 640_000];
+    let inputs: Vec<Vec<u64>> = sizes
+        .iter()
+        .map(|&size| (0..size).map(|_| rng.gen()).collect())
+        .collect();
+
+    for &sequence in &[GapSequence::Shell, GapSequence::Knuth, GapSequence::Ciura] {
+        println!("{:?}:", sequence);
+        let mut previous: Option<u64> = None;
+        for input in &inputs {
+            let mut v = input.clone();
+            let before = Instant::now();
+            shell_sort(&mut v, sequence);
+            let elapsed = before.elapsed();
+            let mut v = counted(input);
+            let ((), comparisons) = count_comparisons(|| shell_sort(&mut v, sequence));
+            let growth = match previous {
+                Some(previous) => format!(
+                    "N^{:.2}",
+                    (comparisons as f64 / previous as f64).ln() / 4f64.ln()
+                ),
+                None => String::new(),
+            };
+            println!(
+                "    size {:>7}  {:>12?}  {:>12} comparisons  {}",
+                input.len(),
+                elapsed,
+                comparisons,
+                growth
+            );
+            previous = Some(comparisons);
+        }
+    }
+}
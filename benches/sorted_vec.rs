@@ -0,0 +1,56 @@
+// Keeping a vector sorted as batches of new items arrive. Run with
+//    cargo bench --bench sorted_vec
+// `append_batch` only has to sort each batch and do one merge, so it
+// should be much faster than pushing the batch and re-sorting everything,
+// and far faster than inserting the new items one at a time once the
+// batches are large. (With tiny batches of random items, inserting each
+// one is competitive: both move about N items per batch, and `insert`
+// moves them with a fast memmove while the merge copies one at a time.)
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use sorting_in_rust::sorted_vec::SortedVec;
+use sorting_in_rust::sorts::heapsort;
+use std::time::Instant;
+
+const BATCHES: usize = 100;
+
+fn main() {
+    let mut rng = StdRng::seed_from_u64(229);
+
+    for &(initial, batch_size) in &[(10_000, 10), (10_000, 1000), (100_000, 10), (100_000, 1000)] {
+        let start: Vec<u64> = (0..initial).map(|_| rng.gen()).collect();
+        let batches: Vec<Vec<u64>> = (0..BATCHES)
+            .map(|_| (0..batch_size).map(|_| rng.gen()).collect())
+            .collect();
+
+        let mut v = SortedVec::from(start.clone());
+        let before = Instant::now();
+        for batch in &batches {
+            v.append_batch(batch.clone());
+        }
+        let batch_time = before.elapsed();
+
+        let mut v = SortedVec::from(start.clone());
+        let before = Instant::now();
+        for batch in &batches {
+            for &x in batch {
+                v.insert(x);
+            }
+        }
+        let insert_time = before.elapsed();
+
+        let mut v = start.clone();
+        heapsort(&mut v);
+        let before = Instant::now();
+        for batch in &batches {
+            v.extend_from_slice(batch);
+            heapsort(&mut v);
+        }
+        let resort_time = before.elapsed();
+
+        println!(
+            "initial {:>8} batch {:>5}  append_batch {:>12?}  insert each {:>12?}  push and heapsort {:>12?}",
+            initial, batch_size, batch_time, insert_time, resort_time
+        );
+    }
+}
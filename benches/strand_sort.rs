@@ -0,0 +1,53 @@
+// Strand sort on random and nearly sorted data. Run with
+//    cargo bench --bench strand_sort
+// On nearly sorted data there are only a few strands, so strand sort
+// should be close to linear: the time per element should stay roughly
+// flat as the size grows. On random data the number of strands grows
+// like √N, and so does the time per element.
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use sorting_in_rust::sorts::{heapsort, strand_sort};
+use std::time::Instant;
+
+// Sorted, except that `swaps` random pairs of elements are swapped.
+fn nearly_sorted(rng: &mut StdRng, size: usize, swaps: usize) -> Vec<u64> {
+    let mut v: Vec<u64> = (0..size as u64).collect();
+    for _ in 0..swaps {
+        let i = rng.gen_range(0, size);
+        let j = rng.gen_range(0, size);
+        v.swap(i, j);
+    }
+    v
+}
+
+fn main() {
+    let mut rng = StdRng::seed_from_u64(213);
+
+    for &size in &[10_000, 100_000, 1_000_000] {
+        let random: Vec<u64> = (0..size).map(|_| rng.gen()).collect();
+        let nearly = nearly_sorted(&mut rng, size, 5);
+        for (name, input) in [("random", &random), ("nearly sorted", &nearly)].iter() {
+            // Random input is O(N^1.5), so skip the biggest size.
+            if *name == "random" && size > 100_000 {
+                continue;
+            }
+            let before = Instant::now();
+            strand_sort(input);
+            let strand_time = before.elapsed();
+
+            let mut v = input.to_vec();
+            let before = Instant::now();
+            heapsort(&mut v);
+            let heap_time = before.elapsed();
+
+            println!(
+                "{:<13} size {:>8}  strand sort {:>12?} ({:>6.1} ns per element)  heapsort {:>12?}",
+                name,
+                size,
+                strand_time,
+                strand_time.as_nanos() as f64 / size as f64,
+                heap_time
+            );
+        }
+    }
+}
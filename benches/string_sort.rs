@@ -0,0 +1,119 @@
+// LCP merge sort, multikey quicksort, and MSD radix sort versus our
+// ordinary merge sort (and the standard library's sort) on strings. Run
+// with
+//    cargo bench --bench string_sort
+// LCP merge sort reads far fewer characters, but comparing a shared
+// prefix of a few dozen bytes is nearly free (it's a `memcmp`), so on
+// the paths and URLs it only about breaks even with our merge sort.
+// Once the shared prefixes are hundreds of characters long (`long`),
+// skipping them pays off, and at a million strings it beats even the
+// standard library's sort.
+//
+// Multikey quicksort is quick on the paths, URLs, and words, but on
+// `long` it's the slowest: it works through the shared prefix one character position
+// at a time, with a pass over all the strings for each one.
+//
+// MSD radix sort splits the strings 257 ways at each character, and
+// when they all share the next character it skips to the end of the
+// shared prefix with a `memcmp`-speed `common_prefix` rather than a
+// pass per character. That makes it the fastest of ours on `long`,
+// where at 100,000 strings and up it beats the standard library's sort,
+// and on the URLs and words it keeps up with std's sort.
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use sorting_in_rust::sorts::merge_sort;
+use sorting_in_rust::strings::{msd_radix_sort, multikey_quicksort, sort_strings_with_lcp};
+use std::time::Instant;
+
+type Generator = fn(&mut StdRng) -> String;
+
+const DIRS: [&str; 6] = ["src", "sorts", "tests", "target", "debug", "build"];
+
+fn path(rng: &mut StdRng) -> String {
+    let depth = rng.gen_range(3, 8);
+    let parts: Vec<&str> = (0..depth)
+        .map(|_| DIRS[rng.gen_range(0, DIRS.len())])
+        .collect();
+    format!(
+        "/home/student/csci-3412/lab-8/{}/file{}.rs",
+        parts.join("/"),
+        rng.gen_range(0, 100)
+    )
+}
+
+fn url(rng: &mut StdRng) -> String {
+    format!(
+        "https://example.com/api/v1/users/{}/orders/{}?page={}",
+        rng.gen_range(0, 1000),
+        rng.gen_range(0, 100_000),
+        rng.gen_range(0, 10)
+    )
+}
+
+// Keys that share a few hundred characters, like the objects in a
+// deeply nested bucket, or URLs with long tracking parameters.
+fn long_key(rng: &mut StdRng) -> String {
+    let group = rng.gen_range(0, 4);
+    format!(
+        "{}/{}/{:06}",
+        "s3://lab-8-benchmark-results/".repeat(10),
+        group,
+        rng.gen_range(0, 1_000_000)
+    )
+}
+
+fn word(rng: &mut StdRng) -> String {
+    let len = rng.gen_range(3, 10);
+    (0..len)
+        .map(|_| rng.gen_range(b'a', b'z' + 1) as char)
+        .collect()
+}
+
+fn main() {
+    let mut rng = StdRng::seed_from_u64(250);
+
+    let generators: [(&str, Generator); 4] = [
+        ("paths", path),
+        ("urls", url),
+        ("long", long_key),
+        ("words", word),
+    ];
+    for &size in &[10_000, 100_000, 1_000_000] {
+        for (name, generate) in generators.iter() {
+            let owned: Vec<String> = (0..size).map(|_| generate(&mut rng)).collect();
+            let strings: Vec<&str> = owned.iter().map(String::as_str).collect();
+
+            let before = Instant::now();
+            let lcp_sorted = sort_strings_with_lcp(&strings);
+            let lcp_time = before.elapsed();
+
+            let before = Instant::now();
+            let merge_sorted = merge_sort(&strings);
+            let merge_time = before.elapsed();
+
+            let mut multikey_sorted = strings.clone();
+            let before = Instant::now();
+            multikey_quicksort(&mut multikey_sorted);
+            let multikey_time = before.elapsed();
+
+            let mut msd_sorted = strings.clone();
+            let before = Instant::now();
+            msd_radix_sort(&mut msd_sorted);
+            let msd_time = before.elapsed();
+
+            let mut std_sorted = strings.clone();
+            let before = Instant::now();
+            std_sorted.sort();
+            let std_time = before.elapsed();
+
+            assert_eq!(std_sorted, lcp_sorted);
+            assert_eq!(std_sorted, merge_sorted);
+            assert_eq!(std_sorted, multikey_sorted);
+            assert_eq!(std_sorted, msd_sorted);
+            println!(
+                "{:<5} size {:>9}  lcp merge sort {:>12?}  multikey quicksort {:>12?}  msd radix sort {:>12?}  merge sort {:>12?}  std sort {:>12?}",
+                name, size, lcp_time, multikey_time, msd_time, merge_time, std_time
+            );
+        }
+    }
+}
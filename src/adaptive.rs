@@ -0,0 +1,179 @@
+// Running each cell of the matrix for as many trials as it takes,
+// rather than a fixed number. A fixed `--trials` is either wasteful
+// (plenty of trials of a sort that takes a microsecond and never
+// varies) or not enough (three trials of one that jumps around), and you
+// have to guess which before you start.
+//
+// Instead, with `--max-time` and/or `--target-ci`, we keep running
+// trials of each (algorithm, size, distribution) until either
+//  * its share of the time budget is used up (the budget is split evenly
+//    between them), or
+//  * the 95% confidence interval for its mean time is narrower than
+//    the target, e.g. within ±5% of the mean for `--target-ci 5%`,
+// but always at least `trials` trials, and never more than
+// `MAX_ADAPTIVE_TRIALS`. So a quick run is quick, and a long run spends
+// its time where the timings are noisiest.
+//
+// Every trial gets its own input (trial t of each algorithm gets the
+// same input, as in a fixed run), so `keep_inputs` doesn't apply. With
+// `schedule_seed` the cells run in a random order, but each cell's
+// trials still run one after another.
+
+use crate::benchmark::{in_cell_order, run_cell, run_on_threads, Cell, MatrixConfig, RunResult};
+use std::time::{Duration, Instant};
+
+// The most trials we'll run of any one cell, in case the target is
+// never reached and there's no time limit.
+pub const MAX_ADAPTIVE_TRIALS: usize = 1000;
+
+// The 97.5th percentile of Student's t distribution with 1, 2, ..., 30
+// degrees of freedom. A 95% confidence interval for the mean of n
+// samples is mean ± t[n - 2] * s / √n, where s is the sample standard
+// deviation. Past 30 the normal distribution's 1.96 is close enough.
+const T_975: [f64; 30] = [
+    12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228, 2.201, 2.179, 2.160,
+    2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086, 2.080, 2.074, 2.069, 2.064, 2.060, 2.056,
+    2.052, 2.048, 2.045, 2.042,
+];
+
+// Half the width of the 95% confidence interval for the mean of `times`,
+// as a fraction of the mean, e.g. 0.05 for ±5%. We need at least two
+// times to say anything about how much they vary.
+pub fn relative_ci(times: &[Duration]) -> Option<f64> {
+    let n = times.len();
+    if n < 2 {
+        return None;
+    }
+    let seconds: Vec<f64> = times.iter().map(Duration::as_secs_f64).collect();
+    let mean = seconds.iter().sum::<f64>() / n as f64;
+    if mean == 0.0 {
+        return Some(0.0);
+    }
+    let variance = seconds.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / (n - 1) as f64;
+    let t = T_975.get(n - 2).cloned().unwrap_or(1.96);
+    Some(t * (variance / n as f64).sqrt() / mean)
+}
+
+// Run the matrix with adaptive trial counts. The results are grouped by
+// cell (all of one cell's trials, then the next cell's), in the order of
+// `config.cells()`.
+pub fn run_adaptive(config: &MatrixConfig) -> Vec<RunResult> {
+    // One entry per cell, ignoring the trials.
+    let cells: Vec<Cell> = config
+        .cells()
+        .into_iter()
+        .filter(|c| c.trial == 0)
+        .collect();
+    let jobs = config.effective_jobs();
+    // With several jobs, that many cells share the budget at once.
+    let budget = config
+        .max_time
+        .map(|max_time| max_time / cells.len().max(1) as u32 * jobs as u32);
+    let schedule = config.schedule(cells.len());
+    let results = run_on_threads(cells.len(), jobs, |k| {
+        run_trials(config, &cells[schedule[k]], budget)
+    });
+    in_cell_order(&schedule, results)
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+fn run_trials(config: &MatrixConfig, cell: &Cell, budget: Option<Duration>) -> Vec<RunResult> {
+    let start = Instant::now();
+    let mut results: Vec<RunResult> = Vec::new();
+    let mut times = Vec::new();
+    for trial in 0..MAX_ADAPTIVE_TRIALS {
+        if trial >= config.trials {
+            let out_of_time = budget.is_some_and(|budget| start.elapsed() >= budget);
+            let precise_enough = match (config.target_ci, relative_ci(&times)) {
+                (Some(target), Some(ci)) => ci <= target,
+                _ => false,
+            };
+            if out_of_time || precise_enough {
+                break;
+            }
+        }
+        let result = run_cell(config, &Cell { trial, ..*cell });
+        times.push(result.elapsed);
+        results.push(result);
+    }
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::benchmark::find_algorithm;
+
+    fn config() -> MatrixConfig {
+        MatrixConfig {
+            algorithms: vec![
+                find_algorithm("insertion").unwrap(),
+                find_algorithm("merge").unwrap(),
+            ],
+            sizes: vec![10, 100],
+            ..MatrixConfig::default()
+        }
+    }
+
+    fn trials_per_cell(results: &[RunResult]) -> Vec<usize> {
+        let mut counts: Vec<usize> = Vec::new();
+        for r in results {
+            if r.trial == 0 {
+                counts.push(0);
+            }
+            *counts.last_mut().unwrap() += 1;
+        }
+        counts
+    }
+
+    #[test]
+    fn ci() {
+        let ms = Duration::from_millis;
+        assert_eq!(None, relative_ci(&[ms(5)]));
+        assert_eq!(Some(0.0), relative_ci(&[ms(5), ms(5), ms(5)]));
+        // Mean 10 and s² = 8/3, so 3.182 * √(8/3) / √4 ≈ 2.6, or 26%.
+        let ci = relative_ci(&[ms(8), ms(10), ms(10), ms(12)]).unwrap();
+        assert!((ci - 0.2598).abs() < 1e-3, "{}", ci);
+    }
+
+    #[test]
+    fn out_of_time() {
+        // No time at all: just the minimum number of trials.
+        let config = MatrixConfig {
+            trials: 3,
+            max_time: Some(Duration::from_nanos(1)),
+            ..config()
+        };
+        let results = run_adaptive(&config);
+        assert_eq!(vec![3, 3, 3, 3], trials_per_cell(&results));
+        assert!(results.iter().all(|r| r.verified));
+    }
+
+    #[test]
+    fn precise_enough() {
+        // Any interval at all is narrow enough, so we stop as soon as
+        // there is one.
+        let config = MatrixConfig {
+            target_ci: Some(f64::INFINITY),
+            ..config()
+        };
+        assert_eq!(vec![2, 2, 2, 2], trials_per_cell(&run_adaptive(&config)));
+    }
+
+    #[test]
+    fn never_precise_enough() {
+        let config = MatrixConfig {
+            algorithms: vec![find_algorithm("insertion").unwrap()],
+            sizes: vec![2],
+            target_ci: Some(-1.0),
+            jobs: 2,
+            ..config()
+        };
+        let results = run_adaptive(&config);
+        assert_eq!(vec![MAX_ADAPTIVE_TRIALS], trials_per_cell(&results));
+        // Numbered in order.
+        assert_eq!(MAX_ADAPTIVE_TRIALS - 1, results.last().unwrap().trial);
+    }
+}
@@ -0,0 +1,346 @@
+// Answering questions about data once it's sorted. Sorting puts equal
+// values next to each other, so lots of questions that would otherwise
+// need a hash table (or O(N^2) time) become a single linear scan.
+
+use crate::random::{shuffle, SeededRandom};
+use crate::search::Numeric;
+use std::ops::Range;
+
+// Runs of equal values in sorted data, as (value, count) pairs.
+pub fn runs<T: PartialEq + Clone>(sorted: &[T]) -> Vec<(T, usize)> {
+    let mut result: Vec<(T, usize)> = Vec::new();
+    for x in sorted {
+        match result.last_mut() {
+            Some((value, count)) if value == x => *count += 1,
+            _ => result.push((x.clone(), 1)),
+        }
+    }
+    result
+}
+
+// The values that appear more than once in sorted data, along with how
+// many times each one appears, in sorted order.
+pub fn duplicates<T: PartialEq + Clone>(sorted: &[T]) -> Vec<(T, usize)> {
+    runs(sorted).into_iter().filter(|&(_, count)| count > 1).collect()
+}
+
+// Where each group of equal keys starts and ends in sorted data, e.g.
+// [1, 1, 2, 5, 5, 5] gives 0..2, 2..3, 3..6.
+pub fn key_groups<T, K: PartialEq, F: FnMut(&T) -> K>(
+    sorted: &[T],
+    mut key: F,
+) -> Vec<Range<usize>> {
+    let mut groups = Vec::new();
+    let mut start = 0;
+    let mut current = sorted.first().map(&mut key);
+    for (i, x) in sorted.iter().enumerate().skip(1) {
+        let k = key(x);
+        if current.as_ref() != Some(&k) {
+            groups.push(start..i);
+            start = i;
+            current = Some(k);
+        }
+    }
+    if !sorted.is_empty() {
+        groups.push(start..sorted.len());
+    }
+    groups
+}
+
+// Put the elements with equal keys in sorted data in a random (but
+// seeded, so repeatable) order. A stable sort leaves ties in the order
+// they came in, and an unstable one in whatever order its swaps happened
+// to leave them; either way, taking "the first k" afterwards, say to
+// pick a sample or to rank candidates who scored the same, quietly
+// favors whoever was first in the input. Shuffling each group of ties
+// gives every one of them the same chance.
+pub fn shuffle_ties<T, K: PartialEq, F: FnMut(&T) -> K>(sorted: &mut [T], key: F, seed: u64) {
+    let mut rng = SeededRandom::new(seed);
+    for group in key_groups(sorted, key) {
+        shuffle(&mut sorted[group], &mut rng);
+    }
+}
+
+// The q-th quantile (0 ≤ q ≤ 1) of sorted data: the median is the 0.5
+// quantile, the quartiles are the 0.25 and 0.75 quantiles, and so on.
+// When the quantile falls between two elements we interpolate linearly
+// between them (this is the same definition spreadsheets and NumPy use
+// by default). Returns `None` for empty data.
+pub fn quantile<T: Numeric>(sorted: &[T], q: f64) -> Option<f64> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let position = q.clamp(0.0, 1.0) * (sorted.len() - 1) as f64;
+    let below = position.floor() as usize;
+    let above = position.ceil() as usize;
+    let fraction = position - below as f64;
+    let low = sorted[below].to_f64();
+    let high = sorted[above].to_f64();
+    Some(low + fraction * (high - low))
+}
+
+// Summary statistics for sorted numeric data.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Summary<T> {
+    pub count: usize,
+    pub distinct: usize,
+    pub min: T,
+    pub max: T,
+    pub lower_quartile: f64,
+    pub median: f64,
+    pub upper_quartile: f64,
+    // The most common value; if there's a tie, the smallest of them.
+    pub mode: T,
+    pub mode_count: usize,
+}
+
+pub fn summarize<T: Numeric>(sorted: &[T]) -> Option<Summary<T>> {
+    let runs = runs(sorted);
+    // `max_by_key` returns the last of the tied maximums, so search
+    // backwards to get the first (smallest) one.
+    let &(mode, mode_count) = runs.iter().rev().max_by_key(|&&(_, count)| count)?;
+    Some(Summary {
+        count: sorted.len(),
+        distinct: runs.len(),
+        min: sorted[0],
+        max: sorted[sorted.len() - 1],
+        lower_quartile: quantile(sorted, 0.25)?,
+        median: quantile(sorted, 0.5)?,
+        upper_quartile: quantile(sorted, 0.75)?,
+        mode,
+        mode_count,
+    })
+}
+
+// One bar of a histogram: the values from `low` to `high`, and how many
+// of them there are.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bucket {
+    pub low: f64,
+    pub high: f64,
+    pub count: usize,
+}
+
+// Split the range from the smallest to the largest value into `bins`
+// equally wide pieces and count how many values fall in each. Each
+// bucket includes its low end, and the last one includes its high end
+// too.
+pub fn equi_width_histogram<T: Numeric>(sorted: &[T], bins: usize) -> Vec<Bucket> {
+    if sorted.is_empty() || bins == 0 {
+        return Vec::new();
+    }
+    let min = sorted[0].to_f64();
+    let max = sorted[sorted.len() - 1].to_f64();
+    let width = (max - min) / bins as f64;
+    let mut buckets: Vec<Bucket> = (0..bins)
+        .map(|i| Bucket {
+            low: min + i as f64 * width,
+            high: if i + 1 == bins { max } else { min + (i + 1) as f64 * width },
+            count: 0,
+        })
+        .collect();
+    for x in sorted {
+        let i = if width > 0.0 {
+            ((x.to_f64() - min) / width) as usize
+        } else {
+            0
+        };
+        buckets[i.min(bins - 1)].count += 1;
+    }
+    buckets
+}
+
+// Split the data into `k` buckets holding (as nearly as possible) the
+// same number of values each; the boundaries are the quantiles. This is
+// where having the data sorted pays off: bucket i is just the i-th
+// k-th of the slice. Equal values can end up split across neighbouring
+// buckets, so two buckets can share a boundary.
+pub fn equi_depth_buckets<T: Numeric>(sorted: &[T], k: usize) -> Vec<Bucket> {
+    let n = sorted.len();
+    (0..k.min(n))
+        .map(|i| {
+            let start = i * n / k.min(n);
+            let end = (i + 1) * n / k.min(n);
+            Bucket {
+                low: sorted[start].to_f64(),
+                high: sorted[end - 1].to_f64(),
+                count: end - start,
+            }
+        })
+        .collect()
+}
+
+// Draw buckets as a text bar chart, one line per bucket, scaling the
+// bars so the biggest one is `width` characters long.
+pub fn render_bars(buckets: &[Bucket], width: usize) -> String {
+    let most = buckets.iter().map(|b| b.count).max().unwrap_or(0).max(1);
+    let mut result = String::new();
+    for b in buckets {
+        let bar = "#".repeat(b.count * width / most);
+        result += &format!("{:>12.2} .. {:<12.2} {:>8} {}\n", b.low, b.high, b.count, bar);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty() {
+        assert_eq!(Vec::<(i32, usize)>::new(), duplicates::<i32>(&[]));
+    }
+
+    #[test]
+    fn ten_items() {
+        let input = [0, 0, 2, 2, 3, 3, 5, 6, 8, 9];
+        assert_eq!(vec![(0, 2), (2, 2), (3, 2)], duplicates(&input));
+        assert_eq!(7, runs(&input).len());
+    }
+
+    #[test]
+    fn no_duplicates() {
+        assert_eq!(Vec::<(i32, usize)>::new(), duplicates(&[1, 2, 3]));
+    }
+
+    #[test]
+    fn long_run() {
+        assert_eq!(vec![(7, 4)], duplicates(&[1, 7, 7, 7, 7, 8]));
+    }
+
+    mod summarize {
+        use super::*;
+
+        #[test]
+        fn empty() {
+            assert_eq!(None, summarize::<i32>(&[]));
+        }
+
+        #[test]
+        fn ten_items() {
+            let summary = summarize(&[0, 0, 2, 2, 3, 3, 5, 6, 8, 9]).unwrap();
+            assert_eq!(
+                Summary {
+                    count: 10,
+                    distinct: 7,
+                    min: 0,
+                    max: 9,
+                    lower_quartile: 2.0,
+                    median: 3.0,
+                    upper_quartile: 5.75,
+                    mode: 0,
+                    mode_count: 2,
+                },
+                summary
+            );
+        }
+
+        #[test]
+        fn one_item() {
+            let summary = summarize(&[4.5]).unwrap();
+            assert_eq!(4.5, summary.median);
+            assert_eq!(4.5, summary.mode);
+        }
+
+        #[test]
+        fn odd_length_median() {
+            assert_eq!(Some(3.0), quantile(&[1, 2, 3, 10, 20], 0.5));
+            assert_eq!(Some(1.0), quantile(&[1, 2, 3, 10, 20], 0.0));
+            assert_eq!(Some(20.0), quantile(&[1, 2, 3, 10, 20], 1.0));
+        }
+    }
+
+    mod histograms {
+        use super::*;
+
+        #[test]
+        fn empty() {
+            assert!(equi_width_histogram::<i32>(&[], 4).is_empty());
+            assert!(equi_depth_buckets::<i32>(&[], 4).is_empty());
+        }
+
+        #[test]
+        fn equi_width() {
+            let counts: Vec<usize> = equi_width_histogram(&[0, 0, 2, 2, 3, 3, 5, 6, 8, 9], 3)
+                .iter()
+                .map(|b| b.count)
+                .collect();
+            // The bins are [0, 3), [3, 6), and [6, 9].
+            assert_eq!(vec![4, 3, 3], counts);
+        }
+
+        #[test]
+        fn all_equal() {
+            let buckets = equi_width_histogram(&[7, 7, 7], 4);
+            assert_eq!(3, buckets[0].count);
+            assert_eq!(3, buckets.iter().map(|b| b.count).sum::<usize>());
+        }
+
+        #[test]
+        fn equi_depth() {
+            let buckets = equi_depth_buckets(&[0, 0, 2, 2, 3, 3, 5, 6, 8, 9], 3);
+            let counts: Vec<usize> = buckets.iter().map(|b| b.count).collect();
+            assert_eq!(vec![3, 3, 4], counts);
+            assert_eq!((0.0, 2.0), (buckets[0].low, buckets[0].high));
+            assert_eq!((5.0, 9.0), (buckets[2].low, buckets[2].high));
+        }
+
+        #[test]
+        fn more_buckets_than_values() {
+            assert_eq!(2, equi_depth_buckets(&[1, 2], 5).len());
+        }
+
+        #[test]
+        fn bars() {
+            let buckets = equi_width_histogram(&[0, 0, 2, 2, 3, 3, 5, 6, 8, 9], 3);
+            let lines: Vec<String> = render_bars(&buckets, 8).lines().map(String::from).collect();
+            assert_eq!(3, lines.len());
+            assert!(lines[0].ends_with(" 4 ########"));
+            assert!(lines[1].ends_with(" 3 ######"));
+        }
+    }
+
+    #[test]
+    fn groups() {
+        assert_eq!(
+            vec![0..2, 2..3, 3..6],
+            key_groups(&[1, 1, 2, 5, 5, 5], |&x| x)
+        );
+        assert!(key_groups(&[] as &[i32], |&x| x).is_empty());
+        // By the tens digit.
+        assert_eq!(vec![0..1, 1..3], key_groups(&[3, 12, 17], |&x| x / 10));
+    }
+
+    #[test]
+    fn shuffled_ties() {
+        // (score, name): sorted by score, with the names in input order.
+        let sorted: Vec<(u32, usize)> = (0..40).map(|i| (i / 10, i as usize)).collect();
+        let mut v = sorted.clone();
+        shuffle_ties(&mut v, |&(score, _)| score, 281);
+        // Still sorted by score, and each score still has the same names.
+        for (group, expected) in v.chunks(10).zip(sorted.chunks(10)) {
+            assert!(group.iter().all(|&(score, _)| score == expected[0].0));
+            let mut names: Vec<usize> = group.iter().map(|&(_, name)| name).collect();
+            names.sort();
+            assert!(names.iter().eq(expected.iter().map(|(_, name)| name)));
+        }
+        assert_ne!(sorted, v);
+        // The same seed gives the same order.
+        let mut again = sorted.clone();
+        shuffle_ties(&mut again, |&(score, _)| score, 281);
+        assert_eq!(v, again);
+    }
+
+    #[test]
+    fn ties_are_fair() {
+        // Over many seeds, each of five tied items should come first
+        // about a fifth of the time.
+        let mut firsts = [0; 5];
+        for seed in 0..5000 {
+            let mut v = [0, 1, 2, 3, 4];
+            shuffle_ties(&mut v, |_| (), seed);
+            firsts[v[0]] += 1;
+        }
+        assert!(firsts.iter().all(|&n| (800..1200).contains(&n)), "{:?}", firsts);
+    }
+}
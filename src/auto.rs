@@ -0,0 +1,129 @@
+// `auto_sort` picks a sorting algorithm for you based on the type of the
+// elements (and how many there are). Rust doesn't (yet) let a generic
+// function check "is T actually u8?", so the choice is made by the
+// `AutoSort` trait instead: every type gets the general-purpose
+// `comparison_sort` unless its impl says otherwise, and the small
+// unsigned integer types say otherwise by using counting sort, as do
+// booleans. (To use `auto_sort` on your own type, such as an enum, add an
+// empty `impl AutoSort for MyType {}`.)
+
+use crate::sorts::{
+    counting_sort_u16, counting_sort_u8, few_values_sort, has_at_most_distinct, heapsort,
+    insertion_sort, sort_bools, COUNTING_SORT_U16_THRESHOLD,
+};
+
+// Below this length insertion sort's low overhead beats heapsort.
+pub const INSERTION_SORT_THRESHOLD: usize = 16;
+
+// If there are at most this many distinct values we use
+// `few_values_sort`, which makes two passes per distinct value.
+pub const FEW_VALUES_THRESHOLD: usize = 4;
+
+pub trait AutoSort: PartialOrd + std::fmt::Debug + Sized {
+    fn auto_sort(v: &mut [Self]) {
+        comparison_sort(v);
+    }
+}
+
+pub fn auto_sort<T: AutoSort>(v: &mut [T]) {
+    T::auto_sort(v);
+}
+
+// What `auto_sort` uses when there's nothing better: insertion sort for
+// short slices, a few linear passes if there are only a few distinct
+// values, and heapsort (which is O(N log N) no matter what the input
+// looks like) for everything else.
+pub fn comparison_sort<T: PartialOrd + std::fmt::Debug>(v: &mut [T]) {
+    if v.len() <= INSERTION_SORT_THRESHOLD {
+        insertion_sort(v);
+    } else if has_at_most_distinct(v, FEW_VALUES_THRESHOLD) {
+        few_values_sort(v);
+    } else {
+        heapsort(v);
+    }
+}
+
+impl AutoSort for u8 {
+    fn auto_sort(v: &mut [u8]) {
+        if v.len() <= INSERTION_SORT_THRESHOLD {
+            insertion_sort(v);
+        } else {
+            counting_sort_u8(v);
+        }
+    }
+}
+
+impl AutoSort for u16 {
+    fn auto_sort(v: &mut [u16]) {
+        if v.len() < COUNTING_SORT_U16_THRESHOLD {
+            comparison_sort(v);
+        } else {
+            counting_sort_u16(v);
+        }
+    }
+}
+
+impl AutoSort for bool {
+    fn auto_sort(v: &mut [bool]) {
+        sort_bools(v);
+    }
+}
+
+macro_rules! comparison_auto_sort {
+    ($($t:ty),*) => {
+        $(impl AutoSort for $t {})*
+    };
+}
+
+comparison_auto_sort!(
+    i8, i16, i32, i64, i128, isize, u32, u64, u128, usize, f32, f64, char, String, &str
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    #[test]
+    fn empty() {
+        let mut input: [i32; 0] = [];
+        auto_sort(&mut input);
+        assert_eq!(input, []);
+    }
+
+    #[test]
+    fn ten_items() {
+        let mut input = [3, 2, 0, 5, 8, 9, 6, 3, 2, 0];
+        auto_sort(&mut input);
+        assert_eq!(input, [0, 0, 2, 2, 3, 3, 5, 6, 8, 9]);
+    }
+
+    #[test]
+    fn every_path() {
+        // Short and long inputs of each kind, so that each of insertion
+        // sort, heapsort, and both counting sorts gets used.
+        let mut rng = StdRng::seed_from_u64(220);
+        for &len in &[10, 100, 10_000] {
+            let mut bytes: Vec<u8> = (0..len).map(|_| rng.gen()).collect();
+            let mut shorts: Vec<u16> = (0..len).map(|_| rng.gen()).collect();
+            let mut ints: Vec<i32> = (0..len).map(|_| rng.gen()).collect();
+            auto_sort(&mut bytes);
+            auto_sort(&mut shorts);
+            auto_sort(&mut ints);
+            assert!(bytes.windows(2).all(|w| w[0] <= w[1]));
+            assert!(shorts.windows(2).all(|w| w[0] <= w[1]));
+            assert!(ints.windows(2).all(|w| w[0] <= w[1]));
+        }
+    }
+
+    #[test]
+    fn few_values() {
+        let mut rng = StdRng::seed_from_u64(221);
+        let mut bools: Vec<bool> = (0..1000).map(|_| rng.gen()).collect();
+        let mut ints: Vec<i32> = (0..1000).map(|_| rng.gen_range(0, 3)).collect();
+        auto_sort(&mut bools);
+        auto_sort(&mut ints);
+        assert!(bools.windows(2).all(|w| w[0] <= w[1]));
+        assert!(ints.windows(2).all(|w| w[0] <= w[1]));
+    }
+}
@@ -0,0 +1,934 @@
+// The benchmark "matrix": time every algorithm on every combination of
+// input size, input distribution, and trial, and collect the results.
+//
+// Each (size, distribution, trial) combination gets its own input, which
+// is regenerated from a seed for each algorithm, so every algorithm sorts
+// exactly the same data without us having to keep a copy of every input
+// around.
+//
+// With `keep_inputs` we instead generate every input once, up front,
+// and give each algorithm a clone. That saves regenerating the input for
+// every algorithm, but means every input is in memory at once, so for
+// big sweeps it's worth setting `max_memory` so we find out before we
+// start rather than getting killed halfway through.
+//
+// The cells of the matrix are independent, so with `jobs > 1` we run
+// them on several threads at once. Running too many at once would make
+// the sorts compete for CPU time (and distort the timings), so we never
+// use more threads than there are cores; that way every sort being
+// timed has a core to itself.
+//
+// With `auto_cases` the distributions are ignored, and each algorithm
+// instead gets its own best-, average-, and worst-case inputs (see
+// `cases.rs`), so each of those inputs belongs to just one algorithm.
+//
+// With `max_time` or `target_ci`, the number of trials isn't fixed: we
+// keep going until we've spent the time or the timings settle down
+// (see `adaptive.rs`).
+//
+// Running every algorithm in the same order each trial can bias the
+// timings: whichever goes first might always find the caches cold, or
+// the CPU not yet throttled by the heat. With `schedule_seed` the cells
+// run in a random order instead (and the seed is reported, so the order
+// can be repeated), which spreads those effects evenly.
+
+use crate::adaptive::run_adaptive;
+use crate::cases::{case_distributions, Case};
+use crate::error::{InputsDiffer, MemoryLimitExceeded};
+use crate::generate::generate_random_array;
+#[cfg(all(feature = "hugepages", target_os = "linux"))]
+use crate::hugepages::copy_to_huge_pages;
+#[cfg(all(feature = "numa", target_os = "linux"))]
+use crate::numa::{generate_placed, Placement};
+use crate::partition::partition_by;
+use crate::pool::init_pool;
+use crate::random::{shuffle, RandomSource, SeededRandom};
+use crate::sorter::{all_sorters, sorters, Sorter};
+use crate::sorts::{parallel_merge_sort, ParallelConfig};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+// A sorting algorithm we can benchmark: one of the `Sorter`s from
+// `sorter.rs`, for `i32`s.
+#[derive(Clone, Copy)]
+pub struct Algorithm {
+    pub name: &'static str,
+    pub sorter: &'static dyn Sorter<i32>,
+    // Whether equal elements always stay in their original order.
+    pub stable: bool,
+}
+
+impl Algorithm {
+    pub fn sort(&self, v: &mut [i32]) {
+        self.sorter.sort(v)
+    }
+}
+
+impl std::fmt::Debug for Algorithm {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Algorithm({})", self.name)
+    }
+}
+
+// All the algorithms the benchmark knows about: the `all_sorters`
+// registry, for `i32`s. This is the one list everything else (the
+// matrix, the CLI, the REPL, grading, ...) gets its algorithms from.
+//
+// An `Algorithm` borrows its `Sorter` for good, so that it can be
+// `Copy`. The sorters are all unit structs, so leaking their boxes
+// doesn't leak any memory.
+pub fn algorithms() -> Vec<Algorithm> {
+    all_sorters()
+        .into_iter()
+        .map(|sorter| {
+            let sorter: &'static dyn Sorter<i32> = Box::leak(sorter);
+            Algorithm {
+                name: sorter.name(),
+                sorter,
+                stable: sorter.stable(),
+            }
+        })
+        .collect()
+}
+
+pub fn find_algorithm(name: &str) -> Option<Algorithm> {
+    algorithms().into_iter().find(|a| a.name == name)
+}
+
+// An `Algorithm` can only sort `i32`s, so to run one on anything else
+// (like the `Counted` and `Traced` wrappers from `instrument.rs`) we
+// look it up in the registry for that type. That works for everything
+// but parallel-merge, whose comparisons happen on other threads where
+// the instrumentation can't see them.
+pub fn sort_generic<T: PartialOrd + Copy + std::fmt::Debug>(
+    name: &str,
+    v: &mut [T],
+) -> Result<(), String> {
+    match sorters().into_iter().find(|sorter| sorter.name() == name) {
+        Some(sorter) => {
+            sorter.sort(v);
+            Ok(())
+        }
+        None => Err(format!("can't run {} on instrumented values", name)),
+    }
+}
+
+// How the input values are distributed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Distribution {
+    // Uniformly random values in 0..size, like `main` has always used.
+    Uniform,
+    // 0, 1, ..., size - 1, already in order.
+    Sorted,
+    // size - 1, ..., 1, 0: the worst case for insertion sort.
+    Reversed,
+    // In order, except that about 1% of the values (at least one pair,
+    // if there are two values) have been swapped with a random other
+    // value.
+    NearlySorted,
+    // Every value the same (0). Quicksort puts everything equal to the
+    // pivot on one side, so for it this is as bad as sorted input.
+    AllEqual,
+    // Random values, but only `FEW_UNIQUE` different ones (0 to 9), so
+    // lots of duplicates: between uniform and all-equal.
+    FewUnique,
+    // 0..size arranged so that the lab's `quicksort`, which pivots on the
+    // first element, always picks the median: its best case.
+    MedianFirst,
+    // 0..size arranged so that every merge in `merge_sort` alternates
+    // between its two halves, which makes it do the most comparisons.
+    MergeWorst,
+}
+
+impl Distribution {
+    pub fn name(self) -> &'static str {
+        match self {
+            Distribution::Uniform => "uniform",
+            Distribution::Sorted => "sorted",
+            Distribution::Reversed => "reversed",
+            Distribution::NearlySorted => "nearly-sorted",
+            Distribution::AllEqual => "all-equal",
+            Distribution::FewUnique => "few-unique",
+            Distribution::MedianFirst => "median-first",
+            Distribution::MergeWorst => "merge-worst",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Distribution> {
+        match name {
+            "uniform" => Some(Distribution::Uniform),
+            "sorted" => Some(Distribution::Sorted),
+            "reversed" => Some(Distribution::Reversed),
+            "nearly-sorted" => Some(Distribution::NearlySorted),
+            "all-equal" => Some(Distribution::AllEqual),
+            "few-unique" => Some(Distribution::FewUnique),
+            "median-first" => Some(Distribution::MedianFirst),
+            "merge-worst" => Some(Distribution::MergeWorst),
+            _ => None,
+        }
+    }
+
+    pub fn generate(self, size: usize, seed: u64) -> Vec<i32> {
+        let mut rng = SeededRandom::new(seed);
+        match self {
+            Distribution::Uniform => generate_random_array(size as i32, 0, size as i32, &mut rng),
+            Distribution::Sorted => (0..size as i32).collect(),
+            Distribution::Reversed => (0..size as i32).rev().collect(),
+            Distribution::NearlySorted => {
+                let mut v: Vec<i32> = (0..size as i32).collect();
+                if size >= 2 {
+                    for _ in 0..(size / 100).max(1) {
+                        let i = rng.below(size);
+                        let j = rng.below(size);
+                        v.swap(i, j);
+                    }
+                }
+                v
+            }
+            Distribution::AllEqual => vec![0; size],
+            Distribution::FewUnique => {
+                generate_random_array(size as i32, 0, FEW_UNIQUE, &mut rng)
+            }
+            Distribution::MedianFirst => median_first(size),
+            Distribution::MergeWorst => {
+                let mut v: Vec<i32> = (0..size as i32).collect();
+                unmerge(&mut v);
+                v
+            }
+        }
+    }
+}
+
+// How many different values `FewUnique` uses.
+pub const FEW_UNIQUE: i32 = 10;
+
+// Quicksort's best case, found by running quicksort's own partitioning
+// on the *positions* of the input: whenever it takes the first position
+// of a range as the pivot, we give that position the median of the
+// values the range will hold, and have the first half of the other
+// positions (in the order partitioning sees them) be the smaller ones.
+// Every position ends up as a pivot or alone in its range, so every
+// position gets a value.
+fn median_first(size: usize) -> Vec<i32> {
+    fn place(positions: &mut [usize], lowest: usize, values: &mut [i32]) {
+        let length = positions.len();
+        if length == 0 {
+            return;
+        }
+        // The pivot gets rank `smaller` within the range, so `smaller`
+        // of the other positions are less than it.
+        let smaller = length / 2;
+        values[positions[0]] = (lowest + smaller) as i32;
+        let mut taken = 0;
+        let split = partition_by(&mut positions[1..], |_| {
+            taken += 1;
+            taken <= smaller
+        });
+        positions.swap(0, split);
+        let (front, back) = positions.split_at_mut(split);
+        place(front, lowest, values);
+        place(&mut back[1..], lowest + split + 1, values);
+    }
+    let mut positions: Vec<usize> = (0..size).collect();
+    let mut values = vec![0; size];
+    place(&mut positions, 0, &mut values);
+    values
+}
+
+// Undo a merge sort as thoroughly as possible: `merge_sort` splits v
+// into v[..n/2] and v[n/2..], so we put the odd-indexed values of the
+// sorted v in the first half and the even-indexed ones in the second
+// (there are n/2 odd indices, so they fit exactly). Merging the halves
+// then has to alternate between them all the way, and we do the same
+// to each half, recursively.
+fn unmerge(v: &mut [i32]) {
+    if v.len() < 2 {
+        return;
+    }
+    let odds: Vec<i32> = v.iter().skip(1).step_by(2).cloned().collect();
+    let evens: Vec<i32> = v.iter().step_by(2).cloned().collect();
+    let (left, right) = v.split_at_mut(odds.len());
+    left.copy_from_slice(&odds);
+    right.copy_from_slice(&evens);
+    unmerge(left);
+    unmerge(right);
+}
+
+#[derive(Debug)]
+pub struct MatrixConfig {
+    pub algorithms: Vec<Algorithm>,
+    pub sizes: Vec<usize>,
+    pub distributions: Vec<Distribution>,
+    pub trials: usize,
+    // Every input is generated from this seed (plus its size,
+    // distribution, and trial number), so the same seed always gives
+    // the same inputs.
+    pub seed: u64,
+    // How many cells to run at the same time.
+    pub jobs: usize,
+    // Generate each input once and clone it for each algorithm, rather
+    // than regenerating it from its seed.
+    pub keep_inputs: bool,
+    // The most memory (in bytes) the inputs and sorts may use.
+    pub max_memory: Option<usize>,
+    // Generate the inputs in parallel with their pages placed this way
+    // (see `numa.rs`), rather than on whichever node `generate` runs on.
+    #[cfg(all(feature = "numa", target_os = "linux"))]
+    pub placement: Option<Placement>,
+    // Copy each big input into a buffer backed by huge pages (see
+    // `hugepages.rs`) before sorting it.
+    #[cfg(all(feature = "hugepages", target_os = "linux"))]
+    pub huge_pages: bool,
+    // How parallel-merge sorts.
+    pub parallel: ParallelConfig,
+    // Run each algorithm on its own best, average, and worst cases
+    // instead of on `distributions`.
+    pub auto_cases: bool,
+    // Keep running trials of each cell until the time's up or the
+    // timings are precise enough (see `adaptive.rs`), in which case
+    // `trials` is just the minimum. The target is the half-width of the
+    // 95% confidence interval as a fraction of the mean.
+    pub max_time: Option<Duration>,
+    pub target_ci: Option<f64>,
+    // Run the cells in a random order, shuffled with this seed, rather
+    // than each algorithm straight after the last. The results still
+    // come back in the order of `cells()`.
+    pub schedule_seed: Option<u64>,
+    // Instead of running the matrix once, keep running its cells every
+    // `soak_interval` for this long, logging how their times drift (see
+    // `soak.rs`).
+    pub soak: Option<Duration>,
+    pub soak_interval: Duration,
+    // Don't time anything: count each sort's comparisons, swaps, moves,
+    // and recursion depth instead (see `opcount.rs`).
+    pub count_only: bool,
+}
+
+impl Default for MatrixConfig {
+    fn default() -> MatrixConfig {
+        MatrixConfig {
+            algorithms: algorithms(),
+            sizes: vec![1000],
+            distributions: vec![Distribution::Uniform],
+            trials: 1,
+            seed: 0,
+            jobs: 1,
+            keep_inputs: false,
+            max_memory: None,
+            #[cfg(all(feature = "numa", target_os = "linux"))]
+            placement: None,
+            #[cfg(all(feature = "hugepages", target_os = "linux"))]
+            huge_pages: false,
+            parallel: ParallelConfig::default(),
+            auto_cases: false,
+            max_time: None,
+            target_ci: None,
+            schedule_seed: None,
+            soak: None,
+            soak_interval: Duration::from_secs(10),
+            count_only: false,
+        }
+    }
+}
+
+// One cell of the matrix: one run of one algorithm on one input.
+#[derive(Debug, Clone, Copy)]
+pub struct Cell {
+    pub algorithm: Algorithm,
+    pub size: usize,
+    pub distribution: Distribution,
+    pub trial: usize,
+    // Which of the algorithm's cases the input is, with `auto_cases`.
+    pub case: Option<Case>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunResult {
+    pub algorithm: &'static str,
+    pub size: usize,
+    pub distribution: Distribution,
+    pub trial: usize,
+    pub case: Option<Case>,
+    pub elapsed: Duration,
+    // Whether the output was actually in order.
+    pub verified: bool,
+    // A hash of the input (see `checksum`), so we can check that every
+    // algorithm sorted the same thing.
+    pub input_checksum: u64,
+    // Whether the input was in memory we'd asked to be backed by huge
+    // pages. (This is always false without the `hugepages` feature.)
+    pub huge_pages: bool,
+}
+
+impl MatrixConfig {
+    // All the cells, in the order we'd run them one at a time.
+    pub fn cells(&self) -> Vec<Cell> {
+        let mut cells = Vec::new();
+        if self.auto_cases {
+            for &size in &self.sizes {
+                for trial in 0..self.trials {
+                    for &algorithm in &self.algorithms {
+                        for (case, distribution) in case_distributions(&algorithm) {
+                            cells.push(Cell {
+                                algorithm,
+                                size,
+                                distribution,
+                                trial,
+                                case: Some(case),
+                            });
+                        }
+                    }
+                }
+            }
+            return cells;
+        }
+        for &size in &self.sizes {
+            for &distribution in &self.distributions {
+                for trial in 0..self.trials {
+                    for &algorithm in &self.algorithms {
+                        cells.push(Cell {
+                            algorithm,
+                            size,
+                            distribution,
+                            trial,
+                            case: None,
+                        });
+                    }
+                }
+            }
+        }
+        cells
+    }
+
+    // How many cells in a row share an input: one per algorithm, or just
+    // the one with `auto_cases`.
+    fn cells_per_input(&self) -> usize {
+        if self.auto_cases {
+            1
+        } else {
+            self.algorithms.len().max(1)
+        }
+    }
+
+    // How many different inputs there are of each size, for each trial.
+    fn inputs_per_size(&self) -> usize {
+        if self.auto_cases {
+            self.algorithms
+                .iter()
+                .map(|a| case_distributions(a).len())
+                .sum()
+        } else {
+            self.distributions.len()
+        }
+    }
+
+    // The seed for the input of a particular (size, distribution, trial).
+    // We mix the numbers together with a few multiply-and-xor steps so
+    // that nearby cells get unrelated seeds.
+    pub fn input_seed(&self, size: usize, distribution: Distribution, trial: usize) -> u64 {
+        let mut seed = self.seed;
+        for &x in &[size as u64, distribution as u64, trial as u64] {
+            seed = (seed ^ x).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+            seed ^= seed >> 29;
+        }
+        seed
+    }
+
+    // The order to run `count` cells (or groups of cells) in: cell
+    // schedule[0] first, then schedule[1], and so on.
+    pub fn schedule(&self, count: usize) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..count).collect();
+        if let Some(seed) = self.schedule_seed {
+            shuffle(&mut order, &mut SeededRandom::new(seed));
+        }
+        order
+    }
+
+    // Whether the number of trials is decided as we go.
+    pub fn is_adaptive(&self) -> bool {
+        self.max_time.is_some() || self.target_ci.is_some()
+    }
+
+    // The number of threads we'll actually use: what was asked for, but
+    // no more than the number of cores (or cells).
+    pub fn effective_jobs(&self) -> usize {
+        let cores = thread::available_parallelism().map_or(1, |n| n.get());
+        self.jobs.max(1).min(cores).min(self.cells().len().max(1))
+    }
+}
+
+// Roughly how many copies of its input a single cell has in memory at
+// once: the input itself, plus merge sort's output and the halves it
+// merges.
+pub const COPIES_PER_CELL: usize = 3;
+
+impl MatrixConfig {
+    // A (deliberately generous) estimate of the most memory the matrix
+    // will use at once: each running cell holds a few copies of its
+    // input, and with `keep_inputs` every input is also held the whole
+    // time.
+    pub fn memory_needed(&self) -> usize {
+        let element_size = std::mem::size_of::<i32>();
+        let largest = self.sizes.iter().cloned().max().unwrap_or(0);
+        let running = largest
+            .saturating_mul(element_size)
+            .saturating_mul(COPIES_PER_CELL)
+            .saturating_mul(self.effective_jobs());
+        if !self.keep_inputs || self.is_adaptive() {
+            return running;
+        }
+        let kept = self
+            .sizes
+            .iter()
+            .fold(0usize, |total, &size| total.saturating_add(size))
+            .saturating_mul(element_size)
+            .saturating_mul(self.inputs_per_size())
+            .saturating_mul(self.trials);
+        running.saturating_add(kept)
+    }
+
+    // Make sure the matrix fits in `max_memory` (if there is one).
+    pub fn check_memory(&self) -> Result<(), MemoryLimitExceeded> {
+        match self.max_memory {
+            Some(max_memory) if self.memory_needed() > max_memory => Err(MemoryLimitExceeded {
+                needed: self.memory_needed(),
+                max_memory,
+                keep_inputs: self.keep_inputs,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    // The input for a cell, freshly generated from its seed.
+    pub fn generate_input(&self, cell: &Cell) -> Vec<i32> {
+        let seed = self.input_seed(cell.size, cell.distribution, cell.trial);
+        #[cfg(all(feature = "numa", target_os = "linux"))]
+        // Only the uniform inputs are expensive enough to be worth
+        // generating in parallel.
+        if let (Some(placement), Distribution::Uniform) = (self.placement, cell.distribution) {
+            let threads = thread::available_parallelism().map_or(1, |n| n.get());
+            return generate_placed(cell.size, 0, cell.size as i32, seed, threads, placement);
+        }
+        cell.distribution.generate(cell.size, seed)
+    }
+}
+
+impl MatrixConfig {
+    // Get an input ready to be sorted: with `huge_pages`, copy it into
+    // memory backed by huge pages. Also returns whether we did.
+    #[cfg(all(feature = "hugepages", target_os = "linux"))]
+    fn prepare_input(&self, v: Vec<i32>) -> (Vec<i32>, bool) {
+        if self.huge_pages {
+            copy_to_huge_pages(&v)
+        } else {
+            (v, false)
+        }
+    }
+
+    #[cfg(not(all(feature = "hugepages", target_os = "linux")))]
+    fn prepare_input(&self, v: Vec<i32>) -> (Vec<i32>, bool) {
+        (v, false)
+    }
+}
+
+pub fn run_cell(config: &MatrixConfig, cell: &Cell) -> RunResult {
+    run_cell_on(config, cell, config.generate_input(cell))
+}
+
+// Run a cell on an input that's already been generated.
+pub(crate) fn run_cell_on(config: &MatrixConfig, cell: &Cell, v: Vec<i32>) -> RunResult {
+    let (mut v, huge_pages) = config.prepare_input(v);
+    let input_checksum = checksum(&v);
+    let before = Instant::now();
+    if cell.algorithm.name == "parallel-merge" {
+        let sorted = parallel_merge_sort(&v, &config.parallel);
+        v.copy_from_slice(&sorted);
+    } else {
+        cell.algorithm.sort(&mut v);
+    }
+    let elapsed = before.elapsed();
+    RunResult {
+        algorithm: cell.algorithm.name,
+        size: cell.size,
+        distribution: cell.distribution,
+        trial: cell.trial,
+        case: cell.case,
+        elapsed,
+        huge_pages,
+        verified: v.windows(2).all(|w| w[0] <= w[1]),
+        input_checksum,
+    }
+}
+
+// A 64-bit FNV-1a hash of the values, in order. It's not
+// cryptographic, but any accidental difference between two inputs (a
+// value changed, or two swapped) will almost certainly change it.
+pub fn checksum(v: &[i32]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for x in v {
+        for byte in x.to_le_bytes().iter() {
+            hash ^= *byte as u64;
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    hash
+}
+
+// Check that all the runs that were supposed to sort the same input
+// (the ones with the same size, distribution, and trial) really did.
+pub fn check_inputs(results: &[RunResult]) -> Result<(), InputsDiffer> {
+    for (i, a) in results.iter().enumerate() {
+        let same_input = |b: &&RunResult| {
+            (b.size, b.distribution, b.trial) == (a.size, a.distribution, a.trial)
+        };
+        // Comparing each run with the first one for its input is enough.
+        if let Some(first) = results[..i].iter().find(same_input) {
+            if first.input_checksum != a.input_checksum {
+                return Err(InputsDiffer {
+                    size: a.size,
+                    input: a.distribution.name(),
+                    trial: a.trial,
+                    first: first.algorithm,
+                    second: a.algorithm,
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+// Run every cell of the matrix and return the results in the same order
+// as `config.cells()`, no matter how many threads we used.
+// Call `check_memory` first if there's a memory limit.
+pub fn run_matrix(config: &MatrixConfig) -> Vec<RunResult> {
+    // Start parallel-merge's threads now, so we don't time starting them.
+    if config.algorithms.iter().any(|a| a.name == "parallel-merge") {
+        init_pool(config.parallel.threads);
+    }
+    if config.is_adaptive() {
+        return run_adaptive(config);
+    }
+    let cells = config.cells();
+    let jobs = config.effective_jobs();
+    // The cells for each input are next to each other, one per
+    // algorithm, so cell i uses input i / (number of algorithms).
+    let per_input = config.cells_per_input();
+    let inputs: Option<Vec<Vec<i32>>> = if config.keep_inputs {
+        Some(
+            cells
+                .iter()
+                .step_by(per_input)
+                .map(|cell| config.generate_input(cell))
+                .collect(),
+        )
+    } else {
+        None
+    };
+    let run = |i: usize| match &inputs {
+        Some(inputs) => run_cell_on(config, &cells[i], inputs[i / per_input].clone()),
+        None => run_cell(config, &cells[i]),
+    };
+    let schedule = config.schedule(cells.len());
+    in_cell_order(&schedule, run_on_threads(cells.len(), jobs, |k| run(schedule[k])))
+}
+
+// Put results that were produced in the order of `schedule` back in
+// cell order.
+pub(crate) fn in_cell_order<R>(schedule: &[usize], results: Vec<R>) -> Vec<R> {
+    let mut ordered: Vec<Option<R>> = (0..results.len()).map(|_| None).collect();
+    for (&i, result) in schedule.iter().zip(results) {
+        ordered[i] = Some(result);
+    }
+    ordered.into_iter().map(Option::unwrap).collect()
+}
+
+// run(0), run(1), ..., run(count - 1) on `jobs` threads, with the
+// results in order.
+pub(crate) fn run_on_threads<R, F>(count: usize, jobs: usize, run: F) -> Vec<R>
+where
+    R: Send,
+    F: Fn(usize) -> R + Sync,
+{
+    if jobs <= 1 {
+        return (0..count).map(run).collect();
+    }
+
+    // Each worker repeatedly claims the next unclaimed index until
+    // there aren't any left.
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new((0..count).map(|_| None).collect::<Vec<Option<R>>>());
+    thread::scope(|scope| {
+        for _ in 0..jobs {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::SeqCst);
+                if i >= count {
+                    break;
+                }
+                let result = run(i);
+                results.lock().unwrap()[i] = Some(result);
+            });
+        }
+    });
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|r| r.expect("every cell should have been run"))
+        .collect()
+}
+
+impl RunResult {
+    // The input's distribution, and which case it was for the algorithm
+    // if we know, e.g. "sorted (worst)".
+    pub fn describe_input(&self) -> String {
+        match self.case {
+            Some(case) => format!("{} ({})", self.distribution.name(), case.name()),
+            None => self.distribution.name().to_string(),
+        }
+    }
+}
+
+// Print the results, one line per cell.
+pub fn print_results(results: &[RunResult]) {
+    for r in results {
+        println!(
+            "{:<22} {:>10} {:<10} trial {:>3}: {:?}{}{}",
+            r.algorithm,
+            r.size,
+            r.describe_input(),
+            r.trial,
+            r.elapsed,
+            if r.huge_pages { "  (huge pages)" } else { "" },
+            if r.verified { "" } else { "  NOT SORTED" }
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn small_config(jobs: usize) -> MatrixConfig {
+        MatrixConfig {
+            sizes: vec![10, 100],
+            trials: 3,
+            seed: 218,
+            jobs,
+            ..MatrixConfig::default()
+        }
+    }
+
+    #[test]
+    fn every_algorithm_sorts() {
+        for algorithm in algorithms() {
+            let mut v = vec![3, 2, 0, 5, 8, 9, 6, 3, 2, 0];
+            algorithm.sort(&mut v);
+            assert_eq!(vec![0, 0, 2, 2, 3, 3, 5, 6, 8, 9], v, "{}", algorithm.name);
+        }
+    }
+
+    #[test]
+    fn every_algorithm_sorts_generically() {
+        for algorithm in algorithms() {
+            let mut v = vec![3, 2, 0, 5, 8, 9, 6, 3, 2, 0];
+            match sort_generic(algorithm.name, &mut v) {
+                Ok(()) => assert_eq!(vec![0, 0, 2, 2, 3, 3, 5, 6, 8, 9], v),
+                // The one algorithm `sort_generic` is allowed to skip.
+                Err(_) => assert_eq!("parallel-merge", algorithm.name),
+            }
+        }
+    }
+
+    #[test]
+    fn distributions() {
+        for name in &[
+            "uniform",
+            "sorted",
+            "reversed",
+            "nearly-sorted",
+            "few-unique",
+            "median-first",
+            "merge-worst",
+        ] {
+            let distribution = Distribution::from_name(name).unwrap();
+            assert_eq!(*name, distribution.name());
+            let mut v = distribution.generate(1000, 238);
+            assert_eq!(1000, v.len());
+            v.sort();
+            if !matches!(distribution, Distribution::Uniform | Distribution::FewUnique) {
+                // A rearrangement of 0..1000.
+                assert_eq!((0..1000).collect::<Vec<i32>>(), v);
+            }
+        }
+        assert_eq!(vec![0, 1, 2], Distribution::Sorted.generate(3, 0));
+        assert_eq!(vec![2, 1, 0], Distribution::Reversed.generate(3, 0));
+        assert_eq!(vec![0; 3], Distribution::AllEqual.generate(3, 0));
+        let mut few = Distribution::FewUnique.generate(1000, 238);
+        few.sort();
+        few.dedup();
+        assert_eq!((0..FEW_UNIQUE).collect::<Vec<i32>>(), few);
+        assert_eq!(vec![1, 0, 2], Distribution::MedianFirst.generate(3, 0));
+        assert_eq!(vec![3, 1, 2, 0], Distribution::MergeWorst.generate(4, 0));
+        let nearly = Distribution::NearlySorted.generate(1000, 238);
+        let misplaced = nearly.iter().enumerate().filter(|&(i, &x)| i as i32 != x).count();
+        assert!(misplaced <= 20, "{} values out of place", misplaced);
+    }
+
+    #[test]
+    fn one_result_per_cell() {
+        let config = small_config(1);
+        let results = run_matrix(&config);
+        assert_eq!(2 * 3 * algorithms().len(), results.len());
+        assert!(results.iter().all(|r| r.verified));
+    }
+
+    #[test]
+    fn parallel_results_in_cell_order() {
+        let config = small_config(4);
+        let results = run_matrix(&config);
+        let cells = config.cells();
+        assert_eq!(cells.len(), results.len());
+        for (cell, result) in cells.iter().zip(&results) {
+            assert_eq!(cell.algorithm.name, result.algorithm);
+            assert_eq!(cell.size, result.size);
+            assert_eq!(cell.trial, result.trial);
+            assert!(result.verified);
+        }
+    }
+
+    #[test]
+    fn inputs_are_reproducible() {
+        let config = small_config(1);
+        let seed = config.input_seed(100, Distribution::Uniform, 2);
+        assert_eq!(
+            Distribution::Uniform.generate(100, seed),
+            Distribution::Uniform.generate(100, seed)
+        );
+        assert_ne!(seed, config.input_seed(100, Distribution::Uniform, 1));
+    }
+
+    #[test]
+    fn kept_inputs_give_same_results() {
+        let config = MatrixConfig {
+            keep_inputs: true,
+            ..small_config(2)
+        };
+        let results = run_matrix(&config);
+        assert_eq!(config.cells().len(), results.len());
+        assert!(results.iter().all(|r| r.verified));
+    }
+
+    #[test]
+    fn memory_limit() {
+        let mut config = MatrixConfig {
+            sizes: vec![1_000_000],
+            trials: 10,
+            ..MatrixConfig::default()
+        };
+        // One cell at a time: 3 copies of 4 MB.
+        assert_eq!(12_000_000, config.memory_needed());
+        config.max_memory = Some(12_000_000);
+        assert!(config.check_memory().is_ok());
+        // Keeping all 10 inputs adds another 40 MB.
+        config.keep_inputs = true;
+        let error = config.check_memory().unwrap_err();
+        assert_eq!(52_000_000, error.needed);
+        assert!(error.to_string().contains("regenerated from its seed"));
+    }
+
+    #[test]
+    fn absurd_sizes_dont_overflow() {
+        let config = MatrixConfig {
+            sizes: vec![usize::MAX],
+            keep_inputs: true,
+            max_memory: Some(1 << 30),
+            ..MatrixConfig::default()
+        };
+        assert_eq!(usize::MAX, config.memory_needed());
+        assert!(config.check_memory().is_err());
+    }
+
+    #[test]
+    fn shuffled_schedule() {
+        let config = MatrixConfig {
+            schedule_seed: Some(266),
+            ..small_config(1)
+        };
+        let mut schedule = config.schedule(50);
+        assert_ne!((0..50).collect::<Vec<usize>>(), schedule);
+        assert_eq!(schedule, config.schedule(50));
+        schedule.sort_unstable();
+        assert_eq!((0..50).collect::<Vec<usize>>(), schedule);
+        assert_eq!(vec![0, 1, 2], small_config(1).schedule(3));
+
+        // Still one result per cell, in cell order.
+        let cells = config.cells();
+        let results = run_matrix(&config);
+        for (cell, result) in cells.iter().zip(&results) {
+            assert_eq!(cell.algorithm.name, result.algorithm);
+            assert_eq!((cell.size, cell.trial), (result.size, result.trial));
+        }
+        assert_eq!(vec!['b', 'c', 'a'], in_cell_order(&[2, 0, 1], vec!['a', 'b', 'c']));
+    }
+
+    #[test]
+    fn same_inputs() {
+        assert_eq!(checksum(&[1, 2, 3]), checksum(&[1, 2, 3]));
+        assert_ne!(checksum(&[1, 2, 3]), checksum(&[2, 1, 3]));
+        assert_ne!(checksum(&[]), checksum(&[0]));
+
+        for &keep_inputs in &[false, true] {
+            let config = MatrixConfig {
+                keep_inputs,
+                auto_cases: keep_inputs,
+                ..small_config(2)
+            };
+            let results = run_matrix(&config);
+            assert_eq!(Ok(()), check_inputs(&results));
+        }
+
+        let mut results = run_matrix(&small_config(1));
+        results[1].input_checksum ^= 1;
+        let error = check_inputs(&results).unwrap_err();
+        assert_eq!((results[0].algorithm, results[1].algorithm), (error.first, error.second));
+    }
+
+    #[test]
+    fn auto_cases() {
+        let config = MatrixConfig {
+            algorithms: vec![find_algorithm("quicksort").unwrap()],
+            sizes: vec![200],
+            trials: 2,
+            keep_inputs: true,
+            auto_cases: true,
+            ..MatrixConfig::default()
+        };
+        let results = run_matrix(&config);
+        let cases: Vec<_> = results.iter().map(|r| (r.trial, r.case, r.distribution)).collect();
+        assert_eq!(
+            vec![
+                (0, Some(Case::Best), Distribution::MedianFirst),
+                (0, Some(Case::Average), Distribution::Uniform),
+                (0, Some(Case::Worst), Distribution::Sorted),
+                (1, Some(Case::Best), Distribution::MedianFirst),
+                (1, Some(Case::Average), Distribution::Uniform),
+                (1, Some(Case::Worst), Distribution::Sorted),
+            ],
+            cases
+        );
+        assert!(results.iter().all(|r| r.verified));
+        assert_eq!("sorted (worst)", results[2].describe_input());
+        // Six inputs kept, of 800 bytes each, plus the running cell.
+        assert_eq!(6 * 800 + 3 * 800, config.memory_needed());
+    }
+
+    #[test]
+    fn jobs_are_limited_by_cores_and_cells() {
+        let config = small_config(1_000_000);
+        assert!(config.effective_jobs() <= config.cells().len());
+        assert!(config.effective_jobs() >= 1);
+        assert_eq!(1, small_config(0).effective_jobs());
+    }
+}
@@ -0,0 +1,129 @@
+// An end-to-end run of the benchmark, start to finish, using only the
+// library's public API: read a config file, generate the inputs, run
+// every algorithm on them, check the results are sorted, and write a
+// report. It's meant as a template for writing your own experiments,
+// e.g.
+//    cargo run --release --bin pipeline -- pipeline.conf --json results.json --markdown results.md
+// See `cli::parse_matrix_config` for the config file format. With no
+// `--json` or `--markdown` the Markdown report goes to standard output.
+
+use sorting_in_rust::benchmark::{check_inputs, run_matrix, RunResult};
+use sorting_in_rust::cli::parse_matrix_config;
+use sorting_in_rust::report::{to_json, to_markdown};
+use std::path::{Path, PathBuf};
+
+const USAGE: &str = "usage: pipeline CONFIG [--json FILE] [--markdown FILE]";
+
+struct Options {
+    config: PathBuf,
+    json: Option<PathBuf>,
+    markdown: Option<PathBuf>,
+}
+
+fn main() {
+    let options = match parse_options(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("error: {}\n\n{}", message, USAGE);
+            std::process::exit(2);
+        }
+    };
+    match run(&options) {
+        Ok(true) => {}
+        Ok(false) => std::process::exit(1),
+        Err(message) => {
+            eprintln!("error: {}", message);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn parse_options<I: Iterator<Item = String>>(mut args: I) -> Result<Options, String> {
+    let mut config = None;
+    let mut json = None;
+    let mut markdown = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--json" => json = Some(PathBuf::from(args.next().ok_or("`--json` needs a file")?)),
+            "--markdown" => {
+                markdown = Some(PathBuf::from(
+                    args.next().ok_or("`--markdown` needs a file")?,
+                ))
+            }
+            _ if arg.starts_with("--") => return Err(format!("unknown option `{}`", arg)),
+            _ if config.is_none() => config = Some(PathBuf::from(arg)),
+            _ => return Err("pipeline takes just one config file".to_string()),
+        }
+    }
+    Ok(Options {
+        config: config.ok_or("pipeline needs a config file")?,
+        json,
+        markdown,
+    })
+}
+
+// Returns whether every sort worked.
+fn run(options: &Options) -> Result<bool, String> {
+    // 1. Read the config.
+    let text = std::fs::read_to_string(&options.config)
+        .map_err(|error| format!("couldn't read {}: {}", options.config.display(), error))?;
+    let config = parse_matrix_config(&text)
+        .map_err(|message| format!("{}: {}", options.config.display(), message))?;
+    config.check_memory().map_err(|error| error.to_string())?;
+
+    // 2. Generate the inputs. `run_matrix` does this for us, one cell at
+    // a time, but we generate each one here too so we can check that it
+    // really is the same input every time.
+    let cells = config.cells();
+    for cell in &cells {
+        if config.generate_input(cell) != config.generate_input(cell) {
+            return Err(format!("input for {:?} isn't reproducible", cell));
+        }
+    }
+    eprintln!(
+        "generated inputs for {} cells ({} algorithms)",
+        cells.len(),
+        config.algorithms.len()
+    );
+
+    // 3. Run the matrix.
+    if config.parallel.verbose {
+        eprintln!("parallel-merge: {}", config.parallel);
+    }
+    let results = run_matrix(&config);
+
+    // 4. Verify: every algorithm got the same inputs, and sorted them.
+    check_inputs(&results).map_err(|error| error.to_string())?;
+    let failures: Vec<&RunResult> = results.iter().filter(|r| !r.verified).collect();
+    for r in &failures {
+        eprintln!(
+            "{} didn't sort {} {} values (trial {})",
+            r.algorithm,
+            r.size,
+            r.describe_input(),
+            r.trial
+        );
+    }
+    eprintln!(
+        "{} of {} runs sorted correctly",
+        results.len() - failures.len(),
+        results.len()
+    );
+
+    // 5. Report.
+    if let Some(path) = &options.json {
+        write(path, &to_json(&results, &config))?;
+    }
+    if let Some(path) = &options.markdown {
+        write(path, &to_markdown(&results, &config))?;
+    }
+    if options.json.is_none() && options.markdown.is_none() {
+        print!("{}", to_markdown(&results, &config));
+    }
+    Ok(failures.is_empty())
+}
+
+fn write(path: &Path, contents: &str) -> Result<(), String> {
+    std::fs::write(path, contents)
+        .map_err(|error| format!("couldn't write {}: {}", path.display(), error))
+}
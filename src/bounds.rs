@@ -0,0 +1,167 @@
+// How few comparisons could any sort make on this input? The classic
+// answer is log2(N!) ≈ N log2 N - 1.44 N: there are N! orders the input
+// could be in, and each comparison at best halves the number still
+// possible. But that's for an input we know nothing about. If the input
+// is already mostly sorted, an "adaptive" sort (insertion sort on nearly
+// sorted data, or a merge sort that finds the runs already there) can
+// do much better, and the question becomes how much better it could
+// possibly do. That depends on how we measure "mostly sorted":
+//
+//  * Runs. If the input is r ascending runs of lengths n1, ..., nr, the
+//    only question is how to interleave them, and there are
+//    N! / (n1! ... nr!) ways to, so it takes at least log2 of that,
+//    which is at most N log2 r and can be much less when most of the
+//    input is in one long run.
+//  * Inversions. If I pairs are out of order, the number of orders with
+//    at most I inversions gives a bound of about N log2(1 + I/N). (This
+//    one's only right to within O(N), so it's an estimate rather than a
+//    strict bound.)
+//
+// An algorithm that's optimal for one of the measures can get down to
+// its bound (more or less), so the best we can hope for on a given input
+// is the smallest of them, but never less than N - 1: it takes that many
+// comparisons just to check that the output is in order.
+//
+// These all treat the values as distinct; with lots of duplicates a
+// sort can do better still.
+
+use crate::instrument::inversions;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LowerBounds {
+    pub size: usize,
+    // log2(N!), for an input we know nothing about.
+    pub information: f64,
+    // How many ascending runs the input has, and the bound from them.
+    pub runs: usize,
+    pub runs_bound: f64,
+    // How many pairs are out of order, and the bound from them.
+    pub inversions: u64,
+    pub inversions_bound: f64,
+}
+
+impl LowerBounds {
+    pub fn of<T: PartialOrd + Clone>(v: &[T]) -> LowerBounds {
+        let n = v.len();
+        let lengths = ascending_runs(v);
+        let runs_bound =
+            log2_factorial(n) - lengths.iter().map(|&k| log2_factorial(k)).sum::<f64>();
+        let inversions = inversions(v);
+        LowerBounds {
+            size: n,
+            information: log2_factorial(n),
+            runs: lengths.len(),
+            runs_bound: runs_bound.max(0.0),
+            inversions,
+            inversions_bound: n as f64 * (1.0 + inversions as f64 / n.max(1) as f64).log2(),
+        }
+    }
+
+    // The fewest comparisons we could hope for: the smallest of the
+    // bounds, but at least enough to check the order (see the top of
+    // the file).
+    pub fn best(&self) -> f64 {
+        let check = self.size.saturating_sub(1) as f64;
+        self.information
+            .min(self.runs_bound)
+            .min(self.inversions_bound)
+            .max(check)
+    }
+
+    // How many times `best` a sort's comparisons were: 1 is as good as
+    // it gets. `None` when there was nothing to compare.
+    pub fn ratio(&self, comparisons: u64) -> Option<f64> {
+        let best = self.best();
+        if best > 0.0 {
+            Some(comparisons as f64 / best)
+        } else {
+            None
+        }
+    }
+}
+
+// The lengths of the maximal ascending (well, non-descending) runs of
+// `v`, e.g. [1, 3, 2, 2, 5, 0] has runs [1, 3], [2, 2, 5], and [0].
+pub fn ascending_runs<T: PartialOrd>(v: &[T]) -> Vec<usize> {
+    let mut lengths = Vec::new();
+    let mut start = 0;
+    for i in 1..v.len() {
+        if v[i] < v[i - 1] {
+            lengths.push(i - start);
+            start = i;
+        }
+    }
+    if !v.is_empty() {
+        lengths.push(v.len() - start);
+    }
+    lengths
+}
+
+// log2(n!), exactly (well, to floating point) for small n, and from
+// Stirling's approximation for big ones, where it's good to better than
+// one part in 10^9.
+pub fn log2_factorial(n: usize) -> f64 {
+    if n < 32 {
+        return (2..=n).map(|k| (k as f64).log2()).sum();
+    }
+    let n = n as f64;
+    let ln = n * n.ln() - n + 0.5 * (2.0 * std::f64::consts::PI * n).ln() + 1.0 / (12.0 * n);
+    ln / std::f64::consts::LN_2
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn factorials() {
+        assert_eq!(0.0, log2_factorial(0));
+        assert_eq!(0.0, log2_factorial(1));
+        assert!((log2_factorial(10) - 3_628_800f64.log2()).abs() < 1e-9);
+        // Stirling takes over at 32; it should agree with the sum.
+        let sum: f64 = (2..=1000).map(|k| (k as f64).log2()).sum();
+        assert!((log2_factorial(1000) - sum).abs() < 1e-6);
+    }
+
+    #[test]
+    fn runs() {
+        assert_eq!(vec![2, 3, 1], ascending_runs(&[1, 3, 2, 2, 5, 0]));
+        assert_eq!(vec![10], ascending_runs(&[0, 0, 2, 2, 3, 3, 5, 6, 8, 9]));
+        assert!(ascending_runs::<i32>(&[]).is_empty());
+    }
+
+    #[test]
+    fn sorted_input() {
+        let v: Vec<i32> = (0..1000).collect();
+        let bounds = LowerBounds::of(&v);
+        assert_eq!((1, 0), (bounds.runs, bounds.inversions));
+        assert_eq!(0.0, bounds.runs_bound);
+        // Nothing to do but check it.
+        assert_eq!(999.0, bounds.best());
+        assert_eq!(Some(1.0), bounds.ratio(999));
+    }
+
+    #[test]
+    fn random_input() {
+        let v = [3, 2, 0, 5, 8, 9, 6, 3, 2, 0];
+        let bounds = LowerBounds::of(&v);
+        assert_eq!((7, 23), (bounds.runs, bounds.inversions));
+        // Even here, the run 0, 5, 8, 9 saves a few comparisons: the
+        // runs bound is log2(10! / 4!) ≈ 17.2, against log2(10!) ≈ 21.8.
+        assert_eq!(bounds.runs_bound, bounds.best());
+        assert!((bounds.runs_bound - 17.2).abs() < 0.01);
+        assert!(bounds.information > 21.7);
+    }
+
+    #[test]
+    fn two_runs() {
+        // Two sorted halves can be merged in N - 1 comparisons, and the
+        // runs bound is log2(C(N, N/2)) ≈ N - log2(N)/2, a bit under.
+        let v: Vec<i32> = (0..500).chain(0..500).collect();
+        let bounds = LowerBounds::of(&v);
+        assert_eq!(2, bounds.runs);
+        assert!(bounds.runs_bound < 1000.0 && bounds.runs_bound > 990.0);
+        assert_eq!(999.0, bounds.best());
+        assert_eq!(None, LowerBounds::of(&[7]).ratio(0));
+    }
+}
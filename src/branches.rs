@@ -0,0 +1,330 @@
+// How predictable are a sort's comparisons? A modern CPU doesn't wait to
+// find out which way an `if` goes: it guesses from which way that branch
+// went before and carries on, and when it guesses wrong it has to throw
+// away the work it did and start again, which costs something like 15
+// or 20 cycles, about as long as a few dozen comparisons that it guessed
+// right. So two sorts making the same number of comparisons can take
+// quite different times. Insertion sort's inner loop compares and
+// finds "still bigger, keep going" over and over and then "stop" once,
+// which is easy to guess; quicksort partitioning random values finds
+// each one on the small side or the big side with even odds, which no
+// predictor can guess better than half the time.
+//
+// We can't see the CPU's predictor, but we can record which way every
+// comparison went and measure how guessable the sequence is:
+//
+//  * How often comparisons come out true, and the entropy of that: 0
+//    bits if they always go the same way, 1 bit if it's a coin flip.
+//  * How long the streaks of the same outcome are, on average.
+//  * The entropy of each outcome given the one before, which is 0 for a
+//    pattern like true, false, true, false (1 bit of plain entropy, but
+//    perfectly predictable) and 1 bit for coin flips.
+//  * How often a simple two-bit predictor (the textbook one: it only
+//    changes its guess after being wrong twice in a row) guesses wrong.
+//
+// Real predictors keep a separate guess for each branch and look at
+// longer histories, so they do better than this on some patterns, but
+// the comparisons that are coin flips to us are coin flips to them too.
+//
+// As with `Counted` in `instrument.rs`, we don't change the sorts: we
+// wrap the elements in `Observed`, whose comparison operators record
+// the answer they give before giving it. We add the outcomes up as they
+// come rather than keeping them, since an insertion sort can make
+// billions.
+
+use crate::benchmark::sort_generic;
+use std::cell::RefCell;
+use std::cmp::Ordering;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutcomeStats {
+    pub comparisons: u64,
+    // How many came out true.
+    pub taken: u64,
+    // How many streaks of the same outcome there were.
+    pub streaks: u64,
+    // `transitions[a][b]` is how many times outcome `b` came straight
+    // after outcome `a` (with false as 0 and true as 1).
+    pub transitions: [[u64; 2]; 2],
+    // How many the two-bit predictor guessed wrong.
+    pub mispredictions: u64,
+    // The last outcome, and the two-bit predictor's state: 0 and 1
+    // guess false, 2 and 3 guess true.
+    last: Option<bool>,
+    counter: u8,
+}
+
+// The predictor starts out weakly guessing false.
+impl Default for OutcomeStats {
+    fn default() -> OutcomeStats {
+        OutcomeStats {
+            comparisons: 0,
+            taken: 0,
+            streaks: 0,
+            transitions: [[0; 2]; 2],
+            mispredictions: 0,
+            last: None,
+            counter: 1,
+        }
+    }
+}
+
+impl OutcomeStats {
+    pub fn record(&mut self, outcome: bool) {
+        self.comparisons += 1;
+        if outcome {
+            self.taken += 1;
+        }
+        match self.last {
+            Some(last) => {
+                self.transitions[last as usize][outcome as usize] += 1;
+                if last != outcome {
+                    self.streaks += 1;
+                }
+            }
+            None => self.streaks = 1,
+        }
+        self.last = Some(outcome);
+        if (self.counter >= 2) != outcome {
+            self.mispredictions += 1;
+        }
+        self.counter = if outcome {
+            (self.counter + 1).min(3)
+        } else {
+            self.counter.saturating_sub(1)
+        };
+    }
+
+    // The fraction of comparisons that came out true.
+    pub fn taken_rate(&self) -> f64 {
+        fraction(self.taken, self.comparisons)
+    }
+
+    // The entropy of a single outcome, in bits, from 0 to 1.
+    pub fn entropy(&self) -> f64 {
+        binary_entropy(self.taken_rate())
+    }
+
+    // The entropy of an outcome once we know the one before it, in
+    // bits, from 0 to 1.
+    pub fn conditional_entropy(&self) -> f64 {
+        let pairs = self.comparisons.saturating_sub(1);
+        self.transitions
+            .iter()
+            .map(|after| {
+                let count = after[0] + after[1];
+                fraction(count, pairs) * binary_entropy(fraction(after[1], count))
+            })
+            .sum()
+    }
+
+    // The average length of a streak of the same outcome.
+    pub fn mean_streak(&self) -> f64 {
+        if self.streaks == 0 {
+            0.0
+        } else {
+            self.comparisons as f64 / self.streaks as f64
+        }
+    }
+
+    // The fraction of comparisons the two-bit predictor got wrong.
+    pub fn misprediction_rate(&self) -> f64 {
+        fraction(self.mispredictions, self.comparisons)
+    }
+}
+
+fn fraction(part: u64, whole: u64) -> f64 {
+    if whole == 0 {
+        0.0
+    } else {
+        part as f64 / whole as f64
+    }
+}
+
+// The entropy of a coin that comes up heads with probability p.
+fn binary_entropy(p: f64) -> f64 {
+    if p <= 0.0 || p >= 1.0 {
+        0.0
+    } else {
+        -p * p.log2() - (1.0 - p) * (1.0 - p).log2()
+    }
+}
+
+thread_local! {
+    // Only `Some` inside `observe_outcomes`.
+    static OUTCOMES: RefCell<Option<OutcomeStats>> = const { RefCell::new(None) };
+}
+
+fn record(outcome: bool) -> bool {
+    OUTCOMES.with(|stats| {
+        if let Some(stats) = stats.borrow_mut().as_mut() {
+            stats.record(outcome);
+        }
+    });
+    outcome
+}
+
+// Run `f` and return its result along with how the comparisons of
+// `Observed` values it made came out.
+pub fn observe_outcomes<R, F: FnOnce() -> R>(f: F) -> (R, OutcomeStats) {
+    let before = OUTCOMES.with(|stats| stats.replace(Some(OutcomeStats::default())));
+    let result = f();
+    let stats = OUTCOMES.with(|stats| stats.replace(before));
+    (result, stats.unwrap_or_default())
+}
+
+// A value whose comparisons record how they came out.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Observed<T>(pub T);
+
+// Wrap every element of a slice.
+pub fn observed<T: Clone>(v: &[T]) -> Vec<Observed<T>> {
+    v.iter().cloned().map(Observed).collect()
+}
+
+impl<T: PartialEq> PartialEq for Observed<T> {
+    fn eq(&self, other: &Self) -> bool {
+        record(self.0 == other.0)
+    }
+}
+
+// The sorts mostly use `<` and friends, so we record the answers to
+// those as they were asked; `partial_cmp` on its own counts as "is it
+// less?".
+impl<T: PartialOrd> PartialOrd for Observed<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        let ordering = self.0.partial_cmp(&other.0);
+        record(ordering == Some(Ordering::Less));
+        ordering
+    }
+
+    fn lt(&self, other: &Self) -> bool {
+        record(self.0 < other.0)
+    }
+
+    fn le(&self, other: &Self) -> bool {
+        record(self.0 <= other.0)
+    }
+
+    fn gt(&self, other: &Self) -> bool {
+        record(self.0 > other.0)
+    }
+
+    fn ge(&self, other: &Self) -> bool {
+        record(self.0 >= other.0)
+    }
+}
+
+// Sort a copy of `input` with the named algorithm and say how its
+// comparisons came out. Like `sort_generic`, this works for everything
+// but parallel-merge.
+pub fn outcome_profile(algorithm: &str, input: &[i32]) -> Result<OutcomeStats, String> {
+    let mut v = observed(input);
+    let (result, stats) = observe_outcomes(|| sort_generic(algorithm, &mut v));
+    result.map(|()| stats)
+}
+
+// The Pearson correlation of two equally long lists of numbers, from -1
+// to 1, e.g. between how often each algorithm's comparisons were
+// mispredicted and how long each comparison took. `None` if there are
+// fewer than two pairs, or either list is all the same.
+pub fn correlation(xs: &[f64], ys: &[f64]) -> Option<f64> {
+    let n = xs.len().min(ys.len());
+    if n < 2 {
+        return None;
+    }
+    let mean = |v: &[f64]| v[..n].iter().sum::<f64>() / n as f64;
+    let (mx, my) = (mean(xs), mean(ys));
+    let (mut sxy, mut sxx, mut syy) = (0.0, 0.0, 0.0);
+    for (x, y) in xs.iter().zip(ys) {
+        sxy += (x - mx) * (y - my);
+        sxx += (x - mx) * (x - mx);
+        syy += (y - my) * (y - my);
+    }
+    if sxx == 0.0 || syy == 0.0 {
+        return None;
+    }
+    Some(sxy / (sxx * syy).sqrt())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::generate_random_array;
+    use crate::random::SeededRandom;
+
+    fn stats_of(outcomes: &[bool]) -> OutcomeStats {
+        let mut stats = OutcomeStats::default();
+        for &outcome in outcomes {
+            stats.record(outcome);
+        }
+        stats
+    }
+
+    #[test]
+    fn always_the_same() {
+        let stats = stats_of(&[true; 100]);
+        assert_eq!(
+            (100, 100, 1),
+            (stats.comparisons, stats.taken, stats.streaks)
+        );
+        assert_eq!(0.0, stats.entropy());
+        assert_eq!(0.0, stats.conditional_entropy());
+        assert_eq!(100.0, stats.mean_streak());
+        // The predictor starts out guessing false, so it's wrong once
+        // before it switches.
+        assert_eq!(1, stats.mispredictions);
+    }
+
+    #[test]
+    fn alternating() {
+        let outcomes: Vec<bool> = (0..100).map(|i| i % 2 == 0).collect();
+        let stats = stats_of(&outcomes);
+        // Half and half, so a whole bit each, but knowing the last one
+        // tells us the next.
+        assert_eq!(1.0, stats.entropy());
+        assert_eq!(0.0, stats.conditional_entropy());
+        assert_eq!(1.0, stats.mean_streak());
+        // The two-bit predictor never catches on: it flips between
+        // states 1 and 2, always guessing whatever just happened.
+        assert_eq!(100, stats.mispredictions);
+    }
+
+    #[test]
+    fn records_operator_answers() {
+        let v = observed(&[1, 2]);
+        let ((), stats) = observe_outcomes(|| {
+            assert!(v[0] < v[1]);
+            assert!(v[0] > v[1] || v[1] >= v[0]);
+            assert!(v[0] <= v[1]);
+            assert!(v[0] != v[1]);
+        });
+        assert_eq!(5, stats.comparisons);
+        // <, >, >=, <=, and == (inside !=) said true, false, true, true,
+        // false.
+        assert_eq!(3, stats.taken);
+        assert_eq!(OutcomeStats::default(), observe_outcomes(|| ()).1);
+    }
+
+    #[test]
+    fn insertion_sort_is_easier_to_predict_than_quicksort() {
+        let input = generate_random_array(2000, 0, 1_000_000, &mut SeededRandom::new(286));
+        let insertion = outcome_profile("insertion", &input).unwrap();
+        let quicksort = outcome_profile("quicksort", &input).unwrap();
+        assert!(insertion.comparisons > quicksort.comparisons);
+        assert!(insertion.misprediction_rate() < 0.01, "{:?}", insertion);
+        assert!(quicksort.misprediction_rate() > 0.25, "{:?}", quicksort);
+        assert!(quicksort.conditional_entropy() > 0.8);
+        assert!(insertion.mean_streak() > 10.0 * quicksort.mean_streak());
+        assert!(outcome_profile("parallel-merge", &input).is_err());
+    }
+
+    #[test]
+    fn correlations() {
+        let xs = [1.0, 2.0, 3.0, 4.0];
+        assert!((correlation(&xs, &[2.0, 4.0, 6.0, 8.0]).unwrap() - 1.0).abs() < 1e-12);
+        assert!((correlation(&xs, &[8.0, 6.0, 4.0, 2.0]).unwrap() + 1.0).abs() < 1e-12);
+        assert_eq!(None, correlation(&xs, &[5.0; 4]));
+        assert_eq!(None, correlation(&[1.0], &[1.0]));
+    }
+}
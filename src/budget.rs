@@ -0,0 +1,99 @@
+// Watching growth rates without a stopwatch. Give every algorithm the
+// same budget of comparisons and see how far each one gets before the
+// budget runs out: an O(N log N) sort finishes inputs hundreds of times
+// bigger than an O(N^2) one does on the same budget. Unlike timings,
+// the numbers are the same on every machine.
+//
+// "How far it got" is the budget as a percentage of the comparisons the
+// algorithm needs for the whole input, which we find by running it once
+// without a budget first.
+
+use crate::benchmark::{sort_generic, Distribution};
+use crate::instrument::{count_comparisons, counted, with_comparison_budget};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BudgetResult {
+    pub algorithm: &'static str,
+    pub size: usize,
+    // Comparisons needed to sort the whole input.
+    pub needed: u64,
+    // How much of that the budget covered, from 0 to 100.
+    pub percent_complete: f64,
+}
+
+// Run `algorithm` on an input of the given size and distribution with a
+// budget of `budget` comparisons.
+pub fn run_budgeted(
+    algorithm: &'static str,
+    size: usize,
+    distribution: Distribution,
+    seed: u64,
+    budget: u64,
+) -> Result<BudgetResult, String> {
+    let input = counted(&distribution.generate(size, seed));
+    let mut v = input.clone();
+    let (result, needed) = count_comparisons(|| sort_generic(algorithm, &mut v));
+    result?;
+    let mut v = input;
+    let finished = with_comparison_budget(budget, || sort_generic(algorithm, &mut v)).is_ok();
+    let percent_complete = if finished {
+        100.0
+    } else {
+        // Randomized quicksort might need more comparisons this time than
+        // last time, so don't go over 100.
+        (100.0 * budget as f64 / needed as f64).min(100.0)
+    };
+    Ok(BudgetResult {
+        algorithm,
+        size,
+        needed,
+        percent_complete,
+    })
+}
+
+// Print a table with a row per algorithm and a column per size.
+pub fn print_budget_table(results: &[BudgetResult], sizes: &[usize]) {
+    print!("{:<22}", "");
+    for size in sizes {
+        print!("{:>10}", size);
+    }
+    println!();
+    for row in results.chunks(sizes.len().max(1)) {
+        print!("{:<22}", row[0].algorithm);
+        for r in row {
+            print!("{:>9.1}%", r.percent_complete);
+        }
+        println!();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn small_input_finishes() {
+        let r = run_budgeted("merge", 10, Distribution::Uniform, 242, 1000).unwrap();
+        assert_eq!(100.0, r.percent_complete);
+        assert!(r.needed > 0 && r.needed <= 1000);
+    }
+
+    #[test]
+    fn quadratic_falls_behind() {
+        let budget = 20_000;
+        let insertion =
+            run_budgeted("insertion", 2000, Distribution::Uniform, 242, budget).unwrap();
+        let merge = run_budgeted("merge", 2000, Distribution::Uniform, 242, budget).unwrap();
+        assert_eq!(100.0, merge.percent_complete);
+        assert!(insertion.percent_complete < 5.0, "{:?}", insertion);
+        assert_eq!(
+            budget as f64 * 100.0 / insertion.needed as f64,
+            insertion.percent_complete
+        );
+    }
+
+    #[test]
+    fn parallel_merge_is_refused() {
+        assert!(run_budgeted("parallel-merge", 10, Distribution::Uniform, 0, 10).is_err());
+    }
+}
@@ -0,0 +1,470 @@
+// A reproducibility bundle: everything needed to run a matrix again and
+// get the same numbers (or to see why you didn't), in one `.tar.gz` to
+// attach to a lab submission or a bug report. It holds
+//    config.conf      the matrix settings, as a config file for the
+//                     `pipeline` binary, with every seed filled in
+//    environment.txt  when, where, and how it was run: the command line,
+//                     the machine, the build, and the `SORTLAB_*`
+//                     variables
+//    results.json     every run (see `report::to_json`)
+//    report.md        the Markdown report and the rankings
+//    README.txt       how to run it again
+// all in a directory named after the bundle, so `out.tar.gz` unpacks
+// into `out/`.
+//
+// We write the archive ourselves rather than pull in crates for it.
+// A tar file is just a 512-byte header per file followed by the file,
+// and a gzip file can hold its data uncompressed ("stored" deflate
+// blocks), which for a few kilobytes of text costs nothing that
+// matters. Every `tar` and `gunzip` reads both.
+
+use crate::benchmark::{MatrixConfig, RunResult};
+use crate::ranking::{rank, summary};
+use crate::report::{to_json, to_markdown};
+use crate::soak::format_utc;
+use crate::sorts::{PAR_THRESHOLD_VAR, THREADS_VAR};
+use std::fmt::Write as _;
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// Write the bundle for `results` (from running `config`) to `path`.
+// `command_line` is how the matrix was run, for the record.
+pub fn write_bundle(
+    path: &Path,
+    config: &MatrixConfig,
+    results: &[RunResult],
+    command_line: &str,
+) -> io::Result<()> {
+    let directory = bundle_name(path);
+    let files: Vec<(String, String)> = bundle_files(config, results, command_line)
+        .into_iter()
+        .map(|(name, contents)| (format!("{}/{}", directory, name), contents))
+        .collect();
+    let file = std::fs::File::create(path)?;
+    write_tar_gz(&files, SystemTime::now(), io::BufWriter::new(file))
+}
+
+// The directory the bundle unpacks into: its file name without the
+// `.tar.gz` (or `.tgz`).
+fn bundle_name(path: &Path) -> String {
+    let name = path
+        .file_name()
+        .map_or(String::new(), |name| name.to_string_lossy().into_owned());
+    let stem = name
+        .strip_suffix(".tar.gz")
+        .or_else(|| name.strip_suffix(".tgz"))
+        .unwrap_or(&name);
+    if stem.is_empty() {
+        "bundle".to_string()
+    } else {
+        stem.to_string()
+    }
+}
+
+// The files that go in the bundle, by name.
+pub fn bundle_files(
+    config: &MatrixConfig,
+    results: &[RunResult],
+    command_line: &str,
+) -> Vec<(String, String)> {
+    let mut report = to_markdown(results, config);
+    report.push('\n');
+    report.push_str(&summary(&rank(results)));
+    vec![
+        ("README.txt".to_string(), README.to_string()),
+        ("config.conf".to_string(), config_file(config)),
+        ("environment.txt".to_string(), environment(command_line)),
+        ("results.json".to_string(), to_json(results, config)),
+        ("report.md".to_string(), report),
+    ]
+}
+
+const README: &str = "\
+This bundle records one run of the sorting-in-rust benchmark matrix.
+
+To run the same matrix again (same algorithms, sizes, inputs, and
+order), build the same version of sorting-in-rust (see environment.txt)
+and run
+
+    cargo run --release --bin pipeline -- config.conf --json results.json --markdown report.md
+
+The inputs are generated from the seeds in config.conf, so they'll be
+exactly the same; the times will only be as close as the machines are
+alike. results.json records a checksum of every input, so you can check.
+";
+
+// `config` as a config file (see `cli::parse_matrix_config`) that
+// reads back as the same matrix.
+pub fn config_file(config: &MatrixConfig) -> String {
+    let mut text = String::from("# The matrix this bundle ran.\n");
+    let names: Vec<&str> = config.algorithms.iter().map(|a| a.name).collect();
+    let _ = writeln!(text, "algorithms = {}", names.join(", "));
+    let sizes: Vec<String> = config.sizes.iter().map(|n| n.to_string()).collect();
+    let _ = writeln!(text, "sizes = {}", sizes.join(", "));
+    if config.auto_cases {
+        text.push_str("cases = auto\n");
+    } else {
+        let names: Vec<&str> = config.distributions.iter().map(|d| d.name()).collect();
+        let _ = writeln!(text, "distributions = {}", names.join(", "));
+    }
+    let _ = writeln!(text, "trials = {}", config.trials);
+    let _ = writeln!(text, "seed = {}", config.seed);
+    if let Some(seed) = config.schedule_seed {
+        let _ = writeln!(text, "shuffle = {}", seed);
+    }
+    let _ = writeln!(text, "jobs = {}", config.jobs);
+    if config.keep_inputs {
+        text.push_str("keep-inputs = true\n");
+    }
+    if let Some(max_memory) = config.max_memory {
+        let _ = writeln!(text, "max-memory = {}", max_memory);
+    }
+    #[cfg(all(feature = "numa", target_os = "linux"))]
+    if let Some(placement) = config.placement {
+        let _ = writeln!(text, "placement = {}", placement.name());
+    }
+    #[cfg(all(feature = "hugepages", target_os = "linux"))]
+    if config.huge_pages {
+        text.push_str("huge-pages = true\n");
+    }
+    if let Some(max_time) = config.max_time {
+        let _ = writeln!(text, "max-time = {}", format_duration(max_time));
+    }
+    if let Some(target_ci) = config.target_ci {
+        let _ = writeln!(text, "target-ci = {}", target_ci);
+    }
+    if let Some(soak) = config.soak {
+        let _ = writeln!(text, "soak = {}", format_duration(soak));
+        let _ = writeln!(
+            text,
+            "soak-interval = {}",
+            format_duration(config.soak_interval)
+        );
+    }
+    // The settings parallel-merge actually used, wherever they came
+    // from, so the environment can't change them next time.
+    let _ = writeln!(text, "threads = {}", config.parallel.threads);
+    let _ = writeln!(
+        text,
+        "par-threshold = {}",
+        config.parallel.threshold_for::<i32>()
+    );
+    text
+}
+
+// In whole seconds or milliseconds if it can be, so it reads back
+// exactly (see `keys::parse_duration`).
+fn format_duration(duration: Duration) -> String {
+    let nanos = duration.as_nanos();
+    if nanos.is_multiple_of(1_000_000_000) {
+        format!("{}s", nanos / 1_000_000_000)
+    } else if nanos.is_multiple_of(1_000_000) {
+        format!("{}ms", nanos / 1_000_000)
+    } else {
+        format!("{}ns", nanos)
+    }
+}
+
+fn environment(command_line: &str) -> String {
+    let mut text = String::new();
+    let _ = writeln!(text, "created: {}", format_utc(SystemTime::now()));
+    let _ = writeln!(text, "command: {}", command_line);
+    let _ = writeln!(
+        text,
+        "version: sorting-in-rust {}",
+        env!("CARGO_PKG_VERSION")
+    );
+    let _ = writeln!(
+        text,
+        "build: {}",
+        if cfg!(debug_assertions) {
+            "debug (times will be much slower than --release)"
+        } else {
+            "release"
+        }
+    );
+    let features: Vec<&str> = [
+        ("numa", cfg!(feature = "numa")),
+        ("hugepages", cfg!(feature = "hugepages")),
+        ("prefetch", cfg!(feature = "prefetch")),
+        ("reference", cfg!(feature = "reference")),
+        ("allocator-api", cfg!(feature = "allocator-api")),
+        ("bump-arena", cfg!(feature = "bump-arena")),
+        ("unsafe-opt", cfg!(feature = "unsafe-opt")),
+        ("mmap", cfg!(feature = "mmap")),
+        ("gpu", cfg!(feature = "gpu")),
+    ]
+    .iter()
+    .filter(|(_, enabled)| *enabled)
+    .map(|(name, _)| *name)
+    .collect();
+    if features.is_empty() {
+        text.push_str("features: none\n");
+    } else {
+        let _ = writeln!(text, "features: {}", features.join(", "));
+    }
+    let _ = writeln!(
+        text,
+        "platform: {} {}",
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    );
+    let cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let _ = writeln!(text, "cores: {}", cores);
+    for var in &[THREADS_VAR, PAR_THRESHOLD_VAR] {
+        match std::env::var(var) {
+            Ok(value) => {
+                let _ = writeln!(text, "{}={}", var, value);
+            }
+            Err(_) => {
+                let _ = writeln!(text, "{} not set", var);
+            }
+        }
+    }
+    text
+}
+
+// `files` as a gzipped tar file, every file with modification time
+// `mtime`.
+pub fn write_tar_gz<W: Write>(
+    files: &[(String, String)],
+    mtime: SystemTime,
+    mut out: W,
+) -> io::Result<()> {
+    let seconds = mtime.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let mut tar = Vec::new();
+    for (name, contents) in files {
+        tar.extend_from_slice(&tar_header(name, contents.len(), seconds)?);
+        tar.extend_from_slice(contents.as_bytes());
+        tar.resize(tar.len() + padding(contents.len()), 0);
+    }
+    // Two empty blocks mark the end of the archive.
+    tar.resize(tar.len() + 1024, 0);
+    out.write_all(&gzip_stored(&tar, seconds as u32))?;
+    out.flush()
+}
+
+// How many zeros fill out the last 512-byte block of `len` bytes.
+fn padding(len: usize) -> usize {
+    (512 - len % 512) % 512
+}
+
+// A POSIX ("ustar") header for a regular file.
+fn tar_header(name: &str, size: usize, mtime: u64) -> io::Result<[u8; 512]> {
+    if name.len() > 100 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("`{}` is too long a name for a tar file", name),
+        ));
+    }
+    let mut header = [0u8; 512];
+    header[..name.len()].copy_from_slice(name.as_bytes());
+    // The numbers are octal, in ASCII, ending with a NUL.
+    let mut field = |offset: usize, width: usize, value: u64| {
+        let text = format!("{:0width$o}\0", value, width = width - 1);
+        header[offset..offset + width].copy_from_slice(text.as_bytes());
+    };
+    field(100, 8, 0o644); // mode
+    field(108, 8, 0); // owner
+    field(116, 8, 0); // group
+    field(124, 12, size as u64);
+    field(136, 12, mtime);
+    header[156] = b'0'; // a regular file
+    header[257..265].copy_from_slice(b"ustar\x0000");
+    // The checksum is the sum of the header's bytes, counting the
+    // checksum field itself as spaces.
+    header[148..156].copy_from_slice(b"        ");
+    let checksum: u32 = header.iter().map(|&b| b as u32).sum();
+    header[148..156].copy_from_slice(format!("{:06o}\0 ", checksum).as_bytes());
+    Ok(header)
+}
+
+// `data` as a gzip file, in uncompressed deflate blocks.
+fn gzip_stored(data: &[u8], mtime: u32) -> Vec<u8> {
+    // Magic number, deflate, no flags, the time, no extra flags, and
+    // "Unix".
+    let mut gzip = vec![0x1f, 0x8b, 8, 0];
+    gzip.extend_from_slice(&mtime.to_le_bytes());
+    gzip.extend_from_slice(&[0, 3]);
+    // Each stored block holds at most 65535 bytes, after a byte saying
+    // whether it's the last block and its length (and the length's
+    // complement, as a check).
+    let mut blocks = data.chunks(0xffff).peekable();
+    if blocks.peek().is_none() {
+        gzip.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let len = block.len() as u16;
+        gzip.push(if blocks.peek().is_none() { 1 } else { 0 });
+        gzip.extend_from_slice(&len.to_le_bytes());
+        gzip.extend_from_slice(&(!len).to_le_bytes());
+        gzip.extend_from_slice(block);
+    }
+    gzip.extend_from_slice(&crc32(data).to_le_bytes());
+    gzip.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    gzip
+}
+
+// The CRC-32 that gzip uses (the same one as zip and PNG), a bit at a
+// time, which is plenty fast for a few kilobytes.
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::benchmark::{find_algorithm, run_matrix, Distribution};
+    use crate::cli::parse_matrix_config;
+
+    // Undo `write_tar_gz`: the files in a gzipped tar file of stored
+    // blocks, checking everything along the way.
+    fn read_tar_gz(gzip: &[u8]) -> Vec<(String, String)> {
+        assert_eq!([0x1f, 0x8b, 8, 0], gzip[..4]);
+        let mut tar = Vec::new();
+        let mut pos = 10;
+        loop {
+            let last = gzip[pos] == 1;
+            let len = u16::from_le_bytes([gzip[pos + 1], gzip[pos + 2]]);
+            let check = u16::from_le_bytes([gzip[pos + 3], gzip[pos + 4]]);
+            assert_eq!(!len, check);
+            let len = len as usize;
+            tar.extend_from_slice(&gzip[pos + 5..pos + 5 + len]);
+            pos += 5 + len;
+            if last {
+                break;
+            }
+        }
+        let crc = u32::from_le_bytes([gzip[pos], gzip[pos + 1], gzip[pos + 2], gzip[pos + 3]]);
+        assert_eq!(crc32(&tar), crc);
+        assert_eq!(pos + 8, gzip.len());
+
+        let mut files = Vec::new();
+        let mut pos = 0;
+        while tar[pos..pos + 512].iter().any(|&b| b != 0) {
+            let header = &tar[pos..pos + 512];
+            let field = |range: std::ops::Range<usize>| {
+                let text = String::from_utf8(header[range].to_vec()).unwrap();
+                text.trim_end_matches(['\0', ' ']).to_string()
+            };
+            let mut unsummed = header.to_vec();
+            unsummed[148..156].copy_from_slice(b"        ");
+            let sum: u32 = unsummed.iter().map(|&b| b as u32).sum();
+            assert_eq!(sum, u32::from_str_radix(&field(148..156), 8).unwrap());
+            let size = usize::from_str_radix(&field(124..136), 8).unwrap();
+            let contents = String::from_utf8(tar[pos + 512..pos + 512 + size].to_vec()).unwrap();
+            files.push((field(0..100), contents));
+            pos += 512 + size + padding(size);
+        }
+        assert_eq!(pos + 1024, tar.len());
+        files
+    }
+
+    fn config() -> MatrixConfig {
+        MatrixConfig {
+            algorithms: vec![
+                find_algorithm("quicksort").unwrap(),
+                find_algorithm("merge").unwrap(),
+            ],
+            sizes: vec![10, 100],
+            distributions: vec![Distribution::Uniform, Distribution::Sorted],
+            trials: 2,
+            seed: 277,
+            schedule_seed: Some(9),
+            max_time: Some(Duration::from_millis(1500)),
+            ..MatrixConfig::default()
+        }
+    }
+
+    #[test]
+    fn checksums() {
+        assert_eq!(0xcbf4_3926, crc32(b"123456789"));
+        assert_eq!(0, crc32(b""));
+    }
+
+    #[test]
+    fn round_trip() {
+        let files = vec![
+            ("a/empty.txt".to_string(), String::new()),
+            ("a/big.txt".to_string(), "x".repeat(70_000)),
+            ("a/small.txt".to_string(), "hello\n".to_string()),
+        ];
+        let mut gzip = Vec::new();
+        write_tar_gz(&files, UNIX_EPOCH, &mut gzip).unwrap();
+        assert_eq!(files, read_tar_gz(&gzip));
+
+        let mut gzip = Vec::new();
+        write_tar_gz(&[], UNIX_EPOCH, &mut gzip).unwrap();
+        assert!(read_tar_gz(&gzip).is_empty());
+        assert!(tar_header(&"x".repeat(101), 0, 0).is_err());
+    }
+
+    #[test]
+    fn config_reads_back() {
+        let config = config();
+        let text = config_file(&config);
+        let read = parse_matrix_config(&text).unwrap();
+        assert_eq!(config.cells().len(), read.cells().len());
+        assert_eq!(config.seed, read.seed);
+        assert_eq!(config.schedule_seed, read.schedule_seed);
+        assert_eq!(config.max_time, read.max_time);
+        assert_eq!(config.parallel.threads, read.parallel.threads);
+        assert_eq!(config_file(&read), text);
+        // Same seeds, same inputs.
+        let checksums = |results: Vec<RunResult>| -> Vec<u64> {
+            results.iter().map(|r| r.input_checksum).collect()
+        };
+        let fixed = MatrixConfig {
+            max_time: None,
+            ..config
+        };
+        let read = parse_matrix_config(&config_file(&fixed)).unwrap();
+        assert_eq!(checksums(run_matrix(&fixed)), checksums(run_matrix(&read)));
+    }
+
+    #[test]
+    fn bundle() {
+        let config = MatrixConfig {
+            max_time: None,
+            ..config()
+        };
+        let path =
+            std::env::temp_dir().join(format!("sorting-in-rust-{}-run.tar.gz", std::process::id()));
+        write_bundle(
+            &path,
+            &config,
+            &run_matrix(&config),
+            "sorting-in-rust matrix",
+        )
+        .unwrap();
+        let files = read_tar_gz(&std::fs::read(&path).unwrap());
+        let names: Vec<&str> = files.iter().map(|(name, _)| name.as_str()).collect();
+        let prefix = format!("sorting-in-rust-{}-run/", std::process::id());
+        for (name, expected) in names.iter().zip(&[
+            "README.txt",
+            "config.conf",
+            "environment.txt",
+            "results.json",
+            "report.md",
+        ]) {
+            assert_eq!(format!("{}{}", prefix, expected), *name);
+        }
+        assert!(files[2].1.contains("command: sorting-in-rust matrix\n"));
+        assert_eq!(16, files[3].1.matches("\"verified\": true").count());
+        assert!(files[4].1.contains("rankings:"));
+        assert_eq!("out", bundle_name(Path::new("results/out.tgz")));
+        assert_eq!("bundle", bundle_name(Path::new(".tar.gz")));
+    }
+}
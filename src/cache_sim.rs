@@ -0,0 +1,272 @@
+// A simulated CPU cache, for seeing why some O(N log N) sorts are faster
+// than others. Heapsort jumps from a parent at i to a child near 2i, so
+// on a big heap nearly every step lands somewhere that isn't in the
+// cache yet; merge sort reads both runs front to back, so once one value
+// has been fetched the next few come along in the same cache line for
+// free. Hardware counters could show that, but they need permissions
+// and vary from machine to machine, so instead we simulate a small
+// cache and watch the sort's element accesses go through it.
+//
+// As with `Counted` in `instrument.rs`, we don't change the sorts: we
+// wrap the elements in `Cached`, whose comparisons look up the
+// addresses of the two values being compared in the simulated cache
+// before comparing them. So what we see is every element the sort
+// reads to compare, at the address it actually reads it from (in the
+// slice, or in a merge's scratch space, or a copy on the stack), but not
+// the moves in between, which don't go through any trait we can watch.
+//
+// The cache is set-associative with least-recently-used replacement:
+// memory is split into lines of `line_size` bytes; each line can only go
+// in one set (line number mod the number of sets), and each set holds
+// `associativity` lines, throwing out the one used longest ago to make
+// room for a new one.
+
+use crate::benchmark::sort_generic;
+use std::cell::RefCell;
+use std::cmp::Ordering;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheConfig {
+    // All sizes in bytes.
+    pub capacity: usize,
+    pub line_size: usize,
+    // Lines per set.
+    pub associativity: usize,
+}
+
+// A small L1 cache: 4K in 64-byte lines, 4 lines to a set (so 16 sets).
+// Real L1 caches are more like 32K or 48K, but a small one shows the
+// same effects on inputs small enough to simulate quickly.
+impl Default for CacheConfig {
+    fn default() -> CacheConfig {
+        CacheConfig {
+            capacity: 4096,
+            line_size: 64,
+            associativity: 4,
+        }
+    }
+}
+
+impl CacheConfig {
+    // Check that the sizes fit together.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.capacity == 0 || self.line_size == 0 || self.associativity == 0 {
+            return Err(
+                "the cache's capacity, line size, and associativity must all be at least 1"
+                    .to_string(),
+            );
+        }
+        if !self
+            .capacity
+            .is_multiple_of(self.line_size * self.associativity)
+        {
+            return Err(format!(
+                "a {}-byte cache can't be split into sets of {} lines of {} bytes",
+                self.capacity, self.associativity, self.line_size
+            ));
+        }
+        Ok(())
+    }
+
+    fn sets(&self) -> usize {
+        self.capacity / (self.line_size * self.associativity)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl CacheStats {
+    pub fn accesses(&self) -> u64 {
+        self.hits + self.misses
+    }
+
+    // The fraction of accesses that missed, from 0 to 1.
+    pub fn miss_rate(&self) -> f64 {
+        if self.accesses() == 0 {
+            0.0
+        } else {
+            self.misses as f64 / self.accesses() as f64
+        }
+    }
+}
+
+struct Cache {
+    config: CacheConfig,
+    // The lines in each set, most recently used last.
+    sets: Vec<Vec<usize>>,
+    stats: CacheStats,
+}
+
+impl Cache {
+    fn new(config: CacheConfig) -> Cache {
+        Cache {
+            config,
+            sets: vec![Vec::with_capacity(config.associativity); config.sets()],
+            stats: CacheStats::default(),
+        }
+    }
+
+    fn access(&mut self, address: usize) {
+        let line = address / self.config.line_size;
+        let set = &mut self.sets[line % self.config.sets()];
+        match set.iter().position(|&cached| cached == line) {
+            Some(i) => {
+                self.stats.hits += 1;
+                let line = set.remove(i);
+                set.push(line);
+            }
+            None => {
+                self.stats.misses += 1;
+                if set.len() == self.config.associativity {
+                    set.remove(0);
+                }
+                set.push(line);
+            }
+        }
+    }
+}
+
+thread_local! {
+    // Only `Some` inside `simulate_cache`.
+    static CACHE: RefCell<Option<Cache>> = const { RefCell::new(None) };
+}
+
+fn access<T>(value: &T) {
+    let address = value as *const T as usize;
+    CACHE.with(|cache| {
+        if let Some(cache) = cache.borrow_mut().as_mut() {
+            cache.access(address);
+        }
+    });
+}
+
+// Run `f` with an empty simulated cache, and return its result along
+// with how the accesses of `Cached` values it made went.
+pub fn simulate_cache<R, F: FnOnce() -> R>(config: CacheConfig, f: F) -> (R, CacheStats) {
+    let before = CACHE.with(|cache| cache.replace(Some(Cache::new(config))));
+    let result = f();
+    let cache = CACHE.with(|cache| cache.replace(before));
+    (
+        result,
+        cache.map_or_else(CacheStats::default, |cache| cache.stats),
+    )
+}
+
+// A value whose comparisons go through the simulated cache.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Cached<T>(pub T);
+
+// Wrap every element of a slice.
+pub fn cached<T: Clone>(v: &[T]) -> Vec<Cached<T>> {
+    v.iter().cloned().map(Cached).collect()
+}
+
+impl<T: PartialEq> PartialEq for Cached<T> {
+    fn eq(&self, other: &Self) -> bool {
+        access(self);
+        access(other);
+        self.0 == other.0
+    }
+}
+
+impl<T: PartialOrd> PartialOrd for Cached<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        access(self);
+        access(other);
+        self.0.partial_cmp(&other.0)
+    }
+}
+
+// Sort a copy of `input` with the named algorithm through the simulated
+// cache. Like `sort_generic`, this works for everything but
+// parallel-merge.
+pub fn cache_profile(
+    algorithm: &str,
+    input: &[i32],
+    config: CacheConfig,
+) -> Result<CacheStats, String> {
+    config.validate()?;
+    let mut v = cached(input);
+    let (result, stats) = simulate_cache(config, || sort_generic(algorithm, &mut v));
+    result.map(|()| stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::generate_random_array;
+    use crate::random::SeededRandom;
+
+    #[test]
+    fn sequential_reads_mostly_hit() {
+        // 16 four-byte values to a 64-byte line: after the first read of
+        // each line, the next 15 are hits.
+        let v = cached(&(0..1024).collect::<Vec<i32>>());
+        let ((), stats) = simulate_cache(CacheConfig::default(), || {
+            for pair in v.windows(2) {
+                assert!(pair[0] < pair[1]);
+            }
+        });
+        assert_eq!(2 * 1023, stats.accesses());
+        assert!(stats.misses <= 1024 / 16 + 1, "{:?}", stats);
+    }
+
+    #[test]
+    fn lru_eviction() {
+        // One set of two lines. Lines 0 and 1 fit; reading 0 again makes
+        // 1 the least recently used, so line 2 evicts it, and then 1 and
+        // 0 each miss in turn.
+        let config = CacheConfig {
+            capacity: 128,
+            line_size: 64,
+            associativity: 2,
+        };
+        let mut cache = Cache::new(config);
+        for &address in &[0, 64, 0, 128, 64, 0] {
+            cache.access(address);
+        }
+        assert_eq!(CacheStats { hits: 1, misses: 5 }, cache.stats);
+    }
+
+    #[test]
+    fn heapsort_has_worse_locality_than_merge_sort() {
+        let input = generate_random_array(20_000, 0, 1_000_000, &mut SeededRandom::new(285));
+        let config = CacheConfig::default();
+        let heap = cache_profile("heapsort", &input, config).unwrap();
+        let merge = cache_profile("merge", &input, config).unwrap();
+        assert!(
+            heap.miss_rate() > 2.0 * merge.miss_rate(),
+            "heapsort {:?}, merge {:?}",
+            heap,
+            merge
+        );
+    }
+
+    #[test]
+    fn bad_configs() {
+        let config = |capacity, line_size, associativity| CacheConfig {
+            capacity,
+            line_size,
+            associativity,
+        };
+        assert!(config(4096, 64, 4).validate().is_ok());
+        assert!(config(4096, 64, 3).validate().is_err());
+        assert!(config(0, 64, 4).validate().is_err());
+        assert!(cache_profile("heapsort", &[1, 2], config(100, 64, 1)).is_err());
+        assert!(cache_profile("parallel-merge", &[1, 2], CacheConfig::default()).is_err());
+    }
+
+    #[test]
+    fn nothing_outside_simulate_cache() {
+        let v = cached(&[1, 2]);
+        assert!(v[0] < v[1]);
+        assert_eq!(
+            CacheStats::default(),
+            simulate_cache(CacheConfig::default(), || ()).1
+        );
+    }
+}
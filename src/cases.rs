@@ -0,0 +1,169 @@
+// The inputs that show off each algorithm at its best and its worst.
+// Timing everything on uniform random input only tells you about the
+// average case, but the reason to know more than one sort is that they
+// differ elsewhere: insertion sort is the fastest thing there is on
+// sorted input and hopeless on reversed input, while the lab's
+// `quicksort` is exactly the other way around.
+//
+// `cases_for` gives you an algorithm's best-case, average-case, and
+// worst-case inputs of a given size, and `matrix --cases auto` times
+// every algorithm on its own. Each choice below was checked by counting
+// comparisons (see the tests), which for these sorts is what the time
+// follows.
+
+use crate::benchmark::{Algorithm, Distribution};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Case {
+    Best,
+    Average,
+    Worst,
+}
+
+impl Case {
+    pub fn name(self) -> &'static str {
+        match self {
+            Case::Best => "best",
+            Case::Average => "average",
+            Case::Worst => "worst",
+        }
+    }
+}
+
+// An input, along with what it's for, e.g. "quicksort worst (sorted)".
+#[derive(Debug, Clone, PartialEq)]
+pub struct NamedDataset {
+    pub name: String,
+    pub case: Case,
+    pub distribution: Distribution,
+    pub data: Vec<i32>,
+}
+
+// Which distribution gives each case of `algorithm`, best first. Some
+// algorithms don't have every case: bitonic sort makes the same
+// comparisons whatever the input, so it only has an average case, and
+// randomized quicksort has no best case (no input makes it lucky).
+pub fn case_distributions(algorithm: &Algorithm) -> Vec<(Case, Distribution)> {
+    use Distribution::*;
+    let (best, worst) = match algorithm.name {
+        // Sorted input needs one comparison per element; reversed input
+        // has to move every element all the way along.
+        "insertion" => (Some(Sorted), Some(Reversed)),
+        // The first element is always the pivot, so we want it to be
+        // the median every time, and sorted input makes it the minimum
+        // every time.
+        "quicksort" => (Some(MedianFirst), Some(Sorted)),
+        // Everything equal to the pivot goes on the same side, so with
+        // all-equal input a random pivot is no help at all.
+        "randomized-quicksort" => (None, Some(AllEqual)),
+        // Median-of-three picks the exact median of sorted input every
+        // time. All-equal input is quicksort's worst case, so introsort
+        // falls back to heapsort.
+        "introsort" => (Some(Sorted), Some(AllEqual)),
+        // With all-equal input the two pivots are equal, so the middle
+        // part needs no sorting and one pass does it all. No simple
+        // input is known to be its worst case.
+        "dual-pivot-quicksort" => (Some(AllEqual), None),
+        // All-equal input is a single partition with nothing left over.
+        "three-way-quicksort" => (Some(AllEqual), None),
+        // Sorted halves don't need merging at all, while merge-worst
+        // makes every merge compare right up to its last element.
+        "merge" | "parallel-merge" => (Some(Sorted), Some(MergeWorst)),
+        // Sifting stops as soon as a parent is no smaller than its
+        // children, which with all-equal input is straight away.
+        // Sorted input builds a heap with the small values on top,
+        // which is the furthest from what heapsort wants.
+        "heapsort" => (Some(AllEqual), Some(Sorted)),
+        // Weak heapsort always makes close to n log₂ n comparisons, but
+        // sorted input needs the fewest and all-equal input the most.
+        "weak-heapsort" => (Some(Sorted), Some(AllEqual)),
+        // Every pass over sorted input stops each element after one
+        // comparison. Nobody knows what the worst input for Ciura's gaps
+        // is.
+        "shell" => (Some(Sorted), None),
+        _ => (None, None),
+    };
+    let mut cases = Vec::new();
+    if let Some(best) = best {
+        cases.push((Case::Best, best));
+    }
+    cases.push((Case::Average, Uniform));
+    if let Some(worst) = worst {
+        cases.push((Case::Worst, worst));
+    }
+    cases
+}
+
+// `algorithm`'s cases as inputs of `size` elements (the random ones
+// generated from `seed`).
+pub fn cases_for(algorithm: &Algorithm, size: usize, seed: u64) -> Vec<NamedDataset> {
+    case_distributions(algorithm)
+        .into_iter()
+        .map(|(case, distribution)| NamedDataset {
+            name: format!(
+                "{} {} ({})",
+                algorithm.name,
+                case.name(),
+                distribution.name()
+            ),
+            case,
+            distribution,
+            data: distribution.generate(size, seed),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::benchmark::{algorithms, find_algorithm, sort_generic};
+    use crate::instrument::{count_comparisons, counted};
+
+    fn comparisons(algorithm: &str, v: &[i32]) -> u64 {
+        let mut v = counted(v);
+        count_comparisons(|| sort_generic(algorithm, &mut v).unwrap()).1
+    }
+
+    #[test]
+    fn every_algorithm_has_an_average_case() {
+        for algorithm in algorithms() {
+            let cases = cases_for(&algorithm, 100, 263);
+            assert!(
+                cases.iter().any(|c| c.case == Case::Average),
+                "{}",
+                algorithm.name
+            );
+            for dataset in cases {
+                assert_eq!(100, dataset.data.len());
+                assert!(dataset.name.starts_with(algorithm.name));
+            }
+        }
+    }
+
+    #[test]
+    fn cases_are_in_order() {
+        // parallel-merge's comparisons happen on other threads, where we
+        // can't count them, but it has the same cases as merge.
+        for algorithm in algorithms().iter().filter(|a| a.name != "parallel-merge") {
+            let counts: Vec<(Case, u64)> = cases_for(algorithm, 1000, 263)
+                .iter()
+                .map(|dataset| (dataset.case, comparisons(algorithm.name, &dataset.data)))
+                .collect();
+            for pair in counts.windows(2) {
+                assert!(pair[0].1 <= pair[1].1, "{}: {:?}", algorithm.name, counts);
+            }
+        }
+    }
+
+    #[test]
+    fn quicksort_best_and_worst() {
+        let quicksort = find_algorithm("quicksort").unwrap();
+        let cases = cases_for(&quicksort, 1023, 263);
+        let best = comparisons("quicksort", &cases[0].data);
+        let worst = comparisons("quicksort", &cases[2].data);
+        // Perfect splits all the way down: each of the 9 levels below
+        // the top compares every element that isn't yet a pivot.
+        assert!(best <= 1023 * 9, "{}", best);
+        assert_eq!(1023 * 1022 / 2, worst);
+    }
+}
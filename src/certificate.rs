@@ -0,0 +1,370 @@
+// Certificates that a sort did its job, which can be checked without
+// trusting (or even having) the code that did the sorting. A
+// certificate records the input, the output, and the permutation the
+// sort applied (output[k] came from input[permutation[k]]), so checking
+// it is easy: the permutation must use every position exactly once,
+// each output value must be the input value it claims to be, and the
+// output must be in order. That's O(N), far less work than sorting, and
+// it's all `verify` needs. It's meant for grading, and for results that
+// come back from somewhere you don't control, like a pool of worker
+// threads or another machine.
+//
+// A certificate also carries a hash chain of every comparison the sort
+// made: which two input positions it compared and what it found, folded
+// into a running FNV-1a hash. The chain doesn't help show the output is
+// right, but re-running the sort and getting the same chain (`replay`)
+// shows it's the same sort making the same decisions. parallel-merge
+// compares on other threads, in no fixed order, so its certificates
+// have no chain; randomized quicksort's chain changes from run to run.
+//
+// Saved as JSON, e.g.
+//    {"algorithm": "insertion", "input": [1, 0], "output": [0, 1],
+//     "permutation": [1, 0], "comparisons": 1,
+//     "compare_chain": "51cef117f81654e5"}
+// (but all on one line).
+
+use crate::benchmark::{find_algorithm, sort_generic};
+use crate::json::{self, quote, Json};
+use crate::mutants;
+use crate::sorts::{parallel_merge_sort, ParallelConfig};
+use std::cell::Cell;
+use std::cmp::Ordering;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Certificate {
+    pub algorithm: String,
+    pub input: Vec<i32>,
+    pub output: Vec<i32>,
+    pub permutation: Vec<usize>,
+    // How many comparisons went into the chain, and the chain itself,
+    // or `None` for parallel-merge.
+    pub comparisons: Option<u64>,
+    pub compare_chain: Option<u64>,
+}
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+thread_local! {
+    // The comparisons so far and the chain, while `certify` is running.
+    static CHAIN: Cell<Option<(u64, u64)>> = const { Cell::new(None) };
+}
+
+// An input value that remembers where it started, and adds every
+// comparison it's part of to the chain.
+#[derive(Debug, Clone, Copy)]
+struct Witnessed {
+    value: i32,
+    index: usize,
+}
+
+impl PartialEq for Witnessed {
+    fn eq(&self, other: &Self) -> bool {
+        self.partial_cmp(other) == Some(Ordering::Equal)
+    }
+}
+
+impl PartialOrd for Witnessed {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        let ordering = self.value.cmp(&other.value);
+        CHAIN.with(|chain| {
+            if let Some((count, hash)) = chain.get() {
+                let link = [self.index as u64, other.index as u64, ordering as i8 as u64];
+                chain.set(Some((count + 1, extend_chain(hash, &link))));
+            }
+        });
+        Some(ordering)
+    }
+}
+
+fn extend_chain(mut hash: u64, words: &[u64]) -> u64 {
+    for word in words {
+        for byte in word.to_le_bytes().iter() {
+            hash ^= *byte as u64;
+            hash = hash.wrapping_mul(FNV_PRIME);
+        }
+    }
+    hash
+}
+
+// Sort a copy of `input` with the named algorithm and certify the
+// result. Like `trace`, quicksort and merge honor `mutants::with_bugs`,
+// so a buggy sort gets a certificate that fails to verify.
+pub fn certify(algorithm: &str, input: &[i32]) -> Result<Certificate, String> {
+    let algorithm = find_algorithm(algorithm)
+        .ok_or_else(|| format!("unknown algorithm `{}`", algorithm))?
+        .name;
+    let mut v: Vec<Witnessed> = input
+        .iter()
+        .enumerate()
+        .map(|(index, &value)| Witnessed { value, index })
+        .collect();
+    let mut chain = None;
+    if algorithm == "parallel-merge" {
+        v = parallel_merge_sort(&v, &ParallelConfig::from_env().unwrap_or_default());
+    } else {
+        let before = CHAIN.with(|c| c.replace(Some((0, FNV_OFFSET))));
+        let sorted = match algorithm {
+            "quicksort" => {
+                mutants::quicksort(&mut v);
+                Ok(())
+            }
+            "merge" => {
+                v = mutants::merge_sort(&v);
+                Ok(())
+            }
+            _ => sort_generic(algorithm, &mut v),
+        };
+        chain = CHAIN.with(|c| c.replace(before));
+        sorted?;
+    }
+    Ok(Certificate {
+        algorithm: algorithm.to_string(),
+        input: input.to_vec(),
+        output: v.iter().map(|w| w.value).collect(),
+        permutation: v.iter().map(|w| w.index).collect(),
+        comparisons: chain.map(|(count, _)| count),
+        compare_chain: chain.map(|(_, hash)| hash),
+    })
+}
+
+// Check that the output is in order and is the claimed permutation of
+// the input. This only looks at the certificate; nothing is sorted.
+pub fn verify(certificate: &Certificate) -> Result<(), String> {
+    let Certificate {
+        input,
+        output,
+        permutation,
+        ..
+    } = certificate;
+    if output.len() != input.len() || permutation.len() != input.len() {
+        return Err(format!(
+            "the input has {} values, but the output has {} and the permutation {}",
+            input.len(),
+            output.len(),
+            permutation.len()
+        ));
+    }
+    let mut used = vec![false; input.len()];
+    for (k, &from) in permutation.iter().enumerate() {
+        if from >= input.len() {
+            return Err(format!(
+                "permutation[{}] = {} is past the end of the input",
+                k, from
+            ));
+        }
+        if used[from] {
+            return Err(format!("input[{}] appears in the output twice", from));
+        }
+        used[from] = true;
+        if output[k] != input[from] {
+            return Err(format!(
+                "output[{}] is {}, but it's meant to be input[{}], which is {}",
+                k, output[k], from, input[from]
+            ));
+        }
+    }
+    if let Some(k) = output.windows(2).position(|w| w[0] > w[1]) {
+        return Err(format!(
+            "the output is out of order at {}: {} then {}",
+            k,
+            output[k],
+            output[k + 1]
+        ));
+    }
+    Ok(())
+}
+
+// Sort the input again and check that the sort makes exactly the same
+// comparisons, with the same results, and ends up in the same place.
+pub fn replay(certificate: &Certificate) -> Result<(), String> {
+    if certificate.compare_chain.is_none() {
+        return Err(format!(
+            "{} certificates have no comparisons to replay",
+            certificate.algorithm
+        ));
+    }
+    let again = certify(&certificate.algorithm, &certificate.input)?;
+    if again.compare_chain != certificate.compare_chain
+        || again.comparisons != certificate.comparisons
+    {
+        return Err(format!(
+            "{} made different comparisons this time ({} rather than {}, chain {} rather than {})",
+            certificate.algorithm,
+            again.comparisons.unwrap_or(0),
+            certificate.comparisons.unwrap_or(0),
+            format_chain(again.compare_chain),
+            format_chain(certificate.compare_chain)
+        ));
+    }
+    if again.permutation != certificate.permutation {
+        return Err(format!(
+            "{} put the values in a different order this time",
+            certificate.algorithm
+        ));
+    }
+    Ok(())
+}
+
+fn format_chain(chain: Option<u64>) -> String {
+    chain.map_or("none".to_string(), |hash| format!("{:016x}", hash))
+}
+
+impl Certificate {
+    pub fn to_json(&self) -> String {
+        let list = |items: Vec<String>| items.join(", ");
+        format!(
+            "{{\"algorithm\": {}, \"input\": [{}], \"output\": [{}], \"permutation\": [{}], \
+             \"comparisons\": {}, \"compare_chain\": {}}}\n",
+            quote(&self.algorithm),
+            list(self.input.iter().map(|x| x.to_string()).collect()),
+            list(self.output.iter().map(|x| x.to_string()).collect()),
+            list(self.permutation.iter().map(|k| k.to_string()).collect()),
+            self.comparisons
+                .map_or("null".to_string(), |count| count.to_string()),
+            self.compare_chain
+                .map_or("null".to_string(), |hash| format!("\"{:016x}\"", hash))
+        )
+    }
+
+    pub fn from_json(text: &str) -> Result<Certificate, String> {
+        let json = json::parse(text)?;
+        let algorithm = json
+            .get("algorithm")
+            .and_then(Json::as_str)
+            .ok_or("certificate needs an `algorithm` string")?
+            .to_string();
+        let compare_chain = match json.get("compare_chain") {
+            None | Some(Json::Null) => None,
+            Some(chain) => Some(
+                chain
+                    .as_str()
+                    .and_then(|hex| u64::from_str_radix(hex, 16).ok())
+                    .ok_or("`compare_chain` should be a hex string")?,
+            ),
+        };
+        let comparisons = match json.get("comparisons") {
+            None | Some(Json::Null) => None,
+            count => Some(int(count, "comparisons")?),
+        };
+        Ok(Certificate {
+            algorithm,
+            input: ints(&json, "input")?,
+            output: ints(&json, "output")?,
+            permutation: ints(&json, "permutation")?,
+            comparisons,
+            compare_chain,
+        })
+    }
+}
+
+fn int<T: std::convert::TryFrom<i64>>(value: Option<&Json>, name: &str) -> Result<T, String> {
+    value
+        .and_then(Json::as_i64)
+        .and_then(|n| T::try_from(n).ok())
+        .ok_or_else(|| format!("`{}` should be a whole number in range", name))
+}
+
+fn ints<T: std::convert::TryFrom<i64>>(json: &Json, name: &str) -> Result<Vec<T>, String> {
+    json.get(name)
+        .and_then(Json::as_array)
+        .ok_or_else(|| format!("certificate needs an `{}` array", name))?
+        .iter()
+        .map(|x| int(Some(x), name))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::benchmark::algorithms;
+    use crate::mutants::{with_bugs, Bug};
+
+    const INPUT: [i32; 10] = [3, 2, 0, 5, 8, 9, 6, 3, 2, 0];
+
+    #[test]
+    fn every_algorithm_verifies() {
+        for algorithm in algorithms() {
+            let certificate = certify(algorithm.name, &INPUT).unwrap();
+            assert_eq!(Ok(()), verify(&certificate), "{}", algorithm.name);
+            assert_eq!(vec![0, 0, 2, 2, 3, 3, 5, 6, 8, 9], certificate.output);
+            assert_eq!(
+                algorithm.name == "parallel-merge",
+                certificate.compare_chain.is_none()
+            );
+        }
+        assert!(certify("bogosort", &INPUT).is_err());
+    }
+
+    #[test]
+    fn stable_sorts_keep_equal_values_in_order() {
+        let certificate = certify("merge", &INPUT).unwrap();
+        // The two 0s are at 2 and 9, the 2s at 1 and 8, and so on.
+        assert_eq!(vec![2, 9, 1, 8, 0, 7, 3, 6, 4, 5], certificate.permutation);
+    }
+
+    #[test]
+    fn tampering_is_caught() {
+        let good = certify("heapsort", &INPUT).unwrap();
+
+        let mut swapped = good.clone();
+        swapped.output.swap(0, 9);
+        swapped.permutation.swap(0, 9);
+        assert!(verify(&swapped).unwrap_err().contains("out of order"));
+
+        let mut changed = good.clone();
+        changed.output[9] = 10;
+        assert!(verify(&changed).unwrap_err().starts_with("output[9] is 10"));
+
+        let mut repeated = good.clone();
+        repeated.permutation[1] = repeated.permutation[0];
+        assert!(verify(&repeated).unwrap_err().contains("twice"));
+
+        let mut short = good;
+        short.output.pop();
+        assert!(verify(&short).is_err());
+    }
+
+    #[test]
+    fn buggy_sorts_fail() {
+        let certificate = with_bugs(&[Bug::MissingMergeTail], || certify("merge", &INPUT)).unwrap();
+        assert!(verify(&certificate).is_err());
+        let certificate =
+            with_bugs(&[Bug::OffByOnePartition], || certify("quicksort", &INPUT)).unwrap();
+        assert!(verify(&certificate).is_err());
+    }
+
+    #[test]
+    fn replays() {
+        let certificate = certify("quicksort", &INPUT).unwrap();
+        assert_eq!(Ok(()), replay(&certificate));
+        let mut forged = certificate.clone();
+        forged.compare_chain = Some(forged.compare_chain.unwrap() ^ 1);
+        assert!(replay(&forged)
+            .unwrap_err()
+            .contains("different comparisons"));
+        // A correct sort, but not the one the certificate claims.
+        let mut relabeled = certify("insertion", &INPUT).unwrap();
+        relabeled.algorithm = "heapsort".to_string();
+        assert_eq!(Ok(()), verify(&relabeled));
+        assert!(replay(&relabeled).is_err());
+        assert!(replay(&certify("parallel-merge", &INPUT).unwrap()).is_err());
+    }
+
+    #[test]
+    fn json() {
+        let certificate = certify("insertion", &[2, 1]).unwrap();
+        let json = certificate.to_json();
+        assert!(json.starts_with(
+            "{\"algorithm\": \"insertion\", \"input\": [2, 1], \"output\": [1, 2], \
+             \"permutation\": [1, 0], \"comparisons\": 1, \"compare_chain\": \""
+        ));
+        assert_eq!(certificate, Certificate::from_json(&json).unwrap());
+        let parallel = certify("parallel-merge", &INPUT).unwrap();
+        assert_eq!(
+            parallel,
+            Certificate::from_json(&parallel.to_json()).unwrap()
+        );
+        assert!(Certificate::from_json("{\"algorithm\": \"merge\"}").is_err());
+    }
+}
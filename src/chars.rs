@@ -0,0 +1,186 @@
+// Sorting the characters of a string, e.g. "listen" → "eilnst". Two
+// words are anagrams exactly when their sorted letters are the same,
+// which makes this the usual first step in anagram puzzles.
+//
+// A Rust `char` is one Unicode code point, which isn't always what a
+// reader would call one character: "é" can be written as "e" followed
+// by a combining acute accent, and a family emoji is several people
+// joined by zero-width joiners. Sorting the code points pulls those
+// apart (the accent ends up on some other letter, or on nothing), so
+// `sort_graphemes` keeps each grapheme cluster together and sorts the
+// clusters.
+//
+// Finding the clusters properly (by the rules in Unicode's UAX #29)
+// takes tables for all of Unicode, so `graphemes` and `sort_graphemes`
+// use the `unicode-segmentation` crate, and need the feature of the
+// same name. Without it there's `approximate_graphemes`, which only
+// knows the commonest cases.
+
+#[cfg(feature = "unicode-segmentation")]
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::sorts::{merge_sort, merge_sort_collect};
+
+// The characters (code points) of `s` in order.
+pub fn sort_chars(s: &str) -> String {
+    let chars: Vec<char> = s.chars().collect();
+    merge_sort_collect(&chars)
+}
+
+// The grapheme clusters of `s` in order, each one compared as the
+// string it is.
+#[cfg(feature = "unicode-segmentation")]
+pub fn sort_graphemes(s: &str) -> String {
+    merge_sort(&graphemes(s)).concat()
+}
+
+// The (extended) grapheme clusters of `s`.
+#[cfg(feature = "unicode-segmentation")]
+pub fn graphemes(s: &str) -> Vec<&str> {
+    s.graphemes(true).collect()
+}
+
+// `sort_graphemes`, with the clusters from `approximate_graphemes`.
+pub fn sort_approximate_graphemes(s: &str) -> String {
+    merge_sort(&approximate_graphemes(s)).concat()
+}
+
+// Split `s` into something close to its grapheme clusters, without any
+// Unicode tables. The clusters that turn up most in ordinary text are a
+// character followed by combining marks, variation selectors, or emoji
+// skin tones, or several of those joined with zero-width joiners, and
+// that's all this looks for. Everything else is split apart, one code
+// point to a cluster, including flags (pairs of regional indicators),
+// Hangul syllables written as separate jamo, "\r\n", and the vowel
+// signs of Indic scripts (the "ि" in "कि"). Use `graphemes` to get those
+// right.
+pub fn approximate_graphemes(s: &str) -> Vec<&str> {
+    let mut clusters = Vec::new();
+    let mut start = 0;
+    let mut joining = false;
+    for (i, c) in s.char_indices() {
+        if i > start && !joining && !extends(c) {
+            clusters.push(&s[start..i]);
+            start = i;
+        }
+        joining = c == ZERO_WIDTH_JOINER;
+    }
+    if start < s.len() {
+        clusters.push(&s[start..]);
+    }
+    clusters
+}
+
+const ZERO_WIDTH_JOINER: char = '\u{200d}';
+
+// Whether `c` belongs to the cluster before it.
+fn extends(c: char) -> bool {
+    matches!(c,
+        // Combining diacritical marks, and their supplement and extended
+        // blocks, and the ones for symbols and half marks.
+        '\u{300}'..='\u{36f}'
+        | '\u{1ab0}'..='\u{1aff}'
+        | '\u{1dc0}'..='\u{1dff}'
+        | '\u{20d0}'..='\u{20ff}'
+        | '\u{fe20}'..='\u{fe2f}'
+        // Variation selectors, e.g. the one that asks for an emoji to be
+        // drawn in color.
+        | '\u{fe00}'..='\u{fe0f}'
+        // Emoji skin tones.
+        | '\u{1f3fb}'..='\u{1f3ff}'
+        | ZERO_WIDTH_JOINER)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chars() {
+        assert_eq!("eilnst", sort_chars("listen"));
+        assert_eq!(sort_chars("listen"), sort_chars("silent"));
+        assert_eq!("", sort_chars(""));
+        // Code point order, so capitals first.
+        assert_eq!("ABab", sort_chars("baBA"));
+        assert_eq!("aeßö", sort_chars("ößae"));
+    }
+
+    #[test]
+    fn clusters() {
+        // "e" and a combining acute accent, then a plain "a".
+        assert_eq!(vec!["e\u{301}", "a"], approximate_graphemes("e\u{301}a"));
+        // Woman, zero-width joiner, laptop: one emoji.
+        let technologist = "\u{1f469}\u{200d}\u{1f4bb}";
+        assert_eq!(
+            vec![technologist, "!"],
+            approximate_graphemes(&format!("{}!", technologist))
+        );
+        // Thumbs up with a skin tone.
+        assert_eq!(1, approximate_graphemes("\u{1f44d}\u{1f3fd}").len());
+        assert!(approximate_graphemes("").is_empty());
+    }
+
+    #[test]
+    fn graphemes_stay_together() {
+        let word = "cafe\u{301}s";
+        // By code point the accent goes to the end, after the "s", and
+        // ends up on nothing.
+        assert_eq!("acefs\u{301}", sort_chars(word));
+        assert_eq!("ace\u{301}fs", sort_approximate_graphemes(word));
+        assert_eq!("abc", sort_approximate_graphemes("cab"));
+    }
+
+    // The cases the approximation gets wrong, and `graphemes` gets right.
+    // Flags are pairs of regional indicators ("🇳🇴" is N and O, "🇫🇷"
+    // is F and R); "한" is written here as its three jamo; and "कि" is
+    // "क" with a vowel sign that's a spacing mark, not a combining one.
+    const FLAGS: &str = "\u{1f1f3}\u{1f1f4}\u{1f1eb}\u{1f1f7}";
+    const HANGUL: &str = "\u{1112}\u{1161}\u{11ab}";
+    const CRLF: &str = "b\r\na";
+    const DEVANAGARI: &str = "\u{915}\u{93f}";
+
+    #[test]
+    fn approximate_clusters_split_apart() {
+        assert_eq!(4, approximate_graphemes(FLAGS).len());
+        assert_eq!(3, approximate_graphemes(HANGUL).len());
+        assert_eq!(vec!["b", "\r", "\n", "a"], approximate_graphemes(CRLF));
+        assert_eq!(2, approximate_graphemes(DEVANAGARI).len());
+    }
+
+    #[cfg(feature = "unicode-segmentation")]
+    #[test]
+    fn full_clusters() {
+        assert_eq!(
+            vec!["\u{1f1f3}\u{1f1f4}", "\u{1f1eb}\u{1f1f7}"],
+            graphemes(FLAGS)
+        );
+        assert_eq!(vec![HANGUL], graphemes(HANGUL));
+        assert_eq!(vec!["b", "\r\n", "a"], graphemes(CRLF));
+        assert_eq!(vec![DEVANAGARI], graphemes(DEVANAGARI));
+        // Everything the approximation gets right, this does too.
+        for s in &[
+            "e\u{301}a",
+            "\u{1f469}\u{200d}\u{1f4bb}!",
+            "\u{1f44d}\u{1f3fd}",
+            "",
+        ] {
+            assert_eq!(approximate_graphemes(s), graphemes(s));
+        }
+    }
+
+    #[cfg(feature = "unicode-segmentation")]
+    #[test]
+    fn sorting_full_clusters() {
+        // France before Norway, each flag kept whole.
+        assert_eq!(
+            "\u{1f1eb}\u{1f1f7}\u{1f1f3}\u{1f1f4}",
+            sort_graphemes(FLAGS)
+        );
+        assert_eq!("\r\nab", sort_graphemes(CRLF));
+        assert_eq!(
+            format!("a{}", DEVANAGARI),
+            sort_graphemes(&format!("{}a", DEVANAGARI))
+        );
+        assert_eq!("ace\u{301}fs", sort_graphemes("cafe\u{301}s"));
+    }
+}
@@ -0,0 +1,317 @@
+// Sorting types that are only `PartialOrd` can silently go wrong. The
+// classic example is floating point numbers: NaN isn't less than,
+// greater than, or equal to anything (including itself), so every `<` or
+// `>` involving a NaN is false. The sorts will happily finish anyway,
+// but the result may not be in order (even ignoring the NaNs).
+//
+// The `try_` sorts here notice when the sort compares two elements that
+// can't be compared (i.e., `partial_cmp` returns `None`) and return an
+// `OrderViolation` saying which elements they were, leaving the input
+// untouched. They do this by sorting `Checked` wrappers (which refer to
+// the elements of v and remember their original indices) instead of the
+// elements themselves, and then only rearranging v if all went well.
+//
+// Giving up isn't the only option, though. `sort_with_policy` takes an
+// `IncomparablePolicy` that says what to do instead, and because it
+// works through the same wrappers it behaves the same way whichever
+// algorithm does the sorting.
+
+use crate::error::OrderViolation;
+use crate::sorts::heapsort;
+use std::cell::Cell;
+use std::cmp::Ordering;
+use std::fmt;
+
+// What to do when the sort compares two elements that can't be
+// compared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IncomparablePolicy {
+    // Stop and report the pair (the `try_` sorts do this).
+    Abort,
+    // Pretend they're equal. The sort always finishes, but "equal" isn't
+    // transitive any more (a NaN is "equal" to both 1 and 2), so the
+    // rest of the result may not be in order either.
+    TreatAsEqual,
+    // Take the elements that can't even be compared with themselves
+    // (like NaN) out before sorting, and put them at the end in their
+    // original order. Any other incomparable pair is still an error,
+    // since there's no telling which of the two is the odd one out.
+    MoveToEnd,
+}
+
+// A reference to an element of the slice being sorted, along with its
+// original index. Comparing two `Checked` values that turn out to be
+// incomparable records an `OrderViolation`, unless `lenient` says to
+// call them equal instead.
+pub struct Checked<'a, T> {
+    item: &'a T,
+    index: usize,
+    violation: &'a Cell<Option<OrderViolation>>,
+    lenient: bool,
+}
+
+// Sort v with `heapsort`, or report the first pair of elements that
+// couldn't be compared.
+pub fn try_sort<T: PartialOrd + fmt::Debug>(v: &mut [T]) -> Result<(), OrderViolation> {
+    try_sort_with(v, |checked| heapsort(checked))
+}
+
+// Sort v with whatever in-place sorting algorithm you like, e.g.,
+//    try_sort_with(&mut v, |checked| insertion_sort(checked))
+// or report the first pair of elements that couldn't be compared. If
+// there's an error v is left unchanged.
+pub fn try_sort_with<T, F>(v: &mut [T], sort: F) -> Result<(), OrderViolation>
+where
+    T: PartialOrd,
+    F: for<'a> FnOnce(&mut [Checked<'a, T>]),
+{
+    sort_with_policy(v, IncomparablePolicy::Abort, sort)
+}
+
+// Sort v with `sort`, as in `try_sort_with`, handling incomparable
+// elements as `policy` says. Only `Abort` and `MoveToEnd` can fail, and
+// if they do v is left unchanged.
+pub fn sort_with_policy<T, F>(
+    v: &mut [T],
+    policy: IncomparablePolicy,
+    sort: F,
+) -> Result<(), OrderViolation>
+where
+    T: PartialOrd,
+    F: for<'a> FnOnce(&mut [Checked<'a, T>]),
+{
+    let (sortable, mut unorderable): (Vec<usize>, Vec<usize>) = match policy {
+        IncomparablePolicy::MoveToEnd => {
+            (0..v.len()).partition(|&i| v[i].partial_cmp(&v[i]).is_some())
+        }
+        _ => ((0..v.len()).collect(), Vec::new()),
+    };
+    let mut order = sort_indices(
+        v,
+        sortable,
+        policy == IncomparablePolicy::TreatAsEqual,
+        sort,
+    )?;
+    order.append(&mut unorderable);
+    apply_permutation(v, &mut order);
+    Ok(())
+}
+
+// Sort the elements of v at `indices` with `sort`, returning their
+// indices in sorted order: the element that belongs at position k
+// started at position `order[k]`.
+fn sort_indices<T, F>(
+    v: &[T],
+    indices: Vec<usize>,
+    lenient: bool,
+    sort: F,
+) -> Result<Vec<usize>, OrderViolation>
+where
+    T: PartialOrd,
+    F: for<'a> FnOnce(&mut [Checked<'a, T>]),
+{
+    let violation = Cell::new(None);
+    let mut checked: Vec<Checked<T>> = indices
+        .into_iter()
+        .map(|index| Checked {
+            item: &v[index],
+            index,
+            violation: &violation,
+            lenient,
+        })
+        .collect();
+    sort(&mut checked);
+    match violation.get() {
+        Some(violation) => Err(violation),
+        None => Ok(checked.iter().map(|c| c.index).collect()),
+    }
+}
+
+// Rearrange v so that the new v[k] is the old v[order[k]], by following
+// each cycle of the permutation and swapping as we go. `order` gets
+// used up along the way (each entry is set to its own index once that
+// position has the right element).
+fn apply_permutation<T>(v: &mut [T], order: &mut [usize]) {
+    for start in 0..v.len() {
+        let mut j = start;
+        loop {
+            let k = order[j];
+            order[j] = j;
+            if k == start {
+                break;
+            }
+            v.swap(j, k);
+            j = k;
+        }
+    }
+}
+
+impl<'a, T: PartialOrd> PartialEq for Checked<'a, T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.partial_cmp(other) == Some(Ordering::Equal)
+    }
+}
+
+impl<'a, T: PartialOrd> PartialOrd for Checked<'a, T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        let result = self.item.partial_cmp(other.item);
+        if result.is_none() && self.lenient {
+            return Some(Ordering::Equal);
+        }
+        if result.is_none() && self.violation.get().is_none() {
+            self.violation.set(Some(OrderViolation {
+                first: self.index.min(other.index),
+                second: self.index.max(other.index),
+            }));
+        }
+        result
+    }
+}
+
+impl<'a, T> Clone for Checked<'a, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, T> Copy for Checked<'a, T> {}
+
+impl<'a, T: fmt::Debug> fmt::Debug for Checked<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}@{}", self.item, self.index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sorts::{insertion_sort, strand_sort};
+
+    #[test]
+    fn empty() {
+        let mut input: [f64; 0] = [];
+        assert_eq!(Ok(()), try_sort(&mut input));
+    }
+
+    #[test]
+    fn ten_items() {
+        let mut input = [3.0, 2.0, 0.0, 5.0, 8.0, 9.0, 6.0, 3.0, 2.0, 0.0];
+        assert_eq!(Ok(()), try_sort(&mut input));
+        let expected = [0.0, 0.0, 2.0, 2.0, 3.0, 3.0, 5.0, 6.0, 8.0, 9.0];
+
+        assert_eq!(expected, input);
+    }
+
+    #[test]
+    fn nan_is_reported() {
+        let mut input = [3.0, 2.0, f64::NAN, 5.0];
+        let result = try_sort(&mut input);
+
+        match result {
+            Err(OrderViolation { first, second }) => assert!(first == 2 || second == 2),
+            Ok(()) => panic!("sorting a NaN should fail"),
+        }
+        // The input should be left alone.
+        assert_eq!(3.0, input[0]);
+        assert!(input[2].is_nan());
+    }
+
+    #[test]
+    fn with_other_algorithms() {
+        let mut input = [3, 2, 0, 5, 8, 9, 6, 3, 2, 0];
+        assert_eq!(Ok(()), try_sort_with(&mut input, |c| insertion_sort(c)));
+        assert_eq!([0, 0, 2, 2, 3, 3, 5, 6, 8, 9], input);
+
+        let mut input = [3.0, f64::NAN];
+        let result = try_sort_with(&mut input, |c| {
+            let sorted = strand_sort(c);
+            c.copy_from_slice(&sorted);
+        });
+        assert_eq!(Err(OrderViolation { first: 0, second: 1 }), result);
+    }
+
+    mod policies {
+        use super::*;
+        use crate::benchmark::{algorithms, sort_generic};
+
+        // Run the named algorithm on the wrappers.
+        fn by_name<'a, T: PartialOrd + fmt::Debug>(name: &str, checked: &mut [Checked<'a, T>]) {
+            let mut v = checked.to_vec();
+            sort_generic(name, &mut v).unwrap();
+            checked.copy_from_slice(&v);
+        }
+
+        #[test]
+        fn abort_leaves_input_alone() {
+            let mut input = [3.0, f64::NAN, 1.0];
+            let result = sort_with_policy(&mut input, IncomparablePolicy::Abort, |c| heapsort(c));
+            assert!(result.is_err());
+            assert_eq!(3.0, input[0]);
+            assert!(input[1].is_nan());
+        }
+
+        #[test]
+        fn move_to_end_with_every_algorithm() {
+            for algorithm in algorithms() {
+                if algorithm.name == "parallel-merge" {
+                    continue;
+                }
+                let mut input = [3.0, f64::NAN, 1.0, 2.0, -f64::NAN, 0.0, 2.0];
+                let result = sort_with_policy(&mut input, IncomparablePolicy::MoveToEnd, |c| {
+                    by_name(algorithm.name, c)
+                });
+                assert_eq!(Ok(()), result, "{}", algorithm.name);
+                assert_eq!([0.0, 1.0, 2.0, 2.0, 3.0], input[..5], "{}", algorithm.name);
+                // In their original order, so the positive NaN is first.
+                assert!(input[5].is_nan() && input[5].is_sign_positive());
+                assert!(input[6].is_nan() && input[6].is_sign_negative());
+            }
+        }
+
+        #[test]
+        fn treat_as_equal_always_finishes() {
+            for algorithm in algorithms() {
+                if algorithm.name == "parallel-merge" {
+                    continue;
+                }
+                let mut input = [3.0, f64::NAN, 1.0, 2.0];
+                let result = sort_with_policy(&mut input, IncomparablePolicy::TreatAsEqual, |c| {
+                    by_name(algorithm.name, c)
+                });
+                assert_eq!(Ok(()), result, "{}", algorithm.name);
+                assert_eq!(1, input.iter().filter(|x| x.is_nan()).count());
+                // Without any NaNs it's just an ordinary sort.
+                let mut input = [3.0, 2.0, 0.0, 5.0, 8.0, 9.0, 6.0, 3.0, 2.0, 0.0];
+                sort_with_policy(&mut input, IncomparablePolicy::TreatAsEqual, |c| {
+                    by_name(algorithm.name, c)
+                })
+                .unwrap();
+                assert_eq!([0.0, 0.0, 2.0, 2.0, 3.0, 3.0, 5.0, 6.0, 8.0, 9.0], input);
+            }
+        }
+
+        #[test]
+        fn move_to_end_still_rejects_other_pairs() {
+            // Sets of bits, ordered by inclusion: {0} and {1} can each be
+            // compared with themselves, but not with each other.
+            #[derive(Debug, Clone, Copy, PartialEq)]
+            struct Bits(u8);
+            impl PartialOrd for Bits {
+                fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                    match (self.0 & !other.0 == 0, other.0 & !self.0 == 0) {
+                        (true, true) => Some(Ordering::Equal),
+                        (true, false) => Some(Ordering::Less),
+                        (false, true) => Some(Ordering::Greater),
+                        (false, false) => None,
+                    }
+                }
+            }
+            let mut input = [Bits(0b11), Bits(0b01), Bits(0b10)];
+            let result = sort_with_policy(&mut input, IncomparablePolicy::MoveToEnd, |c| {
+                insertion_sort(c)
+            });
+            assert!(result.is_err());
+            assert_eq!([Bits(0b11), Bits(0b01), Bits(0b10)], input);
+        }
+    }
+}
@@ -0,0 +1,1682 @@
+// Command-line arguments for the `sorting-in-rust` binary. With no
+// arguments it runs the original lab demo; `matrix` runs the benchmark
+// matrix (see `benchmark.rs`), e.g.
+//    cargo run --release -- matrix --sizes 1000,100000 --trials 5 --jobs 4
+// `set-op` combines two sorted files of numbers (see `sorted_ops.rs`),
+// and `analyze`, `stats`, and `histogram` sort a file of numbers and
+// report on it (see `analysis.rs`).
+
+use crate::benchmark::{
+    algorithms as all_algorithms, find_algorithm, Algorithm, Distribution, MatrixConfig,
+};
+use crate::cache_sim::CacheConfig;
+use crate::grade::{find_subject, subjects as all_subjects, Subject};
+use crate::keys::{parse_duration, KeyType};
+use crate::lines::{LineSortConfig, UnparsablePolicy};
+use crate::mutants::{parse_bugs, Bug};
+#[cfg(all(feature = "numa", target_os = "linux"))]
+use crate::numa::Placement;
+use crate::random::{RandomSource, SeededRandom};
+use crate::report::ReportFormat;
+use crate::sorted_ops::SetOperation;
+use crate::sorts::ParallelConfig;
+use std::path::PathBuf;
+use std::time::Duration;
+
+pub const USAGE: &str = "\
+usage: sorting-in-rust [[matrix] [--preset P] [options]]
+       sorting-in-rust set-op OPERATION FILE1 FILE2
+       sorting-in-rust analyze FILE [--report-dupes]
+       sorting-in-rust stats FILE
+       sorting-in-rust histogram FILE [--bins K] [--equi-depth]
+       sorting-in-rust sort-lines FILE [options]
+       sorting-in-rust repl
+       sorting-in-rust grade [SUBJECT...] [--bugs B,...] [--mutants]
+       sorting-in-rust diff-test [--cases N] [--seed S]
+       sorting-in-rust budget [options]
+       sorting-in-rust cache-sim [options]
+       sorting-in-rust branches [options]
+       sorting-in-rust trace ALGORITHM [--size N] [--distribution D] [--seed S] [--output FILE]
+       sorting-in-rust replay FILE
+       sorting-in-rust trace-diff FILE1 FILE2
+       sorting-in-rust certify ALGORITHM [--size N] [--distribution D] [--seed S] [--output FILE]
+       sorting-in-rust verify-certificate FILE [--replay]
+       sorting-in-rust explain-complexity [RESULTS.json | matrix options]
+       sorting-in-rust --help
+
+With no arguments, runs the lab demo, on a different random input each
+time unless the SORTLAB_SEED environment variable sets the seed (the
+seed used is printed, so a run can be repeated). With options but no
+command, runs the matrix, e.g.
+    sorting-in-rust --algo quicksort --size 500000 --reps 5
+
+set-op combines two files of sorted numbers and prints the sorted result.
+OPERATION is one of union, intersect, difference, symmetric-difference.
+
+analyze sorts a file of numbers and reports how many there are and how
+many are distinct; --report-dupes also lists every value that appears
+more than once, and how often.
+
+stats sorts a file of numbers and prints the min, max, median, quartiles,
+mode, and number of distinct values.
+
+histogram draws a bar chart of a file of numbers, with K (default 10)
+equally wide bins, or with --equi-depth, K bins holding equal numbers of
+values.
+
+sort-lines sorts the lines of a text file (stably) and prints them.
+
+sort-lines options:
+    --key-type T             compare keys as text (the default), number, date
+                             (ISO-8601, e.g. 2022-11-03T14:05:09Z), duration
+                             (e.g. 1h30m, 250ms), ipv4, ipv6, or semver
+    --field N                the key is field N (from 1) rather than the whole line
+    --delimiter C            fields are separated by C (read like CSV, so fields
+                             can be in double quotes; `tab` for a tab) rather
+                             than spaces
+    --header                 keep the first line at the top
+    --on-unparsable P        what to do with lines whose keys are missing or
+                             can't be parsed: error (the default), sort-last, or
+                             skip
+    --zero-copy              keep the file in one buffer and sort the lines by
+                             their offsets into it, without copying any line;
+                             faster, and leaner, for big files. The whole
+                             file is still read into memory, unless this was
+                             built with the `mmap` feature, which maps it
+
+repl starts an interactive prompt for trying the sorts out on small
+arrays; type `help` there for its commands.
+
+grade runs the lab's quicksort, merge, and merge_sort (or just the
+SUBJECTs named) through a battery of checks and prints a JSON score
+report; it exits with an error if any check failed. --bugs (or the
+SORTING_BUGS environment variable) injects known bugs into them first:
+off-by-one-partition, missing-merge-tail, wrong-comparison, or
+unstable-merge. --mutants instead tries each bug in turn and lists the
+checks that caught it.
+
+diff-test (with the `reference` feature) runs partition_by and merge
+side by side with reference versions on N (default 1000) random inputs
+and reports the first place they differ; it honors SORTING_BUGS.
+
+trace records every comparison, swap, and write insertion, selection,
+quicksort, or merge makes sorting N (default 10, at most 64) values as JSON, to FILE or
+standard output; replay draws a recorded trace step by step. trace
+honors SORTING_BUGS (see grade), and trace-diff finds the first step
+where two traces of the same input differ, e.g. a buggy quicksort and a
+correct one.
+
+certify sorts N (default 1000) values and writes a certificate of the
+result as JSON, to FILE or standard output: the input, the output, the
+permutation taking one to the other, and a hash chain of every
+comparison made. verify-certificate checks a certificate without
+sorting anything: the output must be in order and the permutation of
+the input it claims to be. --replay also sorts the input again to check
+that the sort makes exactly the comparisons recorded. certify honors
+SORTING_BUGS (see grade).
+
+budget gives every algorithm the same number of comparisons on each
+size of input and shows how much of the sort each one got through.
+
+cache-sim sorts N (default 10000) values with each algorithm through a
+small simulated cache and shows how many of the values each one read to
+compare were already in the cache: merge sort reads its runs in order
+and mostly hits, while heapsort jumps about the heap and mostly misses.
+
+branches records which way every comparison each algorithm makes on N
+(default 10000) values comes out, and shows how predictable they were
+(how often true, the entropy of each outcome alone and given the one
+before, the average streak of the same outcome, and how often a
+two-bit branch predictor guessed wrong) next to how long each
+comparison took, and how well the two are correlated.
+
+explain-complexity shows how much each algorithm's time grows when n
+doubles, next to what its big-O predicts. It reads the times from a
+results file written by the pipeline binary's --json, or else runs a
+matrix (by default on sizes 1000,2000,4000,8000 with 3 trials).
+
+budget options:
+    --algorithms a,b,...     algorithms to run (default: all but parallel-merge)
+    --sizes n,m,...          input sizes (default: 100,1000,10000)
+    --distribution d         input distribution (default: uniform)
+    --steps N                comparisons allowed per sort (default: 100000)
+    --seed S                 seed for generating the inputs (default: 0)
+
+cache-sim options:
+    --algorithms a,b,...     algorithms to run (default: all but parallel-merge)
+    --size N                 input size (default: 10000)
+    --distribution d         input distribution (default: uniform)
+    --seed S                 seed for generating the input (default: 0)
+    --capacity BYTES         cache size, optionally with K or M (default: 4K)
+    --line-size BYTES        bytes per cache line (default: 64)
+    --associativity W        cache lines per set (default: 4)
+
+branches options:
+    --algorithms a,b,...     algorithms to run (default: all but parallel-merge)
+    --size N                 input size (default: 10000)
+    --distribution d         input distribution (default: uniform)
+    --seed S                 seed for generating the input (default: 0)
+    --trials N               time each sort N times and keep the fastest (default: 3)
+
+matrix options:
+    --preset P               start from a preset: quick (sizes 1000,10000, 3 trials),
+                             classroom (1000,3000,10000 × uniform,sorted,reversed,
+                             10 trials, as Markdown), or paper (the O(N log N) sorts
+                             up to 1000000, 30 trials, shuffled, as JSON); any other
+                             options override the preset's
+    --algorithms a,b,...     algorithms to run (default: all); or --algo
+    --sizes n,m,...          input sizes (default: 1000); or --size
+    --distributions d,...    input distributions: uniform, sorted, reversed,
+                             nearly-sorted, all-equal, few-unique, median-first, or
+                             merge-worst
+                             (default: uniform)
+    --cases auto             instead of the distributions, run each algorithm on its
+                             own best-, average-, and worst-case inputs
+    --trials N               trials per size and distribution (default: 1), or the
+                             fewest to run with --max-time or --target-ci; or --reps
+    --shuffle SEED           run the cells in a random order, shuffled with SEED, to
+                             spread out warm-up effects; `random` picks a seed (which
+                             is printed, so the order can be repeated)
+    --soak T                 instead of running the matrix once, run its cells (the
+                             first trial of each) over and over for T, e.g. 2h, and
+                             log how their times drift
+    --soak-interval T        how often to run them when soaking (default: 10s)
+    --max-time T             run more trials until this much time has been spent,
+                             e.g. 30s or 2m, split evenly between the cells
+    --target-ci P            run more trials of a cell until the 95% confidence
+                             interval for its mean time is within ±P of it, e.g. 5%
+    --seed S                 seed for generating the inputs (default: 0)
+    --jobs N                 cells to run at once, at most one per core (default: 1)
+    --keep-inputs            generate each input once and clone it for each algorithm,
+                             instead of regenerating it from its seed
+    --max-memory BYTES       refuse to start if the run could need more memory than
+                             this; accepts K, M, and G suffixes (e.g. 512M)
+    --placement P            (with the `numa` feature) generate inputs in parallel with
+                             their pages placed by first-touch or interleaved
+    --huge-pages             (with the `hugepages` feature) ask for huge pages for
+                             big inputs
+    --threads N              threads for parallel-merge (default: $SORTLAB_THREADS,
+                             or one per core)
+    --par-threshold N        parallel-merge sorts inputs smaller than this on one
+                             thread (default: $SORTLAB_PAR_THRESHOLD, or the
+                             tuned threshold from tuned.toml)
+    --verbose                print parallel-merge's settings and how many chunks it
+                             found already sorted
+    --count-only             don't time anything; instead count each sort's
+                             comparisons (and, for insertion, selection, quicksort,
+                             and merge, swaps, moves, and recursion depth), which are
+                             the same on every machine, next to the fewest
+                             comparisons any sort could make on that input
+    --format F               print the results as text (the default), markdown, or json
+    --quiet                  print nothing but errors
+    --summary-json FILE      write a one-line JSON summary of the run to FILE (`-` for
+                             standard output)
+    --timeout T              give up if the matrix hasn't finished after T, e.g. 10m
+    --bundle FILE            also write FILE (e.g. out.tar.gz) with the settings, seeds,
+                             environment, results, and report, for reproducing the run
+
+matrix exits with 0 if every run sorted its input, 3 if one didn't, 4 if
+it timed out, 5 if it couldn't read or write a file, and 1 for any other
+error (2 is for bad arguments).";
+
+#[derive(Debug)]
+pub enum Command {
+    Demo,
+    // Print `USAGE`.
+    Help,
+    Matrix {
+        config: Box<MatrixConfig>,
+        script: ScriptOptions,
+    },
+    SetOp {
+        operation: SetOperation,
+        left: PathBuf,
+        right: PathBuf,
+    },
+    Analyze {
+        file: PathBuf,
+        report_dupes: bool,
+    },
+    Stats {
+        file: PathBuf,
+    },
+    Histogram {
+        file: PathBuf,
+        bins: usize,
+        equi_depth: bool,
+    },
+    SortLines {
+        file: PathBuf,
+        config: LineSortConfig,
+        // Sort offsets into the file's buffer rather than separate lines.
+        zero_copy: bool,
+    },
+    Repl,
+    Grade {
+        subjects: Vec<Subject>,
+        // `None` means use `SORTING_BUGS`.
+        bugs: Option<Vec<Bug>>,
+        mutants: bool,
+    },
+    Budget {
+        algorithms: Vec<Algorithm>,
+        sizes: Vec<usize>,
+        distribution: Distribution,
+        steps: u64,
+        seed: u64,
+    },
+    CacheSim {
+        algorithms: Vec<Algorithm>,
+        size: usize,
+        distribution: Distribution,
+        seed: u64,
+        cache: CacheConfig,
+    },
+    Branches {
+        algorithms: Vec<Algorithm>,
+        size: usize,
+        distribution: Distribution,
+        seed: u64,
+        trials: usize,
+    },
+    Trace {
+        algorithm: String,
+        size: usize,
+        distribution: Distribution,
+        seed: u64,
+        output: Option<PathBuf>,
+    },
+    Certify {
+        algorithm: String,
+        size: usize,
+        distribution: Distribution,
+        seed: u64,
+        output: Option<PathBuf>,
+    },
+    VerifyCertificate {
+        file: PathBuf,
+        replay: bool,
+    },
+    Replay {
+        file: PathBuf,
+    },
+    TraceDiff {
+        left: PathBuf,
+        right: PathBuf,
+    },
+    ExplainComplexity(ComplexitySource),
+    #[cfg(feature = "reference")]
+    DiffTest {
+        cases: usize,
+        seed: u64,
+    },
+}
+
+// Parse the arguments (not including the program name). Errors are
+// messages meant to be shown to the user along with `USAGE`.
+pub fn parse_args<I: IntoIterator<Item = String>>(args: I) -> Result<Command, String> {
+    let mut args = args.into_iter();
+    match args.next().as_deref() {
+        None => Ok(Command::Demo),
+        // Before the options-with-no-command case below, which would
+        // take `--help` for a matrix option.
+        Some("--help") | Some("-h") | Some("help") => Ok(Command::Help),
+        Some("matrix") => parse_matrix_command(args),
+        Some("set-op") => parse_set_op(args),
+        Some("analyze") => parse_analyze(args),
+        Some("stats") => parse_stats(args),
+        Some("histogram") => parse_histogram(args),
+        Some("sort-lines") => parse_sort_lines(args),
+        Some("grade") => parse_grade(args),
+        Some("budget") => parse_budget(args),
+        Some("cache-sim") => parse_cache_sim(args),
+        Some("branches") => parse_branches(args),
+        Some("trace") => parse_trace(args, "trace"),
+        Some("certify") => parse_trace(args, "certify"),
+        Some("verify-certificate") => {
+            let args: Vec<String> = args.collect();
+            match args.as_slice() {
+                [file] => Ok(Command::VerifyCertificate {
+                    file: PathBuf::from(file),
+                    replay: false,
+                }),
+                [file, flag] | [flag, file] if flag == "--replay" => {
+                    Ok(Command::VerifyCertificate {
+                        file: PathBuf::from(file),
+                        replay: true,
+                    })
+                }
+                _ => Err("verify-certificate needs exactly one certificate file".to_string()),
+            }
+        }
+        Some("explain-complexity") => parse_explain_complexity(args),
+        Some("trace-diff") => {
+            let args: Vec<String> = args.collect();
+            match args.as_slice() {
+                [left, right] => Ok(Command::TraceDiff {
+                    left: PathBuf::from(left),
+                    right: PathBuf::from(right),
+                }),
+                _ => Err("trace-diff needs exactly two trace files".to_string()),
+            }
+        }
+        Some("replay") => {
+            let args: Vec<String> = args.collect();
+            match args.as_slice() {
+                [file] => Ok(Command::Replay {
+                    file: PathBuf::from(file),
+                }),
+                _ => Err("replay needs exactly one trace file".to_string()),
+            }
+        }
+        #[cfg(feature = "reference")]
+        Some("diff-test") => parse_diff_test(args),
+        Some("repl") => match args.next() {
+            None => Ok(Command::Repl),
+            Some(_) => Err("repl doesn't take any arguments".to_string()),
+        },
+        // Options with no command are for the matrix.
+        Some(flag) if flag.starts_with("--") => {
+            parse_matrix_command(std::iter::once(flag.to_string()).chain(args))
+        }
+        Some(other) => Err(format!("unknown command `{}`", other)),
+    }
+}
+
+fn parse_set_op<I: Iterator<Item = String>>(args: I) -> Result<Command, String> {
+    let args: Vec<String> = args.collect();
+    if args.len() != 3 {
+        return Err("set-op needs an operation and two files".to_string());
+    }
+    let operation = SetOperation::from_name(&args[0])
+        .ok_or_else(|| format!("unknown set operation `{}`", args[0]))?;
+    Ok(Command::SetOp {
+        operation,
+        left: PathBuf::from(&args[1]),
+        right: PathBuf::from(&args[2]),
+    })
+}
+
+fn parse_analyze<I: Iterator<Item = String>>(args: I) -> Result<Command, String> {
+    let mut file = None;
+    let mut report_dupes = false;
+    for arg in args {
+        match arg.as_str() {
+            "--report-dupes" => report_dupes = true,
+            _ if arg.starts_with("--") => return Err(format!("unknown option `{}`", arg)),
+            _ if file.is_none() => file = Some(PathBuf::from(arg)),
+            _ => return Err("analyze takes just one file".to_string()),
+        }
+    }
+    let file = file.ok_or_else(|| "analyze needs a file".to_string())?;
+    Ok(Command::Analyze { file, report_dupes })
+}
+
+fn parse_stats<I: Iterator<Item = String>>(args: I) -> Result<Command, String> {
+    let args: Vec<String> = args.collect();
+    if args.len() != 1 {
+        return Err("stats needs exactly one file".to_string());
+    }
+    Ok(Command::Stats {
+        file: PathBuf::from(&args[0]),
+    })
+}
+
+fn parse_histogram<I: Iterator<Item = String>>(mut args: I) -> Result<Command, String> {
+    let mut file = None;
+    let mut bins = 10;
+    let mut equi_depth = false;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--equi-depth" => equi_depth = true,
+            "--bins" => {
+                let value = args.next().ok_or("`--bins` needs a value")?;
+                bins = parse_number(&arg, &value)?;
+            }
+            _ if arg.starts_with("--") => return Err(format!("unknown option `{}`", arg)),
+            _ if file.is_none() => file = Some(PathBuf::from(arg)),
+            _ => return Err("histogram takes just one file".to_string()),
+        }
+    }
+    let file = file.ok_or("histogram needs a file")?;
+    if bins == 0 {
+        return Err("`--bins` has to be at least 1".to_string());
+    }
+    Ok(Command::Histogram {
+        file,
+        bins,
+        equi_depth,
+    })
+}
+
+fn parse_sort_lines<I: Iterator<Item = String>>(mut args: I) -> Result<Command, String> {
+    let mut file = None;
+    let mut config = LineSortConfig::default();
+    let mut zero_copy = false;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--header" => config.header = true,
+            "--zero-copy" => zero_copy = true,
+            "--key-type" | "--field" | "--delimiter" | "--on-unparsable" => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| format!("`{}` needs a value", arg))?;
+                match arg.as_str() {
+                    "--key-type" => {
+                        config.key_type = KeyType::from_name(&value)
+                            .ok_or_else(|| format!("unknown key type `{}`", value))?
+                    }
+                    "--field" => match parse_number(&arg, &value)? {
+                        0 => return Err("fields are numbered from 1".to_string()),
+                        field => config.field = Some(field),
+                    },
+                    "--on-unparsable" => {
+                        config.unparsable = UnparsablePolicy::from_name(&value)
+                            .ok_or_else(|| format!("unknown policy `{}`", value))?
+                    }
+                    _ => config.delimiter = Some(parse_delimiter(&value)?),
+                }
+            }
+            _ if arg.starts_with("--") => return Err(format!("unknown option `{}`", arg)),
+            _ if file.is_none() => file = Some(PathBuf::from(arg)),
+            _ => return Err("sort-lines takes just one file".to_string()),
+        }
+    }
+    let file = file.ok_or("sort-lines needs a file")?;
+    Ok(Command::SortLines {
+        file,
+        config,
+        zero_copy,
+    })
+}
+
+fn parse_delimiter(value: &str) -> Result<char, String> {
+    let mut chars = value.chars();
+    match (value, chars.next(), chars.next()) {
+        ("tab", _, _) | ("\\t", _, _) => Ok('\t'),
+        (_, Some(c), None) => Ok(c),
+        _ => Err(format!("the delimiter should be one character, not `{}`", value)),
+    }
+}
+
+fn parse_grade<I: Iterator<Item = String>>(mut args: I) -> Result<Command, String> {
+    let mut subjects = Vec::new();
+    let mut bugs = None;
+    let mut mutants = false;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--mutants" => mutants = true,
+            "--bugs" => {
+                let value = args.next().ok_or("`--bugs` needs a value")?;
+                bugs = Some(parse_bugs(&value)?);
+            }
+            _ if arg.starts_with("--") => return Err(format!("unknown option `{}`", arg)),
+            name => subjects.push(
+                find_subject(name).ok_or_else(|| format!("unknown subject `{}`", name))?,
+            ),
+        }
+    }
+    if subjects.is_empty() {
+        subjects = all_subjects();
+    }
+    Ok(Command::Grade {
+        subjects,
+        bugs,
+        mutants,
+    })
+}
+
+// `trace` and `certify` take the same options: which algorithm to run,
+// and on what.
+fn parse_trace<I: Iterator<Item = String>>(mut args: I, command: &str) -> Result<Command, String> {
+    let algorithm = match args.next() {
+        Some(name) if !name.starts_with("--") => name,
+        _ => return Err(format!("{} needs an algorithm", command)),
+    };
+    // Traces get long quickly; certificates don't.
+    let mut size = if command == "certify" { 1000 } else { 10 };
+    let mut distribution = Distribution::Uniform;
+    let mut seed = 0;
+    let mut output = None;
+    while let Some(flag) = args.next() {
+        let value = args
+            .next()
+            .ok_or_else(|| format!("`{}` needs a value", flag))?;
+        match flag.as_str() {
+            "--size" => size = parse_size(&flag, &value)?,
+            "--distribution" => {
+                distribution = Distribution::from_name(&value)
+                    .ok_or_else(|| format!("unknown distribution `{}`", value))?
+            }
+            "--seed" => seed = parse_number(&flag, &value)?,
+            "--output" => output = Some(PathBuf::from(value)),
+            _ => return Err(format!("unknown option `{}`", flag)),
+        }
+    }
+    if command == "certify" {
+        return Ok(Command::Certify {
+            algorithm,
+            size,
+            distribution,
+            seed,
+            output,
+        });
+    }
+    Ok(Command::Trace {
+        algorithm,
+        size,
+        distribution,
+        seed,
+        output,
+    })
+}
+
+fn parse_budget<I: Iterator<Item = String>>(mut args: I) -> Result<Command, String> {
+    let mut algorithms: Vec<Algorithm> = all_algorithms()
+        .into_iter()
+        .filter(|a| a.name != "parallel-merge")
+        .collect();
+    let mut sizes = vec![100, 1000, 10000];
+    let mut distribution = Distribution::Uniform;
+    let mut steps = 100_000;
+    let mut seed = 0;
+    while let Some(flag) = args.next() {
+        let value = args
+            .next()
+            .ok_or_else(|| format!("`{}` needs a value", flag))?;
+        match flag.as_str() {
+            "--algorithms" => {
+                algorithms = parse_list(&value, |name| {
+                    find_algorithm(name).ok_or_else(|| format!("unknown algorithm `{}`", name))
+                })?
+            }
+            "--sizes" => sizes = parse_list(&value, |n| parse_size(&flag, n))?,
+            "--distribution" => {
+                distribution = Distribution::from_name(&value)
+                    .ok_or_else(|| format!("unknown distribution `{}`", value))?
+            }
+            "--steps" => steps = parse_number(&flag, &value)?,
+            "--seed" => seed = parse_number(&flag, &value)?,
+            _ => return Err(format!("unknown option `{}`", flag)),
+        }
+    }
+    Ok(Command::Budget {
+        algorithms,
+        sizes,
+        distribution,
+        steps,
+        seed,
+    })
+}
+
+fn parse_cache_sim<I: Iterator<Item = String>>(mut args: I) -> Result<Command, String> {
+    let mut algorithms: Vec<Algorithm> = all_algorithms()
+        .into_iter()
+        .filter(|a| a.name != "parallel-merge")
+        .collect();
+    let mut size = 10_000;
+    let mut distribution = Distribution::Uniform;
+    let mut seed = 0;
+    let mut cache = CacheConfig::default();
+    while let Some(flag) = args.next() {
+        let value = args
+            .next()
+            .ok_or_else(|| format!("`{}` needs a value", flag))?;
+        match flag.as_str() {
+            "--algorithms" => {
+                algorithms = parse_list(&value, |name| match find_algorithm(name) {
+                    Some(a) if a.name == "parallel-merge" => {
+                        Err("cache-sim can't run parallel-merge".to_string())
+                    }
+                    Some(a) => Ok(a),
+                    None => Err(format!("unknown algorithm `{}`", name)),
+                })?
+            }
+            "--size" => size = parse_size(&flag, &value)?,
+            "--distribution" => {
+                distribution = Distribution::from_name(&value)
+                    .ok_or_else(|| format!("unknown distribution `{}`", value))?
+            }
+            "--seed" => seed = parse_number(&flag, &value)?,
+            "--capacity" => cache.capacity = parse_bytes(&flag, &value)?,
+            "--line-size" => cache.line_size = parse_bytes(&flag, &value)?,
+            "--associativity" => cache.associativity = parse_number(&flag, &value)?,
+            _ => return Err(format!("unknown option `{}`", flag)),
+        }
+    }
+    cache.validate()?;
+    Ok(Command::CacheSim {
+        algorithms,
+        size,
+        distribution,
+        seed,
+        cache,
+    })
+}
+
+fn parse_branches<I: Iterator<Item = String>>(mut args: I) -> Result<Command, String> {
+    let mut algorithms: Vec<Algorithm> = all_algorithms()
+        .into_iter()
+        .filter(|a| a.name != "parallel-merge")
+        .collect();
+    let mut size = 10_000;
+    let mut distribution = Distribution::Uniform;
+    let mut seed = 0;
+    let mut trials = 3;
+    while let Some(flag) = args.next() {
+        let value = args
+            .next()
+            .ok_or_else(|| format!("`{}` needs a value", flag))?;
+        match flag.as_str() {
+            "--algorithms" => {
+                algorithms = parse_list(&value, |name| match find_algorithm(name) {
+                    Some(a) if a.name == "parallel-merge" => {
+                        Err("branches can't run parallel-merge".to_string())
+                    }
+                    Some(a) => Ok(a),
+                    None => Err(format!("unknown algorithm `{}`", name)),
+                })?
+            }
+            "--size" => size = parse_size(&flag, &value)?,
+            "--distribution" => {
+                distribution = Distribution::from_name(&value)
+                    .ok_or_else(|| format!("unknown distribution `{}`", value))?
+            }
+            "--seed" => seed = parse_number(&flag, &value)?,
+            "--trials" => trials = parse_number(&flag, &value)?,
+            _ => return Err(format!("unknown option `{}`", flag)),
+        }
+    }
+    if trials == 0 {
+        return Err("`--trials` must be at least 1".to_string());
+    }
+    Ok(Command::Branches {
+        algorithms,
+        size,
+        distribution,
+        seed,
+        trials,
+    })
+}
+
+// Where `explain-complexity` gets its times from.
+#[derive(Debug)]
+pub enum ComplexitySource {
+    // A results file from the `pipeline` binary.
+    File(PathBuf),
+    // Running this matrix.
+    Matrix(Box<MatrixConfig>),
+}
+
+fn parse_explain_complexity<I: Iterator<Item = String>>(args: I) -> Result<Command, String> {
+    let args: Vec<String> = args.collect();
+    if let [file] = args.as_slice() {
+        if !file.starts_with("--") {
+            return Ok(Command::ExplainComplexity(ComplexitySource::File(
+                PathBuf::from(file),
+            )));
+        }
+    }
+    // A sweep of doubling sizes, unless the options say otherwise (the
+    // later `--sizes` wins).
+    let defaults = ["--sizes", "1000,2000,4000,8000", "--trials", "3"];
+    let args = defaults.iter().map(|s| s.to_string()).chain(args);
+    let config = parse_matrix(args)?;
+    Ok(Command::ExplainComplexity(ComplexitySource::Matrix(Box::new(config))))
+}
+
+#[cfg(feature = "reference")]
+fn parse_diff_test<I: Iterator<Item = String>>(mut args: I) -> Result<Command, String> {
+    let mut cases = 1000;
+    let mut seed = 0;
+    while let Some(flag) = args.next() {
+        let value = args
+            .next()
+            .ok_or_else(|| format!("`{}` needs a value", flag))?;
+        match flag.as_str() {
+            "--cases" => cases = parse_number(&flag, &value)?,
+            "--seed" => seed = parse_number(&flag, &value)?,
+            _ => return Err(format!("unknown option `{}`", flag)),
+        }
+    }
+    Ok(Command::DiffTest { cases, seed })
+}
+
+// How `matrix` reports on itself, including for when a script or CI job
+// is running it rather than a person. These aren't part of
+// `MatrixConfig`, since they don't change what runs (and make no sense
+// in a config file).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ScriptOptions {
+    pub format: ReportFormat,
+    // Print nothing but errors (and the summary, if it goes to standard
+    // output).
+    pub quiet: bool,
+    // Write `report::summary_json` here at the end; `-` is standard
+    // output.
+    pub summary_json: Option<PathBuf>,
+    // Give up if the matrix hasn't finished after this long.
+    pub timeout: Option<Duration>,
+    // Write a reproducibility bundle (see `bundle.rs`) here.
+    pub bundle: Option<PathBuf>,
+}
+
+// Named bundles of `matrix` options, for getting meaningful results
+// without learning every knob first. Options given after (or before)
+// `--preset` override the preset's.
+pub const PRESETS: [(&str, &[&str]); 3] = [
+    // A first look, in a few seconds.
+    ("quick", &["--sizes", "1000,10000", "--trials", "3"]),
+    // Enough to see every algorithm's best and worst, and quadratic
+    // growth, for a lab write-up. The sizes stop at 10000 because sorted
+    // and reversed input is the lab quicksort's worst case, where it
+    // recurses once per item, and much deeper than that overflows the
+    // stack in a debug build.
+    (
+        "classroom",
+        &[
+            "--sizes",
+            "1000,3000,10000",
+            "--distributions",
+            "uniform,sorted,reversed",
+            "--trials",
+            "10",
+            "--format",
+            "markdown",
+        ],
+    ),
+    // Careful timings of the O(N log N) sorts up to a million items, in
+    // a random order to spread out warm-up effects, as JSON to analyze
+    // elsewhere. (Insertion sort would take hours at that size.)
+    (
+        "paper",
+        &[
+            "--algorithms",
+            "quicksort,randomized-quicksort,merge,parallel-merge,bitonic,heapsort,weak-heapsort",
+            "--sizes",
+            "1000,10000,100000,1000000",
+            "--trials",
+            "30",
+            "--shuffle",
+            "random",
+            "--format",
+            "json",
+        ],
+    ),
+];
+
+// Replace `--preset NAME` with the preset's options, put first so that
+// everything else on the command line overrides them.
+fn expand_presets(args: Vec<String>) -> Result<Vec<String>, String> {
+    let mut expanded = Vec::new();
+    let mut rest = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg != "--preset" {
+            rest.push(arg);
+            continue;
+        }
+        let name = args.next().ok_or("`--preset` needs a name")?;
+        let (_, options) = PRESETS
+            .iter()
+            .find(|(preset, _)| *preset == name)
+            .ok_or_else(|| format!("unknown preset `{}`", name))?;
+        expanded.extend(options.iter().map(|option| option.to_string()));
+    }
+    expanded.extend(rest);
+    Ok(expanded)
+}
+
+// Pick out the `ScriptOptions` and leave the rest to `parse_matrix`.
+fn parse_matrix_command<I: Iterator<Item = String>>(args: I) -> Result<Command, String> {
+    let mut args = expand_presets(args.collect())?.into_iter();
+    let mut script = ScriptOptions::default();
+    let mut rest = Vec::new();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => {
+                let value = args.next().ok_or("`--format` needs a value")?;
+                script.format = ReportFormat::from_name(&value)
+                    .ok_or_else(|| format!("unknown format `{}`", value))?;
+            }
+            "--quiet" => script.quiet = true,
+            "--summary-json" => {
+                let file = args.next().ok_or("`--summary-json` needs a file (or `-`)")?;
+                script.summary_json = Some(PathBuf::from(file));
+            }
+            "--timeout" => {
+                let value = args.next().ok_or("`--timeout` needs a value")?;
+                script.timeout = Some(parse_duration(&value)?);
+            }
+            "--bundle" => {
+                let file = args.next().ok_or("`--bundle` needs a file")?;
+                script.bundle = Some(PathBuf::from(file));
+            }
+            _ => rest.push(arg),
+        }
+    }
+    let config = Box::new(parse_matrix(rest.into_iter())?);
+    if config.count_only {
+        let unsupported = [
+            ("--summary-json", script.summary_json.is_some()),
+            ("--timeout", script.timeout.is_some()),
+            ("--bundle", script.bundle.is_some()),
+        ];
+        if let Some((flag, _)) = unsupported.iter().find(|(_, given)| *given) {
+            return Err(format!("`{}` can't go with `--count-only`", flag));
+        }
+    }
+    Ok(Command::Matrix { config, script })
+}
+
+fn parse_matrix<I: Iterator<Item = String>>(mut args: I) -> Result<MatrixConfig, String> {
+    // The `SORTLAB_*` variables set parallel-merge's defaults, and
+    // `--threads` and `--par-threshold` override them.
+    let mut config = MatrixConfig {
+        parallel: ParallelConfig::from_env()?,
+        ..MatrixConfig::default()
+    };
+    while let Some(flag) = args.next() {
+        if flag == "--keep-inputs" {
+            config.keep_inputs = true;
+            continue;
+        }
+        if flag == "--verbose" {
+            config.parallel.verbose = true;
+            continue;
+        }
+        if flag == "--count-only" {
+            config.count_only = true;
+            continue;
+        }
+        #[cfg(all(feature = "hugepages", target_os = "linux"))]
+        if flag == "--huge-pages" {
+            config.huge_pages = true;
+            continue;
+        }
+        let value = args
+            .next()
+            .ok_or_else(|| format!("`{}` needs a value", flag))?;
+        match flag.as_str() {
+            "--algorithms" | "--algo" => {
+                config.algorithms = parse_list(&value, |name| {
+                    find_algorithm(name).ok_or_else(|| format!("unknown algorithm `{}`", name))
+                })?
+            }
+            "--sizes" | "--size" => config.sizes = parse_list(&value, |n| parse_size(&flag, n))?,
+            "--distributions" => {
+                config.distributions = parse_list(&value, |name| {
+                    Distribution::from_name(name)
+                        .ok_or_else(|| format!("unknown distribution `{}`", name))
+                })?
+            }
+            "--cases" => {
+                if value != "auto" {
+                    return Err(format!("`{}` can only be `auto`, not `{}`", flag, value));
+                }
+                config.auto_cases = true;
+            }
+            "--trials" | "--reps" => config.trials = parse_number(&flag, &value)?,
+            "--shuffle" => {
+                config.schedule_seed = Some(if value == "random" {
+                    SeededRandom::from_entropy().next_u64()
+                } else {
+                    parse_number(&flag, &value)?
+                })
+            }
+            "--max-time" => config.max_time = Some(parse_duration(&value)?),
+            "--soak" => config.soak = Some(parse_duration(&value)?),
+            "--soak-interval" => config.soak_interval = parse_duration(&value)?,
+            "--target-ci" => config.target_ci = Some(parse_fraction(&flag, &value)?),
+            "--seed" => config.seed = parse_number(&flag, &value)?,
+            "--jobs" => config.jobs = parse_number(&flag, &value)?,
+            "--max-memory" => config.max_memory = Some(parse_bytes(&flag, &value)?),
+            "--threads" => {
+                config.parallel.threads = parse_number(&flag, &value)?;
+                if config.parallel.threads == 0 {
+                    return Err(format!("`{}` must be at least 1", flag));
+                }
+            }
+            "--par-threshold" => config.parallel.threshold = Some(parse_number(&flag, &value)?),
+            #[cfg(all(feature = "numa", target_os = "linux"))]
+            "--placement" => {
+                config.placement = Some(
+                    Placement::from_name(&value)
+                        .ok_or_else(|| format!("unknown placement `{}`", value))?,
+                )
+            }
+            _ => return Err(format!("unknown option `{}`", flag)),
+        }
+    }
+    if config.count_only {
+        let timed = [
+            ("--max-time", config.max_time.is_some()),
+            ("--target-ci", config.target_ci.is_some()),
+            ("--soak", config.soak.is_some()),
+        ];
+        if let Some((flag, _)) = timed.iter().find(|(_, given)| *given) {
+            return Err(format!("`{}` needs timings, so it can't go with `--count-only`", flag));
+        }
+    }
+    Ok(config)
+}
+
+// Read matrix settings from a config file rather than the command line.
+// Each line is a `key = value` pair, where the keys are the `matrix`
+// options without their dashes, e.g.
+//    algorithms = quicksort, merge
+//    sizes = 1000, 100000
+//    keep-inputs = true
+// Blank lines and anything after a `#` are ignored. We just turn each
+// line back into command-line flags, so the two can't disagree about
+// what's allowed.
+pub fn parse_matrix_config(text: &str) -> Result<MatrixConfig, String> {
+    let mut args = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        let (key, value) = match line.find('=') {
+            Some(i) => (line[..i].trim(), line[i + 1..].trim()),
+            None => return Err(format!("line {}: expected `key = value`", number + 1)),
+        };
+        let flag = format!("--{}", key);
+        if ["--keep-inputs", "--huge-pages", "--verbose", "--count-only"].contains(&flag.as_str()) {
+            match value {
+                "true" => args.push(flag),
+                "false" => {}
+                _ => return Err(format!("line {}: `{}` should be true or false", number + 1, key)),
+            }
+        } else {
+            args.push(flag);
+            args.push(value.to_string());
+        }
+    }
+    parse_matrix(args.into_iter())
+}
+
+fn parse_number<N: std::str::FromStr>(flag: &str, value: &str) -> Result<N, String> {
+    value
+        .parse()
+        .map_err(|_| format!("`{}` expects a number, not `{}`", flag, value))
+}
+
+// Sizes have to fit in an `i32`, since that's what the input generator
+// takes.
+fn parse_size(flag: &str, value: &str) -> Result<usize, String> {
+    let size: usize = parse_number(flag, value)?;
+    if size > i32::MAX as usize {
+        return Err(format!("`{}` can be at most {}, not {}", flag, i32::MAX, size));
+    }
+    Ok(size)
+}
+
+// A number of bytes, optionally followed by K, M, or G (powers of 1024).
+fn parse_bytes(flag: &str, value: &str) -> Result<usize, String> {
+    let (digits, multiplier) = match value.chars().last().map(|c| c.to_ascii_uppercase()) {
+        Some('K') => (&value[..value.len() - 1], 1 << 10),
+        Some('M') => (&value[..value.len() - 1], 1 << 20),
+        Some('G') => (&value[..value.len() - 1], 1 << 30),
+        _ => (value, 1),
+    };
+    let n: usize = parse_number(flag, digits)?;
+    n.checked_mul(multiplier)
+        .ok_or_else(|| format!("`{}` value `{}` is too large", flag, value))
+}
+
+// A fraction, either as it is (0.05) or as a percentage (5%).
+fn parse_fraction(flag: &str, value: &str) -> Result<f64, String> {
+    let fraction = match value.strip_suffix('%') {
+        Some(percent) => parse_number::<f64>(flag, percent)? / 100.0,
+        None => parse_number(flag, value)?,
+    };
+    if !(fraction > 0.0 && fraction.is_finite()) {
+        return Err(format!("`{}` must be more than 0, not `{}`", flag, value));
+    }
+    Ok(fraction)
+}
+
+fn parse_list<T, F: FnMut(&str) -> Result<T, String>>(
+    value: &str,
+    parse: F,
+) -> Result<Vec<T>, String> {
+    value.split(',').map(str::trim).map(parse).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Command, String> {
+        parse_args(args.iter().map(|s| s.to_string()))
+    }
+
+    fn matrix(args: &[&str]) -> MatrixConfig {
+        match parse(args) {
+            Ok(Command::Matrix { config, .. }) => *config,
+            other => panic!("expected a matrix command, got {:?}", other),
+        }
+    }
+
+    // The lab's `quicksort` pivots on the first element, so on anything
+    // but random (or median-first) input it can recurse about once per
+    // item. In a debug build it gets this deep on a 2 MiB stack (a test
+    // thread's, or a `--jobs` worker's) with room to spare.
+    const QUICKSORT_SAFE_DEPTH: usize = 10_000;
+
+    #[test]
+    fn presets_stay_clear_of_quicksorts_worst_case() {
+        for (name, _) in PRESETS.iter() {
+            let config = matrix(&["matrix", "--preset", name]);
+            if !config.algorithms.iter().any(|a| a.name == "quicksort") {
+                continue;
+            }
+            for distribution in &config.distributions {
+                if matches!(distribution, Distribution::Uniform | Distribution::MedianFirst) {
+                    continue;
+                }
+                for &size in &config.sizes {
+                    assert!(
+                        size <= QUICKSORT_SAFE_DEPTH,
+                        "preset {} runs quicksort on {} input of size {}",
+                        name,
+                        distribution.name(),
+                        size
+                    );
+                }
+            }
+        }
+        // And that really is safe.
+        let mut v: Vec<i32> = (0..QUICKSORT_SAFE_DEPTH as i32).collect();
+        crate::sorts::quicksort(&mut v);
+    }
+
+    #[test]
+    fn no_arguments_is_demo() {
+        assert!(matches!(parse(&[]), Ok(Command::Demo)));
+    }
+
+    #[test]
+    fn help() {
+        for flag in &["--help", "-h", "help"] {
+            assert!(matches!(parse(&[flag]), Ok(Command::Help)), "{}", flag);
+        }
+        // Other options with no command still go to the matrix.
+        assert!(matches!(parse(&["--algo", "quicksort"]), Ok(Command::Matrix { .. })));
+        assert!(parse(&["-x"]).is_err());
+    }
+
+    #[test]
+    fn options_without_a_command_run_the_matrix() {
+        let config = matrix(&["--algo", "quicksort", "--size", "500000", "--reps", "5"]);
+        let names: Vec<_> = config.algorithms.iter().map(|a| a.name).collect();
+        assert_eq!(vec!["quicksort"], names);
+        assert_eq!(vec![500_000], config.sizes);
+        assert_eq!(5, config.trials);
+        assert!(matches!(
+            parse(&["--preset", "quick", "--format", "json"]),
+            Ok(Command::Matrix { .. })
+        ));
+        assert!(parse(&["--bogus", "1"]).is_err());
+        assert!(parse(&["bogus"]).is_err());
+    }
+
+    #[test]
+    fn matrix_defaults() {
+        let config = matrix(&["matrix"]);
+        assert_eq!(vec![1000], config.sizes);
+        assert_eq!(1, config.jobs);
+    }
+
+    #[test]
+    fn matrix_options() {
+        let config = matrix(&[
+            "matrix",
+            "--algorithms",
+            "quicksort,merge",
+            "--sizes",
+            "10,100",
+            "--trials",
+            "5",
+            "--seed",
+            "42",
+            "--jobs",
+            "4",
+        ]);
+        let names: Vec<_> = config.algorithms.iter().map(|a| a.name).collect();
+        assert_eq!(vec!["quicksort", "merge"], names);
+        assert_eq!(vec![10, 100], config.sizes);
+        assert_eq!(5, config.trials);
+        assert_eq!(42, config.seed);
+        assert_eq!(4, config.jobs);
+    }
+
+    #[test]
+    fn memory_options() {
+        let config = matrix(&["matrix", "--keep-inputs", "--max-memory", "512M"]);
+        assert!(config.keep_inputs);
+        assert_eq!(Some(512 << 20), config.max_memory);
+        assert_eq!(Some(2 << 30), matrix(&["matrix", "--max-memory", "2g"]).max_memory);
+        assert_eq!(Some(1000), matrix(&["matrix", "--max-memory", "1000"]).max_memory);
+    }
+
+    #[test]
+    fn count_only() {
+        assert!(matrix(&["matrix", "--count-only"]).count_only);
+        assert!(!matrix(&["matrix"]).count_only);
+        assert!(parse_matrix_config("count-only = true").unwrap().count_only);
+        assert!(parse(&["matrix", "--count-only", "--target-ci", "5%"]).is_err());
+        assert!(parse(&["matrix", "--count-only", "--summary-json", "-"]).is_err());
+    }
+
+    #[test]
+    fn set_op() {
+        match parse(&["set-op", "intersect", "a.txt", "b.txt"]) {
+            Ok(Command::SetOp {
+                operation,
+                left,
+                right,
+            }) => {
+                assert_eq!(SetOperation::Intersect, operation);
+                assert_eq!(PathBuf::from("a.txt"), left);
+                assert_eq!(PathBuf::from("b.txt"), right);
+            }
+            other => panic!("expected a set-op command, got {:?}", other),
+        }
+        assert!(parse(&["set-op", "union", "a.txt"]).is_err());
+        assert!(parse(&["set-op", "merge", "a.txt", "b.txt"]).is_err());
+    }
+
+    #[test]
+    fn analyze() {
+        match parse(&["analyze", "--report-dupes", "data.txt"]) {
+            Ok(Command::Analyze { file, report_dupes }) => {
+                assert_eq!(PathBuf::from("data.txt"), file);
+                assert!(report_dupes);
+            }
+            other => panic!("expected an analyze command, got {:?}", other),
+        }
+        assert!(parse(&["analyze"]).is_err());
+        assert!(parse(&["analyze", "a.txt", "b.txt"]).is_err());
+    }
+
+    #[test]
+    fn stats() {
+        match parse(&["stats", "data.txt"]) {
+            Ok(Command::Stats { file }) => assert_eq!(PathBuf::from("data.txt"), file),
+            other => panic!("expected a stats command, got {:?}", other),
+        }
+        assert!(parse(&["stats"]).is_err());
+    }
+
+    #[test]
+    fn histogram() {
+        match parse(&["histogram", "data.txt", "--bins", "4", "--equi-depth"]) {
+            Ok(Command::Histogram {
+                file,
+                bins,
+                equi_depth,
+            }) => {
+                assert_eq!(PathBuf::from("data.txt"), file);
+                assert_eq!(4, bins);
+                assert!(equi_depth);
+            }
+            other => panic!("expected a histogram command, got {:?}", other),
+        }
+        assert!(parse(&["histogram", "data.txt", "--bins", "0"]).is_err());
+        assert!(parse(&["histogram", "--bins", "3"]).is_err());
+    }
+
+    #[cfg(all(feature = "numa", target_os = "linux"))]
+    #[test]
+    fn placement() {
+        let config = matrix(&["matrix", "--placement", "interleaved"]);
+        assert_eq!(Some(Placement::Interleaved), config.placement);
+        assert!(parse(&["matrix", "--placement", "everywhere"]).is_err());
+    }
+
+    #[cfg(all(feature = "hugepages", target_os = "linux"))]
+    #[test]
+    fn huge_pages() {
+        assert!(matrix(&["matrix", "--huge-pages"]).huge_pages);
+        assert!(!matrix(&["matrix"]).huge_pages);
+    }
+
+    #[test]
+    fn parallel_settings() {
+        let config = matrix(&["matrix", "--threads", "3", "--par-threshold", "1000", "--verbose"]);
+        assert_eq!(3, config.parallel.threads);
+        assert_eq!(Some(1000), config.parallel.threshold);
+        assert!(config.parallel.verbose);
+        assert!(parse(&["matrix", "--threads", "0"]).is_err());
+
+        let config = parse_matrix_config("threads = 2\npar-threshold = 50\nverbose = true\n").unwrap();
+        assert_eq!(2, config.parallel.threads);
+        assert_eq!(Some(50), config.parallel.threshold);
+        assert!(config.parallel.verbose);
+    }
+
+    #[test]
+    fn cases() {
+        let config = matrix(&["matrix", "--cases", "auto", "--algorithms", "insertion,bitonic"]);
+        assert!(config.auto_cases);
+        // Insertion's three cases, and bitonic's one.
+        assert_eq!(4, config.cells().len());
+        assert!(parse_matrix_config("cases = auto\n").unwrap().auto_cases);
+        assert!(parse(&["matrix", "--cases", "worst"]).is_err());
+        assert!(!matrix(&["matrix"]).auto_cases);
+    }
+
+    #[test]
+    fn shuffle() {
+        assert_eq!(None, matrix(&["matrix"]).schedule_seed);
+        assert_eq!(Some(266), matrix(&["matrix", "--shuffle", "266"]).schedule_seed);
+        assert!(matrix(&["matrix", "--shuffle", "random"]).schedule_seed.is_some());
+        assert!(parse(&["matrix", "--shuffle", "please"]).is_err());
+        assert_eq!(Some(7), parse_matrix_config("shuffle = 7").unwrap().schedule_seed);
+    }
+
+    #[test]
+    fn soak() {
+        let config = matrix(&["matrix", "--soak", "2h", "--soak-interval", "1m"]);
+        assert_eq!(Some(Duration::from_secs(7200)), config.soak);
+        assert_eq!(Duration::from_secs(60), config.soak_interval);
+        let config = matrix(&["matrix"]);
+        assert_eq!(None, config.soak);
+        assert_eq!(Duration::from_secs(10), config.soak_interval);
+        assert!(parse(&["matrix", "--soak", "forever"]).is_err());
+    }
+
+    #[test]
+    fn script_options() {
+        match parse(&["matrix", "--quiet", "--sizes", "10", "--summary-json", "-", "--timeout", "1m"]) {
+            Ok(Command::Matrix { config, script }) => {
+                assert_eq!(vec![10], config.sizes);
+                assert_eq!(
+                    ScriptOptions {
+                        format: ReportFormat::Text,
+                        quiet: true,
+                        summary_json: Some(PathBuf::from("-")),
+                        timeout: Some(Duration::from_secs(60)),
+                        bundle: None,
+                    },
+                    script
+                );
+            }
+            other => panic!("expected a matrix command, got {:?}", other),
+        }
+        match parse(&["matrix"]) {
+            Ok(Command::Matrix { script, .. }) => assert_eq!(ScriptOptions::default(), script),
+            other => panic!("expected a matrix command, got {:?}", other),
+        }
+        assert!(parse(&["matrix", "--summary-json"]).is_err());
+        assert!(parse(&["matrix", "--timeout", "soon"]).is_err());
+        match parse(&["matrix", "--bundle", "out.tar.gz"]) {
+            Ok(Command::Matrix { script, .. }) => {
+                assert_eq!(Some(PathBuf::from("out.tar.gz")), script.bundle)
+            }
+            other => panic!("expected a matrix command, got {:?}", other),
+        }
+        // They're for the command line, not config files.
+        assert!(parse_matrix_config("quiet = true
+").is_err());
+    }
+
+    #[test]
+    fn adaptive_trials() {
+        let config = matrix(&["matrix", "--max-time", "1m30s", "--target-ci", "5%"]);
+        assert_eq!(Some(Duration::from_secs(90)), config.max_time);
+        assert_eq!(Some(0.05), config.target_ci);
+        assert!(config.is_adaptive());
+        assert_eq!(Some(0.1), matrix(&["matrix", "--target-ci", "0.1"]).target_ci);
+        assert!(!matrix(&["matrix"]).is_adaptive());
+        assert!(parse(&["matrix", "--target-ci", "0"]).is_err());
+        assert!(parse(&["matrix", "--target-ci", "lots"]).is_err());
+        assert!(parse(&["matrix", "--max-time", "soon"]).is_err());
+    }
+
+    #[test]
+    fn matrix_config_file() {
+        let text = "\
+# A small sweep.
+algorithms = quicksort, merge
+sizes = 10, 100   # tiny
+trials = 2
+
+keep-inputs = true
+";
+        let config = parse_matrix_config(text).unwrap();
+        let names: Vec<&str> = config.algorithms.iter().map(|a| a.name).collect();
+        assert_eq!(vec!["quicksort", "merge"], names);
+        assert_eq!(vec![10, 100], config.sizes);
+        assert_eq!(2, config.trials);
+        assert!(config.keep_inputs);
+        assert!(parse_matrix_config("").is_ok());
+        assert!(parse_matrix_config("sizes 10").is_err());
+        assert!(parse_matrix_config("keep-inputs = yes").is_err());
+        assert!(parse_matrix_config("colour = blue").is_err());
+    }
+
+    #[test]
+    fn sort_lines() {
+        let args = [
+            "sort-lines",
+            "log.csv",
+            "--key-type",
+            "date",
+            "--field",
+            "2",
+            "--delimiter",
+            "tab",
+            "--header",
+            "--on-unparsable",
+            "sort-last",
+        ];
+        match parse(&args) {
+            Ok(Command::SortLines {
+                file,
+                config,
+                zero_copy,
+            }) => {
+                assert_eq!(PathBuf::from("log.csv"), file);
+                assert!(!zero_copy);
+                assert_eq!(
+                    LineSortConfig {
+                        key_type: KeyType::Date,
+                        field: Some(2),
+                        delimiter: Some('\t'),
+                        header: true,
+                        unparsable: UnparsablePolicy::SortLast,
+                    },
+                    config
+                );
+            }
+            other => panic!("{:?}", other),
+        }
+        match parse(&["sort-lines", "a.txt", "--delimiter", ";"]) {
+            Ok(Command::SortLines { config, .. }) => assert_eq!(Some(';'), config.delimiter),
+            other => panic!("{:?}", other),
+        }
+        assert!(matches!(
+            parse(&["sort-lines", "--zero-copy", "a.txt"]),
+            Ok(Command::SortLines { zero_copy: true, .. })
+        ));
+        assert!(parse(&["sort-lines"]).is_err());
+        assert!(parse(&["sort-lines", "a.txt", "--field", "0"]).is_err());
+        assert!(parse(&["sort-lines", "a.txt", "--key-type", "colour"]).is_err());
+        assert!(parse(&["sort-lines", "a.txt", "--delimiter", ";;"]).is_err());
+        assert!(parse(&["sort-lines", "a.txt", "--on-unparsable", "ignore"]).is_err());
+    }
+
+    #[test]
+    fn repl() {
+        assert!(matches!(parse(&["repl"]), Ok(Command::Repl)));
+        assert!(parse(&["repl", "now"]).is_err());
+    }
+
+    #[test]
+    fn grade() {
+        let names = |args: &[&str]| match parse(args) {
+            Ok(Command::Grade { subjects, .. }) => {
+                subjects.iter().map(|s| s.name).collect::<Vec<&str>>()
+            }
+            other => panic!("{:?}", other),
+        };
+        assert_eq!(vec!["quicksort", "merge", "merge_sort"], names(&["grade"]));
+        assert_eq!(vec!["merge"], names(&["grade", "merge"]));
+        assert!(parse(&["grade", "bogosort"]).is_err());
+        match parse(&["grade", "--bugs", "unstable-merge", "merge", "--mutants"]) {
+            Ok(Command::Grade {
+                subjects,
+                bugs,
+                mutants,
+            }) => {
+                assert_eq!(1, subjects.len());
+                assert_eq!(Some(vec![Bug::UnstableMerge]), bugs);
+                assert!(mutants);
+            }
+            other => panic!("{:?}", other),
+        }
+        match parse(&["grade"]) {
+            Ok(Command::Grade { bugs, mutants, .. }) => assert!(bugs.is_none() && !mutants),
+            other => panic!("{:?}", other),
+        }
+        assert!(parse(&["grade", "--bugs", "typo"]).is_err());
+    }
+
+    #[cfg(feature = "reference")]
+    #[test]
+    fn diff_test() {
+        match parse(&["diff-test", "--cases", "50", "--seed", "7"]) {
+            Ok(Command::DiffTest { cases, seed }) => assert_eq!((50, 7), (cases, seed)),
+            other => panic!("{:?}", other),
+        }
+        assert!(parse(&["diff-test", "--cases"]).is_err());
+    }
+
+    #[test]
+    fn budget() {
+        match parse(&["budget"]) {
+            Ok(Command::Budget {
+                algorithms, steps, ..
+            }) => {
+                assert!(algorithms.iter().all(|a| a.name != "parallel-merge"));
+                assert_eq!(100_000, steps);
+            }
+            other => panic!("{:?}", other),
+        }
+        let args = [
+            "budget",
+            "--algorithms",
+            "insertion,merge",
+            "--sizes",
+            "10,20",
+            "--distribution",
+            "reversed",
+            "--steps",
+            "500",
+            "--seed",
+            "3",
+        ];
+        match parse(&args) {
+            Ok(Command::Budget {
+                algorithms,
+                sizes,
+                distribution,
+                steps,
+                seed,
+            }) => {
+                assert_eq!(2, algorithms.len());
+                assert_eq!(vec![10, 20], sizes);
+                assert_eq!(Distribution::Reversed, distribution);
+                assert_eq!((500, 3), (steps, seed));
+            }
+            other => panic!("{:?}", other),
+        }
+        assert!(parse(&["budget", "--steps", "lots"]).is_err());
+    }
+
+    #[test]
+    fn cache_sim() {
+        match parse(&["cache-sim"]) {
+            Ok(Command::CacheSim {
+                algorithms,
+                size,
+                cache,
+                ..
+            }) => {
+                assert!(algorithms.iter().all(|a| a.name != "parallel-merge"));
+                assert_eq!(10_000, size);
+                assert_eq!(CacheConfig::default(), cache);
+            }
+            other => panic!("{:?}", other),
+        }
+        let args = [
+            "cache-sim",
+            "--algorithms",
+            "heapsort,merge",
+            "--capacity",
+            "32K",
+            "--line-size",
+            "128",
+            "--associativity",
+            "8",
+        ];
+        match parse(&args) {
+            Ok(Command::CacheSim {
+                algorithms, cache, ..
+            }) => {
+                assert_eq!(2, algorithms.len());
+                assert_eq!(
+                    (32 * 1024, 128, 8),
+                    (cache.capacity, cache.line_size, cache.associativity)
+                );
+            }
+            other => panic!("{:?}", other),
+        }
+        assert!(parse(&["cache-sim", "--associativity", "3"]).is_err());
+        assert!(parse(&["cache-sim", "--algorithms", "parallel-merge"]).is_err());
+    }
+
+    #[test]
+    fn branches() {
+        match parse(&["branches"]) {
+            Ok(Command::Branches {
+                algorithms,
+                size,
+                trials,
+                ..
+            }) => {
+                assert!(algorithms.iter().all(|a| a.name != "parallel-merge"));
+                assert_eq!((10_000, 3), (size, trials));
+            }
+            other => panic!("{:?}", other),
+        }
+        let args = [
+            "branches",
+            "--algorithms",
+            "insertion,quicksort",
+            "--size",
+            "500",
+            "--distribution",
+            "sorted",
+            "--trials",
+            "1",
+        ];
+        match parse(&args) {
+            Ok(Command::Branches {
+                algorithms,
+                size,
+                distribution,
+                trials,
+                ..
+            }) => {
+                assert_eq!(2, algorithms.len());
+                assert_eq!((500, 1), (size, trials));
+                assert_eq!(Distribution::Sorted, distribution);
+            }
+            other => panic!("{:?}", other),
+        }
+        assert!(parse(&["branches", "--trials", "0"]).is_err());
+        assert!(parse(&["branches", "--algorithms", "parallel-merge"]).is_err());
+    }
+
+    #[test]
+    fn trace_and_replay() {
+        match parse(&["trace", "merge", "--size", "5", "--output", "t.json"]) {
+            Ok(Command::Trace {
+                algorithm,
+                size,
+                output,
+                ..
+            }) => {
+                assert_eq!("merge", algorithm);
+                assert_eq!(5, size);
+                assert_eq!(Some(PathBuf::from("t.json")), output);
+            }
+            other => panic!("{:?}", other),
+        }
+        assert!(parse(&["trace"]).is_err());
+        assert!(parse(&["trace", "--size", "5"]).is_err());
+        match parse(&["replay", "t.json"]) {
+            Ok(Command::Replay { file }) => assert_eq!(PathBuf::from("t.json"), file),
+            other => panic!("{:?}", other),
+        }
+        assert!(parse(&["replay"]).is_err());
+        match parse(&["trace-diff", "a.json", "b.json"]) {
+            Ok(Command::TraceDiff { left, right }) => {
+                assert_eq!(PathBuf::from("a.json"), left);
+                assert_eq!(PathBuf::from("b.json"), right);
+            }
+            other => panic!("{:?}", other),
+        }
+        assert!(parse(&["trace-diff", "a.json"]).is_err());
+    }
+
+    #[test]
+    fn certificates() {
+        match parse(&["certify", "heapsort", "--seed", "278"]) {
+            Ok(Command::Certify {
+                algorithm,
+                size,
+                seed,
+                output,
+                ..
+            }) => {
+                assert_eq!(("heapsort", 1000, 278), (algorithm.as_str(), size, seed));
+                assert_eq!(None, output);
+            }
+            other => panic!("{:?}", other),
+        }
+        assert!(parse(&["certify"]).is_err());
+        match parse(&["verify-certificate", "--replay", "c.json"]) {
+            Ok(Command::VerifyCertificate { file, replay }) => {
+                assert_eq!(PathBuf::from("c.json"), file);
+                assert!(replay);
+            }
+            other => panic!("{:?}", other),
+        }
+        assert!(matches!(
+            parse(&["verify-certificate", "c.json"]),
+            Ok(Command::VerifyCertificate { replay: false, .. })
+        ));
+        assert!(parse(&["verify-certificate", "a.json", "b.json"]).is_err());
+    }
+
+    #[test]
+    fn explain_complexity() {
+        match parse(&["explain-complexity"]) {
+            Ok(Command::ExplainComplexity(ComplexitySource::Matrix(config))) => {
+                assert_eq!(vec![1000, 2000, 4000, 8000], config.sizes);
+                assert_eq!(3, config.trials);
+            }
+            other => panic!("{:?}", other),
+        }
+        match parse(&["explain-complexity", "--sizes", "10,20", "--algorithms", "merge"]) {
+            Ok(Command::ExplainComplexity(ComplexitySource::Matrix(config))) => {
+                assert_eq!(vec![10, 20], config.sizes);
+                assert_eq!(1, config.algorithms.len());
+            }
+            other => panic!("{:?}", other),
+        }
+        match parse(&["explain-complexity", "results.json"]) {
+            Ok(Command::ExplainComplexity(ComplexitySource::File(file))) => {
+                assert_eq!(PathBuf::from("results.json"), file)
+            }
+            other => panic!("{:?}", other),
+        }
+        assert!(parse(&["explain-complexity", "a.json", "b.json"]).is_err());
+    }
+
+    #[test]
+    fn errors() {
+        assert!(parse(&["matrix", "--sizes", "3000000000"]).is_err());
+        assert!(parse(&["matrix", "--max-memory", "lots"]).is_err());
+        assert!(parse(&["frobnicate"]).is_err());
+        assert!(parse(&["matrix", "--jobs"]).is_err());
+        assert!(parse(&["matrix", "--jobs", "many"]).is_err());
+        assert!(parse(&["matrix", "--algorithms", "bogosort"]).is_err());
+        assert!(parse(&["matrix", "--colour", "blue"]).is_err());
+    }
+}
@@ -0,0 +1,211 @@
+// A sort is only as good as the comparisons it's given. If a comparator
+// says a < b and b < c but c < a, there _is_ no sorted order, and
+// different algorithms will do different (wrong) things; some sorts in
+// other languages' libraries will even crash. Broken comparators are
+// surprisingly easy to write, e.g., by comparing floats with
+// `partial_cmp(..).unwrap_or(Equal)` or by subtracting integers that can
+// overflow.
+//
+// `validate_comparator` is a smoke test for a comparator: it tries it on
+// the elements of a sample (and pairs and triples of them) and reports
+// any counterexamples to the properties every comparator needs:
+//   reflexivity:   cmp(a, a) = Equal
+//   antisymmetry:  cmp(a, b) is the reverse of cmp(b, a)
+//   transitivity:  a ≤ b and b ≤ c imply a ≤ c (with a = c if all
+//                  three are equal)
+// Finding no counterexamples doesn't prove the comparator is right, of
+// course, but a sample of "interesting" values (including edge cases
+// like 0, negative numbers, NaN, empty strings, ...) will catch most
+// mistakes.
+
+use crate::random::{RandomSource, SeededRandom};
+use std::cmp::Ordering;
+use std::fmt;
+
+// We stop looking once we've found this many counterexamples.
+pub const MAX_COUNTEREXAMPLES: usize = 10;
+
+// Samples with more elements than this have too many triples to check
+// them all, so we check this many³ randomly chosen triples instead.
+const EXHAUSTIVE_LIMIT: usize = 64;
+
+// A counterexample, given as indices into the sample.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ComparatorViolation {
+    // cmp(a, a) wasn't Equal.
+    NotReflexive { a: usize },
+    // cmp(a, b) wasn't the reverse of cmp(b, a).
+    NotAntisymmetric { a: usize, b: usize },
+    // a ≤ b and b ≤ c, but not a ≤ c (or a ≠ c when all three are equal).
+    NotTransitive { a: usize, b: usize, c: usize },
+}
+
+impl fmt::Display for ComparatorViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ComparatorViolation::NotReflexive { a } => {
+                write!(f, "element {} doesn't compare equal to itself", a)
+            }
+            ComparatorViolation::NotAntisymmetric { a, b } => write!(
+                f,
+                "comparing elements {} and {} gives inconsistent answers depending on the order",
+                a, b
+            ),
+            ComparatorViolation::NotTransitive { a, b, c } => write!(
+                f,
+                "element {} ≤ element {} ≤ element {}, but not element {} ≤ element {}",
+                a, b, c, a, c
+            ),
+        }
+    }
+}
+
+// Check `cmp` on the elements of `sample`, returning any counterexamples
+// found (up to `MAX_COUNTEREXAMPLES` of them). An empty result means no
+// problems were found.
+pub fn validate_comparator<T, F>(sample: &[T], mut cmp: F) -> Vec<ComparatorViolation>
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    let n = sample.len();
+    let mut violations = Vec::new();
+
+    for (a, x) in sample.iter().enumerate() {
+        if cmp(x, x) != Ordering::Equal {
+            violations.push(ComparatorViolation::NotReflexive { a });
+        }
+    }
+    for a in 0..n {
+        for b in a + 1..n {
+            if cmp(&sample[a], &sample[b]) != cmp(&sample[b], &sample[a]).reverse() {
+                violations.push(ComparatorViolation::NotAntisymmetric { a, b });
+            }
+        }
+    }
+
+    let mut check_triple = |a: usize, b: usize, c: usize, violations: &mut Vec<_>| {
+        let ab = cmp(&sample[a], &sample[b]);
+        let bc = cmp(&sample[b], &sample[c]);
+        if ab == Ordering::Greater || bc == Ordering::Greater {
+            return;
+        }
+        let expected = if ab == Ordering::Equal && bc == Ordering::Equal {
+            Ordering::Equal
+        } else {
+            Ordering::Less
+        };
+        if cmp(&sample[a], &sample[c]) != expected {
+            violations.push(ComparatorViolation::NotTransitive { a, b, c });
+        }
+    };
+    if n <= EXHAUSTIVE_LIMIT {
+        'all: for a in 0..n {
+            for b in 0..n {
+                for c in 0..n {
+                    if violations.len() >= MAX_COUNTEREXAMPLES {
+                        break 'all;
+                    }
+                    check_triple(a, b, c, &mut violations);
+                }
+            }
+        }
+    } else {
+        // Use a fixed seed so the same sample always gets the same
+        // report.
+        let mut rng = SeededRandom::new(0);
+        for _ in 0..EXHAUSTIVE_LIMIT.pow(3) {
+            if violations.len() >= MAX_COUNTEREXAMPLES {
+                break;
+            }
+            let a = rng.below(n);
+            let b = rng.below(n);
+            let c = rng.below(n);
+            check_triple(a, b, c, &mut violations);
+        }
+    }
+
+    violations.truncate(MAX_COUNTEREXAMPLES);
+    violations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty() {
+        let sample: [i32; 0] = [];
+        assert!(validate_comparator(&sample, |a, b| a.cmp(b)).is_empty());
+    }
+
+    #[test]
+    fn good_comparator() {
+        let sample = [3, 2, 0, 5, 8, 9, 6, 3, 2, 0, i32::MIN, i32::MAX];
+        assert!(validate_comparator(&sample, |a, b| a.cmp(b)).is_empty());
+    }
+
+    #[test]
+    fn good_comparator_on_large_sample() {
+        let sample: Vec<i32> = (0..1000).map(|i| (i * 7919) % 1000).collect();
+        assert!(validate_comparator(&sample, |a, b| b.cmp(a)).is_empty());
+    }
+
+    #[test]
+    fn never_equal() {
+        // Says "greater" for equal elements, which breaks reflexivity and
+        // antisymmetry.
+        let sample = [1, 2, 2];
+        let violations = validate_comparator(&sample, |a, b| {
+            if a < b {
+                Ordering::Less
+            } else {
+                Ordering::Greater
+            }
+        });
+
+        assert!(violations.contains(&ComparatorViolation::NotReflexive { a: 0 }));
+        assert!(violations.contains(&ComparatorViolation::NotAntisymmetric { a: 1, b: 2 }));
+    }
+
+    #[test]
+    fn nan_treated_as_equal() {
+        // 1 = NaN and NaN = 3, but 1 < 3.
+        let sample = [1.0, f64::NAN, 3.0];
+        let violations = validate_comparator(&sample, |a: &f64, b: &f64| {
+            a.partial_cmp(b).unwrap_or(Ordering::Equal)
+        });
+
+        assert!(violations.contains(&ComparatorViolation::NotTransitive { a: 0, b: 1, c: 2 }));
+    }
+
+    #[test]
+    fn rock_paper_scissors() {
+        let sample = ["rock", "paper", "scissors"];
+        let beats = |a: &&str, b: &&str| {
+            matches!(
+                (*a, *b),
+                ("rock", "scissors") | ("scissors", "paper") | ("paper", "rock")
+            )
+        };
+        let violations = validate_comparator(&sample, |a, b| {
+            if a == b {
+                Ordering::Equal
+            } else if beats(a, b) {
+                Ordering::Greater
+            } else {
+                Ordering::Less
+            }
+        });
+
+        assert!(violations
+            .iter()
+            .any(|v| matches!(v, ComparatorViolation::NotTransitive { .. })));
+    }
+
+    #[test]
+    fn stops_after_enough_counterexamples() {
+        let sample: Vec<i32> = (0..100).collect();
+        let violations = validate_comparator(&sample, |_, _| Ordering::Less);
+        assert_eq!(MAX_COUNTEREXAMPLES, violations.len());
+    }
+}
@@ -0,0 +1,376 @@
+// The lab's big question, answered from measurements: how does each
+// sort's time grow with the size of its input? If an algorithm is
+// O(n²), doubling n should make it about 4 times slower; if it's
+// O(n log n), a bit more than 2 times slower; if it's O(n), exactly 2
+// times slower.
+//
+// `growth_table` takes the times from a sweep over several sizes and,
+// for each algorithm and input distribution, works out how much the
+// time grew from each size to the next. When the sizes don't double
+// we scale the growth to what it would be per doubling, so the numbers
+// are always comparable with 2 (linear) and 4 (quadratic). Alongside
+// each measured factor is the one the theory predicts for the same
+// sizes, so you can see at a glance whether the measurements agree.
+
+use crate::benchmark::RunResult;
+use crate::json::{self, Json};
+use std::fmt::Write;
+use std::time::Duration;
+
+// The growth rates we expect to see.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Complexity {
+    Linear,
+    NLogN,
+    NLogSquaredN,
+    Quadratic,
+}
+
+impl Complexity {
+    pub fn name(self) -> &'static str {
+        match self {
+            Complexity::Linear => "n",
+            Complexity::NLogN => "n log n",
+            Complexity::NLogSquaredN => "n log² n",
+            Complexity::Quadratic => "n²",
+        }
+    }
+
+    // The number of steps for an input of size n, give or take a
+    // constant factor.
+    fn steps(self, n: usize) -> f64 {
+        let n = n as f64;
+        let log = n.log2().max(1.0);
+        match self {
+            Complexity::Linear => n,
+            Complexity::NLogN => n * log,
+            Complexity::NLogSquaredN => n * log * log,
+            Complexity::Quadratic => n * n,
+        }
+    }
+}
+
+// How we expect `algorithm`'s time to grow on `distribution`, if we
+// know. (The distribution matters: the lab's quicksort is O(n log n) on
+// random input, but O(n²) on sorted input.)
+pub fn expected_complexity(algorithm: &str, distribution: &str) -> Option<Complexity> {
+    use Complexity::*;
+    let quadratic_for_quicksort = ["sorted", "reversed", "nearly-sorted", "all-equal"];
+    match algorithm {
+        "insertion" => match distribution {
+            "sorted" | "all-equal" => Some(Linear),
+            _ => Some(Quadratic),
+        },
+        "selection" => Some(Quadratic),
+        "quicksort" if quadratic_for_quicksort.contains(&distribution) => Some(Quadratic),
+        "randomized-quicksort" if distribution == "all-equal" => Some(Quadratic),
+        "heapsort" | "dual-pivot-quicksort" if distribution == "all-equal" => Some(Linear),
+        "three-way-quicksort" if ["all-equal", "few-unique"].contains(&distribution) => {
+            Some(Linear)
+        }
+        "quicksort"
+        | "randomized-quicksort"
+        | "introsort"
+        | "dual-pivot-quicksort"
+        | "three-way-quicksort"
+        | "merge"
+        | "parallel-merge"
+        | "heapsort"
+        | "weak-heapsort" => Some(NLogN),
+        "bitonic" => Some(NLogSquaredN),
+        _ => None,
+    }
+}
+
+// One timing from a sweep.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Measurement {
+    pub algorithm: String,
+    pub distribution: String,
+    pub size: usize,
+    pub elapsed: Duration,
+}
+
+impl From<&RunResult> for Measurement {
+    fn from(r: &RunResult) -> Measurement {
+        Measurement {
+            algorithm: r.algorithm.to_string(),
+            distribution: r.distribution.name().to_string(),
+            size: r.size,
+            elapsed: r.elapsed,
+        }
+    }
+}
+
+// The measurements from the verified runs in a results file written by
+// the `pipeline` binary (see `report::to_json`).
+pub fn measurements_from_json(text: &str) -> Result<Vec<Measurement>, String> {
+    let json = json::parse(text)?;
+    let results = json
+        .get("results")
+        .and_then(Json::as_array)
+        .ok_or("expected an object with a `results` array")?;
+    let mut measurements = Vec::new();
+    for (i, result) in results.iter().enumerate() {
+        let bad = |field: &str| format!("result {} has no valid `{}`", i, field);
+        if result.get("verified") == Some(&Json::Bool(false)) {
+            continue;
+        }
+        let text = |field: &str| {
+            result
+                .get(field)
+                .and_then(Json::as_str)
+                .map(str::to_string)
+                .ok_or_else(|| bad(field))
+        };
+        let number = |field: &str| {
+            result
+                .get(field)
+                .and_then(Json::as_i64)
+                .filter(|&n| n >= 0)
+                .ok_or_else(|| bad(field))
+        };
+        measurements.push(Measurement {
+            algorithm: text("algorithm")?,
+            distribution: text("distribution")?,
+            size: number("size")? as usize,
+            elapsed: Duration::from_nanos(number("elapsed_ns")? as u64),
+        });
+    }
+    Ok(measurements)
+}
+
+// How one algorithm's time grew on one distribution, from one size to
+// the next, per doubling of the size.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GrowthRow {
+    pub algorithm: String,
+    pub distribution: String,
+    pub from: usize,
+    pub to: usize,
+    pub measured: f64,
+    // What the theory says, if we know what to expect.
+    pub expected: Option<f64>,
+    pub complexity: Option<Complexity>,
+}
+
+// `ratio` for a growth from size `from` to size `to`, scaled to a single
+// doubling: if the size quadrupled and the time went up 16 times, that's
+// 4 times per doubling.
+fn per_doubling(ratio: f64, from: usize, to: usize) -> f64 {
+    ratio.powf(1.0 / (to as f64 / from as f64).log2())
+}
+
+// Every time measured for each size.
+type TimesBySize = Vec<(usize, Vec<Duration>)>;
+
+// The growth tables for every algorithm and distribution with at least
+// two sizes, using the median time of each size's trials.
+pub fn growth_table(measurements: &[Measurement]) -> Vec<GrowthRow> {
+    // (algorithm, distribution) in the order they first appear, each
+    // with its times by size.
+    let mut series: Vec<(&str, &str, TimesBySize)> = Vec::new();
+    for m in measurements {
+        let i = match series
+            .iter()
+            .position(|(a, d, _)| *a == m.algorithm && *d == m.distribution)
+        {
+            Some(i) => i,
+            None => {
+                series.push((&m.algorithm, &m.distribution, Vec::new()));
+                series.len() - 1
+            }
+        };
+        let sizes = &mut series[i].2;
+        match sizes.iter_mut().find(|(size, _)| *size == m.size) {
+            Some((_, times)) => times.push(m.elapsed),
+            None => sizes.push((m.size, vec![m.elapsed])),
+        }
+    }
+
+    let mut rows = Vec::new();
+    for (algorithm, distribution, mut sizes) in series {
+        sizes.sort_by_key(|&(size, _)| size);
+        let complexity = expected_complexity(algorithm, distribution);
+        let medians: Vec<(usize, f64)> = sizes
+            .into_iter()
+            .filter(|&(size, _)| size > 0)
+            .map(|(size, mut times)| {
+                times.sort_unstable();
+                (size, times[times.len() / 2].as_secs_f64())
+            })
+            .collect();
+        for pair in medians.windows(2) {
+            let ((from, before), (to, after)) = (pair[0], pair[1]);
+            if before == 0.0 {
+                continue;
+            }
+            rows.push(GrowthRow {
+                algorithm: algorithm.to_string(),
+                distribution: distribution.to_string(),
+                from,
+                to,
+                measured: per_doubling(after / before, from, to),
+                expected: complexity.map(|c| per_doubling(c.steps(to) / c.steps(from), from, to)),
+                complexity,
+            });
+        }
+    }
+    rows
+}
+
+// The table as text, e.g.
+//    if n doubles, the time grows by:
+//    algorithm   distribution  n             measured  expected
+//    insertion   uniform       1000 → 2000   ×3.91     ×4.00 (n²)
+//    merge       uniform       1000 → 2000   ×2.19     ×2.20 (n log n)
+pub fn render(rows: &[GrowthRow]) -> String {
+    if rows.is_empty() {
+        return "not enough data: each algorithm needs times for at least two sizes\n".to_string();
+    }
+    let algorithm_width = rows.iter().map(|r| r.algorithm.len()).max().unwrap_or(0);
+    let distribution_width = rows.iter().map(|r| r.distribution.len()).max().unwrap_or(0);
+    let sizes: Vec<String> = rows
+        .iter()
+        .map(|r| format!("{} → {}", r.from, r.to))
+        .collect();
+    let sizes_width = sizes.iter().map(|s| s.chars().count()).max().unwrap_or(0);
+
+    let mut text = String::from("if n doubles, the time grows by:\n");
+    let _ = writeln!(
+        text,
+        "{:<aw$}  {:<dw$}  {:<sw$}  measured  expected",
+        "algorithm",
+        "distribution",
+        "n",
+        aw = algorithm_width.max("algorithm".len()),
+        dw = distribution_width.max("distribution".len()),
+        sw = sizes_width,
+    );
+    for (row, sizes) in rows.iter().zip(&sizes) {
+        let expected = match (row.expected, row.complexity) {
+            (Some(expected), Some(complexity)) => {
+                format!("×{:.2} ({})", expected, complexity.name())
+            }
+            _ => "?".to_string(),
+        };
+        // Padding counts characters rather than bytes, so the sizes
+        // line up even though → takes three bytes.
+        let _ = writeln!(
+            text,
+            "{:<aw$}  {:<dw$}  {:<sw$}  ×{:<7.2}  {}",
+            row.algorithm,
+            row.distribution,
+            sizes,
+            row.measured,
+            expected,
+            aw = algorithm_width.max("algorithm".len()),
+            dw = distribution_width.max("distribution".len()),
+            sw = sizes_width,
+        );
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn measurement(algorithm: &str, size: usize, micros: u64) -> Measurement {
+        Measurement {
+            algorithm: algorithm.to_string(),
+            distribution: "uniform".to_string(),
+            size,
+            elapsed: Duration::from_micros(micros),
+        }
+    }
+
+    #[test]
+    fn expectations() {
+        assert_eq!(
+            Some(Complexity::Quadratic),
+            expected_complexity("insertion", "uniform")
+        );
+        assert_eq!(
+            Some(Complexity::Linear),
+            expected_complexity("insertion", "sorted")
+        );
+        assert_eq!(
+            Some(Complexity::NLogN),
+            expected_complexity("quicksort", "uniform")
+        );
+        assert_eq!(
+            Some(Complexity::Quadratic),
+            expected_complexity("quicksort", "sorted")
+        );
+        assert_eq!(
+            Some(Complexity::NLogSquaredN),
+            expected_complexity("bitonic", "sorted")
+        );
+        assert_eq!(None, expected_complexity("bogosort", "uniform"));
+        // Doubling from 1024 to 2048: n log n grows by 2 * 11/10.
+        assert!((Complexity::NLogN.steps(2048) / Complexity::NLogN.steps(1024) - 2.2).abs() < 1e-9);
+    }
+
+    #[test]
+    fn per_doubling_scales() {
+        assert!((per_doubling(16.0, 1000, 4000) - 4.0).abs() < 1e-9);
+        assert!((per_doubling(4.0, 1000, 2000) - 4.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn table() {
+        let measurements = vec![
+            measurement("insertion", 1000, 100),
+            measurement("insertion", 2000, 400),
+            // Trials: the median (1600) counts, not the mean.
+            measurement("insertion", 4000, 1600),
+            measurement("insertion", 4000, 1500),
+            measurement("insertion", 4000, 9000),
+            measurement("merge", 1000, 10),
+            // Only one size, so no row.
+            measurement("mystery", 1000, 10),
+        ];
+        let rows = growth_table(&measurements);
+        assert_eq!(2, rows.len());
+        assert_eq!((1000, 2000), (rows[0].from, rows[0].to));
+        assert!((rows[0].measured - 4.0).abs() < 1e-9);
+        assert!((rows[1].measured - 4.0).abs() < 1e-9);
+        assert!((rows[1].expected.unwrap() - 4.0).abs() < 1e-9);
+
+        let text = render(&rows);
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!("if n doubles, the time grows by:", lines[0]);
+        assert_eq!(
+            "algorithm  distribution  n            measured  expected",
+            lines[1]
+        );
+        assert_eq!(
+            "insertion  uniform       1000 → 2000  ×4.00     ×4.00 (n²)",
+            lines[2]
+        );
+        assert!(render(&[]).starts_with("not enough data"));
+    }
+
+    #[test]
+    fn from_json() {
+        let text = r#"{"parallel": {}, "results": [
+            {"algorithm": "merge", "size": 10, "distribution": "sorted", "elapsed_ns": 1500,
+             "verified": true},
+            {"algorithm": "merge", "size": 20, "distribution": "sorted", "elapsed_ns": 99,
+             "verified": false}
+        ]}"#;
+        let measurements = measurements_from_json(text).unwrap();
+        assert_eq!(
+            vec![Measurement {
+                algorithm: "merge".to_string(),
+                distribution: "sorted".to_string(),
+                size: 10,
+                elapsed: Duration::from_nanos(1500),
+            }],
+            measurements
+        );
+        assert!(measurements_from_json("[]").is_err());
+        assert!(measurements_from_json(r#"{"results": [{"algorithm": "merge"}]}"#).is_err());
+    }
+}
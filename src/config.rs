@@ -0,0 +1,163 @@
+// Settings that let library users control how the sorts behave, all in
+// one place. Set the fields directly, or use the builder:
+//    let counter = Arc::new(ComparisonCounter::new());
+//    let config = SortConfig::builder()
+//        .pivot(Pivot::MedianOfThree)
+//        .insertion_cutoff(24)
+//        .observer(counter.clone())
+//        .build();
+//    sort_with(&mut v, &config)?;
+// `sort_with` (in `configured.rs`) honors every setting; `try_quicksort`
+// honors all of them except `parallel`. The plain lab sorts
+// (`quicksort(v)` and friends) deliberately take no settings at all.
+
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+// How quicksort picks its pivot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Pivot {
+    // The first element, like the lab's `quicksort`. Sorted input is its
+    // worst case.
+    #[default]
+    First,
+    // A random element, like `randomized_quicksort`.
+    Random,
+    // The median of the first, middle, and last elements, which makes
+    // sorted and reverse sorted input the *best* case.
+    MedianOfThree,
+}
+
+// Something that wants to know what a sort is doing. It gets told about
+// every comparison, possibly from several threads at once.
+pub trait SortObserver: fmt::Debug + Send + Sync {
+    fn compared(&self);
+}
+
+// An observer that just counts the comparisons. Unlike the `Counted`
+// wrappers in `instrument.rs` it counts across threads, so it works for
+// parallel sorts too.
+#[derive(Debug, Default)]
+pub struct ComparisonCounter {
+    count: AtomicU64,
+}
+
+impl ComparisonCounter {
+    pub fn new() -> ComparisonCounter {
+        ComparisonCounter::default()
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count.load(Ordering::Relaxed)
+    }
+}
+
+impl SortObserver for ComparisonCounter {
+    fn compared(&self) {
+        self.count.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SortConfig {
+    // The deepest the recursive sorts are allowed to recurse before
+    // giving up with a `DepthExceeded` error, or `None` for no limit.
+    // Only the fallible (`try_*`) sorts look at this.
+    pub max_depth: Option<usize>,
+    pub pivot: Pivot,
+    // Quicksort hands ranges with fewer than this many elements to
+    // insertion sort, which is faster on tiny ranges. 0 means never.
+    pub insertion_cutoff: usize,
+    // Sort on several threads (with parallel merge sort), in which case
+    // the quicksort settings above don't apply. The result is then the
+    // same, byte for byte, however many threads there are (see
+    // `parallel_merge_sort`).
+    pub parallel: bool,
+    pub observer: Option<Arc<dyn SortObserver>>,
+}
+
+impl SortConfig {
+    pub fn builder() -> SortConfigBuilder {
+        SortConfigBuilder::default()
+    }
+
+    // Tell the observer (if there is one) about a comparison.
+    pub(crate) fn observe_comparison(&self) {
+        if let Some(observer) = &self.observer {
+            observer.compared();
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct SortConfigBuilder {
+    config: SortConfig,
+}
+
+impl SortConfigBuilder {
+    pub fn max_depth(mut self, max_depth: usize) -> SortConfigBuilder {
+        self.config.max_depth = Some(max_depth);
+        self
+    }
+
+    pub fn pivot(mut self, pivot: Pivot) -> SortConfigBuilder {
+        self.config.pivot = pivot;
+        self
+    }
+
+    pub fn insertion_cutoff(mut self, cutoff: usize) -> SortConfigBuilder {
+        self.config.insertion_cutoff = cutoff;
+        self
+    }
+
+    pub fn parallel(mut self, parallel: bool) -> SortConfigBuilder {
+        self.config.parallel = parallel;
+        self
+    }
+
+    pub fn observer<O: SortObserver + 'static>(mut self, observer: Arc<O>) -> SortConfigBuilder {
+        self.config.observer = Some(observer);
+        self
+    }
+
+    pub fn build(self) -> SortConfig {
+        self.config
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builder() {
+        let counter = Arc::new(ComparisonCounter::new());
+        let config = SortConfig::builder()
+            .pivot(Pivot::MedianOfThree)
+            .insertion_cutoff(24)
+            .parallel(true)
+            .max_depth(100)
+            .observer(counter.clone())
+            .build();
+        assert_eq!(Pivot::MedianOfThree, config.pivot);
+        assert_eq!(24, config.insertion_cutoff);
+        assert!(config.parallel);
+        assert_eq!(Some(100), config.max_depth);
+        config.observe_comparison();
+        config.observe_comparison();
+        assert_eq!(2, counter.count());
+    }
+
+    #[test]
+    fn defaults() {
+        let config = SortConfig::builder().build();
+        assert_eq!(Pivot::First, config.pivot);
+        assert_eq!(0, config.insertion_cutoff);
+        assert!(!config.parallel);
+        assert_eq!(None, config.max_depth);
+        assert!(config.observer.is_none());
+        // Observing with no observer is fine.
+        config.observe_comparison();
+    }
+}
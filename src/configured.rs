@@ -0,0 +1,118 @@
+// The one sort that honors every setting in a `SortConfig`. Sequential
+// sorts are `try_quicksort` (pivot, insertion cutoff, depth limit, and
+// observer); parallel ones are `parallel_merge_sort`, with each element
+// wrapped so the observer still hears about every comparison, whichever
+// thread makes it.
+
+use crate::config::{SortConfig, SortObserver};
+use crate::error::DepthExceeded;
+use crate::sorts::{parallel_merge_sort, try_quicksort, ParallelConfig};
+use std::cmp::Ordering;
+use std::fmt;
+
+pub fn sort_with<T>(v: &mut [T], config: &SortConfig) -> Result<(), DepthExceeded>
+where
+    T: PartialOrd + Copy + fmt::Debug + Send + Sync,
+{
+    if !config.parallel {
+        return try_quicksort(v, config);
+    }
+    // Invalid `SORTLAB_*` settings just mean the defaults; the binaries
+    // check them up front.
+    let parallel = ParallelConfig::from_env().unwrap_or_default();
+    let sorted = match &config.observer {
+        None => parallel_merge_sort(v, &parallel),
+        Some(observer) => {
+            let observed: Vec<Observed<T>> = v
+                .iter()
+                .map(|&item| Observed {
+                    item,
+                    observer: observer.as_ref(),
+                })
+                .collect();
+            parallel_merge_sort(&observed, &parallel)
+                .into_iter()
+                .map(|o| o.item)
+                .collect()
+        }
+    };
+    v.copy_from_slice(&sorted);
+    Ok(())
+}
+
+// An element that tells `observer` whenever it's compared.
+#[derive(Clone, Copy)]
+struct Observed<'a, T> {
+    item: T,
+    observer: &'a dyn SortObserver,
+}
+
+impl<T: PartialOrd> PartialEq for Observed<'_, T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.observer.compared();
+        self.item == other.item
+    }
+}
+
+impl<T: PartialOrd> PartialOrd for Observed<'_, T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.observer.compared();
+        self.item.partial_cmp(&other.item)
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Observed<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.item.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ComparisonCounter, Pivot};
+    use crate::random::{RandomSource, SeededRandom};
+    use std::sync::Arc;
+
+    #[test]
+    fn ten_items() {
+        for &parallel in &[false, true] {
+            let mut input = [3, 2, 0, 5, 8, 9, 6, 3, 2, 0];
+            let config = SortConfig::builder().parallel(parallel).build();
+            assert_eq!(Ok(()), sort_with(&mut input, &config));
+            assert_eq!([0, 0, 2, 2, 3, 3, 5, 6, 8, 9], input);
+        }
+    }
+
+    #[test]
+    fn observed_in_parallel() {
+        let mut rng = SeededRandom::new(254);
+        let input: Vec<i32> = (0..100_000).map(|_| rng.range(0, 1000)).collect();
+        let counter = Arc::new(ComparisonCounter::new());
+        let config = SortConfig::builder()
+            .pivot(Pivot::MedianOfThree)
+            .insertion_cutoff(24)
+            .parallel(true)
+            .observer(counter.clone())
+            .build();
+        let mut v = input.clone();
+        sort_with(&mut v, &config).unwrap();
+        let mut expected = input;
+        expected.sort_unstable();
+        assert_eq!(expected, v);
+        // At least one comparison per element, and not much more than
+        // N log₂ N of them.
+        assert!(counter.count() >= 100_000);
+        assert!(counter.count() < 2_000_000, "{}", counter.count());
+    }
+
+    #[test]
+    fn depth_limit_applies_sequentially() {
+        let mut input: Vec<i32> = (0..1000).collect();
+        let config = SortConfig::builder().max_depth(16).build();
+        assert_eq!(
+            Err(DepthExceeded { max_depth: 16 }),
+            sort_with(&mut input, &config)
+        );
+    }
+}
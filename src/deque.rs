@@ -0,0 +1,99 @@
+// Sorting a `VecDeque` with the slice sorts.
+//
+// A `VecDeque` keeps its items in a ring buffer, so they may wrap around
+// the end of the buffer: the front items at the end of the buffer, and
+// the back items at the start. That's why it can't just hand out a
+// `&mut [T]` the way a `Vec` does. But `make_contiguous` rotates the
+// items so they're all in one piece (at most O(N) moves, and no
+// allocation, since it works within the buffer it already has), and
+// after that any of our in-place sorts can sort them where they are. So
+// there's no need to copy a deque into a `Vec` and back to sort it.
+
+use crate::sorts::merge_sort;
+use std::collections::VecDeque;
+
+// Sort `deque` with any in-place slice sort, e.g.
+//    sort_deque(&mut deque, heapsort);
+pub fn sort_deque<T, F: FnOnce(&mut [T])>(deque: &mut VecDeque<T>, sort: F) {
+    sort(deque.make_contiguous());
+}
+
+// `merge_sort` returns a new `Vec` rather than sorting in place, so we
+// copy its result back over the deque's (now contiguous) items, which
+// keeps the deque's buffer.
+pub fn merge_sort_deque<T: PartialOrd + Copy + std::fmt::Debug>(deque: &mut VecDeque<T>) {
+    let items = deque.make_contiguous();
+    let sorted = merge_sort(items);
+    items.copy_from_slice(&sorted);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::random::SeededRandom;
+    use crate::sorts::{
+        bitonic_sort, heapsort, insertion_sort, quicksort, randomized_quicksort, weak_heapsort,
+    };
+
+    type Sort = fn(&mut [i32]);
+
+    // [3, 2, 0, 5, 8, 9, 6, 3, 2, 0], but wrapped around the end of the
+    // ring buffer: the first four were pushed on the front.
+    fn wrapped() -> VecDeque<i32> {
+        let mut deque = VecDeque::with_capacity(10);
+        for &x in &[8, 9, 6, 3, 2, 0] {
+            deque.push_back(x);
+        }
+        for &x in &[5, 0, 2, 3] {
+            deque.push_front(x);
+        }
+        assert!(!deque.as_slices().1.is_empty());
+        deque
+    }
+
+    #[test]
+    fn in_place_sorts() {
+        let sorts: [(&str, Sort); 5] = [
+            ("insertion", insertion_sort),
+            ("quicksort", quicksort),
+            ("heapsort", heapsort),
+            ("weak-heapsort", weak_heapsort),
+            ("bitonic", bitonic_sort),
+        ];
+        for (name, sort) in sorts.iter() {
+            let mut deque = wrapped();
+            sort_deque(&mut deque, sort);
+            assert!(
+                deque.iter().eq(&[0, 0, 2, 2, 3, 3, 5, 6, 8, 9]),
+                "{}: {:?}",
+                name,
+                deque
+            );
+        }
+    }
+
+    #[test]
+    fn closures() {
+        let mut deque = wrapped();
+        let mut rng = SeededRandom::new(272);
+        sort_deque(&mut deque, |v| randomized_quicksort(v, &mut rng));
+        assert!(deque.iter().eq(&[0, 0, 2, 2, 3, 3, 5, 6, 8, 9]));
+    }
+
+    #[test]
+    fn merge_sort() {
+        let mut deque = wrapped();
+        let capacity = deque.capacity();
+        merge_sort_deque(&mut deque);
+        assert!(deque.iter().eq(&[0, 0, 2, 2, 3, 3, 5, 6, 8, 9]));
+        assert_eq!(capacity, deque.capacity());
+    }
+
+    #[test]
+    fn empty() {
+        let mut deque: VecDeque<i32> = VecDeque::new();
+        sort_deque(&mut deque, quicksort);
+        merge_sort_deque(&mut deque);
+        assert!(deque.is_empty());
+    }
+}
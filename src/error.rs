@@ -0,0 +1,111 @@
+// The errors the fallible (`try_*`) sorts, the benchmark, and the
+// multi-pass record sort can return.
+
+use std::error::Error;
+use std::fmt;
+
+// The recursion got deeper than `SortConfig::max_depth` allows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DepthExceeded {
+    pub max_depth: usize,
+}
+
+impl fmt::Display for DepthExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "sort recursion exceeded the maximum depth of {}", self.max_depth)
+    }
+}
+
+impl Error for DepthExceeded {}
+
+// The sort tried to compare the elements at these two indices (of the
+// original input), and `partial_cmp` said they couldn't be compared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OrderViolation {
+    pub first: usize,
+    pub second: usize,
+}
+
+impl fmt::Display for OrderViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "the elements at indices {} and {} can't be compared",
+            self.first, self.second
+        )
+    }
+}
+
+impl Error for OrderViolation {}
+
+// Running the benchmark would need more memory than `--max-memory`
+// allows. `keep_inputs` says whether the inputs were going to be kept
+// in memory, since not doing that is the first thing to try.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryLimitExceeded {
+    pub needed: usize,
+    pub max_memory: usize,
+    pub keep_inputs: bool,
+}
+
+impl fmt::Display for MemoryLimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "the benchmark would need about {} bytes, but the limit is {} bytes; ",
+            self.needed, self.max_memory
+        )?;
+        if self.keep_inputs {
+            write!(f, "drop --keep-inputs so each input is regenerated from its seed instead of kept in memory")
+        } else {
+            write!(f, "try smaller --sizes or fewer --jobs")
+        }
+    }
+}
+
+impl Error for MemoryLimitExceeded {}
+
+// Two algorithms in the benchmark were supposed to sort the same input,
+// but were given different ones (their checksums differ), so their
+// timings can't be compared. That's a bug in the benchmark itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InputsDiffer {
+    pub size: usize,
+    pub input: &'static str,
+    pub trial: usize,
+    pub first: &'static str,
+    pub second: &'static str,
+}
+
+impl fmt::Display for InputsDiffer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} and {} were given different {} inputs of {} values in trial {}, \
+             so their timings can't be compared",
+            self.first, self.second, self.input, self.size, self.trial
+        )
+    }
+}
+
+impl Error for InputsDiffer {}
+
+// `sort_by_keys_in_passes` was asked to use an algorithm that doesn't
+// keep equal elements in order, so each pass would scramble the order
+// the earlier passes set up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnstableAlgorithm {
+    pub algorithm: &'static str,
+}
+
+impl fmt::Display for UnstableAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} isn't stable, so it can't be used to sort in passes; try insertion or merge",
+            self.algorithm
+        )
+    }
+}
+
+impl Error for UnstableAlgorithm {}
@@ -0,0 +1,214 @@
+// Sorting values that might not be there. Parse a column of numbers and
+// you get a `Vec<Option<f64>>` or a `Vec<Result<i32, ParseIntError>>`,
+// and usually you want the good values in order with the failures out
+// of the way at one end, rather than having to filter them out first.
+//
+// Rust already orders `Option`s (`None` before any `Some`) and
+// `Result`s (every `Ok` before any `Err`), but not always the way we
+// want: there's no way to put the `None`s last, and `Err`s are compared
+// with each other, so the error type has to be comparable and the
+// errors get shuffled about. So, as with `Counted` in `instrument.rs`,
+// we wrap each value in a type whose `PartialOrd` does what we want,
+// and any of our sorts can sort the wrapped values:
+//    let mut v = options_last(&parsed);
+//    quicksort(&mut v);
+
+use std::cmp::Ordering;
+
+// `None` before every `Some` (which is `Option`'s own order).
+#[derive(Debug, Clone, Copy)]
+pub struct OptionsFirst<T>(pub Option<T>);
+
+// `None` after every `Some`.
+#[derive(Debug, Clone, Copy)]
+pub struct OptionsLast<T>(pub Option<T>);
+
+// Every `Ok` (in order) before every `Err`. The errors all compare equal,
+// so the error type needn't be comparable, and a stable sort keeps them
+// in the order they came in.
+#[derive(Debug, Clone, Copy)]
+pub struct OksBeforeErrs<T, E>(pub Result<T, E>);
+
+// Wrap every element of a slice.
+pub fn options_first<T: Clone>(v: &[Option<T>]) -> Vec<OptionsFirst<T>> {
+    v.iter().cloned().map(OptionsFirst).collect()
+}
+
+pub fn options_last<T: Clone>(v: &[Option<T>]) -> Vec<OptionsLast<T>> {
+    v.iter().cloned().map(OptionsLast).collect()
+}
+
+pub fn oks_before_errs<T: Clone, E: Clone>(v: &[Result<T, E>]) -> Vec<OksBeforeErrs<T, E>> {
+    v.iter().cloned().map(OksBeforeErrs).collect()
+}
+
+// Compare two options, with `None` on the side `none` says.
+fn compare_options<T: PartialOrd>(
+    a: &Option<T>,
+    b: &Option<T>,
+    none: Ordering,
+) -> Option<Ordering> {
+    match (a, b) {
+        (Some(a), Some(b)) => a.partial_cmp(b),
+        (None, None) => Some(Ordering::Equal),
+        (None, Some(_)) => Some(none),
+        (Some(_), None) => Some(none.reverse()),
+    }
+}
+
+impl<T: PartialOrd> PartialEq for OptionsFirst<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.partial_cmp(other) == Some(Ordering::Equal)
+    }
+}
+
+impl<T: PartialOrd> PartialOrd for OptionsFirst<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        compare_options(&self.0, &other.0, Ordering::Less)
+    }
+}
+
+impl<T: PartialOrd> PartialEq for OptionsLast<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.partial_cmp(other) == Some(Ordering::Equal)
+    }
+}
+
+impl<T: PartialOrd> PartialOrd for OptionsLast<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        compare_options(&self.0, &other.0, Ordering::Greater)
+    }
+}
+
+impl<T: PartialOrd, E> PartialEq for OksBeforeErrs<T, E> {
+    fn eq(&self, other: &Self) -> bool {
+        self.partial_cmp(other) == Some(Ordering::Equal)
+    }
+}
+
+impl<T: PartialOrd, E> PartialOrd for OksBeforeErrs<T, E> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match (&self.0, &other.0) {
+            (Ok(a), Ok(b)) => a.partial_cmp(b),
+            (Err(_), Err(_)) => Some(Ordering::Equal),
+            (Ok(_), Err(_)) => Some(Ordering::Less),
+            (Err(_), Ok(_)) => Some(Ordering::Greater),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::comparator::validate_comparator;
+    use crate::sorts::{heapsort, insertion_sort, merge_sort, quicksort};
+    use std::num::{IntErrorKind, ParseIntError};
+
+    const TEN: [Option<i32>; 10] = [
+        Some(3),
+        None,
+        Some(0),
+        Some(5),
+        None,
+        Some(9),
+        Some(6),
+        Some(3),
+        None,
+        Some(0),
+    ];
+
+    #[test]
+    fn nones_first() {
+        let mut v = options_first(&TEN);
+        quicksort(&mut v);
+        let sorted: Vec<Option<i32>> = v.into_iter().map(|o| o.0).collect();
+        assert_eq!(
+            vec![
+                None,
+                None,
+                None,
+                Some(0),
+                Some(0),
+                Some(3),
+                Some(3),
+                Some(5),
+                Some(6),
+                Some(9)
+            ],
+            sorted
+        );
+    }
+
+    #[test]
+    fn nones_last() {
+        let mut v = options_last(&TEN);
+        heapsort(&mut v);
+        let sorted: Vec<Option<i32>> = v.into_iter().map(|o| o.0).collect();
+        assert_eq!(
+            vec![
+                Some(0),
+                Some(0),
+                Some(3),
+                Some(3),
+                Some(5),
+                Some(6),
+                Some(9),
+                None,
+                None,
+                None
+            ],
+            sorted
+        );
+    }
+
+    #[test]
+    fn parse_results() {
+        let parsed: Vec<Result<i32, ParseIntError>> = ["12", "x", "-4", "", "7", "y"]
+            .iter()
+            .map(|s| s.parse())
+            .collect();
+        let mut v = oks_before_errs(&parsed);
+        // Insertion sort is stable, so the errors stay in their order.
+        insertion_sort(&mut v);
+        let oks: Vec<i32> = v
+            .iter()
+            .filter_map(|r| r.0.as_ref().ok().copied())
+            .collect();
+        assert_eq!(vec![-4, 7, 12], oks);
+        let errors: Vec<IntErrorKind> = v[3..]
+            .iter()
+            .map(|r| *r.0.as_ref().unwrap_err().kind())
+            .collect();
+        // "x", "", "y".
+        assert_eq!(
+            vec![
+                IntErrorKind::InvalidDigit,
+                IntErrorKind::Empty,
+                IntErrorKind::InvalidDigit
+            ],
+            errors
+        );
+    }
+
+    #[test]
+    fn errors_need_not_be_comparable() {
+        #[derive(Debug, Clone, Copy)]
+        struct Opaque;
+        let v: Vec<Result<f64, Opaque>> = vec![Err(Opaque), Ok(2.5), Ok(-1.0), Err(Opaque)];
+        let sorted = merge_sort(&oks_before_errs(&v));
+        assert_eq!(Ok(-1.0), sorted[0].0.map_err(|_| ()));
+        assert_eq!(Ok(2.5), sorted[1].0.map_err(|_| ()));
+        assert!(sorted[2].0.is_err() && sorted[3].0.is_err());
+    }
+
+    #[test]
+    fn consistent_comparators() {
+        fn cmp<T: PartialOrd>(a: &T, b: &T) -> Ordering {
+            a.partial_cmp(b).unwrap()
+        }
+        assert!(validate_comparator(&options_first(&TEN), cmp).is_empty());
+        assert!(validate_comparator(&options_last(&TEN), cmp).is_empty());
+        let results: Vec<Result<i32, ()>> = TEN.iter().map(|o| o.ok_or(())).collect();
+        assert!(validate_comparator(&oks_before_errs(&results), cmp).is_empty());
+    }
+}
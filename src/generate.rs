@@ -0,0 +1,302 @@
+// Generating data to sort.
+
+use crate::random::RandomSource;
+
+// `len` random numbers in the range min..max.
+pub fn generate_random_array<R: RandomSource>(
+    len: i32,
+    min: i32,
+    max: i32,
+    rng: &mut R,
+) -> Vec<i32> {
+    let mut v = Vec::new();
+    for _i in 0..len {
+        v.push(rng.range(min, max));
+    }
+    // Rust returns the last expression in a function, so
+    // this is equivalent to `return v`.
+    v
+}
+
+// Skewed data. Real data is rarely uniform: measurements pile up around
+// an average, waiting times are mostly short with a long tail, and a
+// few words (or customers, or URLs) account for most of the traffic.
+// These are rounded to whole numbers, which is where the duplicates
+// come from: the narrower the spread, the more of them there are, and
+// the more it matters how a sort handles values equal to the pivot.
+
+// Normally distributed values with the given mean and standard
+// deviation, rounded to the nearest whole number (and clamped to fit in
+// an `i32`).
+pub fn generate_gaussian<R: RandomSource>(
+    len: usize,
+    mean: f64,
+    stddev: f64,
+    rng: &mut R,
+) -> Vec<i32> {
+    assert!(stddev >= 0.0, "the standard deviation can't be negative");
+    (0..len)
+        .map(|_| to_i32(mean + stddev * standard_normal(rng)))
+        .collect()
+}
+
+// Exponentially distributed values with rate `lambda` (so a mean of
+// 1 / lambda), rounded down: lots of 0s, fewer 1s, fewer 2s still...
+pub fn generate_exponential<R: RandomSource>(len: usize, lambda: f64, rng: &mut R) -> Vec<i32> {
+    assert!(lambda > 0.0, "the rate has to be positive");
+    (0..len)
+        .map(|_| to_i32((-(1.0 - f64::random(rng)).ln() / lambda).floor()))
+        .collect()
+}
+
+// Values from 1 to n, where k turns up in proportion to 1 / k^exponent,
+// so 1 is the most common, 2 the next, and so on. An exponent of 0 is
+// uniform; around 1 (Zipf's law) is how often words turn up in English.
+pub fn generate_zipf<R: RandomSource>(
+    len: usize,
+    n: usize,
+    exponent: f64,
+    rng: &mut R,
+) -> Vec<i32> {
+    assert!(
+        n > 0 && n <= i32::MAX as usize,
+        "need between 1 and i32::MAX values to choose from"
+    );
+    // cumulative[k - 1] is the total weight of 1 through k; we pick a
+    // random point in the total and look up whose weight it's in.
+    let mut total = 0.0;
+    let cumulative: Vec<f64> = (1..=n)
+        .map(|k| {
+            total += (k as f64).powf(-exponent);
+            total
+        })
+        .collect();
+    (0..len)
+        .map(|_| {
+            let point = f64::random(rng) * total;
+            let k = cumulative.partition_point(|&c| c <= point).min(n - 1);
+            k as i32 + 1
+        })
+        .collect()
+}
+
+// A standard normal value, by the Box-Muller transform.
+fn standard_normal<R: RandomSource>(rng: &mut R) -> f64 {
+    // 1 - u is in (0, 1], so its log is finite.
+    let u = 1.0 - f64::random(rng);
+    let v = f64::random(rng);
+    (-2.0 * u.ln()).sqrt() * (2.0 * std::f64::consts::PI * v).cos()
+}
+
+fn to_i32(x: f64) -> i32 {
+    // `as` saturates, so anything too big or small ends up at the ends.
+    x.round() as i32
+}
+
+// Anything we can make a random one of, so that we can try the sorts on
+// more than `i32`s. The integers are uniform over all their values,
+// floats are uniform in [0, 1), strings are up to 10 lowercase letters,
+// and tuples are random in each part. For a type of your own, implement
+// this by building one out of random parts:
+//    impl RandomValue for Student {
+//        fn random<R: RandomSource>(rng: &mut R) -> Student {
+//            Student { name: String::random(rng), grade: rng.range(0, 101) }
+//        }
+//    }
+pub trait RandomValue: Sized {
+    fn random<R: RandomSource>(rng: &mut R) -> Self;
+}
+
+// `len` random values of any type we can make.
+pub fn generate_random<T: RandomValue, R: RandomSource>(len: usize, rng: &mut R) -> Vec<T> {
+    (0..len).map(|_| T::random(rng)).collect()
+}
+
+// Keeping the low bits of a random `u64` is still uniform.
+macro_rules! random_integer {
+    ($($t:ty),*) => {
+        $(
+            impl RandomValue for $t {
+                fn random<R: RandomSource>(rng: &mut R) -> $t {
+                    rng.next_u64() as $t
+                }
+            }
+        )*
+    };
+}
+
+random_integer!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize);
+
+impl RandomValue for bool {
+    fn random<R: RandomSource>(rng: &mut R) -> bool {
+        rng.next_u64() & 1 == 1
+    }
+}
+
+impl RandomValue for f64 {
+    fn random<R: RandomSource>(rng: &mut R) -> f64 {
+        // 53 random bits, which is all an `f64` holds, as a multiple of
+        // 2^-53.
+        (rng.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+impl RandomValue for f32 {
+    fn random<R: RandomSource>(rng: &mut R) -> f32 {
+        (rng.next_u64() >> 40) as f32 / (1u32 << 24) as f32
+    }
+}
+
+impl RandomValue for String {
+    fn random<R: RandomSource>(rng: &mut R) -> String {
+        let len = rng.below(11);
+        (0..len)
+            .map(|_| (b'a' + rng.below(26) as u8) as char)
+            .collect()
+    }
+}
+
+impl<A: RandomValue, B: RandomValue> RandomValue for (A, B) {
+    fn random<R: RandomSource>(rng: &mut R) -> (A, B) {
+        let a = A::random(rng);
+        (a, B::random(rng))
+    }
+}
+
+impl<A: RandomValue, B: RandomValue, C: RandomValue> RandomValue for (A, B, C) {
+    fn random<R: RandomSource>(rng: &mut R) -> (A, B, C) {
+        let a = A::random(rng);
+        let b = B::random(rng);
+        (a, b, C::random(rng))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::random::{ScriptedRandom, SeededRandom};
+
+    #[test]
+    fn in_range() {
+        let v = generate_random_array(1000, 10, 20, &mut SeededRandom::new(217));
+        assert_eq!(1000, v.len());
+        assert!(v.iter().all(|x| (10..20).contains(x)));
+    }
+
+    #[test]
+    fn reproducible() {
+        let a = generate_random_array(100, 0, 100, &mut SeededRandom::new(217));
+        let b = generate_random_array(100, 0, 100, &mut SeededRandom::new(217));
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn scripted() {
+        let v = generate_random_array(4, 0, 10, &mut ScriptedRandom::new(vec![3, 12, 7]));
+        assert_eq!(vec![3, 2, 7, 3], v);
+    }
+
+    fn mean(v: &[i32]) -> f64 {
+        v.iter().map(|&x| x as f64).sum::<f64>() / v.len() as f64
+    }
+
+    #[test]
+    fn gaussian() {
+        let v = generate_gaussian(100_000, 50.0, 10.0, &mut SeededRandom::new(507));
+        let m = mean(&v);
+        let variance = v.iter().map(|&x| (x as f64 - m).powi(2)).sum::<f64>() / v.len() as f64;
+        assert!((m - 50.0).abs() < 0.2, "mean {}", m);
+        // Rounding adds 1/12 to the variance of 100.
+        assert!(
+            (variance.sqrt() - 10.0).abs() < 0.2,
+            "stddev {}",
+            variance.sqrt()
+        );
+        // About 68% within one standard deviation.
+        let within = v.iter().filter(|&&x| (40..=60).contains(&x)).count();
+        assert!((70_000..75_000).contains(&within), "{}", within);
+        assert_eq!(
+            vec![7; 5],
+            generate_gaussian(5, 7.0, 0.0, &mut SeededRandom::new(507))
+        );
+    }
+
+    #[test]
+    fn exponential() {
+        let v = generate_exponential(100_000, 0.1, &mut SeededRandom::new(507));
+        assert!(v.iter().all(|&x| x >= 0));
+        // The mean of the continuous values is 10; rounding down takes
+        // off about a half.
+        assert!((mean(&v) - 9.5).abs() < 0.2, "mean {}", mean(&v));
+        let zeros = v.iter().filter(|&&x| x == 0).count();
+        let tens = v.iter().filter(|&&x| x == 10).count();
+        assert!(zeros > 2 * tens);
+    }
+
+    #[test]
+    fn zipf() {
+        let v = generate_zipf(100_000, 100, 1.0, &mut SeededRandom::new(507));
+        assert!(v.iter().all(|&x| (1..=100).contains(&x)));
+        let count = |k| v.iter().filter(|&&x| x == k).count() as f64;
+        // 1 turns up about twice as often as 2 and 10 times as often as
+        // 10, and 1 / H(100) ≈ 19% of the time.
+        assert!((count(1) / count(2) - 2.0).abs() < 0.15);
+        assert!((count(1) / count(10) - 10.0).abs() < 1.5);
+        assert!((count(1) / 100_000.0 - 0.193).abs() < 0.01);
+        // With an exponent of 0, every value's equally likely.
+        let flat = generate_zipf(100_000, 4, 0.0, &mut SeededRandom::new(507));
+        for k in 1..=4 {
+            let n = flat.iter().filter(|&&x| x == k).count();
+            assert!((24_000..26_000).contains(&n), "{}: {}", k, n);
+        }
+    }
+
+    #[test]
+    fn other_types() {
+        let mut rng = SeededRandom::new(505);
+        let floats: Vec<f64> = generate_random(1000, &mut rng);
+        assert!(floats.iter().all(|x| (0.0..1.0).contains(x)));
+        let strings: Vec<String> = generate_random(1000, &mut rng);
+        assert!(strings
+            .iter()
+            .all(|s| s.len() <= 10 && s.chars().all(|c| c.is_ascii_lowercase())));
+        let small: Vec<u8> = generate_random(1000, &mut rng);
+        assert!(small.iter().any(|&x| x > 200) && small.iter().any(|&x| x < 50));
+        let a: Vec<(u64, String)> = generate_random(100, &mut SeededRandom::new(505));
+        let b: Vec<(u64, String)> = generate_random(100, &mut SeededRandom::new(505));
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn scripted_values() {
+        let mut rng = ScriptedRandom::new(vec![3, 0, 1, 2, u64::MAX, u64::MAX, 0]);
+        // A length of 3, then the letters 0, 1, and 2.
+        assert_eq!("abc", String::random(&mut rng));
+        assert_eq!(u64::MAX, u64::random(&mut rng));
+        assert_eq!(-1, i8::random(&mut rng));
+        assert_eq!(0.0, f64::random(&mut rng));
+    }
+
+    #[test]
+    fn sorting_a_type_of_our_own() {
+        use crate::sorts::quicksort;
+        #[derive(Debug, Clone, PartialEq, PartialOrd)]
+        struct Student {
+            grade: i32,
+            name: String,
+        }
+        impl RandomValue for Student {
+            fn random<R: RandomSource>(rng: &mut R) -> Student {
+                let grade = rng.range(0, 101);
+                Student {
+                    grade,
+                    name: String::random(rng),
+                }
+            }
+        }
+        let mut v: Vec<Student> = generate_random(200, &mut SeededRandom::new(505));
+        quicksort(&mut v);
+        assert!(v.windows(2).all(|w| w[0] <= w[1]));
+        assert!(v.iter().all(|s| (0..=100).contains(&s.grade)));
+    }
+}
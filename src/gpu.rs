@@ -0,0 +1,426 @@
+// Bitonic sort on the GPU, with wgpu compute shaders.
+//
+// `sorts/bitonic.rs` explains the network: a fixed sequence of passes,
+// indexed by (k, j), in each of which element i is compared with element
+// i ^ j. On the CPU we run the pairs of a pass one after another; here
+// each pass is one compute dispatch with a thread per element, so all
+// the compare-and-swaps of a pass happen at once. The passes themselves
+// still have to run in order, but they all go into one command buffer,
+// so we only wait for the GPU once, when we read the result back.
+//
+// The shader only knows how to sort `u32`s. `f32`s are sorted by
+// turning each one into a `u32` whose unsigned order is the float's
+// order (see `f32_key`), and back again afterwards.
+//
+// This module is only available with the `gpu` feature:
+//    cargo test --features gpu gpu
+//    cargo bench --features gpu --bench gpu
+// It works with whatever adapter wgpu finds, including a software one
+// like Mesa's llvmpipe, which is useful for testing but, being a CPU,
+// isn't fast.
+
+use crate::sorts::bitonic_passes;
+use std::borrow::Cow;
+use std::sync::mpsc;
+use wgpu::util::DeviceExt;
+
+// One thread per element, in workgroups of this many threads. It has to
+// match `@workgroup_size` in the shader.
+const WORKGROUP_SIZE: u32 = 256;
+
+// One pass of the network. Each thread looks at one element, and the
+// lower index of each pair does the compare-and-swap. Real GPUs limit a
+// dispatch to 65,535 workgroups in each dimension, so big arrays are
+// dispatched as a grid of rows, and the thread's index is its position
+// in that grid.
+const SHADER: &str = "
+struct Pass {
+    k: u32,
+    j: u32,
+}
+
+@group(0) @binding(0) var<storage, read_write> data: array<u32>;
+@group(0) @binding(1) var<uniform> pass_: Pass;
+
+@compute @workgroup_size(256)
+fn main(
+    @builtin(global_invocation_id) id: vec3<u32>,
+    @builtin(num_workgroups) groups: vec3<u32>,
+) {
+    let i = id.y * groups.x * 256u + id.x;
+    let partner = i ^ pass_.j;
+    if (i >= arrayLength(&data) || partner <= i) {
+        return;
+    }
+    let a = data[i];
+    let b = data[partner];
+    let ascending = (i & pass_.k) == 0u;
+    if ((a > b) == ascending) {
+        data[i] = b;
+        data[partner] = a;
+    }
+}
+";
+
+// A GPU, ready to sort on. Setting up the device and compiling the
+// shader takes a while, so make one and sort with it many times.
+pub struct GpuSorter {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    pipeline: wgpu::ComputePipeline,
+    layout: wgpu::BindGroupLayout,
+    adapter: String,
+}
+
+impl GpuSorter {
+    // Use the first adapter wgpu offers, or say why we can't.
+    pub fn new() -> Result<GpuSorter, String> {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
+        let adapter =
+            pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions::default()))
+                .map_err(|e| format!("no GPU adapter: {}", e))?;
+        // Ask for everything the adapter can do, in particular the
+        // biggest storage buffers, since that's what limits how much we
+        // can sort.
+        let (device, queue) = pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor {
+            label: Some("sorting-in-rust"),
+            required_limits: adapter.limits(),
+            ..Default::default()
+        }))
+        .map_err(|e| format!("can't open the GPU: {}", e))?;
+
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("bitonic pass"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(SHADER)),
+        });
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("bitonic pass"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: false },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                // Every pass's (k, j) is in the same buffer, and each
+                // dispatch points at its own with a dynamic offset.
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: true,
+                        min_binding_size: wgpu::BufferSize::new(8),
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("bitonic pass"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("bitonic pass"),
+            layout: Some(&pipeline_layout),
+            module: &module,
+            entry_point: Some("main"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+        let info = adapter.get_info();
+        Ok(GpuSorter {
+            device,
+            queue,
+            pipeline,
+            layout,
+            adapter: format!("{} ({:?})", info.name, info.backend),
+        })
+    }
+
+    // The adapter's name and which API we're using it through.
+    pub fn adapter(&self) -> &str {
+        &self.adapter
+    }
+
+    // The most elements we can sort: the padded array has to fit in one
+    // storage buffer.
+    pub fn max_len(&self) -> usize {
+        let bytes = self.device.limits().max_storage_buffer_binding_size as usize;
+        // The biggest power of two that fits.
+        let elements = bytes / 4;
+        if elements == 0 {
+            0
+        } else {
+            1 << (usize::BITS - 1 - elements.leading_zeros())
+        }
+    }
+
+    pub fn sort_u32(&self, v: &mut [u32]) -> Result<(), String> {
+        if v.len() < 2 {
+            return Ok(());
+        }
+        if v.len() > self.max_len() {
+            return Err(format!(
+                "{} elements is more than this GPU can sort at once ({})",
+                v.len(),
+                self.max_len()
+            ));
+        }
+        // Pad to a power of two with the largest value, which sorts to
+        // the end where we can drop it.
+        let size = v.len().next_power_of_two();
+        let mut padded = v.to_vec();
+        padded.resize(size, u32::MAX);
+        let sorted = self.run_network(&padded)?;
+        v.copy_from_slice(&sorted[..v.len()]);
+        Ok(())
+    }
+
+    // Sorts in the order of `f32::total_cmp`, which agrees with `<` on
+    // everything but NaN (and puts -0.0 before 0.0). NaNs go to the end,
+    // or to the start if their sign bit is set.
+    pub fn sort_f32(&self, v: &mut [f32]) -> Result<(), String> {
+        let mut keys: Vec<u32> = v.iter().map(|&x| f32_key(x)).collect();
+        self.sort_u32(&mut keys)?;
+        for (x, key) in v.iter_mut().zip(keys) {
+            *x = f32_from_key(key);
+        }
+        Ok(())
+    }
+
+    // Run every pass of the network on `data`, whose length is a power
+    // of two, and read back the result.
+    fn run_network(&self, data: &[u32]) -> Result<Vec<u32>, String> {
+        let bytes = (data.len() * 4) as wgpu::BufferAddress;
+        let storage = self
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("data"),
+                contents: &to_bytes(data),
+                usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            });
+        let readback = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("readback"),
+            size: bytes,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        // Each pass's (k, j), spaced out to the alignment dynamic offsets
+        // need.
+        let passes = bitonic_passes(data.len());
+        let stride = self.device.limits().min_uniform_buffer_offset_alignment as usize;
+        let mut uniforms = vec![0u8; passes.len() * stride];
+        for (n, &(k, j)) in passes.iter().enumerate() {
+            let at = n * stride;
+            uniforms[at..at + 4].copy_from_slice(&(k as u32).to_ne_bytes());
+            uniforms[at + 4..at + 8].copy_from_slice(&(j as u32).to_ne_bytes());
+        }
+        let uniforms = self
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("passes"),
+                contents: &uniforms,
+                usage: wgpu::BufferUsages::UNIFORM,
+            });
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("bitonic pass"),
+            layout: &self.layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: storage.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                        buffer: &uniforms,
+                        offset: 0,
+                        size: wgpu::BufferSize::new(8),
+                    }),
+                },
+            ],
+        });
+
+        let groups = (data.len() as u32).div_ceil(WORKGROUP_SIZE);
+        let max = self.device.limits().max_compute_workgroups_per_dimension;
+        let (x, y) = if groups <= max {
+            (groups, 1)
+        } else {
+            (max, groups.div_ceil(max))
+        };
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        {
+            // Dispatches in the same compute pass see each other's
+            // writes, so the passes of the network run in order.
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor::default());
+            pass.set_pipeline(&self.pipeline);
+            for n in 0..passes.len() {
+                pass.set_bind_group(0, &bind_group, &[(n * stride) as u32]);
+                pass.dispatch_workgroups(x, y, 1);
+            }
+        }
+        encoder.copy_buffer_to_buffer(&storage, 0, &readback, 0, bytes);
+        self.queue.submit(Some(encoder.finish()));
+
+        let slice = readback.slice(..);
+        let (sender, receiver) = mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            // If we've stopped listening there's nobody to tell.
+            let _ = sender.send(result);
+        });
+        self.device
+            .poll(wgpu::PollType::Wait)
+            .map_err(|e| format!("waiting for the GPU: {}", e))?;
+        receiver
+            .recv()
+            .map_err(|e| e.to_string())?
+            .map_err(|e| format!("reading back from the GPU: {}", e))?;
+        let result = from_bytes(&slice.get_mapped_range());
+        readback.unmap();
+        Ok(result)
+    }
+}
+
+// Sort a copy of `v` on the GPU and check that it comes out the same as
+// sorting it with the CPU version of the same network, `bitonic_sort`.
+pub fn verify_u32(gpu: &GpuSorter, v: &[u32]) -> Result<(), String> {
+    let mut on_gpu = v.to_vec();
+    gpu.sort_u32(&mut on_gpu)?;
+    let mut on_cpu = v.to_vec();
+    crate::sorts::bitonic_sort(&mut on_cpu);
+    match on_gpu.iter().zip(&on_cpu).position(|(a, b)| a != b) {
+        None => Ok(()),
+        Some(i) => Err(format!(
+            "the GPU put {} at index {}, where the CPU put {}",
+            on_gpu[i], i, on_cpu[i]
+        )),
+    }
+}
+
+// A `u32` that sorts the way `x` does. Floats are sign-and-magnitude:
+// for positive ones (sign bit clear) the bits already sort as unsigned
+// integers, so setting the sign bit puts them above all the negative
+// ones. Negative ones sort backwards (a bigger magnitude is smaller), so
+// flipping all their bits both clears the sign bit and reverses them.
+pub fn f32_key(x: f32) -> u32 {
+    let bits = x.to_bits();
+    if bits >> 31 == 0 {
+        bits | 1 << 31
+    } else {
+        !bits
+    }
+}
+
+pub fn f32_from_key(key: u32) -> f32 {
+    if key >> 31 == 1 {
+        f32::from_bits(key & !(1 << 31))
+    } else {
+        f32::from_bits(!key)
+    }
+}
+
+fn to_bytes(v: &[u32]) -> Vec<u8> {
+    v.iter().flat_map(|x| x.to_ne_bytes()).collect()
+}
+
+fn from_bytes(bytes: &[u8]) -> Vec<u32> {
+    bytes
+        .chunks_exact(4)
+        .map(|b| u32::from_ne_bytes([b[0], b[1], b[2], b[3]]))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::random::{RandomSource, SeededRandom};
+
+    // Tests that need a GPU pass (and say so) if there isn't one.
+    fn gpu() -> Option<GpuSorter> {
+        match GpuSorter::new() {
+            Ok(gpu) => Some(gpu),
+            Err(e) => {
+                eprintln!("skipping: {}", e);
+                None
+            }
+        }
+    }
+
+    #[test]
+    fn float_keys_keep_the_order() {
+        let floats = [
+            f32::NEG_INFINITY,
+            -1e30,
+            -1.0,
+            -f32::MIN_POSITIVE,
+            -0.0,
+            0.0,
+            f32::MIN_POSITIVE,
+            1.0,
+            1e30,
+            f32::INFINITY,
+        ];
+        for pair in floats.windows(2) {
+            assert!(f32_key(pair[0]) < f32_key(pair[1]), "{:?}", pair);
+        }
+        for &x in &floats {
+            assert_eq!(x.to_bits(), f32_from_key(f32_key(x)).to_bits());
+        }
+    }
+
+    #[test]
+    fn ten_items() {
+        if let Some(gpu) = gpu() {
+            let mut input = [3, 2, 0, 5, 8, 9, 6, 3, 2, 0];
+            gpu.sort_u32(&mut input).unwrap();
+            assert_eq!([0, 0, 2, 2, 3, 3, 5, 6, 8, 9], input);
+            let mut empty: [u32; 0] = [];
+            gpu.sort_u32(&mut empty).unwrap();
+        }
+    }
+
+    #[test]
+    fn matches_the_cpu() {
+        if let Some(gpu) = gpu() {
+            let mut rng = SeededRandom::new(234);
+            // Powers of two and not, and big enough to need more than
+            // one workgroup.
+            for &len in &[1, 2, 3, 255, 256, 257, 1000, 1 << 16, 100_000] {
+                let v: Vec<u32> = (0..len).map(|_| rng.next_u64() as u32).collect();
+                verify_u32(&gpu, &v).unwrap();
+                let mut sorted = v.clone();
+                gpu.sort_u32(&mut sorted).unwrap();
+                let mut expected = v;
+                expected.sort_unstable();
+                assert_eq!(expected, sorted, "{} elements", len);
+            }
+            // Padding with u32::MAX mustn't get mixed up with real ones.
+            verify_u32(&gpu, &[u32::MAX, 0, u32::MAX, 7, 1]).unwrap();
+        }
+    }
+
+    #[test]
+    fn floats() {
+        if let Some(gpu) = gpu() {
+            let mut rng = SeededRandom::new(234);
+            let mut v: Vec<f32> = (0..10_000)
+                .map(|_| (rng.next_u64() as i32) as f32 / 1000.0)
+                .collect();
+            v.extend(&[f32::INFINITY, f32::NEG_INFINITY, 0.0, -0.0, f32::NAN]);
+            let mut expected = v.clone();
+            expected.sort_by(|a, b| a.total_cmp(b));
+            gpu.sort_f32(&mut v).unwrap();
+            let bits = |v: &[f32]| v.iter().map(|x| x.to_bits()).collect::<Vec<u32>>();
+            assert_eq!(bits(&expected), bits(&v));
+        }
+    }
+}
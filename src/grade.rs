@@ -0,0 +1,528 @@
+// Automatic grading for the lab: run the student implementations of
+// `quicksort`, `merge`, and `merge_sort` through a battery of checks and
+// produce a score report a grading script can read.
+//
+// Each check compares the student's output against a "hidden" reference
+// answer (from the standard library's stable sort), rather than just
+// checking the output is in order, so a sort that loses or duplicates
+// elements can't pass. The checks are:
+//   * correctness on a handful of small and tricky inputs, and on random
+//     inputs of a few sizes;
+//   * that the output is a permutation of the input (every element comes
+//     out exactly once);
+//   * stability, for the algorithms that claim to be stable; and
+//   * a sanity check on the number of comparisons, which catches, say, a
+//     "merge sort" that's really O(N^2).
+// A check that panics just fails.
+
+use crate::instrument::{count_comparisons, counted, uncounted, Counted};
+use crate::json::quote;
+use crate::mutants::{merge, merge_sort, quicksort, with_bugs, Bug, ALL_BUGS};
+use crate::random::{RandomSource, SeededRandom};
+use std::cmp::Ordering;
+use std::fmt::Write;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+// An element with a key to sort on, and a tag recording where it was in
+// the input. Only the key is compared, so the tags let us see whether
+// equal keys stayed in their original order.
+#[derive(Debug, Clone, Copy)]
+pub struct Tagged {
+    pub key: i32,
+    pub tag: usize,
+}
+
+impl PartialEq for Tagged {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+
+impl PartialOrd for Tagged {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.key.partial_cmp(&other.key)
+    }
+}
+
+// Something to grade.
+#[derive(Clone, Copy)]
+pub struct Subject {
+    pub name: &'static str,
+    // Whether it promises to keep equal elements in order.
+    pub stable: bool,
+    // Sort the input (however it likes) and return the result.
+    pub run: fn(&[Counted<Tagged>]) -> Vec<Counted<Tagged>>,
+    // The most comparisons it should need for a random input of this
+    // size.
+    pub max_comparisons: fn(usize) -> u64,
+}
+
+impl std::fmt::Debug for Subject {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Subject({})", self.name)
+    }
+}
+
+// N log₂ N, rounded up.
+fn n_log_n(n: usize) -> u64 {
+    let log = (usize::BITS - n.saturating_sub(1).leading_zeros()) as u64;
+    n as u64 * log
+}
+
+// The lab's three functions. To grade `merge` on its own we sort each
+// half of the input with the reference sort and have `merge` put the
+// two halves together. We call them through `mutants.rs` so that bugs
+// can be injected; with no bugs turned on those are the real ones.
+pub fn subjects() -> Vec<Subject> {
+    vec![
+        Subject {
+            name: "quicksort",
+            stable: false,
+            run: |v| {
+                let mut v = v.to_vec();
+                quicksort(&mut v);
+                v
+            },
+            // On average quicksort makes about 1.39 N log₂ N.
+            max_comparisons: |n| 3 * n_log_n(n),
+        },
+        Subject {
+            name: "merge",
+            stable: true,
+            run: |v| {
+                let (xs, ys) = v.split_at(v.len() / 2);
+                merge(sort_uncounted(xs), sort_uncounted(ys))
+            },
+            // Galloping can cost a few extra comparisons.
+            max_comparisons: |n| 2 * n as u64,
+        },
+        Subject {
+            name: "merge_sort",
+            stable: true,
+            run: |v| merge_sort(v),
+            max_comparisons: |n| 2 * n_log_n(n),
+        },
+    ]
+}
+
+pub fn find_subject(name: &str) -> Option<Subject> {
+    subjects().into_iter().find(|s| s.name == name)
+}
+
+// Sort without counting the comparisons against the subject.
+fn sort_uncounted(v: &[Counted<Tagged>]) -> Vec<Counted<Tagged>> {
+    let mut sorted = v.to_vec();
+    sorted.sort_by_key(|t| t.0.key);
+    sorted
+}
+
+// The reference answer.
+fn reference_sort<T: PartialOrd + Clone>(v: &[T]) -> Vec<T> {
+    let mut sorted = v.to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    sorted
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheckResult {
+    pub name: String,
+    pub passed: bool,
+    // Why it failed (or empty if it passed).
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GradeReport {
+    pub subject: &'static str,
+    pub checks: Vec<CheckResult>,
+}
+
+impl GradeReport {
+    pub fn score(&self) -> usize {
+        self.checks.iter().filter(|c| c.passed).count()
+    }
+
+    pub fn out_of(&self) -> usize {
+        self.checks.len()
+    }
+}
+
+fn tag(keys: &[i32]) -> Vec<Tagged> {
+    keys.iter()
+        .enumerate()
+        .map(|(tag, &key)| Tagged { key, tag })
+        .collect()
+}
+
+fn random_keys(size: usize, max: i32, seed: u64) -> Vec<i32> {
+    let mut rng = SeededRandom::new(seed);
+    (0..size).map(|_| rng.range(0, max)).collect()
+}
+
+// Run the subject on `keys`, catching any panic, and return its output
+// along with how many comparisons it made.
+fn attempt(subject: &Subject, keys: &[i32]) -> Result<(Vec<Tagged>, u64), String> {
+    let input = counted(&tag(keys));
+    catch_unwind(AssertUnwindSafe(|| {
+        count_comparisons(|| (subject.run)(&input))
+    }))
+    .map(|(output, comparisons)| (uncounted(output), comparisons))
+    .map_err(|panic| {
+        let message = panic
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| panic.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown panic".to_string());
+        format!("panicked: {}", message)
+    })
+}
+
+// Whether `output` has the same keys as the reference answer.
+fn check_sorted(keys: &[i32], output: &[Tagged]) -> Result<(), String> {
+    let expected = reference_sort(keys);
+    let actual: Vec<i32> = output.iter().map(|t| t.key).collect();
+    if actual.len() != expected.len() {
+        return Err(format!(
+            "expected {} elements, got {}",
+            expected.len(),
+            actual.len()
+        ));
+    }
+    match actual.iter().zip(&expected).position(|(a, e)| a != e) {
+        Some(i) => Err(format!(
+            "position {} should be {}, not {}",
+            i, expected[i], actual[i]
+        )),
+        None => Ok(()),
+    }
+}
+
+// Whether every input element (by tag) comes out exactly once.
+fn check_permutation(keys: &[i32], output: &[Tagged]) -> Result<(), String> {
+    let mut seen = vec![0; keys.len()];
+    for t in output {
+        match seen.get_mut(t.tag) {
+            Some(count) => *count += 1,
+            None => return Err(format!("element with tag {} wasn't in the input", t.tag)),
+        }
+    }
+    match seen.iter().position(|&count| count != 1) {
+        Some(tag) => Err(format!(
+            "element {} (key {}) came out {} times",
+            tag, keys[tag], seen[tag]
+        )),
+        None => Ok(()),
+    }
+}
+
+// Whether equal keys came out in the same order they went in. For
+// `merge` the order they went in is the order after sorting each half,
+// which (with a stable reference sort) is the original order.
+fn check_stable(output: &[Tagged]) -> Result<(), String> {
+    match output
+        .windows(2)
+        .position(|w| w[0].key == w[1].key && w[0].tag > w[1].tag)
+    {
+        Some(i) => Err(format!(
+            "equal keys {} at positions {} and {} are out of order",
+            output[i].key,
+            i,
+            i + 1
+        )),
+        None => Ok(()),
+    }
+}
+
+pub fn grade(subject: &Subject) -> GradeReport {
+    let mut checks = Vec::new();
+    let mut check = |name: &str, result: Result<(), String>| {
+        checks.push(CheckResult {
+            name: name.to_string(),
+            passed: result.is_ok(),
+            detail: result.err().unwrap_or_default(),
+        })
+    };
+
+    let fixed: Vec<(&str, Vec<i32>)> = vec![
+        ("empty", vec![]),
+        ("one-item", vec![7]),
+        ("ten-items", vec![3, 2, 0, 5, 8, 9, 6, 3, 2, 0]),
+        ("presorted", (0..100).collect()),
+        ("reversed", (0..100).rev().collect()),
+        ("all-equal", vec![4; 100]),
+        ("negatives", vec![-3, 5, i32::MIN, 0, i32::MAX, -1]),
+    ];
+    for (name, keys) in &fixed {
+        check(
+            name,
+            attempt(subject, keys).and_then(|(out, _)| check_sorted(keys, &out)),
+        );
+    }
+    for &size in &[10, 100, 1000] {
+        let keys = random_keys(size, size as i32, size as u64);
+        let name = format!("random-{}", size);
+        check(
+            &name,
+            attempt(subject, &keys).and_then(|(out, _)| check_sorted(&keys, &out)),
+        );
+    }
+
+    // Lots of duplicates, to catch elements being lost or copied.
+    let keys = random_keys(500, 10, 239);
+    let result = attempt(subject, &keys);
+    check(
+        "permutation",
+        result
+            .clone()
+            .and_then(|(out, _)| check_permutation(&keys, &out)),
+    );
+    if subject.stable {
+        check("stable", result.and_then(|(out, _)| check_stable(&out)));
+    }
+
+    let size = 1000;
+    let keys = random_keys(size, i32::MAX, 1239);
+    let limit = (subject.max_comparisons)(size);
+    check(
+        "comparisons",
+        attempt(subject, &keys).and_then(|(_, comparisons)| {
+            if comparisons <= limit {
+                Ok(())
+            } else {
+                Err(format!(
+                    "{} comparisons for {} random items; expected at most {}",
+                    comparisons, size, limit
+                ))
+            }
+        }),
+    );
+
+    GradeReport {
+        subject: subject.name,
+        checks,
+    }
+}
+
+// Grade the subjects with `bugs` turned on.
+pub fn grade_with_bugs(subjects: &[Subject], bugs: &[Bug]) -> Vec<GradeReport> {
+    with_bugs(bugs, || subjects.iter().map(grade).collect())
+}
+
+// For each bug, the checks that catch it (as "subject/check"). A bug
+// with no checks listed "survived" the battery.
+pub fn mutation_table(subjects: &[Subject]) -> Vec<(Bug, Vec<String>)> {
+    ALL_BUGS
+        .iter()
+        .map(|&bug| {
+            let caught = grade_with_bugs(subjects, &[bug])
+                .iter()
+                .flat_map(|report| {
+                    report
+                        .checks
+                        .iter()
+                        .filter(|c| !c.passed)
+                        .map(move |c| format!("{}/{}", report.subject, c.name))
+                })
+                .collect();
+            (bug, caught)
+        })
+        .collect()
+}
+
+// The reports as JSON, e.g.
+//    [
+//      {"subject": "quicksort", "score": 12, "out_of": 12, "checks": [
+//        {"name": "empty", "passed": true, "detail": ""},
+//        ...
+//      ]}
+//    ]
+pub fn to_json(reports: &[GradeReport]) -> String {
+    let mut json = String::from("[\n");
+    for (i, report) in reports.iter().enumerate() {
+        let _ = writeln!(
+            json,
+            "  {{\"subject\": {}, \"score\": {}, \"out_of\": {}, \"checks\": [",
+            quote(report.subject),
+            report.score(),
+            report.out_of()
+        );
+        for (j, c) in report.checks.iter().enumerate() {
+            let _ = write!(
+                json,
+                "    {{\"name\": {}, \"passed\": {}, \"detail\": {}}}",
+                quote(&c.name),
+                c.passed,
+                quote(&c.detail)
+            );
+            json.push_str(if j + 1 < report.checks.len() {
+                ",\n"
+            } else {
+                "\n"
+            });
+        }
+        json.push_str(if i + 1 < reports.len() {
+            "  ]},\n"
+        } else {
+            "  ]}\n"
+        });
+    }
+    json.push_str("]\n");
+    json
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lab_solutions_get_full_marks() {
+        for subject in subjects() {
+            let report = grade(&subject);
+            let failed: Vec<&CheckResult> = report.checks.iter().filter(|c| !c.passed).collect();
+            assert!(failed.is_empty(), "{}: {:?}", subject.name, failed);
+        }
+    }
+
+    #[test]
+    fn stability_only_checked_when_claimed() {
+        let names = |s| -> Vec<String> {
+            grade(&find_subject(s).unwrap())
+                .checks
+                .into_iter()
+                .map(|c| c.name)
+                .collect()
+        };
+        assert!(!names("quicksort").contains(&"stable".to_string()));
+        assert!(names("merge_sort").contains(&"stable".to_string()));
+    }
+
+    #[test]
+    fn catches_lost_elements() {
+        let subject = Subject {
+            name: "dedup",
+            stable: true,
+            run: |v| {
+                let mut v = v.to_vec();
+                v.sort_by_key(|t| t.0.key);
+                v.dedup_by(|a, b| a.0.key == b.0.key);
+                v
+            },
+            max_comparisons: |n| n_log_n(n),
+        };
+        let report = grade(&subject);
+        let passed = |name: &str| {
+            report
+                .checks
+                .iter()
+                .find(|c| c.name == name)
+                .unwrap()
+                .passed
+        };
+        assert!(passed("empty"));
+        assert!(passed("presorted"));
+        assert!(!passed("ten-items"));
+        assert!(!passed("permutation"));
+        assert!(report.score() < report.out_of());
+    }
+
+    #[test]
+    fn catches_instability_and_slowness() {
+        // Stable by accident on distinct keys, but not in general, and
+        // O(N^2) comparisons.
+        let subject = Subject {
+            name: "backwards-insertion",
+            stable: true,
+            run: |v| {
+                let mut v = v.to_vec();
+                for i in 1..v.len() {
+                    let mut j = i;
+                    while j > 0 && v[j] <= v[j - 1] {
+                        v.swap(j, j - 1);
+                        j -= 1;
+                    }
+                }
+                v
+            },
+            max_comparisons: |n| 2 * n_log_n(n),
+        };
+        let report = grade(&subject);
+        let failed: Vec<&str> = report
+            .checks
+            .iter()
+            .filter(|c| !c.passed)
+            .map(|c| c.name.as_str())
+            .collect();
+        assert_eq!(vec!["stable", "comparisons"], failed);
+    }
+
+    #[test]
+    fn panics_fail_the_check() {
+        let subject = Subject {
+            name: "panicky",
+            stable: false,
+            run: |v| {
+                assert!(v.len() < 50, "too big");
+                v.to_vec()
+            },
+            max_comparisons: |n| n as u64,
+        };
+        let report = grade(&subject);
+        let presorted = report
+            .checks
+            .iter()
+            .find(|c| c.name == "presorted")
+            .unwrap();
+        assert!(!presorted.passed);
+        assert_eq!("panicked: too big", presorted.detail);
+    }
+
+    #[test]
+    fn every_bug_is_caught() {
+        for (bug, caught) in mutation_table(&subjects()) {
+            assert!(!caught.is_empty(), "{} survived", bug.name());
+        }
+    }
+
+    #[test]
+    fn only_the_stability_check_catches_unstable_merge() {
+        let table = mutation_table(&subjects());
+        let (_, caught) = table
+            .iter()
+            .find(|(bug, _)| *bug == Bug::UnstableMerge)
+            .unwrap();
+        assert_eq!(&vec!["merge/stable", "merge_sort/stable"], caught);
+    }
+
+    #[test]
+    fn n_log_n_rounds_up() {
+        assert_eq!(0, n_log_n(1));
+        assert_eq!(2, n_log_n(2));
+        assert_eq!(10_000, n_log_n(1000));
+        assert_eq!(1024 * 10, n_log_n(1024));
+    }
+
+    #[test]
+    fn json() {
+        let report = GradeReport {
+            subject: "merge",
+            checks: vec![
+                CheckResult {
+                    name: "empty".to_string(),
+                    passed: true,
+                    detail: String::new(),
+                },
+                CheckResult {
+                    name: "stable".to_string(),
+                    passed: false,
+                    detail: "out of \"order\"".to_string(),
+                },
+            ],
+        };
+        let expected = "[\n  \
+            {\"subject\": \"merge\", \"score\": 1, \"out_of\": 2, \"checks\": [\n    \
+            {\"name\": \"empty\", \"passed\": true, \"detail\": \"\"},\n    \
+            {\"name\": \"stable\", \"passed\": false, \"detail\": \"out of \\\"order\\\"\"}\n  \
+            ]}\n]\n";
+        assert_eq!(expected, to_json(&[report]));
+    }
+}
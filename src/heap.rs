@@ -0,0 +1,461 @@
+// A small heap module. Heapsort, the top-k selection, and the k-way
+// merge all need a heap, and it's much easier to get one heap right
+// (and test it) than to get three ad-hoc copies right.
+//
+// The free functions here all work on _max_-heaps stored in a slice in
+// the usual way. In a binary heap the children of index i are at 2i+1
+// and 2i+2, and the parent of index i is at (i-1)/2. A slice v is a
+// max-heap when
+//   (All i | 0 < i < length : v[(i-1)/2] ≥ v[i])
+// so the largest element is always at v[0]. If you need a min-heap you
+// can wrap the elements in `std::cmp::Reverse`, which is exactly what
+// `MinHeap` does below.
+//
+// Nothing says each node has to have exactly two children, though. In a
+// d-ary heap the children of i are at d·i+1 through d·i+d and the parent
+// of i is at (i-1)/d. Larger arities make the heap shallower (so sifting
+// up is cheaper and there are fewer levels to sift down through) at the
+// cost of more comparisons per level when sifting down. Since the
+// children of a node are next to each other in memory, 4-ary heaps are
+// often faster in practice than binary ones. The `_d` versions of the
+// functions take the arity as a parameter; the plain versions are
+// binary.
+
+use crate::prefetch::prefetch_read;
+use std::cmp::Reverse;
+
+pub const BINARY: usize = 2;
+
+fn parent(i: usize, arity: usize) -> usize {
+    (i - 1) / arity
+}
+
+fn first_child(i: usize, arity: usize) -> usize {
+    arity * i + 1
+}
+
+// Move the element at `root` down until it's at least as large as all
+// of its children, assuming that all of its subtrees are already heaps.
+// This is O(log N) since each step moves down one level.
+pub fn sift_down<T: PartialOrd>(v: &mut [T], root: usize) {
+    sift_down_d(v, root, BINARY);
+}
+
+pub fn sift_down_d<T: PartialOrd>(v: &mut [T], root: usize, arity: usize) {
+    assert!(arity >= 2, "heaps need an arity of at least 2");
+    let length = v.len();
+    let mut i = root;
+    loop {
+        let first = first_child(i, arity);
+        if first >= length {
+            return;
+        }
+        // Pick the largest of the children (there might be fewer than
+        // `arity` of them at the bottom of the heap).
+        let last = length.min(first + arity);
+        // Whichever child we pick, the next step will look at its
+        // children, which are all in the block of grandchildren from
+        // the first child's first child to the last child's last child.
+        prefetch_read(v, first_child(first, arity));
+        prefetch_read(v, first_child(last - 1, arity));
+        let mut child = first;
+        for c in first + 1..last {
+            if v[c] > v[child] {
+                child = c;
+            }
+        }
+        // If the largest child isn't bigger than v[i] then v[i] has
+        // found its spot and we're done.
+        if v[child] <= v[i] {
+            return;
+        }
+        v.swap(i, child);
+        i = child;
+    }
+}
+
+// Move the element at `child` up until it's no larger than its parent,
+// assuming everything else in the slice is already a heap. This is what
+// we use when adding a new element to the end of a heap.
+pub fn sift_up<T: PartialOrd>(v: &mut [T], child: usize) {
+    sift_up_d(v, child, BINARY);
+}
+
+pub fn sift_up_d<T: PartialOrd>(v: &mut [T], child: usize, arity: usize) {
+    assert!(arity >= 2, "heaps need an arity of at least 2");
+    let mut i = child;
+    while i > 0 && v[parent(i, arity)] < v[i] {
+        v.swap(parent(i, arity), i);
+        i = parent(i, arity);
+    }
+}
+
+// Turn an arbitrary slice into a heap. There are two classic ways to do
+// this, and `heapify` uses the faster one, `heapify_floyd`.
+pub fn heapify<T: PartialOrd>(v: &mut [T]) {
+    heapify_floyd(v);
+}
+
+pub fn heapify_d<T: PartialOrd>(v: &mut [T], arity: usize) {
+    heapify_floyd_d(v, arity);
+}
+
+// Build the heap bottom-up by sifting down every non-leaf, starting from
+// the last one. This is Floyd's construction, and (perhaps surprisingly)
+// it's O(N) rather than O(N log N) because most of the nodes are near
+// the bottom and don't have far to sift. Half the nodes are leaves and
+// don't move at all, a quarter can move at most one level, and so on,
+// and that sum works out to less than 2N.
+pub fn heapify_floyd<T: PartialOrd>(v: &mut [T]) {
+    heapify_floyd_d(v, BINARY);
+}
+
+pub fn heapify_floyd_d<T: PartialOrd>(v: &mut [T], arity: usize) {
+    let length = v.len();
+    if length < 2 {
+        return;
+    }
+    // The last non-leaf is the parent of the last element.
+    for i in (0..=parent(length - 1, arity)).rev() {
+        sift_down_d(v, i, arity);
+    }
+}
+
+// Build the heap top-down by "inserting" each element in turn: the
+// prefix v[0..i] is already a heap, so sifting v[i] up makes v[0..i+1]
+// a heap. This is the obvious approach, but here most of the nodes are
+// near the bottom and _do_ have a long way to go (up), so it's
+// O(N log N) in the worst case (e.g., when v is already in increasing
+// order and every element has to go all the way to the root).
+pub fn heapify_by_insertion<T: PartialOrd>(v: &mut [T]) {
+    heapify_by_insertion_d(v, BINARY);
+}
+
+pub fn heapify_by_insertion_d<T: PartialOrd>(v: &mut [T], arity: usize) {
+    for i in 1..v.len() {
+        sift_up_d(v, i, arity);
+    }
+}
+
+// Check the heap property; mostly useful in tests.
+pub fn is_heap<T: PartialOrd>(v: &[T]) -> bool {
+    is_heap_d(v, BINARY)
+}
+
+pub fn is_heap_d<T: PartialOrd>(v: &[T], arity: usize) -> bool {
+    (1..v.len()).all(|i| v[parent(i, arity)] >= v[i])
+}
+
+// A growable max-heap built on the functions above. `pop()` always
+// returns the largest remaining element.
+#[derive(Debug, Clone)]
+pub struct MaxHeap<T> {
+    data: Vec<T>,
+    arity: usize,
+}
+
+impl<T: PartialOrd> MaxHeap<T> {
+    pub fn new() -> MaxHeap<T> {
+        MaxHeap::with_capacity(0)
+    }
+
+    pub fn with_capacity(capacity: usize) -> MaxHeap<T> {
+        MaxHeap::with_capacity_and_arity(capacity, BINARY)
+    }
+
+    pub fn with_capacity_and_arity(capacity: usize, arity: usize) -> MaxHeap<T> {
+        assert!(arity >= 2, "heaps need an arity of at least 2");
+        MaxHeap {
+            data: Vec::with_capacity(capacity),
+            arity,
+        }
+    }
+
+    pub fn from_vec_with_arity(mut data: Vec<T>, arity: usize) -> MaxHeap<T> {
+        heapify_d(&mut data, arity);
+        MaxHeap { data, arity }
+    }
+
+    pub fn arity(&self) -> usize {
+        self.arity
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn peek(&self) -> Option<&T> {
+        self.data.first()
+    }
+
+    pub fn push(&mut self, item: T) {
+        self.data.push(item);
+        let last = self.data.len() - 1;
+        sift_up_d(&mut self.data, last, self.arity);
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        if self.data.is_empty() {
+            return None;
+        }
+        // Swap the root with the last element so we can pop it off
+        // the end of the Vec, then restore the heap property.
+        let last = self.data.len() - 1;
+        self.data.swap(0, last);
+        let result = self.data.pop();
+        sift_down_d(&mut self.data, 0, self.arity);
+        result
+    }
+
+    pub fn into_vec(self) -> Vec<T> {
+        self.data
+    }
+}
+
+impl<T: PartialOrd> Default for MaxHeap<T> {
+    fn default() -> MaxHeap<T> {
+        MaxHeap::new()
+    }
+}
+
+impl<T: PartialOrd> From<Vec<T>> for MaxHeap<T> {
+    fn from(data: Vec<T>) -> MaxHeap<T> {
+        MaxHeap::from_vec_with_arity(data, BINARY)
+    }
+}
+
+// A min-heap is just a max-heap of `Reverse`d elements, so `pop()`
+// always returns the smallest remaining element.
+#[derive(Debug, Clone)]
+pub struct MinHeap<T> {
+    heap: MaxHeap<Reverse<T>>,
+}
+
+impl<T: PartialOrd> MinHeap<T> {
+    pub fn new() -> MinHeap<T> {
+        MinHeap {
+            heap: MaxHeap::new(),
+        }
+    }
+
+    pub fn with_capacity(capacity: usize) -> MinHeap<T> {
+        MinHeap::with_capacity_and_arity(capacity, BINARY)
+    }
+
+    pub fn with_capacity_and_arity(capacity: usize, arity: usize) -> MinHeap<T> {
+        MinHeap {
+            heap: MaxHeap::with_capacity_and_arity(capacity, arity),
+        }
+    }
+
+    pub fn arity(&self) -> usize {
+        self.heap.arity()
+    }
+
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+
+    pub fn peek(&self) -> Option<&T> {
+        self.heap.peek().map(|r| &r.0)
+    }
+
+    pub fn push(&mut self, item: T) {
+        self.heap.push(Reverse(item));
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        self.heap.pop().map(|r| r.0)
+    }
+}
+
+impl<T: PartialOrd> Default for MinHeap<T> {
+    fn default() -> MinHeap<T> {
+        MinHeap::new()
+    }
+}
+
+impl<T: PartialOrd> From<Vec<T>> for MinHeap<T> {
+    fn from(data: Vec<T>) -> MinHeap<T> {
+        MinHeap {
+            heap: MaxHeap::from(data.into_iter().map(Reverse).collect::<Vec<_>>()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instrument::{count_comparisons, counted};
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    fn random_vec(rng: &mut StdRng) -> Vec<i32> {
+        let len = rng.gen_range(0, 100);
+        (0..len).map(|_| rng.gen_range(0, 50)).collect()
+    }
+
+    mod heapify {
+        use super::*;
+
+        #[test]
+        fn empty() {
+            let mut input: [i32; 0] = [];
+            heapify(&mut input);
+            assert!(is_heap(&input));
+        }
+
+        #[test]
+        fn ten_items() {
+            let mut input = [3, 2, 0, 5, 8, 9, 6, 3, 2, 0];
+            heapify(&mut input);
+            assert!(is_heap(&input));
+            assert_eq!(9, input[0]);
+        }
+
+        #[test]
+        fn random_inputs() {
+            let mut rng = StdRng::seed_from_u64(205);
+            for _ in 0..200 {
+                let mut v = random_vec(&mut rng);
+                heapify(&mut v);
+                assert!(is_heap(&v));
+            }
+        }
+
+        #[test]
+        fn other_arities() {
+            let mut rng = StdRng::seed_from_u64(206);
+            for &arity in &[3, 4, 8] {
+                for _ in 0..100 {
+                    let mut v = random_vec(&mut rng);
+                    heapify_d(&mut v, arity);
+                    assert!(is_heap_d(&v, arity));
+                }
+            }
+        }
+    }
+
+    mod heapify_by_insertion {
+        use super::*;
+
+        #[test]
+        fn random_inputs() {
+            let mut rng = StdRng::seed_from_u64(207);
+            for _ in 0..200 {
+                let mut v = random_vec(&mut rng);
+                heapify_by_insertion(&mut v);
+                assert!(is_heap(&v));
+            }
+        }
+
+        #[test]
+        fn other_arities() {
+            let mut rng = StdRng::seed_from_u64(207);
+            for &arity in &[3, 4, 8] {
+                let mut v = random_vec(&mut rng);
+                heapify_by_insertion_d(&mut v, arity);
+                assert!(is_heap_d(&v, arity));
+            }
+        }
+
+        // On increasing input every insertion has to go all the way to
+        // the root, so insertion makes about N log N comparisons while
+        // Floyd's construction stays under 2N.
+        #[test]
+        fn floyd_makes_fewer_comparisons() {
+            let size = 1 << 12;
+            let input: Vec<i32> = (0..size).collect();
+
+            let mut floyd = counted(&input);
+            let (_, floyd_count) = count_comparisons(|| heapify_floyd(&mut floyd));
+            let mut insertion = counted(&input);
+            let (_, insertion_count) = count_comparisons(|| heapify_by_insertion(&mut insertion));
+
+            assert!(floyd_count < 2 * size as u64);
+            assert!(insertion_count > 10 * size as u64);
+        }
+    }
+
+    mod sift_up {
+        use super::*;
+
+        #[test]
+        fn new_maximum_moves_to_root() {
+            let mut input = [9, 5, 8, 3, 2, 7, 6, 10];
+            sift_up(&mut input, 7);
+            assert!(is_heap(&input));
+            assert_eq!(10, input[0]);
+        }
+    }
+
+    mod max_heap {
+        use super::*;
+
+        #[test]
+        fn pops_in_descending_order() {
+            let mut rng = StdRng::seed_from_u64(205);
+            let v = random_vec(&mut rng);
+            let mut heap = MaxHeap::new();
+            for &x in &v {
+                heap.push(x);
+            }
+            let mut expected = v.clone();
+            expected.sort_by(|a, b| b.cmp(a));
+            let mut result = Vec::new();
+            while let Some(x) = heap.pop() {
+                result.push(x);
+            }
+            assert_eq!(expected, result);
+        }
+
+        #[test]
+        fn four_ary_pops_in_descending_order() {
+            let mut heap = MaxHeap::with_capacity_and_arity(10, 4);
+            for &x in &[3, 2, 0, 5, 8, 9, 6, 3, 2, 0] {
+                heap.push(x);
+            }
+            let mut result = Vec::new();
+            while let Some(x) = heap.pop() {
+                result.push(x);
+            }
+            assert_eq!(vec![9, 8, 6, 5, 3, 3, 2, 2, 0, 0], result);
+        }
+
+        #[test]
+        fn from_vec() {
+            let heap = MaxHeap::from(vec![3, 2, 0, 5, 8, 9, 6, 3, 2, 0]);
+            assert_eq!(Some(&9), heap.peek());
+            assert_eq!(10, heap.len());
+        }
+    }
+
+    mod min_heap {
+        use super::*;
+
+        #[test]
+        fn pops_in_ascending_order() {
+            let mut heap = MinHeap::from(vec![3, 2, 0, 5, 8, 9, 6, 3, 2, 0]);
+            let mut result = Vec::new();
+            while let Some(x) = heap.pop() {
+                result.push(x);
+            }
+            assert_eq!(vec![0, 0, 2, 2, 3, 3, 5, 6, 8, 9], result);
+        }
+
+        #[test]
+        fn empty() {
+            let mut heap: MinHeap<i32> = MinHeap::new();
+            assert!(heap.is_empty());
+            assert_eq!(None, heap.peek());
+            assert_eq!(None, heap.pop());
+        }
+    }
+}
@@ -0,0 +1,73 @@
+// Backing big arrays with huge pages. The CPU caches address
+// translations in its TLB, which only has room for a few thousand pages;
+// with ordinary 4 KiB pages that covers just a few megabytes, so sorting
+// a big array with lots of random access (heapsort, say) can spend a
+// surprising amount of time on TLB misses. With 2 MiB "transparent huge
+// pages" the same TLB covers gigabytes.
+//
+// We ask for huge pages with `madvise(MADV_HUGEPAGE)`. That's only a
+// request: the kernel may not have any huge pages free, or may have
+// them turned off entirely (see /sys/kernel/mm/transparent_hugepage/).
+//
+// This module is only available on Linux with the `hugepages` feature.
+
+// Huge pages are 2 MiB, so there's no point asking for them for anything
+// much smaller.
+pub const HUGE_PAGE_THRESHOLD: usize = 4 << 20;
+
+// A copy of v in a freshly allocated buffer that we've asked the kernel
+// to back with huge pages, along with whether we actually asked (we
+// don't for small arrays) and it agreed to consider it.
+pub fn copy_to_huge_pages<T: Copy + Default>(v: &[T]) -> (Vec<T>, bool) {
+    let mut result = vec![T::default(); v.len()];
+    let advised = std::mem::size_of_val(v) >= HUGE_PAGE_THRESHOLD && advise_huge_pages(&mut result);
+    // Nothing has touched the new buffer's pages yet, so this copy is
+    // what actually allocates them, after the advice.
+    result.copy_from_slice(v);
+    (result, advised)
+}
+
+fn advise_huge_pages<T>(v: &mut [T]) -> bool {
+    // SAFETY: `sysconf` has no preconditions.
+    let page = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+    let start = v.as_mut_ptr() as usize;
+    let end = start + std::mem::size_of_val(v);
+    // madvise needs a page-aligned start, and we mustn't give advice
+    // about memory that isn't ours, so round inwards.
+    let aligned_start = start.div_ceil(page) * page;
+    let aligned_end = end - end % page;
+    if aligned_start >= aligned_end {
+        return false;
+    }
+    // SAFETY: the range is inside our own allocation, and MADV_HUGEPAGE
+    // only affects how it's backed, not what it contains.
+    let result = unsafe {
+        libc::madvise(
+            aligned_start as *mut libc::c_void,
+            aligned_end - aligned_start,
+            libc::MADV_HUGEPAGE,
+        )
+    };
+    result == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn small_arrays_not_advised() {
+        let (v, advised) = copy_to_huge_pages(&[3, 2, 0, 5, 8, 9, 6, 3, 2, 0]);
+        assert_eq!(vec![3, 2, 0, 5, 8, 9, 6, 3, 2, 0], v);
+        assert!(!advised);
+    }
+
+    #[test]
+    fn large_arrays_copied() {
+        // Whether the kernel takes the advice depends on the machine, so
+        // all we can check is that the copy is right.
+        let input: Vec<i32> = (0..(HUGE_PAGE_THRESHOLD / 4) as i32 + 100).collect();
+        let (v, _) = copy_to_huge_pages(&input);
+        assert_eq!(input, v);
+    }
+}
@@ -0,0 +1,60 @@
+// Reading numbers from files, for the subcommands that work on real
+// data rather than generated arrays. A file is just numbers separated by
+// whitespace (usually one per line).
+
+use std::path::Path;
+
+pub fn parse_numbers(text: &str) -> Result<Vec<i64>, String> {
+    let mut numbers = Vec::new();
+    for (line_number, line) in text.lines().enumerate() {
+        for word in line.split_whitespace() {
+            let n = word.parse().map_err(|_| {
+                format!("line {}: `{}` isn't a whole number", line_number + 1, word)
+            })?;
+            numbers.push(n);
+        }
+    }
+    Ok(numbers)
+}
+
+pub fn read_numbers<P: AsRef<Path>>(path: P) -> Result<Vec<i64>, String> {
+    let path = path.as_ref();
+    let text = std::fs::read_to_string(path)
+        .map_err(|error| format!("can't read {}: {}", path.display(), error))?;
+    parse_numbers(&text).map_err(|error| format!("{}: {}", path.display(), error))
+}
+
+// Like `read_numbers`, but the numbers have to be in order already.
+pub fn read_sorted_numbers<P: AsRef<Path>>(path: P) -> Result<Vec<i64>, String> {
+    let numbers = read_numbers(&path)?;
+    match numbers.windows(2).position(|w| w[0] > w[1]) {
+        None => Ok(numbers),
+        Some(i) => Err(format!(
+            "{} isn't sorted: {} comes before {} (try `sort -n` first)",
+            path.as_ref().display(),
+            numbers[i],
+            numbers[i + 1]
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty() {
+        assert_eq!(Ok(vec![]), parse_numbers(""));
+    }
+
+    #[test]
+    fn lines_and_spaces() {
+        assert_eq!(Ok(vec![3, -2, 0, 5]), parse_numbers("3\n-2  0\n\n5\n"));
+    }
+
+    #[test]
+    fn bad_number() {
+        let error = parse_numbers("1\n2\nthree\n").unwrap_err();
+        assert!(error.starts_with("line 3:"), "{}", error);
+    }
+}
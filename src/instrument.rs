@@ -0,0 +1,284 @@
+// Instrumentation for counting how much work the algorithms do. Wall
+// clock times depend on the machine, the compiler, what else is running,
+// and so on, but the number of comparisons an algorithm makes on a given
+// input doesn't, which makes comparison counts a nice way to check our
+// big-O reasoning.
+//
+// Rather than change every algorithm to count its own comparisons, we
+// wrap the elements in `Counted`, whose comparison operators bump a
+// counter before comparing the wrapped values. Since all the sorts are
+// generic over `PartialOrd`, they'll happily sort `Counted` values
+// without knowing they're being watched.
+//
+// The counter is thread-local, so tests running in parallel don't
+// interfere with each other's counts.
+
+use std::cell::{Cell, RefCell};
+use std::cmp::Ordering;
+use std::fmt;
+
+thread_local! {
+    static COMPARISONS: Cell<u64> = const { Cell::new(0) };
+}
+
+thread_local! {
+    // Only `Some` inside `trace_comparisons`.
+    static TRACE: RefCell<Option<Vec<String>>> = const { RefCell::new(None) };
+}
+
+thread_local! {
+    // How many more comparisons are allowed; only `Some` inside
+    // `with_comparison_budget`.
+    static BUDGET: Cell<Option<u64>> = const { Cell::new(None) };
+}
+
+fn record_comparison() {
+    COMPARISONS.with(|c| c.set(c.get() + 1));
+    match BUDGET.with(|b| b.get()) {
+        Some(0) => std::panic::resume_unwind(Box::new(BudgetExhausted)),
+        Some(left) => BUDGET.with(|b| b.set(Some(left - 1))),
+        None => {}
+    }
+}
+
+// Set the comparison counter for this thread back to zero.
+pub fn reset_comparisons() {
+    COMPARISONS.with(|c| c.set(0));
+}
+
+// The number of comparisons made on this thread since the last reset.
+pub fn comparisons() -> u64 {
+    COMPARISONS.with(|c| c.get())
+}
+
+// Run `f` and return its result along with the number of comparisons
+// of `Counted` values it made.
+pub fn count_comparisons<R, F: FnOnce() -> R>(f: F) -> (R, u64) {
+    let before = comparisons();
+    let result = f();
+    (result, comparisons() - before)
+}
+
+// Run `f` and return its result along with a description of every
+// comparison of `Traced` values it made, in order.
+pub fn trace_comparisons<R, F: FnOnce() -> R>(f: F) -> (R, Vec<String>) {
+    let before = TRACE.with(|t| t.replace(Some(Vec::new())));
+    let result = f();
+    let trace = TRACE.with(|t| t.replace(before)).unwrap_or_default();
+    (result, trace)
+}
+
+// What `with_comparison_budget` returns if `f` ran out of comparisons.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BudgetExhausted;
+
+// Run `f`, but stop it as soon as it tries to make more than `budget`
+// comparisons of `Counted` (or `Traced`) values. We stop it by unwinding
+// the stack, as if it had panicked (but without printing a message), so
+// whatever `f` was working on is left half done.
+pub fn with_comparison_budget<R, F: FnOnce() -> R>(budget: u64, f: F) -> Result<R, BudgetExhausted> {
+    let before = BUDGET.with(|b| b.replace(Some(budget)));
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(f));
+    BUDGET.with(|b| b.set(before));
+    match result {
+        Ok(result) => Ok(result),
+        Err(payload) if payload.is::<BudgetExhausted>() => Err(BudgetExhausted),
+        // Some other panic; let it carry on.
+        Err(payload) => std::panic::resume_unwind(payload),
+    }
+}
+
+// The number of inversions in v, i.e., the number of pairs of elements
+// that are out of order:
+//   #{ (i, j) | 0 ≤ i < j < length : v[i] > v[j] }
+// This is exactly how many swaps insertion sort does, so it's a handy
+// way to measure how "unsorted" an input is. We count them with a merge
+// sort (on a copy of v), which makes this O(N log N).
+pub fn inversions<T: PartialOrd + Clone>(v: &[T]) -> u64 {
+    let mut copy = v.to_vec();
+    count_inversions(&mut copy)
+}
+
+fn count_inversions<T: PartialOrd + Clone>(v: &mut [T]) -> u64 {
+    let length = v.len();
+    if length < 2 {
+        return 0;
+    }
+    let middle = length / 2;
+    let mut count = count_inversions(&mut v[0..middle]) + count_inversions(&mut v[middle..length]);
+    let mut merged = Vec::with_capacity(length);
+    let (mut i, mut j) = (0, middle);
+    while i < middle && j < length {
+        if v[j] < v[i] {
+            // v[j] is smaller than everything left in the left half,
+            // so it's out of order with all of them.
+            count += (middle - i) as u64;
+            merged.push(v[j].clone());
+            j += 1;
+        } else {
+            merged.push(v[i].clone());
+            i += 1;
+        }
+    }
+    merged.extend_from_slice(&v[i..middle]);
+    merged.extend_from_slice(&v[j..length]);
+    v.clone_from_slice(&merged);
+    count
+}
+
+// A value whose comparisons are counted. Each call to `partial_cmp`,
+// `cmp`, `<`, `≤`, etc. counts as one comparison. Equality checks
+// (`==`) count too, since some algorithms use them.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Counted<T>(pub T);
+
+// Wrap every element of a slice for counting.
+pub fn counted<T: Clone>(v: &[T]) -> Vec<Counted<T>> {
+    v.iter().cloned().map(Counted).collect()
+}
+
+// And unwrap them again.
+pub fn uncounted<T>(v: Vec<Counted<T>>) -> Vec<T> {
+    v.into_iter().map(|c| c.0).collect()
+}
+
+impl<T: PartialEq> PartialEq for Counted<T> {
+    fn eq(&self, other: &Self) -> bool {
+        record_comparison();
+        self.0 == other.0
+    }
+}
+
+impl<T: Eq> Eq for Counted<T> {}
+
+impl<T: PartialOrd> PartialOrd for Counted<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        record_comparison();
+        self.0.partial_cmp(&other.0)
+    }
+}
+
+impl<T: Ord> Ord for Counted<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        record_comparison();
+        self.0.cmp(&other.0)
+    }
+}
+
+// A value whose comparisons are written down, e.g. "3 < 5", so we can
+// watch what an algorithm does step by step (see `trace_comparisons`).
+// Traced comparisons are counted too.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Traced<T>(pub T);
+
+// Wrap every element of a slice for tracing.
+pub fn traced<T: Clone>(v: &[T]) -> Vec<Traced<T>> {
+    v.iter().cloned().map(Traced).collect()
+}
+
+// And unwrap them again.
+pub fn untraced<T>(v: Vec<Traced<T>>) -> Vec<T> {
+    v.into_iter().map(|t| t.0).collect()
+}
+
+fn record_trace<T: fmt::Debug>(a: &T, symbol: &str, b: &T) {
+    record_comparison();
+    TRACE.with(|t| {
+        if let Some(trace) = t.borrow_mut().as_mut() {
+            trace.push(format!("{:?} {} {:?}", a, symbol, b));
+        }
+    });
+}
+
+impl<T: PartialEq + fmt::Debug> PartialEq for Traced<T> {
+    fn eq(&self, other: &Self) -> bool {
+        let equal = self.0 == other.0;
+        record_trace(&self.0, if equal { "==" } else { "!=" }, &other.0);
+        equal
+    }
+}
+
+impl<T: PartialOrd + fmt::Debug> PartialOrd for Traced<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        let ordering = self.0.partial_cmp(&other.0);
+        let symbol = match ordering {
+            Some(Ordering::Less) => "<",
+            Some(Ordering::Equal) => "==",
+            Some(Ordering::Greater) => ">",
+            None => "incomparable to",
+        };
+        record_trace(&self.0, symbol, &other.0);
+        ordering
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_each_comparison() {
+        let a = Counted(1);
+        let b = Counted(2);
+        let (_, count) = count_comparisons(|| {
+            assert!(a < b);
+            assert!(b >= a);
+            assert!(a != b);
+        });
+        assert_eq!(3, count);
+    }
+
+    #[test]
+    fn reset() {
+        let _ = Counted(1) < Counted(2);
+        reset_comparisons();
+        assert_eq!(0, comparisons());
+    }
+
+    #[test]
+    fn inversions_of_small_arrays() {
+        assert_eq!(0, inversions::<i32>(&[]));
+        assert_eq!(0, inversions(&[0, 0, 2, 2, 3]));
+        assert_eq!(10, inversions(&[5, 4, 3, 2, 1]));
+        assert_eq!(3, inversions(&[3, 2, 0, 5]));
+    }
+
+    #[test]
+    fn round_trip() {
+        let input = [3, 2, 0];
+        assert_eq!(input.to_vec(), uncounted(counted(&input)));
+        assert_eq!(input.to_vec(), untraced(traced(&input)));
+    }
+
+    #[test]
+    fn budget() {
+        let v = counted(&[3, 2, 0, 5, 8, 9, 6, 3, 2, 0]);
+        let (_, needed) = count_comparisons(|| crate::sorts::merge_sort(&v));
+        assert!(with_comparison_budget(needed - 1, || crate::sorts::merge_sort(&v)).is_err());
+        let sorted = with_comparison_budget(needed, || crate::sorts::merge_sort(&v)).unwrap();
+        assert_eq!(vec![0, 0, 2, 2, 3, 3, 5, 6, 8, 9], uncounted(sorted));
+        // And no budget afterwards.
+        let (_, count) = count_comparisons(|| crate::sorts::merge_sort(&v));
+        assert_eq!(needed, count);
+    }
+
+    #[test]
+    fn other_panics_pass_through() {
+        let result = std::panic::catch_unwind(|| with_comparison_budget(10, || panic!("oops")));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn traces_each_comparison() {
+        let (_, trace) = trace_comparisons(|| {
+            assert!(Traced(1) < Traced(2));
+            assert!(Traced(2) >= Traced(2));
+            assert!(Traced(1) != Traced(2));
+            assert!(Traced(f64::NAN).partial_cmp(&Traced(1.0)).is_none());
+        });
+        assert_eq!(vec!["1 < 2", "2 == 2", "1 != 2", "NaN incomparable to 1.0"], trace);
+        // Outside of `trace_comparisons` nothing is written down.
+        let _ = Traced(1) < Traced(2);
+        assert!(trace_comparisons(|| ()).1.is_empty());
+    }
+}
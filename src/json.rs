@@ -0,0 +1,298 @@
+// Just enough JSON for the files this crate writes and reads back: a
+// function to quote strings, and a small parser. We don't need the
+// generality (or the dependencies) of a real JSON library.
+
+use std::fmt::Write;
+
+// A JSON value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    // The fields in the order they appeared.
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    // The field called `name`, if this is an object that has one.
+    pub fn get(&self, name: &str) -> Option<&Json> {
+        match self {
+            Json::Object(fields) => fields.iter().find(|(n, _)| n == name).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    // The number, if it's a whole number that fits in an `i64`.
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            Json::Number(n) if n.fract() == 0.0 && n.abs() <= i64::MAX as f64 => Some(n as i64),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+}
+
+// `s` as a JSON string literal, quotes and all.
+pub fn quote(s: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(quoted, "\\u{:04x}", c as u32);
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+// Parse a complete JSON document. Errors say what went wrong and the
+// byte offset where it happened.
+pub fn parse(text: &str) -> Result<Json, String> {
+    let mut parser = Parser { text, pos: 0 };
+    let value = parser.value()?;
+    parser.skip_whitespace();
+    if parser.pos < text.len() {
+        return Err(parser.error("unexpected text after the end"));
+    }
+    Ok(value)
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self, message: &str) -> String {
+        format!("{} at byte {}", message, self.pos)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.text[self.pos..].chars().next()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek() {
+            if !c.is_ascii_whitespace() {
+                break;
+            }
+            self.pos += 1;
+        }
+    }
+
+    // Skip `expected` (after any whitespace), or fail.
+    fn expect(&mut self, expected: &str) -> Result<(), String> {
+        self.skip_whitespace();
+        if self.text[self.pos..].starts_with(expected) {
+            self.pos += expected.len();
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected `{}`", expected)))
+        }
+    }
+
+    fn value(&mut self) -> Result<Json, String> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('{') => self.object(),
+            Some('[') => self.array(),
+            Some('"') => self.string().map(Json::String),
+            Some('t') => self.expect("true").map(|_| Json::Bool(true)),
+            Some('f') => self.expect("false").map(|_| Json::Bool(false)),
+            Some('n') => self.expect("null").map(|_| Json::Null),
+            Some(c) if c == '-' || c.is_ascii_digit() => self.number(),
+            Some(_) => Err(self.error("unexpected character")),
+            None => Err(self.error("unexpected end")),
+        }
+    }
+
+    fn object(&mut self) -> Result<Json, String> {
+        self.expect("{")?;
+        let mut fields = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.pos += 1;
+            return Ok(Json::Object(fields));
+        }
+        loop {
+            self.skip_whitespace();
+            let name = self.string()?;
+            self.expect(":")?;
+            fields.push((name, self.value()?));
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                Some('}') => {
+                    self.pos += 1;
+                    return Ok(Json::Object(fields));
+                }
+                _ => return Err(self.error("expected `,` or `}`")),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Json, String> {
+        self.expect("[")?;
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.pos += 1;
+            return Ok(Json::Array(items));
+        }
+        loop {
+            items.push(self.value()?);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                Some(']') => {
+                    self.pos += 1;
+                    return Ok(Json::Array(items));
+                }
+                _ => return Err(self.error("expected `,` or `]`")),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        if self.peek() != Some('"') {
+            return Err(self.error("expected a string"));
+        }
+        self.pos += 1;
+        let mut s = String::new();
+        loop {
+            let c = self
+                .peek()
+                .ok_or_else(|| self.error("unterminated string"))?;
+            self.pos += c.len_utf8();
+            match c {
+                '"' => return Ok(s),
+                '\\' => {
+                    let escape = self
+                        .peek()
+                        .ok_or_else(|| self.error("unterminated string"))?;
+                    self.pos += 1;
+                    s.push(match escape {
+                        '"' => '"',
+                        '\\' => '\\',
+                        '/' => '/',
+                        'b' => '\u{8}',
+                        'f' => '\u{c}',
+                        'n' => '\n',
+                        'r' => '\r',
+                        't' => '\t',
+                        'u' => {
+                            let hex = self
+                                .text
+                                .get(self.pos..self.pos + 4)
+                                .ok_or_else(|| self.error("bad \\u escape"))?;
+                            let code = u32::from_str_radix(hex, 16)
+                                .map_err(|_| self.error("bad \\u escape"))?;
+                            self.pos += 4;
+                            // We never write surrogate pairs, so we don't
+                            // try to read them.
+                            std::char::from_u32(code)
+                                .ok_or_else(|| self.error("unsupported \\u escape"))?
+                        }
+                        _ => return Err(self.error("bad escape")),
+                    });
+                }
+                c => s.push(c),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<Json, String> {
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            if c.is_ascii_digit() || "+-.eE".contains(c) {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+        self.text[start..self.pos]
+            .parse()
+            .map(Json::Number)
+            .map_err(|_| format!("bad number at byte {}", start))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quoting() {
+        assert_eq!("\"a\\\"b\\\\c\\u000a\"", quote("a\"b\\c\n"));
+    }
+
+    #[test]
+    fn values() {
+        assert_eq!(Ok(Json::Null), parse("null"));
+        assert_eq!(Ok(Json::Bool(true)), parse(" true "));
+        assert_eq!(Ok(Json::Number(-12.5)), parse("-12.5"));
+        assert_eq!(
+            Ok(Json::String("a\"b\nc\u{e9}".to_string())),
+            parse(r#""a\"b\ncé""#)
+        );
+        assert_eq!(Ok(Json::Array(vec![])), parse("[ ]"));
+    }
+
+    #[test]
+    fn nested() {
+        let json = parse(r#"{"name": "quicksort", "input": [3, 2, 0], "empty": {}}"#).unwrap();
+        assert_eq!(Some("quicksort"), json.get("name").and_then(Json::as_str));
+        let input: Vec<i64> = json
+            .get("input")
+            .and_then(Json::as_array)
+            .unwrap()
+            .iter()
+            .filter_map(Json::as_i64)
+            .collect();
+        assert_eq!(vec![3, 2, 0], input);
+        assert_eq!(Some(&Json::Object(vec![])), json.get("empty"));
+        assert_eq!(None, json.get("missing"));
+    }
+
+    #[test]
+    fn round_trip() {
+        let s = "tab\there \"quoted\" \\ \u{1}";
+        assert_eq!(Ok(Json::String(s.to_string())), parse(&quote(s)));
+    }
+
+    #[test]
+    fn errors() {
+        for bad in &[
+            "",
+            "[1, 2",
+            "{\"a\" 1}",
+            "tru",
+            "\"open",
+            "[1] 2",
+            "{1: 2}",
+            "-",
+        ] {
+            assert!(parse(bad).is_err(), "{}", bad);
+        }
+    }
+}
@@ -0,0 +1,516 @@
+// Sort keys parsed out of text. Sorting lines of a log file as plain
+// strings works for some timestamps but not others (`9:05` sorts after
+// `10:30`), and sorting durations like `1h30m` as strings is hopeless,
+// so we parse each key into a value that compares the way people mean
+// it to.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::time::Duration;
+
+// How to interpret the text of a key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyType {
+    // Plain string comparison.
+    Text,
+    // A decimal number, e.g. `-12.5`.
+    Number,
+    // An ISO-8601 date or timestamp, e.g. `2022-11-03` or
+    // `2022-11-03T14:05:09.25-05:00` (see `parse_timestamp`).
+    Date,
+    // A humanized duration, e.g. `1h30m` or `250ms` (see
+    // `parse_duration`).
+    Duration,
+    // An IPv4 address, e.g. `10.0.0.2`, which should come before
+    // `10.0.0.10` (it doesn't as text).
+    Ipv4,
+    // An IPv6 address, e.g. `fe80::1`.
+    Ipv6,
+    // A semantic version, e.g. `1.10.0-rc.1` (see `Version`).
+    Semver,
+}
+
+impl KeyType {
+    pub fn name(self) -> &'static str {
+        match self {
+            KeyType::Text => "text",
+            KeyType::Number => "number",
+            KeyType::Date => "date",
+            KeyType::Duration => "duration",
+            KeyType::Ipv4 => "ipv4",
+            KeyType::Ipv6 => "ipv6",
+            KeyType::Semver => "semver",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<KeyType> {
+        match name {
+            "text" => Some(KeyType::Text),
+            "number" => Some(KeyType::Number),
+            "date" => Some(KeyType::Date),
+            "duration" => Some(KeyType::Duration),
+            "ipv4" => Some(KeyType::Ipv4),
+            "ipv6" => Some(KeyType::Ipv6),
+            "semver" => Some(KeyType::Semver),
+            _ => None,
+        }
+    }
+
+    pub fn parse(self, text: &str) -> Result<Key, String> {
+        let text = text.trim();
+        match self {
+            KeyType::Text => Ok(Key::Text(text.to_string())),
+            KeyType::Number => match text.parse::<f64>() {
+                // NaN doesn't compare with anything, so it can't be sorted.
+                Ok(n) if !n.is_nan() => Ok(Key::Number(n)),
+                _ => Err(format!("`{}` isn't a number", text)),
+            },
+            KeyType::Date => parse_timestamp(text).map(Key::Date),
+            KeyType::Duration => parse_duration(text).map(Key::Duration),
+            KeyType::Ipv4 => text
+                .parse()
+                .map(Key::Ipv4)
+                .map_err(|_| format!("`{}` isn't an IPv4 address", text)),
+            KeyType::Ipv6 => text
+                .parse()
+                .map(Key::Ipv6)
+                .map_err(|_| format!("`{}` isn't an IPv6 address", text)),
+            KeyType::Semver => parse_version(text).map(Key::Semver),
+        }
+    }
+}
+
+// A parsed key. All the keys being sorted together have the same type,
+// so we only ever compare like with like.
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub enum Key {
+    Text(String),
+    Number(f64),
+    Date(Timestamp),
+    Duration(Duration),
+    Ipv4(Ipv4Addr),
+    Ipv6(Ipv6Addr),
+    Semver(Version),
+}
+
+// A moment in time, as seconds (and nanoseconds) since the start of
+// 1970 UTC. Timestamps without a time zone are taken to be UTC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Timestamp {
+    pub seconds: i64,
+    pub nanos: u32,
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}s+{}ns since 1970", self.seconds, self.nanos)
+    }
+}
+
+// Days since 1970-01-01 of a (proleptic Gregorian) date; see
+// http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = month as i64;
+    let day_of_year =
+        (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// Exactly `n` ASCII digits from the front of `text`, as a number.
+fn digits(text: &str, n: usize) -> Option<(u32, &str)> {
+    let (number, rest) = (text.get(..n)?, &text[n..]);
+    if !number.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    Some((number.parse().ok()?, rest))
+}
+
+// The ISO-8601 forms people actually write:
+//    2022-11-03
+//    2022-11-03T14:05          (or with a space instead of the T)
+//    2022-11-03T14:05:09
+//    2022-11-03T14:05:09.25
+// optionally followed by `Z` or an offset like `+01:00` or `-0500`.
+pub fn parse_timestamp(text: &str) -> Result<Timestamp, String> {
+    let bad = || format!("`{}` isn't an ISO-8601 date or timestamp", text);
+    let (year, rest) = digits(text, 4).ok_or_else(bad)?;
+    let rest = rest.strip_prefix('-').ok_or_else(bad)?;
+    let (month, rest) = digits(rest, 2).ok_or_else(bad)?;
+    let rest = rest.strip_prefix('-').ok_or_else(bad)?;
+    let (day, mut rest) = digits(rest, 2).ok_or_else(bad)?;
+    let year = year as i64;
+    if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) {
+        return Err(format!("`{}` isn't a real date", text));
+    }
+    let mut seconds = days_from_civil(year, month, day) * 86_400;
+    let mut nanos = 0;
+    if let Some(time) = rest.strip_prefix('T').or_else(|| rest.strip_prefix(' ')) {
+        let (hour, time) = digits(time, 2).ok_or_else(bad)?;
+        let time = time.strip_prefix(':').ok_or_else(bad)?;
+        let (minute, mut time) = digits(time, 2).ok_or_else(bad)?;
+        let mut second = 0;
+        if let Some(more) = time.strip_prefix(':') {
+            let (s, more) = digits(more, 2).ok_or_else(bad)?;
+            second = s;
+            time = more;
+            if let Some(fraction) = time.strip_prefix('.') {
+                let length = fraction.bytes().take_while(u8::is_ascii_digit).count();
+                if length == 0 {
+                    return Err(bad());
+                }
+                // Keep at most nanosecond precision.
+                let kept = &fraction[..length.min(9)];
+                nanos = kept.parse::<u32>().map_err(|_| bad())? * 10u32.pow(9 - kept.len() as u32);
+                time = &fraction[length..];
+            }
+        }
+        // 60 allows for leap seconds.
+        if hour > 23 || minute > 59 || second > 60 {
+            return Err(format!("`{}` isn't a real time", text));
+        }
+        seconds += (hour * 3600 + minute * 60 + second) as i64;
+        rest = time;
+    }
+    match rest {
+        "" | "Z" => {}
+        _ => {
+            let sign = match rest.as_bytes()[0] {
+                b'+' => 1,
+                b'-' => -1,
+                _ => return Err(bad()),
+            };
+            let (hours, offset) = digits(&rest[1..], 2).ok_or_else(bad)?;
+            let offset = offset.strip_prefix(':').unwrap_or(offset);
+            let (minutes, offset) = digits(offset, 2).ok_or_else(bad)?;
+            if !offset.is_empty() || hours > 23 || minutes > 59 {
+                return Err(bad());
+            }
+            // 14:00+01:00 is 13:00 UTC.
+            seconds -= sign * (hours * 3600 + minutes * 60) as i64;
+        }
+    }
+    Ok(Timestamp { seconds, nanos })
+}
+
+// A duration written as numbers with units, like `1h30m`, `1.5h`,
+// `2d 4h`, `90s`, or `250ms`. The units are w, d, h, m, s, ms, us (or
+// µs), and ns; a bare number is taken to be seconds.
+pub fn parse_duration(text: &str) -> Result<Duration, String> {
+    let bad = || format!("`{}` isn't a duration like `1h30m`", text);
+    if let Ok(seconds) = text.parse::<f64>() {
+        return seconds_to_duration(seconds).ok_or_else(bad);
+    }
+    let mut total = 0.0;
+    let mut rest = text.trim_start();
+    if rest.is_empty() {
+        return Err(bad());
+    }
+    while !rest.is_empty() {
+        let number_length = rest
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .ok_or_else(bad)?;
+        let number: f64 = rest[..number_length].parse().map_err(|_| bad())?;
+        rest = &rest[number_length..];
+        let unit_length = rest
+            .find(|c: char| c.is_ascii_digit() || c.is_whitespace())
+            .unwrap_or(rest.len());
+        let seconds_per_unit = match &rest[..unit_length] {
+            "w" => 604_800.0,
+            "d" => 86_400.0,
+            "h" => 3600.0,
+            "m" => 60.0,
+            "s" => 1.0,
+            "ms" => 1e-3,
+            "us" | "µs" => 1e-6,
+            "ns" => 1e-9,
+            _ => return Err(bad()),
+        };
+        total += number * seconds_per_unit;
+        rest = rest[unit_length..].trim_start();
+    }
+    seconds_to_duration(total).ok_or_else(bad)
+}
+
+fn seconds_to_duration(seconds: f64) -> Option<Duration> {
+    if seconds.is_finite() && seconds >= 0.0 && seconds < u64::MAX as f64 {
+        Some(Duration::from_secs_f64(seconds))
+    } else {
+        None
+    }
+}
+
+// A semantic version (https://semver.org): MAJOR.MINOR.PATCH, maybe
+// with a pre-release like `-rc.1` and build metadata like `+build.5`.
+// Versions compare by their numbers, numerically, so 1.10.0 comes after
+// 1.9.0; a pre-release comes before the release itself; and build
+// metadata is ignored.
+#[derive(Debug, Clone)]
+pub struct Version {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+    pub pre: Vec<Identifier>,
+    pub build: String,
+}
+
+// One dot-separated part of a pre-release.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Identifier {
+    // Numeric identifiers compare numerically, and before alphanumeric
+    // ones (which is the order the variants are declared in).
+    Numeric(u64),
+    Alphanumeric(String),
+}
+
+impl Version {
+    fn cmp_precedence(&self, other: &Version) -> Ordering {
+        (self.major, self.minor, self.patch)
+            .cmp(&(other.major, other.minor, other.patch))
+            .then_with(|| match (self.pre.is_empty(), other.pre.is_empty()) {
+                (true, true) => Ordering::Equal,
+                (true, false) => Ordering::Greater,
+                (false, true) => Ordering::Less,
+                // Vec comparison is exactly the semver rule: compare the
+                // identifiers in turn, and if one runs out first it's
+                // the smaller.
+                (false, false) => self.pre.cmp(&other.pre),
+            })
+    }
+}
+
+impl PartialEq for Version {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp_precedence(other) == Ordering::Equal
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp_precedence(other))
+    }
+}
+
+// Parse a version like `1.2.3`, `v1.2.3`, `1.2.3-beta.2`, or
+// `1.2.3+20221103`.
+pub fn parse_version(text: &str) -> Result<Version, String> {
+    let bad = || format!("`{}` isn't a semantic version like 1.2.3", text);
+    let number = |part: &str| -> Result<u64, String> {
+        // No leading zeros, as the spec says.
+        if part.is_empty()
+            || !part.bytes().all(|b| b.is_ascii_digit())
+            || (part.len() > 1 && part.starts_with('0'))
+        {
+            return Err(bad());
+        }
+        part.parse().map_err(|_| bad())
+    };
+    let version = text.strip_prefix('v').unwrap_or(text);
+    let (version, build) = match version.find('+') {
+        Some(i) => (&version[..i], &version[i + 1..]),
+        None => (version, ""),
+    };
+    let (core, pre) = match version.find('-') {
+        Some(i) => (&version[..i], Some(&version[i + 1..])),
+        None => (version, None),
+    };
+    let parts: Vec<&str> = core.split('.').collect();
+    if parts.len() != 3 {
+        return Err(bad());
+    }
+    let pre = match pre {
+        None => Vec::new(),
+        Some(pre) => pre
+            .split('.')
+            .map(|part| {
+                if part.is_empty() || !part.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-')
+                {
+                    Err(bad())
+                } else if part.bytes().all(|b| b.is_ascii_digit()) {
+                    number(part).map(Identifier::Numeric)
+                } else {
+                    Ok(Identifier::Alphanumeric(part.to_string()))
+                }
+            })
+            .collect::<Result<_, _>>()?,
+    };
+    Ok(Version {
+        major: number(parts[0])?,
+        minor: number(parts[1])?,
+        patch: number(parts[2])?,
+        pre,
+        build: build.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(text: &str) -> i64 {
+        parse_timestamp(text).unwrap().seconds
+    }
+
+    #[test]
+    fn dates() {
+        assert_eq!(0, at("1970-01-01"));
+        assert_eq!(86_400, at("1970-01-02"));
+        assert_eq!(-86_400, at("1969-12-31"));
+        assert_eq!(951_782_400, at("2000-02-29"));
+        assert_eq!(1_667_433_600, at("2022-11-03"));
+    }
+
+    #[test]
+    fn times_and_offsets() {
+        assert_eq!(1_667_484_309, at("2022-11-03T14:05:09"));
+        assert_eq!(1_667_484_309, at("2022-11-03 14:05:09Z"));
+        assert_eq!(1_667_484_300, at("2022-11-03T14:05"));
+        assert_eq!(1_667_484_309 - 3600, at("2022-11-03T14:05:09+01:00"));
+        assert_eq!(1_667_484_309 + 5 * 3600, at("2022-11-03T14:05:09-0500"));
+        let t = parse_timestamp("2022-11-03T14:05:09.25").unwrap();
+        assert_eq!(250_000_000, t.nanos);
+        let t = parse_timestamp("2022-11-03T14:05:09.1234567891").unwrap();
+        assert_eq!(123_456_789, t.nanos);
+    }
+
+    #[test]
+    fn timestamps_sort_by_time_not_text() {
+        let mut keys: Vec<Key> = [
+            "2022-11-03T10:30:00Z",
+            "2022-11-03T09:05:00-02:00",
+            "2022-11-02",
+        ]
+        .iter()
+        .map(|t| KeyType::Date.parse(t).unwrap())
+        .collect();
+        crate::sorts::insertion_sort(&mut keys);
+        assert_eq!(KeyType::Date.parse("2022-11-02").unwrap(), keys[0]);
+        assert_eq!(
+            KeyType::Date.parse("2022-11-03T11:05:00Z").unwrap(),
+            keys[2]
+        );
+    }
+
+    #[test]
+    fn bad_dates() {
+        for bad in &[
+            "",
+            "2022",
+            "2022-13-01",
+            "2022-02-29",
+            "1900-02-29",
+            "2022-11-3",
+            "2022-11-03T25:00",
+            "2022-11-03T14",
+            "2022-11-03T14:05:09.",
+            "2022-11-03T14:05+1",
+            "2022-11-03 junk",
+            "03/11/2022",
+        ] {
+            assert!(parse_timestamp(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn durations() {
+        let d = |text| parse_duration(text).unwrap();
+        assert_eq!(Duration::from_secs(5400), d("1h30m"));
+        assert_eq!(Duration::from_secs(5400), d("1.5h"));
+        assert_eq!(Duration::from_secs(5400), d("1h 30m"));
+        assert_eq!(Duration::from_secs(2 * 86_400 + 4 * 3600), d("2d4h"));
+        assert_eq!(Duration::from_secs(604_800), d("1w"));
+        assert_eq!(Duration::from_millis(250), d("250ms"));
+        assert_eq!(Duration::from_micros(3), d("3µs"));
+        assert_eq!(Duration::from_secs(90), d("90"));
+        assert!(d("59m") < d("1h"));
+    }
+
+    #[test]
+    fn bad_durations() {
+        for bad in &["", "h", "1x", "1h30", "-5s", "1..5h", "forever"] {
+            assert!(parse_duration(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn addresses() {
+        let v4 = |text| KeyType::Ipv4.parse(text).unwrap();
+        assert!(v4("10.0.0.2") < v4("10.0.0.10"));
+        assert!(v4("9.255.255.255") < v4("10.0.0.0"));
+        assert!(KeyType::Ipv4.parse("10.0.0.256").is_err());
+        let v6 = |text| KeyType::Ipv6.parse(text).unwrap();
+        assert!(v6("::1") < v6("fe80::1"));
+        assert!(v6("fe80::2") < v6("fe80::10"));
+        assert_eq!(v6("fe80::1"), v6("fe80:0:0:0:0:0:0:1"));
+        assert!(KeyType::Ipv6.parse("10.0.0.1").is_err());
+    }
+
+    #[test]
+    fn versions_in_precedence_order() {
+        // The example from the semver spec, plus a couple of our own.
+        let ordered = [
+            "1.0.0-alpha",
+            "1.0.0-alpha.1",
+            "1.0.0-alpha.beta",
+            "1.0.0-beta",
+            "1.0.0-beta.2",
+            "1.0.0-beta.11",
+            "1.0.0-rc.1",
+            "1.0.0",
+            "v1.9.0",
+            "1.10.0",
+        ];
+        for pair in ordered.windows(2) {
+            let a = parse_version(pair[0]).unwrap();
+            let b = parse_version(pair[1]).unwrap();
+            assert!(a < b, "{} < {}", pair[0], pair[1]);
+        }
+        let built = parse_version("1.0.0+build.5").unwrap();
+        assert_eq!(parse_version("1.0.0").unwrap(), built);
+        assert_eq!("build.5", built.build);
+    }
+
+    #[test]
+    fn bad_versions() {
+        for bad in &[
+            "",
+            "1",
+            "1.2",
+            "1.2.3.4",
+            "01.2.3",
+            "1.2.x",
+            "1.2.3-",
+            "1.2.3-a..b",
+            "1.2.3-01",
+            "1.2.3-a_b",
+        ] {
+            assert!(parse_version(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn key_types() {
+        for name in &[
+            "text", "number", "date", "duration", "ipv4", "ipv6", "semver",
+        ] {
+            assert_eq!(*name, KeyType::from_name(name).unwrap().name());
+        }
+        assert_eq!("date", KeyType::Date.name());
+        assert!(KeyType::Number.parse("NaN").is_err());
+        assert!(KeyType::Number.parse("-2").unwrap() < KeyType::Number.parse("10").unwrap());
+        assert!(KeyType::Text.parse("10").unwrap() < KeyType::Text.parse("9").unwrap());
+    }
+}
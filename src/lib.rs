@@ -0,0 +1,71 @@
+// The reusable pieces of the sorting lab live here so that they can be
+// used (and tested) independently of the timing code in `main.rs`.
+
+#![cfg_attr(feature = "allocator-api", feature(allocator_api))]
+
+pub mod adaptive;
+pub mod analysis;
+pub mod auto;
+pub mod benchmark;
+pub mod bounds;
+pub mod branches;
+pub mod budget;
+pub mod bundle;
+pub mod cache_sim;
+pub mod cases;
+pub mod certificate;
+pub mod chars;
+pub mod checked;
+pub mod cli;
+pub mod comparator;
+pub mod complexity;
+pub mod config;
+pub mod configured;
+pub mod deque;
+pub mod error;
+pub mod fallible;
+pub mod generate;
+#[cfg(feature = "gpu")]
+pub mod gpu;
+pub mod grade;
+pub mod heap;
+#[cfg(all(feature = "hugepages", target_os = "linux"))]
+pub mod hugepages;
+pub mod input;
+pub mod instrument;
+pub mod json;
+pub mod keys;
+pub mod lines;
+pub mod list;
+pub mod merge;
+pub mod mutants;
+#[cfg(all(feature = "numa", target_os = "linux"))]
+pub mod numa;
+pub mod opcount;
+pub mod outcome;
+pub mod partition;
+pub mod passes;
+pub mod pool;
+pub mod prefetch;
+pub mod random;
+pub mod ranking;
+#[cfg(feature = "reference")]
+pub mod reference;
+pub mod repl;
+pub mod report;
+pub mod sampling;
+#[cfg(feature = "unsafe-opt")]
+pub mod scratch;
+pub mod search;
+pub mod select;
+pub mod soak;
+pub mod sorted_ops;
+pub mod sorted_vec;
+pub mod sorter;
+pub mod sorts;
+pub mod stream;
+pub mod strings;
+pub mod suffix;
+pub mod trace;
+pub mod tuned;
+pub mod yielding;
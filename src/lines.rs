@@ -0,0 +1,527 @@
+// Sorting lines of text by a key: the whole line, or one field of it,
+// interpreted as text, a number, a date, or a duration (see `keys.rs`).
+// Fields are separated by whitespace, or by a delimiter; with a
+// delimiter the line is split like a CSV row, so a field in double
+// quotes can contain the delimiter (and `""` stands for a quote).
+//
+// The sort is stable (it's our merge sort), so lines with equal keys
+// stay in the order they were in.
+//
+// A line whose key is missing or can't be parsed is an error by
+// default, but it can also be put after all the others (in the order
+// they came) or left out.
+//
+// For big files there's also `LineBuffer`, which sorts the lines of a
+// single buffer by index without copying any of them. The buffer is
+// the whole file read into memory, unless we're built with the `mmap`
+// feature, which can map the file instead (`LineBuffer::map`).
+
+use crate::keys::KeyType;
+use crate::sorts::merge_sort;
+use std::borrow::Cow;
+use std::cmp::Ordering;
+#[cfg(feature = "mmap")]
+use std::fs::File;
+use std::io::{self, Write};
+use std::ops::Range;
+
+// What to do with lines whose keys can't be parsed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnparsablePolicy {
+    Error,
+    SortLast,
+    Skip,
+}
+
+impl UnparsablePolicy {
+    pub fn name(self) -> &'static str {
+        match self {
+            UnparsablePolicy::Error => "error",
+            UnparsablePolicy::SortLast => "sort-last",
+            UnparsablePolicy::Skip => "skip",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<UnparsablePolicy> {
+        match name {
+            "error" => Some(UnparsablePolicy::Error),
+            "sort-last" => Some(UnparsablePolicy::SortLast),
+            "skip" => Some(UnparsablePolicy::Skip),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineSortConfig {
+    pub key_type: KeyType,
+    // Which field (counting from 1) holds the key, or `None` for the
+    // whole line.
+    pub field: Option<usize>,
+    // What separates fields, or `None` for runs of whitespace.
+    pub delimiter: Option<char>,
+    // Leave the first line where it is.
+    pub header: bool,
+    pub unparsable: UnparsablePolicy,
+}
+
+impl Default for LineSortConfig {
+    fn default() -> LineSortConfig {
+        LineSortConfig {
+            key_type: KeyType::Text,
+            field: None,
+            delimiter: None,
+            header: false,
+            unparsable: UnparsablePolicy::Error,
+        }
+    }
+}
+
+// Split a CSV-style row on `delimiter`.
+pub fn split_fields(line: &str, delimiter: char) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' if quoted => quoted = false,
+            '"' if field.is_empty() => quoted = true,
+            c if c == delimiter && !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+// The text of the key for one line.
+pub fn key_text(line: &str, config: &LineSortConfig) -> Result<String, String> {
+    key_slice(line, config).map(Cow::into_owned)
+}
+
+// The same, but borrowed from the line whenever it can be: everything
+// except a CSV field with quotes in it, which has to be unescaped.
+fn key_slice<'a>(line: &'a str, config: &LineSortConfig) -> Result<Cow<'a, str>, String> {
+    let field = match config.field {
+        None => return Ok(Cow::Borrowed(line)),
+        Some(field) => field,
+    };
+    let found = match config.delimiter {
+        None => line.split_whitespace().nth(field - 1).map(Cow::Borrowed),
+        Some(delimiter) if !line.contains('"') => {
+            line.split(delimiter).nth(field - 1).map(Cow::Borrowed)
+        }
+        Some(delimiter) => split_fields(line, delimiter)
+            .into_iter()
+            .nth(field - 1)
+            .map(Cow::Owned),
+    };
+    found.ok_or_else(|| format!("there's no field {}", field))
+}
+
+// A line's key along with its position, so we can sort the (cheap to
+// copy) pairs rather than the lines themselves. A missing key (`None`)
+// comes after every key.
+#[derive(Debug)]
+struct Keyed<'a, K> {
+    key: Option<&'a K>,
+    index: usize,
+}
+
+// Not derived, since that would insist on `K: Copy`, and we only copy
+// the reference.
+impl<K> Clone for Keyed<'_, K> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<K> Copy for Keyed<'_, K> {}
+
+impl<K: PartialOrd> PartialEq for Keyed<'_, K> {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+
+impl<K: PartialOrd> PartialOrd for Keyed<'_, K> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match (self.key, other.key) {
+            (Some(a), Some(b)) => a.partial_cmp(b),
+            (Some(_), None) => Some(Ordering::Less),
+            (None, Some(_)) => Some(Ordering::Greater),
+            (None, None) => Some(Ordering::Equal),
+        }
+    }
+}
+
+// Sort the lines by their keys. Errors name the (1-based) line number
+// whose key couldn't be found or parsed.
+pub fn sort_lines<'a>(lines: &[&'a str], config: &LineSortConfig) -> Result<Vec<&'a str>, String> {
+    let order = sort_order(lines.len(), |i| lines[i], config)?;
+    Ok(order.into_iter().map(|i| lines[i]).collect())
+}
+
+// The order the `count` lines that `line` gives us should go in, as
+// indices. Text keys are compared just as they are, so they're borrowed
+// straight from the lines instead of being copied into a `Key`.
+fn sort_order<'a, L>(count: usize, line: L, config: &LineSortConfig) -> Result<Vec<usize>, String>
+where
+    L: Fn(usize) -> &'a str,
+{
+    if config.key_type == KeyType::Text {
+        order_by(count, line, config, |text| key_slice(text, config))
+    } else {
+        order_by(count, line, config, |text| {
+            key_slice(text, config).and_then(|key| config.key_type.parse(&key))
+        })
+    }
+}
+
+fn order_by<'a, L, F, K>(
+    count: usize,
+    line: L,
+    config: &LineSortConfig,
+    key: F,
+) -> Result<Vec<usize>, String>
+where
+    L: Fn(usize) -> &'a str,
+    F: Fn(&'a str) -> Result<K, String>,
+    K: PartialOrd + std::fmt::Debug,
+{
+    let first = if config.header && count > 0 { 1 } else { 0 };
+    let mut keys = Vec::with_capacity(count - first);
+    for i in first..count {
+        match key(line(i)) {
+            Ok(key) => keys.push(Some(key)),
+            Err(message) => match config.unparsable {
+                UnparsablePolicy::Error => return Err(format!("line {}: {}", i + 1, message)),
+                UnparsablePolicy::SortLast | UnparsablePolicy::Skip => keys.push(None),
+            },
+        }
+    }
+    let keyed: Vec<Keyed<K>> = keys
+        .iter()
+        .enumerate()
+        .filter(|(_, key)| key.is_some() || config.unparsable != UnparsablePolicy::Skip)
+        .map(|(index, key)| Keyed {
+            key: key.as_ref(),
+            index: first + index,
+        })
+        .collect();
+    let mut order: Vec<usize> = (0..first).collect();
+    order.extend(merge_sort(&keyed).into_iter().map(|k| k.index));
+    Ok(order)
+}
+
+// A whole file's worth of lines kept in the one buffer they were read
+// into, with each line just a range of offsets into it. Sorting gives a
+// permutation of the line numbers, and the output is written straight
+// from the buffer in that order, so no line is ever copied into a
+// `String` of its own. For big files that saves an allocation per line
+// (and the memory for them).
+#[derive(Debug)]
+pub struct LineBuffer {
+    text: Text,
+    lines: Vec<Range<usize>>,
+}
+
+// Where the buffer's text lives: a `String` we read the file into, or
+// (with the `mmap` feature) the file itself, mapped into memory, so the
+// operating system pages it in as we go and the file doesn't have to
+// fit in memory all at once.
+#[derive(Debug)]
+enum Text {
+    Owned(String),
+    #[cfg(feature = "mmap")]
+    Mapped(memmap2::Mmap),
+}
+
+impl LineBuffer {
+    pub fn new(text: String) -> LineBuffer {
+        let lines = split(text.as_bytes());
+        LineBuffer {
+            text: Text::Owned(text),
+            lines,
+        }
+    }
+
+    // Map `file` into memory rather than reading it. The whole file is
+    // checked to be UTF-8 up front, so a bad file is an error here and
+    // not halfway through the sort.
+    //
+    // Mapping a file is unsafe because the file could be changed (by
+    // another process) while it's mapped, and then the bytes we've
+    // checked could change under us. `line` checks each line again as
+    // it hands it out, so that can only make it panic.
+    #[cfg(feature = "mmap")]
+    pub fn map(file: &File) -> io::Result<LineBuffer> {
+        let map = unsafe { memmap2::Mmap::map(file)? };
+        std::str::from_utf8(&map)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+        let lines = split(&map);
+        Ok(LineBuffer {
+            text: Text::Mapped(map),
+            lines,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.lines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    pub fn line(&self, i: usize) -> &str {
+        let range = self.lines[i].clone();
+        match &self.text {
+            Text::Owned(text) => &text[range],
+            #[cfg(feature = "mmap")]
+            Text::Mapped(map) => std::str::from_utf8(&map[range])
+                .expect("the mapped file changed while we were sorting it"),
+        }
+    }
+
+    // The order the lines should go in; see `sort_lines`.
+    pub fn sort(&self, config: &LineSortConfig) -> Result<Vec<usize>, String> {
+        sort_order(self.len(), |i| self.line(i), config)
+    }
+
+    // Write the lines in `order`, each followed by `\n`.
+    pub fn write_in_order<W: Write>(&self, order: &[usize], mut output: W) -> io::Result<()> {
+        for &i in order {
+            output.write_all(self.line(i).as_bytes())?;
+            output.write_all(b"\n")?;
+        }
+        output.flush()
+    }
+}
+
+// Split `text` into lines the way `str::lines` does: on `\n`, with a
+// `\r` before it dropped, and no empty line after a final `\n`.
+fn split(text: &[u8]) -> Vec<Range<usize>> {
+    let mut lines = Vec::new();
+    let mut start = 0;
+    for (i, &byte) in text.iter().enumerate() {
+        if byte == b'\n' {
+            let end = if i > start && text[i - 1] == b'\r' { i - 1 } else { i };
+            lines.push(start..end);
+            start = i + 1;
+        }
+    }
+    if start < text.len() {
+        lines.push(start..text.len());
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sort(text: &str, config: &LineSortConfig) -> Result<Vec<String>, String> {
+        let lines: Vec<&str> = text.lines().collect();
+        sort_lines(&lines, config).map(|sorted| sorted.into_iter().map(String::from).collect())
+    }
+
+    #[test]
+    fn whole_lines() {
+        let sorted = sort("pear\napple\nfig", &LineSortConfig::default()).unwrap();
+        assert_eq!(vec!["apple", "fig", "pear"], sorted);
+        assert!(sort("", &LineSortConfig::default()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn csv_fields() {
+        assert_eq!(
+            vec!["a", "b,c", "say \"hi\"", ""],
+            split_fields("a,\"b,c\",\"say \"\"hi\"\"\",", ',')
+        );
+        let config = LineSortConfig {
+            key_type: KeyType::Number,
+            field: Some(2),
+            delimiter: Some(','),
+            header: true,
+            ..LineSortConfig::default()
+        };
+        let sorted = sort("name,size\n\"b, inc\",10\na,9\nc,-1", &config).unwrap();
+        assert_eq!(vec!["name,size", "c,-1", "a,9", "\"b, inc\",10"], sorted);
+    }
+
+    #[test]
+    fn log_lines_by_timestamp() {
+        let log = "\
+2022-11-03T10:30:00Z started
+2022-11-03T09:05:00-02:00 stopped
+2022-11-02T23:59:59Z booted";
+        let config = LineSortConfig {
+            key_type: KeyType::Date,
+            field: Some(1),
+            ..LineSortConfig::default()
+        };
+        let sorted = sort(log, &config).unwrap();
+        let words: Vec<&str> = sorted
+            .iter()
+            .map(|l| l.split(' ').nth(1).unwrap())
+            .collect();
+        assert_eq!(vec!["booted", "started", "stopped"], words);
+    }
+
+    #[test]
+    fn durations_are_stable() {
+        let config = LineSortConfig {
+            key_type: KeyType::Duration,
+            field: Some(2),
+            ..LineSortConfig::default()
+        };
+        let sorted = sort("a 1h30m\nb 90m\nc 45s\nd 1.5h", &config).unwrap();
+        assert_eq!(vec!["c 45s", "a 1h30m", "b 90m", "d 1.5h"], sorted);
+    }
+
+    #[test]
+    fn unparsable_lines() {
+        let text = "10.0.0.10 b\nnot-an-address a\n10.0.0.2 c\n\n10.0.0.1 d";
+        let mut config = LineSortConfig {
+            key_type: KeyType::Ipv4,
+            field: Some(1),
+            ..LineSortConfig::default()
+        };
+        assert_eq!(
+            Err("line 2: `not-an-address` isn't an IPv4 address".to_string()),
+            sort(text, &config)
+        );
+        config.unparsable = UnparsablePolicy::SortLast;
+        assert_eq!(
+            vec![
+                "10.0.0.1 d",
+                "10.0.0.2 c",
+                "10.0.0.10 b",
+                "not-an-address a",
+                ""
+            ],
+            sort(text, &config).unwrap()
+        );
+        config.unparsable = UnparsablePolicy::Skip;
+        assert_eq!(
+            vec!["10.0.0.1 d", "10.0.0.2 c", "10.0.0.10 b"],
+            sort(text, &config).unwrap()
+        );
+    }
+
+    #[test]
+    fn semver() {
+        let config = LineSortConfig {
+            key_type: KeyType::Semver,
+            ..LineSortConfig::default()
+        };
+        assert_eq!(
+            vec!["1.0.0-rc.1", "1.0.0", "1.9.0", "1.10.0"],
+            sort("1.10.0\n1.0.0\n1.9.0\n1.0.0-rc.1", &config).unwrap()
+        );
+    }
+
+    #[test]
+    fn errors_name_the_line() {
+        let config = LineSortConfig {
+            key_type: KeyType::Number,
+            field: Some(2),
+            header: true,
+            ..LineSortConfig::default()
+        };
+        assert_eq!(
+            Err("line 3: there's no field 2".to_string()),
+            sort("x y\na 1\nb", &config)
+        );
+        assert_eq!(
+            Err("line 2: `one` isn't a number".to_string()),
+            sort("x y\na one", &config)
+        );
+    }
+
+    #[test]
+    fn buffer_splits_like_lines() {
+        for text in &["", "a", "a\n", "a\r\nb", "\n\nc\r\n", "x\ry\n"] {
+            let buffer = LineBuffer::new(text.to_string());
+            let lines: Vec<&str> = (0..buffer.len()).map(|i| buffer.line(i)).collect();
+            assert_eq!(text.lines().collect::<Vec<&str>>(), lines, "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn buffer_sorts_like_sort_lines() {
+        let text = "name,size\n\"b, inc\",10\r\na,9\nc,-1\nd,9\n";
+        for &key_type in &[KeyType::Text, KeyType::Number] {
+            let config = LineSortConfig {
+                key_type,
+                field: Some(2),
+                delimiter: Some(','),
+                header: true,
+                ..LineSortConfig::default()
+            };
+            let buffer = LineBuffer::new(text.to_string());
+            let order = buffer.sort(&config).unwrap();
+            let mut output = Vec::new();
+            buffer.write_in_order(&order, &mut output).unwrap();
+            let mut expected = sort(text, &config).unwrap().join("\n");
+            expected.push('\n');
+            assert_eq!(expected, String::from_utf8(output).unwrap());
+        }
+        let buffer = LineBuffer::new("b\n\na\n".to_string());
+        let config = LineSortConfig {
+            field: Some(1),
+            unparsable: UnparsablePolicy::Skip,
+            ..LineSortConfig::default()
+        };
+        assert_eq!(vec![2, 0], buffer.sort(&config).unwrap());
+        assert!(LineBuffer::new(String::new()).is_empty());
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn mapped_buffer_matches_the_read_one() {
+        let path = std::env::temp_dir().join(format!("lines-mmap-{}.txt", std::process::id()));
+        let config = LineSortConfig::default();
+        for text in &["", "c\r\nb\na", "z\ny\n\nx\n"] {
+            std::fs::write(&path, text).unwrap();
+            let mapped = LineBuffer::map(&File::open(&path).unwrap()).unwrap();
+            let read = LineBuffer::new(text.to_string());
+            assert_eq!(read.sort(&config).unwrap(), mapped.sort(&config).unwrap());
+            let lines: Vec<&str> = (0..mapped.len()).map(|i| mapped.line(i)).collect();
+            assert_eq!(text.lines().collect::<Vec<&str>>(), lines);
+        }
+        std::fs::write(&path, b"ok\n\xff\n").unwrap();
+        assert!(LineBuffer::map(&File::open(&path).unwrap()).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn keys_are_borrowed_when_they_can_be() {
+        let config = LineSortConfig {
+            field: Some(2),
+            delimiter: Some(','),
+            ..LineSortConfig::default()
+        };
+        assert!(matches!(
+            key_slice("a,b,c", &config),
+            Ok(Cow::Borrowed("b"))
+        ));
+        assert_eq!("b,c", key_slice("a,\"b,c\"", &config).unwrap());
+        assert!(matches!(
+            key_slice(
+                "a  b",
+                &LineSortConfig {
+                    delimiter: None,
+                    ..config
+                }
+            ),
+            Ok(Cow::Borrowed("b"))
+        ));
+    }
+}
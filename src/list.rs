@@ -0,0 +1,280 @@
+// A singly linked list, and merge sort for it.
+//
+// Everything else in the crate sorts slices, where we can get at any
+// element in O(1). A linked list only lets you walk from the front, which
+// rules out most of the sorts: quicksort and heapsort both jump about,
+// and insertion sort is O(N^2) whatever you store things in. Merge sort
+// only ever looks at the fronts of two sequences and takes the smaller,
+// which is exactly what a list is good at. Better still, a list merge
+// doesn't need the O(N) scratch space a slice merge does: we just relink
+// the nodes we already have. And if we merge bottom up (runs of 1 into
+// runs of 2, then 4, and so on) rather than recursing, there's no stack
+// either, so `List::sort` takes O(N log N) time and O(1) extra space.
+//
+// (`std::collections::LinkedList` is doubly linked, but doesn't let you
+// relink its nodes, so the best you could do with it is `split_off` and
+// `append`, which have to walk the list to find where to split.)
+
+// `None` is the end of the list.
+type Link<T> = Option<Box<Node<T>>>;
+
+#[derive(Debug)]
+struct Node<T> {
+    value: T,
+    next: Link<T>,
+}
+
+#[derive(Debug)]
+pub struct List<T> {
+    head: Link<T>,
+    len: usize,
+}
+
+impl<T> List<T> {
+    pub fn new() -> List<T> {
+        List { head: None, len: 0 }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.head.is_none()
+    }
+
+    pub fn push_front(&mut self, value: T) {
+        let next = self.head.take();
+        self.head = Some(Box::new(Node { value, next }));
+        self.len += 1;
+    }
+
+    pub fn pop_front(&mut self) -> Option<T> {
+        let node = self.head.take()?;
+        self.head = node.next;
+        self.len -= 1;
+        Some(node.value)
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            next: self.head.as_deref(),
+        }
+    }
+}
+
+impl<T: PartialOrd> List<T> {
+    // Sort the list (stably) by relinking its nodes; see the top of the
+    // file.
+    pub fn sort(&mut self) {
+        let mut width = 1;
+        loop {
+            // One pass: cut the list into pairs of runs `width` long, and
+            // merge each pair onto the end of the new list.
+            let mut rest = self.head.take();
+            let mut tail = &mut self.head;
+            let mut merges = 0;
+            while rest.is_some() {
+                let (left, after_left) = split(rest, width);
+                let (right, after_right) = split(after_left, width);
+                tail = merge_onto(tail, left, right);
+                rest = after_right;
+                merges += 1;
+            }
+            // Once a pass only had one pair to merge, it's all one run.
+            if merges <= 1 {
+                return;
+            }
+            width *= 2;
+        }
+    }
+}
+
+// Cut `list` after its first `n` nodes, giving back the two halves.
+fn split<T>(mut list: Link<T>, n: usize) -> (Link<T>, Link<T>) {
+    let mut cursor = &mut list;
+    for _ in 0..n {
+        match cursor {
+            Some(node) => cursor = &mut node.next,
+            None => break,
+        }
+    }
+    let rest = cursor.take();
+    (list, rest)
+}
+
+// Merge the sorted lists `left` and `right` onto `tail` (the end of
+// another list), and return the new end.
+fn merge_onto<T: PartialOrd>(
+    mut tail: &mut Link<T>,
+    mut left: Link<T>,
+    mut right: Link<T>,
+) -> &mut Link<T> {
+    while let (Some(l), Some(r)) = (&left, &right) {
+        // Taking from the left when the two are equal keeps the sort
+        // stable.
+        let from = if r.value < l.value {
+            &mut right
+        } else {
+            &mut left
+        };
+        let mut node = from.take().unwrap();
+        *from = node.next.take();
+        tail = &mut tail.insert(node).next;
+    }
+    // Whatever's left is already in order, so link it on as it is.
+    *tail = left.or(right);
+    while let Some(node) = tail {
+        tail = &mut node.next;
+    }
+    tail
+}
+
+impl<T> Default for List<T> {
+    fn default() -> List<T> {
+        List::new()
+    }
+}
+
+// The default drop would drop the first node, which drops the second,
+// and so on, one stack frame per node, which overflows the stack on a
+// long list. So we unlink them one at a time.
+impl<T> Drop for List<T> {
+    fn drop(&mut self) {
+        let mut next = self.head.take();
+        while let Some(mut node) = next {
+            next = node.next.take();
+        }
+    }
+}
+
+// The values in order (so the first one ends up at the front).
+impl<T> From<Vec<T>> for List<T> {
+    fn from(values: Vec<T>) -> List<T> {
+        let mut list = List::new();
+        for value in values.into_iter().rev() {
+            list.push_front(value);
+        }
+        list
+    }
+}
+
+impl<T> From<List<T>> for Vec<T> {
+    fn from(mut list: List<T>) -> Vec<T> {
+        let mut values = Vec::with_capacity(list.len());
+        while let Some(value) = list.pop_front() {
+            values.push(value);
+        }
+        values
+    }
+}
+
+pub struct Iter<'a, T> {
+    next: Option<&'a Node<T>>,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        let node = self.next?;
+        self.next = node.next.as_deref();
+        Some(&node.value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instrument::{count_comparisons, counted, Counted};
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    fn sorted(values: Vec<i32>) -> Vec<i32> {
+        let mut list = List::from(values);
+        list.sort();
+        Vec::from(list)
+    }
+
+    #[test]
+    fn ten_items() {
+        assert_eq!(
+            vec![0, 0, 2, 2, 3, 3, 5, 6, 8, 9],
+            sorted(vec![3, 2, 0, 5, 8, 9, 6, 3, 2, 0])
+        );
+    }
+
+    #[test]
+    fn small_lists() {
+        assert_eq!(Vec::<i32>::new(), sorted(vec![]));
+        assert_eq!(vec![7], sorted(vec![7]));
+        assert_eq!(vec![1, 2], sorted(vec![2, 1]));
+        assert_eq!(vec![1, 2, 3], sorted(vec![3, 1, 2]));
+    }
+
+    #[test]
+    fn random_lists() {
+        let mut rng = StdRng::seed_from_u64(271);
+        for _ in 0..100 {
+            let values: Vec<i32> = (0..rng.gen_range(0, 200))
+                .map(|_| rng.gen_range(0, 50))
+                .collect();
+            let mut expected = values.clone();
+            expected.sort();
+            assert_eq!(expected, sorted(values));
+        }
+    }
+
+    #[test]
+    fn stable() {
+        // Compare on the first field only.
+        #[derive(Debug, Clone, Copy, PartialEq)]
+        struct Tagged(i32, char);
+        impl PartialOrd for Tagged {
+            fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+                self.0.partial_cmp(&other.0)
+            }
+        }
+        let mut list = List::from(vec![
+            Tagged(2, 'a'),
+            Tagged(1, 'a'),
+            Tagged(2, 'b'),
+            Tagged(1, 'b'),
+            Tagged(2, 'c'),
+        ]);
+        list.sort();
+        let tags: String = list.iter().map(|t| t.1).collect();
+        assert_eq!("ababc", tags);
+    }
+
+    #[test]
+    fn n_log_n_comparisons() {
+        let mut rng = StdRng::seed_from_u64(271);
+        let values: Vec<i32> = (0..1024).map(|_| rng.gen()).collect();
+        let mut list: List<Counted<i32>> = List::from(counted(&values));
+        let ((), comparisons) = count_comparisons(|| list.sort());
+        assert!(comparisons <= 1024 * 10, "{}", comparisons);
+        assert_eq!(1024, list.len());
+    }
+
+    #[test]
+    fn long_lists() {
+        // Long enough that recursing once per node (in the sort or in
+        // dropping the list) would overflow the stack.
+        let mut list = List::from((0..200_000).rev().collect::<Vec<i32>>());
+        list.sort();
+        assert!(list.iter().zip(0..).all(|(&x, i)| x == i));
+    }
+
+    #[test]
+    fn push_and_pop() {
+        let mut list = List::new();
+        assert!(list.is_empty());
+        list.push_front(1);
+        list.push_front(2);
+        assert_eq!(2, list.len());
+        assert_eq!(Some(2), list.pop_front());
+        assert_eq!(Some(1), list.pop_front());
+        assert_eq!(None, list.pop_front());
+        assert_eq!(0, list.len());
+    }
+}
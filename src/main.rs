@@ -1,303 +1,614 @@
-use rand::{thread_rng, Rng};
-use std::time::{Instant};
+use sorting_in_rust::analysis::{
+    duplicates, equi_depth_buckets, equi_width_histogram, render_bars, runs, summarize,
+};
+use sorting_in_rust::auto::auto_sort;
+use sorting_in_rust::benchmark::{
+    check_inputs, find_algorithm, print_results, run_matrix, Algorithm, Distribution, MatrixConfig,
+    RunResult,
+};
+use sorting_in_rust::branches::{correlation, outcome_profile};
+use sorting_in_rust::budget::{print_budget_table, run_budgeted};
+use sorting_in_rust::bundle::write_bundle;
+use sorting_in_rust::cache_sim::{cache_profile, CacheConfig};
+use sorting_in_rust::certificate::{self, certify, verify, Certificate};
+use sorting_in_rust::cli::{parse_args, Command, ComplexitySource, ScriptOptions, USAGE};
+use sorting_in_rust::complexity::{growth_table, measurements_from_json, render, Measurement};
+use sorting_in_rust::generate::generate_random_array;
+use sorting_in_rust::grade::{self, Subject};
+use sorting_in_rust::input::{read_numbers, read_sorted_numbers};
+use sorting_in_rust::lines::{self, LineBuffer, LineSortConfig};
+use sorting_in_rust::mutants::{bugs_from_env, with_bugs, Bug};
+use sorting_in_rust::opcount::{counts_to_json, counts_to_markdown, print_counts, run_counts};
+use sorting_in_rust::outcome::run_sort;
+use sorting_in_rust::random::{seed_from, SeededRandom, SEED_VAR};
+use sorting_in_rust::ranking::{rank, summary};
+use sorting_in_rust::repl;
+use sorting_in_rust::report::{summary_json, to_json, to_markdown, ReportFormat, RunStatus};
+use sorting_in_rust::soak::run_soak;
+use sorting_in_rust::sorted_ops::SetOperation;
+use sorting_in_rust::sorts::ParallelConfig;
+use sorting_in_rust::trace::{diff, record, Trace};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
 
 fn main() {
-    // Feel free to raise size if you want to see the timing difference
-    // between the different algorithms. Since insertion sort is O(N^2)
-    // and the other two are O(N log N), you should definitely be able
-    // to see a difference between it and the two faster algorithms.
-    let size = 1000; // 100000;
-    let v = generate_random_array(size, 0, size);
-
-    let mut u = v.clone();
-    let before_insertion = Instant::now();
-    insertion_sort(&mut u);
-    println!("Elapsed time for insertion sort was {:?}.", before_insertion.elapsed());
-
-    let mut w = v.clone();
-    // println!("{:?}", &w);
-    let before_quicksort = Instant::now();
-    quicksort(&mut w);
-    println!("Elapsed time for quicksort was {:?}.", before_quicksort.elapsed());
-    // println!("{:?}", &w);
-
-    let before_merged = Instant::now();
-    let merged_v = merge_sort(&v);
-    println!("Elapsed time for merge sort was {:?}.", before_merged.elapsed());
-    // println!("{:?}", v);
-    // println!("{:?}", merged_v);
-    println!("Is the original, random list in order?: {:?}", is_sorted(&v));
-    println!("Was insertion sort in order?: {:?}", is_sorted(&u));
-    println!("Was quicksort in order?: {:?}", is_sorted(&w));
-    println!("Was merge sort in order?: {:?}", is_sorted(&merged_v));
+    let command = match parse_args(std::env::args().skip(1)) {
+        Ok(command) => command,
+        Err(message) => {
+            eprintln!("error: {}\n\n{}", message, USAGE);
+            std::process::exit(2);
+        }
+    };
+    // Catch bad `SORTLAB_*` settings here, rather than having
+    // parallel-merge quietly ignore them later.
+    if let Err(message) = ParallelConfig::from_env() {
+        eprintln!("error: {}", message);
+        std::process::exit(2);
+    }
+    let result = match command {
+        Command::Demo => demo(),
+        Command::Help => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        Command::Matrix { config, script } => {
+            let status = matrix(*config, &script);
+            if let Some(message) = status.message() {
+                eprintln!("error: {}", message);
+            }
+            std::process::exit(status.exit_code());
+        }
+        Command::SetOp { operation, left, right } => set_op(operation, &left, &right),
+        Command::Analyze { file, report_dupes } => analyze(&file, report_dupes),
+        Command::Stats { file } => stats(&file),
+        Command::Histogram { file, bins, equi_depth } => histogram(&file, bins, equi_depth),
+        Command::Grade {
+            subjects,
+            bugs,
+            mutants,
+        } => grade(&subjects, bugs, mutants),
+        #[cfg(feature = "reference")]
+        Command::DiffTest { cases, seed } => diff_test(cases, seed),
+        Command::Budget {
+            algorithms,
+            sizes,
+            distribution,
+            steps,
+            seed,
+        } => budget(&algorithms, &sizes, distribution, steps, seed),
+        Command::CacheSim {
+            algorithms,
+            size,
+            distribution,
+            seed,
+            cache,
+        } => cache_sim(&algorithms, &distribution.generate(size, seed), cache),
+        Command::Branches {
+            algorithms,
+            size,
+            distribution,
+            seed,
+            trials,
+        } => branches(&algorithms, &distribution.generate(size, seed), trials),
+        Command::Trace {
+            algorithm,
+            size,
+            distribution,
+            seed,
+            output,
+        } => trace(&algorithm, distribution.generate(size, seed), output.as_deref()),
+        Command::Replay { file } => replay(&file),
+        Command::Certify {
+            algorithm,
+            size,
+            distribution,
+            seed,
+            output,
+        } => certify_sort(&algorithm, distribution.generate(size, seed), output.as_deref()),
+        Command::VerifyCertificate { file, replay } => verify_certificate(&file, replay),
+        Command::TraceDiff { left, right } => trace_diff(&left, &right),
+        Command::ExplainComplexity(source) => explain_complexity(source),
+        Command::SortLines {
+            file,
+            config,
+            zero_copy,
+        } => sort_lines(&file, &config, zero_copy),
+        Command::Repl => {
+            let stdin = std::io::stdin();
+            repl::run(stdin.lock(), std::io::stdout()).map_err(|error| error.to_string())
+        }
+    };
+    if let Err(message) = result {
+        eprintln!("error: {}", message);
+        std::process::exit(1);
+    }
 }
 
-// Insertion sort is "in place", so we modify the input array v
-// directly and do _not_ return anything. The elements of the
-// array need to traits `PartialOrd` (so they support < and ≤).
-// Also requiring the trait `Debug` means you can print the array
-// and slices of the array for debugging purposes with `{:?}`. I
-// don't do that here, but you could add some print statements if,
-// for example, you want to watch the bubbling happen.
-//
-// Note that the parameter v *has* to be mutable because we're 
-// modifying it in place.
-fn insertion_sort<T: PartialOrd + std::fmt::Debug>(v: &mut [T]) {
-    // Goal: (All x, y | 0 ≤ x < y < length : v[x] ≤ v[y])
-    for i in 0..v.len() {
-        // Invariant: (All x, y | 0 ≤ x < y < i : v[x] ≤ v[y])
-        // I.e., we assume everything < i is already sorted
-        // by previous passes. Now we want to get everything
-        // ≤ i to be sorted. This requires "bubbling" v[i]
-        // to the left until it "finds its spot", i.e., until
-        // swapping it one more time would make it _larger_
-        // than the value to its right.
-        //
-        // j is where we are in the bubbling process, so we
-        // start with j=i.
-        let mut j = i;
-        // If j > 0 we might still need to move left, so continue. 
-        // But _only_ continue if v[j] _should_ move left, i.e.,
-        // if it's less than the value to its left (so those two
-        // are out of order.)
-        while j > 0 && v[j-1] > v[j] {
-            // Since j-1 and j are out of order swap them, and move
-            // j one to the left to continue the bubbling if necessary.
-            v.swap(j-1, j);
-            j -= 1;
+// Run the matrix and say how it went (see `RunStatus` for the exit
+// codes), writing the one-line summary if there's somewhere to write it.
+fn matrix(config: MatrixConfig, script: &ScriptOptions) -> RunStatus {
+    if config.count_only {
+        return count_matrix(&config, script);
+    }
+    let start = Instant::now();
+    let config = Arc::new(config);
+    let (mut status, results) = match run_and_print(&config, script) {
+        Ok(results) if results.iter().all(|r| r.verified) => (RunStatus::Ok, results),
+        Ok(results) => (RunStatus::Unsorted, results),
+        Err(status) => (status, Vec::new()),
+    };
+    if let (Some(path), RunStatus::Ok | RunStatus::Unsorted) = (&script.bundle, &status) {
+        let command_line: Vec<String> = std::env::args().collect();
+        if let Err(error) = write_bundle(path, &config, &results, &command_line.join(" ")) {
+            status = RunStatus::Io(format!("couldn't write {}: {}", path.display(), error));
         }
     }
-    // And we're done! The outer for loop is done O(N) times, and
-    // the inner while loop is (on average) O(N), so insertion sort
-    // is O(N^2).
+    if let Some(path) = &script.summary_json {
+        let json = summary_json(&status, &results, &config, start.elapsed());
+        if let Err(error) = write_summary(path, &json) {
+            status = RunStatus::Io(format!("couldn't write {}: {}", path.display(), error));
+        }
+    }
+    status
 }
 
-// Quicksort sort is also "in place", so we modify the input array v
-// directly and do _not_ return anything. The elements of the
-// array need to traits `PartialOrd` (so they support < and ≤).
-// Also requiring the trait `Debug` means you can print the array
-// and slices of the array for debugging purposes with `{:?}`. I
-// don't do that here, but you could add some print statements if,
-// for example, you want to watch the sorting happen.
-//
-// Note that the parameter v *has* to be mutable because we're 
-// modifying it in place.
-fn quicksort<T: PartialOrd + std::fmt::Debug>(v: &mut [T]) {
-    // Quicksort is a recursive solution where we select a pivot
-    // value (usually just the first element) and split (in place)
-    // the array into two sections: The "front" is all < the pivot,
-    // and the "back" is all ≥ pivot. More formally, there's an
-    // index smaller where:
-    //   (All i | 0 ≤ i < smaller : v[i] < pivot) /\
-    //   (All i | smaller ≤ i < length : v[i] ≥ pivot)
-    // Now you can recursively call quicksort on the front using
-    // the slice v[0..smaller] to sort that part, and call it
-    // recursively on the slice v[smaller+1..length] to sort 
-    // the back half. (You need the +1 to ensure that both slices
-    // are smaller than the original array; without it you can
-    // end up with infinite recursion.)
-
-    let length = v.len();
-    // If the array has 0 or 1 elements it's already sorted
-    // and we'll just stop.
-    if length < 2 {
-        return;
+fn run_and_print(
+    config: &Arc<MatrixConfig>,
+    script: &ScriptOptions,
+) -> Result<Vec<RunResult>, RunStatus> {
+    config
+        .check_memory()
+        .map_err(|error| RunStatus::Failed(error.to_string()))?;
+    if let Some(duration) = config.soak {
+        let soaked = if script.quiet {
+            run_soak(config, duration, &mut std::io::sink())
+        } else {
+            let stdout = std::io::stdout();
+            run_soak(config, duration, &mut stdout.lock())
+        };
+        soaked.map_err(|error| RunStatus::Io(error.to_string()))?;
+        return Ok(Vec::new());
     }
-
-    // Now choose a pivot and do the organizing.
-    
-    // ...
-
-    let smaller = 0; // Totally wrong – you should fix this.
-
-    // Sort all the items < pivot
-    quicksort(&mut v[0..smaller]);
-    // Sort all the items ≥ pivot, *not* including the
-    // pivot value itself. If we don't include the +1
-    // here you can end up in infinite recursions.
-    quicksort(&mut v[smaller+1..length]);
+    if config.parallel.verbose {
+        eprintln!("parallel-merge: {}", config.parallel);
+    }
+    // (The other formats include the seed in the report.)
+    let text = script.format == ReportFormat::Text && !script.quiet;
+    if let (Some(seed), true) = (config.schedule_seed, text) {
+        println!("cells run in random order (schedule seed {})", seed);
+    }
+    let results = match script.timeout {
+        Some(timeout) => run_with_timeout(config, timeout)?,
+        None => run_matrix(config),
+    };
+    check_inputs(&results).map_err(|error| RunStatus::Failed(error.to_string()))?;
+    if !script.quiet {
+        match script.format {
+            ReportFormat::Text => {
+                print_results(&results);
+                println!();
+                print!("{}", summary(&rank(&results)));
+            }
+            ReportFormat::Markdown => {
+                print!("{}", to_markdown(&results, config));
+                println!();
+                print!("{}", summary(&rank(&results)));
+            }
+            ReportFormat::Json => print!("{}", to_json(&results, config)),
+        }
+    }
+    Ok(results)
 }
 
-// Merge sort can't be done "in place", so it needs to return a _new_
-// Vec<T> of the sorted elements. The array elements need to have
-// the traits `PartialOrd` and `Debug` like in the other sorting
-// algorithms, but they also need to have the `Copy` trait so we
-// can do things like `result.push(v[i])` to push element v[i] onto
-// a vector result. This ends up copying v[i] (to prevent ownership
-// issues on the array values), so we have to implement the `Copy`
-// trait. Numbers all do this, so that should be fine.
-// Note, however, that this has significant consequences – we can use `merge_sort`
-// to sort things like numbers, but sorting "large" things (e.g., student records)
-// would involve copying them, and that's likely to be expensive and perhaps undesirable.
-//
-// Note that here the parameter v does *not* have to be mutable because we're 
-// creating and returning a new vector instead of modifying v in place.
-// We're returning a vector instead of an array here because arrays have to
-// know exactly how big they are. I suspect there's a way to make that work
-// but I (Nic) couldn't figure out an easy way to sort out the types on the
-// `merge()` function keeping everything as arrays. It was a lot easier to 
-// just have the return type be Vec, so that's what I did. 
-fn merge_sort<T: PartialOrd + std::marker::Copy + std::fmt::Debug>(v: &[T]) -> Vec<T> {
-    // Merge sort is a recursive solution where we split the
-    // array in half (slices make this easy), sort each half,
-    // and then merge the results together. All the "interesting"
-    // work is in the merge here, where in quicksort the "interesting"
-    // work is in organizing around the pivot.
-
-    let len = v.len();
-    if len == 0 {
-        return Vec::<T>::new();
+// `--count-only`: nothing's timed, so there's no ranking to print.
+fn count_matrix(config: &MatrixConfig, script: &ScriptOptions) -> RunStatus {
+    let results = run_counts(config);
+    if !script.quiet {
+        match script.format {
+            ReportFormat::Text => print_counts(&results),
+            ReportFormat::Markdown => print!("{}", counts_to_markdown(&results)),
+            ReportFormat::Json => print!("{}", counts_to_json(&results)),
+        }
     }
-    if len == 1 {
-        let mut result = Vec::<T>::new();
-        result.push(v[0]);
-        return result;
+    if results.iter().all(|r| r.verified) {
+        RunStatus::Ok
+    } else {
+        RunStatus::Unsorted
     }
-    let middle = v.len() / 2; //rounds down by default
-    let left = merge_sort(&v[0..middle]);
-    let right = merge_sort(&v[middle .. len]);
-    // Note that in Rust the last expression is what is
-    // returned, and we don't need the explicit `return`
-    // keyword. So this merges `left` and `right` and
-    // returns the result as the result of this call to
-    // `merge_sort()`.
-    merge(left, right)
 }
 
-// "Out of the box" there's a warning here about `ys` being
-// unused. Presumably you'll actually use `ys` in your solution,
-// so that warning should go away. You can remove this comment
-// if you wish since it won't be relevant any longer.
-fn merge<T: PartialOrd + std::marker::Copy + std::fmt::Debug>(xs: Vec<T>, ys: Vec<T>) -> Vec<T> {
-    // This takes two sorted vectors, like:
-    //    <5, 8, 9> and
-    //    <0, 2, 3, 6>
-    // and merges them into a single sorted vector like:
-    //    <0, 2, 3, 5, 6, 8, 9>
-    // You should be able to do this in linear time by having
-    // two indices that point to where you are in xs and ys.
-    // You then compare those values, push the smaller one onto
-    // the result vector, and increment the appropriate index.
-    // You stop when one of your indices hits the end of its
-    // vector, and then push all the remaining elements from the
-    // other vector onto the result.
+// There's no stopping a sort part way through, so the matrix runs on a
+// thread of its own, and if it runs out of time we leave it there (it
+// goes when the process exits).
+fn run_with_timeout(
+    config: &Arc<MatrixConfig>,
+    timeout: Duration,
+) -> Result<Vec<RunResult>, RunStatus> {
+    let (sender, receiver) = mpsc::channel();
+    let config = Arc::clone(config);
+    thread::spawn(move || {
+        let _ = sender.send(run_matrix(&config));
+    });
+    receiver.recv_timeout(timeout).map_err(|error| match error {
+        RecvTimeoutError::Timeout => RunStatus::TimedOut,
+        RecvTimeoutError::Disconnected => RunStatus::Failed("the matrix panicked".to_string()),
+    })
+}
 
-    // This is totally wrong and will not sort. You should replace it
-    // with something useful. :)
-    xs
+// `-` is standard output.
+fn write_summary(path: &Path, json: &str) -> std::io::Result<()> {
+    if path == Path::new("-") {
+        let stdout = std::io::stdout();
+        let mut stdout = stdout.lock();
+        stdout.write_all(json.as_bytes())?;
+        stdout.flush()
+    } else {
+        std::fs::write(path, json)
+    }
 }
 
-fn is_sorted<T: PartialOrd>(slice: &[T]) -> bool {
-    let len = slice.len();
-    for i in 0..len-1{
-        if slice[i] > slice[i+1]{
-            return false;
-        }
+fn set_op(operation: SetOperation, left: &Path, right: &Path) -> Result<(), String> {
+    let xs = read_sorted_numbers(left)?;
+    let ys = read_sorted_numbers(right)?;
+    for n in operation.apply(&xs, &ys) {
+        println!("{}", n);
     }
-    true
+    Ok(())
 }
 
-fn generate_random_array(len: i32, min: i32, max:i32) -> Vec<i32> {
-    let mut rng = thread_rng();
-    let mut v = Vec::new();
-    for _i in 0..len{
-        v.push(rng.gen_range(min, max));
+fn analyze(file: &Path, report_dupes: bool) -> Result<(), String> {
+    let mut numbers = read_numbers(file)?;
+    auto_sort(&mut numbers);
+    let runs = runs(&numbers);
+    println!("{} numbers, {} distinct", numbers.len(), runs.len());
+    if report_dupes {
+        let dupes = duplicates(&numbers);
+        println!("{} values appear more than once", dupes.len());
+        for (value, count) in dupes {
+            println!("{:>12} appears {} times", value, count);
+        }
     }
-    // Rust returns the last expression in a function, so
-    // this is equivalent to `return v`. 
-    v
+    Ok(())
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    mod insertion_sort {
-        use super::*;
-        #[test]
-        fn empty() {
-            let mut input : [i32; 0] = [];
-            insertion_sort(&mut input);
-            let expected : [i32; 0] = [];
+fn stats(file: &Path) -> Result<(), String> {
+    let mut numbers = read_numbers(file)?;
+    auto_sort(&mut numbers);
+    let summary = summarize(&numbers).ok_or_else(|| format!("{} has no numbers", file.display()))?;
+    println!("count:          {}", summary.count);
+    println!("distinct:       {}", summary.distinct);
+    println!("min:            {}", summary.min);
+    println!("lower quartile: {}", summary.lower_quartile);
+    println!("median:         {}", summary.median);
+    println!("upper quartile: {}", summary.upper_quartile);
+    println!("max:            {}", summary.max);
+    println!("mode:           {} ({} times)", summary.mode, summary.mode_count);
+    Ok(())
+}
 
-            assert_eq!(expected, input);
-        }
+fn histogram(file: &Path, bins: usize, equi_depth: bool) -> Result<(), String> {
+    let mut numbers = read_numbers(file)?;
+    auto_sort(&mut numbers);
+    let buckets = if equi_depth {
+        equi_depth_buckets(&numbers, bins)
+    } else {
+        equi_width_histogram(&numbers, bins)
+    };
+    print!("{}", render_bars(&buckets, 50));
+    Ok(())
+}
 
-        #[test]
-        fn ten_items() {
-            let mut input = [3, 2, 0, 5, 8, 9, 6, 3, 2, 0];
-            insertion_sort(&mut input);
-            let expected = [0, 0, 2, 2, 3, 3, 5, 6, 8, 9];
+#[cfg(feature = "mmap")]
+fn line_buffer(file: &Path) -> Result<LineBuffer, String> {
+    std::fs::File::open(file)
+        .and_then(|file| LineBuffer::map(&file))
+        .map_err(|error| format!("couldn't map {}: {}", file.display(), error))
+}
 
-            assert_eq!(expected, input);
-        }
+#[cfg(not(feature = "mmap"))]
+fn line_buffer(file: &Path) -> Result<LineBuffer, String> {
+    std::fs::read_to_string(file)
+        .map(LineBuffer::new)
+        .map_err(|error| format!("couldn't read {}: {}", file.display(), error))
+}
 
-        #[test]
-        fn presorted() {
-            let mut input = [0, 0, 2, 2, 3, 3, 5, 6, 8, 9];
-            insertion_sort(&mut input);
-            let expected = [0, 0, 2, 2, 3, 3, 5, 6, 8, 9];
+fn sort_lines(file: &Path, config: &LineSortConfig, zero_copy: bool) -> Result<(), String> {
+    if zero_copy {
+        let buffer = line_buffer(file)?;
+        let order = buffer.sort(config).map_err(|message| format!("{}: {}", file.display(), message))?;
+        let stdout = std::io::stdout();
+        return buffer
+            .write_in_order(&order, BufWriter::new(stdout.lock()))
+            .map_err(|error| error.to_string());
+    }
+    let text = std::fs::read_to_string(file)
+        .map_err(|error| format!("couldn't read {}: {}", file.display(), error))?;
+    let lines: Vec<&str> = text.lines().collect();
+    let sorted = lines::sort_lines(&lines, config).map_err(|message| format!("{}: {}", file.display(), message))?;
+    for line in sorted {
+        println!("{}", line);
+    }
+    Ok(())
+}
 
-            assert_eq!(expected, input);
+fn trace(algorithm: &str, input: Vec<i32>, output: Option<&Path>) -> Result<(), String> {
+    let bugs = bugs_from_env()?;
+    let json = with_bugs(&bugs, || record(algorithm, &input))?.to_json();
+    match output {
+        Some(path) => std::fs::write(path, json)
+            .map_err(|error| format!("couldn't write {}: {}", path.display(), error)),
+        None => {
+            print!("{}", json);
+            Ok(())
         }
     }
+}
 
-    mod quicksort {
-        use super::*;
-        #[test]
-        fn empty() {
-            let mut input : [i32; 0] = [];
-            quicksort(&mut input);
-            let expected : [i32; 0] = [];
+fn read_trace(file: &Path) -> Result<Trace, String> {
+    let text = std::fs::read_to_string(file)
+        .map_err(|error| format!("couldn't read {}: {}", file.display(), error))?;
+    Trace::from_json(&text).map_err(|message| format!("{}: {}", file.display(), message))
+}
+
+fn replay(file: &Path) -> Result<(), String> {
+    print!("{}", read_trace(file)?.render()?);
+    Ok(())
+}
 
-            assert_eq!(expected, input);
+fn certify_sort(algorithm: &str, input: Vec<i32>, output: Option<&Path>) -> Result<(), String> {
+    let bugs = bugs_from_env()?;
+    let json = with_bugs(&bugs, || certify(algorithm, &input))?.to_json();
+    match output {
+        Some(path) => std::fs::write(path, json)
+            .map_err(|error| format!("couldn't write {}: {}", path.display(), error)),
+        None => {
+            print!("{}", json);
+            Ok(())
         }
+    }
+}
 
-        #[test]
-        fn ten_items() {
-            let mut input = [3, 2, 0, 5, 8, 9, 6, 3, 2, 0];
-            quicksort(&mut input);
-            let expected = [0, 0, 2, 2, 3, 3, 5, 6, 8, 9];
+fn verify_certificate(file: &Path, replay: bool) -> Result<(), String> {
+    let text = std::fs::read_to_string(file)
+        .map_err(|error| format!("couldn't read {}: {}", file.display(), error))?;
+    let certificate =
+        Certificate::from_json(&text).map_err(|message| format!("{}: {}", file.display(), message))?;
+    verify(&certificate).map_err(|message| format!("{}: {}", file.display(), message))?;
+    if replay {
+        certificate::replay(&certificate)
+            .map_err(|message| format!("{}: {}", file.display(), message))?;
+    }
+    println!(
+        "{}: {} sorted {} values correctly{}",
+        file.display(),
+        certificate.algorithm,
+        certificate.input.len(),
+        if replay { ", with the same comparisons" } else { "" }
+    );
+    Ok(())
+}
 
-            assert_eq!(expected, input);
+fn explain_complexity(source: ComplexitySource) -> Result<(), String> {
+    let measurements: Vec<Measurement> = match source {
+        ComplexitySource::File(file) => {
+            let text = std::fs::read_to_string(&file)
+                .map_err(|error| format!("couldn't read {}: {}", file.display(), error))?;
+            measurements_from_json(&text)
+                .map_err(|message| format!("{}: {}", file.display(), message))?
+        }
+        ComplexitySource::Matrix(config) => {
+            config.check_memory().map_err(|error| error.to_string())?;
+            let results = run_matrix(&config);
+            check_inputs(&results).map_err(|error| error.to_string())?;
+            results.iter().filter(|r| r.verified).map(Measurement::from).collect()
         }
+    };
+    print!("{}", render(&growth_table(&measurements)));
+    Ok(())
+}
 
-        #[test]
-        fn presorted() {
-            let mut input = [0, 0, 2, 2, 3, 3, 5, 6, 8, 9];
-            quicksort(&mut input);
-            let expected = [0, 0, 2, 2, 3, 3, 5, 6, 8, 9];
+fn trace_diff(left: &Path, right: &Path) -> Result<(), String> {
+    let divergence = match diff(&read_trace(left)?, &read_trace(right)?)? {
+        Some(divergence) => divergence,
+        None => {
+            println!("the traces are identical");
+            return Ok(());
+        }
+    };
+    let describe = |op: Option<_>| match op {
+        Some(op) => format!("{}", op),
+        None => "nothing (it's finished)".to_string(),
+    };
+    println!("the traces agree for the first {} operations; then", divergence.step);
+    println!("  {} does: {}", left.display(), describe(divergence.left));
+    println!("  {} does: {}", right.display(), describe(divergence.right));
+    println!("with the array at {:?}", divergence.array);
+    Err("the traces differ".to_string())
+}
 
-            assert_eq!(expected, input);
+fn budget(
+    algorithms: &[Algorithm],
+    sizes: &[usize],
+    distribution: Distribution,
+    steps: u64,
+    seed: u64,
+) -> Result<(), String> {
+    let mut results = Vec::new();
+    for algorithm in algorithms {
+        for &size in sizes {
+            results.push(run_budgeted(algorithm.name, size, distribution, seed, steps)?);
         }
     }
+    println!("Percent of each sort completed in {} comparisons:", steps);
+    print_budget_table(&results, sizes);
+    Ok(())
+}
 
-    mod merge_sort {
-        use super::*;
-        #[test]
-        fn empty() {
-            let input : [i32; 0] = [];
-            let result = merge_sort(&input);
-            let expected : Vec<i32> = Vec::new();
+fn cache_sim(algorithms: &[Algorithm], input: &[i32], cache: CacheConfig) -> Result<(), String> {
+    println!(
+        "{} values through a {}-byte cache ({}-byte lines, {} to a set):",
+        input.len(),
+        cache.capacity,
+        cache.line_size,
+        cache.associativity
+    );
+    println!(
+        "{:<22}{:>12}{:>12}{:>12}{:>11}",
+        "", "reads", "hits", "misses", "miss rate"
+    );
+    for algorithm in algorithms {
+        let stats = cache_profile(algorithm.name, input, cache)?;
+        println!(
+            "{:<22}{:>12}{:>12}{:>12}{:>10.1}%",
+            algorithm.name,
+            stats.accesses(),
+            stats.hits,
+            stats.misses,
+            100.0 * stats.miss_rate()
+        );
+    }
+    Ok(())
+}
 
-            assert_eq!(expected, result);
+fn branches(algorithms: &[Algorithm], input: &[i32], trials: usize) -> Result<(), String> {
+    println!(
+        "{:<22}{:>12}{:>7}{:>9}{:>9}{:>8}{:>8}{:>10}{:>9}",
+        "", "comparisons", "true", "entropy", "given", "streak", "missed", "time", "ns/comp"
+    );
+    let (mut missed, mut per_comparison) = (Vec::new(), Vec::new());
+    for algorithm in algorithms {
+        let stats = outcome_profile(algorithm.name, input)?;
+        // The fastest of the trials, on plain `i32`s.
+        let mut fastest = Duration::MAX;
+        for _ in 0..trials {
+            let mut v = input.to_vec();
+            let before = Instant::now();
+            algorithm.sort(&mut v);
+            fastest = fastest.min(before.elapsed());
         }
+        let nanos = fastest.as_secs_f64() * 1e9 / stats.comparisons.max(1) as f64;
+        println!(
+            "{:<22}{:>12}{:>6.1}%{:>9.3}{:>9.3}{:>8.1}{:>7.1}%{:>8.2}ms{:>9.2}",
+            algorithm.name,
+            stats.comparisons,
+            100.0 * stats.taken_rate(),
+            stats.entropy(),
+            stats.conditional_entropy(),
+            stats.mean_streak(),
+            100.0 * stats.misprediction_rate(),
+            fastest.as_secs_f64() * 1e3,
+            nanos
+        );
+        missed.push(stats.misprediction_rate());
+        per_comparison.push(nanos);
+    }
+    println!();
+    println!("(entropy is in bits; `given` is the entropy given the outcome before,");
+    println!("and `missed` is how often a two-bit predictor guessed wrong)");
+    if let Some(r) = correlation(&missed, &per_comparison) {
+        println!(
+            "correlation between mispredictions and time per comparison: {:.2}",
+            r
+        );
+    }
+    Ok(())
+}
 
-        #[test]
-        fn ten_items() {
-            let input = [3, 2, 0, 5, 8, 9, 6, 3, 2, 0];
-            let result = merge_sort(&input);
-            let expected = [0, 0, 2, 2, 3, 3, 5, 6, 8, 9].to_vec();
+fn grade(subjects: &[Subject], bugs: Option<Vec<Bug>>, mutants: bool) -> Result<(), String> {
+    if mutants {
+        for (bug, caught) in grade::mutation_table(subjects) {
+            if caught.is_empty() {
+                println!("{}: SURVIVED", bug.name());
+            } else {
+                println!("{}: caught by {}", bug.name(), caught.join(", "));
+            }
+        }
+        return Ok(());
+    }
+    let bugs = match bugs {
+        Some(bugs) => bugs,
+        None => bugs_from_env()?,
+    };
+    let reports = grade::grade_with_bugs(subjects, &bugs);
+    print!("{}", grade::to_json(&reports));
+    let mut failed = 0;
+    for report in &reports {
+        eprintln!("{}: {}/{}", report.subject, report.score(), report.out_of());
+        failed += report.out_of() - report.score();
+    }
+    if failed > 0 {
+        return Err(format!("{} checks failed", failed));
+    }
+    Ok(())
+}
 
-            assert_eq!(expected, result);
+#[cfg(feature = "reference")]
+fn diff_test(cases: usize, seed: u64) -> Result<(), String> {
+    use sorting_in_rust::mutants::with_bugs;
+    use sorting_in_rust::reference::{diff_merge, diff_partition};
+    let bugs = bugs_from_env()?;
+    let mut diverged = false;
+    for result in with_bugs(&bugs, || vec![diff_partition(cases, seed), diff_merge(cases, seed)]) {
+        if let Err(divergence) = result {
+            println!("{}", divergence);
+            diverged = true;
         }
+    }
+    if diverged {
+        return Err("the lab code doesn't match the reference".to_string());
+    }
+    println!("partition_by and merge matched the reference on {} cases each", cases);
+    Ok(())
+}
 
-        #[test]
-        fn presorted() {
-            let input = [0, 0, 2, 2, 3, 3, 5, 6, 8, 9];
-            let result = merge_sort(&input);
-            let expected = [0, 0, 2, 2, 3, 3, 5, 6, 8, 9].to_vec();
+fn demo() -> Result<(), String> {
+    // Feel free to raise size if you want to see the timing difference
+    // between the different algorithms. Since insertion sort is O(N^2)
+    // and the others are O(N log N), you should definitely be able
+    // to see a difference between it and the faster algorithms.
+    let size = 1000; // 100000;
+    // A different input every time, unless SORTLAB_SEED says otherwise;
+    // we print the seed so that an interesting run can be repeated.
+    let seed = seed_from(|name| std::env::var(name).ok())?;
+    println!("Using seed {} (set {}={} to repeat this input)", seed, SEED_VAR, seed);
+    let v = generate_random_array(size, 0, size, &mut SeededRandom::new(seed));
+    println!("Is the original, random list in order?: {:?}", is_sorted(&v));
 
-            assert_eq!(expected, result);
+    // Times depend on the machine, but comparison counts don't, so
+    // they're a nice way to compare algorithms that are all O(N log N).
+    // Weak-heap sort and merge sort should both come in a little under
+    // N log₂ N, while heapsort should make nearly twice as many. No sort
+    // can make fewer than the lower bound (see `bounds.rs`), which for a
+    // random input is about log₂ N! ≈ N log₂ N - 1.44 N.
+    let mut lower_bound = 0.0;
+    for name in &["insertion", "quicksort", "merge", "heapsort", "weak-heapsort"] {
+        let algorithm = find_algorithm(name).expect("the demo's algorithms all exist");
+        let outcome = run_sort(&algorithm, &mut v.clone());
+        let comparisons = outcome.stats.comparisons.unwrap_or(0);
+        lower_bound = outcome.stats.bounds.best();
+        println!(
+            "{}: {:?}, {} comparisons ({:.2}× the lower bound), in order: {:?}",
+            outcome.algorithm,
+            outcome.elapsed,
+            comparisons,
+            outcome.stats.bounds.ratio(comparisons).unwrap_or(1.0),
+            outcome.verified
+        );
+    }
+    println!("lower bound for this input: {:.0} comparisons", lower_bound);
+    Ok(())
+}
+
+fn is_sorted<T: PartialOrd>(slice: &[T]) -> bool {
+    let len = slice.len();
+    for i in 0..len-1{
+        if slice[i] > slice[i+1]{
+            return false;
         }
     }
-}
\ No newline at end of file
+    true
+}
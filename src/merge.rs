@@ -0,0 +1,334 @@
+// Merging sorted sequences together.
+
+use crate::heap::{MinHeap, BINARY};
+use crate::search::gallop_by;
+#[cfg(feature = "allocator-api")]
+use std::alloc::Allocator;
+use std::cmp::Ordering;
+
+// Once one side has won this many times in a row, we switch to
+// galloping (see `merge`). This is the value Timsort uses.
+pub const MIN_GALLOP: usize = 7;
+
+// The two-way merge at the heart of merge sort.
+pub fn merge<T: PartialOrd + std::marker::Copy + std::fmt::Debug>(xs: Vec<T>, ys: Vec<T>) -> Vec<T> {
+    // This takes two sorted vectors, like:
+    //    <5, 8, 9> and
+    //    <0, 2, 3, 6>
+    // and merges them into a single sorted vector like:
+    //    <0, 2, 3, 5, 6, 8, 9>
+    // We do this in linear time by having two indices that
+    // point to where we are in xs and ys. We compare those
+    // values, push the smaller one onto the result vector, and
+    // increment the appropriate index. We stop when one of the
+    // indices hits the end of its vector, and then push all the
+    // remaining elements from the other vector onto the result.
+    //
+    // When the input is partly sorted, one side often "wins" many times
+    // in a row. Once that's happened `MIN_GALLOP` times we gallop (see
+    // `search::gallop_by`) to find how far the run goes and copy the
+    // whole run at once, in O(log k) comparisons rather than k.
+
+    let mut result = Vec::with_capacity(xs.len() + ys.len());
+    let mut i = 0;
+    let mut j = 0;
+    let mut xs_wins = 0;
+    let mut ys_wins = 0;
+    while i < xs.len() && j < ys.len() {
+        // Taking from xs when the two are equal keeps the merge stable.
+        if ys[j] < xs[i] {
+            result.push(ys[j]);
+            j += 1;
+            ys_wins += 1;
+            xs_wins = 0;
+            if ys_wins >= MIN_GALLOP {
+                let run = gallop_by(&ys[j..], |y| *y < xs[i]);
+                result.extend_from_slice(&ys[j..j + run]);
+                j += run;
+                ys_wins = 0;
+            }
+        } else {
+            result.push(xs[i]);
+            i += 1;
+            xs_wins += 1;
+            ys_wins = 0;
+            if xs_wins >= MIN_GALLOP && j < ys.len() {
+                // Same test as above: take from xs unless ys[j] < x.
+                let run = gallop_by(&xs[i..], |x| ys[j].partial_cmp(x) != Some(Ordering::Less));
+                result.extend_from_slice(&xs[i..i + run]);
+                i += run;
+                xs_wins = 0;
+            }
+        }
+    }
+    // At most one of these will have anything left in it.
+    result.extend_from_slice(&xs[i..]);
+    result.extend_from_slice(&ys[j..]);
+    result
+}
+
+// The same merge, but into a vector allocated with `alloc`, for
+// programs that manage their own memory (see the `allocator-api`
+// feature). It takes slices rather than vectors since the inputs don't
+// have to live in the same allocator, and doesn't bother galloping.
+#[cfg(feature = "allocator-api")]
+pub fn merge_in<T: PartialOrd + Copy, A: Allocator>(xs: &[T], ys: &[T], alloc: A) -> Vec<T, A> {
+    let mut result = Vec::with_capacity_in(xs.len() + ys.len(), alloc);
+    let mut i = 0;
+    let mut j = 0;
+    while i < xs.len() && j < ys.len() {
+        // Taking from xs when the two are equal keeps the merge stable.
+        if ys[j] < xs[i] {
+            result.push(ys[j]);
+            j += 1;
+        } else {
+            result.push(xs[i]);
+            i += 1;
+        }
+    }
+    result.extend_from_slice(&xs[i..]);
+    result.extend_from_slice(&ys[j..]);
+    result
+}
+
+// Merge any number of sorted vectors into one sorted vector. We keep a
+// min-heap holding the front element of each list (along with which list
+// it came from); popping the heap gives us the smallest front element,
+// and we then replace it with the next element from the same list. With
+// k lists and N total elements this is O(N log k).
+//
+// Ties are broken by list index, so equal elements come out in the order
+// of the lists they came from, which makes the merge stable.
+pub fn kway_merge<T: PartialOrd>(lists: Vec<Vec<T>>) -> Vec<T> {
+    kway_merge_d(lists, BINARY)
+}
+
+// The same merge using a d-ary heap, which can be faster when merging
+// lots of lists.
+pub fn kway_merge_d<T: PartialOrd>(lists: Vec<Vec<T>>, arity: usize) -> Vec<T> {
+    let total = lists.iter().map(|list| list.len()).sum();
+    let mut result = Vec::with_capacity(total);
+    let sources = lists.into_iter().map(|list| list.into_iter()).collect();
+    result.extend(KwayMerge::with_arity(sources, arity));
+    result
+}
+
+// `kway_merge`, with the result allocated with `alloc`.
+#[cfg(feature = "allocator-api")]
+pub fn kway_merge_in<T: PartialOrd, A: Allocator>(lists: Vec<Vec<T>>, alloc: A) -> Vec<T, A> {
+    let total = lists.iter().map(|list| list.len()).sum();
+    let mut result = Vec::with_capacity_in(total, alloc);
+    let sources = lists.into_iter().map(|list| list.into_iter()).collect();
+    result.extend(KwayMerge::new(sources));
+    result
+}
+
+// The k-way merge as an iterator, so the sources can be anything that
+// produces sorted items (a file being read, say) and the merged items
+// can be consumed one at a time, without either side having to fit in
+// memory.
+pub struct KwayMerge<I: Iterator> {
+    sources: Vec<I>,
+    heap: MinHeap<Entry<I::Item>>,
+}
+
+impl<I: Iterator> KwayMerge<I>
+where
+    I::Item: PartialOrd,
+{
+    pub fn new(sources: Vec<I>) -> KwayMerge<I> {
+        KwayMerge::with_arity(sources, BINARY)
+    }
+
+    pub fn with_arity(mut sources: Vec<I>, arity: usize) -> KwayMerge<I> {
+        let mut heap = MinHeap::with_capacity_and_arity(sources.len(), arity);
+        for (i, source) in sources.iter_mut().enumerate() {
+            if let Some(item) = source.next() {
+                heap.push(Entry { item, source: i });
+            }
+        }
+        KwayMerge { sources, heap }
+    }
+}
+
+impl<I: Iterator> Iterator for KwayMerge<I>
+where
+    I::Item: PartialOrd,
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        let Entry { item, source } = self.heap.pop()?;
+        if let Some(next) = self.sources[source].next() {
+            self.heap.push(Entry { item: next, source });
+        }
+        Some(item)
+    }
+}
+
+// A heap entry: an item plus the index of the list it came from. We
+// compare by item first and then by list index, which is what makes the
+// merge stable.
+struct Entry<T> {
+    item: T,
+    source: usize,
+}
+
+impl<T: PartialOrd> PartialEq for Entry<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.item == other.item && self.source == other.source
+    }
+}
+
+impl<T: PartialOrd> PartialOrd for Entry<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match self.item.partial_cmp(&other.item) {
+            Some(Ordering::Equal) => Some(self.source.cmp(&other.source)),
+            ordering => ordering,
+        }
+    }
+}
+
+// An allocator that counts how many times it's been asked for memory,
+// so the tests can check that the `_in` functions really use it.
+#[cfg(all(test, feature = "allocator-api"))]
+#[derive(Clone, Copy)]
+pub(crate) struct CountingAllocator<'a>(pub &'a std::cell::Cell<usize>);
+
+#[cfg(all(test, feature = "allocator-api"))]
+unsafe impl Allocator for CountingAllocator<'_> {
+    fn allocate(
+        &self,
+        layout: std::alloc::Layout,
+    ) -> Result<std::ptr::NonNull<[u8]>, std::alloc::AllocError> {
+        self.0.set(self.0.get() + 1);
+        std::alloc::Global.allocate(layout)
+    }
+
+    unsafe fn deallocate(&self, ptr: std::ptr::NonNull<u8>, layout: std::alloc::Layout) {
+        // SAFETY: `ptr` came from `Global.allocate`, in `allocate`.
+        unsafe { std::alloc::Global.deallocate(ptr, layout) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod merge {
+        use super::*;
+
+        #[test]
+        fn empty() {
+            let result: Vec<i32> = merge(Vec::new(), Vec::new());
+            assert_eq!(Vec::<i32>::new(), result);
+        }
+
+        #[test]
+        fn one_side_empty() {
+            assert_eq!(vec![1, 2], merge(vec![1, 2], Vec::new()));
+            assert_eq!(vec![1, 2], merge(Vec::new(), vec![1, 2]));
+        }
+
+        #[test]
+        fn interleaved() {
+            let result = merge(vec![5, 8, 9], vec![0, 2, 3, 6]);
+            assert_eq!(vec![0, 2, 3, 5, 6, 8, 9], result);
+        }
+
+        #[test]
+        fn long_runs() {
+            // Long runs from each side, so the merge gallops.
+            let xs: Vec<i32> = (0..100).chain(200..300).collect();
+            let ys: Vec<i32> = (100..200).chain(300..400).collect();
+            assert_eq!((0..400).collect::<Vec<i32>>(), merge(xs, ys));
+        }
+
+        #[test]
+        fn galloping_is_stable() {
+            // Equal keys, tagged with which side they came from; every
+            // xs copy has to come out before every ys copy.
+            #[derive(Debug, Clone, Copy, PartialEq)]
+            struct Tagged(i32, bool);
+            impl PartialOrd for Tagged {
+                fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+                    self.0.partial_cmp(&other.0)
+                }
+            }
+            let xs: Vec<Tagged> = (0..50).map(|k| Tagged(k / 10, false)).collect();
+            let ys: Vec<Tagged> = (0..50).map(|k| Tagged(k / 10, true)).collect();
+            let result = merge(xs, ys);
+            for w in result.windows(2) {
+                assert!(w[0].0 < w[1].0 || (w[0].0 == w[1].0 && w[0].1 <= w[1].1));
+            }
+        }
+
+        #[test]
+        fn fewer_comparisons_on_runs() {
+            use crate::instrument::{count_comparisons, counted, uncounted};
+            let xs = counted(&(0..1000).collect::<Vec<i32>>());
+            let ys = counted(&(1000..2000).collect::<Vec<i32>>());
+            let (result, comparisons) = count_comparisons(|| merge(xs, ys));
+            assert_eq!((0..2000).collect::<Vec<i32>>(), uncounted(result));
+            assert!(comparisons < 100, "{} comparisons", comparisons);
+        }
+    }
+
+    #[test]
+    fn empty() {
+        let result: Vec<i32> = kway_merge(Vec::new());
+        assert_eq!(Vec::<i32>::new(), result);
+    }
+
+    #[test]
+    fn some_empty_lists() {
+        let result = kway_merge(vec![vec![], vec![1, 4], vec![], vec![2, 3]]);
+        assert_eq!(vec![1, 2, 3, 4], result);
+    }
+
+    #[test]
+    fn three_lists() {
+        let lists = vec![vec![0, 3, 5, 9], vec![0, 2, 6], vec![2, 3, 8]];
+        let result = kway_merge(lists);
+        assert_eq!(vec![0, 0, 2, 2, 3, 3, 5, 6, 8, 9], result);
+    }
+
+    #[test]
+    fn other_arities() {
+        for &arity in &[3, 4, 8] {
+            let lists = vec![vec![0, 3, 5, 9], vec![0, 2, 6], vec![2, 3, 8], vec![1], vec![4, 7]];
+            let result = kway_merge_d(lists, arity);
+            assert_eq!(vec![0, 0, 1, 2, 2, 3, 3, 4, 5, 6, 7, 8, 9], result);
+        }
+    }
+
+    #[test]
+    fn stable_for_equal_items() {
+        // Compare on the first field only, and check that the second
+        // field comes out in list order.
+        #[derive(Debug, PartialEq)]
+        struct Tagged(i32, usize);
+        impl PartialOrd for Tagged {
+            fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+                self.0.partial_cmp(&other.0)
+            }
+        }
+        let lists = vec![vec![Tagged(1, 0)], vec![Tagged(1, 1)], vec![Tagged(1, 2)]];
+        let tags: Vec<usize> = kway_merge(lists).into_iter().map(|t| t.1).collect();
+        assert_eq!(vec![0, 1, 2], tags);
+    }
+
+    #[cfg(feature = "allocator-api")]
+    #[test]
+    fn merge_in_uses_the_allocator() {
+        let allocations = std::cell::Cell::new(0);
+        let result = merge_in(&[0, 3, 5, 9], &[0, 2, 6], CountingAllocator(&allocations));
+        assert_eq!([0, 0, 2, 3, 5, 6, 9], result[..]);
+        assert_eq!(1, allocations.get());
+
+        let lists = vec![vec![0, 3, 5, 9], vec![0, 2, 6], vec![2, 3, 8]];
+        let result = kway_merge_in(lists, CountingAllocator(&allocations));
+        assert_eq!([0, 0, 2, 2, 3, 3, 5, 6, 8, 9], result[..]);
+        assert_eq!(2, allocations.get());
+    }
+}
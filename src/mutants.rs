@@ -0,0 +1,242 @@
+// Deliberately broken versions of the lab's sorts, for teaching about
+// testing. Each `Bug` is a mistake students really make; turn some on
+// (with `with_bugs`, the `grade --bugs` flag, or the `SORTING_BUGS`
+// environment variable) and run the grading battery to see which checks
+// notice. A good test suite should catch ("kill") every one of them.
+//
+// The functions here have the same names and signatures as the real
+// ones; with no bugs turned on they just call the real ones.
+
+use crate::sorts;
+use std::cell::Cell;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bug {
+    // Quicksort's partition loop stops one element early, so the last
+    // element never gets moved to the right side of the pivot.
+    OffByOnePartition,
+    // `merge` copies what's left of `xs` once one side runs out, but
+    // forgets what's left of `ys`.
+    MissingMergeTail,
+    // Comparisons use `>` where they should use `<`.
+    WrongComparison,
+    // `merge` takes from `ys` when the two fronts are equal, which still
+    // sorts but isn't stable.
+    UnstableMerge,
+}
+
+pub const ALL_BUGS: [Bug; 4] = [
+    Bug::OffByOnePartition,
+    Bug::MissingMergeTail,
+    Bug::WrongComparison,
+    Bug::UnstableMerge,
+];
+
+// The environment variable `bugs_from_env` reads, e.g.
+//    SORTING_BUGS=missing-merge-tail,unstable-merge cargo run -- grade
+pub const BUGS_VAR: &str = "SORTING_BUGS";
+
+impl Bug {
+    pub fn name(self) -> &'static str {
+        match self {
+            Bug::OffByOnePartition => "off-by-one-partition",
+            Bug::MissingMergeTail => "missing-merge-tail",
+            Bug::WrongComparison => "wrong-comparison",
+            Bug::UnstableMerge => "unstable-merge",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Bug> {
+        ALL_BUGS.iter().copied().find(|bug| bug.name() == name)
+    }
+
+    fn bit(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+// A comma-separated list of bug names; an empty string means none.
+pub fn parse_bugs(list: &str) -> Result<Vec<Bug>, String> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| Bug::from_name(name).ok_or_else(|| format!("unknown bug `{}`", name)))
+        .collect()
+}
+
+// The bugs listed in `SORTING_BUGS`, if it's set.
+pub fn bugs_from_env() -> Result<Vec<Bug>, String> {
+    match std::env::var(BUGS_VAR) {
+        Ok(list) => parse_bugs(&list).map_err(|message| format!("{}: {}", BUGS_VAR, message)),
+        Err(_) => Ok(Vec::new()),
+    }
+}
+
+// Which bugs are turned on, one bit each. Like the comparison counter
+// in `instrument.rs` this is per thread, so tests running in parallel
+// can't turn on each other's bugs.
+thread_local! {
+    static ACTIVE: Cell<u8> = const { Cell::new(0) };
+}
+
+pub(crate) fn is_active(bug: Bug) -> bool {
+    ACTIVE.with(|active| active.get() & bug.bit() != 0)
+}
+
+// Run `f` with exactly these bugs turned on (on this thread).
+pub fn with_bugs<R, F: FnOnce() -> R>(bugs: &[Bug], f: F) -> R {
+    let bits = bugs.iter().fold(0, |bits, bug| bits | bug.bit());
+    let before = ACTIVE.with(|active| active.replace(bits));
+    // Put things back even if `f` panics.
+    struct Restore(u8);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            ACTIVE.with(|active| active.set(self.0));
+        }
+    }
+    let _restore = Restore(before);
+    f()
+}
+
+// `a < b`, or `a > b` with `WrongComparison`.
+fn less<T: PartialOrd>(a: &T, b: &T) -> bool {
+    if is_active(Bug::WrongComparison) {
+        a > b
+    } else {
+        a < b
+    }
+}
+
+pub fn quicksort<T: PartialOrd + std::fmt::Debug>(v: &mut [T]) {
+    if !is_active(Bug::OffByOnePartition) && !is_active(Bug::WrongComparison) {
+        return sorts::quicksort(v);
+    }
+    let length = v.len();
+    if length < 2 {
+        return;
+    }
+    // Partition around v[0], as in the real quicksort: afterwards
+    //   (All i | 1 ≤ i ≤ smaller : v[i] < pivot)
+    let mut smaller = 0;
+    let end = if is_active(Bug::OffByOnePartition) {
+        length - 1
+    } else {
+        length
+    };
+    for i in 1..end {
+        if less(&v[i], &v[0]) {
+            smaller += 1;
+            v.swap(i, smaller);
+        }
+    }
+    v.swap(0, smaller);
+    quicksort(&mut v[0..smaller]);
+    quicksort(&mut v[smaller + 1..length]);
+}
+
+pub fn merge<T: PartialOrd + Copy + std::fmt::Debug>(xs: Vec<T>, ys: Vec<T>) -> Vec<T> {
+    if !is_active(Bug::MissingMergeTail)
+        && !is_active(Bug::WrongComparison)
+        && !is_active(Bug::UnstableMerge)
+    {
+        return crate::merge::merge(xs, ys);
+    }
+    // The plain merge from `merge.rs`, without the galloping.
+    let mut result = Vec::with_capacity(xs.len() + ys.len());
+    let (mut i, mut j) = (0, 0);
+    while i < xs.len() && j < ys.len() {
+        let take_ys = if is_active(Bug::UnstableMerge) {
+            !less(&xs[i], &ys[j])
+        } else {
+            less(&ys[j], &xs[i])
+        };
+        if take_ys {
+            result.push(ys[j]);
+            j += 1;
+        } else {
+            result.push(xs[i]);
+            i += 1;
+        }
+    }
+    result.extend_from_slice(&xs[i..]);
+    if !is_active(Bug::MissingMergeTail) {
+        result.extend_from_slice(&ys[j..]);
+    }
+    result
+}
+
+pub fn merge_sort<T: PartialOrd + Copy + std::fmt::Debug>(v: &[T]) -> Vec<T> {
+    if v.len() < 2 {
+        return v.to_vec();
+    }
+    let middle = v.len() / 2;
+    merge(merge_sort(&v[..middle]), merge_sort(&v[middle..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEN: [i32; 10] = [3, 2, 0, 5, 8, 9, 6, 3, 2, 0];
+    const SORTED_TEN: [i32; 10] = [0, 0, 2, 2, 3, 3, 5, 6, 8, 9];
+
+    #[test]
+    fn no_bugs_means_real_sorts() {
+        let mut v = TEN.to_vec();
+        quicksort(&mut v);
+        assert_eq!(SORTED_TEN.to_vec(), v);
+        assert_eq!(SORTED_TEN.to_vec(), merge_sort(&TEN));
+    }
+
+    #[test]
+    fn names() {
+        for &bug in &ALL_BUGS {
+            assert_eq!(Some(bug), Bug::from_name(bug.name()));
+        }
+        assert_eq!(
+            Ok(vec![Bug::MissingMergeTail, Bug::UnstableMerge]),
+            parse_bugs("missing-merge-tail, unstable-merge")
+        );
+        assert_eq!(Ok(vec![]), parse_bugs(""));
+        assert!(parse_bugs("typo").is_err());
+    }
+
+    #[test]
+    fn off_by_one_partition() {
+        let mut v = TEN.to_vec();
+        with_bugs(&[Bug::OffByOnePartition], || quicksort(&mut v));
+        assert_ne!(SORTED_TEN.to_vec(), v);
+        // Still the same elements, though.
+        v.sort();
+        assert_eq!(SORTED_TEN.to_vec(), v);
+    }
+
+    #[test]
+    fn missing_merge_tail() {
+        let merged = with_bugs(&[Bug::MissingMergeTail], || merge(vec![1, 2], vec![3, 4]));
+        assert_eq!(vec![1, 2], merged);
+    }
+
+    #[test]
+    fn wrong_comparison() {
+        let mut v = TEN.to_vec();
+        with_bugs(&[Bug::WrongComparison], || quicksort(&mut v));
+        let mut reversed = SORTED_TEN.to_vec();
+        reversed.reverse();
+        assert_eq!(reversed, v);
+    }
+
+    #[test]
+    fn unstable_merge_still_sorts() {
+        let sorted = with_bugs(&[Bug::UnstableMerge], || merge_sort(&TEN));
+        assert_eq!(SORTED_TEN.to_vec(), sorted);
+    }
+
+    #[test]
+    fn bugs_are_turned_off_afterwards() {
+        let _ = std::panic::catch_unwind(|| {
+            with_bugs(&[Bug::MissingMergeTail], || panic!("oops"));
+        });
+        assert_eq!(vec![1, 2, 3, 4], merge(vec![1, 2], vec![3, 4]));
+    }
+}
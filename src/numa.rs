@@ -0,0 +1,167 @@
+// Controlling which memory node the pages of big benchmark arrays end up
+// on. On a machine with several sockets, each socket has its own memory,
+// and reading another socket's memory is noticeably slower. Linux puts
+// each page on the node of the thread that first writes to it ("first
+// touch"), so an array generated by a single thread ends up entirely on
+// one node, and a parallel sort running on the other sockets spends its
+// time waiting for cross-node traffic rather than sorting.
+//
+// This module is only available on Linux with the `numa` feature:
+//    cargo run --release --features numa -- matrix --placement interleaved ...
+
+use crate::random::{RandomSource, SeededRandom};
+use std::thread;
+
+// How to place the pages of a generated array.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Placement {
+    // Generate the array in parallel, so each thread's part of the array
+    // lands on that thread's node.
+    FirstTouch,
+    // Spread the pages round-robin across all the nodes, so every thread
+    // sees the same mix of local and remote memory no matter where it
+    // runs.
+    Interleaved,
+}
+
+impl Placement {
+    pub fn name(self) -> &'static str {
+        match self {
+            Placement::FirstTouch => "first-touch",
+            Placement::Interleaved => "interleaved",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Placement> {
+        match name {
+            "first-touch" => Some(Placement::FirstTouch),
+            "interleaved" => Some(Placement::Interleaved),
+            _ => None,
+        }
+    }
+}
+
+// Each block of this many elements gets its own random seed, so the
+// values we generate don't depend on how many threads generate them.
+const BLOCK: usize = 1 << 16;
+
+// `len` random numbers in the range min..max, placed as asked. These are
+// _not_ the same numbers `generate_random_array` would give for the
+// same seed, since each block is generated from its own seed.
+//
+// Interleaving is only a hint: if the kernel refuses (for example
+// because it was built without NUMA support), we just fall back to
+// first touch.
+pub fn generate_placed(
+    len: usize,
+    min: i32,
+    max: i32,
+    seed: u64,
+    threads: usize,
+    placement: Placement,
+) -> Vec<i32> {
+    // A big zeroed allocation comes straight from the kernel as untouched
+    // pages, so nothing has been placed yet.
+    let mut v = vec![0; len];
+    if placement == Placement::Interleaved {
+        let _ = interleave(&mut v);
+    }
+    let blocks: Vec<&mut [i32]> = v.chunks_mut(BLOCK).collect();
+    // Each thread gets a contiguous run of blocks.
+    let per_thread = blocks.len().div_ceil(threads.max(1)).max(1);
+    let mut blocks = blocks.into_iter().enumerate().peekable();
+    thread::scope(|scope| {
+        while blocks.peek().is_some() {
+            let mine: Vec<_> = blocks.by_ref().take(per_thread).collect();
+            scope.spawn(move || {
+                for (k, block) in mine {
+                    let mut rng = SeededRandom::new(seed ^ (k as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+                    for x in block.iter_mut() {
+                        *x = rng.range(min, max);
+                    }
+                }
+            });
+        }
+    });
+    v
+}
+
+// The `mbind` policy that interleaves pages across a set of nodes.
+const MPOL_INTERLEAVE: libc::c_int = 3;
+
+// Ask the kernel to interleave the (not yet touched) pages of v across
+// every online node.
+fn interleave(v: &mut [i32]) -> std::io::Result<()> {
+    let mask = online_nodes()?;
+    // SAFETY: `sysconf` has no preconditions.
+    let page = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+    let start = v.as_mut_ptr() as usize;
+    let end = start + std::mem::size_of_val(v);
+    let aligned = start - start % page;
+    // SAFETY: mbind only changes the placement policy of the pages, not
+    // their contents, and the range is (rounded out to whole pages) our
+    // own allocation.
+    let result = unsafe {
+        libc::syscall(
+            libc::SYS_mbind,
+            aligned,
+            end - aligned,
+            MPOL_INTERLEAVE,
+            mask.as_ptr(),
+            mask.len() * 64 + 1,
+            0,
+        )
+    };
+    if result == 0 {
+        Ok(())
+    } else {
+        Err(std::io::Error::last_os_error())
+    }
+}
+
+// The online nodes as a bitmask, read from sysfs, which lists them as
+// ranges like "0-3,6".
+fn online_nodes() -> std::io::Result<Vec<u64>> {
+    let text = std::fs::read_to_string("/sys/devices/system/node/online")?;
+    let bad = || std::io::Error::new(std::io::ErrorKind::InvalidData, "bad node list");
+    let mut mask = vec![0u64; 1];
+    for range in text.trim().split(',') {
+        let mut ends = range.split('-').map(|n| n.parse::<usize>().map_err(|_| bad()));
+        let first = ends.next().ok_or_else(bad)??;
+        let last = ends.next().transpose()?.unwrap_or(first);
+        for node in first..=last {
+            if node / 64 >= mask.len() {
+                mask.resize(node / 64 + 1, 0);
+            }
+            mask[node / 64] |= 1 << (node % 64);
+        }
+    }
+    Ok(mask)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names() {
+        for &p in &[Placement::FirstTouch, Placement::Interleaved] {
+            assert_eq!(Some(p), Placement::from_name(p.name()));
+        }
+    }
+
+    #[test]
+    fn same_values_for_any_thread_count() {
+        let len = 3 * BLOCK + 17;
+        let one = generate_placed(len, 0, 1000, 231, 1, Placement::FirstTouch);
+        assert_eq!(len, one.len());
+        assert!(one.iter().all(|x| (0..1000).contains(x)));
+        assert_eq!(one, generate_placed(len, 0, 1000, 231, 3, Placement::FirstTouch));
+        assert_eq!(one, generate_placed(len, 0, 1000, 231, 8, Placement::Interleaved));
+    }
+
+    #[test]
+    fn empty() {
+        assert!(generate_placed(0, 0, 10, 231, 4, Placement::FirstTouch).is_empty());
+    }
+}
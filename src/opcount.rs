@@ -0,0 +1,297 @@
+// Running the matrix without a stopwatch (`matrix --count-only`).
+// Instead of timing each cell we count what the sort did: how many
+// comparisons, swaps, and moves it made, and how deep its recursion
+// went. Timings change from machine to machine and run to run; these
+// numbers only depend on the input, which is what an autograder or an
+// "is this really O(N^2)?" assignment wants.
+//
+// The comparisons are the real sorts' own, counted with the `Counted`
+// wrapper from `instrument.rs`, so we can count them for everything but
+// parallel-merge (see `sort_generic`). The swaps, moves, and depth come
+// from the step-by-step versions in `trace.rs`, so we only have those
+// for the lab's three algorithms and selection sort; the rest show a
+// `-`. Next to them is the fewest comparisons any sort could make on
+// that input (see `bounds.rs`), to show how close each one comes.
+
+use crate::benchmark::{sort_generic, Cell, Distribution, MatrixConfig};
+use crate::bounds::LowerBounds;
+use crate::cases::Case;
+use crate::instrument::{count_comparisons, counted};
+use crate::json::quote;
+use crate::random::SeededRandom;
+use crate::sorts::{parallel_merge_sort, randomized_quicksort};
+use crate::trace::{count_ops, TRACEABLE};
+use std::fmt::Write;
+use std::thread;
+
+// Quicksort on a sorted input recurses once per item, and the
+// step-by-step version can't turn that into a loop the way the real one
+// can, so we count on a thread with plenty of stack. (It's only
+// reserved, not used, unless the recursion really goes that deep.)
+const STACK_SIZE: usize = 1 << 30;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CountResult {
+    pub algorithm: &'static str,
+    pub size: usize,
+    pub distribution: Distribution,
+    pub trial: usize,
+    pub case: Option<Case>,
+    // `None` where we can't count that for this algorithm.
+    pub comparisons: Option<u64>,
+    pub swaps: Option<u64>,
+    pub moves: Option<u64>,
+    pub max_depth: Option<usize>,
+    // The fewest comparisons any sort could make on the input.
+    pub lower_bound: f64,
+    // Whether the output was actually in order.
+    pub verified: bool,
+}
+
+impl CountResult {
+    // As `RunResult::describe_input`.
+    pub fn describe_input(&self) -> String {
+        match self.case {
+            Some(case) => format!("{} ({})", self.distribution.name(), case.name()),
+            None => self.distribution.name().to_string(),
+        }
+    }
+}
+
+// Count one cell of the matrix.
+pub fn count_cell(config: &MatrixConfig, cell: &Cell) -> CountResult {
+    let name = cell.algorithm.name;
+    let input = config.generate_input(cell);
+    let (comparisons, verified) = if name == "parallel-merge" {
+        let sorted = parallel_merge_sort(&input, &config.parallel);
+        (None, is_sorted(&sorted))
+    } else {
+        let mut v = counted(&input);
+        let ((), comparisons) = count_comparisons(|| {
+            if name == "randomized-quicksort" {
+                // Seeded from the input, so the counts are the same
+                // every run.
+                let seed = config.input_seed(cell.size, cell.distribution, cell.trial);
+                randomized_quicksort(&mut v, &mut SeededRandom::new(seed));
+            } else {
+                sort_generic(name, &mut v).expect("every other algorithm sorts counted values");
+            }
+        });
+        (Some(comparisons), is_sorted(&v))
+    };
+    let steps = if TRACEABLE.contains(&name) {
+        count_ops(name, &input).ok()
+    } else {
+        None
+    };
+    CountResult {
+        algorithm: name,
+        size: cell.size,
+        distribution: cell.distribution,
+        trial: cell.trial,
+        case: cell.case,
+        comparisons,
+        swaps: steps.map(|s| s.swaps),
+        moves: steps.map(|s| s.moves),
+        max_depth: steps.map(|s| s.max_depth),
+        lower_bound: LowerBounds::of(&input).best(),
+        verified,
+    }
+}
+
+fn is_sorted<T: PartialOrd>(v: &[T]) -> bool {
+    v.windows(2).all(|w| w[0] <= w[1])
+}
+
+// Count every cell, in the order of `cells()`. There's nothing to gain
+// from `jobs` or a shuffled schedule when nothing's timed, so they're
+// ignored.
+pub fn run_counts(config: &MatrixConfig) -> Vec<CountResult> {
+    thread::scope(|scope| {
+        thread::Builder::new()
+            .stack_size(STACK_SIZE)
+            .spawn_scoped(scope, || {
+                config
+                    .cells()
+                    .iter()
+                    .map(|cell| count_cell(config, cell))
+                    .collect()
+            })
+            .expect("couldn't start the counting thread")
+            .join()
+            .expect("the counting thread panicked")
+    })
+}
+
+fn show<N: ToString>(count: Option<N>) -> String {
+    count.map_or("-".to_string(), |n| n.to_string())
+}
+
+// Print the counts, one line per cell.
+pub fn print_counts(results: &[CountResult]) {
+    println!(
+        "{:<22} {:>10} {:<18} {:>5} {:>12} {:>12} {:>12} {:>12} {:>6}",
+        "algorithm",
+        "size",
+        "input",
+        "trial",
+        "comparisons",
+        "lower bound",
+        "swaps",
+        "moves",
+        "depth"
+    );
+    for r in results {
+        println!(
+            "{:<22} {:>10} {:<18} {:>5} {:>12} {:>12.0} {:>12} {:>12} {:>6}{}",
+            r.algorithm,
+            r.size,
+            r.describe_input(),
+            r.trial,
+            show(r.comparisons),
+            r.lower_bound,
+            show(r.swaps),
+            show(r.moves),
+            show(r.max_depth),
+            if r.verified { "" } else { "  NOT SORTED" }
+        );
+    }
+}
+
+// The counts as a Markdown table, one row per cell.
+pub fn counts_to_markdown(results: &[CountResult]) -> String {
+    let mut markdown = String::from(
+        "| algorithm | size | distribution | trial | comparisons | lower bound | swaps | moves \
+         | depth | sorted |\n\
+         |-----------|-----:|--------------|------:|------------:|------------:|------:|------:\
+         |------:|:------:|\n",
+    );
+    for r in results {
+        let _ = writeln!(
+            markdown,
+            "| {} | {} | {} | {} | {} | {:.0} | {} | {} | {} | {} |",
+            r.algorithm,
+            r.size,
+            r.describe_input(),
+            r.trial,
+            show(r.comparisons),
+            r.lower_bound,
+            show(r.swaps),
+            show(r.moves),
+            show(r.max_depth),
+            if r.verified { "yes" } else { "**NO**" }
+        );
+    }
+    markdown
+}
+
+// The counts as JSON, with `null` for the ones we couldn't count.
+pub fn counts_to_json(results: &[CountResult]) -> String {
+    let null = |count: Option<u64>| count.map_or("null".to_string(), |n| n.to_string());
+    let mut json = String::from("{\n  \"count_only\": true,\n  \"results\": [\n");
+    for (i, r) in results.iter().enumerate() {
+        let _ = write!(
+            json,
+            "    {{\"algorithm\": {}, \"size\": {}, \"distribution\": {}, \"trial\": {}, \
+             \"case\": {}, \"comparisons\": {}, \"lower_bound\": {:.1}, \"swaps\": {}, \
+             \"moves\": {}, \"max_depth\": {}, \"verified\": {}}}",
+            quote(r.algorithm),
+            r.size,
+            quote(r.distribution.name()),
+            r.trial,
+            r.case.map_or("null".to_string(), |case| quote(case.name())),
+            null(r.comparisons),
+            r.lower_bound,
+            null(r.swaps),
+            null(r.moves),
+            null(r.max_depth.map(|depth| depth as u64)),
+            r.verified
+        );
+        json.push_str(if i + 1 < results.len() { ",\n" } else { "\n" });
+    }
+    json.push_str("  ]\n}\n");
+    json
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::benchmark::find_algorithm;
+    use crate::json::{self, Json};
+
+    fn config(names: &[&str], distribution: Distribution) -> MatrixConfig {
+        MatrixConfig {
+            algorithms: names.iter().map(|n| find_algorithm(n).unwrap()).collect(),
+            sizes: vec![100, 200],
+            distributions: vec![distribution],
+            trials: 2,
+            seed: 279,
+            ..MatrixConfig::default()
+        }
+    }
+
+    #[test]
+    fn counts_are_reproducible() {
+        let names = [
+            "insertion",
+            "quicksort",
+            "randomized-quicksort",
+            "merge",
+            "heapsort",
+        ];
+        let config = config(&names, Distribution::Uniform);
+        let results = run_counts(&config);
+        assert_eq!(names.len() * 2 * 2, results.len());
+        assert!(results
+            .iter()
+            .all(|r| r.verified && r.comparisons.is_some()));
+        assert_eq!(results, run_counts(&config));
+    }
+
+    #[test]
+    fn what_we_can_count() {
+        let config = config(
+            &["quicksort", "heapsort", "parallel-merge"],
+            Distribution::Sorted,
+        );
+        let results = run_counts(&config);
+        let quick = &results[0];
+        // Already sorted, so every partition is as lopsided as can be.
+        assert_eq!(
+            (Some(99 * 100 / 2), Some(0), Some(99)),
+            (quick.comparisons, quick.swaps, quick.max_depth)
+        );
+        // And the best any sort could do is check it's in order.
+        assert_eq!(99.0, quick.lower_bound);
+        let heap = &results[1];
+        assert!(heap.comparisons.is_some() && heap.swaps.is_none());
+        let parallel = &results[2];
+        assert_eq!(None, parallel.comparisons);
+        assert!(parallel.verified);
+    }
+
+    #[test]
+    fn deep_recursion() {
+        // Deep enough to overflow an ordinary thread's stack.
+        let mut config = config(&["quicksort"], Distribution::Sorted);
+        config.sizes = vec![20_000];
+        config.trials = 1;
+        let results = run_counts(&config);
+        assert_eq!(Some(19_999), results[0].max_depth);
+    }
+
+    #[test]
+    fn json() {
+        let config = config(&["merge", "parallel-merge"], Distribution::Reversed);
+        let results = run_counts(&config);
+        let parsed = json::parse(&counts_to_json(&results)).unwrap();
+        let runs = parsed.get("results").and_then(Json::as_array).unwrap();
+        assert_eq!(results.len(), runs.len());
+        assert_eq!(
+            results[0].comparisons.map(|n| n as i64),
+            runs[0].get("comparisons").and_then(Json::as_i64)
+        );
+        assert_eq!(Some(&Json::Null), runs[1].get("comparisons"));
+        assert!(counts_to_markdown(&results).contains("| merge | 100 | reversed | 0 |"));
+    }
+}
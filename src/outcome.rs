@@ -0,0 +1,147 @@
+// Running one algorithm on one input and finding out everything the
+// demo prints about it: how long it took, how many comparisons it made,
+// how unsorted the input was (and so how few comparisons any sort could
+// have got away with; see `bounds.rs`), and whether the result is
+// actually right.
+// Anything that wants that information (the demo in `main.rs`, or a
+// program using this as a library) can call `run_sort` and get it all
+// back in one `SortOutcome`, rather than timing and checking the sort
+// itself.
+
+use crate::benchmark::{sort_generic, Algorithm};
+use crate::bounds::LowerBounds;
+use crate::instrument::{count_comparisons, counted};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SortStats {
+    pub size: usize,
+    // How many pairs of the input were out of order.
+    pub inversions: u64,
+    // The fewest comparisons a sort could make on this input, by various
+    // measures of how sorted it already was.
+    pub bounds: LowerBounds,
+    // How many comparisons the sort made, or `None` for parallel-merge,
+    // whose comparisons happen on other threads where we can't count
+    // them.
+    pub comparisons: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SortOutcome {
+    pub algorithm: &'static str,
+    pub elapsed: Duration,
+    pub stats: SortStats,
+    // Whether the result is in order and has exactly the values the
+    // input had.
+    pub verified: bool,
+}
+
+// Sort `v` with `algorithm` and report on how it went. The timing is of
+// the plain sort; the comparisons are counted on a separate run over a
+// copy of the input, so counting them doesn't slow down the timed run.
+pub fn run_sort(algorithm: &Algorithm, v: &mut [i32]) -> SortOutcome {
+    let input = v.to_vec();
+    let mut counted_input = counted(&input);
+    let (result, comparisons) =
+        count_comparisons(|| sort_generic(algorithm.name, &mut counted_input));
+
+    let before = Instant::now();
+    algorithm.sort(v);
+    let elapsed = before.elapsed();
+
+    let mut expected = input.clone();
+    expected.sort_unstable();
+    let bounds = LowerBounds::of(&input);
+    SortOutcome {
+        algorithm: algorithm.name,
+        elapsed,
+        stats: SortStats {
+            size: v.len(),
+            inversions: bounds.inversions,
+            bounds,
+            comparisons: result.ok().map(|()| comparisons),
+        },
+        verified: v == expected.as_slice(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::benchmark::{algorithms, find_algorithm};
+    use crate::sorter::Sorter;
+
+    #[test]
+    fn ten_items() {
+        let insertion = find_algorithm("insertion").unwrap();
+        let mut v = [3, 2, 0, 5, 8, 9, 6, 3, 2, 0];
+        let outcome = run_sort(&insertion, &mut v);
+        assert_eq!([0, 0, 2, 2, 3, 3, 5, 6, 8, 9], v);
+        assert_eq!("insertion", outcome.algorithm);
+        assert!(outcome.verified);
+        assert_eq!(10, outcome.stats.size);
+        assert_eq!(23, outcome.stats.inversions);
+        // Insertion sort makes one comparison per inversion, plus at
+        // most one more for each element after the first.
+        let comparisons = outcome.stats.comparisons.unwrap();
+        assert!((23..=23 + 9).contains(&comparisons), "{}", comparisons);
+        assert_eq!(7, outcome.stats.bounds.runs);
+    }
+
+    #[test]
+    fn adaptive_sorts_get_close_to_the_bound() {
+        // Sorted but for a few swaps: insertion sort only has to fix the
+        // inversions, so it should be within a small factor of the best
+        // possible, while heapsort ignores the order it was given.
+        let mut v: Vec<i32> = (0..1000).collect();
+        v.swap(10, 500);
+        v.swap(300, 301);
+        let ratio = |name| {
+            let outcome = run_sort(&find_algorithm(name).unwrap(), &mut v.clone());
+            let comparisons = outcome.stats.comparisons.unwrap();
+            outcome.stats.bounds.ratio(comparisons).unwrap()
+        };
+        assert!(ratio("insertion") < 3.0, "{}", ratio("insertion"));
+        assert!(ratio("heapsort") > 10.0, "{}", ratio("heapsort"));
+    }
+
+    #[test]
+    fn every_algorithm() {
+        for algorithm in algorithms() {
+            let mut v: Vec<i32> = (0..100).rev().collect();
+            let outcome = run_sort(&algorithm, &mut v);
+            assert!(outcome.verified, "{}", algorithm.name);
+            assert_eq!(100 * 99 / 2, outcome.stats.inversions);
+            assert_eq!(
+                algorithm.name != "parallel-merge",
+                outcome.stats.comparisons.is_some(),
+                "{}",
+                algorithm.name
+            );
+        }
+    }
+
+    #[test]
+    fn catches_a_broken_sort() {
+        struct Reverse;
+        impl Sorter<i32> for Reverse {
+            fn name(&self) -> &'static str {
+                "insertion"
+            }
+            fn stable(&self) -> bool {
+                true
+            }
+            fn sort(&self, v: &mut [i32]) {
+                v.reverse()
+            }
+        }
+        let broken = Algorithm {
+            name: "insertion",
+            sorter: &Reverse,
+            stable: true,
+        };
+        let mut v = [1, 2, 3];
+        assert!(!run_sort(&broken, &mut v).verified);
+    }
+}
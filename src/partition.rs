@@ -0,0 +1,157 @@
+// Partitioning is the "interesting" part of quicksort, but it's also
+// useful on its own: "move everything that satisfies some predicate to
+// the front" comes up all the time. These functions rearrange the slice
+// `v` so that all the elements for which `pred` returns `true` come
+// before all the elements for which it returns `false`, and return the
+// index of the first `false` element. More formally, they return an
+// index k where:
+//   (All i | 0 ≤ i < k : pred(v[i])) /\
+//   (All i | k ≤ i < length : !pred(v[i]))
+
+// The unstable version is the classic one-pass partition used by
+// quicksort. It's O(N) and does at most one swap per element, but
+// (as with quicksort itself) it doesn't preserve the relative order of
+// the elements within each of the two groups.
+pub fn partition_by<T, F: FnMut(&T) -> bool>(v: &mut [T], mut pred: F) -> usize {
+    // k is the boundary between the two groups; everything to the left of
+    // k is known to satisfy `pred`.
+    let mut k = 0;
+    for i in 0..v.len() {
+        // Invariant: (All x | 0 ≤ x < k : pred(v[x])) /\
+        //            (All x | k ≤ x < i : !pred(v[x]))
+        if pred(&v[i]) {
+            // v[i] belongs in the front, so swap it to the boundary
+            // and move the boundary one to the right.
+            v.swap(k, i);
+            k += 1;
+        }
+    }
+    k
+}
+
+// The stable version keeps the elements within each group in their
+// original relative order. We want to do this in place without requiring
+// `Clone` or `Copy`, so we use a divide-and-conquer approach: stably
+// partition each half, which gives us
+//    <front of left> <back of left> <front of right> <back of right>
+// and then rotate the middle two blocks so the two fronts end up next to
+// each other. Each level of recursion does O(N) work rotating, and there
+// are O(log N) levels, so this is O(N log N).
+pub fn stable_partition_by<T, F: FnMut(&T) -> bool>(v: &mut [T], mut pred: F) -> usize {
+    stable_partition_rec(v, &mut pred)
+}
+
+fn stable_partition_rec<T, F: FnMut(&T) -> bool>(v: &mut [T], pred: &mut F) -> usize {
+    let length = v.len();
+    if length == 0 {
+        return 0;
+    }
+    if length == 1 {
+        return if pred(&v[0]) { 1 } else { 0 };
+    }
+    let middle = length / 2;
+    let left_k = stable_partition_rec(&mut v[0..middle], pred);
+    let right_k = stable_partition_rec(&mut v[middle..length], pred);
+    // v[left_k..middle] is the back of the left half, and
+    // v[middle..middle+right_k] is the front of the right half.
+    // Rotating that range left by the size of the back of the left half
+    // swaps the two blocks while keeping each block in order.
+    v[left_k..middle + right_k].rotate_left(middle - left_k);
+    left_k + right_k
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    fn is_even(x: &i32) -> bool {
+        x % 2 == 0
+    }
+
+    fn random_vec(rng: &mut StdRng) -> Vec<i32> {
+        let len = rng.gen_range(0, 50);
+        (0..len).map(|_| rng.gen_range(0, 20)).collect()
+    }
+
+    fn assert_partitioned(v: &[i32], k: usize) {
+        assert!(v[0..k].iter().all(is_even));
+        assert!(!v[k..].iter().any(is_even));
+    }
+
+    fn assert_same_elements(original: &[i32], result: &[i32]) {
+        let mut a = original.to_vec();
+        let mut b = result.to_vec();
+        a.sort();
+        b.sort();
+        assert_eq!(a, b);
+    }
+
+    mod partition_by {
+        use super::*;
+
+        #[test]
+        fn empty() {
+            let mut input: [i32; 0] = [];
+            assert_eq!(0, partition_by(&mut input, is_even));
+        }
+
+        #[test]
+        fn ten_items() {
+            let mut input = [3, 2, 0, 5, 8, 9, 6, 3, 2, 0];
+            let k = partition_by(&mut input, is_even);
+            assert_eq!(6, k);
+            assert_partitioned(&input, k);
+        }
+
+        #[test]
+        fn random_inputs() {
+            let mut rng = StdRng::seed_from_u64(204);
+            for _ in 0..500 {
+                let original = random_vec(&mut rng);
+                let mut v = original.clone();
+                let k = partition_by(&mut v, is_even);
+                assert_partitioned(&v, k);
+                assert_same_elements(&original, &v);
+            }
+        }
+    }
+
+    mod stable_partition_by {
+        use super::*;
+
+        #[test]
+        fn empty() {
+            let mut input: [i32; 0] = [];
+            assert_eq!(0, stable_partition_by(&mut input, is_even));
+        }
+
+        #[test]
+        fn ten_items() {
+            let mut input = [3, 2, 0, 5, 8, 9, 6, 3, 2, 0];
+            let k = stable_partition_by(&mut input, is_even);
+            assert_eq!(6, k);
+            assert_eq!([2, 0, 8, 6, 2, 0, 3, 5, 9, 3], input);
+        }
+
+        #[test]
+        fn random_inputs() {
+            let mut rng = StdRng::seed_from_u64(204);
+            for _ in 0..500 {
+                let original = random_vec(&mut rng);
+                let mut v = original.clone();
+                let k = stable_partition_by(&mut v, is_even);
+                // A stable partition is exactly "all the evens in order,
+                // followed by all the odds in order".
+                let expected: Vec<i32> = original
+                    .iter()
+                    .filter(|x| is_even(x))
+                    .chain(original.iter().filter(|x| !is_even(x)))
+                    .cloned()
+                    .collect();
+                assert_eq!(expected, v);
+                assert_eq!(original.iter().filter(|x| is_even(x)).count(), k);
+            }
+        }
+    }
+}
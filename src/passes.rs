@@ -0,0 +1,165 @@
+// Sorting records by several keys, one key at a time.
+//
+// To sort people by last name and then, among people with the same last
+// name, by first name, we can sort by the *least* significant key
+// (first name) first, and then by each more significant key in turn
+// (last name). That works as long as every pass is stable: when the
+// last-name pass sees two Smiths it leaves them in the order the
+// first-name pass put them in. This is the same trick LSD radix sort
+// uses, one digit at a time.
+//
+// An unstable sort is free to swap equal elements, which would undo
+// the earlier passes, so we check the algorithm before sorting anything.
+
+use crate::benchmark::{sort_generic, Algorithm};
+use crate::error::UnstableAlgorithm;
+use crate::sorts::merge_sort;
+use std::cmp::Ordering;
+use std::fmt;
+
+// A key comparison: how two records compare on one field.
+pub type KeyOrder<'a, T> = &'a dyn Fn(&T, &T) -> Ordering;
+
+// Sort `v` by `keys`, most significant first, running `algorithm` once
+// per key starting from the last one. Fails without touching `v` if
+// `algorithm` isn't stable.
+pub fn sort_by_keys_in_passes<T: Copy + fmt::Debug>(
+    v: &mut [T],
+    keys: &[KeyOrder<T>],
+    algorithm: &Algorithm,
+) -> Result<(), UnstableAlgorithm> {
+    if !algorithm.stable {
+        return Err(UnstableAlgorithm {
+            algorithm: algorithm.name,
+        });
+    }
+    for key in keys.iter().rev() {
+        let mut wrapped: Vec<ByKey<T>> = v.iter().map(|&item| ByKey { item, key: *key }).collect();
+        // `sort_generic` can run everything except parallel-merge, whose
+        // threads would need the key to be `Sync`. Its output is exactly
+        // merge's, so we use that instead.
+        if sort_generic(algorithm.name, &mut wrapped).is_err() {
+            wrapped = merge_sort(&wrapped);
+        }
+        for (slot, wrapped) in v.iter_mut().zip(wrapped) {
+            *slot = wrapped.item;
+        }
+    }
+    Ok(())
+}
+
+// A record that compares by a single key, so the generic sorts (which
+// only know about `PartialOrd`) can sort by it.
+#[derive(Clone, Copy)]
+struct ByKey<'a, T> {
+    item: T,
+    key: KeyOrder<'a, T>,
+}
+
+impl<'a, T> PartialEq for ByKey<'a, T> {
+    fn eq(&self, other: &Self) -> bool {
+        (self.key)(&self.item, &other.item) == Ordering::Equal
+    }
+}
+
+impl<'a, T> PartialOrd for ByKey<'a, T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some((self.key)(&self.item, &other.item))
+    }
+}
+
+impl<'a, T: fmt::Debug> fmt::Debug for ByKey<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.item.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::benchmark::{algorithms, find_algorithm};
+    use crate::random::{RandomSource, SeededRandom};
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    struct Person {
+        last: &'static str,
+        first: &'static str,
+        age: u32,
+    }
+
+    fn person(last: &'static str, first: &'static str, age: u32) -> Person {
+        Person { last, first, age }
+    }
+
+    #[test]
+    fn last_then_first() {
+        let mut people = vec![
+            person("Smith", "Sam", 30),
+            person("Jones", "Ann", 41),
+            person("Smith", "Ann", 25),
+            person("Adams", "Zoe", 19),
+            person("Jones", "Al", 41),
+        ];
+        let by_last: KeyOrder<Person> = &|a, b| a.last.cmp(b.last);
+        let by_first: KeyOrder<Person> = &|a, b| a.first.cmp(b.first);
+        let merge = find_algorithm("merge").unwrap();
+        sort_by_keys_in_passes(&mut people, &[by_last, by_first], &merge).unwrap();
+        assert_eq!(
+            vec![
+                person("Adams", "Zoe", 19),
+                person("Jones", "Al", 41),
+                person("Jones", "Ann", 41),
+                person("Smith", "Ann", 25),
+                person("Smith", "Sam", 30),
+            ],
+            people
+        );
+    }
+
+    #[test]
+    fn matches_tuple_sort() {
+        // Three small keys, so there are lots of ties at every level.
+        let mut rng = SeededRandom::new(247);
+        let records: Vec<(i32, i32, i32)> = (0..200)
+            .map(|_| (rng.range(0, 4), rng.range(0, 4), rng.range(0, 4)))
+            .collect();
+        let mut expected = records.clone();
+        expected.sort_by_key(|&(a, b, _)| (a, b));
+        let keys: [KeyOrder<(i32, i32, i32)>; 2] = [&|x, y| x.0.cmp(&y.0), &|x, y| x.1.cmp(&y.1)];
+        for algorithm in algorithms().iter().filter(|a| a.stable) {
+            let mut v = records.clone();
+            sort_by_keys_in_passes(&mut v, &keys, algorithm).unwrap();
+            // The third field is never a key, so it has to keep its
+            // original order among ties too.
+            assert_eq!(expected, v, "{}", algorithm.name);
+        }
+    }
+
+    #[test]
+    fn unstable_algorithms_are_rejected() {
+        let original = vec![(1, 'b'), (0, 'a'), (1, 'a')];
+        let by_number: KeyOrder<(i32, char)> = &|x, y| x.0.cmp(&y.0);
+        for algorithm in algorithms().iter().filter(|a| !a.stable) {
+            let mut v = original.clone();
+            let result = sort_by_keys_in_passes(&mut v, &[by_number], algorithm);
+            assert_eq!(
+                Err(UnstableAlgorithm {
+                    algorithm: algorithm.name
+                }),
+                result
+            );
+            assert_eq!(original, v);
+        }
+    }
+
+    #[test]
+    fn no_records_or_no_keys() {
+        let insertion = find_algorithm("insertion").unwrap();
+        let mut empty: Vec<i32> = Vec::new();
+        sort_by_keys_in_passes(&mut empty, &[&|a: &i32, b: &i32| a.cmp(b)], &insertion).unwrap();
+        assert!(empty.is_empty());
+        let mut v = vec![3, 1, 2];
+        sort_by_keys_in_passes(&mut v, &[], &insertion).unwrap();
+        assert_eq!(vec![3, 1, 2], v);
+    }
+}
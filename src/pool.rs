@@ -0,0 +1,186 @@
+// A pool of worker threads that the parallel sorts share, so that
+// sorting doesn't start (and then throw away) a new set of threads every
+// time. Starting a thread takes tens of microseconds, which on a small
+// input can be more than the sort itself, and the benchmark would
+// mostly be timing thread startup.
+//
+// The pool starts empty and grows the first time somebody asks for more
+// threads than it has. Call `init_pool` first to start them ahead of
+// time, e.g. before timing anything.
+//
+// The only way to use the pool is `broadcast`, which runs a closure once
+// on each of several threads and waits for them all to finish, like
+// `std::thread::scope`. The calling thread always runs part 0 itself,
+// so `broadcast(n, f)` only needs n - 1 pool threads.
+
+use std::cell::Cell;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+// One channel per pool thread; thread i runs whatever is sent on
+// `senders[i]`, one job at a time.
+static SENDERS: Mutex<Vec<Sender<Job>>> = Mutex::new(Vec::new());
+
+thread_local! {
+    static IN_POOL: Cell<bool> = const { Cell::new(false) };
+}
+
+// Make sure the pool has enough threads for `broadcast(threads, ...)`,
+// starting any that are missing.
+pub fn init_pool(threads: usize) {
+    let mut senders = SENDERS.lock().unwrap();
+    while senders.len() + 1 < threads {
+        let (sender, receiver) = mpsc::channel::<Job>();
+        thread::Builder::new()
+            .name(format!("sort-pool-{}", senders.len() + 1))
+            .spawn(move || {
+                IN_POOL.with(|in_pool| in_pool.set(true));
+                for job in receiver {
+                    job();
+                }
+            })
+            .expect("couldn't start a pool thread");
+        senders.push(sender);
+    }
+}
+
+// How many threads `broadcast` can use without starting more: the pool
+// threads plus the calling thread.
+pub fn pool_size() -> usize {
+    SENDERS.lock().unwrap().len() + 1
+}
+
+// Run f(0), f(1), ..., f(threads - 1), each on its own thread, and
+// return once they've all finished. Part i always runs on the same pool
+// thread. If any part panics, so does `broadcast` (after waiting for the
+// others).
+//
+// Called from inside a pool thread (by a part of another `broadcast`),
+// this runs every part on the calling thread, one after another, since
+// otherwise the part could end up waiting for a job queued behind
+// itself.
+pub fn broadcast<F: Fn(usize) + Sync>(threads: usize, f: F) {
+    if threads <= 1 || IN_POOL.with(Cell::get) {
+        (0..threads).for_each(f);
+        return;
+    }
+    init_pool(threads);
+
+    // How many parts are still running, and whether any panicked.
+    let done = Arc::new((Mutex::new((threads - 1, false)), Condvar::new()));
+    let f: &(dyn Fn(usize) + Sync) = &f;
+    // SAFETY: the pool threads need a `'static` job, but `f` only lives
+    // until we return. That's fine, because we don't return (or unwind)
+    // until every job has finished with it: each job counts itself off
+    // `done` as its very last step, even if `f` panics, and we wait for
+    // the count to reach zero below. Nothing between here and that wait
+    // can panic once the first job is sent: a job we can't send is run
+    // here instead, and `f(0)` is run under `catch_unwind`. (This is on
+    // the list of unsafe code next to the `unsafe-opt` feature in
+    // Cargo.toml, with the Miri command that checks it.)
+    let f: &'static (dyn Fn(usize) + Sync) = unsafe { std::mem::transmute(f) };
+    let mut unsent = Vec::new();
+    {
+        let senders = SENDERS.lock().unwrap();
+        for (i, sender) in senders.iter().take(threads - 1).enumerate() {
+            let done = done.clone();
+            let job = move || {
+                let panicked = panic::catch_unwind(AssertUnwindSafe(|| f(i + 1))).is_err();
+                let (state, finished) = &*done;
+                let mut state = state.lock().unwrap();
+                state.0 -= 1;
+                state.1 |= panicked;
+                finished.notify_one();
+            };
+            // Pool threads never exit (their jobs catch panics), so this
+            // shouldn't happen, but if it does the part still gets run.
+            if let Err(mpsc::SendError(job)) = sender.send(Box::new(job)) {
+                unsent.push(job);
+            }
+        }
+    }
+    unsent.into_iter().for_each(|job| job());
+    let ours = panic::catch_unwind(AssertUnwindSafe(|| f(0)));
+
+    let (state, finished) = &*done;
+    let mut state = state.lock().unwrap();
+    while state.0 > 0 {
+        state = finished.wait(state).unwrap();
+    }
+    if let Err(payload) = ours {
+        panic::resume_unwind(payload);
+    }
+    if state.1 {
+        panic!("a pool thread panicked");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn thread_ids(threads: usize) -> Vec<thread::ThreadId> {
+        let ids = Mutex::new(vec![None; threads]);
+        broadcast(threads, |i| {
+            ids.lock().unwrap()[i] = Some(thread::current().id())
+        });
+        ids.into_inner()
+            .unwrap()
+            .into_iter()
+            .map(Option::unwrap)
+            .collect()
+    }
+
+    #[test]
+    fn runs_every_part_once() {
+        let count = AtomicUsize::new(0);
+        let parts = Mutex::new(Vec::new());
+        broadcast(5, |i| {
+            count.fetch_add(1, Ordering::SeqCst);
+            parts.lock().unwrap().push(i);
+        });
+        assert_eq!(5, count.into_inner());
+        let mut parts = parts.into_inner().unwrap();
+        parts.sort_unstable();
+        assert_eq!(vec![0, 1, 2, 3, 4], parts);
+        assert!(pool_size() >= 5);
+    }
+
+    #[test]
+    fn reuses_threads() {
+        let first = thread_ids(4);
+        let second = thread_ids(4);
+        // Part 0 runs on the calling thread; the rest on the same pool
+        // threads both times.
+        assert_eq!(thread::current().id(), first[0]);
+        assert_eq!(first, second);
+        let mut distinct = first.clone();
+        distinct.sort_by_key(|id| format!("{:?}", id));
+        distinct.dedup();
+        assert_eq!(4, distinct.len());
+    }
+
+    #[test]
+    fn nested_broadcasts_run_inline() {
+        let count = AtomicUsize::new(0);
+        broadcast(3, |_| {
+            broadcast(3, |_| {
+                count.fetch_add(1, Ordering::SeqCst);
+            })
+        });
+        assert_eq!(9, count.into_inner());
+    }
+
+    #[test]
+    fn panics_are_passed_on() {
+        let result = panic::catch_unwind(|| broadcast(3, |i| assert_ne!(2, i)));
+        assert!(result.is_err());
+        // And the pool still works afterwards.
+        assert_eq!(3, thread_ids(3).len());
+    }
+}
@@ -0,0 +1,77 @@
+// Software prefetching. In a big heap each level of a sift-down jumps
+// to a part of the array roughly `arity` times further along, so every
+// step is likely a cache miss. We know which elements the _next_ step
+// will look at (the grandchildren of the current node) before we've
+// finished this one, so we can ask the CPU to start loading them early.
+//
+// The only place that does this is `heap::sift_down_d`. Everything built
+// on the heap gets it from there: heapsort, `MaxHeap` and `MinHeap`, and
+// so the k-way merge in `merge.rs`, whose heap of list heads is a
+// `MinHeap`. (The crate has no loser-tree merge to prefetch in.)
+//
+// This only does anything with the `prefetch` feature on x86-64;
+// otherwise `prefetch_read` compiles to nothing. Compare
+//    cargo bench --bench prefetch
+//    cargo bench --bench prefetch --features prefetch
+// to see whether it helps on your machine.
+
+// Start loading v[index] into the cache, if it exists.
+#[cfg(all(feature = "prefetch", target_arch = "x86_64"))]
+#[inline(always)]
+pub fn prefetch_read<T>(v: &[T], index: usize) {
+    use std::arch::x86_64::{_mm_prefetch, _MM_HINT_T0};
+    if index < v.len() {
+        // SAFETY: prefetching has no visible effects, and the pointer
+        // is in bounds.
+        #[allow(unused_unsafe)]
+        unsafe {
+            _mm_prefetch(v.as_ptr().add(index) as *const i8, _MM_HINT_T0);
+        }
+    }
+}
+
+#[cfg(not(all(feature = "prefetch", target_arch = "x86_64")))]
+#[inline(always)]
+pub fn prefetch_read<T>(_v: &[T], _index: usize) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn out_of_bounds_is_ignored() {
+        let v = [1, 2, 3];
+        prefetch_read(&v, 1);
+        prefetch_read(&v, 3);
+        prefetch_read(&v, usize::MAX);
+    }
+
+    // Heaps far too big for the cache, so with the feature on the sorts
+    // and the merge really do go through the prefetching sift-down.
+    #[test]
+    fn heap_paths_still_sort() {
+        use crate::merge::kway_merge_d;
+        use crate::random::{RandomSource, SeededRandom};
+        use crate::sorts::heapsort_d;
+
+        let mut rng = SeededRandom::new(233);
+        let input: Vec<u64> = (0..1 << 20).map(|_| rng.next_u64()).collect();
+        let mut expected = input.clone();
+        expected.sort_unstable();
+        for &arity in &[2, 3, 4, 8] {
+            let mut v = input.clone();
+            heapsort_d(&mut v, arity);
+            assert_eq!(expected, v, "arity {}", arity);
+        }
+
+        let lists: Vec<Vec<u64>> = input
+            .chunks(16)
+            .map(|chunk| {
+                let mut list = chunk.to_vec();
+                list.sort_unstable();
+                list
+            })
+            .collect();
+        assert_eq!(expected, kway_merge_d(lists, 4));
+    }
+}
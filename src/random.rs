@@ -0,0 +1,185 @@
+// Where the crate's randomness comes from. Anything random (generating
+// test data, picking random pivots, shuffling) takes a `RandomSource`
+// rather than calling `thread_rng()` directly. That way a run can be
+// made reproducible by using a `SeededRandom` with a fixed seed, and
+// tests can use a `ScriptedRandom` to make exactly the "random" choices
+// they want to test.
+
+use rand::{rngs::StdRng, RngCore, SeedableRng};
+
+pub trait RandomSource {
+    // The next random 64-bit number.
+    fn next_u64(&mut self) -> u64;
+
+    // A random index in 0..bound. This just reduces `next_u64()` modulo
+    // `bound`, which is very slightly biased towards small values when
+    // `bound` doesn't divide 2^64; that doesn't matter for anything we
+    // do with it, and it keeps `ScriptedRandom` easy to script.
+    fn below(&mut self, bound: usize) -> usize {
+        assert!(bound > 0, "can't choose a random number below 0");
+        (self.next_u64() % bound as u64) as usize
+    }
+
+    // A random number in min..max.
+    fn range(&mut self, min: i32, max: i32) -> i32 {
+        assert!(min < max, "can't choose a random number from an empty range");
+        let width = (max as i64 - min as i64) as usize;
+        (min as i64 + self.below(width) as i64) as i32
+    }
+}
+
+// The normal source of randomness: a seedable pseudo-random number
+// generator.
+pub struct SeededRandom {
+    rng: StdRng,
+}
+
+impl SeededRandom {
+    // The same seed always produces the same sequence of numbers.
+    pub fn new(seed: u64) -> SeededRandom {
+        SeededRandom {
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    // Seeded from the operating system, so different every time.
+    pub fn from_entropy() -> SeededRandom {
+        SeededRandom {
+            rng: StdRng::from_entropy(),
+        }
+    }
+}
+
+impl RandomSource for SeededRandom {
+    fn next_u64(&mut self) -> u64 {
+        self.rng.next_u64()
+    }
+}
+
+// The environment variable that fixes the demo's seed, e.g.
+//    SORTLAB_SEED=42 cargo run
+pub const SEED_VAR: &str = "SORTLAB_SEED";
+
+// The seed in `SORTLAB_SEED`, as `lookup` sees it (the binary passes in
+// the real environment, and the tests their own), or else a random one.
+// Either way we hand back the seed itself, so it can be printed and the
+// run repeated.
+pub fn seed_from<F: Fn(&str) -> Option<String>>(lookup: F) -> Result<u64, String> {
+    match lookup(SEED_VAR) {
+        Some(value) => value
+            .trim()
+            .parse()
+            .map_err(|_| format!("{}: expected a number, not `{}`", SEED_VAR, value)),
+        None => Ok(SeededRandom::from_entropy().next_u64()),
+    }
+}
+
+// A "random" source that just plays back a fixed list of numbers, over
+// and over. Remember that `below(bound)` returns the scripted number
+// modulo `bound`, so scripting 0 always picks the first choice.
+pub struct ScriptedRandom {
+    values: Vec<u64>,
+    next: usize,
+}
+
+impl ScriptedRandom {
+    pub fn new(values: Vec<u64>) -> ScriptedRandom {
+        assert!(!values.is_empty(), "a scripted random source needs a script");
+        ScriptedRandom { values, next: 0 }
+    }
+}
+
+impl RandomSource for ScriptedRandom {
+    fn next_u64(&mut self) -> u64 {
+        let value = self.values[self.next];
+        self.next = (self.next + 1) % self.values.len();
+        value
+    }
+}
+
+// Put v in a uniformly random order using the Fisher-Yates shuffle: for
+// each position from the end back to the start, swap in a random element
+// from the part we haven't shuffled yet.
+pub fn shuffle<T, R: RandomSource>(v: &mut [T], rng: &mut R) {
+    for i in (1..v.len()).rev() {
+        // Invariant: v[i+1..length] is already shuffled.
+        let j = rng.below(i + 1);
+        v.swap(i, j);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seed_from_the_environment() {
+        let set = |value: &'static str| move |name: &str| match name {
+            SEED_VAR => Some(value.to_string()),
+            _ => None,
+        };
+        assert_eq!(Ok(504), seed_from(set("504")));
+        assert_eq!(Ok(504), seed_from(set(" 504\n")));
+        assert!(seed_from(set("lots")).is_err());
+        // Unset, so random, and different (almost certainly) each time.
+        assert_ne!(seed_from(|_| None), seed_from(|_| None));
+    }
+
+    #[test]
+    fn same_seed_same_numbers() {
+        let mut a = SeededRandom::new(217);
+        let mut b = SeededRandom::new(217);
+        for _ in 0..10 {
+            assert_eq!(a.next_u64(), b.next_u64());
+        }
+    }
+
+    #[test]
+    fn scripted_values_repeat() {
+        let mut rng = ScriptedRandom::new(vec![3, 1]);
+        assert_eq!(3, rng.next_u64());
+        assert_eq!(1, rng.next_u64());
+        assert_eq!(3, rng.next_u64());
+    }
+
+    #[test]
+    fn range_stays_in_range() {
+        let mut rng = SeededRandom::new(217);
+        for _ in 0..1000 {
+            let x = rng.range(-5, 5);
+            assert!((-5..5).contains(&x));
+        }
+        let mut rng = ScriptedRandom::new(vec![0, 9, 10]);
+        assert_eq!(-5, rng.range(-5, 5));
+        assert_eq!(4, rng.range(-5, 5));
+        assert_eq!(-5, rng.range(-5, 5));
+    }
+
+    mod shuffle {
+        use super::*;
+
+        #[test]
+        fn empty() {
+            let mut input: [i32; 0] = [];
+            shuffle(&mut input, &mut SeededRandom::new(217));
+        }
+
+        // Always choosing 0 swaps each element with the first one, from
+        // the back: [0,1,2,3] → [3,1,2,0] → [2,1,3,0] → [1,2,3,0].
+        #[test]
+        fn scripted() {
+            let mut input = [0, 1, 2, 3];
+            shuffle(&mut input, &mut ScriptedRandom::new(vec![0]));
+            assert_eq!([1, 2, 3, 0], input);
+        }
+
+        #[test]
+        fn keeps_the_same_elements() {
+            let mut input: Vec<i32> = (0..100).collect();
+            shuffle(&mut input, &mut SeededRandom::new(217));
+            assert_ne!((0..100).collect::<Vec<i32>>(), input);
+            input.sort();
+            assert_eq!((0..100).collect::<Vec<i32>>(), input);
+        }
+    }
+}
@@ -0,0 +1,233 @@
+// The headline conclusion of the lab, "which sort should I use when?",
+// computed from a matrix run rather than eyeballed from its output.
+//
+// For each scenario (an input size and distribution) we rank the
+// algorithms by their median time over the trials, fastest first. Then
+// to rank them overall we give each algorithm a Borda count: in a
+// scenario with k algorithms, first place is worth k - 1 points, second
+// place k - 2, and so on down to nothing for last. Adding up the points
+// rewards doing well everywhere, rather than being spectacular in one
+// scenario and hopeless in the rest (which is what adding up the times
+// would reward, since the biggest inputs would swamp everything else).
+// Algorithms with the same number of points share a medal.
+//
+// A run that didn't sort its input doesn't count, so an algorithm that
+// fails a scenario simply doesn't place in it.
+
+use crate::benchmark::RunResult;
+use std::cmp::Reverse;
+use std::fmt::Write;
+use std::time::Duration;
+
+// One scenario's ranking, fastest first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScenarioRanking {
+    pub size: usize,
+    // The distribution, and case if there is one (see
+    // `RunResult::describe_input`).
+    pub input: String,
+    pub places: Vec<(&'static str, Duration)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rankings {
+    // In the order the scenarios first appear in the results.
+    pub scenarios: Vec<ScenarioRanking>,
+    // Every algorithm with its Borda count, most points first.
+    pub overall: Vec<(&'static str, usize)>,
+}
+
+// Every time each algorithm took in one scenario.
+type Times = Vec<(&'static str, Vec<Duration>)>;
+
+const MEDALS: [&str; 3] = ["🥇", "🥈", "🥉"];
+
+pub fn rank(results: &[RunResult]) -> Rankings {
+    // Group the times by scenario, and within that by algorithm, keeping
+    // everything in the order it first appears.
+    let mut grouped: Vec<(usize, String, Times)> = Vec::new();
+    for r in results.iter().filter(|r| r.verified) {
+        let input = r.describe_input();
+        let scenario = match grouped
+            .iter()
+            .position(|(size, name, _)| *size == r.size && *name == input)
+        {
+            Some(i) => i,
+            None => {
+                grouped.push((r.size, input, Vec::new()));
+                grouped.len() - 1
+            }
+        };
+        let times = &mut grouped[scenario].2;
+        match times.iter_mut().find(|(name, _)| *name == r.algorithm) {
+            Some((_, elapsed)) => elapsed.push(r.elapsed),
+            None => times.push((r.algorithm, vec![r.elapsed])),
+        }
+    }
+
+    let scenarios: Vec<ScenarioRanking> = grouped
+        .into_iter()
+        .map(|(size, input, times)| {
+            let mut places: Vec<(&'static str, Duration)> = times
+                .into_iter()
+                .map(|(name, elapsed)| (name, median(elapsed)))
+                .collect();
+            // A stable sort, so ties keep the order the algorithms ran in.
+            places.sort_by_key(|&(_, elapsed)| elapsed);
+            ScenarioRanking {
+                size,
+                input,
+                places,
+            }
+        })
+        .collect();
+
+    let mut overall: Vec<(&'static str, usize)> = Vec::new();
+    for scenario in &scenarios {
+        let k = scenario.places.len();
+        for (place, &(name, _)) in scenario.places.iter().enumerate() {
+            let points = k - 1 - place;
+            match overall.iter_mut().find(|(n, _)| *n == name) {
+                Some((_, total)) => *total += points,
+                None => overall.push((name, points)),
+            }
+        }
+    }
+    overall.sort_by_key(|&(_, points)| Reverse(points));
+    Rankings { scenarios, overall }
+}
+
+fn median(mut times: Vec<Duration>) -> Duration {
+    times.sort_unstable();
+    times[times.len() / 2]
+}
+
+// The rankings as text, e.g.
+//    rankings:
+//      1000 uniform:  🥇 merge (40µs)  🥈 quicksort (52µs)  🥉 heapsort (61µs)
+//    overall (Borda count):
+//      🥇 merge 6  🥈 quicksort 4  🥉 heapsort 2
+pub fn summary(rankings: &Rankings) -> String {
+    let mut text = String::from("rankings:\n");
+    for scenario in &rankings.scenarios {
+        let _ = write!(text, "  {} {}:", scenario.size, scenario.input);
+        for (place, (name, elapsed)) in scenario.places.iter().enumerate() {
+            let _ = write!(text, "  {} {} ({:?})", medal(place), name, elapsed);
+        }
+        text.push('\n');
+    }
+    text.push_str("overall (Borda count):\n");
+    for (name, points) in &rankings.overall {
+        // Algorithms with the same number of points share a place.
+        let place = rankings.overall.iter().filter(|o| o.1 > *points).count();
+        let _ = write!(text, "  {} {} {}", medal(place), name, points);
+    }
+    text.push('\n');
+    text
+}
+
+fn medal(place: usize) -> String {
+    match MEDALS.get(place) {
+        Some(medal) => medal.to_string(),
+        None => format!("{}.", place + 1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::benchmark::Distribution;
+
+    fn result(algorithm: &'static str, size: usize, micros: u64, trial: usize) -> RunResult {
+        RunResult {
+            algorithm,
+            size,
+            distribution: Distribution::Uniform,
+            trial,
+            case: None,
+            elapsed: Duration::from_micros(micros),
+            verified: true,
+            huge_pages: false,
+            input_checksum: 0,
+        }
+    }
+
+    fn results() -> Vec<RunResult> {
+        vec![
+            result("insertion", 10, 1, 0),
+            result("quicksort", 10, 2, 0),
+            result("merge", 10, 3, 0),
+            result("insertion", 1000, 300, 0),
+            result("quicksort", 1000, 40, 0),
+            result("merge", 1000, 50, 0),
+            result("insertion", 1000, 290, 1),
+            // One bad trial shouldn't matter: we take the median.
+            result("quicksort", 1000, 900, 1),
+            result("merge", 1000, 55, 1),
+            result("insertion", 1000, 310, 2),
+            result("quicksort", 1000, 45, 2),
+            result("merge", 1000, 52, 2),
+        ]
+    }
+
+    #[test]
+    fn per_scenario() {
+        let rankings = rank(&results());
+        assert_eq!(2, rankings.scenarios.len());
+        let small = &rankings.scenarios[0];
+        assert_eq!((10, "uniform"), (small.size, small.input.as_str()));
+        let names: Vec<&str> = small.places.iter().map(|p| p.0).collect();
+        assert_eq!(vec!["insertion", "quicksort", "merge"], names);
+        let big = &rankings.scenarios[1];
+        assert_eq!(
+            vec![
+                ("quicksort", Duration::from_micros(45)),
+                ("merge", Duration::from_micros(52)),
+                ("insertion", Duration::from_micros(300)),
+            ],
+            big.places
+        );
+    }
+
+    #[test]
+    fn borda_count() {
+        let rankings = rank(&results());
+        // quicksort: 1 + 2, insertion: 2 + 0, merge: 0 + 1.
+        assert_eq!(
+            vec![("quicksort", 3), ("insertion", 2), ("merge", 1)],
+            rankings.overall
+        );
+    }
+
+    #[test]
+    fn failures_dont_place() {
+        let mut results = results();
+        results[0].verified = false;
+        let rankings = rank(&results);
+        let names: Vec<&str> = rankings.scenarios[0].places.iter().map(|p| p.0).collect();
+        assert_eq!(vec!["quicksort", "merge"], names);
+    }
+
+    #[test]
+    fn text() {
+        let text = summary(&rank(&results()));
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(
+            vec![
+                "rankings:",
+                "  10 uniform:  🥇 insertion (1µs)  🥈 quicksort (2µs)  🥉 merge (3µs)",
+                "  1000 uniform:  🥇 quicksort (45µs)  🥈 merge (52µs)  🥉 insertion (300µs)",
+                "overall (Borda count):",
+                "  🥇 quicksort 3  🥈 insertion 2  🥉 merge 1",
+            ],
+            lines
+        );
+        assert_eq!("4.", medal(3));
+        let tied = Rankings {
+            scenarios: Vec::new(),
+            overall: vec![("merge", 5), ("quicksort", 5), ("heapsort", 2)],
+        };
+        assert!(summary(&tied).ends_with("  🥇 merge 5  🥇 quicksort 5  🥉 heapsort 2\n"));
+        assert_eq!("rankings:\noverall (Borda count):\n\n", summary(&rank(&[])));
+    }
+}
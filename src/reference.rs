@@ -0,0 +1,210 @@
+// Known-correct versions of the two functions the lab asks students to
+// write, `partition::partition_by` and `merge::merge`, for differential
+// testing: feed both versions lots of random inputs and report the first
+// place their outputs differ. Only built with the `reference` feature,
+// so the answers aren't sitting in the lab handout.
+//
+// The comparison is element by element, and the elements are `Tagged`
+// with their original positions, so it checks not just that the output
+// is sorted (or partitioned) but that it's exactly the arrangement the
+// algorithm is supposed to produce.
+
+use crate::grade::Tagged;
+use crate::random::{RandomSource, SeededRandom};
+use std::fmt;
+
+// The one-pass partition: everything satisfying `pred` is swapped down
+// to the boundary as we come to it.
+pub fn partition_by<T, F: FnMut(&T) -> bool>(v: &mut [T], mut pred: F) -> usize {
+    let mut k = 0;
+    for i in 0..v.len() {
+        if pred(&v[i]) {
+            v.swap(k, i);
+            k += 1;
+        }
+    }
+    k
+}
+
+// The plain two-way merge, taking from `xs` on ties.
+pub fn merge<T: PartialOrd + Copy>(xs: &[T], ys: &[T]) -> Vec<T> {
+    let mut result = Vec::with_capacity(xs.len() + ys.len());
+    let (mut i, mut j) = (0, 0);
+    while i < xs.len() && j < ys.len() {
+        if ys[j] < xs[i] {
+            result.push(ys[j]);
+            j += 1;
+        } else {
+            result.push(xs[i]);
+            i += 1;
+        }
+    }
+    result.extend_from_slice(&xs[i..]);
+    result.extend_from_slice(&ys[j..]);
+    result
+}
+
+// Where the lab's version first disagreed with the reference.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Divergence {
+    pub function: &'static str,
+    // Which random case (counting from 0) it happened on.
+    pub case: usize,
+    // The input, written as `key@position`.
+    pub input: String,
+    pub detail: String,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} diverged from the reference on case {}, input {}: {}",
+            self.function, self.case, self.input, self.detail
+        )
+    }
+}
+
+fn show(items: &[Tagged]) -> String {
+    let items: Vec<String> = items
+        .iter()
+        .map(|t| format!("{}@{}", t.key, t.tag))
+        .collect();
+    format!("[{}]", items.join(", "))
+}
+
+// The first index where `actual` differs from `expected`, comparing tags
+// as well as keys.
+fn compare(expected: &[Tagged], actual: &[Tagged]) -> Result<(), String> {
+    let same = |a: &Tagged, b: &Tagged| a.key == b.key && a.tag == b.tag;
+    match (0..expected.len().max(actual.len()))
+        .find(|&i| !matches!((expected.get(i), actual.get(i)), (Some(e), Some(a)) if same(e, a)))
+    {
+        Some(i) => {
+            let describe = |item: Option<&Tagged>| match item {
+                Some(t) => format!("{}@{}", t.key, t.tag),
+                None => "nothing".to_string(),
+            };
+            Err(format!(
+                "at index {} expected {}, got {} (expected {}, got {})",
+                i,
+                describe(expected.get(i)),
+                describe(actual.get(i)),
+                show(expected),
+                show(actual)
+            ))
+        }
+        None => Ok(()),
+    }
+}
+
+// A random input: up to 20 items with keys in 0..5, so there are plenty
+// of ties.
+fn random_case(rng: &mut SeededRandom) -> Vec<Tagged> {
+    let size = rng.below(21);
+    (0..size)
+        .map(|tag| Tagged {
+            key: rng.range(0, 5),
+            tag,
+        })
+        .collect()
+}
+
+// Partition `cases` random inputs around a random key with both
+// versions. Returns the number of cases tried, or the first divergence.
+pub fn diff_partition(cases: usize, seed: u64) -> Result<usize, Divergence> {
+    let mut rng = SeededRandom::new(seed);
+    for case in 0..cases {
+        let input = random_case(&mut rng);
+        let pivot = rng.range(0, 5);
+        let mut expected = input.clone();
+        let expected_k = partition_by(&mut expected, |t| t.key < pivot);
+        let mut actual = input.clone();
+        let actual_k = crate::partition::partition_by(&mut actual, |t| t.key < pivot);
+        let result = if expected_k != actual_k {
+            Err(format!("returned {}, expected {}", actual_k, expected_k))
+        } else {
+            compare(&expected, &actual)
+        };
+        if let Err(detail) = result {
+            return Err(Divergence {
+                function: "partition_by",
+                case,
+                input: format!("{} around < {}", show(&input), pivot),
+                detail,
+            });
+        }
+    }
+    Ok(cases)
+}
+
+// Merge `cases` random pairs of sorted inputs with both versions. The
+// lab's merge is called through `mutants::merge`, so injected bugs show
+// up here too.
+pub fn diff_merge(cases: usize, seed: u64) -> Result<usize, Divergence> {
+    let mut rng = SeededRandom::new(seed);
+    for case in 0..cases {
+        let mut input = random_case(&mut rng);
+        let middle = rng.below(input.len() + 1);
+        input[..middle].sort_by_key(|t| t.key);
+        input[middle..].sort_by_key(|t| t.key);
+        let (xs, ys) = input.split_at(middle);
+        let expected = merge(xs, ys);
+        let actual = crate::mutants::merge(xs.to_vec(), ys.to_vec());
+        if let Err(detail) = compare(&expected, &actual) {
+            return Err(Divergence {
+                function: "merge",
+                case,
+                input: format!("{} and {}", show(xs), show(ys)),
+                detail,
+            });
+        }
+    }
+    Ok(cases)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mutants::{with_bugs, Bug};
+
+    #[test]
+    fn reference_partition() {
+        let mut v = vec![3, 2, 0, 5, 8, 9, 6, 3, 2, 0];
+        let k = partition_by(&mut v, |&x| x < 3);
+        assert_eq!(4, k);
+        assert!(v[..k].iter().all(|&x| x < 3));
+        assert!(v[k..].iter().all(|&x| x >= 3));
+    }
+
+    #[test]
+    fn reference_merge() {
+        assert_eq!(vec![0, 2, 3, 5, 6, 8, 9], merge(&[5, 8, 9], &[0, 2, 3, 6]));
+    }
+
+    #[test]
+    fn lab_code_agrees_with_reference() {
+        assert_eq!(Ok(1000), diff_partition(1000, 241));
+        assert_eq!(Ok(1000), diff_merge(1000, 241));
+    }
+
+    #[test]
+    fn reports_divergence() {
+        let divergence = with_bugs(&[Bug::UnstableMerge], || diff_merge(1000, 241)).unwrap_err();
+        assert_eq!("merge", divergence.function);
+        assert!(divergence.detail.starts_with("at index"), "{}", divergence);
+    }
+
+    #[test]
+    fn compare_lengths() {
+        let t = |key, tag| Tagged { key, tag };
+        assert_eq!(Ok(()), compare(&[t(1, 0)], &[t(1, 0)]));
+        assert!(compare(&[t(1, 0)], &[t(1, 1)]).is_err());
+        let missing = compare(&[t(1, 0), t(2, 1)], &[t(1, 0)]).unwrap_err();
+        assert!(
+            missing.starts_with("at index 1 expected 2@1, got nothing"),
+            "{}",
+            missing
+        );
+    }
+}
@@ -0,0 +1,309 @@
+// An interactive prompt for playing with the sorts on small arrays, e.g.
+//    > gen 10 nearly-sorted
+//    > show
+//    > sort quick --trace
+//    > stats
+// Type `help` for the full list of commands. It's handy for working
+// through an algorithm by hand in recitation, or for seeing exactly
+// which comparisons a buggy sort makes.
+
+use crate::analysis::summarize;
+use crate::benchmark::{algorithms, sort_generic, Algorithm, Distribution};
+use crate::instrument::{inversions, trace_comparisons, traced, untraced};
+use std::io::{self, BufRead, Write};
+use std::time::Instant;
+
+pub const HELP: &str = "\
+commands:
+    gen N [DISTRIBUTION] [SEED]   make a new array of N values; DISTRIBUTION is
+                                  uniform (the default), sorted, reversed,
+                                  nearly-sorted, all-equal, few-unique,
+                                  median-first, or merge-worst
+    set X Y Z ...                 use exactly these values as the array
+    show                          print the array
+    sort ALGORITHM [--trace]      sort the array; --trace prints every comparison
+    stats                         describe the array
+    algorithms                    list the algorithms (any unique prefix will do)
+    help                          print this message
+    quit                          leave";
+
+// The state of a session: just the array we're playing with.
+#[derive(Debug, Default)]
+pub struct Repl {
+    array: Vec<i32>,
+}
+
+impl Repl {
+    pub fn new() -> Repl {
+        Repl::default()
+    }
+
+    pub fn array(&self) -> &[i32] {
+        &self.array
+    }
+
+    // Run one line's worth of command, returning what to print (or an
+    // error message to print instead).
+    pub fn execute(&mut self, line: &str) -> Result<String, String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.split_first() {
+            None => Ok(String::new()),
+            Some((&"gen", args)) => self.generate(args),
+            Some((&"set", args)) => self.set(args),
+            Some((&"show", [])) => Ok(format!("{:?}", self.array)),
+            Some((&"sort", args)) => self.sort(args),
+            Some((&"stats", [])) => Ok(self.stats()),
+            Some((&"algorithms", [])) => {
+                let names: Vec<&str> = algorithms().iter().map(|a| a.name).collect();
+                Ok(names.join("\n"))
+            }
+            Some((&"help", [])) => Ok(HELP.to_string()),
+            Some((command, _)) => Err(format!("can't make sense of `{}`; try `help`", command)),
+        }
+    }
+
+    fn generate(&mut self, args: &[&str]) -> Result<String, String> {
+        let (size, distribution, seed) = match args {
+            [size] => (size, "uniform", None),
+            [size, distribution] => (size, *distribution, None),
+            [size, distribution, seed] => (size, *distribution, Some(seed)),
+            _ => return Err("usage: gen N [DISTRIBUTION] [SEED]".to_string()),
+        };
+        let size: usize = size
+            .parse()
+            .map_err(|_| format!("`{}` isn't a size", size))?;
+        if size > i32::MAX as usize {
+            return Err(format!("{} is too many values", size));
+        }
+        let distribution = Distribution::from_name(distribution)
+            .ok_or_else(|| format!("unknown distribution `{}`", distribution))?;
+        let seed = match seed {
+            Some(seed) => seed
+                .parse()
+                .map_err(|_| format!("`{}` isn't a seed", seed))?,
+            None => rand::random(),
+        };
+        self.array = distribution.generate(size, seed);
+        Ok(format!(
+            "{} {} values (seed {})",
+            size,
+            distribution.name(),
+            seed
+        ))
+    }
+
+    fn set(&mut self, args: &[&str]) -> Result<String, String> {
+        self.array = args
+            .iter()
+            .map(|x| x.parse().map_err(|_| format!("`{}` isn't a number", x)))
+            .collect::<Result<_, _>>()?;
+        Ok(format!("{} values", self.array.len()))
+    }
+
+    fn sort(&mut self, args: &[&str]) -> Result<String, String> {
+        let (name, trace) = match args {
+            [name] => (name, false),
+            [name, "--trace"] => (name, true),
+            _ => return Err("usage: sort ALGORITHM [--trace]".to_string()),
+        };
+        let algorithm = find_by_prefix(name)?;
+        if !trace {
+            let before = Instant::now();
+            algorithm.sort(&mut self.array);
+            return Ok(format!(
+                "sorted {} values with {} in {:?}",
+                self.array.len(),
+                algorithm.name,
+                before.elapsed()
+            ));
+        }
+        let mut v = traced(&self.array);
+        let (result, trace) = trace_comparisons(|| sort_generic(algorithm.name, &mut v));
+        result?;
+        self.array = untraced(v);
+        let mut output = trace.join("\n");
+        if !output.is_empty() {
+            output.push('\n');
+        }
+        output.push_str(&format!(
+            "sorted {} values with {} in {} comparisons",
+            self.array.len(),
+            algorithm.name,
+            trace.len()
+        ));
+        Ok(output)
+    }
+
+    fn stats(&self) -> String {
+        let mut sorted = self.array.clone();
+        sorted.sort_unstable();
+        let mut output = format!(
+            "{} values, {} inversions{}",
+            self.array.len(),
+            inversions(&self.array),
+            if sorted == self.array {
+                " (sorted)"
+            } else {
+                ""
+            }
+        );
+        if let Some(summary) = summarize(&sorted) {
+            output.push_str(&format!(
+                "\nmin {}, median {}, max {}, {} distinct",
+                summary.min, summary.median, summary.max, summary.distinct
+            ));
+        }
+        output
+    }
+}
+
+// The algorithm with exactly this name, or failing that the only one
+// whose name starts with it, so `quick` means `quicksort`.
+fn find_by_prefix(name: &str) -> Result<Algorithm, String> {
+    let all = algorithms();
+    if let Some(algorithm) = all.iter().find(|a| a.name == name) {
+        return Ok(*algorithm);
+    }
+    let matches: Vec<&Algorithm> = all.iter().filter(|a| a.name.starts_with(name)).collect();
+    match matches.as_slice() {
+        [algorithm] => Ok(**algorithm),
+        [] => Err(format!("unknown algorithm `{}`", name)),
+        _ => {
+            let names: Vec<&str> = matches.iter().map(|a| a.name).collect();
+            Err(format!("`{}` could be any of {}", name, names.join(", ")))
+        }
+    }
+}
+
+// Read commands from `input` until it runs out or we're told to quit,
+// printing a prompt before each one.
+pub fn run<R: BufRead, W: Write>(input: R, mut output: W) -> io::Result<()> {
+    let mut repl = Repl::new();
+    let mut lines = input.lines();
+    loop {
+        write!(output, "> ")?;
+        output.flush()?;
+        let line = match lines.next() {
+            Some(line) => line?,
+            None => break,
+        };
+        match line.trim() {
+            "quit" | "exit" => break,
+            line => match repl.execute(line) {
+                Ok(text) if text.is_empty() => {}
+                Ok(text) => writeln!(output, "{}", text)?,
+                Err(message) => writeln!(output, "error: {}", message)?,
+            },
+        }
+    }
+    writeln!(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_and_sort() {
+        let mut repl = Repl::new();
+        repl.execute("set 3 2 0 5 8 9 6 3 2 0").unwrap();
+        assert_eq!(
+            "[3, 2, 0, 5, 8, 9, 6, 3, 2, 0]",
+            repl.execute("show").unwrap()
+        );
+        assert!(repl
+            .execute("sort quick")
+            .unwrap()
+            .starts_with("sorted 10 values with quicksort"));
+        assert_eq!(&[0, 0, 2, 2, 3, 3, 5, 6, 8, 9], repl.array());
+    }
+
+    #[test]
+    fn generate() {
+        let mut repl = Repl::new();
+        repl.execute("gen 50 nearly-sorted 238").unwrap();
+        assert_eq!(50, repl.array().len());
+        let first = repl.array().to_vec();
+        repl.execute("gen 50 nearly-sorted 238").unwrap();
+        assert_eq!(first, repl.array());
+        repl.execute("gen 5 reversed").unwrap();
+        assert_eq!(&[4, 3, 2, 1, 0], repl.array());
+    }
+
+    #[test]
+    fn trace() {
+        let mut repl = Repl::new();
+        repl.execute("set 2 1 3").unwrap();
+        let output = repl.execute("sort insertion --trace").unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(
+            vec![
+                "2 > 1",
+                "2 < 3",
+                "sorted 3 values with insertion in 2 comparisons"
+            ],
+            lines[lines.len() - 3..].to_vec()
+        );
+        assert_eq!(&[1, 2, 3], repl.array());
+        for algorithm in algorithms() {
+            repl.execute("gen 20 uniform 1").unwrap();
+            let traced = repl.execute(&format!("sort {} --trace", algorithm.name));
+            if algorithm.name == "parallel-merge" {
+                assert!(traced.is_err());
+            } else {
+                assert!(traced.is_ok(), "{}", algorithm.name);
+                assert!(
+                    repl.array().windows(2).all(|w| w[0] <= w[1]),
+                    "{}",
+                    algorithm.name
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn stats() {
+        let mut repl = Repl::new();
+        assert_eq!(
+            "0 values, 0 inversions (sorted)",
+            repl.execute("stats").unwrap()
+        );
+        repl.execute("set 3 1 2 2").unwrap();
+        assert_eq!(
+            "4 values, 3 inversions\nmin 1, median 2, max 3, 3 distinct",
+            repl.execute("stats").unwrap()
+        );
+    }
+
+    #[test]
+    fn prefixes() {
+        assert_eq!("heapsort", find_by_prefix("heap").unwrap().name);
+        assert_eq!("merge", find_by_prefix("merge").unwrap().name);
+        assert!(find_by_prefix("bogo").is_err());
+        assert_eq!("weak-heapsort", find_by_prefix("w").unwrap().name);
+        // Ambiguous.
+        assert!(find_by_prefix("").is_err());
+    }
+
+    #[test]
+    fn errors() {
+        let mut repl = Repl::new();
+        assert!(repl.execute("gen").is_err());
+        assert!(repl.execute("gen ten").is_err());
+        assert!(repl.execute("gen 10 lumpy").is_err());
+        assert!(repl.execute("set 1 two").is_err());
+        assert!(repl.execute("sort").is_err());
+        assert!(repl.execute("sort quick --verbose").is_err());
+        assert!(repl.execute("shuffle").is_err());
+    }
+
+    #[test]
+    fn session() {
+        let input = "set 2 1\n\nbogus\nsort merge\nshow\nquit\nshow\n";
+        let mut output = Vec::new();
+        run(input.as_bytes(), &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("> error: can't make sense of `bogus`"));
+        assert!(output.contains("> [1, 2]\n> \n"), "{}", output);
+    }
+}
@@ -0,0 +1,328 @@
+// Writing benchmark results out in formats other programs (and people)
+// can read: JSON for scripts and spreadsheets, a Markdown table for
+// pasting into a lab write-up, and a one-line JSON summary for grading
+// scripts and CI jobs that just want to know how the run went.
+//
+// There's nothing fancy enough in here to need a JSON library; the only
+// strings we write are algorithm, distribution, and case names.
+
+use crate::benchmark::{MatrixConfig, RunResult};
+use crate::json::quote;
+use crate::ranking::rank;
+use std::fmt::Write;
+use std::time::Duration;
+
+// The results as JSON: the parallel-merge settings they were run with
+// (with the threshold in `i32`s, which is what the matrix sorts), the
+// seed the cells were shuffled with (or null if they weren't), and an
+// array with one object per cell, e.g.
+//    {
+//      "parallel": {"threads": 8, "chunk_size": 65536, "threshold": 65536},
+//      "schedule_seed": 266,
+//      "results": [
+//        {"algorithm": "quicksort", "size": 1000, "distribution": "uniform",
+//         "trial": 0, "case": null, "elapsed_ns": 41230, "verified": true,
+//         "huge_pages": false, "input_checksum": "8cf1e3f0f75e1b5b"}
+//      ]
+//    }
+// (but with each object on one line). The case is "best", "average", or
+// "worst" when the matrix was run with `--cases auto`. The checksum is a
+// string because JSON numbers can't hold every 64-bit value exactly.
+pub fn to_json(results: &[RunResult], config: &MatrixConfig) -> String {
+    let parallel = &config.parallel;
+    let mut json = String::from("{\n");
+    let _ = writeln!(
+        json,
+        "  \"parallel\": {{\"threads\": {}, \"chunk_size\": {}, \"threshold\": {}}},",
+        parallel.threads,
+        parallel.chunk_size,
+        parallel.threshold_for::<i32>()
+    );
+    let _ = writeln!(
+        json,
+        "  \"schedule_seed\": {},",
+        config
+            .schedule_seed
+            .map_or("null".to_string(), |seed| seed.to_string())
+    );
+    json.push_str("  \"results\": [\n");
+    for (i, r) in results.iter().enumerate() {
+        let _ = write!(
+            json,
+            "    {{\"algorithm\": {}, \"size\": {}, \"distribution\": {}, \"trial\": {}, \
+             \"case\": {}, \"elapsed_ns\": {}, \"verified\": {}, \"huge_pages\": {}, \
+             \"input_checksum\": \"{:016x}\"}}",
+            quote(r.algorithm),
+            r.size,
+            quote(r.distribution.name()),
+            r.trial,
+            r.case.map_or("null".to_string(), |case| quote(case.name())),
+            r.elapsed.as_nanos(),
+            r.verified,
+            r.huge_pages,
+            r.input_checksum
+        );
+        json.push_str(if i + 1 < results.len() { ",\n" } else { "\n" });
+    }
+    json.push_str("  ]\n}\n");
+    json
+}
+
+// The results as a Markdown table, one row per cell, after a line
+// saying how parallel-merge was set up (and one saying how the cells
+// were shuffled, if they were).
+pub fn to_markdown(results: &[RunResult], config: &MatrixConfig) -> String {
+    let mut markdown = format!("parallel-merge: {}\n", config.parallel);
+    if let Some(seed) = config.schedule_seed {
+        let _ = writeln!(markdown, "cells run in random order (schedule seed {})", seed);
+    }
+    markdown.push('\n');
+    markdown.push_str(
+        "| algorithm | size | distribution | trial | time | sorted |\n\
+         |-----------|-----:|--------------|------:|-----:|:------:|\n",
+    );
+    for r in results {
+        let _ = writeln!(
+            markdown,
+            "| {} | {} | {} | {} | {:?} | {} |",
+            r.algorithm,
+            r.size,
+            r.describe_input(),
+            r.trial,
+            r.elapsed,
+            if r.verified { "yes" } else { "**NO**" }
+        );
+    }
+    markdown
+}
+
+// How `matrix` prints its results: the usual text (one line per run,
+// then the rankings), a Markdown report (`to_markdown`, then the
+// rankings), or a JSON one (`to_json`, and nothing else, so it can be
+// piped straight into another program).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReportFormat {
+    #[default]
+    Text,
+    Markdown,
+    Json,
+}
+
+impl ReportFormat {
+    pub fn from_name(name: &str) -> Option<ReportFormat> {
+        match name {
+            "text" => Some(ReportFormat::Text),
+            "markdown" => Some(ReportFormat::Markdown),
+            "json" => Some(ReportFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+// How a matrix run ended. Each has its own exit code, so a script can
+// tell them apart without parsing anything:
+//    0  every run sorted its input
+//    1  something else went wrong, e.g. the inputs wouldn't fit in
+//       `--max-memory` (2 is for bad arguments, before anything runs)
+//    3  at least one run didn't sort its input
+//    4  the matrix didn't finish within `--timeout`
+//    5  we couldn't read or write a file, or standard output
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RunStatus {
+    Ok,
+    Unsorted,
+    TimedOut,
+    Io(String),
+    Failed(String),
+}
+
+impl RunStatus {
+    pub fn name(&self) -> &'static str {
+        match self {
+            RunStatus::Ok => "ok",
+            RunStatus::Unsorted => "unsorted",
+            RunStatus::TimedOut => "timeout",
+            RunStatus::Io(_) => "io-error",
+            RunStatus::Failed(_) => "error",
+        }
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            RunStatus::Ok => 0,
+            RunStatus::Failed(_) => 1,
+            RunStatus::Unsorted => 3,
+            RunStatus::TimedOut => 4,
+            RunStatus::Io(_) => 5,
+        }
+    }
+
+    // What went wrong, for the statuses that come with a message.
+    pub fn message(&self) -> Option<&str> {
+        match self {
+            RunStatus::Io(message) | RunStatus::Failed(message) => Some(message),
+            _ => None,
+        }
+    }
+}
+
+// The whole run summed up in a single line of JSON (newline included),
+// e.g.
+//    {"status": "unsorted", "exit_code": 3, "runs": 8, "verified": 7,
+//     "unsorted": [{"algorithm": "merge", "size": 100, "input": "uniform",
+//     "trial": 1}], "winner": "quicksort", "schedule_seed": null,
+//     "elapsed_ms": 41, "error": null}
+// `results` is whatever finished (nothing, if the run timed out or
+// never started). The winner is the algorithm with the best Borda count
+// (see `ranking.rs`), or null if nothing sorted its input.
+pub fn summary_json(
+    status: &RunStatus,
+    results: &[RunResult],
+    config: &MatrixConfig,
+    elapsed: Duration,
+) -> String {
+    let unsorted: Vec<String> = results
+        .iter()
+        .filter(|r| !r.verified)
+        .map(|r| {
+            format!(
+                "{{\"algorithm\": {}, \"size\": {}, \"input\": {}, \"trial\": {}}}",
+                quote(r.algorithm),
+                r.size,
+                quote(&r.describe_input()),
+                r.trial
+            )
+        })
+        .collect();
+    format!(
+        "{{\"status\": {}, \"exit_code\": {}, \"runs\": {}, \"verified\": {}, \
+         \"unsorted\": [{}], \"winner\": {}, \"schedule_seed\": {}, \"elapsed_ms\": {}, \
+         \"error\": {}}}\n",
+        quote(status.name()),
+        status.exit_code(),
+        results.len(),
+        results.len() - unsorted.len(),
+        unsorted.join(", "),
+        rank(results)
+            .overall
+            .first()
+            .map_or("null".to_string(), |&(name, _)| quote(name)),
+        config
+            .schedule_seed
+            .map_or("null".to_string(), |seed| seed.to_string()),
+        elapsed.as_millis(),
+        status.message().map_or("null".to_string(), quote)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::benchmark::Distribution;
+    use crate::cases::Case;
+    use crate::sorts::ParallelConfig;
+    use std::time::Duration;
+
+    fn results() -> Vec<RunResult> {
+        vec![
+            RunResult {
+                algorithm: "quicksort",
+                size: 10,
+                distribution: Distribution::Uniform,
+                trial: 0,
+                case: None,
+                elapsed: Duration::from_nanos(1500),
+                verified: true,
+                huge_pages: false,
+                input_checksum: 0xff,
+            },
+            RunResult {
+                algorithm: "merge",
+                size: 10,
+                distribution: Distribution::MergeWorst,
+                trial: 1,
+                case: Some(Case::Worst),
+                elapsed: Duration::from_micros(2),
+                verified: false,
+                huge_pages: false,
+                input_checksum: 0x0123_4567_89ab_cdef,
+            },
+        ]
+    }
+
+    fn config() -> MatrixConfig {
+        MatrixConfig {
+            parallel: ParallelConfig {
+                threads: 4,
+                chunk_size: 1000,
+                threshold: Some(5000),
+                verbose: false,
+            },
+            ..MatrixConfig::default()
+        }
+    }
+
+    #[test]
+    fn json() {
+        let expected = "{\n  \
+            \"parallel\": {\"threads\": 4, \"chunk_size\": 1000, \"threshold\": 5000},\n  \
+            \"schedule_seed\": null,\n  \
+            \"results\": [\n    \
+            {\"algorithm\": \"quicksort\", \"size\": 10, \"distribution\": \"uniform\", \
+            \"trial\": 0, \"case\": null, \"elapsed_ns\": 1500, \"verified\": true, \
+            \"huge_pages\": false, \"input_checksum\": \"00000000000000ff\"},\n    \
+            {\"algorithm\": \"merge\", \"size\": 10, \"distribution\": \"merge-worst\", \
+            \"trial\": 1, \"case\": \"worst\", \"elapsed_ns\": 2000, \"verified\": false, \
+            \"huge_pages\": false, \"input_checksum\": \"0123456789abcdef\"}\n  \
+            ]\n}\n";
+        assert_eq!(expected, to_json(&results(), &config()));
+        assert!(to_json(&[], &config()).ends_with("\"results\": [\n  ]\n}\n"));
+        let shuffled = MatrixConfig {
+            schedule_seed: Some(266),
+            ..config()
+        };
+        assert!(to_json(&[], &shuffled).contains("\n  \"schedule_seed\": 266,\n"));
+    }
+
+    #[test]
+    fn markdown() {
+        let markdown = to_markdown(&results(), &config());
+        let lines: Vec<&str> = markdown.lines().collect();
+        assert_eq!(6, lines.len());
+        assert_eq!(
+            "parallel-merge: 4 threads, chunks of 1000, sequential below 5000 elements",
+            lines[0]
+        );
+        assert_eq!("| quicksort | 10 | uniform | 0 | 1.5µs | yes |", lines[4]);
+        assert_eq!("| merge | 10 | merge-worst (worst) | 1 | 2µs | **NO** |", lines[5]);
+
+        let shuffled = MatrixConfig {
+            schedule_seed: Some(266),
+            ..config()
+        };
+        let markdown = to_markdown(&results(), &shuffled);
+        let lines: Vec<&str> = markdown.lines().collect();
+        assert_eq!(7, lines.len());
+        assert_eq!("cells run in random order (schedule seed 266)", lines[1]);
+    }
+
+    #[test]
+    fn summary() {
+        let expected = "{\"status\": \"unsorted\", \"exit_code\": 3, \"runs\": 2, \
+            \"verified\": 1, \"unsorted\": [{\"algorithm\": \"merge\", \"size\": 10, \
+            \"input\": \"merge-worst (worst)\", \"trial\": 1}], \"winner\": \"quicksort\", \
+            \"schedule_seed\": null, \"elapsed_ms\": 1500, \"error\": null}\n";
+        let elapsed = Duration::from_millis(1500);
+        let summary = summary_json(&RunStatus::Unsorted, &results(), &config(), elapsed);
+        assert_eq!(expected, summary);
+        assert_eq!(1, summary.lines().count());
+
+        let failed = RunStatus::Io("couldn't write \"out.json\"".to_string());
+        let summary = summary_json(&failed, &[], &config(), Duration::ZERO);
+        assert!(summary.starts_with("{\"status\": \"io-error\", \"exit_code\": 5, \"runs\": 0,"));
+        assert!(summary.contains("\"unsorted\": [], \"winner\": null,"));
+        assert!(summary.ends_with("\"error\": \"couldn't write \\\"out.json\\\"\"}\n"));
+        assert_eq!(4, RunStatus::TimedOut.exit_code());
+        assert_eq!(0, RunStatus::Ok.exit_code());
+    }
+}
@@ -0,0 +1,128 @@
+// Picking k items "mostly from the top". A recommender that always shows
+// the k best-scoring items shows everyone the same thing, and nothing
+// below the top k ever gets a chance; picking k at random ignores the
+// scores altogether. In between, we sort by score and then draw k items
+// (without replacement) where the item at rank r (0 for the best) has
+// weight
+//    e^(-r / temperature)
+// With a low temperature the top items are almost always the ones
+// drawn; as it goes up the weights even out, until every item is about
+// as likely as any other.
+//
+// To draw without replacement we use the "Gumbel top-k" trick: add
+// random noise with a Gumbel distribution to each item's log-weight
+// (-r / temperature) and keep the k items with the largest totals.
+// That's exactly the same as drawing one item at a time in proportion
+// to the weights of the ones that are left, but it's one pass and a
+// `top_k`, rather than k passes over the weights.
+
+use crate::analysis::shuffle_ties;
+use crate::random::{RandomSource, SeededRandom};
+use crate::select::top_k;
+use crate::sorts::heapsort;
+
+// Draw k of the items (or all of them, if there are fewer), in the
+// order they were drawn. The items are their own
+// scores, highest best; to sample things by a score, put the score
+// first in a tuple. A temperature of 0 (or less) always gives the top k.
+// Items with equal scores are put in a random order first (see
+// `shuffle_ties`), so none of them is favored for where it was in `v`.
+pub fn sample_by_rank<T: PartialOrd + Clone + std::fmt::Debug>(
+    v: &[T],
+    k: usize,
+    temperature: f64,
+    seed: u64,
+) -> Vec<T> {
+    if temperature.is_nan() || temperature <= 0.0 {
+        return top_k(v.iter().cloned(), k);
+    }
+    let mut ranked = v.to_vec();
+    heapsort(&mut ranked);
+    ranked.reverse();
+    shuffle_ties(&mut ranked, |x| x.clone(), seed);
+    let mut rng = SeededRandom::new(seed);
+    let keys = (0..ranked.len()).map(|rank| {
+        let key = -(rank as f64) / temperature + gumbel(&mut rng);
+        // `top_k` keeps the largest; the rank is there to break ties.
+        (key, std::cmp::Reverse(rank))
+    });
+    top_k(keys, k)
+        .into_iter()
+        .map(|(_, rank)| ranked[rank.0].clone())
+        .collect()
+}
+
+// A sample from the standard Gumbel distribution, -ln(-ln(u)) for u
+// uniform in (0, 1).
+fn gumbel<R: RandomSource>(rng: &mut R) -> f64 {
+    // 53 random bits make a multiple of 2^-53 in [0, 1); adding half a
+    // step keeps it off 0 and 1, either of which would give us an
+    // infinity.
+    let u = ((rng.next_u64() >> 11) as f64 + 0.5) / (1u64 << 53) as f64;
+    -(-u.ln()).ln()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cold_is_top_k() {
+        let v = [3, 2, 0, 5, 8, 9, 6, 3, 2, 0];
+        assert_eq!(vec![9, 8, 6], sample_by_rank(&v, 3, 0.0, 282));
+        // So cold that nothing but the top could be drawn.
+        assert_eq!(vec![9, 8, 6], sample_by_rank(&v, 3, 1e-6, 282));
+        assert_eq!(Vec::<i32>::new(), sample_by_rank(&v, 0, 1.0, 282));
+    }
+
+    #[test]
+    fn draws_without_replacement() {
+        let v: Vec<i32> = (0..20).collect();
+        for seed in 0..50 {
+            let mut drawn = sample_by_rank(&v, 20, 3.0, seed);
+            drawn.sort();
+            assert_eq!(v, drawn);
+        }
+        assert_eq!(20, sample_by_rank(&v, 50, 3.0, 282).len());
+    }
+
+    #[test]
+    fn same_seed_same_sample() {
+        let v: Vec<(u32, &str)> = vec![(5, "a"), (3, "b"), (5, "c"), (1, "d"), (4, "e")];
+        assert_eq!(
+            sample_by_rank(&v, 3, 2.0, 282),
+            sample_by_rank(&v, 3, 2.0, 282)
+        );
+    }
+
+    #[test]
+    fn weights_follow_rank() {
+        // With temperature 1, the best of five is drawn first with
+        // probability 1 / (1 + e^-1 + e^-2 + e^-3 + e^-4) ≈ 0.64, and the
+        // worst with probability e^-4 times that ≈ 0.012.
+        let v = [10, 20, 30, 40, 50];
+        let mut firsts = [0; 5];
+        for seed in 0..5000 {
+            let first = sample_by_rank(&v, 1, 1.0, seed)[0];
+            firsts[(50 - first) as usize / 10] += 1;
+        }
+        assert!((3000..3400).contains(&firsts[0]), "{:?}", firsts);
+        assert!(firsts.windows(2).all(|w| w[0] > w[1]), "{:?}", firsts);
+        assert!((20..110).contains(&firsts[4]), "{:?}", firsts);
+    }
+
+    #[test]
+    fn hot_is_uniform() {
+        let v = [10, 20, 30, 40, 50];
+        let mut firsts = [0; 5];
+        for seed in 0..5000 {
+            let first = sample_by_rank(&v, 1, 1e9, seed)[0];
+            firsts[(50 - first) as usize / 10] += 1;
+        }
+        assert!(
+            firsts.iter().all(|&n| (800..1200).contains(&n)),
+            "{:?}",
+            firsts
+        );
+    }
+}
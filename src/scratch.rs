@@ -0,0 +1,331 @@
+// Scratch space that doesn't have to be initialized before it's used,
+// and the merge that needs it. This is the one place the `unsafe-opt`
+// feature's unsafe code lives, so it can be checked in one go with
+//    cargo +nightly miri test --features unsafe-opt --lib -- scratch buffered
+// (the tests are kept small so that finishes in reasonable time). The
+// crate's other unsafe code is listed next to the feature in Cargo.toml.
+//
+// `merge_sort` can only sort `Copy` values, because its merges copy
+// elements around freely. To sort things like `String`s in place we need
+// to *move* elements into a buffer and back, and the buffer has to be
+// able to hold moved-out values without thinking it owns them (or it
+// would drop them a second time). That's what `MaybeUninit` is for:
+// memory the compiler treats as possibly garbage, which it never drops
+// or reads unless we say so.
+//
+// `ScratchBuffer` wraps that up behind an API that can't be misused:
+//  * the elements it does own (from `push` and `fill_cloned`) are always
+//    a prefix of the buffer, `len` long, and only those get dropped;
+//  * `merge` borrows the buffer to hold half of a merge, and leaves the
+//    slice it's merging a permutation of what it was even if a
+//    comparison panics partway through.
+
+use std::mem::MaybeUninit;
+use std::ptr;
+
+pub struct ScratchBuffer<T> {
+    buf: Vec<MaybeUninit<T>>,
+    // (All i | 0 ≤ i < len : buf[i] is initialized and owned by us)
+    len: usize,
+}
+
+impl<T> ScratchBuffer<T> {
+    pub fn new() -> ScratchBuffer<T> {
+        ScratchBuffer::with_capacity(0)
+    }
+
+    pub fn with_capacity(capacity: usize) -> ScratchBuffer<T> {
+        let mut buf = Vec::with_capacity(capacity);
+        // Uninitialized slots don't need initializing, so this doesn't
+        // write anything; it just makes them part of the `Vec`.
+        buf.resize_with(capacity, MaybeUninit::uninit);
+        ScratchBuffer { buf, len: 0 }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.buf.len()
+    }
+
+    // How many elements the buffer currently holds.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // Make room for at least `capacity` elements in all.
+    pub fn reserve(&mut self, capacity: usize) {
+        if self.buf.len() < capacity {
+            self.buf.resize_with(capacity, MaybeUninit::uninit);
+        }
+    }
+
+    pub fn push(&mut self, item: T) {
+        self.reserve(self.len + 1);
+        self.buf[self.len].write(item);
+        self.len += 1;
+    }
+
+    // Drop everything in the buffer, keeping the memory.
+    pub fn clear(&mut self) {
+        // Set `len` first, so that if a destructor panics we leak the
+        // rest rather than dropping any of them twice.
+        let len = std::mem::replace(&mut self.len, 0);
+        let initialized = ptr::slice_from_raw_parts_mut(self.buf.as_mut_ptr() as *mut T, len);
+        // SAFETY: the first `len` elements were initialized and owned by
+        // us, and nothing will read them again now that `len` is 0.
+        unsafe { ptr::drop_in_place(initialized) };
+    }
+
+    // Replace the contents with clones of `items`. Each clone is counted
+    // as soon as it's made, so if `clone` panics the ones made so far
+    // still get dropped.
+    pub fn fill_cloned(&mut self, items: &[T]) -> &mut [T]
+    where
+        T: Clone,
+    {
+        self.clear();
+        self.reserve(items.len());
+        for item in items {
+            self.buf[self.len].write(item.clone());
+            self.len += 1;
+        }
+        self.as_mut_slice()
+    }
+
+    pub fn as_slice(&self) -> &[T] {
+        // SAFETY: the first `len` elements are initialized, and
+        // `MaybeUninit<T>` has the same layout as `T`.
+        unsafe { &*(ptr::slice_from_raw_parts(self.buf.as_ptr() as *const T, self.len)) }
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        // SAFETY: as for `as_slice`.
+        unsafe { &mut *(ptr::slice_from_raw_parts_mut(self.buf.as_mut_ptr() as *mut T, self.len)) }
+    }
+
+    // Stably merge the sorted runs v[..middle] and v[middle..] in place,
+    // using the buffer to hold the left run. The buffer's own contents
+    // are dropped first. `is_less(a, b)` should be `a < b`.
+    pub fn merge<F>(&mut self, v: &mut [T], middle: usize, is_less: &mut F)
+    where
+        F: FnMut(&T, &T) -> bool,
+    {
+        let len = v.len();
+        if middle == 0 || middle >= len {
+            return;
+        }
+        self.clear();
+        self.reserve(middle);
+        let v = v.as_mut_ptr();
+        let buf = self.buf.as_mut_ptr() as *mut T;
+        // SAFETY: we move the left run into the buffer, leaving a hole in
+        // v[..middle]. From then on
+        //    (All i | 0 ≤ i < dest : v[i] is merged output)
+        //    hole.start..hole.end is what's left of the left run (in buf)
+        //    right..len is what's left of the right run (in v)
+        //    dest == hole.start + (right - middle)
+        // so dest + (hole.end - hole.start) == right: the hole is exactly
+        // v[dest..right], and writing to v[dest] never overwrites an
+        // unmerged element. Dropping `hole` (after the loop, or if
+        // `is_less` panics) moves what's left of the left run into the
+        // hole, so v ends up holding every element exactly once. The
+        // buffer's `len` stays 0 throughout, so it never drops any of
+        // these elements itself.
+        unsafe {
+            ptr::copy_nonoverlapping(v, buf, middle);
+            let mut hole = Hole {
+                buf,
+                start: 0,
+                end: middle,
+                dest: v,
+            };
+            let mut right = middle;
+            while hole.start < hole.end && right < len {
+                // Take from the right only when it's strictly smaller, so
+                // equal elements stay in order.
+                let take_right = is_less(&*v.add(right), &*buf.add(hole.start));
+                let source = if take_right {
+                    v.add(right)
+                } else {
+                    buf.add(hole.start)
+                };
+                ptr::copy_nonoverlapping(source, hole.dest, 1);
+                hole.dest = hole.dest.add(1);
+                if take_right {
+                    right += 1;
+                } else {
+                    hole.start += 1;
+                }
+            }
+        }
+    }
+}
+
+impl<T> Default for ScratchBuffer<T> {
+    fn default() -> ScratchBuffer<T> {
+        ScratchBuffer::new()
+    }
+}
+
+impl<T> Drop for ScratchBuffer<T> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+// The part of a merge's left run that hasn't been put back yet:
+// buf[start..end], which belongs at dest.
+struct Hole<T> {
+    buf: *mut T,
+    start: usize,
+    end: usize,
+    dest: *mut T,
+}
+
+impl<T> Drop for Hole<T> {
+    fn drop(&mut self) {
+        // SAFETY: see `merge`; the hole at `dest` is exactly this long.
+        unsafe {
+            ptr::copy_nonoverlapping(self.buf.add(self.start), self.dest, self.end - self.start);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::panic::{self, AssertUnwindSafe};
+    use std::rc::Rc;
+
+    // A value that counts how many of its kind are alive, so we can
+    // check nothing is dropped twice (the count would go negative) or
+    // leaked (it wouldn't get back to zero).
+    #[derive(Debug)]
+    struct Tracked {
+        key: i32,
+        id: usize,
+        alive: Rc<Cell<isize>>,
+    }
+
+    impl Tracked {
+        fn new(key: i32, id: usize, alive: &Rc<Cell<isize>>) -> Tracked {
+            alive.set(alive.get() + 1);
+            Tracked {
+                key,
+                id,
+                alive: alive.clone(),
+            }
+        }
+    }
+
+    impl Clone for Tracked {
+        fn clone(&self) -> Tracked {
+            assert!(self.key >= 0, "can't clone negative keys");
+            Tracked::new(self.key, self.id, &self.alive)
+        }
+    }
+
+    impl Drop for Tracked {
+        fn drop(&mut self) {
+            self.alive.set(self.alive.get() - 1);
+            assert!(self.alive.get() >= 0, "dropped twice");
+        }
+    }
+
+    #[test]
+    fn push_and_clear() {
+        let alive = Rc::new(Cell::new(0));
+        let mut buffer = ScratchBuffer::new();
+        for i in 0..5 {
+            buffer.push(Tracked::new(i, 0, &alive));
+        }
+        assert_eq!(5, buffer.len());
+        assert_eq!(5, alive.get());
+        assert_eq!(3, buffer.as_slice()[3].key);
+        buffer.clear();
+        assert!(buffer.is_empty());
+        assert_eq!(0, alive.get());
+        assert!(buffer.capacity() >= 5);
+
+        buffer.push(Tracked::new(7, 0, &alive));
+        drop(buffer);
+        assert_eq!(0, alive.get());
+    }
+
+    #[test]
+    fn fill_cloned_cleans_up_after_a_panic() {
+        let alive = Rc::new(Cell::new(0));
+        let items: Vec<Tracked> = [1, 2, -1, 3]
+            .iter()
+            .map(|&key| Tracked::new(key, 0, &alive))
+            .collect();
+        let mut buffer = ScratchBuffer::new();
+        assert_eq!(2, buffer.fill_cloned(&items[..2]).len());
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            buffer.fill_cloned(&items);
+        }));
+        assert!(result.is_err());
+        // The two clones made before the panic are still counted.
+        assert_eq!(items.len() + 2, alive.get() as usize);
+        drop(buffer);
+        drop(items);
+        assert_eq!(0, alive.get());
+    }
+
+    #[test]
+    fn merge() {
+        let mut buffer = ScratchBuffer::new();
+        let mut v = vec![0, 3, 5, 9, 0, 2, 2, 6, 8];
+        buffer.merge(&mut v, 4, &mut |a, b| a < b);
+        assert_eq!(vec![0, 0, 2, 2, 3, 5, 6, 8, 9], v);
+        // Either run can be empty.
+        buffer.merge(&mut v, 0, &mut |a, b| a < b);
+        buffer.merge(&mut v, 9, &mut |a, b| a < b);
+        assert_eq!(vec![0, 0, 2, 2, 3, 5, 6, 8, 9], v);
+    }
+
+    #[test]
+    fn merge_is_stable() {
+        let alive = Rc::new(Cell::new(0));
+        let mut v: Vec<Tracked> = [1, 1, 2, 1, 2, 2]
+            .iter()
+            .enumerate()
+            .map(|(id, &key)| Tracked::new(key, id, &alive))
+            .collect();
+        ScratchBuffer::new().merge(&mut v, 3, &mut |a, b| a.key < b.key);
+        let ids: Vec<usize> = v.iter().map(|t| t.id).collect();
+        assert_eq!(vec![0, 1, 3, 2, 4, 5], ids);
+        drop(v);
+        assert_eq!(0, alive.get());
+    }
+
+    #[test]
+    fn merge_survives_a_panicking_comparison() {
+        let alive = Rc::new(Cell::new(0));
+        let mut v: Vec<Tracked> = [1, 4, 6, 2, 3, 5]
+            .iter()
+            .enumerate()
+            .map(|(id, &key)| Tracked::new(key, id, &alive))
+            .collect();
+        let mut comparisons = 0;
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            ScratchBuffer::new().merge(&mut v, 3, &mut |a: &Tracked, b: &Tracked| {
+                comparisons += 1;
+                assert!(comparisons < 3, "comparison failed");
+                a.key < b.key
+            })
+        }));
+        assert!(result.is_err());
+        // Every element is still there exactly once.
+        let mut ids: Vec<usize> = v.iter().map(|t| t.id).collect();
+        ids.sort_unstable();
+        assert_eq!(vec![0, 1, 2, 3, 4, 5], ids);
+        assert_eq!(6, alive.get());
+        drop(v);
+        assert_eq!(0, alive.get());
+    }
+}
@@ -0,0 +1,309 @@
+// Searching sorted slices: the companion to sorting. Like the standard
+// library's `binary_search`, the searches return `Ok(i)` if `v[i] == target`,
+// and otherwise `Err(i)` where i is the index target would have to be
+// inserted at to keep `v` sorted.
+
+use std::cmp::Ordering;
+
+// Plain binary search: halve the range every step, so O(log N)
+// comparisons no matter what the data looks like.
+pub fn binary_search<T: PartialOrd>(v: &[T], target: &T) -> Result<usize, usize> {
+    let mut lo = 0;
+    let mut hi = v.len();
+    while lo < hi {
+        // Invariant: (All i | 0 ≤ i < lo : v[i] < target) /\
+        //            (All i | hi ≤ i < length : v[i] > target)
+        let mid = lo + (hi - lo) / 2;
+        match v[mid].partial_cmp(target) {
+            Some(Ordering::Less) => lo = mid + 1,
+            Some(Ordering::Greater) => hi = mid,
+            _ => return Ok(mid),
+        }
+    }
+    Err(lo)
+}
+
+// Types we can do arithmetic on well enough to guess where a value is.
+pub trait Numeric: PartialOrd + Copy {
+    fn to_f64(self) -> f64;
+}
+
+macro_rules! numeric {
+    ($($t:ty),*) => {
+        $(impl Numeric for $t {
+            fn to_f64(self) -> f64 {
+                self as f64
+            }
+        })*
+    };
+}
+
+numeric!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize, f32, f64);
+
+// Interpolation search is what you do with a phone book: if you're
+// looking for "Smith" you don't open it in the middle, you open it about
+// three quarters of the way through. Rather than probing the middle of
+// the range, we probe where the target _would_ be if the values were
+// spread evenly between the two ends of the range.
+//
+// On uniformly distributed data this takes O(log log N) probes, which
+// is much better than binary search. On badly skewed data, though, the
+// guesses can be terrible and it degrades to O(N) probes (see
+// `benches/search.rs`).
+//
+// The guesses are only guesses: we convert to `f64` to compute them, but
+// all the decisions are made by comparing the actual values, so losing
+// precision (say, with huge `u64`s) can make it slower but never wrong.
+pub fn interpolation_search<T: Numeric>(v: &[T], target: T) -> Result<usize, usize> {
+    let mut lo = 0;
+    let mut hi = v.len();
+    while lo < hi {
+        // Invariant: (All i | 0 ≤ i < lo : v[i] < target) /\
+        //            (All i | hi ≤ i < length : v[i] > target)
+        if target < v[lo] {
+            return Err(lo);
+        }
+        if target > v[hi - 1] {
+            return Err(hi);
+        }
+        // Now v[lo] ≤ target ≤ v[hi - 1], so the guess lands in the
+        // range (as long as the values are comparable at all).
+        let low = v[lo].to_f64();
+        let high = v[hi - 1].to_f64();
+        let fraction = if high > low {
+            (target.to_f64() - low) / (high - low)
+        } else {
+            0.0
+        };
+        // `as usize` turns NaN into 0 and clamps at the top.
+        let probe = (lo + (fraction * (hi - 1 - lo) as f64) as usize).min(hi - 1);
+        match v[probe].partial_cmp(&target) {
+            Some(Ordering::Less) => lo = probe + 1,
+            Some(Ordering::Greater) => hi = probe,
+            Some(Ordering::Equal) => return Ok(probe),
+            // The target can't be compared (it's NaN), so it isn't here.
+            None => return Err(lo),
+        }
+    }
+    Err(lo)
+}
+
+// Galloping (exponential search): find the first element of `v` that
+// doesn't satisfy `pred`, assuming every element that does comes before
+// every element that doesn't (as with `partition_by`). Rather than
+// starting in the middle, we check positions 1, 2, 4, 8, ... from the
+// front until we overshoot, and then binary search the last gap. If the
+// answer is k this takes O(log k) comparisons, so it's much better than
+// binary search when the answer is near the front, and never more than
+// about twice as bad.
+pub fn gallop_by<T, F: FnMut(&T) -> bool>(v: &[T], mut pred: F) -> usize {
+    let mut lo = 0;
+    let mut bound = 1;
+    // Invariant: (All i | 0 ≤ i < lo : pred(v[i]))
+    while bound <= v.len() && pred(&v[bound - 1]) {
+        lo = bound;
+        bound *= 2;
+    }
+    // Now either we went off the end, or !pred(v[bound - 1]).
+    let hi = if bound <= v.len() { bound - 1 } else { v.len() };
+    lo + partition_point(&v[lo..hi], pred)
+}
+
+// The same thing, but galloping backwards from the end of `v`: this is
+// fast when the answer is near the end.
+pub fn gallop_back_by<T, F: FnMut(&T) -> bool>(v: &[T], mut pred: F) -> usize {
+    let length = v.len();
+    let mut hi = length;
+    let mut bound = 1;
+    // Invariant: (All i | hi ≤ i < length : !pred(v[i]))
+    while bound <= length && !pred(&v[length - bound]) {
+        hi = length - bound;
+        bound *= 2;
+    }
+    let lo = if bound <= length { length - bound + 1 } else { 0 };
+    lo + partition_point(&v[lo..hi], pred)
+}
+
+// Binary search for the first element that doesn't satisfy `pred`.
+fn partition_point<T, F: FnMut(&T) -> bool>(v: &[T], mut pred: F) -> usize {
+    let mut lo = 0;
+    let mut hi = v.len();
+    while lo < hi {
+        // Invariant: (All i | 0 ≤ i < lo : pred(v[i])) /        //            (All i | hi ≤ i < length : !pred(v[i]))
+        let mid = lo + (hi - lo) / 2;
+        if pred(&v[mid]) {
+            lo = mid + 1;
+        } else {
+            hi = mid;
+        }
+    }
+    lo
+}
+
+// Search for `target` starting from a guess, `hint`, at where it is. We
+// gallop outwards from the hint in whichever direction the target is, so
+// if the target is d places from the hint this takes O(log d)
+// comparisons. That's just the thing for a series of lookups where each
+// one is close to the last (say, walking forward through a time
+// series): pass the previous answer as the hint.
+//
+// Unlike `binary_search`, if there are several copies of target this
+// always finds the first one.
+pub fn search_from_hint<T: PartialOrd>(v: &[T], target: &T, hint: usize) -> Result<usize, usize> {
+    let hint = hint.min(v.len());
+    let less = |x: &T| x < target;
+    let index = if hint < v.len() && less(&v[hint]) {
+        hint + 1 + gallop_by(&v[hint + 1..], less)
+    } else {
+        gallop_back_by(&v[..hint], less)
+    };
+    if index < v.len() && v[index] == *target {
+        Ok(index)
+    } else {
+        Err(index)
+    }
+}
+
+// Exponential search from the front of the slice.
+pub fn exponential_search<T: PartialOrd>(v: &[T], target: &T) -> Result<usize, usize> {
+    search_from_hint(v, target, 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    // Both searches should agree with the standard library about whether
+    // the target is there, and about where it would go if it isn't.
+    // (When there are duplicates any matching index will do.)
+    fn check<F: Fn(&[i64], i64) -> Result<usize, usize>>(search: F) {
+        let mut rng = StdRng::seed_from_u64(222);
+        for _ in 0..500 {
+            let len = rng.gen_range(0, 50);
+            let mut v: Vec<i64> = (0..len).map(|_| rng.gen_range(-20, 20)).collect();
+            v.sort();
+            let target = rng.gen_range(-25, 25);
+            match (search(&v, target), v.binary_search(&target)) {
+                (Ok(i), Ok(_)) => assert_eq!(target, v[i]),
+                (Err(i), Err(j)) => assert_eq!(j, i),
+                (ours, theirs) => panic!("{:?} vs {:?} for {} in {:?}", ours, theirs, target, v),
+            }
+        }
+    }
+
+    mod binary_search {
+        use super::*;
+
+        #[test]
+        fn empty() {
+            assert_eq!(Err(0), binary_search(&[], &3));
+        }
+
+        #[test]
+        fn ten_items() {
+            let input = [0, 0, 2, 2, 3, 3, 5, 6, 8, 9];
+            assert_eq!(Ok(7), binary_search(&input, &6));
+            assert_eq!(Err(8), binary_search(&input, &7));
+            assert_eq!(Err(10), binary_search(&input, &10));
+        }
+
+        #[test]
+        fn random() {
+            check(|v, target| binary_search(v, &target));
+        }
+    }
+
+    mod interpolation_search {
+        use super::*;
+
+        #[test]
+        fn empty() {
+            assert_eq!(Err(0), interpolation_search(&[], 3));
+        }
+
+        #[test]
+        fn ten_items() {
+            let input = [0, 0, 2, 2, 3, 3, 5, 6, 8, 9];
+            assert_eq!(Ok(7), interpolation_search(&input, 6));
+            assert_eq!(Err(8), interpolation_search(&input, 7));
+            assert_eq!(Err(0), interpolation_search(&input, -1));
+            assert_eq!(Err(10), interpolation_search(&input, 10));
+        }
+
+        #[test]
+        fn all_equal() {
+            assert_eq!(Ok(0), interpolation_search(&[4, 4, 4], 4));
+            assert_eq!(Err(3), interpolation_search(&[4, 4, 4], 5));
+        }
+
+        #[test]
+        fn floats() {
+            let input = [0.5, 1.0, 2.5, 1000.0];
+            assert_eq!(Ok(2), interpolation_search(&input, 2.5));
+            assert_eq!(Err(3), interpolation_search(&input, 3.0));
+            assert!(interpolation_search(&input, f64::NAN).is_err());
+        }
+
+        #[test]
+        fn random() {
+            check(interpolation_search);
+        }
+    }
+
+    mod search_from_hint {
+        use super::*;
+
+        #[test]
+        fn empty() {
+            assert_eq!(Err(0), search_from_hint(&[], &3, 0));
+            assert_eq!(Err(0), search_from_hint(&[], &3, 5));
+        }
+
+        #[test]
+        fn ten_items() {
+            let input = [0, 0, 2, 2, 3, 3, 5, 6, 8, 9];
+            for hint in 0..12 {
+                assert_eq!(Ok(7), search_from_hint(&input, &6, hint));
+                assert_eq!(Ok(2), search_from_hint(&input, &2, hint));
+                assert_eq!(Ok(0), search_from_hint(&input, &0, hint));
+                assert_eq!(Err(8), search_from_hint(&input, &7, hint));
+                assert_eq!(Err(10), search_from_hint(&input, &10, hint));
+            }
+        }
+
+        #[test]
+        fn random() {
+            let mut rng = StdRng::seed_from_u64(223);
+            for _ in 0..500 {
+                let len = rng.gen_range(0, 50);
+                let mut v: Vec<i64> = (0..len).map(|_| rng.gen_range(-20, 20)).collect();
+                v.sort();
+                let target = rng.gen_range(-25, 25);
+                let hint = rng.gen_range(0, len + 2);
+                let first = v.iter().position(|&x| x >= target).unwrap_or(v.len());
+                let expected = if v.get(first) == Some(&target) {
+                    Ok(first)
+                } else {
+                    Err(first)
+                };
+                assert_eq!(expected, search_from_hint(&v, &target, hint));
+            }
+        }
+
+        #[test]
+        fn exponential() {
+            check(|v, target| exponential_search(v, &target));
+        }
+    }
+
+    #[test]
+    fn gallops() {
+        let input = [0, 0, 2, 2, 3, 3, 5, 6, 8, 9];
+        for target in -1..11 {
+            let expected = input.iter().position(|&x| x >= target).unwrap_or(10);
+            assert_eq!(expected, gallop_by(&input, |&x| x < target));
+            assert_eq!(expected, gallop_back_by(&input, |&x| x < target));
+        }
+    }
+}
@@ -0,0 +1,65 @@
+// Selection utilities: finding some of the "best" elements without
+// sorting everything.
+
+use crate::heap::MinHeap;
+
+// Return the k largest items, largest first. We keep a min-heap of the
+// best k items seen so far; its root is the smallest of them, so any new
+// item larger than the root should replace it. This is O(N log k) time
+// and O(k) space, which is much better than sorting everything when k
+// is small compared to N.
+pub fn top_k<T: PartialOrd, I: IntoIterator<Item = T>>(items: I, k: usize) -> Vec<T> {
+    if k == 0 {
+        return Vec::new();
+    }
+    // `k` can be bigger than the input (even `usize::MAX`, for "all of
+    // them"), so don't reserve more than the input says it has.
+    let items = items.into_iter();
+    let mut best = MinHeap::with_capacity(k.min(items.size_hint().0));
+    for item in items {
+        if best.len() < k {
+            best.push(item);
+        } else if best.peek().is_some_and(|smallest| item > *smallest) {
+            best.pop();
+            best.push(item);
+        }
+    }
+    // Popping a min-heap gives us smallest first, so reverse at the end.
+    let mut result = Vec::with_capacity(best.len());
+    while let Some(item) = best.pop() {
+        result.push(item);
+    }
+    result.reverse();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty() {
+        let input: Vec<i32> = Vec::new();
+        assert_eq!(Vec::<i32>::new(), top_k(input, 3));
+    }
+
+    #[test]
+    fn ten_items() {
+        let input = vec![3, 2, 0, 5, 8, 9, 6, 3, 2, 0];
+        assert_eq!(vec![9, 8, 6], top_k(input, 3));
+    }
+
+    #[test]
+    fn k_larger_than_input() {
+        let input = vec![3, 1, 2];
+        assert_eq!(vec![3, 2, 1], top_k(input, 10));
+    }
+
+    #[test]
+    fn k_is_everything() {
+        assert_eq!(vec![3, 2, 1], top_k(vec![3, 1, 2], usize::MAX));
+        // An iterator that doesn't know its length still gets them all.
+        let unknown = (0..5).filter(|n| n % 2 == 0);
+        assert_eq!(vec![4, 2, 0], top_k(unknown, usize::MAX));
+    }
+}
@@ -0,0 +1,197 @@
+// A soak test: run the same cells over and over for a long time (hours,
+// say) and log how their times drift. On a laptop the same sort on the
+// same input can take noticeably longer after ten minutes, once the CPU
+// has warmed up and started throttling itself, or whenever something in
+// the background wakes up. A soak run shows how big that effect is, and
+// when it happens, so you can allow for it in the real measurements
+// (or go and close that browser).
+//
+//    sorting-in-rust matrix --algorithms quicksort --sizes 100000 --soak 2h
+//
+// Every `soak_interval` we run each cell of the matrix (just the first
+// trial; the rest would only be more of the same) on exactly the same
+// input as last time, and log a line per cell with the time of day, how
+// far into the run we are, the time the sort took, and how that
+// compares with the first round. At the end we summarize each cell.
+
+use crate::benchmark::{run_cell_on, Cell, MatrixConfig};
+use std::io::{self, Write};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+// Every time each cell took, round by round, in the order of the cells.
+pub type SoakTimes = Vec<(Cell, Vec<Duration>)>;
+
+// Soak `config`'s cells for `duration`, writing the log to `out`.
+pub fn run_soak<W: Write>(
+    config: &MatrixConfig,
+    duration: Duration,
+    out: &mut W,
+) -> io::Result<SoakTimes> {
+    let cells: Vec<Cell> = config
+        .cells()
+        .into_iter()
+        .filter(|c| c.trial == 0)
+        .collect();
+    let inputs: Vec<Vec<i32>> = cells.iter().map(|c| config.generate_input(c)).collect();
+    let mut times: SoakTimes = cells.into_iter().map(|c| (c, Vec::new())).collect();
+    let start = Instant::now();
+    let mut round: u32 = 0;
+    loop {
+        for ((cell, elapsed), input) in times.iter_mut().zip(&inputs) {
+            let result = run_cell_on(config, cell, input.clone());
+            let first = *elapsed.first().unwrap_or(&result.elapsed);
+            writeln!(
+                out,
+                "{}  +{}  {} {} {}  {:?}  ({:+.1}% vs first){}",
+                format_utc(SystemTime::now()),
+                format_offset(start.elapsed()),
+                result.algorithm,
+                result.size,
+                result.describe_input(),
+                result.elapsed,
+                percent_change(first, result.elapsed),
+                if result.verified { "" } else { "  NOT SORTED" }
+            )?;
+            elapsed.push(result.elapsed);
+        }
+        out.flush()?;
+        round += 1;
+        // Start each round on schedule, however long the last one took
+        // (if it overran, the next one starts straight away).
+        let next = config.soak_interval * round;
+        if next >= duration {
+            break;
+        }
+        if let Some(wait) = next.checked_sub(start.elapsed()) {
+            thread::sleep(wait);
+        }
+    }
+
+    writeln!(out, "after {} rounds:", round)?;
+    for (cell, elapsed) in &times {
+        let mut sorted = elapsed.clone();
+        sorted.sort_unstable();
+        let (min, max) = (sorted[0], sorted[sorted.len() - 1]);
+        writeln!(
+            out,
+            "  {} {} {}: min {:?}, median {:?}, max {:?} ({:+.1}% from min to max)",
+            cell.algorithm.name,
+            cell.size,
+            cell.distribution.name(),
+            min,
+            sorted[sorted.len() / 2],
+            max,
+            percent_change(min, max)
+        )?;
+    }
+    Ok(times)
+}
+
+// How much bigger `now` is than `then`, as a percentage.
+fn percent_change(then: Duration, now: Duration) -> f64 {
+    if then == Duration::ZERO {
+        return 0.0;
+    }
+    (now.as_secs_f64() / then.as_secs_f64() - 1.0) * 100.0
+}
+
+// H:MM:SS.
+fn format_offset(offset: Duration) -> String {
+    let seconds = offset.as_secs();
+    format!(
+        "{}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+// ISO-8601 in UTC, to the second, e.g. 2022-11-03T14:05:09Z.
+pub(crate) fn format_utc(time: SystemTime) -> String {
+    let seconds = time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs()) as i64;
+    let (year, month, day) = civil_from_days(seconds.div_euclid(86_400));
+    let of_day = seconds.rem_euclid(86_400);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        of_day / 3600,
+        of_day / 60 % 60,
+        of_day % 60
+    )
+}
+
+// The (proleptic Gregorian) date that's `days` days after 1970-01-01;
+// the inverse of `days_from_civil` in `keys.rs`. See
+// http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_from_march + 2) / 5 + 1) as u32;
+    let month = if month_from_march < 10 {
+        month_from_march + 3
+    } else {
+        month_from_march - 9
+    } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::benchmark::find_algorithm;
+
+    #[test]
+    fn dates() {
+        assert_eq!((1970, 1, 1), civil_from_days(0));
+        assert_eq!((2000, 2, 29), civil_from_days(11_016));
+        assert_eq!((1969, 12, 31), civil_from_days(-1));
+        let time = UNIX_EPOCH + Duration::from_secs(1_667_484_309);
+        assert_eq!("2022-11-03T14:05:09Z", format_utc(time));
+        assert_eq!("1:02:03", format_offset(Duration::from_secs(3723)));
+    }
+
+    #[test]
+    fn changes() {
+        let ms = Duration::from_millis;
+        assert!((percent_change(ms(100), ms(110)) - 10.0).abs() < 1e-9);
+        assert!((percent_change(ms(100), ms(75)) + 25.0).abs() < 1e-9);
+        assert_eq!(0.0, percent_change(Duration::ZERO, ms(5)));
+    }
+
+    #[test]
+    fn soak() {
+        let config = MatrixConfig {
+            algorithms: vec![
+                find_algorithm("quicksort").unwrap(),
+                find_algorithm("merge").unwrap(),
+            ],
+            sizes: vec![100],
+            trials: 5,
+            soak_interval: Duration::from_millis(20),
+            ..MatrixConfig::default()
+        };
+        let mut log = Vec::new();
+        let times = run_soak(&config, Duration::from_millis(60), &mut log).unwrap();
+        // Rounds at 0, 20, and 40ms, and just the first trial.
+        assert_eq!(2, times.len());
+        assert!(times.iter().all(|(_, elapsed)| elapsed.len() == 3));
+
+        let log = String::from_utf8(log).unwrap();
+        let lines: Vec<&str> = log.lines().collect();
+        assert_eq!(3 * 2 + 3, lines.len());
+        assert!(lines[0].contains("+0:00:00  quicksort 100 uniform  "));
+        assert!(lines[0].ends_with("(+0.0% vs first)"));
+        assert!(lines[1].contains("  merge 100 uniform  "));
+        assert_eq!("after 3 rounds:", lines[6]);
+        assert!(lines[7].starts_with("  quicksort 100 uniform: min "));
+    }
+}
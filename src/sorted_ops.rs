@@ -0,0 +1,165 @@
+// Set operations on sorted slices. Since both inputs are sorted, we can
+// walk through them together just like `merge` does, and produce a
+// sorted result in O(N + M) time.
+//
+// Duplicates are treated as in a multiset: if x appears a times in xs
+// and b times in ys, then it appears
+//    max(a, b) times in the union,
+//    min(a, b) times in the intersection,
+//    a - b times in the difference (or not at all if b ≥ a), and
+//    |a - b| times in the symmetric difference.
+// When neither input has duplicates these are the usual set operations.
+
+use std::cmp::Ordering;
+
+// Which of the inputs an element was found in.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Side {
+    OnlyXs,
+    Both,
+    OnlyYs,
+}
+
+// Walk through xs and ys together, calling `visit` for each element
+// that's only in xs (or is one of the extra copies in xs), each pair of
+// matched equal elements, and each element that's only in ys. The
+// elements are visited in sorted order, so pushing some of them onto a
+// vector gives a sorted result.
+fn walk<T: PartialOrd, F: FnMut(Side, &T)>(xs: &[T], ys: &[T], mut visit: F) {
+    let mut i = 0;
+    let mut j = 0;
+    while i < xs.len() && j < ys.len() {
+        match xs[i].partial_cmp(&ys[j]) {
+            Some(Ordering::Equal) => {
+                visit(Side::Both, &xs[i]);
+                i += 1;
+                j += 1;
+            }
+            Some(Ordering::Greater) => {
+                visit(Side::OnlyYs, &ys[j]);
+                j += 1;
+            }
+            // Incomparable elements (like NaN) can't be matched with
+            // anything, so we treat them as unique to their side.
+            Some(Ordering::Less) | None => {
+                visit(Side::OnlyXs, &xs[i]);
+                i += 1;
+            }
+        }
+    }
+    xs[i..].iter().for_each(|x| visit(Side::OnlyXs, x));
+    ys[j..].iter().for_each(|y| visit(Side::OnlyYs, y));
+}
+
+// Everything visited on one of the given sides.
+fn collect<T: PartialOrd + Clone>(xs: &[T], ys: &[T], sides: &[Side]) -> Vec<T> {
+    let mut result = Vec::new();
+    walk(xs, ys, |side, x| {
+        if sides.contains(&side) {
+            result.push(x.clone());
+        }
+    });
+    result
+}
+
+pub fn union<T: PartialOrd + Clone>(xs: &[T], ys: &[T]) -> Vec<T> {
+    collect(xs, ys, &[Side::OnlyXs, Side::Both, Side::OnlyYs])
+}
+
+pub fn intersect<T: PartialOrd + Clone>(xs: &[T], ys: &[T]) -> Vec<T> {
+    collect(xs, ys, &[Side::Both])
+}
+
+pub fn difference<T: PartialOrd + Clone>(xs: &[T], ys: &[T]) -> Vec<T> {
+    collect(xs, ys, &[Side::OnlyXs])
+}
+
+pub fn symmetric_difference<T: PartialOrd + Clone>(xs: &[T], ys: &[T]) -> Vec<T> {
+    collect(xs, ys, &[Side::OnlyXs, Side::OnlyYs])
+}
+
+// The operations by name, for the `set-op` subcommand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetOperation {
+    Union,
+    Intersect,
+    Difference,
+    SymmetricDifference,
+}
+
+impl SetOperation {
+    pub fn from_name(name: &str) -> Option<SetOperation> {
+        match name {
+            "union" => Some(SetOperation::Union),
+            "intersect" => Some(SetOperation::Intersect),
+            "difference" => Some(SetOperation::Difference),
+            "symmetric-difference" => Some(SetOperation::SymmetricDifference),
+            _ => None,
+        }
+    }
+
+    pub fn apply<T: PartialOrd + Clone>(self, xs: &[T], ys: &[T]) -> Vec<T> {
+        match self {
+            SetOperation::Union => union(xs, ys),
+            SetOperation::Intersect => intersect(xs, ys),
+            SetOperation::Difference => difference(xs, ys),
+            SetOperation::SymmetricDifference => symmetric_difference(xs, ys),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    const XS: [i32; 7] = [0, 2, 2, 3, 5, 8, 9];
+    const YS: [i32; 6] = [0, 2, 4, 5, 5, 9];
+
+    #[test]
+    fn empty() {
+        let empty: [i32; 0] = [];
+        assert_eq!(XS.to_vec(), union(&XS, &empty));
+        assert_eq!(Vec::<i32>::new(), intersect(&XS, &empty));
+        assert_eq!(XS.to_vec(), difference(&XS, &empty));
+        assert_eq!(YS.to_vec(), symmetric_difference(&empty, &YS));
+    }
+
+    #[test]
+    fn with_duplicates() {
+        assert_eq!(vec![0, 2, 2, 3, 4, 5, 5, 8, 9], union(&XS, &YS));
+        assert_eq!(vec![0, 2, 5, 9], intersect(&XS, &YS));
+        assert_eq!(vec![2, 3, 8], difference(&XS, &YS));
+        assert_eq!(vec![4, 5], difference(&YS, &XS));
+        assert_eq!(vec![2, 3, 4, 5, 8], symmetric_difference(&XS, &YS));
+    }
+
+    // Check the multiset counts against a brute-force count of each value.
+    #[test]
+    fn random() {
+        let mut rng = StdRng::seed_from_u64(224);
+        for _ in 0..200 {
+            let mut xs: Vec<i32> = (0..rng.gen_range(0, 30)).map(|_| rng.gen_range(0, 10)).collect();
+            let mut ys: Vec<i32> = (0..rng.gen_range(0, 30)).map(|_| rng.gen_range(0, 10)).collect();
+            xs.sort();
+            ys.sort();
+            let results = [
+                union(&xs, &ys),
+                intersect(&xs, &ys),
+                difference(&xs, &ys),
+                symmetric_difference(&xs, &ys),
+            ];
+            for result in results.iter() {
+                assert!(result.windows(2).all(|w| w[0] <= w[1]));
+            }
+            let count = |v: &[i32], x: i32| v.iter().filter(|&&y| y == x).count();
+            for x in 0..10 {
+                let (a, b) = (count(&xs, x), count(&ys, x));
+                assert_eq!(a.max(b), count(&results[0], x));
+                assert_eq!(a.min(b), count(&results[1], x));
+                assert_eq!(a.saturating_sub(b), count(&results[2], x));
+                assert_eq!(a.max(b) - a.min(b), count(&results[3], x));
+            }
+        }
+    }
+}
@@ -0,0 +1,126 @@
+// A `Vec` that's always kept sorted.
+
+use crate::search::gallop_back_by;
+use crate::sorts::{merge_sort, resort_tail};
+use std::cmp::Ordering;
+
+#[derive(Debug, Clone)]
+pub struct SortedVec<T> {
+    data: Vec<T>,
+}
+
+impl<T: PartialOrd + Copy + std::fmt::Debug> SortedVec<T> {
+    pub fn new() -> SortedVec<T> {
+        SortedVec { data: Vec::new() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn as_slice(&self) -> &[T] {
+        &self.data
+    }
+
+    pub fn into_vec(self) -> Vec<T> {
+        self.data
+    }
+
+    // Insert a single item after any items equal to it. Finding the spot
+    // is O(log N), but making room for it is O(N), so inserting lots of
+    // items this way one at a time is O(N^2); use `append_batch` instead.
+    pub fn insert(&mut self, item: T) {
+        // New items are often large, so gallop back from the end to find
+        // the first item that's bigger than the new one.
+        let index = gallop_back_by(&self.data, |x| item.partial_cmp(x) != Some(Ordering::Less));
+        self.data.insert(index, item);
+    }
+
+    // Add a whole batch of items at once. We put them on the end and let
+    // `resort_tail` sort them and merge them into place, which costs
+    // O(B log B) to sort a batch of B items plus one O(N) merge, rather
+    // than O(N) per item. Items already in the vector come before equal
+    // items from the batch.
+    pub fn append_batch(&mut self, batch: Vec<T>) {
+        self.data.extend(batch);
+        resort_tail(&mut self.data);
+    }
+}
+
+impl<T: PartialOrd + Copy + std::fmt::Debug> Default for SortedVec<T> {
+    fn default() -> SortedVec<T> {
+        SortedVec::new()
+    }
+}
+
+impl<T: PartialOrd + Copy + std::fmt::Debug> From<Vec<T>> for SortedVec<T> {
+    fn from(data: Vec<T>) -> SortedVec<T> {
+        SortedVec {
+            data: merge_sort(&data),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    #[test]
+    fn empty() {
+        let v: SortedVec<i32> = SortedVec::new();
+        assert!(v.is_empty());
+        assert_eq!(Vec::<i32>::new(), v.into_vec());
+    }
+
+    #[test]
+    fn ten_items() {
+        let v = SortedVec::from(vec![3, 2, 0, 5, 8, 9, 6, 3, 2, 0]);
+        assert_eq!([0, 0, 2, 2, 3, 3, 5, 6, 8, 9], v.as_slice());
+    }
+
+    #[test]
+    fn insert() {
+        let mut v = SortedVec::new();
+        for &x in &[3, 2, 0, 5, 8, 9, 6, 3, 2, 0] {
+            v.insert(x);
+        }
+        assert_eq!([0, 0, 2, 2, 3, 3, 5, 6, 8, 9], v.as_slice());
+    }
+
+    #[test]
+    fn batches() {
+        let mut rng = StdRng::seed_from_u64(229);
+        let mut v = SortedVec::new();
+        let mut expected = Vec::new();
+        for _ in 0..50 {
+            let batch: Vec<i32> = (0..rng.gen_range(0, 20)).map(|_| rng.gen_range(0, 100)).collect();
+            expected.extend_from_slice(&batch);
+            v.append_batch(batch);
+        }
+        expected.sort();
+        assert_eq!(expected, v.into_vec());
+    }
+
+    #[test]
+    fn stable() {
+        // Compare on the first field only; the old item has to stay in
+        // front of the new equal ones.
+        #[derive(Debug, Clone, Copy, PartialEq)]
+        struct Tagged(i32, char);
+        impl PartialOrd for Tagged {
+            fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+                self.0.partial_cmp(&other.0)
+            }
+        }
+        let mut v = SortedVec::from(vec![Tagged(1, 'a'), Tagged(2, 'a')]);
+        v.append_batch(vec![Tagged(2, 'b'), Tagged(1, 'b')]);
+        v.insert(Tagged(1, 'c'));
+        let tags: String = v.as_slice().iter().map(|t| t.1).collect();
+        assert_eq!("abcab", tags);
+    }
+}
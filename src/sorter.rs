@@ -0,0 +1,170 @@
+// The algorithms as values: each one is a type that implements `Sorter`,
+// and `sorters` (or `all_sorters`) is the list of them. The benchmark
+// (through `benchmark::algorithms`) and everything that runs an
+// algorithm by name (through `benchmark::sort_generic`) get their
+// algorithms from here, so adding an algorithm means a `sorter!` line
+// below and an entry in `sorters`, and nothing more.
+//
+// The trait is generic in the type being sorted, rather than having a
+// generic `sort` method, because a trait object's methods can't be
+// generic. That way a `Box<dyn Sorter<i32>>` sorts plain `i32`s for the
+// timings, and a `Box<dyn Sorter<Counted<i32>>>` sorts the wrappers from
+// `instrument.rs` for the comparison counts.
+
+use crate::random::SeededRandom;
+use crate::sorts::{
+    bitonic_sort, dual_pivot_quicksort, heapsort, insertion_sort, introsort, merge_sort,
+    parallel_merge_sort, quicksort, quicksort_3way, randomized_quicksort, selection_sort,
+    shell_sort, weak_heapsort, GapSequence, ParallelConfig,
+};
+use std::fmt::Debug;
+
+pub trait Sorter<T>: Send + Sync {
+    // The name the CLI, the REPL, and the reports know it by.
+    fn name(&self) -> &'static str;
+
+    // Whether equal elements always stay in their original order.
+    fn stable(&self) -> bool;
+
+    fn sort(&self, v: &mut [T]);
+}
+
+// A unit struct for an algorithm, and its `Sorter` impl for any `T` the
+// algorithm can sort.
+macro_rules! sorter {
+    ($sorter:ident, $name:expr, stable: $stable:expr, |$v:ident| $sort:expr) => {
+        #[derive(Debug, Clone, Copy)]
+        pub struct $sorter;
+
+        impl<T: PartialOrd + Copy + Debug> Sorter<T> for $sorter {
+            fn name(&self) -> &'static str {
+                $name
+            }
+
+            fn stable(&self) -> bool {
+                $stable
+            }
+
+            fn sort(&self, $v: &mut [T]) {
+                $sort
+            }
+        }
+    };
+}
+
+sorter!(InsertionSort, "insertion", stable: true, |v| insertion_sort(v));
+sorter!(SelectionSort, "selection", stable: false, |v| selection_sort(v));
+sorter!(Quicksort, "quicksort", stable: false, |v| quicksort(v));
+// The pivots don't need to be reproducible, just random.
+sorter!(RandomizedQuicksort, "randomized-quicksort", stable: false, |v| {
+    randomized_quicksort(v, &mut SeededRandom::from_entropy())
+});
+sorter!(Introsort, "introsort", stable: false, |v| introsort(v));
+sorter!(DualPivotQuicksort, "dual-pivot-quicksort", stable: false, |v| {
+    dual_pivot_quicksort(v)
+});
+sorter!(ThreeWayQuicksort, "three-way-quicksort", stable: false, |v| {
+    quicksort_3way(v)
+});
+sorter!(MergeSort, "merge", stable: true, |v| {
+    let sorted = merge_sort(v);
+    v.copy_from_slice(&sorted);
+});
+sorter!(BitonicSort, "bitonic", stable: false, |v| bitonic_sort(v));
+sorter!(Heapsort, "heapsort", stable: false, |v| heapsort(v));
+sorter!(WeakHeapsort, "weak-heapsort", stable: false, |v| weak_heapsort(v));
+sorter!(ShellSort, "shell", stable: false, |v| shell_sort(v, GapSequence::Ciura));
+
+// Parallel merge sort can only sort values that can be shared between
+// threads, so it gets an impl of its own.
+#[derive(Debug, Clone, Copy)]
+pub struct ParallelMergeSort;
+
+impl<T: PartialOrd + Copy + Debug + Send + Sync> Sorter<T> for ParallelMergeSort {
+    fn name(&self) -> &'static str {
+        "parallel-merge"
+    }
+
+    fn stable(&self) -> bool {
+        true
+    }
+
+    // The matrix runs this with its own `parallel` settings (see
+    // `benchmark::run_cell_on`); anywhere else it picks up the
+    // `SORTLAB_*` overrides, or the defaults if they're invalid (the
+    // binaries check them up front).
+    fn sort(&self, v: &mut [T]) {
+        let sorted = parallel_merge_sort(v, &ParallelConfig::from_env().unwrap_or_default());
+        v.copy_from_slice(&sorted);
+    }
+}
+
+// Every algorithm that can sort any `T`, in the order the benchmark runs
+// them: all but parallel-merge. (Its comparisons happen on other threads,
+// so it couldn't sort the instrumented wrappers usefully anyway; they
+// count comparisons per thread.)
+pub fn sorters<T: PartialOrd + Copy + Debug>() -> Vec<Box<dyn Sorter<T>>> {
+    vec![
+        Box::new(InsertionSort),
+        Box::new(SelectionSort),
+        Box::new(Quicksort),
+        Box::new(RandomizedQuicksort),
+        Box::new(Introsort),
+        Box::new(DualPivotQuicksort),
+        Box::new(ThreeWayQuicksort),
+        Box::new(MergeSort),
+        Box::new(BitonicSort),
+        Box::new(Heapsort),
+        Box::new(WeakHeapsort),
+        Box::new(ShellSort),
+    ]
+}
+
+// Every algorithm, for values that can be shared between threads:
+// `sorters`, with parallel-merge right after merge.
+pub fn all_sorters<T: PartialOrd + Copy + Debug + Send + Sync>() -> Vec<Box<dyn Sorter<T>>> {
+    let mut all = sorters();
+    let merge = all
+        .iter()
+        .position(|sorter| sorter.name() == "merge")
+        .map_or(all.len(), |i| i + 1);
+    all.insert(merge, Box::new(ParallelMergeSort));
+    all
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instrument::{count_comparisons, counted};
+
+    #[test]
+    fn names_are_unique() {
+        let mut names: Vec<&str> = all_sorters::<i32>().iter().map(|s| s.name()).collect();
+        let count = names.len();
+        names.sort_unstable();
+        names.dedup();
+        assert_eq!(count, names.len());
+        assert_eq!(sorters::<i32>().len() + 1, count);
+    }
+
+    #[test]
+    fn every_sorter_sorts_through_a_trait_object() {
+        for sorter in all_sorters::<i32>() {
+            let mut v = vec![3, 2, 0, 5, 8, 9, 6, 3, 2, 0];
+            sorter.sort(&mut v);
+            assert_eq!(vec![0, 0, 2, 2, 3, 3, 5, 6, 8, 9], v, "{}", sorter.name());
+            // Each one is a unit struct, so boxing it doesn't allocate.
+            assert_eq!(0, std::mem::size_of_val(&*sorter), "{}", sorter.name());
+        }
+    }
+
+    #[test]
+    fn generic_sorters_sort_instrumented_values() {
+        for sorter in sorters() {
+            let mut v = counted(&[3, 2, 1]);
+            let ((), comparisons) = count_comparisons(|| sorter.sort(&mut v));
+            assert!(comparisons > 0, "{}", sorter.name());
+            assert!(v.windows(2).all(|w| w[0].0 <= w[1].0), "{}", sorter.name());
+        }
+    }
+}
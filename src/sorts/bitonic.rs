@@ -0,0 +1,125 @@
+// Bitonic sort is a _sorting network_: a fixed sequence of
+// compare-and-swap steps that doesn't depend on the data at all. That
+// makes it a poor choice on a CPU (it's O(N log² N)), but it's the
+// classic way to sort on a GPU, where thousands of threads can each do
+// one compare-and-swap of a pass at the same time.
+//
+// The network is organised as passes, indexed by (k, j): k is the size
+// of the bitonic sequences being merged (2, 4, 8, ..., N) and j is the
+// distance between the elements being compared (k/2, k/4, ..., 1). In
+// each pass, element i is compared with element i ^ j, and the pair is
+// put in ascending order if i & k == 0 and descending order otherwise.
+// Every comparison within a pass is independent, so on a GPU each pass
+// is a single compute dispatch with one thread per pair. Here we just
+// run the passes one after another, which gives exactly the same result
+// and makes a good reference to check the GPU version (`gpu.rs`, with
+// the `gpu` feature) against.
+//
+// The network needs a power-of-two length, so we sort a padded copy:
+// `None` sorts after every `Some`, so padding with `None`s and dropping
+// them afterwards works for any length.
+
+pub fn bitonic_sort<T: PartialOrd + Copy + std::fmt::Debug>(v: &mut [T]) {
+    let length = v.len();
+    if length < 2 {
+        return;
+    }
+    let size = length.next_power_of_two();
+    let mut padded: Vec<Option<T>> = v.iter().map(|&x| Some(x)).collect();
+    padded.resize(size, None);
+    for (k, j) in bitonic_passes(size) {
+        bitonic_pass(&mut padded, k, j);
+    }
+    for (slot, x) in v.iter_mut().zip(padded) {
+        // The first `length` slots are all `Some`, since the `None`s
+        // sort last.
+        *slot = x.expect("padding should sort to the end");
+    }
+}
+
+// The (k, j) pairs for every pass of the network on `size` elements, in
+// order. `size` has to be a power of two.
+pub fn bitonic_passes(size: usize) -> Vec<(usize, usize)> {
+    assert!(size.is_power_of_two(), "bitonic sort needs a power-of-two size");
+    let mut passes = Vec::new();
+    let mut k = 2;
+    while k <= size {
+        let mut j = k / 2;
+        while j > 0 {
+            passes.push((k, j));
+            j /= 2;
+        }
+        k *= 2;
+    }
+    passes
+}
+
+// One pass of the network. `None` counts as larger than everything.
+fn bitonic_pass<T: PartialOrd>(v: &mut [Option<T>], k: usize, j: usize) {
+    for i in 0..v.len() {
+        let partner = i ^ j;
+        // Each pair is handled once, by its lower index.
+        if partner <= i {
+            continue;
+        }
+        let ascending = i & k == 0;
+        if out_of_order(&v[i], &v[partner]) == ascending {
+            v.swap(i, partner);
+        }
+    }
+}
+
+// Whether a should come after b.
+fn out_of_order<T: PartialOrd>(a: &Option<T>, b: &Option<T>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => a > b,
+        (None, Some(_)) => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    #[test]
+    fn empty() {
+        let mut input: [i32; 0] = [];
+        bitonic_sort(&mut input);
+        assert_eq!(input, []);
+    }
+
+    #[test]
+    fn ten_items() {
+        let mut input = [3, 2, 0, 5, 8, 9, 6, 3, 2, 0];
+        bitonic_sort(&mut input);
+        assert_eq!(input, [0, 0, 2, 2, 3, 3, 5, 6, 8, 9]);
+    }
+
+    #[test]
+    fn presorted() {
+        let mut input = [0, 0, 2, 2, 3, 3, 5, 6, 8, 9];
+        bitonic_sort(&mut input);
+        assert_eq!(input, [0, 0, 2, 2, 3, 3, 5, 6, 8, 9]);
+    }
+
+    #[test]
+    fn pass_count() {
+        // log₂ N (log₂ N + 1) / 2 passes.
+        assert_eq!(1, bitonic_passes(2).len());
+        assert_eq!(10, bitonic_passes(16).len());
+    }
+
+    #[test]
+    fn random_lengths() {
+        let mut rng = StdRng::seed_from_u64(234);
+        for len in 0..70 {
+            let mut v: Vec<u32> = (0..len).map(|_| rng.gen_range(0, 100)).collect();
+            let mut expected = v.clone();
+            expected.sort();
+            bitonic_sort(&mut v);
+            assert_eq!(expected, v);
+        }
+    }
+}
@@ -0,0 +1,130 @@
+// Bucket sort, for floats spread evenly over [0, 1). With N elements we
+// make N buckets, each covering 1/N of the range, and drop each element
+// into its own: x goes in bucket floor(x * N). Every element of bucket i
+// is smaller than every element of bucket i + 1, so once each bucket is
+// sorted we just read them out in order.
+//
+// If the values really are uniform, each bucket gets about one element,
+// and the chance of any bucket getting many is tiny, so the insertion
+// sorts take O(1) time per bucket on average and the whole thing is
+// expected O(N), with no comparisons between elements in different
+// buckets at all. (With k elements in a bucket insertion sort takes
+// O(k²), and the expected sum of k² over the buckets works out to less
+// than 2N.) If they aren't uniform, say they're all in [0, 0.001), they
+// all land in the same few buckets and we're back to insertion sort's
+// O(N²).
+//
+// Floats are only `PartialOrd`, because NaN isn't less than, greater
+// than, or equal to anything, so there's no right place to put it. We
+// refuse NaN, along with anything else outside [0, 1), by panicking.
+use super::insertion_sort;
+
+pub fn bucket_sort(v: &mut [f64]) {
+    let n = v.len();
+    // x * n can round up to n when x is just below 1.
+    let bucket = |x: f64| ((x * n as f64) as usize).min(n - 1);
+    // A `Vec` for each bucket would mean a million allocations for a
+    // million floats, which costs more than the sorting. Instead we lay
+    // the buckets out one after another in a single buffer, the way
+    // counting sort does: count how many go in each, which tells us
+    // where each one starts.
+    let mut starts = vec![0usize; n + 1];
+    for &x in v.iter() {
+        // NaN isn't in any range, so this catches it too.
+        assert!((0.0..1.0).contains(&x), "{} is outside [0, 1)", x);
+        starts[bucket(x) + 1] += 1;
+    }
+    for i in 0..n {
+        starts[i + 1] += starts[i];
+    }
+    // Bucket i is sorted[starts[i]..starts[i + 1]].
+    let mut sorted = vec![0.0; n];
+    let mut next = starts.clone();
+    for &x in v.iter() {
+        let i = bucket(x);
+        sorted[next[i]] = x;
+        next[i] += 1;
+    }
+    for bounds in starts.windows(2) {
+        insertion_sort(&mut sorted[bounds[0]..bounds[1]]);
+    }
+    v.copy_from_slice(&sorted);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::generate_random;
+    use crate::random::SeededRandom;
+
+    #[test]
+    fn empty() {
+        let mut input: [f64; 0] = [];
+        bucket_sort(&mut input);
+        let expected: [f64; 0] = [];
+
+        assert_eq!(expected, input);
+    }
+
+    #[test]
+    fn ten_items() {
+        let mut input = [0.3, 0.2, 0.0, 0.5, 0.8, 0.9, 0.6, 0.3, 0.2, 0.0];
+        bucket_sort(&mut input);
+        let expected = [0.0, 0.0, 0.2, 0.2, 0.3, 0.3, 0.5, 0.6, 0.8, 0.9];
+
+        assert_eq!(expected, input);
+    }
+
+    #[test]
+    fn presorted() {
+        let mut input = [0.0, 0.0, 0.2, 0.2, 0.3, 0.3, 0.5, 0.6, 0.8, 0.9];
+        bucket_sort(&mut input);
+        let expected = [0.0, 0.0, 0.2, 0.2, 0.3, 0.3, 0.5, 0.6, 0.8, 0.9];
+
+        assert_eq!(expected, input);
+    }
+
+    #[test]
+    fn ends_of_the_range() {
+        // The largest f64 below 1, which rounds up to bucket 3 of 3.
+        let almost_one = 1.0 - f64::EPSILON / 2.0;
+        let mut input = [almost_one, 0.5, -0.0];
+        bucket_sort(&mut input);
+        assert_eq!([0.0, 0.5, almost_one], input);
+    }
+
+    #[test]
+    fn uniform() {
+        let mut rng = SeededRandom::new(514);
+        let mut v: Vec<f64> = generate_random(1_000_000, &mut rng);
+        let mut expected = v.clone();
+        expected.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        bucket_sort(&mut v);
+        assert_eq!(expected, v);
+    }
+
+    #[test]
+    fn bunched_up() {
+        // Still sorted, just slowly: everything lands in the first tenth
+        // of the buckets.
+        let mut rng = SeededRandom::new(514);
+        let mut v: Vec<f64> = generate_random::<f64, _>(2000, &mut rng)
+            .into_iter()
+            .map(|x| x / 10.0)
+            .collect();
+        bucket_sort(&mut v);
+        assert!(v.windows(2).all(|w| w[0] <= w[1]));
+    }
+
+    #[test]
+    #[should_panic(expected = "outside [0, 1)")]
+    fn one_is_out_of_range() {
+        bucket_sort(&mut [0.5, 1.0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "NaN is outside [0, 1)")]
+    fn nan_is_out_of_range() {
+        bucket_sort(&mut [0.5, f64::NAN]);
+    }
+}
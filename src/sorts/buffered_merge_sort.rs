@@ -0,0 +1,67 @@
+// Merge sort that sorts in place and works on any `PartialOrd` values,
+// not just `Copy` ones: each merge moves the left run into a
+// `ScratchBuffer` (see `scratch.rs`) and merges it back. So it can sort
+// a `Vec<String>` without cloning a single string, with one buffer of
+// n/2 elements for the whole sort.
+//
+// Like `merge_sort` it's stable, and it skips the merge when the two
+// halves are already in order.
+
+use crate::scratch::ScratchBuffer;
+
+pub fn buffered_merge_sort<T: PartialOrd>(v: &mut [T]) {
+    buffered_merge_sort_by(v, |a, b| a < b);
+}
+
+// Sort using `is_less(a, b)` in place of `a < b`.
+pub fn buffered_merge_sort_by<T, F: FnMut(&T, &T) -> bool>(v: &mut [T], mut is_less: F) {
+    let mut buffer = ScratchBuffer::with_capacity(v.len() / 2);
+    sort(v, &mut buffer, &mut is_less);
+}
+
+fn sort<T, F: FnMut(&T, &T) -> bool>(v: &mut [T], buffer: &mut ScratchBuffer<T>, is_less: &mut F) {
+    let len = v.len();
+    if len < 2 {
+        return;
+    }
+    let middle = len / 2;
+    sort(&mut v[..middle], buffer, is_less);
+    sort(&mut v[middle..], buffer, is_less);
+    if is_less(&v[middle], &v[middle - 1]) {
+        buffer.merge(v, middle, is_less);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    #[test]
+    fn ten_items() {
+        let mut v = [3, 2, 0, 5, 8, 9, 6, 3, 2, 0];
+        buffered_merge_sort(&mut v);
+        assert_eq!([0, 0, 2, 2, 3, 3, 5, 6, 8, 9], v);
+    }
+
+    #[test]
+    fn strings() {
+        let mut rng = StdRng::seed_from_u64(262);
+        let mut v: Vec<String> = (0..200).map(|_| rng.gen_range(0, 50).to_string()).collect();
+        let mut expected = v.clone();
+        expected.sort();
+        buffered_merge_sort(&mut v);
+        assert_eq!(expected, v);
+    }
+
+    #[test]
+    fn stable() {
+        let mut rng = StdRng::seed_from_u64(262);
+        let input: Vec<(u8, usize)> = (0..300).map(|i| (rng.gen_range(0, 8), i)).collect();
+        let mut v = input.clone();
+        buffered_merge_sort_by(&mut v, |a, b| a.0 < b.0);
+        let mut expected = input;
+        expected.sort_by_key(|pair| pair.0);
+        assert_eq!(expected, v);
+    }
+}
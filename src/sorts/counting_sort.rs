@@ -0,0 +1,164 @@
+// Counting sort for small integer types. When every possible key fits
+// in a small table we don't need to compare anything: count how many
+// times each value appears, then write the values back out in order.
+// That's O(N + K), where K is the number of possible values (256 for
+// `u8`, 65,536 for `u16`), so for anything but tiny inputs it beats any
+// comparison sort.
+
+pub fn counting_sort_u8(v: &mut [u8]) {
+    let mut counts = [0usize; 1 << 8];
+    for &x in v.iter() {
+        counts[x as usize] += 1;
+    }
+    write_counts(v, &counts, |value| value as u8);
+}
+
+// With 65,536 buckets just clearing and scanning the table is a fair
+// amount of work, so this only pays off on larger inputs; `auto_sort`
+// only uses it when there are at least `COUNTING_SORT_U16_THRESHOLD`
+// elements.
+pub fn counting_sort_u16(v: &mut [u16]) {
+    let mut counts = vec![0usize; 1 << 16];
+    for &x in v.iter() {
+        counts[x as usize] += 1;
+    }
+    write_counts(v, &counts, |value| value as u16);
+}
+
+pub const COUNTING_SORT_U16_THRESHOLD: usize = 1 << 12;
+
+// For `i32`s we can't have a count for every possible value, but often
+// we know they're all in min..max (`generate_random_array` promises
+// that, for one), and then K = max - min counts are enough. Panics if a
+// value is outside the range.
+pub fn counting_sort(v: &mut [i32], min: i32, max: i32) {
+    assert!(min <= max, "the range {}..{} is backwards", min, max);
+    // In `i64`s, since max - min can be too big for an `i32`.
+    let mut counts = vec![0usize; (max as i64 - min as i64) as usize];
+    for &x in v.iter() {
+        assert!(
+            (min..max).contains(&x),
+            "{} is outside the range {}..{}",
+            x,
+            min,
+            max
+        );
+        counts[(x as i64 - min as i64) as usize] += 1;
+    }
+    write_counts(v, &counts, |offset| (min as i64 + offset as i64) as i32);
+}
+
+// Write counts[value] copies of each value into v, in order.
+fn write_counts<T: Copy, F: Fn(usize) -> T>(v: &mut [T], counts: &[usize], from_index: F) {
+    let mut start = 0;
+    for (value, &count) in counts.iter().enumerate() {
+        // Invariant: v[0..start] holds, in order, every element less
+        // than `value`.
+        v[start..start + count].fill(from_index(value));
+        start += count;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    mod counting_sort_u8 {
+        use super::*;
+
+        #[test]
+        fn empty() {
+            let mut input: [u8; 0] = [];
+            counting_sort_u8(&mut input);
+            assert_eq!(input, []);
+        }
+
+        #[test]
+        fn ten_items() {
+            let mut input = [3, 2, 0, 5, 8, 9, 6, 3, 2, 0];
+            counting_sort_u8(&mut input);
+            assert_eq!(input, [0, 0, 2, 2, 3, 3, 5, 6, 8, 9]);
+        }
+
+        #[test]
+        fn extremes() {
+            let mut input = [255, 0, 255, 1, 0];
+            counting_sort_u8(&mut input);
+            assert_eq!(input, [0, 0, 1, 255, 255]);
+        }
+    }
+
+    mod counting_sort {
+        use super::*;
+        use crate::generate::generate_random_array;
+        use crate::random::SeededRandom;
+
+        #[test]
+        fn empty() {
+            let mut input: [i32; 0] = [];
+            counting_sort(&mut input, 0, 0);
+            assert_eq!(input, []);
+        }
+
+        #[test]
+        fn ten_items() {
+            let mut input = [3, 2, 0, 5, 8, 9, 6, 3, 2, 0];
+            counting_sort(&mut input, 0, 10);
+            assert_eq!(input, [0, 0, 2, 2, 3, 3, 5, 6, 8, 9]);
+        }
+
+        #[test]
+        fn negative_and_extreme_values() {
+            let mut input = [5, -3, 0, -3, 4];
+            counting_sort(&mut input, -3, 6);
+            assert_eq!(input, [-3, -3, 0, 4, 5]);
+            let mut input = [i32::MAX - 1, i32::MAX - 2, i32::MAX - 1];
+            counting_sort(&mut input, i32::MAX - 2, i32::MAX);
+            assert_eq!(input, [i32::MAX - 2, i32::MAX - 1, i32::MAX - 1]);
+        }
+
+        #[test]
+        fn random() {
+            let mut v = generate_random_array(10_000, -500, 500, &mut SeededRandom::new(511));
+            let mut expected = v.clone();
+            expected.sort();
+            counting_sort(&mut v, -500, 500);
+            assert_eq!(expected, v);
+        }
+
+        #[test]
+        #[should_panic(expected = "10 is outside the range 0..10")]
+        fn out_of_range() {
+            counting_sort(&mut [3, 10, 2], 0, 10);
+        }
+    }
+
+    mod counting_sort_u16 {
+        use super::*;
+
+        #[test]
+        fn empty() {
+            let mut input: [u16; 0] = [];
+            counting_sort_u16(&mut input);
+            assert_eq!(input, []);
+        }
+
+        #[test]
+        fn ten_items() {
+            let mut input = [3, 2, 0, 5, 8, 9, 6, 3, 2, 0];
+            counting_sort_u16(&mut input);
+            assert_eq!(input, [0, 0, 2, 2, 3, 3, 5, 6, 8, 9]);
+        }
+
+        #[test]
+        fn random() {
+            let mut rng = StdRng::seed_from_u64(220);
+            let mut v: Vec<u16> = (0..10_000).map(|_| rng.gen()).collect();
+            let mut expected = v.clone();
+            expected.sort();
+            counting_sort_u16(&mut v);
+            assert_eq!(expected, v);
+        }
+    }
+}
@@ -0,0 +1,116 @@
+// Sorting when there are only a few distinct values. Booleans, enums
+// with a handful of variants, and "status" fields are all like this, and
+// we can sort them with a few linear passes instead of O(N log N)
+// comparisons.
+
+use crate::partition::partition_by;
+
+// There are only two booleans, so we just count the `false`s.
+pub fn sort_bools(v: &mut [bool]) {
+    let falses = v.iter().filter(|&&b| !b).count();
+    v[..falses].fill(false);
+    v[falses..].fill(true);
+}
+
+// Sort by pulling out one distinct value at a time: find the smallest
+// remaining value, move it to the front of the unsorted part, and then
+// partition every other copy of it up next to it. Each distinct value
+// takes two O(N) passes, so with k distinct values this is O(kN), which
+// only beats an O(N log N) sort when k is small.
+pub fn few_values_sort<T: PartialOrd + std::fmt::Debug>(v: &mut [T]) {
+    let mut start = 0;
+    // Invariant: v[0..start] is sorted, and everything in it is less
+    // than everything in v[start..].
+    while start < v.len() {
+        let mut smallest = start;
+        for i in start + 1..v.len() {
+            if v[i] < v[smallest] {
+                smallest = i;
+            }
+        }
+        v.swap(start, smallest);
+        let (done, rest) = v.split_at_mut(start + 1);
+        let value = &done[start];
+        start += 1 + partition_by(rest, |x| x == value);
+    }
+}
+
+// Whether `v` has at most `k` distinct values. We keep the index of the
+// first copy of each value we've seen, and give up as soon as we've seen
+// more than k; that's O(kN) in the worst case, but on data with lots of
+// different values we'll usually give up after just a few elements.
+pub fn has_at_most_distinct<T: PartialOrd>(v: &[T], k: usize) -> bool {
+    let mut seen: Vec<usize> = Vec::with_capacity(k);
+    for (i, x) in v.iter().enumerate() {
+        if !seen.iter().any(|&j| v[j] == *x) {
+            if seen.len() == k {
+                return false;
+            }
+            seen.push(i);
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod sort_bools {
+        use super::*;
+
+        #[test]
+        fn empty() {
+            let mut input: [bool; 0] = [];
+            sort_bools(&mut input);
+            assert_eq!(input, []);
+        }
+
+        #[test]
+        fn mixed() {
+            let mut input = [true, false, true, true, false];
+            sort_bools(&mut input);
+            assert_eq!(input, [false, false, true, true, true]);
+        }
+    }
+
+    mod few_values_sort {
+        use super::*;
+
+        #[test]
+        fn empty() {
+            let mut input: [i32; 0] = [];
+            few_values_sort(&mut input);
+            assert_eq!(input, []);
+        }
+
+        #[test]
+        fn ten_items() {
+            let mut input = [3, 2, 0, 5, 8, 9, 6, 3, 2, 0];
+            few_values_sort(&mut input);
+            assert_eq!(input, [0, 0, 2, 2, 3, 3, 5, 6, 8, 9]);
+        }
+
+        #[test]
+        fn enum_values() {
+            #[derive(Debug, PartialEq, PartialOrd)]
+            enum Status {
+                Todo,
+                Doing,
+                Done,
+            }
+            use Status::*;
+            let mut input = [Done, Todo, Doing, Todo, Done, Doing, Todo];
+            few_values_sort(&mut input);
+            assert_eq!(input, [Todo, Todo, Todo, Doing, Doing, Done, Done]);
+        }
+    }
+
+    #[test]
+    fn distinct_values() {
+        let input = [3, 2, 0, 5, 8, 9, 6, 3, 2, 0];
+        assert!(has_at_most_distinct(&input, 7));
+        assert!(!has_at_most_distinct(&input, 6));
+        assert!(has_at_most_distinct::<i32>(&[], 0));
+    }
+}
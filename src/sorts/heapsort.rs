@@ -0,0 +1,71 @@
+use crate::heap::{heapify_d, sift_down_d, BINARY};
+
+// Heapsort is "in place" like insertion sort and quicksort. We first
+// turn the whole array into a max-heap, so the largest element is at
+// v[0]. Then we repeatedly swap that largest element to the end of the
+// "heap part" of the array and sift the new root down, shrinking the
+// heap by one each time. More formally, after k passes:
+//   v[0..length-k] is a heap /\
+//   v[length-k..length] is sorted /\
+//   (All i, j | 0 ≤ i < length-k ≤ j < length : v[i] ≤ v[j])
+// Building the heap is O(N) and each of the N passes is O(log N), so
+// heapsort is O(N log N) even in the worst case. Unlike merge sort it
+// needs no extra space, but it isn't stable.
+pub fn heapsort<T: PartialOrd + std::fmt::Debug>(v: &mut [T]) {
+    heapsort_d(v, BINARY);
+}
+
+// The same thing using a d-ary heap; see `heap.rs` for why you might
+// want an arity other than 2.
+pub fn heapsort_d<T: PartialOrd + std::fmt::Debug>(v: &mut [T], arity: usize) {
+    heapify_d(v, arity);
+    for end in (1..v.len()).rev() {
+        // v[0] is the largest thing left in the heap, so it belongs
+        // at the end of the heap part.
+        v.swap(0, end);
+        sift_down_d(&mut v[0..end], 0, arity);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty() {
+        let mut input: [i32; 0] = [];
+        heapsort(&mut input);
+        let expected: [i32; 0] = [];
+
+        assert_eq!(expected, input);
+    }
+
+    #[test]
+    fn ten_items() {
+        let mut input = [3, 2, 0, 5, 8, 9, 6, 3, 2, 0];
+        heapsort(&mut input);
+        let expected = [0, 0, 2, 2, 3, 3, 5, 6, 8, 9];
+
+        assert_eq!(expected, input);
+    }
+
+    #[test]
+    fn presorted() {
+        let mut input = [0, 0, 2, 2, 3, 3, 5, 6, 8, 9];
+        heapsort(&mut input);
+        let expected = [0, 0, 2, 2, 3, 3, 5, 6, 8, 9];
+
+        assert_eq!(expected, input);
+    }
+
+    #[test]
+    fn other_arities() {
+        for &arity in &[3, 4, 8] {
+            let mut input = [3, 2, 0, 5, 8, 9, 6, 3, 2, 0];
+            heapsort_d(&mut input, arity);
+            let expected = [0, 0, 2, 2, 3, 3, 5, 6, 8, 9];
+
+            assert_eq!(expected, input);
+        }
+    }
+}
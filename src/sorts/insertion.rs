@@ -0,0 +1,397 @@
+// Insertion sort is "in place", so we modify the input array v
+// directly and do _not_ return anything. The elements of the
+// array need to traits `PartialOrd` (so they support < and ≤).
+// Also requiring the trait `Debug` means you can print the array
+// and slices of the array for debugging purposes with `{:?}`. I
+// don't do that here, but you could add some print statements if,
+// for example, you want to watch the bubbling happen.
+//
+// Note that the parameter v *has* to be mutable because we're 
+// modifying it in place.
+pub fn insertion_sort<T: PartialOrd + std::fmt::Debug>(v: &mut [T]) {
+    // Goal: (All x, y | 0 ≤ x < y < length : v[x] ≤ v[y])
+    for i in 0..v.len() {
+        // Invariant: (All x, y | 0 ≤ x < y < i : v[x] ≤ v[y])
+        // I.e., we assume everything < i is already sorted
+        // by previous passes. Now we want to get everything
+        // ≤ i to be sorted. This requires "bubbling" v[i]
+        // to the left until it "finds its spot", i.e., until
+        // swapping it one more time would make it _larger_
+        // than the value to its right.
+        //
+        // j is where we are in the bubbling process, so we
+        // start with j=i.
+        let mut j = i;
+        // If j > 0 we might still need to move left, so continue. 
+        // But _only_ continue if v[j] _should_ move left, i.e.,
+        // if it's less than the value to its left (so those two
+        // are out of order.)
+        while j > 0 && v[j-1] > v[j] {
+            // Since j-1 and j are out of order swap them, and move
+            // j one to the left to continue the bubbling if necessary.
+            v.swap(j-1, j);
+            j -= 1;
+        }
+    }
+    // And we're done! The outer for loop is done O(N) times, and
+    // the inner while loop is (on average) O(N), so insertion sort
+    // is O(N^2).
+}
+
+// Every time around insertion sort's inner loop we check `j > 0` as
+// well as comparing v[j-1] and v[j]. If we knew that v[0] was no larger
+// than anything else, though, we wouldn't need the `j > 0` check at all:
+// the bubbling would always stop at j=1 because v[0] > v[1] would be
+// false. v[0] acts as a _sentinel_ that guards the left end of the
+// array. `sentinel_insertion_sort` makes its own sentinel by moving the
+// smallest element to the front first, which costs one O(N) pass.
+//
+// Rotating (rather than swapping) the smallest element to the front, and
+// picking the _first_ smallest element, keeps the sort stable.
+//
+// Don't expect miracles, though (see `benches/insertion.rs`). Since we
+// don't use any unsafe indexing, Rust still checks that j-1 is in bounds
+// every time around the loop, which is just the `j > 0` check in another
+// form. The `j > 0` check is also very predictable, so the CPU hardly
+// notices it. The sentinel trick pays off much more in languages (or
+// unsafe code) without bounds checks.
+pub fn sentinel_insertion_sort<T: PartialOrd + std::fmt::Debug>(v: &mut [T]) {
+    if v.len() < 2 {
+        return;
+    }
+    let mut smallest = 0;
+    for i in 1..v.len() {
+        if v[i] < v[smallest] {
+            smallest = i;
+        }
+    }
+    v[0..=smallest].rotate_right(1);
+    unguarded_insertion_sort(v);
+}
+
+// Insertion sort without the `j > 0` check. The caller has to guarantee
+// that v[0] is a sentinel, i.e.,
+//   (All x | 0 ≤ x < length : v[0] ≤ v[x])
+// This is the situation in quicksort, for example, where everything in
+// the right-hand part is ≥ the pivot just to its left, so the right-hand
+// part can be sorted as `unguarded_insertion_sort(&mut v[smaller..])`.
+// If the guarantee doesn't hold we'll run off the left end of the slice,
+// which panics (we're not using any unsafe indexing here).
+pub fn unguarded_insertion_sort<T: PartialOrd + std::fmt::Debug>(v: &mut [T]) {
+    for i in 2..v.len() {
+        // Invariant: (All x, y | 0 ≤ x < y < i : v[x] ≤ v[y])
+        let mut j = i;
+        while v[j - 1] > v[j] {
+            v.swap(j - 1, j);
+            j -= 1;
+        }
+    }
+}
+
+// Binary insertion sort is insertion sort where we find where v[i]
+// belongs using binary search instead of by comparing it with each of
+// its neighbors in turn. Since v[0..i] is already sorted, that only
+// takes about log₂ i comparisons, so the total number of comparisons
+// drops from O(N^2) to O(N log N).
+//
+// Sadly, we still have to _move_ v[i] to its spot, which means shifting
+// everything between its spot and i one to the right. That's exactly the
+// same amount of moving as regular insertion sort does (one move for
+// every pair of elements that is out of order), so binary insertion sort
+// is still O(N^2) overall. It's a good choice when comparisons are much
+// more expensive than moves, e.g., when sorting long strings.
+pub fn binary_insertion_sort<T: PartialOrd + std::fmt::Debug>(v: &mut [T]) {
+    for i in 1..v.len() {
+        // Invariant: (All x, y | 0 ≤ x < y < i : v[x] ≤ v[y])
+        // Find the first position in v[0..i] holding something
+        // _larger_ than v[i]. Putting v[i] there (rather than before
+        // any equal values) keeps the sort stable.
+        let mut low = 0;
+        let mut high = i;
+        while low < high {
+            // (All x | 0 ≤ x < low : v[x] ≤ v[i]) /            // (All x | high ≤ x < i : v[x] > v[i])
+            let middle = low + (high - low) / 2;
+            if v[middle] > v[i] {
+                high = middle;
+            } else {
+                low = middle + 1;
+            }
+        }
+        // Rotating v[low..=i] one to the right moves v[i] to position
+        // low and shifts everything else over by one.
+        v[low..=i].rotate_right(1);
+    }
+}
+
+// Pair insertion sort is the trick Java's `Arrays.sort` uses for small
+// arrays: instead of inserting one element per pass, insert two. We
+// first put the pair in order, larger first, and then slide the larger
+// one left to its spot, moving everything bigger than it over by _two_
+// to make room for both. Then the smaller one continues sliding left
+// from where the larger one stopped, so it never has to look at the
+// elements the larger one already passed. This is still O(N^2), but the
+// elements bigger than both only get moved once per pair instead of
+// twice, and there's half as much loop overhead.
+//
+// Like `merge_sort` this needs `Copy`: we copy the pair out of the
+// array and then move elements over (by copying) into the space they
+// leave behind, rather than bubbling with swaps.
+pub fn pair_insertion_sort<T: PartialOrd + Copy + std::fmt::Debug>(v: &mut [T]) {
+    let length = v.len();
+    let mut i = 1;
+    while i + 1 < length {
+        // Invariant: (All x, y | 0 ≤ x < y < i : v[x] ≤ v[y])
+        // Make `larger` the larger of the pair. If they're equal we
+        // make `larger` the one on the right so it stays on the
+        // right, which keeps the sort stable.
+        let (larger, smaller) = if v[i] <= v[i + 1] {
+            (v[i + 1], v[i])
+        } else {
+            (v[i], v[i + 1])
+        };
+        // Move everything bigger than `larger` over by two. That
+        // leaves a gap at j and j+1 for the pair.
+        let mut j = i;
+        while j > 0 && v[j - 1] > larger {
+            v[j + 1] = v[j - 1];
+            j -= 1;
+        }
+        v[j + 1] = larger;
+        // Now keep going with `smaller`, moving things over by one.
+        while j > 0 && v[j - 1] > smaller {
+            v[j] = v[j - 1];
+            j -= 1;
+        }
+        v[j] = smaller;
+        i += 2;
+    }
+    // If there was an odd number of elements left over after the first,
+    // the last one still needs to be inserted on its own.
+    if i < length {
+        let last = v[i];
+        let mut j = i;
+        while j > 0 && v[j - 1] > last {
+            v[j] = v[j - 1];
+            j -= 1;
+        }
+        v[j] = last;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instrument::{count_comparisons, counted, inversions};
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    mod insertion_sort {
+        use super::*;
+        #[test]
+        fn empty() {
+            let mut input : [i32; 0] = [];
+            insertion_sort(&mut input);
+            let expected : [i32; 0] = [];
+
+            assert_eq!(expected, input);
+        }
+
+        #[test]
+        fn ten_items() {
+            let mut input = [3, 2, 0, 5, 8, 9, 6, 3, 2, 0];
+            insertion_sort(&mut input);
+            let expected = [0, 0, 2, 2, 3, 3, 5, 6, 8, 9];
+
+            assert_eq!(expected, input);
+        }
+
+        #[test]
+        fn presorted() {
+            let mut input = [0, 0, 2, 2, 3, 3, 5, 6, 8, 9];
+            insertion_sort(&mut input);
+            let expected = [0, 0, 2, 2, 3, 3, 5, 6, 8, 9];
+
+            assert_eq!(expected, input);
+        }
+    }
+
+    mod sentinel_insertion_sort {
+        use super::*;
+        #[test]
+        fn empty() {
+            let mut input : [i32; 0] = [];
+            sentinel_insertion_sort(&mut input);
+            let expected : [i32; 0] = [];
+
+            assert_eq!(expected, input);
+        }
+
+        #[test]
+        fn ten_items() {
+            let mut input = [3, 2, 0, 5, 8, 9, 6, 3, 2, 0];
+            sentinel_insertion_sort(&mut input);
+            let expected = [0, 0, 2, 2, 3, 3, 5, 6, 8, 9];
+
+            assert_eq!(expected, input);
+        }
+
+        #[test]
+        fn presorted() {
+            let mut input = [0, 0, 2, 2, 3, 3, 5, 6, 8, 9];
+            sentinel_insertion_sort(&mut input);
+            let expected = [0, 0, 2, 2, 3, 3, 5, 6, 8, 9];
+
+            assert_eq!(expected, input);
+        }
+
+        #[test]
+        fn unguarded_with_sentinel() {
+            let mut input = [-1, 3, 2, 0, 5, 8, 9, 6, 3, 2, 0];
+            unguarded_insertion_sort(&mut input);
+            let expected = [-1, 0, 0, 2, 2, 3, 3, 5, 6, 8, 9];
+
+            assert_eq!(expected, input);
+        }
+
+        #[test]
+        #[should_panic]
+        fn unguarded_without_sentinel() {
+            let mut input = [3, 2, 0, 5];
+            unguarded_insertion_sort(&mut input);
+        }
+    }
+
+    mod binary_insertion_sort {
+        use super::*;
+        #[test]
+        fn empty() {
+            let mut input : [i32; 0] = [];
+            binary_insertion_sort(&mut input);
+            let expected : [i32; 0] = [];
+
+            assert_eq!(expected, input);
+        }
+
+        #[test]
+        fn ten_items() {
+            let mut input = [3, 2, 0, 5, 8, 9, 6, 3, 2, 0];
+            binary_insertion_sort(&mut input);
+            let expected = [0, 0, 2, 2, 3, 3, 5, 6, 8, 9];
+
+            assert_eq!(expected, input);
+        }
+
+        #[test]
+        fn presorted() {
+            let mut input = [0, 0, 2, 2, 3, 3, 5, 6, 8, 9];
+            binary_insertion_sort(&mut input);
+            let expected = [0, 0, 2, 2, 3, 3, 5, 6, 8, 9];
+
+            assert_eq!(expected, input);
+        }
+
+        #[test]
+        fn stable() {
+            // Sort pairs by their first element only.
+            #[derive(Debug, PartialEq)]
+            struct Tagged(i32, usize);
+            impl PartialOrd for Tagged {
+                fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+                    self.0.partial_cmp(&other.0)
+                }
+            }
+            let mut input: Vec<Tagged> = [2, 1, 2, 1, 2]
+                .iter()
+                .enumerate()
+                .map(|(i, &x)| Tagged(x, i))
+                .collect();
+            binary_insertion_sort(&mut input);
+            let tags: Vec<usize> = input.iter().map(|t| t.1).collect();
+
+            assert_eq!(vec![1, 3, 0, 2, 4], tags);
+        }
+
+        // Insertion sort makes at least one comparison per inversion
+        // (pair of elements that are out of order), which is O(N^2) on
+        // random input. Binary insertion sort makes at most ⌈log₂ i⌉
+        // comparisons when inserting v[i], no matter how many inversions
+        // there are. Both do one move per inversion.
+        #[test]
+        fn fewer_comparisons_than_insertion_sort() {
+            let mut rng = StdRng::seed_from_u64(209);
+            let size = 1000;
+            let input: Vec<i32> = (0..size).map(|_| rng.gen()).collect();
+            let moves = inversions(&input);
+
+            let mut plain = counted(&input);
+            let (_, plain_count) = count_comparisons(|| insertion_sort(&mut plain));
+            let mut binary = counted(&input);
+            let (_, binary_count) = count_comparisons(|| binary_insertion_sort(&mut binary));
+
+            let bound: u64 = (1..size as u64).map(|i| 64 - i.leading_zeros() as u64).sum();
+            assert!(plain_count >= moves);
+            assert!(binary_count <= bound);
+            // On random input the moves swamp the comparisons.
+            assert!(10 * binary_count < moves);
+        }
+    }
+
+    mod pair_insertion_sort {
+        use super::*;
+        #[test]
+        fn empty() {
+            let mut input : [i32; 0] = [];
+            pair_insertion_sort(&mut input);
+            let expected : [i32; 0] = [];
+
+            assert_eq!(expected, input);
+        }
+
+        #[test]
+        fn ten_items() {
+            let mut input = [3, 2, 0, 5, 8, 9, 6, 3, 2, 0];
+            pair_insertion_sort(&mut input);
+            let expected = [0, 0, 2, 2, 3, 3, 5, 6, 8, 9];
+
+            assert_eq!(expected, input);
+        }
+
+        #[test]
+        fn presorted() {
+            let mut input = [0, 0, 2, 2, 3, 3, 5, 6, 8, 9];
+            pair_insertion_sort(&mut input);
+            let expected = [0, 0, 2, 2, 3, 3, 5, 6, 8, 9];
+
+            assert_eq!(expected, input);
+        }
+
+        #[test]
+        fn random_inputs() {
+            let mut rng = StdRng::seed_from_u64(210);
+            for len in 0..100 {
+                let input: Vec<(i32, usize)> =
+                    (0..len).map(|i| (rng.gen_range(0, 10), i)).collect();
+                // Sorting on the first field only lets us check stability:
+                // the result should match a stable sort of the pairs.
+                let mut v: Vec<ByFirst> = input.iter().map(|&p| ByFirst(p)).collect();
+                pair_insertion_sort(&mut v);
+                let mut expected = input.clone();
+                expected.sort_by_key(|p| p.0);
+
+                assert_eq!(expected, v.iter().map(|b| b.0).collect::<Vec<_>>());
+            }
+        }
+
+        #[derive(Debug, Clone, Copy)]
+        struct ByFirst((i32, usize));
+        impl PartialEq for ByFirst {
+            fn eq(&self, other: &Self) -> bool {
+                (self.0).0 == (other.0).0
+            }
+        }
+        impl PartialOrd for ByFirst {
+            fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+                (self.0).0.partial_cmp(&(other.0).0)
+            }
+        }
+    }
+}
@@ -0,0 +1,165 @@
+// Library sort (Bender, Farach-Colton, and Mosteiro, 2006), also known
+// as gapped insertion sort. Insertion sort is slow because inserting an
+// element means shifting everything after it over by one. A librarian
+// shelving books leaves some empty space on each shelf so that adding a
+// book only means moving a few books over, and library sort does the
+// same thing: it keeps the sorted elements spread out in an array with
+// gaps between them, so an insertion usually only has to shift a few
+// elements before it reaches a gap.
+//
+// We insert the elements in rounds of 1, 2, 4, 8, ... elements. At the
+// end of each round we _rebalance_, spreading the elements evenly over a
+// new array with room for the next round plus a `gap_factor` fraction of
+// extra space. Finding where each element goes is a binary search (that
+// skips over the gaps), so the whole thing is O(N log N) with high
+// probability on randomly ordered input.
+//
+// The price is memory: the gapped array has about (1 + gap_factor)·N
+// slots of `Option<T>` (plus the old array while rebalancing), where
+// merge sort needs N extra slots and insertion sort needs none. Bigger
+// gap factors mean fewer elements to shift per insertion, but more
+// memory and more gaps for the binary search to skip.
+//
+// Library sort is also the opposite of adaptive: sorted (or nearly
+// sorted) input is its _worst_ case. Every element goes at the far right
+// end, where the gaps quickly run out, so the gaps elsewhere don't help
+// and it degrades to O(N^2). The original paper avoids this by inserting
+// the elements in a random order, at the cost of stability; here we keep
+// the input order so the sort is stable.
+//
+// Like `merge_sort` this returns a new `Vec` and needs `Copy` for the
+// elements.
+pub fn library_sort<T: PartialOrd + Copy + std::fmt::Debug>(v: &[T], gap_factor: f64) -> Vec<T> {
+    assert!(gap_factor > 0.0, "library sort needs a positive gap factor");
+    let length = v.len();
+    let size_for = |count: usize| (((1.0 + gap_factor) * count as f64).ceil() as usize) + 1;
+
+    let mut slots: Vec<Option<T>> = vec![None; size_for(1)];
+    for (count, &x) in v.iter().enumerate() {
+        // Rebalance after each round, i.e., whenever the number of
+        // elements inserted so far (`count`) is a power of two.
+        if count > 0 && count.is_power_of_two() {
+            slots = rebalance(&slots, count, size_for(length.min(2 * count)));
+        }
+        insert(&mut slots, x);
+    }
+    slots.into_iter().flatten().collect()
+}
+
+// Spread the `count` elements in `slots` evenly over a new array with
+// `size` slots. Since `size` is bigger than `count`, the positions
+// k·size/count are all different, so nothing gets overwritten.
+fn rebalance<T: Copy>(slots: &[Option<T>], count: usize, size: usize) -> Vec<Option<T>> {
+    let mut result = vec![None; size];
+    for (k, &x) in slots.iter().flatten().enumerate() {
+        result[k * size / count] = Some(x);
+    }
+    result
+}
+
+// Insert x into the gapped array, after any elements equal to it (to keep
+// the sort stable).
+fn insert<T: PartialOrd + Copy>(slots: &mut [Option<T>], x: T) {
+    // Binary search for a position `low` where:
+    //   (All i | 0 ≤ i < low ∧ slots[i] occupied : slots[i] ≤ x) /\
+    //   (All i | low ≤ i < size ∧ slots[i] occupied : slots[i] > x)
+    // Whenever `middle` lands on a gap we look right for the nearest
+    // occupied slot and compare against that instead.
+    let mut low = 0;
+    let mut high = slots.len();
+    while low < high {
+        let middle = low + (high - low) / 2;
+        let mut m = middle;
+        while m < high && slots[m].is_none() {
+            m += 1;
+        }
+        if m == high {
+            // Everything from middle up to high is a gap.
+            high = middle;
+        } else {
+            match slots[m] {
+                Some(y) if y <= x => low = m + 1,
+                _ => high = middle,
+            }
+        }
+    }
+
+    if low < slots.len() && slots[low].is_none() {
+        // The easy (and hopefully common) case: there's a gap right
+        // where x belongs.
+        slots[low] = Some(x);
+    } else if let Some(gap) = (low..slots.len()).find(|&i| slots[i].is_none()) {
+        // Shift everything from low up to the next gap one to the right.
+        slots[low..=gap].rotate_right(1);
+        slots[low] = Some(x);
+    } else {
+        // There's no gap to the right, so there must be one to the left
+        // (there's always at least one gap). Shift everything between it
+        // and low one to the left, and put x just before low.
+        let gap = (0..low).rev().find(|&i| slots[i].is_none()).unwrap();
+        slots[gap..low].rotate_left(1);
+        slots[low - 1] = Some(x);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    #[test]
+    fn empty() {
+        let input: [i32; 0] = [];
+        let result = library_sort(&input, 1.0);
+        let expected: Vec<i32> = Vec::new();
+
+        assert_eq!(expected, result);
+    }
+
+    #[test]
+    fn ten_items() {
+        let input = [3, 2, 0, 5, 8, 9, 6, 3, 2, 0];
+        let result = library_sort(&input, 1.0);
+        let expected = [0, 0, 2, 2, 3, 3, 5, 6, 8, 9].to_vec();
+
+        assert_eq!(expected, result);
+    }
+
+    #[test]
+    fn presorted() {
+        let input = [0, 0, 2, 2, 3, 3, 5, 6, 8, 9];
+        let result = library_sort(&input, 1.0);
+        let expected = [0, 0, 2, 2, 3, 3, 5, 6, 8, 9].to_vec();
+
+        assert_eq!(expected, result);
+    }
+
+    #[test]
+    fn reversed() {
+        let input: Vec<i32> = (0..100).rev().collect();
+        let result = library_sort(&input, 0.5);
+        let expected: Vec<i32> = (0..100).collect();
+
+        assert_eq!(expected, result);
+    }
+
+    #[test]
+    fn random_inputs_and_gap_factors() {
+        let mut rng = StdRng::seed_from_u64(212);
+        for &gap_factor in &[0.1, 0.5, 1.0, 3.0] {
+            for len in 0..200 {
+                let input: Vec<i32> = (0..len).map(|_| rng.gen_range(0, 50)).collect();
+                let mut expected = input.clone();
+                expected.sort();
+
+                assert_eq!(expected, library_sort(&input, gap_factor));
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn zero_gap_factor() {
+        library_sort(&[1, 2, 3], 0.0);
+    }
+}
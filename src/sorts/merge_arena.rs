@@ -0,0 +1,268 @@
+// Merge sort without the allocations. `merge_sort` allocates two new
+// vectors for every call (one per half, then one for the merge), which
+// is about 2n allocations to sort n elements. When lots of threads are
+// each sorting lots of small arrays (like the chunks in
+// `parallel_merge_sort`), those allocations all go through the same
+// allocator and start to get in each other's way.
+//
+// `merge_sort_in` instead sorts in place, using a scratch buffer from a
+// `MergeArena` for its merges. An arena keeps its buffer between sorts,
+// so a thread that keeps one around allocates once (when the arena first
+// grows to the size of its biggest input) and never again:
+//    let mut arena = MergeArena::new();
+//    for v in batch.iter_mut() {
+//        merge_sort_in(&mut arena, v);
+//    }
+// The sort is the same as `merge_sort` (split in half, sort each half,
+// merge), so it's stable and the result is exactly what `merge_sort`
+// returns.
+//
+// With the `bump-arena` feature there's also `merge_sort_in_bump`, which
+// takes its scratch buffer from a `bumpalo::Bump` instead. Allocating
+// from a bump arena is just moving a pointer, and a thread that resets
+// its own arena between sorts never touches the shared allocator after
+// the arena's first chunk:
+//    let mut bump = Bump::new();
+//    for v in batch.iter_mut() {
+//        merge_sort_in_bump(&bump, v);
+//        bump.reset();
+//    }
+
+#[cfg(feature = "allocator-api")]
+use std::alloc::{Allocator, Global};
+
+// A reusable scratch buffer for `merge_sort_in`. With the
+// `allocator-api` feature the buffer can come from any allocator (see
+// `new_in`), so a sort can avoid the global allocator entirely:
+//    let mut v = Vec::new_in(&bump);
+//    v.extend_from_slice(&input);
+//    merge_sort_in(&mut MergeArena::new_in(&bump), &mut v);
+#[cfg(not(feature = "allocator-api"))]
+#[derive(Debug, Clone, Default)]
+pub struct MergeArena<T> {
+    scratch: Vec<T>,
+}
+
+#[cfg(feature = "allocator-api")]
+#[derive(Debug, Clone)]
+pub struct MergeArena<T, A: Allocator = Global> {
+    scratch: Vec<T, A>,
+}
+
+#[cfg(feature = "allocator-api")]
+impl<T> Default for MergeArena<T> {
+    fn default() -> MergeArena<T> {
+        MergeArena {
+            scratch: Vec::new(),
+        }
+    }
+}
+
+impl<T: Copy> MergeArena<T> {
+    pub fn new() -> MergeArena<T> {
+        MergeArena {
+            scratch: Vec::new(),
+        }
+    }
+
+    pub fn with_capacity(capacity: usize) -> MergeArena<T> {
+        MergeArena {
+            scratch: Vec::with_capacity(capacity),
+        }
+    }
+}
+
+#[cfg(feature = "allocator-api")]
+impl<T: Copy, A: Allocator> MergeArena<T, A> {
+    pub fn new_in(alloc: A) -> MergeArena<T, A> {
+        MergeArena {
+            scratch: Vec::new_in(alloc),
+        }
+    }
+
+    pub fn with_capacity_in(capacity: usize, alloc: A) -> MergeArena<T, A> {
+        MergeArena {
+            scratch: Vec::with_capacity_in(capacity, alloc),
+        }
+    }
+}
+
+// The rest is the same with or without an allocator, so we write it
+// once, for whichever `MergeArena` we have.
+macro_rules! arena_impl {
+    ($($alloc:ident)?) => {
+        impl<T: Copy $(, $alloc: Allocator)?> MergeArena<T $(, $alloc)?> {
+            // The biggest input we can sort without growing the buffer.
+            pub fn capacity(&self) -> usize {
+                self.scratch.capacity()
+            }
+
+            // A scratch buffer as long as `v`. We have to fill it with
+            // *something* to grow it, and copies of `v[0]` will do, since
+            // everything in it is overwritten before it's read.
+            fn scratch_for(&mut self, v: &[T]) -> &mut [T] {
+                if self.scratch.len() < v.len() {
+                    self.scratch.resize(v.len(), v[0]);
+                }
+                &mut self.scratch[..v.len()]
+            }
+        }
+
+        pub fn merge_sort_in<T: PartialOrd + Copy $(, $alloc: Allocator)?>(
+            arena: &mut MergeArena<T $(, $alloc)?>,
+            v: &mut [T],
+        ) {
+            if v.len() < 2 {
+                return;
+            }
+            let scratch = arena.scratch_for(v);
+            sort(v, scratch);
+        }
+    };
+}
+
+#[cfg(not(feature = "allocator-api"))]
+arena_impl!();
+#[cfg(feature = "allocator-api")]
+arena_impl!(A);
+
+// `merge_sort_in`, with the scratch buffer allocated from `bump`. The
+// buffer stays allocated until `bump` is reset (or dropped).
+#[cfg(feature = "bump-arena")]
+pub fn merge_sort_in_bump<T: PartialOrd + Copy>(bump: &bumpalo::Bump, v: &mut [T]) {
+    if v.len() < 2 {
+        return;
+    }
+    let scratch = bump.alloc_slice_copy(v);
+    sort(v, scratch);
+}
+
+// Sort `v`, using `scratch` (which is the same length) for the merges.
+fn sort<T: PartialOrd + Copy>(v: &mut [T], scratch: &mut [T]) {
+    let len = v.len();
+    if len < 2 {
+        return;
+    }
+    let middle = len / 2;
+    sort(&mut v[..middle], &mut scratch[..middle]);
+    sort(&mut v[middle..], &mut scratch[middle..]);
+    // If the halves are already in order there's nothing to merge.
+    if v[middle - 1] <= v[middle] {
+        return;
+    }
+    scratch.copy_from_slice(v);
+    let (left, right) = scratch.split_at(middle);
+    merge_into(left, right, v);
+}
+
+// Merge the sorted `left` and `right` into `out`, which is exactly as
+// long as both of them together. Like `merge::merge`, we take from
+// `left` when the two are equal, which keeps the sort stable.
+fn merge_into<T: PartialOrd + Copy>(left: &[T], right: &[T], out: &mut [T]) {
+    let mut i = 0;
+    let mut j = 0;
+    for slot in out.iter_mut() {
+        // Invariant:
+        //    (All k | 0 ≤ k < i + j : out[k] is the (k+1)th smallest of
+        //     left ∪ right)
+        if j < right.len() && (i == left.len() || right[j] < left[i]) {
+            *slot = right[j];
+            j += 1;
+        } else {
+            *slot = left[i];
+            i += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sorts::merge_sort;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    #[test]
+    fn ten_items() {
+        let mut arena = MergeArena::new();
+        let mut v = [3, 2, 0, 5, 8, 9, 6, 3, 2, 0];
+        merge_sort_in(&mut arena, &mut v);
+        assert_eq!([0, 0, 2, 2, 3, 3, 5, 6, 8, 9], v);
+
+        let mut empty: [i32; 0] = [];
+        merge_sort_in(&mut arena, &mut empty);
+    }
+
+    #[test]
+    fn same_as_merge_sort() {
+        // Pairs compared by their first element only, so we can check
+        // that equal elements stay in order.
+        #[derive(Debug, Clone, Copy)]
+        struct Keyed(u8, usize);
+        impl PartialEq for Keyed {
+            fn eq(&self, other: &Keyed) -> bool {
+                self.0 == other.0
+            }
+        }
+        impl PartialOrd for Keyed {
+            fn partial_cmp(&self, other: &Keyed) -> Option<std::cmp::Ordering> {
+                self.0.partial_cmp(&other.0)
+            }
+        }
+
+        let mut rng = StdRng::seed_from_u64(260);
+        let mut arena = MergeArena::new();
+        for _ in 0..200 {
+            let len = rng.gen_range(0, 300);
+            let input: Vec<Keyed> = (0..len).map(|i| Keyed(rng.gen_range(0, 10), i)).collect();
+            let expected: Vec<usize> = merge_sort(&input).iter().map(|k| k.1).collect();
+            let mut v = input.clone();
+            merge_sort_in(&mut arena, &mut v);
+            assert_eq!(expected, v.iter().map(|k| k.1).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn reuses_its_buffer() {
+        let mut arena = MergeArena::with_capacity(100);
+        let mut v: Vec<i32> = (0..100).rev().collect();
+        merge_sort_in(&mut arena, &mut v);
+        assert_eq!(100, arena.capacity());
+        let mut w: Vec<i32> = (0..50).rev().collect();
+        merge_sort_in(&mut arena, &mut w);
+        assert_eq!(100, arena.capacity());
+        assert_eq!((0..50).collect::<Vec<i32>>(), w);
+    }
+
+    #[cfg(feature = "bump-arena")]
+    #[test]
+    fn bump_arena() {
+        let mut rng = StdRng::seed_from_u64(260);
+        let mut bump = bumpalo::Bump::new();
+        for len in 0..300 {
+            let input: Vec<u32> = (0..len).map(|_| rng.gen_range(0, 50)).collect();
+            let mut v = input.clone();
+            merge_sort_in_bump(&bump, &mut v);
+            assert_eq!(merge_sort(&input), v);
+            if len > 1 {
+                assert!(bump.allocated_bytes() > 0);
+            }
+            bump.reset();
+        }
+    }
+
+    #[cfg(feature = "allocator-api")]
+    #[test]
+    fn uses_the_allocator() {
+        use crate::merge::CountingAllocator;
+        let allocations = std::cell::Cell::new(0);
+        let mut arena = MergeArena::new_in(CountingAllocator(&allocations));
+        for n in 1..=100 {
+            let mut v: Vec<i32> = (0..n).rev().collect();
+            merge_sort_in(&mut arena, &mut v);
+            assert_eq!((0..n).collect::<Vec<i32>>(), v);
+        }
+        // However many times `resize` grew the buffer, it was all in our
+        // allocator.
+        assert!(allocations.get() > 0);
+    }
+}
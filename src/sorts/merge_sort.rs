@@ -0,0 +1,239 @@
+use crate::merge::merge;
+
+// Merge sort can't be done "in place", so it needs to return a _new_
+// Vec<T> of the sorted elements. The array elements need to have
+// the traits `PartialOrd` and `Debug` like in the other sorting
+// algorithms, but they also need to have the `Copy` trait so we
+// can do things like `result.push(v[i])` to push element v[i] onto
+// a vector result. This ends up copying v[i] (to prevent ownership
+// issues on the array values), so we have to implement the `Copy`
+// trait. Numbers all do this, so that should be fine.
+// Note, however, that this has significant consequences – we can use `merge_sort`
+// to sort things like numbers, but sorting "large" things (e.g., student records)
+// would involve copying them, and that's likely to be expensive and perhaps undesirable.
+//
+// Note that here the parameter v does *not* have to be mutable because we're 
+// creating and returning a new vector instead of modifying v in place.
+// We're returning a vector instead of an array here because arrays have to
+// know exactly how big they are. I suspect there's a way to make that work
+// but I (Nic) couldn't figure out an easy way to sort out the types on the
+// `merge()` function keeping everything as arrays. It was a lot easier to 
+// just have the return type be Vec, so that's what I did. 
+//
+// The base cases are the lab's, as handed out, so we don't take clippy's
+// suggestion to write `vec![v[0]]`.
+#[allow(clippy::vec_init_then_push)]
+pub fn merge_sort<T: PartialOrd + std::marker::Copy + std::fmt::Debug>(v: &[T]) -> Vec<T> {
+    // Merge sort is a recursive solution where we split the
+    // array in half (slices make this easy), sort each half,
+    // and then merge the results together. All the "interesting"
+    // work is in the merge here, where in quicksort the "interesting"
+    // work is in organizing around the pivot.
+
+    let len = v.len();
+    if len == 0 {
+        return Vec::<T>::new();
+    }
+    if len == 1 {
+        let mut result = Vec::<T>::new();
+        result.push(v[0]);
+        return result;
+    }
+    let middle = v.len() / 2; //rounds down by default
+    let left = merge_sort(&v[0..middle]);
+    let right = merge_sort(&v[middle .. len]);
+    // Note that in Rust the last expression is what is
+    // returned, and we don't need the explicit `return`
+    // keyword. So this merges `left` and `right` and
+    // returns the result as the result of this call to
+    // `merge_sort()`.
+    merge(left, right)
+}
+
+// The same, but collected straight into whatever kind of collection you
+// want back, rather than always a `Vec`: a `VecDeque`, a `BTreeSet`, a
+// `String` (of `char`s), ... (anything that implements `FromIterator`).
+// We sort the two halves as usual, but do the last merge lazily, so the
+// sorted items go straight into the collection without being gathered
+// into a `Vec` first.
+pub fn merge_sort_collect<T, C>(v: &[T]) -> C
+where
+    T: PartialOrd + std::marker::Copy + std::fmt::Debug,
+    C: std::iter::FromIterator<T>,
+{
+    let middle = v.len() / 2;
+    Merging {
+        left: merge_sort(&v[0..middle]).into_iter().peekable(),
+        right: merge_sort(&v[middle..]).into_iter().peekable(),
+    }
+    .collect()
+}
+
+// `merge_sort_collect` under the name the small-vector types (`SmallVec`,
+// `ArrayVec`) were first given it. The in-place sorts don't need
+// anything like this, since those types all dereference to a `&mut [T]`.
+// (An `ArrayVec` panics if it's collected into past its capacity, so
+// it has to be big enough for all of `v`.) The tests for those types
+// are behind the `smallvec` and `arrayvec` features.
+pub fn merge_sort_into_collection<T, C>(v: &[T]) -> C
+where
+    T: PartialOrd + std::marker::Copy + std::fmt::Debug,
+    C: std::iter::FromIterator<T>,
+{
+    merge_sort_collect(v)
+}
+
+// The merge from `merge::merge`, one item at a time.
+struct Merging<T> {
+    left: std::iter::Peekable<std::vec::IntoIter<T>>,
+    right: std::iter::Peekable<std::vec::IntoIter<T>>,
+}
+
+impl<T: PartialOrd> Iterator for Merging<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        // Taking from the left when the two are equal keeps it stable.
+        let take_right = match (self.left.peek(), self.right.peek()) {
+            (Some(x), Some(y)) => y < x,
+            (None, _) => true,
+            (_, None) => false,
+        };
+        if take_right {
+            self.right.next()
+        } else {
+            self.left.next()
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.left.len() + self.right.len();
+        (len, Some(len))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod merge_sort {
+        use super::*;
+        #[test]
+        fn empty() {
+            let input : [i32; 0] = [];
+            let result = merge_sort(&input);
+            let expected : Vec<i32> = Vec::new();
+
+            assert_eq!(expected, result);
+        }
+
+        #[test]
+        fn ten_items() {
+            let input = [3, 2, 0, 5, 8, 9, 6, 3, 2, 0];
+            let result = merge_sort(&input);
+            let expected = [0, 0, 2, 2, 3, 3, 5, 6, 8, 9].to_vec();
+
+            assert_eq!(expected, result);
+        }
+
+        #[test]
+        fn presorted() {
+            let input = [0, 0, 2, 2, 3, 3, 5, 6, 8, 9];
+            let result = merge_sort(&input);
+            let expected = [0, 0, 2, 2, 3, 3, 5, 6, 8, 9].to_vec();
+
+            assert_eq!(expected, result);
+        }
+    }
+
+    mod merge_sort_collect {
+        use super::*;
+        use std::collections::{BTreeSet, VecDeque};
+
+        #[test]
+        fn other_collections() {
+            let input = [3, 2, 0, 5, 8, 9, 6, 3, 2, 0];
+            let expected = [0, 0, 2, 2, 3, 3, 5, 6, 8, 9];
+
+            let v: Vec<i32> = merge_sort_collect(&input);
+            assert_eq!(expected.to_vec(), v);
+            let deque: VecDeque<i32> = merge_sort_collect(&input);
+            assert!(deque.iter().eq(&expected));
+            let set: BTreeSet<i32> = merge_sort_collect(&input);
+            assert!(set.into_iter().eq(vec![0, 2, 3, 5, 6, 8, 9]));
+            let boxed: Box<[i32]> = merge_sort_into_collection(&input);
+            assert_eq!(expected, *boxed);
+            let text: String = merge_sort_collect(&['c', 'a', 'b']);
+            assert_eq!("abc", text);
+        }
+
+        #[test]
+        fn small_inputs() {
+            let empty: Vec<i32> = merge_sort_collect(&[]);
+            assert!(empty.is_empty());
+            let one: Vec<i32> = merge_sort_collect(&[7]);
+            assert_eq!(vec![7], one);
+        }
+
+        #[test]
+        fn stable() {
+            // Compare on the first field only.
+            #[derive(Debug, Clone, Copy, PartialEq)]
+            struct Tagged(i32, char);
+            impl PartialOrd for Tagged {
+                fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+                    self.0.partial_cmp(&other.0)
+                }
+            }
+            let input = [Tagged(2, 'a'), Tagged(1, 'a'), Tagged(2, 'b'), Tagged(1, 'b')];
+            let tags: String = merge_sort_collect::<_, Vec<Tagged>>(&input)
+                .iter()
+                .map(|t| t.1)
+                .collect();
+            assert_eq!("abab", tags);
+        }
+
+        #[cfg(feature = "smallvec")]
+        #[test]
+        fn small_vec() {
+            use smallvec::SmallVec;
+            let input = [3, 2, 0, 5, 8, 9, 6, 3, 2, 0];
+            let expected = [0, 0, 2, 2, 3, 3, 5, 6, 8, 9];
+
+            let inline: SmallVec<[i32; 16]> = merge_sort_into_collection(&input);
+            assert!(!inline.spilled());
+            assert_eq!(expected, inline[..]);
+            let spilled: SmallVec<[i32; 4]> = merge_sort_into_collection(&input);
+            assert!(spilled.spilled());
+            assert_eq!(expected, spilled[..]);
+
+            let mut in_place: SmallVec<[i32; 16]> = input.iter().copied().collect();
+            crate::sorts::quicksort(&mut in_place);
+            assert_eq!(expected, in_place[..]);
+        }
+
+        #[cfg(feature = "arrayvec")]
+        #[test]
+        fn array_vec() {
+            use arrayvec::ArrayVec;
+            let input = [3, 2, 0, 5, 8, 9, 6, 3, 2, 0];
+            let expected = [0, 0, 2, 2, 3, 3, 5, 6, 8, 9];
+
+            let sorted: ArrayVec<i32, 10> = merge_sort_into_collection(&input);
+            assert_eq!(expected, sorted[..]);
+            let empty: ArrayVec<i32, 0> = merge_sort_into_collection(&[]);
+            assert!(empty.is_empty());
+
+            let mut in_place: ArrayVec<i32, 16> = input.iter().copied().collect();
+            crate::sorts::merge_sort_in(&mut crate::sorts::MergeArena::new(), &mut in_place);
+            assert_eq!(expected, in_place[..]);
+        }
+
+        #[cfg(feature = "arrayvec")]
+        #[test]
+        #[should_panic]
+        fn array_vec_too_small() {
+            let _: arrayvec::ArrayVec<i32, 4> = merge_sort_into_collection(&[3, 2, 1, 0, 4]);
+        }
+    }
+}
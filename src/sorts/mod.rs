@@ -0,0 +1,57 @@
+// The sorting algorithms themselves, one file per algorithm (or family
+// of closely related algorithms).
+
+mod bitonic;
+mod bucket_sort;
+#[cfg(feature = "unsafe-opt")]
+mod buffered_merge_sort;
+mod counting_sort;
+mod few_values;
+mod heapsort;
+mod insertion;
+mod library_sort;
+mod merge_arena;
+mod merge_sort;
+mod parallel_merge_sort;
+mod quicksort;
+mod radix_sort;
+mod resort;
+mod rough_sort;
+mod selection_sort;
+mod shell_sort;
+mod strand_sort;
+mod weak_heapsort;
+
+pub use bitonic::{bitonic_passes, bitonic_sort};
+pub use bucket_sort::bucket_sort;
+#[cfg(feature = "unsafe-opt")]
+pub use buffered_merge_sort::{buffered_merge_sort, buffered_merge_sort_by};
+pub use counting_sort::{
+    counting_sort, counting_sort_u16, counting_sort_u8, COUNTING_SORT_U16_THRESHOLD,
+};
+pub use few_values::{few_values_sort, has_at_most_distinct, sort_bools};
+pub use heapsort::{heapsort, heapsort_d};
+pub use insertion::{
+    binary_insertion_sort, insertion_sort, pair_insertion_sort, sentinel_insertion_sort,
+    unguarded_insertion_sort,
+};
+pub use library_sort::library_sort;
+pub use merge_arena::{merge_sort_in, MergeArena};
+#[cfg(feature = "bump-arena")]
+pub use merge_arena::merge_sort_in_bump;
+pub use merge_sort::{merge_sort, merge_sort_collect, merge_sort_into_collection};
+pub use parallel_merge_sort::{
+    parallel_merge_sort, parallel_merge_sort_with_stats, ParallelConfig, ParallelSortStats,
+    WorkerStats, PAR_THRESHOLD_VAR, THREADS_VAR,
+};
+pub use quicksort::{
+    dual_pivot_quicksort, introsort, quicksort, quicksort_3way, randomized_quicksort,
+    try_quicksort, DUAL_PIVOT_INSERTION_CUTOFF, INTROSORT_INSERTION_CUTOFF,
+};
+pub use radix_sort::{radix_sort_lsd, RadixKey};
+pub use resort::{resort_tail, sorted_prefix_len, sorted_suffix_len};
+pub use rough_sort::{is_roughly_sorted, max_displacement, rough_sort};
+pub use selection_sort::selection_sort;
+pub use shell_sort::{shell_sort, GapSequence};
+pub use strand_sort::strand_sort;
+pub use weak_heapsort::weak_heapsort;
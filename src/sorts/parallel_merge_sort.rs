@@ -0,0 +1,603 @@
+use crate::merge::merge;
+use crate::pool::broadcast;
+use crate::sorts::{merge_sort_in, sorted_prefix_len, MergeArena};
+use crate::tuned::tuned;
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Settings for `parallel_merge_sort`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParallelConfig {
+    // How many threads to use.
+    pub threads: usize,
+    // How many elements each thread sorts at a time. Smaller chunks
+    // spread the work more evenly (and are more likely to be already
+    // sorted) but mean more merging.
+    pub chunk_size: usize,
+    // Inputs with fewer elements than this are sorted on the calling
+    // thread alone, since starting threads costs more than it saves on
+    // small inputs. `None` means work it out from the size of the
+    // elements and `tuned.toml` (see `threshold_for`), which is what
+    // lets callers use the parallel sort on inputs of any size without
+    // it being slower than the sequential one on small ones.
+    pub threshold: Option<usize>,
+    // Print the settings, and how many chunks were skipped because they
+    // were already sorted.
+    pub verbose: bool,
+}
+
+// The environment variables `ParallelConfig::from_env` reads, e.g.
+//    SORTLAB_THREADS=2 SORTLAB_PAR_THRESHOLD=100000 cargo run -- matrix
+// so the parallel sort can be tuned without recompiling.
+pub const THREADS_VAR: &str = "SORTLAB_THREADS";
+pub const PAR_THRESHOLD_VAR: &str = "SORTLAB_PAR_THRESHOLD";
+
+impl Default for ParallelConfig {
+    fn default() -> ParallelConfig {
+        ParallelConfig {
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+            chunk_size: 1 << 16,
+            threshold: None,
+            verbose: false,
+        }
+    }
+}
+
+impl ParallelConfig {
+    // The default settings, with `SORTLAB_THREADS` and
+    // `SORTLAB_PAR_THRESHOLD` applied if they're set.
+    pub fn from_env() -> Result<ParallelConfig, String> {
+        ParallelConfig::default().with_overrides(|name| std::env::var(name).ok())
+    }
+
+    // These settings, with the thread count and threshold replaced by
+    // whatever `lookup` returns for their variables. (`from_env` passes
+    // in the real environment; the tests pass in their own, since every
+    // test thread shares the real one.)
+    pub fn with_overrides<F>(mut self, lookup: F) -> Result<ParallelConfig, String>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        if let Some(value) = lookup(THREADS_VAR) {
+            self.threads = match value.trim().parse() {
+                Ok(threads) if threads > 0 => threads,
+                _ => {
+                    return Err(format!(
+                        "{}: expected at least 1 thread, not `{}`",
+                        THREADS_VAR, value
+                    ))
+                }
+            };
+        }
+        if let Some(value) = lookup(PAR_THRESHOLD_VAR) {
+            self.threshold = Some(value.trim().parse().map_err(|_| {
+                format!("{}: expected a number, not `{}`", PAR_THRESHOLD_VAR, value)
+            })?);
+        }
+        Ok(self)
+    }
+
+    // The threshold for sorting `T`s: the one we were given, or else the
+    // tuned one for elements of `T`'s size.
+    pub fn threshold_for<T>(&self) -> usize {
+        self.threshold
+            .unwrap_or_else(|| tuned().par_threshold::<T>())
+    }
+}
+
+// e.g. "8 threads, chunks of 65536, sequential below 65536 elements", or
+// "... sequential below 262144 bytes (tuned)" with no threshold set.
+impl fmt::Display for ParallelConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} thread{}, chunks of {}, ",
+            self.threads,
+            if self.threads == 1 { "" } else { "s" },
+            self.chunk_size
+        )?;
+        match self.threshold {
+            Some(threshold) => write!(f, "sequential below {} elements", threshold),
+            None => write!(
+                f,
+                "sequential below {} bytes (tuned)",
+                tuned().par_threshold_bytes
+            ),
+        }
+    }
+}
+
+// What happened during a parallel sort.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ParallelSortStats {
+    pub chunks: usize,
+    // Chunks that were already sorted, so we didn't sort them.
+    pub skipped: usize,
+    // What each worker thread did, over all the rounds of the sort.
+    pub workers: Vec<WorkerStats>,
+}
+
+// How one worker thread spent the sort.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct WorkerStats {
+    // Tasks (chunks to sort, or pairs of runs to merge) it ran.
+    pub tasks: usize,
+    // How many of those it stole from another worker.
+    pub steals: usize,
+    // Time spent running tasks, as opposed to waiting for the other
+    // workers to finish the round.
+    pub busy: Duration,
+}
+
+impl ParallelSortStats {
+    // The busiest worker's busy time over the average worker's: 1.0 means
+    // the work was spread perfectly evenly, and 2.0 means the busiest
+    // worker did twice its share (so the others spent a lot of time
+    // waiting for it).
+    pub fn imbalance(&self) -> f64 {
+        let busy: Vec<f64> = self.workers.iter().map(|w| w.busy.as_secs_f64()).collect();
+        let total: f64 = busy.iter().sum();
+        if total == 0.0 {
+            return 1.0;
+        }
+        let most = busy.iter().cloned().fold(0.0, f64::max);
+        most / (total / busy.len() as f64)
+    }
+}
+
+// Merge sort, spread across several threads. We cut the input into
+// chunks and have the threads sort them, and then merge the sorted
+// chunks pairwise, with all the merges in each round running in
+// parallel.
+//
+// Before sorting a chunk we check whether it's already sorted (which
+// takes just one O(n) pass), and if so leave it alone. Then when we merge
+// two chunks we first check whether the last element of the left one is
+// ≤ the first element of the right one, in which case "merging" is just
+// putting them next to each other. So on nearly sorted data almost all
+// the work is linear scans and copies, which parallelize very well.
+//
+// Like `merge_sort` this returns a new `Vec` and is stable. In fact the
+// result is always exactly what `merge_sort` returns, whatever the
+// thread count, chunk size, and threshold: each chunk is sorted stably,
+// and the runs are always paired up the same way (the first with the
+// second, the third with the fourth, ...) and merged stably, whichever
+// thread gets to them first. So a pipeline that diffs its output can
+// turn parallelism on or off, or run on a machine with more cores,
+// without the output changing by a single byte, even when equal
+// elements are distinguishable (like records with equal keys, or 0.0
+// and -0.0).
+pub fn parallel_merge_sort<T>(v: &[T], config: &ParallelConfig) -> Vec<T>
+where
+    T: PartialOrd + Copy + std::fmt::Debug + Send + Sync,
+{
+    let (result, stats) = parallel_merge_sort_with_stats(v, config);
+    if config.verbose {
+        eprintln!(
+            "parallel merge sort ({}): {} of {} chunks were already sorted",
+            config, stats.skipped, stats.chunks
+        );
+        for (i, worker) in stats.workers.iter().enumerate() {
+            eprintln!(
+                "  worker {}: {} tasks ({} stolen), busy {:?}",
+                i, worker.tasks, worker.steals, worker.busy
+            );
+        }
+        eprintln!(
+            "  imbalance: {:.2} (busiest worker / average)",
+            stats.imbalance()
+        );
+    }
+    result
+}
+
+pub fn parallel_merge_sort_with_stats<T>(
+    v: &[T],
+    config: &ParallelConfig,
+) -> (Vec<T>, ParallelSortStats)
+where
+    T: PartialOrd + Copy + std::fmt::Debug + Send + Sync,
+{
+    let threads = if v.len() < config.threshold_for::<T>() {
+        1
+    } else {
+        config.threads
+    };
+    let skipped = AtomicUsize::new(0);
+    let chunks: Vec<&[T]> = v.chunks(config.chunk_size.max(1)).collect();
+    let mut stats = ParallelSortStats {
+        chunks: chunks.len(),
+        skipped: 0,
+        workers: Vec::new(),
+    };
+    let mut runs = parallel_map(chunks, threads, &mut stats.workers, |chunk| {
+        if sorted_prefix_len(chunk) == chunk.len() {
+            skipped.fetch_add(1, Ordering::Relaxed);
+            chunk.to_vec()
+        } else {
+            // One scratch allocation for the whole chunk, rather than
+            // `merge_sort`'s two per call, since all the threads share
+            // the allocator.
+            let mut sorted = chunk.to_vec();
+            merge_sort_in(&mut MergeArena::with_capacity(chunk.len()), &mut sorted);
+            sorted
+        }
+    });
+    stats.skipped = skipped.into_inner();
+
+    while runs.len() > 1 {
+        // Pair up the runs; if there's an odd one out it goes through to
+        // the next round on its own.
+        let mut pairs = Vec::with_capacity(runs.len() / 2 + 1);
+        let mut runs_iter = runs.into_iter();
+        while let Some(left) = runs_iter.next() {
+            pairs.push((left, runs_iter.next()));
+        }
+        runs = parallel_map(
+            pairs,
+            threads,
+            &mut stats.workers,
+            |(left, right)| match right {
+                None => left,
+                Some(right) => merge_runs(left, right),
+            },
+        );
+    }
+    (runs.pop().unwrap_or_default(), stats)
+}
+
+fn merge_runs<T: PartialOrd + Copy + std::fmt::Debug>(mut left: Vec<T>, right: Vec<T>) -> Vec<T> {
+    match (left.last(), right.first()) {
+        (Some(last), Some(first)) if last <= first => {
+            left.extend_from_slice(&right);
+            left
+        }
+        _ => merge(left, right),
+    }
+}
+
+// Apply f to every item using `threads` threads (from the shared pool
+// in `pool.rs`, so we don't start new ones for every round), keeping
+// the results in the same order as the items, and adding what each
+// thread did to `workers`.
+//
+// The items are scheduled by work stealing. Each thread starts with its
+// own deque holding an equal share of the items (a contiguous block, so
+// neighbouring chunks tend to stay on the same core), and takes items
+// from the front of it. A thread that runs out steals from the *back*
+// of another thread's deque, which keeps thieves and owners out of each
+// other's way. So when some items take much longer than others (say one
+// chunk is reversed and the rest are already sorted), the threads that
+// finish early take over the rest of the slow thread's share instead of
+// sitting idle.
+fn parallel_map<A, B, F>(
+    items: Vec<A>,
+    threads: usize,
+    workers: &mut Vec<WorkerStats>,
+    f: F,
+) -> Vec<B>
+where
+    A: Send,
+    B: Send,
+    F: Fn(A) -> B + Sync,
+{
+    let threads = threads.max(1).min(items.len());
+    if workers.len() < threads {
+        workers.resize(threads, WorkerStats::default());
+    }
+    if threads <= 1 {
+        let before = Instant::now();
+        let results: Vec<B> = items.into_iter().map(f).collect();
+        if let Some(worker) = workers.first_mut() {
+            worker.tasks += results.len();
+            worker.busy += before.elapsed();
+        }
+        return results;
+    }
+    let count = items.len();
+    let items: Vec<Mutex<Option<A>>> = items.into_iter().map(|a| Mutex::new(Some(a))).collect();
+    let results: Mutex<Vec<Option<B>>> = Mutex::new((0..count).map(|_| None).collect());
+    // Thread t starts with items t * count / threads up to (but not
+    // including) (t + 1) * count / threads.
+    let deques: Vec<Mutex<VecDeque<usize>>> = (0..threads)
+        .map(|t| Mutex::new((t * count / threads..(t + 1) * count / threads).collect()))
+        .collect();
+    let round = Mutex::new(vec![WorkerStats::default(); threads]);
+    broadcast(threads, |t| {
+        let mut stats = WorkerStats::default();
+        while let Some((i, stolen)) = next_task(&deques, t) {
+            let item = items[i]
+                .lock()
+                .unwrap()
+                .take()
+                .expect("each item is claimed once");
+            let before = Instant::now();
+            let result = f(item);
+            stats.busy += before.elapsed();
+            stats.tasks += 1;
+            stats.steals += stolen as usize;
+            results.lock().unwrap()[i] = Some(result);
+        }
+        round.lock().unwrap()[t] = stats;
+    });
+    let round = round.into_inner().unwrap();
+    for (total, this_round) in workers.iter_mut().zip(round) {
+        total.tasks += this_round.tasks;
+        total.steals += this_round.steals;
+        total.busy += this_round.busy;
+    }
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|b| b.expect("every item should have been processed"))
+        .collect()
+}
+
+// The next item for thread `t`: the front of its own deque, or else the
+// back of the first other deque (starting with the next thread's) that
+// has anything left. Also returns whether it was stolen. `None` means
+// every deque is empty, and since nothing new is ever added, the round
+// is over.
+fn next_task(deques: &[Mutex<VecDeque<usize>>], t: usize) -> Option<(usize, bool)> {
+    if let Some(i) = deques[t].lock().unwrap().pop_front() {
+        return Some((i, false));
+    }
+    (1..deques.len())
+        .map(|offset| (t + offset) % deques.len())
+        .find_map(|victim| deques[victim].lock().unwrap().pop_back())
+        .map(|i| (i, true))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sorts::merge_sort;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    fn small_chunks(threads: usize) -> ParallelConfig {
+        ParallelConfig {
+            threads,
+            chunk_size: 3,
+            threshold: Some(0),
+            verbose: false,
+        }
+    }
+
+    #[test]
+    fn empty() {
+        let input: [i32; 0] = [];
+        let result = parallel_merge_sort(&input, &small_chunks(4));
+        assert_eq!(result, []);
+    }
+
+    #[test]
+    fn ten_items() {
+        let input = [3, 2, 0, 5, 8, 9, 6, 3, 2, 0];
+        let result = parallel_merge_sort(&input, &small_chunks(4));
+        assert_eq!(result, [0, 0, 2, 2, 3, 3, 5, 6, 8, 9]);
+    }
+
+    #[test]
+    fn presorted() {
+        let input = [0, 0, 2, 2, 3, 3, 5, 6, 8, 9];
+        let (result, stats) = parallel_merge_sort_with_stats(&input, &small_chunks(4));
+        assert_eq!(result, [0, 0, 2, 2, 3, 3, 5, 6, 8, 9]);
+        assert_eq!((4, 4), (stats.chunks, stats.skipped));
+    }
+
+    #[test]
+    fn skips_only_sorted_chunks() {
+        // Chunks of 3: [1, 2, 3] [6, 5, 4] [7, 8, 9] [0]
+        let input = [1, 2, 3, 6, 5, 4, 7, 8, 9, 0];
+        let (result, stats) = parallel_merge_sort_with_stats(&input, &small_chunks(2));
+        assert_eq!(result, [0, 1, 2, 3, 4, 5, 6, 7, 8, 9]);
+        assert_eq!((4, 3), (stats.chunks, stats.skipped));
+    }
+
+    #[test]
+    fn random() {
+        let mut rng = StdRng::seed_from_u64(230);
+        for &threads in &[1, 2, 3, 8] {
+            for _ in 0..50 {
+                let input: Vec<i32> = (0..rng.gen_range(0, 200)).map(|_| rng.gen_range(0, 50)).collect();
+                let config = ParallelConfig {
+                    threads,
+                    chunk_size: rng.gen_range(1, 40),
+                    threshold: Some(rng.gen_range(0, 200)),
+                    verbose: false,
+                };
+                assert_eq!(merge_sort(&input), parallel_merge_sort(&input, &config));
+            }
+        }
+    }
+
+    #[test]
+    fn worker_stats() {
+        // Chunks of 3 make 4 chunks, then 2 + 1 merge tasks.
+        let input = [3, 2, 0, 5, 8, 9, 6, 3, 2, 0];
+        let (_, stats) = parallel_merge_sort_with_stats(&input, &small_chunks(2));
+        assert_eq!(2, stats.workers.len());
+        assert_eq!(
+            4 + 2 + 1,
+            stats.workers.iter().map(|w| w.tasks).sum::<usize>()
+        );
+        assert!(stats.imbalance() >= 1.0);
+
+        // Below the threshold it's all one worker, with nothing to steal.
+        let config = ParallelConfig {
+            threshold: Some(100),
+            ..small_chunks(4)
+        };
+        let (_, stats) = parallel_merge_sort_with_stats(&input, &config);
+        assert_eq!(1, stats.workers.len());
+        assert_eq!(7, stats.workers[0].tasks);
+        assert_eq!(0, stats.workers[0].steals);
+    }
+
+    #[test]
+    fn adaptive_threshold() {
+        // With no threshold set, small inputs don't start any threads.
+        let config = ParallelConfig {
+            threads: 4,
+            chunk_size: 3,
+            threshold: None,
+            verbose: false,
+        };
+        let small = vec![0u8; 100];
+        assert_eq!(tuned().par_threshold_bytes, config.threshold_for::<u8>());
+        let (_, stats) = parallel_merge_sort_with_stats(&small, &config);
+        assert_eq!(1, stats.workers.len());
+
+        let big = vec![[0u64; 4]; tuned().par_threshold::<[u64; 4]>()];
+        let (_, stats) = parallel_merge_sort_with_stats(&big, &config);
+        assert_eq!(4, stats.workers.len());
+    }
+
+    #[test]
+    fn idle_workers_steal() {
+        // Item 0 is slow and starts in thread 0's deque, along with items
+        // 1 to 3, so the other thread should steal some of those.
+        let mut workers = Vec::new();
+        let results = parallel_map((0..8).collect(), 2, &mut workers, |i: u64| {
+            if i == 0 {
+                thread::sleep(Duration::from_millis(50));
+            }
+            i * 2
+        });
+        assert_eq!(vec![0, 2, 4, 6, 8, 10, 12, 14], results);
+        assert_eq!(8, workers[0].tasks + workers[1].tasks);
+        assert!(workers[0].steals + workers[1].steals > 0, "{:?}", workers);
+        assert!(workers[0].busy + workers[1].busy >= Duration::from_millis(50));
+    }
+
+    #[test]
+    fn imbalance() {
+        let worker = |millis| WorkerStats {
+            tasks: 1,
+            steals: 0,
+            busy: Duration::from_millis(millis),
+        };
+        let stats = |workers| ParallelSortStats {
+            chunks: 2,
+            skipped: 0,
+            workers,
+        };
+        assert_eq!(1.0, stats(vec![worker(10), worker(10)]).imbalance());
+        assert_eq!(1.5, stats(vec![worker(30), worker(10)]).imbalance());
+        assert_eq!(1.0, stats(vec![]).imbalance());
+    }
+
+    // Equal keys, but different ids, so we can tell whether equal
+    // elements come out in the same order.
+    #[derive(Debug, Clone, Copy)]
+    struct Record {
+        key: i32,
+        id: usize,
+    }
+
+    impl PartialEq for Record {
+        fn eq(&self, other: &Record) -> bool {
+            self.key == other.key
+        }
+    }
+
+    impl PartialOrd for Record {
+        fn partial_cmp(&self, other: &Record) -> Option<std::cmp::Ordering> {
+            self.key.partial_cmp(&other.key)
+        }
+    }
+
+    #[test]
+    fn same_output_as_merge_sort_on_any_number_of_threads() {
+        let mut rng = StdRng::seed_from_u64(256);
+        let input: Vec<Record> = (0..5000)
+            .map(|id| Record {
+                key: rng.gen_range(0, 100),
+                id,
+            })
+            .collect();
+        let expected: Vec<(i32, usize)> =
+            merge_sort(&input).iter().map(|r| (r.key, r.id)).collect();
+        for threads in 1..=8 {
+            for &chunk_size in &[1, 7, 64, 1000, 5000, 1 << 16] {
+                for &threshold in &[Some(0), Some(5000), None] {
+                    let config = ParallelConfig {
+                        threads,
+                        chunk_size,
+                        threshold,
+                        verbose: false,
+                    };
+                    let sorted: Vec<(i32, usize)> = parallel_merge_sort(&input, &config)
+                        .iter()
+                        .map(|r| (r.key, r.id))
+                        .collect();
+                    assert_eq!(expected, sorted, "{:?}", config);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn same_bits_as_merge_sort() {
+        // 0.0 == -0.0, but they're different bytes.
+        let mut rng = StdRng::seed_from_u64(256);
+        let input: Vec<f64> = (0..2000)
+            .map(|_| match rng.gen_range(0, 3) {
+                0 => 0.0,
+                1 => -0.0,
+                _ => rng.gen_range(-1.0, 1.0),
+            })
+            .collect();
+        let bits = |v: Vec<f64>| -> Vec<u64> { v.into_iter().map(f64::to_bits).collect() };
+        let expected = bits(merge_sort(&input));
+        for &threads in &[1, 2, 3, 8] {
+            let config = ParallelConfig {
+                threads,
+                chunk_size: 100,
+                threshold: Some(0),
+                verbose: false,
+            };
+            assert_eq!(expected, bits(parallel_merge_sort(&input, &config)));
+        }
+    }
+
+    #[test]
+    fn overrides() {
+        let lookup = |name: &str| match name {
+            THREADS_VAR => Some("3".to_string()),
+            PAR_THRESHOLD_VAR => Some(" 100000 ".to_string()),
+            _ => None,
+        };
+        let config = ParallelConfig::default().with_overrides(lookup).unwrap();
+        assert_eq!(3, config.threads);
+        assert_eq!(Some(100_000), config.threshold);
+        assert_eq!(1 << 16, config.chunk_size);
+        assert_eq!(100_000, config.threshold_for::<u8>());
+        assert_eq!(
+            "3 threads, chunks of 65536, sequential below 100000 elements",
+            config.to_string()
+        );
+
+        // Nothing set means nothing changes.
+        let config = ParallelConfig::default().with_overrides(|_| None).unwrap();
+        assert_eq!(ParallelConfig::default(), config);
+    }
+
+    #[test]
+    fn bad_overrides() {
+        for &(name, value) in &[
+            (THREADS_VAR, "0"),
+            (THREADS_VAR, "many"),
+            (PAR_THRESHOLD_VAR, "-1"),
+        ] {
+            let lookup = |n: &str| (n == name).then(|| value.to_string());
+            let result = ParallelConfig::default().with_overrides(lookup);
+            assert!(result.unwrap_err().starts_with(name));
+        }
+    }
+}
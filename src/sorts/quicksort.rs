@@ -0,0 +1,895 @@
+use crate::config::{Pivot, SortConfig};
+use crate::error::DepthExceeded;
+use crate::partition::partition_by;
+use crate::random::{RandomSource, SeededRandom};
+use crate::sorts::heapsort;
+
+// Quicksort sort is also "in place", so we modify the input array v
+// directly and do _not_ return anything. The elements of the
+// array need to traits `PartialOrd` (so they support < and ≤).
+// Also requiring the trait `Debug` means you can print the array
+// and slices of the array for debugging purposes with `{:?}`. I
+// don't do that here, but you could add some print statements if,
+// for example, you want to watch the sorting happen.
+//
+// Note that the parameter v *has* to be mutable because we're 
+// modifying it in place.
+pub fn quicksort<T: PartialOrd + std::fmt::Debug>(v: &mut [T]) {
+    // Quicksort is a recursive solution where we select a pivot
+    // value (usually just the first element) and split (in place)
+    // the array into two sections: The "front" is all < the pivot,
+    // and the "back" is all ≥ pivot. More formally, there's an
+    // index smaller where:
+    //   (All i | 0 ≤ i < smaller : v[i] < pivot) /\
+    //   (All i | smaller ≤ i < length : v[i] ≥ pivot)
+    // Now you can recursively call quicksort on the front using
+    // the slice v[0..smaller] to sort that part, and call it
+    // recursively on the slice v[smaller+1..length] to sort 
+    // the back half. (You need the +1 to ensure that both slices
+    // are smaller than the original array; without it you can
+    // end up with infinite recursion.)
+
+    let length = v.len();
+    // If the array has 0 or 1 elements it's already sorted
+    // and we'll just stop.
+    if length < 2 {
+        return;
+    }
+
+    // Now choose a pivot and do the organizing.
+    let smaller = partition_around_first(v);
+
+    // Sort all the items < pivot
+    quicksort(&mut v[0..smaller]);
+    // Sort all the items ≥ pivot, *not* including the
+    // pivot value itself. If we don't include the +1
+    // here you can end up in infinite recursions.
+    quicksort(&mut v[smaller+1..length]);
+}
+
+// We use the first element as the pivot, and partition everything after
+// it into the items < pivot and the items ≥ pivot. `partition_by`
+// returns the number of items < pivot, so swapping the pivot into that
+// position puts it right between the two groups. We return the pivot's
+// new position.
+fn partition_around_first<T: PartialOrd>(v: &mut [T]) -> usize {
+    let (pivot, rest) = v.split_first_mut().unwrap();
+    let smaller = partition_by(rest, |x| x < pivot);
+    v.swap(0, smaller);
+    smaller
+}
+
+// Quicksort with a randomly chosen pivot. Choosing the first element
+// as the pivot means that sorted input (which is pretty common!) always
+// partitions as badly as possible. With a random pivot no particular
+// input is bad; the expected time is O(N log N) for _every_ input, and
+// the O(N^2) worst case only happens if we're really unlucky with the
+// random choices.
+pub fn randomized_quicksort<T: PartialOrd + std::fmt::Debug, R: RandomSource>(
+    v: &mut [T],
+    rng: &mut R,
+) {
+    let length = v.len();
+    if length < 2 {
+        return;
+    }
+    // Swap a random element to the front and use it as the pivot.
+    v.swap(0, rng.below(length));
+    let smaller = partition_around_first(v);
+    randomized_quicksort(&mut v[0..smaller], rng);
+    randomized_quicksort(&mut v[smaller + 1..length], rng);
+}
+
+// The naive quicksort above always uses the first element as the pivot,
+// so on sorted (or reverse sorted) input every partition puts everything
+// on one side and the recursion goes N levels deep. For big enough N that
+// overflows the stack and crashes the whole program, which isn't
+// something a library should let its callers' inputs do.
+//
+// `try_quicksort` is the same algorithm, but it gives up with an
+// `Err(DepthExceeded)` if the recursion gets deeper than
+// `config.max_depth`. If that happens, v will be partly sorted, but
+// it will still hold exactly the same elements it started with. If
+// `config.max_depth` is `None` there is no limit, and this behaves just
+// like `quicksort`. (If you want a sort that can't fail this way, use
+// `heapsort`, which doesn't recurse at all.)
+//
+// It also follows the rest of `config`: how to choose the pivot, when to
+// switch to insertion sort, and who to tell about each comparison.
+pub fn try_quicksort<T: PartialOrd + std::fmt::Debug>(
+    v: &mut [T],
+    config: &SortConfig,
+) -> Result<(), DepthExceeded> {
+    let less = |a: &T, b: &T| {
+        config.observe_comparison();
+        a < b
+    };
+    // Only used for `Pivot::Random`.
+    let mut rng = SeededRandom::from_entropy();
+    try_quicksort_at_depth(v, config, &less, &mut rng, 0)
+}
+
+fn try_quicksort_at_depth<T, F: Fn(&T, &T) -> bool>(
+    v: &mut [T],
+    config: &SortConfig,
+    less: &F,
+    rng: &mut SeededRandom,
+    depth: usize,
+) -> Result<(), DepthExceeded> {
+    let length = v.len();
+    if length < 2 {
+        return Ok(());
+    }
+    if length < config.insertion_cutoff {
+        insertion_sort_by(v, less);
+        return Ok(());
+    }
+    if let Some(max_depth) = config.max_depth {
+        if depth >= max_depth {
+            return Err(DepthExceeded { max_depth });
+        }
+    }
+    let pivot = match config.pivot {
+        Pivot::First => 0,
+        Pivot::Random => rng.below(length),
+        Pivot::MedianOfThree => median_of_three(v, 0, length / 2, length - 1, less),
+    };
+    v.swap(0, pivot);
+    let (pivot, rest) = v.split_first_mut().unwrap();
+    let smaller = partition_by(rest, |x| less(x, pivot));
+    v.swap(0, smaller);
+    try_quicksort_at_depth(&mut v[0..smaller], config, less, rng, depth + 1)?;
+    try_quicksort_at_depth(&mut v[smaller + 1..length], config, less, rng, depth + 1)
+}
+
+// Introsort (Musser, 1997) is quicksort that can't go quadratic. A
+// median-of-three pivot fixes sorted input, but there are still inputs
+// (see the tests) that make it pick a bad pivot every time. Those are
+// rare, and we can tell when we've hit one, because the recursion gets
+// deeper than quicksort ever goes on a good day. So we sort with
+// quicksort, but once we're more than 2·log₂(N) levels deep we give up
+// on quicksort for whatever's left and heapsort it instead, which is
+// O(N log N) whatever the input. Most of the work is done by quicksort,
+// which is usually the fastest, and the worst case is heapsort's
+// O(N log N). Below `INTROSORT_INSERTION_CUTOFF` elements it uses
+// insertion sort, which is faster on small slices than either.
+pub fn introsort<T: PartialOrd + std::fmt::Debug>(v: &mut [T]) {
+    // 2·floor(log₂(N)), or 0 for an empty slice.
+    let max_depth = 2 * (usize::BITS - v.len().leading_zeros()).saturating_sub(1) as usize;
+    introsort_at_depth(v, max_depth);
+}
+
+pub const INTROSORT_INSERTION_CUTOFF: usize = 16;
+
+fn introsort_at_depth<T: PartialOrd + std::fmt::Debug>(v: &mut [T], depth_left: usize) {
+    let less = |a: &T, b: &T| a < b;
+    let length = v.len();
+    if length < INTROSORT_INSERTION_CUTOFF {
+        insertion_sort_by(v, &less);
+        return;
+    }
+    if depth_left == 0 {
+        heapsort(v);
+        return;
+    }
+    // The same partitioning as `try_quicksort` with `Pivot::MedianOfThree`.
+    let pivot = median_of_three(v, 0, length / 2, length - 1, &less);
+    v.swap(0, pivot);
+    let (pivot, rest) = v.split_first_mut().unwrap();
+    let smaller = partition_by(rest, |x| x < pivot);
+    v.swap(0, smaller);
+    introsort_at_depth(&mut v[0..smaller], depth_left - 1);
+    introsort_at_depth(&mut v[smaller + 1..length], depth_left - 1);
+}
+
+// Our other quicksorts split into the elements < pivot and the ones
+// ≥ pivot, so every copy of the pivot value but the pivot itself ends up
+// on the same side, to be partitioned again. With lots of copies of a
+// few values that's a disaster: all-equal input goes N levels deep,
+// like sorted input with a first-element pivot. Dijkstra's "Dutch
+// national flag" partition fixes that by splitting three ways, into the
+// elements < pivot, = pivot, and > pivot. The middle part is done, so we
+// only recurse on the outer two, and each level gets rid of every copy
+// of its pivot value. With K different values there can be at most K
+// levels, so it's O(N·K) at worst and linear on all-equal input.
+//
+// It picks its pivot with median-of-three, as `introsort` does.
+pub fn quicksort_3way<T: PartialOrd + std::fmt::Debug>(v: &mut [T]) {
+    let length = v.len();
+    if length < 2 {
+        return;
+    }
+    let pivot = median_of_three(v, 0, length / 2, length - 1, &|a: &T, b: &T| a < b);
+    v.swap(0, pivot);
+    let (pivot, rest) = v.split_first_mut().unwrap();
+    // As we go
+    //    rest[..lt] < pivot, rest[lt..i] == pivot, rest[gt..] > pivot
+    // and rest[i..gt] hasn't been looked at yet.
+    let mut lt = 0;
+    let mut i = 0;
+    let mut gt = rest.len();
+    while i < gt {
+        if rest[i] < *pivot {
+            rest.swap(lt, i);
+            lt += 1;
+            i += 1;
+        } else if rest[i] > *pivot {
+            gt -= 1;
+            rest.swap(i, gt);
+        } else {
+            i += 1;
+        }
+    }
+    // In v, the pivot is followed by the lt smaller elements, then the
+    // equal ones; swapping it with the last of the smaller ones puts it
+    // at the start of the equal ones, which are v[lt..gt + 1].
+    v.swap(0, lt);
+    quicksort_3way(&mut v[..lt]);
+    quicksort_3way(&mut v[gt + 1..]);
+}
+
+// Dual-pivot quicksort (Yaroslavskiy, 2009), the quicksort that Java's
+// `Arrays.sort` has used for primitive arrays since Java 7. Instead of
+// splitting around one pivot, we pick two, p ≤ q, and split into three
+// parts in a single pass: the elements < p, the ones from p to q, and
+// the ones > q. Three smaller parts means fewer levels of recursion,
+// and although an element may be compared with both pivots, on random
+// input it makes about 6% fewer comparisons than the lab's `quicksort`.
+// (A median-of-three pivot does a little better still. Java found it
+// faster anyway, since it makes fewer passes over the data, but on
+// random values ours only about ties with single-pivot quicksort; see
+// `benches/dual_pivot.rs`.)
+//
+// It also copes with duplicates well. If p == q the middle part is all
+// copies of the pivot and doesn't need sorting at all, so inputs with
+// only a few different values, which send single-pivot quicksort
+// quadratic, take it about linear time.
+//
+// Like Java, we take the pivots from five elements spread through the
+// slice, which avoids sorted input's worst case, and switch to
+// insertion sort on slices shorter than `DUAL_PIVOT_INSERTION_CUTOFF`.
+pub fn dual_pivot_quicksort<T: PartialOrd + std::fmt::Debug>(v: &mut [T]) {
+    let length = v.len();
+    if length < DUAL_PIVOT_INSERTION_CUTOFF {
+        insertion_sort_by(v, &|a: &T, b: &T| a < b);
+        return;
+    }
+    choose_two_pivots(v);
+    // The pivots are p = v[0] and q = v[last], and as we go
+    //   v[1..lt] < p /\ p ≤ v[lt..k] ≤ q /\ v[gt + 1..last] > q
+    // while v[k..=gt] haven't been looked at yet.
+    let last = length - 1;
+    let mut lt = 1;
+    let mut k = 1;
+    let mut gt = last - 1;
+    while k <= gt {
+        if v[k] < v[0] {
+            v.swap(k, lt);
+            lt += 1;
+        } else if v[k] > v[last] {
+            // Find something from the right end that isn't > q to swap
+            // with it, which might also need moving to the left part.
+            while k < gt && v[gt] > v[last] {
+                gt -= 1;
+            }
+            v.swap(k, gt);
+            gt -= 1;
+            if v[k] < v[0] {
+                v.swap(k, lt);
+                lt += 1;
+            }
+        }
+        k += 1;
+    }
+    // Put the pivots between the parts.
+    let p = lt - 1;
+    let q = gt + 1;
+    v.swap(0, p);
+    v.swap(last, q);
+    let pivots_equal = v[p] >= v[q];
+    dual_pivot_quicksort(&mut v[..p]);
+    if !pivots_equal {
+        dual_pivot_quicksort(&mut v[p + 1..q]);
+    }
+    dual_pivot_quicksort(&mut v[q + 1..]);
+}
+
+// Yaroslavskiy's own experiments found this about the best cutoff.
+pub const DUAL_PIVOT_INSERTION_CUTOFF: usize = 27;
+
+// Sort five elements spaced about a seventh of the way apart around the
+// middle, and move the second and fourth (which split the sample, and
+// so hopefully the slice, into thirds) to the ends as p and q.
+fn choose_two_pivots<T: PartialOrd>(v: &mut [T]) {
+    let length = v.len();
+    let seventh = length / 7;
+    let middle = length / 2;
+    let sample = [
+        middle - 2 * seventh,
+        middle - seventh,
+        middle,
+        middle + seventh,
+        middle + 2 * seventh,
+    ];
+    // Insertion sort the five where they are.
+    for i in 1..sample.len() {
+        let mut j = i;
+        while j > 0 && v[sample[j]] < v[sample[j - 1]] {
+            v.swap(sample[j], sample[j - 1]);
+            j -= 1;
+        }
+    }
+    v.swap(0, sample[1]);
+    v.swap(length - 1, sample[3]);
+}
+
+// Which of v[a], v[b], and v[c] is the median.
+fn median_of_three<T, F: Fn(&T, &T) -> bool>(
+    v: &[T],
+    a: usize,
+    b: usize,
+    c: usize,
+    less: &F,
+) -> usize {
+    let (lo, hi) = if less(&v[b], &v[a]) { (b, a) } else { (a, b) };
+    if less(&v[c], &v[lo]) {
+        lo
+    } else if less(&v[hi], &v[c]) {
+        hi
+    } else {
+        c
+    }
+}
+
+// Insertion sort (as in `insertion.rs`), but comparing with `less`.
+fn insertion_sort_by<T, F: Fn(&T, &T) -> bool>(v: &mut [T], less: &F) {
+    for i in 1..v.len() {
+        let mut j = i;
+        while j > 0 && less(&v[j], &v[j - 1]) {
+            v.swap(j - 1, j);
+            j -= 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instrument::{count_comparisons, counted};
+    use crate::random::{ScriptedRandom, SeededRandom};
+
+    mod quicksort {
+        use super::*;
+        #[test]
+        fn empty() {
+            let mut input : [i32; 0] = [];
+            quicksort(&mut input);
+            let expected : [i32; 0] = [];
+
+            assert_eq!(expected, input);
+        }
+
+        #[test]
+        fn ten_items() {
+            let mut input = [3, 2, 0, 5, 8, 9, 6, 3, 2, 0];
+            quicksort(&mut input);
+            let expected = [0, 0, 2, 2, 3, 3, 5, 6, 8, 9];
+
+            assert_eq!(expected, input);
+        }
+
+        #[test]
+        fn presorted() {
+            let mut input = [0, 0, 2, 2, 3, 3, 5, 6, 8, 9];
+            quicksort(&mut input);
+            let expected = [0, 0, 2, 2, 3, 3, 5, 6, 8, 9];
+
+            assert_eq!(expected, input);
+        }
+    }
+
+    mod randomized_quicksort {
+        use super::*;
+        #[test]
+        fn empty() {
+            let mut input : [i32; 0] = [];
+            randomized_quicksort(&mut input, &mut SeededRandom::new(217));
+            let expected : [i32; 0] = [];
+
+            assert_eq!(expected, input);
+        }
+
+        #[test]
+        fn ten_items() {
+            let mut input = [3, 2, 0, 5, 8, 9, 6, 3, 2, 0];
+            randomized_quicksort(&mut input, &mut SeededRandom::new(217));
+            let expected = [0, 0, 2, 2, 3, 3, 5, 6, 8, 9];
+
+            assert_eq!(expected, input);
+        }
+
+        #[test]
+        fn presorted() {
+            let mut input = [0, 0, 2, 2, 3, 3, 5, 6, 8, 9];
+            randomized_quicksort(&mut input, &mut SeededRandom::new(217));
+            let expected = [0, 0, 2, 2, 3, 3, 5, 6, 8, 9];
+
+            assert_eq!(expected, input);
+        }
+
+        // If the "random" choice is always the first element, we're back
+        // to naive quicksort, which on sorted input compares every pair
+        // of elements: N(N-1)/2 comparisons.
+        #[test]
+        fn always_first_pivot_is_quadratic_on_sorted_input() {
+            let input: Vec<i32> = (0..100).collect();
+            let mut v = counted(&input);
+            let mut rng = ScriptedRandom::new(vec![0]);
+            let (_, count) = count_comparisons(|| randomized_quicksort(&mut v, &mut rng));
+
+            assert_eq!(100 * 99 / 2, count);
+        }
+
+        #[test]
+        fn random_pivots_are_fast_on_sorted_input() {
+            let input: Vec<i32> = (0..1000).collect();
+            let mut v = counted(&input);
+            let mut rng = SeededRandom::new(217);
+            let (_, count) = count_comparisons(|| randomized_quicksort(&mut v, &mut rng));
+
+            assert!(count < 20_000);
+        }
+    }
+
+    mod try_quicksort {
+        use super::*;
+
+        fn limited(max_depth: usize) -> SortConfig {
+            SortConfig {
+                max_depth: Some(max_depth),
+                ..SortConfig::default()
+            }
+        }
+
+        #[test]
+        fn empty() {
+            let mut input : [i32; 0] = [];
+            assert_eq!(Ok(()), try_quicksort(&mut input, &limited(0)));
+        }
+
+        #[test]
+        fn ten_items() {
+            let mut input = [3, 2, 0, 5, 8, 9, 6, 3, 2, 0];
+            assert_eq!(Ok(()), try_quicksort(&mut input, &limited(10)));
+            let expected = [0, 0, 2, 2, 3, 3, 5, 6, 8, 9];
+
+            assert_eq!(expected, input);
+        }
+
+        #[test]
+        fn no_limit() {
+            let mut input: Vec<i32> = (0..1000).collect();
+            assert_eq!(Ok(()), try_quicksort(&mut input, &SortConfig::default()));
+        }
+
+        // Sorted input is the worst case for a first-element pivot, and
+        // would need 1000 levels of recursion here.
+        #[test]
+        fn adversarial_input() {
+            let mut input: Vec<i32> = (0..1000).collect();
+            let result = try_quicksort(&mut input, &limited(64));
+
+            assert_eq!(Err(DepthExceeded { max_depth: 64 }), result);
+            // Nothing should have been lost along the way.
+            input.sort();
+            assert_eq!((0..1000).collect::<Vec<i32>>(), input);
+        }
+
+        #[test]
+        fn pivots_and_cutoffs() {
+            let input: Vec<i32> = (0..1000).map(|i| (i * 7919) % 1000).collect();
+            for &pivot in &[Pivot::First, Pivot::Random, Pivot::MedianOfThree] {
+                for &cutoff in &[0, 1, 2, 24, 2000] {
+                    let config = SortConfig::builder()
+                        .pivot(pivot)
+                        .insertion_cutoff(cutoff)
+                        .build();
+                    let mut v = input.clone();
+                    assert_eq!(Ok(()), try_quicksort(&mut v, &config));
+                    assert_eq!((0..1000).collect::<Vec<i32>>(), v, "{:?} {}", pivot, cutoff);
+                }
+            }
+        }
+
+        // Median-of-three turns sorted input from the worst case into
+        // the best, so the same depth limit that stopped the
+        // first-element pivot above is no problem.
+        #[test]
+        fn median_of_three_on_sorted_input() {
+            let mut input: Vec<i32> = (0..1000).collect();
+            let config = SortConfig::builder()
+                .pivot(Pivot::MedianOfThree)
+                .max_depth(64)
+                .build();
+            assert_eq!(Ok(()), try_quicksort(&mut input, &config));
+        }
+
+        #[test]
+        fn observer_sees_every_comparison() {
+            use crate::config::ComparisonCounter;
+            use std::sync::Arc;
+
+            let input: Vec<i32> = (0..500).rev().collect();
+            let counter = Arc::new(ComparisonCounter::new());
+            let config = SortConfig::builder()
+                .pivot(Pivot::MedianOfThree)
+                .insertion_cutoff(16)
+                .observer(counter.clone())
+                .build();
+            let mut v = counted(&input);
+            let (_, expected) = count_comparisons(|| try_quicksort(&mut v, &config));
+            assert_eq!(expected, counter.count());
+        }
+    }
+
+    mod introsort {
+        use super::*;
+        use std::cell::RefCell;
+        use std::cmp::Ordering;
+
+        #[test]
+        fn empty() {
+            let mut input: [i32; 0] = [];
+            introsort(&mut input);
+            let expected: [i32; 0] = [];
+
+            assert_eq!(expected, input);
+        }
+
+        #[test]
+        fn ten_items() {
+            let mut input = [3, 2, 0, 5, 8, 9, 6, 3, 2, 0];
+            introsort(&mut input);
+            let expected = [0, 0, 2, 2, 3, 3, 5, 6, 8, 9];
+
+            assert_eq!(expected, input);
+        }
+
+        #[test]
+        fn presorted() {
+            let mut input = [0, 0, 2, 2, 3, 3, 5, 6, 8, 9];
+            introsort(&mut input);
+            let expected = [0, 0, 2, 2, 3, 3, 5, 6, 8, 9];
+
+            assert_eq!(expected, input);
+        }
+
+        #[test]
+        fn random_and_awkward_inputs() {
+            let mut rng = SeededRandom::new(515);
+            let random: Vec<i32> = (0..5000).map(|_| rng.range(0, 1000)).collect();
+            for input in [
+                random,
+                (0..5000).collect(),
+                (0..5000).rev().collect(),
+                vec![7; 5000],
+                (0..5000).map(|i| i % 2).collect(),
+            ]
+            .iter()
+            {
+                let mut v = input.clone();
+                introsort(&mut v);
+                let mut expected = input.clone();
+                expected.sort();
+                assert_eq!(expected, v);
+            }
+        }
+
+        // McIlroy's "A Killer Adversary for Quicksort" builds an input
+        // that makes a quicksort pick a bad pivot every time, by deciding
+        // what the values are while the sort runs. Every element starts
+        // out as "gas", bigger than any value decided so far. When two
+        // gas elements are compared, one of them (the one that's most
+        // likely the pivot) is frozen to the next smallest value, so the
+        // pivot always turns out to be about the smallest element left.
+        // The answers are consistent with the values it ends up with, so
+        // sorting those values makes the same comparisons.
+        struct Adversary {
+            values: Vec<usize>,
+            frozen: usize,
+            candidate: usize,
+        }
+
+        thread_local! {
+            static ADVERSARY: RefCell<Adversary> = const {
+                RefCell::new(Adversary {
+                    values: Vec::new(),
+                    frozen: 0,
+                    candidate: 0,
+                })
+            };
+        }
+
+        #[derive(Debug, Clone, Copy)]
+        struct Gas(usize);
+
+        impl PartialEq for Gas {
+            fn eq(&self, other: &Gas) -> bool {
+                self.partial_cmp(other) == Some(Ordering::Equal)
+            }
+        }
+
+        impl PartialOrd for Gas {
+            fn partial_cmp(&self, other: &Gas) -> Option<Ordering> {
+                ADVERSARY.with(|adversary| {
+                    let a = &mut *adversary.borrow_mut();
+                    let gas = a.values.len();
+                    let (x, y) = (self.0, other.0);
+                    if a.values[x] == gas && a.values[y] == gas {
+                        let freeze = if x == a.candidate { x } else { y };
+                        a.values[freeze] = a.frozen;
+                        a.frozen += 1;
+                    }
+                    if a.values[x] == gas {
+                        a.candidate = x;
+                    } else if a.values[y] == gas {
+                        a.candidate = y;
+                    }
+                    Some(a.values[x].cmp(&a.values[y]))
+                })
+            }
+        }
+
+        // An input on which median-of-three quicksort, with the same
+        // insertion sort cutoff as introsort, is quadratic.
+        fn median_of_three_killer(n: usize) -> Vec<i32> {
+            ADVERSARY.with(|adversary| {
+                *adversary.borrow_mut() = Adversary {
+                    values: vec![n; n],
+                    frozen: 0,
+                    candidate: 0,
+                }
+            });
+            let mut v: Vec<Gas> = (0..n).map(Gas).collect();
+            try_quicksort(&mut v, &median_of_three()).unwrap();
+            ADVERSARY.with(|adversary| {
+                adversary
+                    .borrow()
+                    .values
+                    .iter()
+                    .map(|&x| x as i32)
+                    .collect()
+            })
+        }
+
+        fn median_of_three() -> SortConfig {
+            SortConfig::builder()
+                .pivot(Pivot::MedianOfThree)
+                .insertion_cutoff(INTROSORT_INSERTION_CUTOFF)
+                .build()
+        }
+
+        #[test]
+        fn no_quadratic_worst_case() {
+            let n = 4000;
+            let killer = median_of_three_killer(n);
+
+            let mut v = counted(&killer);
+            let (_, quicksort) = count_comparisons(|| try_quicksort(&mut v, &median_of_three()));
+            let mut v = counted(&killer);
+            let (_, introsort) = count_comparisons(|| introsort(&mut v));
+            let mut expected = killer.clone();
+            expected.sort();
+            assert_eq!(expected, v.iter().map(|c| c.0).collect::<Vec<i32>>());
+
+            // Plain median-of-three quicksort is well into quadratic
+            // territory, while introsort stays within a small multiple
+            // of N log₂ N (about 48,000 here).
+            let n = n as u64;
+            assert!(quicksort > n * n / 8, "{}", quicksort);
+            assert!(introsort < 5 * 48_000, "{}", introsort);
+        }
+
+        // We can't make a killer much bigger than that in a test (the
+        // adversary takes quadratic time too), but all-equal input puts
+        // everything on one side of every partition just the same, and
+        // with 100,000 elements plain quicksort would make five billion
+        // comparisons, if it didn't overflow the stack first.
+        #[test]
+        fn all_equal_is_not_quadratic() {
+            let input = vec![1; 100_000];
+            let mut v = counted(&input);
+            let (_, comparisons) = count_comparisons(|| introsort(&mut v));
+            // N log₂ N is about 1,700,000.
+            assert!(comparisons < 3 * 1_700_000, "{}", comparisons);
+        }
+    }
+
+    mod quicksort_3way {
+        use super::*;
+
+        #[test]
+        fn empty() {
+            let mut input: [i32; 0] = [];
+            quicksort_3way(&mut input);
+            let expected: [i32; 0] = [];
+
+            assert_eq!(expected, input);
+        }
+
+        #[test]
+        fn ten_items() {
+            let mut input = [3, 2, 0, 5, 8, 9, 6, 3, 2, 0];
+            quicksort_3way(&mut input);
+            let expected = [0, 0, 2, 2, 3, 3, 5, 6, 8, 9];
+
+            assert_eq!(expected, input);
+        }
+
+        #[test]
+        fn presorted() {
+            let mut input = [0, 0, 2, 2, 3, 3, 5, 6, 8, 9];
+            quicksort_3way(&mut input);
+            let expected = [0, 0, 2, 2, 3, 3, 5, 6, 8, 9];
+
+            assert_eq!(expected, input);
+        }
+
+        #[test]
+        fn mostly_equal() {
+            // 7s everywhere, except for about one in a hundred.
+            let mut rng = SeededRandom::new(517);
+            let input: Vec<i32> = (0..100_000)
+                .map(|_| {
+                    if rng.below(100) == 0 {
+                        rng.range(0, 15)
+                    } else {
+                        7
+                    }
+                })
+                .collect();
+            let mut expected = input.clone();
+            expected.sort();
+            let mut v = counted(&input);
+            let (_, comparisons) = count_comparisons(|| quicksort_3way(&mut v));
+            assert_eq!(expected, v.iter().map(|c| c.0).collect::<Vec<i32>>());
+            // Two comparisons per element per level, and getting rid of
+            // the 7s takes just one level.
+            assert!(comparisons < 3 * 100_000, "{}", comparisons);
+
+            for &(len, values) in &[(2, 1), (3, 2), (1000, 1), (1000, 2), (1000, 3)] {
+                let input: Vec<i32> = (0..len).map(|_| rng.range(0, values)).collect();
+                let mut v = input.clone();
+                quicksort_3way(&mut v);
+                let mut expected = input;
+                expected.sort();
+                assert_eq!(expected, v);
+            }
+        }
+
+        // One level: three comparisons to choose the pivot, then < and >
+        // for every other element, and nothing left to recurse on.
+        #[test]
+        fn all_equal_is_linear() {
+            let mut v = counted(&vec![5; 10_000]);
+            let (_, comparisons) = count_comparisons(|| quicksort_3way(&mut v));
+            assert_eq!(3 + 2 * 9_999, comparisons);
+        }
+
+        // Two-way partitioning sends every copy of the pivot value to the
+        // same side, so with ten values among 10,000 it's quadratic.
+        #[test]
+        fn few_unique_beats_two_way() {
+            let mut rng = SeededRandom::new(517);
+            let input: Vec<i32> = (0..10_000).map(|_| rng.range(0, 10)).collect();
+            let mut v = counted(&input);
+            let (_, three_way) = count_comparisons(|| quicksort_3way(&mut v));
+            let config = SortConfig::builder().pivot(Pivot::MedianOfThree).build();
+            let mut v = counted(&input);
+            let (_, two_way) = count_comparisons(|| try_quicksort(&mut v, &config));
+            assert!(three_way < 20 * 10_000, "{}", three_way);
+            assert!(two_way > 50 * three_way, "{} {}", two_way, three_way);
+        }
+
+        #[test]
+        fn random_and_awkward_inputs() {
+            let mut rng = SeededRandom::new(517);
+            let random: Vec<i32> = (0..5000).map(|_| rng.range(0, 1000)).collect();
+            for input in [
+                random,
+                (0..5000).collect(),
+                (0..5000).rev().collect(),
+                (0..5000).map(|i| i % 2).collect(),
+            ]
+            .iter()
+            {
+                let mut v = input.clone();
+                quicksort_3way(&mut v);
+                let mut expected = input.clone();
+                expected.sort();
+                assert_eq!(expected, v);
+            }
+        }
+    }
+
+    mod dual_pivot_quicksort {
+        use super::*;
+
+        #[test]
+        fn empty() {
+            let mut input: [i32; 0] = [];
+            dual_pivot_quicksort(&mut input);
+            let expected: [i32; 0] = [];
+
+            assert_eq!(expected, input);
+        }
+
+        #[test]
+        fn ten_items() {
+            let mut input = [3, 2, 0, 5, 8, 9, 6, 3, 2, 0];
+            dual_pivot_quicksort(&mut input);
+            let expected = [0, 0, 2, 2, 3, 3, 5, 6, 8, 9];
+
+            assert_eq!(expected, input);
+        }
+
+        #[test]
+        fn presorted() {
+            let mut input = [0, 0, 2, 2, 3, 3, 5, 6, 8, 9];
+            dual_pivot_quicksort(&mut input);
+            let expected = [0, 0, 2, 2, 3, 3, 5, 6, 8, 9];
+
+            assert_eq!(expected, input);
+        }
+
+        #[test]
+        fn random_and_awkward_inputs() {
+            let mut rng = SeededRandom::new(516);
+            let random: Vec<i32> = (0..5000).map(|_| rng.range(0, 1000)).collect();
+            for input in [
+                random,
+                (0..5000).collect(),
+                (0..5000).rev().collect(),
+                vec![7; 5000],
+                (0..5000).map(|i| i % 2).collect(),
+                (0..5000).map(|i| if i < 2500 { i } else { 5000 - i }).collect(),
+            ]
+            .iter()
+            {
+                for len in [0, 26, 27, 28, 100, 5000].iter() {
+                    let mut v = input[..*len].to_vec();
+                    dual_pivot_quicksort(&mut v);
+                    let mut expected = input[..*len].to_vec();
+                    expected.sort();
+                    assert_eq!(expected, v);
+                }
+            }
+        }
+
+        #[test]
+        fn fewer_comparisons_than_first_element_pivot() {
+            let mut rng = SeededRandom::new(516);
+            let input: Vec<i32> = (0..100_000).map(|_| rng.range(0, 1_000_000_000)).collect();
+            let mut v = counted(&input);
+            let (_, dual) = count_comparisons(|| dual_pivot_quicksort(&mut v));
+            let mut v = counted(&input);
+            let (_, single) = count_comparisons(|| quicksort(&mut v));
+            assert!(dual < single, "{} {}", dual, single);
+        }
+
+        // With ten different values among 100,000, single-pivot
+        // quicksort makes about 500 million comparisons.
+        #[test]
+        fn duplicates_are_about_linear() {
+            let mut rng = SeededRandom::new(516);
+            let few: Vec<i32> = (0..100_000).map(|_| rng.range(0, 10)).collect();
+            let mut v = counted(&few);
+            let (_, comparisons) = count_comparisons(|| dual_pivot_quicksort(&mut v));
+            assert!(comparisons < 10 * 100_000, "{}", comparisons);
+
+            let mut v = counted(&vec![1; 100_000]);
+            let (_, comparisons) = count_comparisons(|| dual_pivot_quicksort(&mut v));
+            assert!(comparisons < 3 * 100_000, "{}", comparisons);
+        }
+    }
+}
@@ -0,0 +1,141 @@
+// LSD (least significant digit first) radix sort. Counting sort needs a
+// count for every possible value, which is fine for bytes but hopeless
+// for `u32`s, let alone `u64`s. Radix sort gets around that by sorting
+// one byte at a time, each with a counting sort of just 256 counts:
+// first by the lowest byte, then by the next one up, and so on to the
+// top byte. Because each pass is stable, after sorting by byte k the
+// elements with the same byte k are still in the order the earlier
+// passes put them in, i.e. sorted by bytes 0..k, so after the last pass
+// they're sorted by the whole key. (`passes.rs` does the same with
+// several sort keys.)
+//
+// That's 4 passes over the data for a `u32` and 8 for a `u64`, whatever
+// N is, so it's O(N), and on big arrays it leaves every comparison sort
+// behind. Each pass copies the elements from one buffer to the other,
+// so it needs a scratch buffer as big as the input. When every element
+// has the same byte in some position (the top bytes of small numbers,
+// say) the pass wouldn't change anything, so we skip it.
+
+pub trait RadixKey: Copy + Default {
+    // How many bytes, and so how many passes, the key has.
+    const BYTES: usize;
+    // Byte `i` of the key, counting from the least significant.
+    fn byte(self, i: usize) -> usize;
+}
+
+macro_rules! radix_key {
+    ($($t:ty),*) => {
+        $(
+            impl RadixKey for $t {
+                const BYTES: usize = std::mem::size_of::<$t>();
+                fn byte(self, i: usize) -> usize {
+                    ((self >> (8 * i)) & 0xff) as usize
+                }
+            }
+        )*
+    };
+}
+
+radix_key!(u16, u32, u64);
+
+pub fn radix_sort_lsd<T: RadixKey>(v: &mut [T]) {
+    let mut scratch = vec![T::default(); v.len()];
+    // Which buffer holds the elements after the passes so far.
+    let mut in_scratch = false;
+    for byte in 0..T::BYTES {
+        let moved = if in_scratch {
+            counting_pass(&scratch, v, byte)
+        } else {
+            counting_pass(v, &mut scratch, byte)
+        };
+        if moved {
+            in_scratch = !in_scratch;
+        }
+    }
+    if in_scratch {
+        v.copy_from_slice(&scratch);
+    }
+}
+
+// Stably sort `from` by byte `byte` into `to`, and say whether we did;
+// if every element has the same byte there, we leave them where they
+// are.
+fn counting_pass<T: RadixKey>(from: &[T], to: &mut [T], byte: usize) -> bool {
+    let mut counts = [0usize; 256];
+    for &x in from {
+        counts[x.byte(byte)] += 1;
+    }
+    if counts.contains(&from.len()) {
+        return false;
+    }
+    // Turn the counts into where each byte value's elements start.
+    let mut next = [0usize; 256];
+    let mut start = 0;
+    for (value, &count) in counts.iter().enumerate() {
+        next[value] = start;
+        start += count;
+    }
+    for &x in from {
+        let b = x.byte(byte);
+        to[next[b]] = x;
+        next[b] += 1;
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::random::{RandomSource, SeededRandom};
+
+    #[test]
+    fn empty() {
+        let mut input: [u32; 0] = [];
+        radix_sort_lsd(&mut input);
+        assert_eq!(input, []);
+    }
+
+    #[test]
+    fn ten_items() {
+        let mut input: [u32; 10] = [3, 2, 0, 5, 8, 9, 6, 3, 2, 0];
+        radix_sort_lsd(&mut input);
+        assert_eq!(input, [0, 0, 2, 2, 3, 3, 5, 6, 8, 9]);
+    }
+
+    #[test]
+    fn zero_and_max() {
+        let mut input = [u64::MAX, 0, 1 << 63, u64::MAX, 0, 255, 256];
+        radix_sort_lsd(&mut input);
+        assert_eq!(input, [0, 0, 255, 256, 1 << 63, u64::MAX, u64::MAX]);
+        let mut input = [u32::MAX, 0, u32::MAX - 1];
+        radix_sort_lsd(&mut input);
+        assert_eq!(input, [0, u32::MAX - 1, u32::MAX]);
+        let mut input = [0u16; 5];
+        radix_sort_lsd(&mut input);
+        assert_eq!(input, [0; 5]);
+    }
+
+    #[test]
+    fn skipped_passes() {
+        // Only the low byte differs, so there's only one pass to make,
+        // and the result ends up in the scratch buffer.
+        let mut input: Vec<u64> = (0..200).rev().collect();
+        radix_sort_lsd(&mut input);
+        assert_eq!((0..200).collect::<Vec<u64>>(), input);
+    }
+
+    #[test]
+    fn millions() {
+        let mut rng = SeededRandom::new(512);
+        let mut v: Vec<u64> = (0..2_000_000).map(|_| rng.next_u64()).collect();
+        let mut expected = v.clone();
+        expected.sort_unstable();
+        radix_sort_lsd(&mut v);
+        assert_eq!(expected, v);
+        let mut v: Vec<u32> = (0..2_000_000).map(|_| rng.next_u64() as u32).collect();
+        let mut expected = v.clone();
+        expected.sort_unstable();
+        radix_sort_lsd(&mut v);
+        assert_eq!(expected, v);
+    }
+}
@@ -0,0 +1,101 @@
+use crate::merge::merge;
+use crate::search::gallop_back_by;
+use crate::sorts::merge_sort;
+use std::cmp::Ordering;
+
+// The length of the longest prefix of v that's already sorted.
+pub fn sorted_prefix_len<T: PartialOrd>(v: &[T]) -> usize {
+    match v.windows(2).position(|w| w[0] > w[1]) {
+        Some(i) => i + 1,
+        None => v.len(),
+    }
+}
+
+// The length of the longest suffix of v that's already sorted.
+pub fn sorted_suffix_len<T: PartialOrd>(v: &[T]) -> usize {
+    match v.windows(2).rev().position(|w| w[0] > w[1]) {
+        Some(i) => i + 1,
+        None => v.len(),
+    }
+}
+
+// A common pattern is to keep a vector sorted, push a few new items onto
+// the end, and then need it sorted again. Sorting the whole thing from
+// scratch wastes all the work that went into the sorted prefix. Instead
+// we sort just the unsorted tail, find where the smallest new item goes
+// in the prefix (galloping back from the end, since new items are often
+// large), and merge the tail with only the part of the prefix from there
+// on. With a tail of length t that's O(t log t + log N) comparisons and
+// O(N) moves at worst, and much less when the new items are mostly
+// large.
+//
+// Like `merge_sort` this needs `Copy` for the elements, and it's stable.
+pub fn resort_tail<T: PartialOrd + Copy + std::fmt::Debug>(v: &mut [T]) {
+    let k = sorted_prefix_len(v);
+    if k == v.len() {
+        return;
+    }
+    let tail = merge_sort(&v[k..]);
+    // Everything in v[..start] is ≤ every new item, so stays put.
+    let start = gallop_back_by(&v[..k], |x| tail[0].partial_cmp(x) != Some(Ordering::Less));
+    let merged = merge(v[start..k].to_vec(), tail);
+    v[start..].copy_from_slice(&merged);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    #[test]
+    fn prefix_and_suffix() {
+        let input = [0, 2, 2, 5, 3, 1, 4, 8, 9];
+        assert_eq!(4, sorted_prefix_len(&input));
+        assert_eq!(4, sorted_suffix_len(&input));
+        assert_eq!(0, sorted_prefix_len::<i32>(&[]));
+        assert_eq!(3, sorted_suffix_len(&[1, 2, 3]));
+        assert_eq!(1, sorted_prefix_len(&[3, 2, 1]));
+        assert_eq!(1, sorted_suffix_len(&[3, 2, 1]));
+    }
+
+    mod resort_tail {
+        use super::*;
+
+        #[test]
+        fn empty() {
+            let mut input: [i32; 0] = [];
+            resort_tail(&mut input);
+            assert_eq!(input, []);
+        }
+
+        #[test]
+        fn ten_items() {
+            let mut input = [3, 2, 0, 5, 8, 9, 6, 3, 2, 0];
+            resort_tail(&mut input);
+            assert_eq!(input, [0, 0, 2, 2, 3, 3, 5, 6, 8, 9]);
+        }
+
+        #[test]
+        fn presorted() {
+            let mut input = [0, 0, 2, 2, 3, 3, 5, 6, 8, 9];
+            resort_tail(&mut input);
+            assert_eq!(input, [0, 0, 2, 2, 3, 3, 5, 6, 8, 9]);
+        }
+
+        #[test]
+        fn appended() {
+            let mut rng = StdRng::seed_from_u64(228);
+            for _ in 0..200 {
+                let mut v: Vec<i32> = (0..rng.gen_range(0, 40)).map(|_| rng.gen_range(0, 50)).collect();
+                v.sort();
+                for _ in 0..rng.gen_range(0, 5) {
+                    v.push(rng.gen_range(0, 60));
+                }
+                let mut expected = v.clone();
+                expected.sort();
+                resort_tail(&mut v);
+                assert_eq!(expected, v);
+            }
+        }
+    }
+}
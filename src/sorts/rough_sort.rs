@@ -0,0 +1,169 @@
+use crate::partition::partition_by;
+use crate::sorts::heapsort;
+
+// Sorting "roughly": every element ends up within εN places of where it
+// would be if we sorted properly. That's enough for lots of jobs (a
+// histogram, a rough percentile, splitting work into about-equal
+// batches, a progress bar ordered "mostly by size") and it's cheaper.
+//
+// It's quicksort that stops early. Once quicksort has partitioned a
+// range, everything in the range belongs in the range; it's only the
+// order within it that's still wrong. So if we stop partitioning ranges
+// once they're at most εN long, every element is in the right range of
+// at most εN places, and can be at most εN - 1 places from where it
+// belongs. Quicksort does about log2(N) levels of partitioning; this
+// skips the last log2(εN) of them, so with ε = 1% on a million items
+// it's roughly 7 levels out of 20.
+//
+// We partition around the middle element rather than the first, so that
+// sorted input isn't quadratic, and we put the elements equal to the
+// pivot in a group of their own, so that lots of duplicates aren't
+// either. And we loop on the bigger side rather than recursing, which
+// keeps the recursion O(log N) deep whatever happens.
+//
+// ε is clamped to 0..=1 (and NaN is treated as 0); ε = 0 is a full sort.
+pub fn rough_sort<T: PartialOrd>(v: &mut [T], epsilon: f64) {
+    let block = rough_block(v.len(), epsilon);
+    rough_sort_blocks(v, block);
+}
+
+// Ranges at most this long are left as they are.
+fn rough_block(length: usize, epsilon: f64) -> usize {
+    let epsilon = if epsilon.is_nan() {
+        0.0
+    } else {
+        epsilon.clamp(0.0, 1.0)
+    };
+    ((epsilon * length as f64) as usize).max(1)
+}
+
+fn rough_sort_blocks<T: PartialOrd>(mut v: &mut [T], block: usize) {
+    while v.len() > block {
+        let (smaller, equal) = partition_around_middle(v);
+        let (front, rest) = v.split_at_mut(smaller);
+        let back = &mut rest[equal..];
+        if front.len() < back.len() {
+            rough_sort_blocks(front, block);
+            v = back;
+        } else {
+            rough_sort_blocks(back, block);
+            v = front;
+        }
+    }
+}
+
+// Partition `v` into the elements < the middle one, then the ones equal
+// to it, then the ones > it, and return how many there are of the first
+// two groups.
+fn partition_around_middle<T: PartialOrd>(v: &mut [T]) -> (usize, usize) {
+    let middle = v.len() / 2;
+    v.swap(0, middle);
+    let (pivot, rest) = v.split_first_mut().unwrap();
+    let pivot = &*pivot;
+    let smaller = partition_by(rest, |x| x < pivot);
+    // Everything after the smaller ones is ≥ the pivot, so ≤ means =.
+    let equal = partition_by(&mut rest[smaller..], |x| x <= pivot);
+    v.swap(0, smaller);
+    // The pivot is one of the equal elements too.
+    (smaller, equal + 1)
+}
+
+// The farthest any element of `v` is from where it'd be if `v` were
+// sorted, which is what `rough_sort` promises to keep at most εN. An
+// element with duplicates could be anywhere among them, so we measure
+// to the nearest end of that stretch.
+pub fn max_displacement<T: PartialOrd + Clone + std::fmt::Debug>(v: &[T]) -> usize {
+    let mut sorted = v.to_vec();
+    heapsort(&mut sorted);
+    let mut worst = 0;
+    for (i, x) in v.iter().enumerate() {
+        // Where the elements equal to x start and end in sorted order.
+        let first = sorted.partition_point(|y| y < x);
+        let last = sorted.partition_point(|y| y <= x) - 1;
+        let displacement = if i < first {
+            first - i
+        } else {
+            i.saturating_sub(last)
+        };
+        worst = worst.max(displacement);
+    }
+    worst
+}
+
+// Whether every element of `v` is within εN of its sorted position.
+pub fn is_roughly_sorted<T: PartialOrd + Clone + std::fmt::Debug>(v: &[T], epsilon: f64) -> bool {
+    max_displacement(v) < rough_block(v.len(), epsilon)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instrument::{count_comparisons, counted};
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    #[test]
+    fn ten_items() {
+        let mut v = vec![3, 2, 0, 5, 8, 9, 6, 3, 2, 0];
+        rough_sort(&mut v, 0.0);
+        assert_eq!(vec![0, 0, 2, 2, 3, 3, 5, 6, 8, 9], v);
+        let mut v = vec![3, 2, 0, 5, 8, 9, 6, 3, 2, 0];
+        rough_sort(&mut v, 0.3);
+        assert!(max_displacement(&v) < 3, "{:?}", v);
+    }
+
+    #[test]
+    fn within_epsilon() {
+        let mut rng = StdRng::seed_from_u64(283);
+        for &epsilon in &[0.0, 0.001, 0.01, 0.1, 0.5, 1.0] {
+            for &values in &[10, 1_000_000] {
+                let mut v: Vec<i32> = (0..10_000).map(|_| rng.gen_range(0, values)).collect();
+                rough_sort(&mut v, epsilon);
+                assert!(is_roughly_sorted(&v, epsilon), "ε = {}", epsilon);
+                assert!(max_displacement(&v) as f64 <= epsilon * 10_000.0);
+            }
+        }
+    }
+
+    #[test]
+    fn awkward_inputs() {
+        for input in [
+            (0..10_000).collect::<Vec<i32>>(),
+            (0..10_000).rev().collect(),
+            vec![7; 10_000],
+            vec![],
+            vec![1],
+        ]
+        .iter()
+        {
+            let mut v = input.clone();
+            rough_sort(&mut v, 0.01);
+            assert!(is_roughly_sorted(&v, 0.01));
+        }
+    }
+
+    #[test]
+    fn cheaper_than_sorting() {
+        let mut rng = StdRng::seed_from_u64(283);
+        let v: Vec<i32> = (0..100_000).map(|_| rng.gen()).collect();
+        let comparisons = |epsilon| {
+            let mut v = counted(&v);
+            count_comparisons(|| rough_sort(&mut v, epsilon)).1
+        };
+        let full = comparisons(0.0);
+        let rough = comparisons(0.01);
+        // Blocks of 1,000 out of 100,000 means only about 7 of the 17
+        // levels of partitioning.
+        assert!(rough * 3 < full * 2, "{} vs {}", rough, full);
+    }
+
+    #[test]
+    fn displacement() {
+        assert_eq!(0, max_displacement(&[1, 2, 2, 3]));
+        assert_eq!(3, max_displacement(&[4, 1, 2, 3]));
+        // The 2s could be either way round.
+        assert_eq!(1, max_displacement(&[2, 1, 2]));
+        assert_eq!(0, max_displacement::<i32>(&[]));
+        assert!(!is_roughly_sorted(&[4, 1, 2, 3], 0.5));
+        assert!(is_roughly_sorted(&[4, 1, 2, 3], 1.0));
+    }
+}
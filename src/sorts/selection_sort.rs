@@ -0,0 +1,75 @@
+// Selection sort is the other classic O(N^2) sort, and a nice contrast
+// with insertion sort. Insertion sort takes the next element and finds
+// where it goes among the sorted ones; selection sort finds the element
+// that goes next (the smallest one left) and puts it there. After k
+// passes:
+//   v[0..k] is sorted /\
+//   (All i, j | 0 ≤ i < k ≤ j < length : v[i] ≤ v[j])
+//
+// Finding the smallest of the N - k elements left takes N - k - 1
+// comparisons whatever order they're in, so selection sort always makes
+// N(N-1)/2 comparisons, even on sorted input, where insertion sort
+// only needs N - 1. But it makes at most N - 1 swaps, one per pass,
+// where insertion sort makes one swap per inversion, about N^2/4 on
+// random input. So when moving elements is much more expensive than
+// comparing them, selection sort can come out ahead.
+//
+// The long-distance swaps make it unstable: [2a, 2b, 1] becomes
+// [1, 2b, 2a].
+pub fn selection_sort<T: PartialOrd + std::fmt::Debug>(v: &mut [T]) {
+    let length = v.len();
+    for k in 0..length {
+        // Find the smallest of v[k..], the first one if there are ties.
+        let mut smallest = k;
+        for i in k + 1..length {
+            if v[i] < v[smallest] {
+                smallest = i;
+            }
+        }
+        if smallest != k {
+            v.swap(k, smallest);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instrument::{count_comparisons, counted};
+
+    #[test]
+    fn empty() {
+        let mut input: [i32; 0] = [];
+        selection_sort(&mut input);
+        let expected: [i32; 0] = [];
+
+        assert_eq!(expected, input);
+    }
+
+    #[test]
+    fn ten_items() {
+        let mut input = [3, 2, 0, 5, 8, 9, 6, 3, 2, 0];
+        selection_sort(&mut input);
+        let expected = [0, 0, 2, 2, 3, 3, 5, 6, 8, 9];
+
+        assert_eq!(expected, input);
+    }
+
+    #[test]
+    fn presorted() {
+        let mut input = [0, 0, 2, 2, 3, 3, 5, 6, 8, 9];
+        selection_sort(&mut input);
+        let expected = [0, 0, 2, 2, 3, 3, 5, 6, 8, 9];
+
+        assert_eq!(expected, input);
+    }
+
+    #[test]
+    fn always_n_squared_over_two_comparisons() {
+        for input in [(0..100).collect::<Vec<i32>>(), (0..100).rev().collect()].iter() {
+            let mut v = counted(input);
+            let ((), comparisons) = count_comparisons(|| selection_sort(&mut v));
+            assert_eq!(100 * 99 / 2, comparisons);
+        }
+    }
+}
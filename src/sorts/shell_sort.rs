@@ -0,0 +1,168 @@
+// Shell sort (Shell, 1959) is insertion sort with a head start. Insertion
+// sort is slow on random input because each element only moves one
+// place per comparison, and on average has N/4 places to go. So first we
+// insertion sort the elements `gap` apart (every gap-th element, starting
+// from each of 0..gap) for some big gap, which moves elements a long way
+// in a few steps, then do it again with a smaller gap, and so on down to
+// a gap of 1, which is plain insertion sort, but on an array that's
+// nearly sorted by then, where insertion sort is at its best.
+//
+// How fast that is depends entirely on the gaps, and nobody knows the
+// best sequence. Shell's own (N/2, N/4, ..., 1) is O(N²) in the worst
+// case, because elements at odd and even positions never get compared
+// until the very end; Knuth's (1, 4, 13, 40, ..., each 3h + 1) is
+// O(N^1.5); and Ciura's (1, 4, 10, 23, 57, 132, 301, 701, 1750, found by
+// experiment, then ×2.25 each time) is the best known in practice,
+// though nobody has proved its complexity. `benches/shell_sort.rs`
+// shows the difference.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GapSequence {
+    Shell,
+    Knuth,
+    Ciura,
+}
+
+// Ciura's experimentally found gaps; the bigger ones are these times 2.25.
+const CIURA: [usize; 9] = [1, 4, 10, 23, 57, 132, 301, 701, 1750];
+
+impl GapSequence {
+    // The gaps to use on `length` elements, largest first and ending in
+    // 1 (unless there's nothing to sort).
+    pub fn gaps(self, length: usize) -> Vec<usize> {
+        // Each sequence is built smallest first.
+        let mut gaps = Vec::new();
+        match self {
+            GapSequence::Shell => {
+                let mut gap = length / 2;
+                while gap > 0 {
+                    gaps.push(gap);
+                    gap /= 2;
+                }
+                gaps.reverse();
+            }
+            GapSequence::Knuth => {
+                // Knuth suggests stopping below N/3: a gap much bigger
+                // than that sorts runs of only two or three elements.
+                let mut gap = 1;
+                while gap == 1 || gap < length / 3 {
+                    gaps.push(gap);
+                    gap = 3 * gap + 1;
+                }
+            }
+            GapSequence::Ciura => {
+                let mut gap = 1;
+                let mut i = 0;
+                while gap == 1 || gap < length {
+                    gaps.push(gap);
+                    i += 1;
+                    gap = match CIURA.get(i) {
+                        Some(&next) => next,
+                        None => (gap as f64 * 2.25) as usize,
+                    };
+                }
+            }
+        }
+        if length < 2 {
+            gaps.clear();
+        }
+        gaps.reverse();
+        gaps
+    }
+}
+
+pub fn shell_sort<T: PartialOrd>(v: &mut [T], sequence: GapSequence) {
+    for gap in sequence.gaps(v.len()) {
+        // Insertion sort, but stepping by `gap` rather than 1. Doing
+        // every element in turn, rather than one gap-th slice after
+        // another, sorts all the slices at once.
+        for i in gap..v.len() {
+            let mut j = i;
+            while j >= gap && v[j - gap] > v[j] {
+                v.swap(j - gap, j);
+                j -= gap;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instrument::{count_comparisons, counted};
+    use crate::random::{RandomSource, SeededRandom};
+
+    const SEQUENCES: [GapSequence; 3] =
+        [GapSequence::Shell, GapSequence::Knuth, GapSequence::Ciura];
+
+    #[test]
+    fn gaps() {
+        assert_eq!(vec![50, 25, 12, 6, 3, 1], GapSequence::Shell.gaps(100));
+        assert_eq!(vec![13, 4, 1], GapSequence::Knuth.gaps(100));
+        assert_eq!(vec![57, 23, 10, 4, 1], GapSequence::Ciura.gaps(100));
+        // Past the end of Ciura's list.
+        assert_eq!(
+            vec![3937, 1750, 701],
+            GapSequence::Ciura.gaps(5000)[..3].to_vec()
+        );
+        for &sequence in &SEQUENCES {
+            assert_eq!(vec![1], sequence.gaps(2));
+            assert!(sequence.gaps(1).is_empty());
+        }
+    }
+
+    #[test]
+    fn ten_items() {
+        for &sequence in &SEQUENCES {
+            let mut v = vec![3, 2, 0, 5, 8, 9, 6, 3, 2, 0];
+            shell_sort(&mut v, sequence);
+            assert_eq!(vec![0, 0, 2, 2, 3, 3, 5, 6, 8, 9], v, "{:?}", sequence);
+        }
+    }
+
+    #[test]
+    fn random_and_awkward_inputs() {
+        let mut rng = SeededRandom::new(509);
+        let random: Vec<i32> = (0..5000).map(|_| rng.range(0, 1000)).collect();
+        for input in [
+            random,
+            (0..5000).collect(),
+            (0..5000).rev().collect(),
+            vec![7; 5000],
+            vec![],
+        ]
+        .iter()
+        {
+            for &sequence in &SEQUENCES {
+                let mut v = input.clone();
+                shell_sort(&mut v, sequence);
+                assert!(v.windows(2).all(|w| w[0] <= w[1]), "{:?}", sequence);
+            }
+        }
+    }
+
+    #[test]
+    fn better_gaps_fewer_comparisons() {
+        // Shell's gaps never compare odd positions with even ones until
+        // the last pass, so with the small values at the odd positions
+        // and the big ones at the even ones, that last pass is a
+        // quadratic insertion sort.
+        let n = 4096;
+        let input: Vec<i32> = (0..n)
+            .map(|i| if i % 2 == 1 { i / 2 } else { n / 2 + i / 2 })
+            .collect();
+        let comparisons = |sequence| {
+            let mut v = counted(&input);
+            count_comparisons(|| shell_sort(&mut v, sequence)).1
+        };
+        let shell = comparisons(GapSequence::Shell);
+        let knuth = comparisons(GapSequence::Knuth);
+        let ciura = comparisons(GapSequence::Ciura);
+        assert!(shell > (n * n / 16) as u64, "{}", shell);
+        assert!(
+            knuth * 10 < shell && ciura * 10 < shell,
+            "{} {}",
+            knuth,
+            ciura
+        );
+    }
+}
@@ -0,0 +1,83 @@
+use crate::merge::merge;
+
+// Strand sort repeatedly pulls an increasing subsequence (a "strand")
+// out of the input and merges it into the result. To pull out a strand
+// we take the first remaining element, then walk through the rest taking
+// every element that's at least as large as the last one we took.
+// Everything we don't take is left for the next pass.
+//
+// If the input has k strands this is O(kN): each pass is O(N) to pull
+// out the strand plus O(N) to merge it in. On random input k is about
+// √N, so it's O(N^1.5), but on nearly sorted input there are only a few
+// strands and strand sort is close to O(N). On reverse sorted input every
+// strand is a single element, and it's O(N^2).
+//
+// Like `merge_sort` this returns a new `Vec` and needs `Copy` for the
+// elements. It's also stable, since `merge` prefers elements from the
+// result so far (i.e., from earlier strands) when there are ties, and an
+// element only ends up in a later strand than an equal element to its
+// left.
+pub fn strand_sort<T: PartialOrd + Copy + std::fmt::Debug>(v: &[T]) -> Vec<T> {
+    let mut result = Vec::new();
+    let mut remaining = v.to_vec();
+    while !remaining.is_empty() {
+        let mut strand = vec![remaining[0]];
+        let mut rest = Vec::new();
+        for &x in &remaining[1..] {
+            // strand isn't empty, so last() always succeeds.
+            if x >= *strand.last().unwrap() {
+                strand.push(x);
+            } else {
+                rest.push(x);
+            }
+        }
+        result = merge(result, strand);
+        remaining = rest;
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    #[test]
+    fn empty() {
+        let input: [i32; 0] = [];
+        let result = strand_sort(&input);
+        let expected: Vec<i32> = Vec::new();
+
+        assert_eq!(expected, result);
+    }
+
+    #[test]
+    fn ten_items() {
+        let input = [3, 2, 0, 5, 8, 9, 6, 3, 2, 0];
+        let result = strand_sort(&input);
+        let expected = [0, 0, 2, 2, 3, 3, 5, 6, 8, 9].to_vec();
+
+        assert_eq!(expected, result);
+    }
+
+    #[test]
+    fn presorted() {
+        let input = [0, 0, 2, 2, 3, 3, 5, 6, 8, 9];
+        let result = strand_sort(&input);
+        let expected = [0, 0, 2, 2, 3, 3, 5, 6, 8, 9].to_vec();
+
+        assert_eq!(expected, result);
+    }
+
+    #[test]
+    fn random_inputs() {
+        let mut rng = StdRng::seed_from_u64(213);
+        for len in 0..100 {
+            let input: Vec<i32> = (0..len).map(|_| rng.gen_range(0, 20)).collect();
+            let mut expected = input.clone();
+            expected.sort();
+
+            assert_eq!(expected, strand_sort(&input));
+        }
+    }
+}
@@ -0,0 +1,184 @@
+// A sorting stage for pipelines built out of threads and channels:
+// items come in on one channel, and go out, sorted, on another.
+//
+// Of course nothing can come out until everything has gone in (the last
+// item might be the smallest), so the stage has to hold on to the whole
+// stream. To keep that within a memory budget we do an _external_ sort:
+// whenever the buffered items fill the budget we sort them and "spill"
+// them to a temporary file as a sorted run. At the end we merge all the
+// runs together with a k-way merge, which only needs to hold one item
+// per run in memory at a time.
+
+use crate::merge::KwayMerge;
+use crate::sorts::merge_sort;
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{Receiver, Sender};
+
+// Types that can be written to (and read back from) a spill file.
+pub trait Spill: Sized {
+    fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()>;
+
+    // `Ok(None)` means we've reached the end of the file.
+    fn read_from<R: Read>(reader: &mut R) -> io::Result<Option<Self>>;
+}
+
+macro_rules! spill_as_bytes {
+    ($($t:ty),*) => {
+        $(impl Spill for $t {
+            fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+                writer.write_all(&self.to_le_bytes())
+            }
+
+            fn read_from<R: Read>(reader: &mut R) -> io::Result<Option<$t>> {
+                let mut bytes = [0; std::mem::size_of::<$t>()];
+                match reader.read_exact(&mut bytes) {
+                    Ok(()) => Ok(Some(<$t>::from_le_bytes(bytes))),
+                    Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => Ok(None),
+                    Err(error) => Err(error),
+                }
+            }
+        })*
+    };
+}
+
+spill_as_bytes!(i8, i16, i32, i64, i128, u8, u16, u32, u64, u128, f32, f64);
+
+// Read everything from `rx`, and send it all to `tx` in sorted order,
+// using at most about `memory_budget` bytes for buffered items. The
+// sort is stable. If whoever's on the other end of `tx` hangs up we
+// just stop; the only errors are from reading and writing spill files.
+pub fn sort_stage<T: Spill + PartialOrd + Copy + std::fmt::Debug>(
+    rx: Receiver<T>,
+    tx: Sender<T>,
+    memory_budget: usize,
+) -> io::Result<()> {
+    let capacity = (memory_budget / std::mem::size_of::<T>().max(1)).max(1);
+    let mut buffer = Vec::new();
+    let mut runs = Vec::new();
+    for item in rx {
+        buffer.push(item);
+        if buffer.len() >= capacity {
+            runs.push(SpillFile::write(&merge_sort(&buffer))?);
+            buffer.clear();
+        }
+    }
+    let last_run = merge_sort(&buffer);
+    drop(buffer);
+
+    // Read the runs back in the order they were written, with the items
+    // still in memory last, so ties come out in the order they came in.
+    // If a read fails we stop that run and remember the error.
+    let error = RefCell::new(None);
+    let mut sources: Vec<Box<dyn Iterator<Item = T>>> = Vec::new();
+    for run in &runs {
+        let mut reader = BufReader::new(File::open(&run.path)?);
+        let error = &error;
+        sources.push(Box::new(std::iter::from_fn(move || {
+            match T::read_from(&mut reader) {
+                Ok(item) => item,
+                Err(e) => {
+                    error.borrow_mut().get_or_insert(e);
+                    None
+                }
+            }
+        })));
+    }
+    sources.push(Box::new(last_run.into_iter()));
+    for item in KwayMerge::new(sources) {
+        if tx.send(item).is_err() {
+            break;
+        }
+    }
+    match error.into_inner() {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
+}
+
+// A temporary file holding one sorted run, deleted when dropped.
+struct SpillFile {
+    path: PathBuf,
+}
+
+impl SpillFile {
+    fn write<T: Spill>(items: &[T]) -> io::Result<SpillFile> {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "sorting-in-rust-{}-{}.run",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::SeqCst)
+        ));
+        let spill = SpillFile { path };
+        let mut writer = BufWriter::new(File::create(&spill.path)?);
+        for item in items {
+            item.write_to(&mut writer)?;
+        }
+        writer.flush()?;
+        Ok(spill)
+    }
+}
+
+impl Drop for SpillFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+    use std::sync::mpsc::channel;
+    use std::thread;
+
+    // Run `items` through a sort stage on its own thread.
+    fn run_stage(items: Vec<i64>, memory_budget: usize) -> Vec<i64> {
+        let (in_tx, in_rx) = channel();
+        let (out_tx, out_rx) = channel();
+        let stage = thread::spawn(move || sort_stage(in_rx, out_tx, memory_budget));
+        for item in items {
+            in_tx.send(item).unwrap();
+        }
+        drop(in_tx);
+        let result = out_rx.iter().collect();
+        stage.join().unwrap().unwrap();
+        result
+    }
+
+    #[test]
+    fn empty() {
+        assert_eq!(Vec::<i64>::new(), run_stage(vec![], 1024));
+    }
+
+    #[test]
+    fn ten_items() {
+        let result = run_stage(vec![3, 2, 0, 5, 8, 9, 6, 3, 2, 0], 1024);
+        assert_eq!(vec![0, 0, 2, 2, 3, 3, 5, 6, 8, 9], result);
+    }
+
+    #[test]
+    fn spills() {
+        // A 64-byte budget holds 8 i64s, so 1000 items make 125 runs.
+        let mut rng = StdRng::seed_from_u64(236);
+        let items: Vec<i64> = (0..1000).map(|_| rng.gen_range(-500, 500)).collect();
+        let mut expected = items.clone();
+        expected.sort();
+        assert_eq!(expected, run_stage(items, 64));
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut bytes = Vec::new();
+        for x in &[1.5f64, -2.25] {
+            x.write_to(&mut bytes).unwrap();
+        }
+        let mut reader = &bytes[..];
+        assert_eq!(Some(1.5), f64::read_from(&mut reader).unwrap());
+        assert_eq!(Some(-2.25), f64::read_from(&mut reader).unwrap());
+        assert_eq!(None, f64::read_from(&mut reader).unwrap());
+    }
+}
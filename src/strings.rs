@@ -0,0 +1,496 @@
+// Sorting strings that share long prefixes, like file paths or URLs.
+//
+// Comparing two strings costs as many character comparisons as their
+// common prefix is long, so when every string starts with
+// "https://example.com/api/v1/" an ordinary comparison sort spends most
+// of its time re-reading those 27 characters. LCP merge sort (Ng and
+// Kakehi's "LCP-mergesort") avoids that by keeping, for every string,
+// the length of its longest common prefix (LCP) with the string before
+// it in the output. Two strings that have both been compared against
+// the same string often don't need to be compared with each other at
+// all, and when they do, we can start at the first character where
+// they might differ.
+//
+// Multikey quicksort and MSD radix sort get the same benefit a
+// different way, by sorting on one character position at a time (see
+// `multikey_quicksort` and `msd_radix_sort`).
+
+use std::cmp::Ordering;
+
+// The length of the longest common prefix of `a` and `b`. We compare
+// eight bytes at a time while we can, which is several times faster
+// than going a byte at a time on long prefixes.
+pub fn common_prefix(a: &[u8], b: &[u8]) -> usize {
+    let n = a.len().min(b.len());
+    let mut i = 0;
+    while i + 8 <= n && a[i..i + 8] == b[i..i + 8] {
+        i += 8;
+    }
+    i + a[i..n]
+        .iter()
+        .zip(&b[i..n])
+        .take_while(|(x, y)| x == y)
+        .count()
+}
+
+// The LCP array of a sorted list of strings: lcps[i] is the length of
+// the common prefix of strings[i - 1] and strings[i], and lcps[0] is 0.
+pub fn lcp_of_sorted(strings: &[&str]) -> Vec<usize> {
+    let mut lcps = Vec::with_capacity(strings.len());
+    if !strings.is_empty() {
+        lcps.push(0);
+    }
+    for w in strings.windows(2) {
+        lcps.push(common_prefix(w[0].as_bytes(), w[1].as_bytes()));
+    }
+    lcps
+}
+
+// Sort `strings` (in the same order as `str`'s `Ord`, which compares
+// bytes) with LCP merge sort. Equal strings stay in their original
+// order.
+pub fn sort_strings_with_lcp<'a>(strings: &[&'a str]) -> Vec<&'a str> {
+    lcp_merge_sort(strings)
+        .into_iter()
+        .map(|(s, _)| s)
+        .collect()
+}
+
+// The sorted strings, each paired with its LCP with the one before it.
+fn lcp_merge_sort<'a>(strings: &[&'a str]) -> Vec<(&'a str, usize)> {
+    if strings.len() <= 1 {
+        return strings.iter().map(|&s| (s, 0)).collect();
+    }
+    let middle = strings.len() / 2;
+    lcp_merge(
+        lcp_merge_sort(&strings[..middle]),
+        lcp_merge_sort(&strings[middle..]),
+    )
+}
+
+// Merge two sorted runs, where each string comes with its LCP with the
+// string before it in its run.
+//
+// Suppose the last string we output was p, and the next strings in the
+// two runs are x and y, with LCPs h_x and h_y with p. (The first string
+// of a run has an LCP of 0, which is its LCP with an empty string, and
+// that's what we pretend p is when we start.) Both x and y are >= p, so
+// if h_x > h_y then x agrees with p for longer than y does, and y must
+// be bigger than p (and so bigger than x) at character h_y. So x comes
+// next, and y's LCP with x is still h_y. Only when h_x == h_y do we
+// actually have to compare x and y, and then we can start at
+// character h_x, since both agree with p (and so each other) up to
+// there.
+//
+// (All i | 0 < i < result.len() : result[i].1 is the LCP of
+// result[i - 1].0 and result[i].0)
+fn lcp_merge<'a>(xs: Vec<(&'a str, usize)>, ys: Vec<(&'a str, usize)>) -> Vec<(&'a str, usize)> {
+    let mut result = Vec::with_capacity(xs.len() + ys.len());
+    let mut xs = xs.into_iter();
+    let mut ys = ys.into_iter();
+    let mut x = xs.next();
+    let mut y = ys.next();
+    while let (Some((xstr, hx)), Some((ystr, hy))) = (x, y) {
+        let x_first = match hx.cmp(&hy) {
+            Ordering::Greater => true,
+            Ordering::Less => false,
+            Ordering::Equal => {
+                let (a, b) = (xstr.as_bytes(), ystr.as_bytes());
+                let h = hx + common_prefix(&a[hx..], &b[hx..]);
+                // They differ at character h, or one of them ends there
+                // (and the shorter one comes first). Taking from xs when
+                // the two are equal keeps the merge stable.
+                let x_first = h == a.len() || (h < b.len() && a[h] < b[h]);
+                // Whichever goes second now has an LCP of h with the
+                // one going first.
+                if x_first {
+                    y = Some((ystr, h));
+                } else {
+                    x = Some((xstr, h));
+                }
+                x_first
+            }
+        };
+        if x_first {
+            result.push(x.take().unwrap());
+            x = xs.next();
+        } else {
+            result.push(y.take().unwrap());
+            y = ys.next();
+        }
+    }
+    // At most one of these will have anything left in it, and its next
+    // string's LCP is already relative to the last string we output.
+    result.extend(x.into_iter().chain(xs));
+    result.extend(y.into_iter().chain(ys));
+    result
+}
+
+// Below this many strings, multikey quicksort switches to insertion
+// sort.
+const MULTIKEY_INSERTION_THRESHOLD: usize = 10;
+
+// Sort `v` with Bentley and Sedgewick's multikey quicksort (also called
+// three-way radix quicksort). It works one character position at a
+// time: pick a pivot character c at position `depth`, and partition
+// the strings three ways, into those whose character there is less
+// than c, equal to c, and greater than c. The "less" and "greater"
+// parts still need sorting at the same depth, but the strings in the
+// "equal" part all agree up to and including `depth`, so they only
+// need sorting from the next character on. Like radix sort, it never
+// looks at a character twice once it's known to be shared; like
+// quicksort, it doesn't need a bucket for every possible character.
+//
+// Strings can be anything that looks like bytes (`String`, `&str`,
+// `&[u8]`, ...), and sort the same way `str`'s `Ord` does. It isn't
+// stable, but equal strings are indistinguishable anyway.
+pub fn multikey_quicksort<S: AsRef<[u8]>>(v: &mut [S]) {
+    multikey_quicksort_from(v, 0);
+}
+
+// Sort `v`, all of whose strings agree on their first `depth` bytes.
+fn multikey_quicksort_from<S: AsRef<[u8]>>(v: &mut [S], depth: usize) {
+    if v.len() < MULTIKEY_INSERTION_THRESHOLD {
+        insertion_sort_from(v, depth);
+        return;
+    }
+    let pivot = median_of_three(
+        char_at(&v[0], depth),
+        char_at(&v[v.len() / 2], depth),
+        char_at(&v[v.len() - 1], depth),
+    );
+    // Dijkstra's three-way partition:
+    //    v[..lt] < pivot, v[lt..i] == pivot, v[gt..] > pivot
+    // and v[i..gt] hasn't been looked at yet.
+    let mut lt = 0;
+    let mut i = 0;
+    let mut gt = v.len();
+    while i < gt {
+        let c = char_at(&v[i], depth);
+        if c < pivot {
+            v.swap(lt, i);
+            lt += 1;
+            i += 1;
+        } else if c > pivot {
+            gt -= 1;
+            v.swap(i, gt);
+        } else {
+            i += 1;
+        }
+    }
+    multikey_quicksort_from(&mut v[..lt], depth);
+    // If the pivot is the end of the string, the equal strings are all
+    // the same string, and there's nothing left to sort.
+    if pivot.is_some() {
+        multikey_quicksort_from(&mut v[lt..gt], depth + 1);
+    }
+    multikey_quicksort_from(&mut v[gt..], depth);
+}
+
+// The character at `depth`, or `None` if the string has ended. `None`
+// is less than every character, so shorter strings come first.
+fn char_at<S: AsRef<[u8]>>(s: &S, depth: usize) -> Option<u8> {
+    s.as_ref().get(depth).copied()
+}
+
+fn median_of_three(a: Option<u8>, b: Option<u8>, c: Option<u8>) -> Option<u8> {
+    if a < b {
+        if b < c {
+            b
+        } else if a < c {
+            c
+        } else {
+            a
+        }
+    } else if a < c {
+        a
+    } else if b < c {
+        c
+    } else {
+        b
+    }
+}
+
+fn insertion_sort_from<S: AsRef<[u8]>>(v: &mut [S], depth: usize) {
+    for i in 1..v.len() {
+        let mut j = i;
+        while j > 0 && v[j].as_ref()[depth..] < v[j - 1].as_ref()[depth..] {
+            v.swap(j, j - 1);
+            j -= 1;
+        }
+    }
+}
+
+// Below this many strings, MSD radix sort switches to insertion sort:
+// clearing and scanning 257 counts isn't worth it for a handful.
+const MSD_INSERTION_THRESHOLD: usize = 32;
+
+// Sort `v` with MSD (most significant digit first) radix sort, the other
+// way of sorting one character position at a time. Where multikey
+// quicksort splits the strings three ways around a pivot character, this
+// splits them 257 ways at once, one bucket for each byte value and one
+// (first) for strings that have already ended, and then sorts each
+// bucket from the next character on. Unlike LSD radix sort
+// (`sorts::radix_sort_lsd`) it can stop as soon as a bucket is down to
+// one string, so it only looks at as many characters as it takes to
+// tell the strings apart.
+//
+// We move the strings into their buckets in place, without a scratch
+// buffer, the way McIlroy, Bostic, and McIlroy's "American flag sort"
+// does: count how many strings go in each bucket, which tells us where
+// each bucket starts, then walk through the buckets swapping each string
+// that's in the wrong one into the next free place of the right one.
+// Like multikey quicksort, it sorts the same way `str`'s `Ord` does and
+// isn't stable.
+pub fn msd_radix_sort<S: AsRef<[u8]>>(v: &mut [S]) {
+    msd_radix_sort_from(v, 0);
+}
+
+// Sort `v`, all of whose strings agree on their first `depth` bytes.
+fn msd_radix_sort_from<S: AsRef<[u8]>>(v: &mut [S], mut depth: usize) {
+    loop {
+        if v.len() < MSD_INSERTION_THRESHOLD {
+            insertion_sort_from(v, depth);
+            return;
+        }
+        let mut counts = [0usize; 257];
+        for s in v.iter() {
+            counts[bucket(s, depth)] += 1;
+        }
+        // When they all agree on this character too, they probably share
+        // a longer prefix, like the "https://" of a list of URLs. Rather
+        // than find that out one counting pass per character, we skip to
+        // the end of it, comparing eight bytes at a time with
+        // `common_prefix`. (If they've all ended, they're all the same
+        // string.)
+        if counts.contains(&v.len()) {
+            if counts[0] == v.len() {
+                return;
+            }
+            let first = v[0].as_ref();
+            depth += v[1..]
+                .iter()
+                .map(|s| common_prefix(&first[depth..], &s.as_ref()[depth..]))
+                .min()
+                .unwrap_or(0);
+            continue;
+        }
+        // Where each bucket starts, and where its next string goes.
+        let mut starts = [0usize; 257];
+        let mut start = 0;
+        for (b, &count) in counts.iter().enumerate() {
+            starts[b] = start;
+            start += count;
+        }
+        let mut next = starts;
+        for b in 0..257 {
+            let end = starts[b] + counts[b];
+            // Everything in v[starts[b]..next[b]] belongs in bucket b.
+            // Swap whatever's at next[b] into its own bucket until
+            // something that belongs here turns up.
+            while next[b] < end {
+                let home = bucket(&v[next[b]], depth);
+                if home != b {
+                    v.swap(next[b], next[home]);
+                }
+                next[home] += 1;
+            }
+        }
+        // The strings in bucket 0 have all ended, so they're all equal.
+        for b in 1..257 {
+            let bucket = &mut v[starts[b]..starts[b] + counts[b]];
+            msd_radix_sort_from(bucket, depth + 1);
+        }
+        return;
+    }
+}
+
+// Which bucket `s` goes in at `depth`: 0 if it's ended, or its byte
+// there plus 1.
+fn bucket<S: AsRef<[u8]>>(s: &S, depth: usize) -> usize {
+    char_at(s, depth).map_or(0, |c| c as usize + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::random::{RandomSource, SeededRandom};
+
+    #[test]
+    fn sorts() {
+        let words = ["pear", "apple", "fig", "", "apples", "app", "fig"];
+        let mut expected = words.to_vec();
+        expected.sort();
+        assert_eq!(expected, sort_strings_with_lcp(&words));
+        assert!(sort_strings_with_lcp(&[]).is_empty());
+        assert_eq!(vec!["x"], sort_strings_with_lcp(&["x"]));
+    }
+
+    #[test]
+    fn lcps() {
+        assert_eq!(4, common_prefix(b"apple", b"apply"));
+        assert_eq!(0, common_prefix(b"", b"apply"));
+        assert_eq!(
+            vec![0, 3, 5, 0],
+            lcp_of_sorted(&["app", "apple", "apples", "fig"])
+        );
+        assert!(lcp_of_sorted(&[]).is_empty());
+    }
+
+    #[test]
+    fn merge_keeps_lcps() {
+        let sorted = lcp_merge_sort(&["/a/b/c", "/a/b", "/a/bc", "/a/b/c", "/b", "/a"]);
+        let strings: Vec<&str> = sorted.iter().map(|&(s, _)| s).collect();
+        let lcps: Vec<usize> = sorted.iter().map(|&(_, h)| h).collect();
+        assert_eq!(
+            vec!["/a", "/a/b", "/a/b/c", "/a/b/c", "/a/bc", "/b"],
+            strings
+        );
+        assert_eq!(lcp_of_sorted(&strings), lcps);
+    }
+
+    #[test]
+    fn shared_prefixes() {
+        // Paths from a small alphabet of directory names, so there are
+        // lots of long shared prefixes, some duplicates, and some
+        // strings that are prefixes of others.
+        let mut rng = SeededRandom::new(250);
+        let dirs = ["src", "sorts", "s", "target", "debug"];
+        for len in 0..100 {
+            let paths: Vec<String> = (0..len)
+                .map(|_| {
+                    let depth = rng.below(6);
+                    let parts: Vec<&str> =
+                        (0..depth).map(|_| dirs[rng.below(dirs.len())]).collect();
+                    format!("/{}", parts.join("/"))
+                })
+                .collect();
+            let paths: Vec<&str> = paths.iter().map(String::as_str).collect();
+            let mut expected = paths.clone();
+            expected.sort();
+            assert_eq!(expected, sort_strings_with_lcp(&paths));
+            assert_eq!(
+                lcp_of_sorted(&expected),
+                lcp_merge_sort(&paths)
+                    .iter()
+                    .map(|&(_, h)| h)
+                    .collect::<Vec<usize>>()
+            );
+        }
+    }
+
+    mod multikey_quicksort {
+        use super::*;
+
+        #[test]
+        fn words() {
+            let mut words = vec!["pear", "apple", "fig", "", "apples", "app", "fig"];
+            let mut expected = words.clone();
+            expected.sort();
+            multikey_quicksort(&mut words);
+            assert_eq!(expected, words);
+            let mut empty: Vec<String> = Vec::new();
+            multikey_quicksort(&mut empty);
+            assert!(empty.is_empty());
+        }
+
+        #[test]
+        fn owned_strings_and_bytes() {
+            let mut rng = SeededRandom::new(251);
+            for len in 0..200 {
+                // Lots of shared prefixes, strings that are prefixes of
+                // others, and duplicates.
+                let mut strings: Vec<String> = (0..len)
+                    .map(|_| {
+                        let n = rng.below(8);
+                        (0..n).map(|_| ['a', 'b', 'c'][rng.below(3)]).collect()
+                    })
+                    .collect();
+                let mut expected = strings.clone();
+                expected.sort();
+                let mut bytes: Vec<&[u8]> = strings.iter().map(|s| s.as_bytes()).collect();
+                multikey_quicksort(&mut bytes);
+                let expected_bytes: Vec<&[u8]> = expected.iter().map(|s| s.as_bytes()).collect();
+                assert_eq!(expected_bytes, bytes);
+                multikey_quicksort(&mut strings);
+                assert_eq!(expected, strings);
+            }
+        }
+
+        #[test]
+        fn non_ascii() {
+            // Bytes above 127 sort after ASCII, just as in `str`'s order.
+            let mut words = vec!["zoo", "\u{e9}t\u{e9}", "eat", "\u{e9}", "Zoo"];
+            multikey_quicksort(&mut words);
+            assert_eq!(vec!["Zoo", "eat", "zoo", "\u{e9}", "\u{e9}t\u{e9}"], words);
+        }
+
+        #[test]
+        fn same_string_many_times() {
+            let mut v = vec!["same".to_string(); 1000];
+            multikey_quicksort(&mut v);
+            assert!(v.iter().all(|s| s == "same"));
+        }
+    }
+
+    mod msd_radix_sort {
+        use super::*;
+
+        #[test]
+        fn words() {
+            let mut words = vec!["pear", "apple", "fig", "", "apples", "app", "fig"];
+            let mut expected = words.clone();
+            expected.sort();
+            msd_radix_sort(&mut words);
+            assert_eq!(expected, words);
+            let mut empty: Vec<String> = Vec::new();
+            msd_radix_sort(&mut empty);
+            assert!(empty.is_empty());
+        }
+
+        #[test]
+        fn owned_strings_and_bytes() {
+            let mut rng = SeededRandom::new(513);
+            for &len in &[0, 1, 31, 32, 33, 100, 1000, 5000] {
+                let mut strings: Vec<String> = (0..len)
+                    .map(|_| {
+                        let n = rng.below(8);
+                        (0..n)
+                            .map(|_| ['a', 'b', 'c', '\u{e9}'][rng.below(4)])
+                            .collect()
+                    })
+                    .collect();
+                let mut expected = strings.clone();
+                expected.sort();
+                let mut bytes: Vec<&[u8]> = strings.iter().map(|s| s.as_bytes()).collect();
+                msd_radix_sort(&mut bytes);
+                let expected_bytes: Vec<&[u8]> = expected.iter().map(|s| s.as_bytes()).collect();
+                assert_eq!(expected_bytes, bytes);
+                msd_radix_sort(&mut strings);
+                assert_eq!(expected, strings);
+            }
+        }
+
+        #[test]
+        fn long_shared_prefixes() {
+            let mut rng = SeededRandom::new(513);
+            let prefix = "s3://lab-8-benchmark-results/".repeat(10);
+            let mut keys: Vec<String> = (0..2000)
+                .map(|_| format!("{}{}/{}", prefix, rng.below(3), rng.below(500)))
+                .collect();
+            keys.push(prefix.clone());
+            keys.push(prefix[..100].to_string());
+            let mut expected = keys.clone();
+            expected.sort();
+            msd_radix_sort(&mut keys);
+            assert_eq!(expected, keys);
+        }
+
+        #[test]
+        fn same_string_many_times() {
+            let mut v = vec!["same".to_string(); 1000];
+            msd_radix_sort(&mut v);
+            assert!(v.iter().all(|s| s == "same"));
+        }
+    }
+}
@@ -0,0 +1,332 @@
+// Suffix arrays: the starting positions of every suffix of a text, in
+// sorted order. For "banana" the suffixes are
+//    0 banana, 1 anana, 2 nana, 3 ana, 4 na, 5 a
+// and sorting them gives <5, 3, 1, 0, 4, 2>. With a suffix array you
+// can find every occurrence of a pattern with a binary search, which is
+// why they're at the heart of full-text indexes and of tools like bzip2.
+//
+// The obvious way to build one is to sort the suffixes with a
+// comparison sort (`naive_suffix_array`), but each comparison can take
+// O(N) time, so on repetitive text that's O(N^2 log N). SA-IS
+// (`suffix_array`) does it in O(N) by never comparing two whole
+// suffixes: it sorts a small subset of them (recursively) and then
+// "induces" the order of all the rest from those, a bit like the way
+// a counting sort places items into buckets without comparing them.
+// On a megabyte of repetitive text that's the difference between a few
+// milliseconds and hours.
+
+use crate::sorts::merge_sort;
+use std::cmp::Ordering;
+use std::fmt;
+
+// Marks a slot of the suffix array that hasn't been filled in yet.
+const EMPTY: usize = usize::MAX;
+
+// Below this length it's quicker to just sort the suffixes.
+const NAIVE_THRESHOLD: usize = 10;
+
+// The suffix array of `text`, built with SA-IS.
+pub fn suffix_array(text: &[u8]) -> Vec<u32> {
+    assert!(
+        text.len() <= u32::MAX as usize,
+        "the text is too long for a u32 suffix array"
+    );
+    let symbols: Vec<usize> = text.iter().map(|&b| b as usize).collect();
+    sa_is(&symbols, u8::MAX as usize)
+        .into_iter()
+        .map(|i| i as u32)
+        .collect()
+}
+
+// The suffix array of `text`, built by sorting the suffixes with our
+// merge sort. It's only here as a baseline (and a check) for
+// `suffix_array`.
+pub fn naive_suffix_array(text: &[u8]) -> Vec<u32> {
+    assert!(
+        text.len() <= u32::MAX as usize,
+        "the text is too long for a u32 suffix array"
+    );
+    sort_suffixes(text).into_iter().map(|i| i as u32).collect()
+}
+
+fn sort_suffixes<T: Ord + Copy>(text: &[T]) -> Vec<usize> {
+    let suffixes: Vec<Suffix<T>> = (0..text.len())
+        .map(|start| Suffix { text, start })
+        .collect();
+    merge_sort(&suffixes).into_iter().map(|s| s.start).collect()
+}
+
+// A suffix of `text`, compared with other suffixes of the same text.
+#[derive(Clone, Copy)]
+struct Suffix<'a, T> {
+    text: &'a [T],
+    start: usize,
+}
+
+impl<T: Ord> PartialEq for Suffix<'_, T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.start == other.start
+    }
+}
+
+impl<T: Ord> PartialOrd for Suffix<'_, T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.text[self.start..].cmp(&other.text[other.start..]))
+    }
+}
+
+impl<T> fmt::Debug for Suffix<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Suffix({})", self.start)
+    }
+}
+
+// The LCP array for `text` and its suffix array `sa`: lcp[i] is the
+// length of the longest common prefix of the suffixes at sa[i - 1] and
+// sa[i], and lcp[0] is 0. Together with the suffix array it answers
+// questions like "what's the longest repeated substring?" (the biggest
+// entry).
+//
+// Comparing each pair of neighbours from scratch would be O(N^2) on
+// repetitive text. Kasai's algorithm is O(N): it visits the suffixes
+// in text order, and if suffix i shares h characters with the suffix
+// before it in `sa`, then suffix i + 1 shares at least h - 1 with the
+// suffix before *it*, so we can start comparing there.
+pub fn lcp_array(text: &[u8], sa: &[u32]) -> Vec<u32> {
+    let n = text.len();
+    assert_eq!(n, sa.len(), "the suffix array is for a different text");
+    // rank[i]: where suffix i is in `sa`.
+    let mut rank = vec![0; n];
+    for (r, &i) in sa.iter().enumerate() {
+        rank[i as usize] = r;
+    }
+    let mut lcp = vec![0; n];
+    let mut h: usize = 0;
+    for i in 0..n {
+        if rank[i] == 0 {
+            h = 0;
+            continue;
+        }
+        let j = sa[rank[i] - 1] as usize;
+        while i + h < n && j + h < n && text[i + h] == text[j + h] {
+            h += 1;
+        }
+        lcp[rank[i]] = h as u32;
+        h = h.saturating_sub(1);
+    }
+    lcp
+}
+
+// SA-IS on a text whose symbols are all in 0..=max.
+//
+// Each suffix is either S-type (smaller than the suffix after it) or
+// L-type (larger); the last suffix counts as L-type. An LMS
+// ("leftmost S") suffix is an S-type suffix right after an L-type one.
+// Given the LMS suffixes in sorted order, one pass from the left puts
+// every L-type suffix in place and one pass from the right does the
+// S-types (`induce`). To get the LMS suffixes sorted in the first
+// place, we induce from them in text order, which is enough to sort
+// the LMS *substrings* (the stretches from one LMS position to the
+// next); if those are all different we're done, and if not we name
+// each by its rank and build the suffix array of the (at most half as
+// long) string of names recursively.
+fn sa_is(s: &[usize], max: usize) -> Vec<usize> {
+    let n = s.len();
+    if n < NAIVE_THRESHOLD {
+        return sort_suffixes(s);
+    }
+
+    // is_s[i]: whether suffix i is S-type.
+    let mut is_s = vec![false; n];
+    for i in (0..n - 1).rev() {
+        is_s[i] = if s[i] == s[i + 1] {
+            is_s[i + 1]
+        } else {
+            s[i] < s[i + 1]
+        };
+    }
+
+    // Every suffix starting with symbol c goes in c's bucket, with the
+    // L-types at the front and the S-types at the back. l_start[c] is
+    // where c's L-types start and s_start[c] where its S-types start.
+    let mut l_start = vec![0; max + 2];
+    let mut s_start = vec![0; max + 1];
+    for i in 0..n {
+        if is_s[i] {
+            l_start[s[i] + 1] += 1;
+        } else {
+            s_start[s[i]] += 1;
+        }
+    }
+    for c in 0..=max {
+        s_start[c] += l_start[c];
+        l_start[c + 1] += s_start[c];
+    }
+
+    let is_lms = |i: usize| i > 0 && !is_s[i - 1] && is_s[i];
+    let lms: Vec<usize> = (1..n).filter(|&i| is_lms(i)).collect();
+    // lms_index[i]: which LMS suffix i is, counting from the left.
+    let mut lms_index = vec![EMPTY; n];
+    for (k, &i) in lms.iter().enumerate() {
+        lms_index[i] = k;
+    }
+
+    let mut sa = vec![EMPTY; n];
+    induce(s, &is_s, &l_start, &s_start, &lms, &mut sa);
+
+    if !lms.is_empty() {
+        // The LMS suffixes in the order the induction put them in,
+        // which sorts them by their LMS substrings.
+        let sorted_lms: Vec<usize> = sa
+            .iter()
+            .copied()
+            .filter(|&i| lms_index[i] != EMPTY)
+            .collect();
+        // Name each LMS substring by its rank, giving equal substrings
+        // the same name.
+        let end = |i: usize| lms.get(lms_index[i] + 1).copied().unwrap_or(n);
+        let mut names = vec![0; lms.len()];
+        let mut name = 0;
+        for w in sorted_lms.windows(2) {
+            let (a, b) = (w[0], w[1]);
+            let same = end(a) - a == end(b) - b
+                && end(a) < n
+                && end(b) < n
+                && s[a..=end(a)] == s[b..=end(b)];
+            if !same {
+                name += 1;
+            }
+            names[lms_index[b]] = name;
+        }
+        // Sort the LMS suffixes properly, and induce from that.
+        let order: Vec<usize> = if name + 1 == lms.len() {
+            // Every name is different, so the names are already ranks.
+            let mut order = vec![0; lms.len()];
+            for (k, &name) in names.iter().enumerate() {
+                order[name] = k;
+            }
+            order
+        } else {
+            sa_is(&names, name)
+        };
+        let sorted_lms: Vec<usize> = order.into_iter().map(|k| lms[k]).collect();
+        induce(s, &is_s, &l_start, &s_start, &sorted_lms, &mut sa);
+    }
+    sa
+}
+
+// Fill in `sa` from the LMS suffixes `lms`, which go at the back of
+// their buckets in the order given.
+fn induce(
+    s: &[usize],
+    is_s: &[bool],
+    l_start: &[usize],
+    s_start: &[usize],
+    lms: &[usize],
+    sa: &mut [usize],
+) {
+    let n = s.len();
+    for slot in sa.iter_mut() {
+        *slot = EMPTY;
+    }
+    // Seed the S-type parts of the buckets with the LMS suffixes.
+    let mut next = s_start.to_vec();
+    for &i in lms {
+        sa[next[s[i]]] = i;
+        next[s[i]] += 1;
+    }
+    // Left to right: whenever suffix i is in place and suffix i - 1 is
+    // L-type, suffix i - 1 goes next in the front of its bucket. The
+    // last suffix is just one symbol, so it's a prefix of (and comes
+    // before) everything else in its bucket.
+    let mut next = l_start[..l_start.len() - 1].to_vec();
+    sa[next[s[n - 1]]] = n - 1;
+    next[s[n - 1]] += 1;
+    for k in 0..n {
+        let i = sa[k];
+        if i != EMPTY && i > 0 && !is_s[i - 1] {
+            sa[next[s[i - 1]]] = i - 1;
+            next[s[i - 1]] += 1;
+        }
+    }
+    // Right to left: the same for the S-types, filling each bucket from
+    // the back (and overwriting the LMS seeds).
+    let mut next = l_start[1..].to_vec();
+    for k in (0..n).rev() {
+        let i = sa[k];
+        if i != EMPTY && i > 0 && is_s[i - 1] {
+            next[s[i - 1]] -= 1;
+            sa[next[s[i - 1]]] = i - 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::random::{RandomSource, SeededRandom};
+
+    #[test]
+    fn known_strings() {
+        assert_eq!(vec![5, 3, 1, 0, 4, 2], suffix_array(b"banana"));
+        assert_eq!(
+            vec![10, 7, 4, 1, 0, 9, 8, 6, 3, 5, 2],
+            suffix_array(b"mississippi")
+        );
+        assert_eq!(
+            vec![11, 10, 7, 0, 3, 5, 8, 1, 4, 6, 9, 2],
+            suffix_array(b"abracadabra!")
+        );
+    }
+
+    #[test]
+    fn tiny_and_repetitive() {
+        assert!(suffix_array(b"").is_empty());
+        assert_eq!(vec![0], suffix_array(b"x"));
+        let same = vec![b'a'; 100];
+        let expected: Vec<u32> = (0..100).rev().collect();
+        assert_eq!(expected, suffix_array(&same));
+        let repeated = b"abcabcabcabcabcabcabcabc";
+        assert_eq!(naive_suffix_array(repeated), suffix_array(repeated));
+    }
+
+    #[test]
+    fn matches_naive() {
+        // Small alphabets give lots of repeated LMS substrings, which
+        // is what makes SA-IS recurse.
+        let mut rng = SeededRandom::new(249);
+        for &alphabet in &[1, 2, 3, 4, 26, 256] {
+            for len in 0..200 {
+                let text: Vec<u8> = (0..len).map(|_| rng.below(alphabet) as u8).collect();
+                assert_eq!(naive_suffix_array(&text), suffix_array(&text), "{:?}", text);
+            }
+        }
+    }
+
+    #[test]
+    fn lcps() {
+        // a, ana, anana, banana, na, nana
+        assert_eq!(
+            vec![0, 1, 3, 0, 0, 2],
+            lcp_array(b"banana", &suffix_array(b"banana"))
+        );
+        assert!(lcp_array(b"", &[]).is_empty());
+        let mut rng = SeededRandom::new(250);
+        for len in 0..100 {
+            let text: Vec<u8> = (0..len).map(|_| rng.below(3) as u8).collect();
+            let sa = suffix_array(&text);
+            let lcp = lcp_array(&text, &sa);
+            for r in 1..len {
+                let (a, b) = (&text[sa[r - 1] as usize..], &text[sa[r] as usize..]);
+                let expected = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+                assert_eq!(expected as u32, lcp[r]);
+            }
+        }
+    }
+
+    #[test]
+    fn the_whole_byte_range() {
+        let text: Vec<u8> = (0..=255).rev().chain(0..=255).collect();
+        assert_eq!(naive_suffix_array(&text), suffix_array(&text));
+    }
+}
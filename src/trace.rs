@@ -0,0 +1,650 @@
+// Recording what a sort does, step by step, so it can be replayed
+// later. A `Trace` is the input plus the list of operations the sort
+// performed on it (comparisons, swaps, and writes). It can be saved as
+// JSON, e.g.
+//    {"algorithm": "insertion", "input": [2, 1], "ops": [
+//      {"op": "compare", "left": 2, "right": 1},
+//      {"op": "swap", "i": 0, "j": 1}
+//    ]}
+// and `render` turns it back into a step-by-step picture. Recording and
+// drawing are separate, so a trace can be animated, graded, or diffed
+// against another one without running the sort again.
+//
+// Only the lab's three algorithms (and selection sort, to compare with
+// insertion sort) can be recorded, using the versions
+// below, which do exactly what the real ones do but report each step.
+// They make the same mistakes as `mutants.rs` when its bugs are turned
+// on, so `diff` can show exactly where a buggy sort goes wrong. Traces
+// get long quickly, so inputs are limited to `MAX_TRACE_SIZE`.
+
+use crate::json::{self, quote, Json};
+use crate::mutants::{is_active, Bug};
+use std::fmt::Write;
+
+pub const MAX_TRACE_SIZE: usize = 64;
+
+pub const TRACEABLE: [&str; 4] = ["insertion", "selection", "quicksort", "merge"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    // Compared these two values.
+    Compare { left: i32, right: i32 },
+    // Swapped v[i] and v[j].
+    Swap { i: usize, j: usize },
+    // Set v[index] to value (merge sort copying its merged result back).
+    Set { index: usize, value: i32 },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trace {
+    pub algorithm: String,
+    pub input: Vec<i32>,
+    pub ops: Vec<Op>,
+}
+
+// Sort a copy of `input` with the named algorithm and record every step.
+pub fn record(algorithm: &str, input: &[i32]) -> Result<Trace, String> {
+    if input.len() > MAX_TRACE_SIZE {
+        return Err(format!(
+            "can only trace up to {} values, not {}",
+            MAX_TRACE_SIZE,
+            input.len()
+        ));
+    }
+    let recorder = run(algorithm, input, true)?;
+    Ok(Trace {
+        algorithm: algorithm.to_string(),
+        input: input.to_vec(),
+        ops: recorder.ops,
+    })
+}
+
+// How much work a sort did: the operations a trace would record, counted
+// rather than kept, plus how deep the recursion went (1 for a sort that
+// doesn't recurse, 0 for one that had nothing to do).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OpCounts {
+    pub comparisons: u64,
+    pub swaps: u64,
+    // Values written into place (merge sort copying back its merges).
+    pub moves: u64,
+    pub max_depth: usize,
+}
+
+// Sort a copy of `input` as `record` would, but only count the steps, so
+// there's no limit on the size. The counts depend only on the input,
+// never on the machine, which makes them good for grading.
+pub fn count_ops(algorithm: &str, input: &[i32]) -> Result<OpCounts, String> {
+    Ok(run(algorithm, input, false)?.counts)
+}
+
+fn run(algorithm: &str, input: &[i32], keep_ops: bool) -> Result<Recorder, String> {
+    let mut recorder = Recorder {
+        v: input.to_vec(),
+        ops: Vec::new(),
+        keep_ops,
+        counts: OpCounts::default(),
+        depth: 0,
+    };
+    let length = input.len();
+    match algorithm {
+        "insertion" => {
+            if length > 1 {
+                recorder.enter();
+                recorder.insertion_sort();
+                recorder.leave();
+            }
+        }
+        "selection" => {
+            if length > 1 {
+                recorder.enter();
+                recorder.selection_sort();
+                recorder.leave();
+            }
+        }
+        "quicksort" => recorder.quicksort(0, length),
+        "merge" => recorder.merge_sort(0, length),
+        _ => {
+            return Err(format!(
+                "can't trace {}; try one of {}",
+                algorithm,
+                TRACEABLE.join(", ")
+            ))
+        }
+    }
+    Ok(recorder)
+}
+
+struct Recorder {
+    v: Vec<i32>,
+    ops: Vec<Op>,
+    // Whether to keep each op in `ops`, or only count it.
+    keep_ops: bool,
+    counts: OpCounts,
+    // How many calls (on at least two values) we're inside.
+    depth: usize,
+}
+
+impl Recorder {
+    fn push(&mut self, op: Op) {
+        match op {
+            Op::Compare { .. } => self.counts.comparisons += 1,
+            Op::Swap { .. } => self.counts.swaps += 1,
+            Op::Set { .. } => self.counts.moves += 1,
+        }
+        if self.keep_ops {
+            self.ops.push(op);
+        }
+    }
+
+    fn enter(&mut self) {
+        self.depth += 1;
+        self.counts.max_depth = self.counts.max_depth.max(self.depth);
+    }
+
+    fn leave(&mut self) {
+        self.depth -= 1;
+    }
+
+    fn less(&mut self, left: i32, right: i32) -> bool {
+        self.push(Op::Compare { left, right });
+        if is_active(Bug::WrongComparison) {
+            left > right
+        } else {
+            left < right
+        }
+    }
+
+    fn swap(&mut self, i: usize, j: usize) {
+        self.push(Op::Swap { i, j });
+        self.v.swap(i, j);
+    }
+
+    fn set(&mut self, index: usize, value: i32) {
+        self.push(Op::Set { index, value });
+        self.v[index] = value;
+    }
+
+    // As in `sorts/insertion.rs`: bubble each v[i] left into place.
+    fn insertion_sort(&mut self) {
+        for i in 0..self.v.len() {
+            let mut j = i;
+            while j > 0 && self.less(self.v[j], self.v[j - 1]) {
+                self.swap(j - 1, j);
+                j -= 1;
+            }
+        }
+    }
+
+    // As in `sorts/selection_sort.rs`: swap the smallest of v[k..] into
+    // v[k].
+    fn selection_sort(&mut self) {
+        let length = self.v.len();
+        for k in 0..length {
+            let mut smallest = k;
+            for i in k + 1..length {
+                if self.less(self.v[i], self.v[smallest]) {
+                    smallest = i;
+                }
+            }
+            if smallest != k {
+                self.swap(k, smallest);
+            }
+        }
+    }
+
+    // As in `sorts/quicksort.rs`: partition v[lo..hi] around its first
+    // element with the one-pass partition from `partition.rs`.
+    fn quicksort(&mut self, lo: usize, hi: usize) {
+        if hi - lo < 2 {
+            return;
+        }
+        let pivot = self.v[lo];
+        let mut k = lo + 1;
+        let end = if is_active(Bug::OffByOnePartition) {
+            hi - 1
+        } else {
+            hi
+        };
+        for i in lo + 1..end {
+            if self.less(self.v[i], pivot) {
+                if k != i {
+                    self.swap(k, i);
+                }
+                k += 1;
+            }
+        }
+        let smaller = k - 1;
+        if smaller != lo {
+            self.swap(lo, smaller);
+        }
+        self.enter();
+        self.quicksort(lo, smaller);
+        self.quicksort(smaller + 1, hi);
+        self.leave();
+    }
+
+    // As in `sorts/merge_sort.rs` (without the galloping): sort each
+    // half, merge them, and write the result back over v[lo..hi].
+    fn merge_sort(&mut self, lo: usize, hi: usize) {
+        if hi - lo < 2 {
+            return;
+        }
+        let middle = lo + (hi - lo) / 2;
+        self.enter();
+        self.merge_sort(lo, middle);
+        self.merge_sort(middle, hi);
+        self.leave();
+        let xs = self.v[lo..middle].to_vec();
+        let ys = self.v[middle..hi].to_vec();
+        let (mut i, mut j) = (0, 0);
+        let mut merged = Vec::with_capacity(hi - lo);
+        while i < xs.len() && j < ys.len() {
+            let take_ys = if is_active(Bug::UnstableMerge) {
+                !self.less(xs[i], ys[j])
+            } else {
+                self.less(ys[j], xs[i])
+            };
+            if take_ys {
+                merged.push(ys[j]);
+                j += 1;
+            } else {
+                merged.push(xs[i]);
+                i += 1;
+            }
+        }
+        merged.extend_from_slice(&xs[i..]);
+        if !is_active(Bug::MissingMergeTail) {
+            merged.extend_from_slice(&ys[j..]);
+        }
+        for (offset, value) in merged.into_iter().enumerate() {
+            if self.v[lo + offset] != value {
+                self.set(lo + offset, value);
+            }
+        }
+    }
+}
+
+impl Trace {
+    // Apply the operations to the input, and return the final array.
+    // Fails if an operation refers to an index that isn't there (say,
+    // in a hand-edited trace).
+    pub fn replay(&self) -> Result<Vec<i32>, String> {
+        let mut v = self.input.clone();
+        for (step, op) in self.ops.iter().enumerate() {
+            apply(&mut v, op).map_err(|message| format!("step {}: {}", step, message))?;
+        }
+        Ok(v)
+    }
+
+    // A step-by-step picture of the sort: the array after every swap or
+    // write, with the positions that just changed in brackets, and the
+    // comparisons in between, e.g.
+    //      2   1
+    //    compare 1 with 2
+    //    [1] [2]
+    pub fn render(&self) -> Result<String, String> {
+        let width = self
+            .input
+            .iter()
+            .map(|x| x.to_string().len())
+            .max()
+            .unwrap_or(1)
+            + 2;
+        let row = |v: &[i32], changed: &[usize]| {
+            let mut line = String::new();
+            for (i, x) in v.iter().enumerate() {
+                let cell = if changed.contains(&i) {
+                    format!("[{}]", x)
+                } else {
+                    x.to_string()
+                };
+                let _ = write!(line, "{:>w$} ", cell, w = width);
+            }
+            line.trim_end().to_string()
+        };
+        let mut v = self.input.clone();
+        let mut picture = format!("{}\n{}\n", self.algorithm, row(&v, &[]));
+        let (mut compares, mut moves) = (0, 0);
+        for (step, op) in self.ops.iter().enumerate() {
+            apply(&mut v, op).map_err(|message| format!("step {}: {}", step, message))?;
+            match *op {
+                Op::Compare { left, right } => {
+                    compares += 1;
+                    let _ = writeln!(picture, "  compare {} with {}", left, right);
+                }
+                Op::Swap { i, j } => {
+                    moves += 1;
+                    let _ = writeln!(picture, "{}", row(&v, &[i, j]));
+                }
+                Op::Set { index, .. } => {
+                    moves += 1;
+                    let _ = writeln!(picture, "{}", row(&v, &[index]));
+                }
+            }
+        }
+        let sorted = v.windows(2).all(|w| w[0] <= w[1]);
+        let _ = writeln!(
+            picture,
+            "{} comparisons, {} swaps or writes; {}",
+            compares,
+            moves,
+            if sorted { "sorted" } else { "NOT sorted" }
+        );
+        Ok(picture)
+    }
+
+    pub fn to_json(&self) -> String {
+        let input: Vec<String> = self.input.iter().map(|x| x.to_string()).collect();
+        let ops: Vec<String> = self
+            .ops
+            .iter()
+            .map(|op| match *op {
+                Op::Compare { left, right } => format!(
+                    "  {{\"op\": \"compare\", \"left\": {}, \"right\": {}}}",
+                    left, right
+                ),
+                Op::Swap { i, j } => format!("  {{\"op\": \"swap\", \"i\": {}, \"j\": {}}}", i, j),
+                Op::Set { index, value } => format!(
+                    "  {{\"op\": \"set\", \"index\": {}, \"value\": {}}}",
+                    index, value
+                ),
+            })
+            .collect();
+        format!(
+            "{{\"algorithm\": {}, \"input\": [{}], \"ops\": [\n{}\n]}}\n",
+            quote(&self.algorithm),
+            input.join(", "),
+            ops.join(",\n")
+        )
+    }
+
+    pub fn from_json(text: &str) -> Result<Trace, String> {
+        let json = json::parse(text)?;
+        let algorithm = json
+            .get("algorithm")
+            .and_then(Json::as_str)
+            .ok_or("trace needs an `algorithm` string")?
+            .to_string();
+        let input = json
+            .get("input")
+            .and_then(Json::as_array)
+            .ok_or("trace needs an `input` array")?
+            .iter()
+            .map(|x| int(Some(x), "input"))
+            .collect::<Result<Vec<i32>, String>>()?;
+        let ops = json
+            .get("ops")
+            .and_then(Json::as_array)
+            .ok_or("trace needs an `ops` array")?
+            .iter()
+            .enumerate()
+            .map(|(step, op)| parse_op(op).map_err(|message| format!("op {}: {}", step, message)))
+            .collect::<Result<Vec<Op>, String>>()?;
+        Ok(Trace {
+            algorithm,
+            input,
+            ops,
+        })
+    }
+}
+
+// Where two traces of the same input first part ways.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceDivergence {
+    // How many operations they had in common.
+    pub step: usize,
+    // What each did next (`None` if it had finished).
+    pub left: Option<Op>,
+    pub right: Option<Op>,
+    // The array just before they differed.
+    pub array: Vec<i32>,
+}
+
+// Compare two traces operation by operation. Returns `None` if they're
+// identical, and an error if they aren't traces of the same input (so
+// can't meaningfully be compared).
+pub fn diff(left: &Trace, right: &Trace) -> Result<Option<TraceDivergence>, String> {
+    if left.input != right.input {
+        return Err("the traces are of different inputs".to_string());
+    }
+    let mut v = left.input.clone();
+    for step in 0..left.ops.len().max(right.ops.len()) {
+        let (a, b) = (left.ops.get(step), right.ops.get(step));
+        if a != b {
+            return Ok(Some(TraceDivergence {
+                step,
+                left: a.copied(),
+                right: b.copied(),
+                array: v,
+            }));
+        }
+        apply(&mut v, a.unwrap()).map_err(|message| format!("step {}: {}", step, message))?;
+    }
+    Ok(None)
+}
+
+impl std::fmt::Display for Op {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            Op::Compare { left, right } => write!(f, "compare {} with {}", left, right),
+            Op::Swap { i, j } => write!(f, "swap positions {} and {}", i, j),
+            Op::Set { index, value } => write!(f, "set position {} to {}", index, value),
+        }
+    }
+}
+
+fn apply(v: &mut [i32], op: &Op) -> Result<(), String> {
+    let check = |i: usize| {
+        if i < v.len() {
+            Ok(())
+        } else {
+            Err(format!("index {} is past the end ({} values)", i, v.len()))
+        }
+    };
+    match *op {
+        Op::Compare { .. } => {}
+        Op::Swap { i, j } => {
+            check(i)?;
+            check(j)?;
+            v.swap(i, j);
+        }
+        Op::Set { index, value } => {
+            check(index)?;
+            v[index] = value;
+        }
+    }
+    Ok(())
+}
+
+fn int<T: std::convert::TryFrom<i64>>(value: Option<&Json>, name: &str) -> Result<T, String> {
+    value
+        .and_then(Json::as_i64)
+        .and_then(|n| T::try_from(n).ok())
+        .ok_or_else(|| format!("`{}` should be a whole number in range", name))
+}
+
+fn parse_op(op: &Json) -> Result<Op, String> {
+    match op.get("op").and_then(Json::as_str) {
+        Some("compare") => Ok(Op::Compare {
+            left: int(op.get("left"), "left")?,
+            right: int(op.get("right"), "right")?,
+        }),
+        Some("swap") => Ok(Op::Swap {
+            i: int(op.get("i"), "i")?,
+            j: int(op.get("j"), "j")?,
+        }),
+        Some("set") => Ok(Op::Set {
+            index: int(op.get("index"), "index")?,
+            value: int(op.get("value"), "value")?,
+        }),
+        Some(other) => Err(format!("unknown op `{}`", other)),
+        None => Err("needs an `op` name".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instrument::{count_comparisons, counted};
+    use crate::sorts::{insertion_sort, merge_sort, quicksort, selection_sort};
+
+    const TEN: [i32; 10] = [3, 2, 0, 5, 8, 9, 6, 3, 2, 0];
+
+    #[test]
+    fn replay_sorts() {
+        for &algorithm in &TRACEABLE {
+            let trace = record(algorithm, &TEN).unwrap();
+            assert_eq!(
+                vec![0, 0, 2, 2, 3, 3, 5, 6, 8, 9],
+                trace.replay().unwrap(),
+                "{}",
+                algorithm
+            );
+        }
+    }
+
+    #[test]
+    fn same_comparisons_as_the_real_sorts() {
+        let compares = |algorithm| {
+            let trace = record(algorithm, &TEN).unwrap();
+            trace
+                .ops
+                .iter()
+                .filter(|op| matches!(op, Op::Compare { .. }))
+                .count() as u64
+        };
+        let v = counted(&TEN);
+        assert_eq!(
+            count_comparisons(|| insertion_sort(&mut v.clone())).1,
+            compares("insertion")
+        );
+        assert_eq!(
+            count_comparisons(|| quicksort(&mut v.clone())).1,
+            compares("quicksort")
+        );
+        assert_eq!(count_comparisons(|| merge_sort(&v)).1, compares("merge"));
+        assert_eq!(
+            count_comparisons(|| selection_sort(&mut v.clone())).1,
+            compares("selection")
+        );
+    }
+
+    #[test]
+    fn selection_swaps_less_than_insertion() {
+        // Insertion sort swaps once per inversion, selection sort at
+        // most once per element.
+        let reversed: Vec<i32> = (0..100).rev().collect();
+        let insertion = count_ops("insertion", &reversed).unwrap();
+        let selection = count_ops("selection", &reversed).unwrap();
+        assert_eq!(100 * 99 / 2, insertion.swaps);
+        assert_eq!(50, selection.swaps);
+        assert_eq!(insertion.comparisons, selection.comparisons);
+    }
+
+    #[test]
+    fn counts_match_the_trace() {
+        for &algorithm in &TRACEABLE {
+            let trace = record(algorithm, &TEN).unwrap();
+            let counts = count_ops(algorithm, &TEN).unwrap();
+            let count =
+                |kind: fn(&Op) -> bool| trace.ops.iter().filter(|op| kind(op)).count() as u64;
+            assert_eq!(
+                (
+                    count(|op| matches!(op, Op::Compare { .. })),
+                    count(|op| matches!(op, Op::Swap { .. })),
+                    count(|op| matches!(op, Op::Set { .. }))
+                ),
+                (counts.comparisons, counts.swaps, counts.moves),
+                "{}",
+                algorithm
+            );
+        }
+        assert_eq!(1, count_ops("insertion", &TEN).unwrap().max_depth);
+        assert_eq!(OpCounts::default(), count_ops("merge", &[7]).unwrap());
+    }
+
+    #[test]
+    fn counts_any_size() {
+        let sorted: Vec<i32> = (0..1000).collect();
+        // Already sorted: every partition puts everything on one side.
+        let quick = count_ops("quicksort", &sorted).unwrap();
+        assert_eq!((999 * 1000 / 2, 999), (quick.comparisons, quick.max_depth));
+        assert_eq!(0, quick.swaps);
+        // Merge sort halves every time, whatever the input.
+        let merge = count_ops("merge", &sorted).unwrap();
+        assert_eq!(10, merge.max_depth);
+        assert!(count_ops("heapsort", &sorted).is_err());
+    }
+
+    #[test]
+    fn json_round_trip() {
+        for &algorithm in &TRACEABLE {
+            let trace = record(algorithm, &TEN).unwrap();
+            assert_eq!(Ok(trace.clone()), Trace::from_json(&trace.to_json()));
+        }
+        let empty = record("quicksort", &[]).unwrap();
+        assert_eq!(Ok(empty.clone()), Trace::from_json(&empty.to_json()));
+    }
+
+    #[test]
+    fn render() {
+        let trace = record("insertion", &[2, 1]).unwrap();
+        assert_eq!(
+            "insertion\n  2   1\n  compare 1 with 2\n[1] [2]\n1 comparisons, 1 swaps or writes; sorted\n",
+            trace.render().unwrap()
+        );
+    }
+
+    #[test]
+    fn identical_traces() {
+        let trace = record("quicksort", &TEN).unwrap();
+        assert_eq!(Ok(None), diff(&trace, &trace.clone()));
+    }
+
+    #[test]
+    fn finds_first_divergence() {
+        use crate::mutants::with_bugs;
+        let good = record("quicksort", &TEN).unwrap();
+        let bad = with_bugs(&[Bug::OffByOnePartition], || record("quicksort", &TEN)).unwrap();
+        let divergence = diff(&good, &bad).unwrap().unwrap();
+        // The first partition stops before comparing the last 0 with
+        // the pivot 3.
+        assert_eq!(Some(Op::Compare { left: 0, right: 3 }), divergence.left);
+        assert_ne!(divergence.left, divergence.right);
+        assert_eq!(good.ops[..divergence.step], bad.ops[..divergence.step]);
+        assert_eq!(10, divergence.array.len());
+        let other = record("quicksort", &[1, 2]).unwrap();
+        assert!(diff(&good, &other).is_err());
+    }
+
+    #[test]
+    fn one_trace_stops_early() {
+        let mut short = record("insertion", &[2, 1]).unwrap();
+        let long = short.clone();
+        short.ops.pop();
+        let divergence = diff(&short, &long).unwrap().unwrap();
+        assert_eq!(
+            (1, None, Some(Op::Swap { i: 0, j: 1 })),
+            (divergence.step, divergence.left, divergence.right)
+        );
+        assert_eq!(vec![2, 1], divergence.array);
+    }
+
+    #[test]
+    fn bad_traces() {
+        assert!(record("heapsort", &TEN).is_err());
+        assert!(record("insertion", &[0; MAX_TRACE_SIZE + 1]).is_err());
+        let past_the_end =
+            r#"{"algorithm": "x", "input": [1], "ops": [{"op": "swap", "i": 0, "j": 1}]}"#;
+        let trace = Trace::from_json(past_the_end).unwrap();
+        assert!(trace.replay().is_err());
+        assert!(trace.render().is_err());
+        assert!(Trace::from_json(r#"{"algorithm": "x", "input": [1.5], "ops": []}"#).is_err());
+        assert!(
+            Trace::from_json(r#"{"algorithm": "x", "input": [], "ops": [{"op": "jump"}]}"#)
+                .is_err()
+        );
+        assert!(Trace::from_json(r#"{"input": [], "ops": []}"#).is_err());
+    }
+}
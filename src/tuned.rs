@@ -0,0 +1,97 @@
+// Settings that depend on the machine more than on the algorithms, like
+// how big an input has to be before sorting it on several threads pays
+// for starting them. They live in `tuned.toml` at the top of the crate,
+// which is compiled in, so the numbers can be re-measured (with
+// `cargo bench --bench parallel_threshold`) and updated without
+// touching the code.
+//
+// We only need a tiny part of TOML: `key = integer` lines, with `#`
+// comments and `_` between digits, so we parse it ourselves.
+
+use std::sync::OnceLock;
+
+pub const TUNED_TOML: &str = include_str!("../tuned.toml");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tuned {
+    // Parallel merge sort sorts inputs smaller than this many bytes on
+    // one thread.
+    pub par_threshold_bytes: usize,
+}
+
+impl Tuned {
+    pub fn parse(text: &str) -> Result<Tuned, String> {
+        let mut par_threshold_bytes = None;
+        for (number, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (key, value) = match line.find('=') {
+                Some(i) => (line[..i].trim(), line[i + 1..].trim()),
+                None => return Err(format!("line {}: expected `key = value`", number + 1)),
+            };
+            let value: usize = value
+                .replace('_', "")
+                .parse()
+                .map_err(|_| format!("line {}: `{}` should be a number", number + 1, key))?;
+            match key {
+                "par_threshold_bytes" => par_threshold_bytes = Some(value),
+                _ => return Err(format!("line {}: unknown setting `{}`", number + 1, key)),
+            }
+        }
+        Ok(Tuned {
+            par_threshold_bytes: par_threshold_bytes
+                .ok_or_else(|| "missing `par_threshold_bytes`".to_string())?,
+        })
+    }
+
+    // The parallel threshold in elements of type `T`: inputs with fewer
+    // elements than this should be sorted on one thread.
+    pub fn par_threshold<T>(&self) -> usize {
+        (self.par_threshold_bytes / std::mem::size_of::<T>().max(1)).max(1)
+    }
+}
+
+// The settings from `tuned.toml`.
+pub fn tuned() -> &'static Tuned {
+    static TUNED: OnceLock<Tuned> = OnceLock::new();
+    TUNED.get_or_init(|| Tuned::parse(TUNED_TOML).expect("tuned.toml should be valid"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tuned_toml_is_valid() {
+        assert!(Tuned::parse(TUNED_TOML).is_ok());
+        assert!(tuned().par_threshold_bytes > 0);
+    }
+
+    #[test]
+    fn parse() {
+        let text = "# comment\n\npar_threshold_bytes = 1_048_576  # 1 MiB\n";
+        assert_eq!(
+            Ok(Tuned {
+                par_threshold_bytes: 1 << 20
+            }),
+            Tuned::parse(text)
+        );
+        assert!(Tuned::parse("").is_err());
+        assert!(Tuned::parse("par_threshold_bytes = lots").is_err());
+        assert!(Tuned::parse("par_threshold_bytes 10").is_err());
+        assert!(Tuned::parse("par_threshold_bytes = 10\ncolour = 3").is_err());
+    }
+
+    #[test]
+    fn threshold_scales_with_element_size() {
+        let tuned = Tuned {
+            par_threshold_bytes: 1 << 18,
+        };
+        assert_eq!(1 << 16, tuned.par_threshold::<i32>());
+        assert_eq!(1 << 13, tuned.par_threshold::<[u64; 4]>());
+        assert_eq!(1, tuned.par_threshold::<[u8; 1 << 20]>());
+        assert_eq!(1 << 18, tuned.par_threshold::<()>());
+    }
+}
@@ -0,0 +1,113 @@
+// Sorting inside async code. An async runtime like tokio runs many
+// tasks on a few threads, and a task only gives its thread back when it
+// reaches an `.await` that isn't ready. A big sort has no `.await`s in
+// it, so while it runs every other task on that thread is stuck waiting,
+// which shows up as latency spikes in a server.
+//
+// `sort_yielding` is heapsort with a yield point every `budget` steps,
+// where a step is one sift-down (O(log N) comparisons). A smaller budget
+// keeps other tasks more responsive at the cost of a little more
+// overhead. It doesn't depend on any particular runtime: yielding just
+// means returning `Pending` once after asking to be woken up again
+// straight away, which every executor understands.
+
+use crate::heap::sift_down;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+pub async fn sort_yielding<T: PartialOrd + std::fmt::Debug>(v: &mut [T], budget: usize) {
+    let budget = budget.max(1);
+    let mut steps = 0;
+    let length = v.len();
+    // This is `heapsort`, with the loops in `heapify` and `heapsort`
+    // written out so we can count the sift-downs.
+    for i in (0..length / 2).rev() {
+        sift_down(v, i);
+        steps += 1;
+        if steps % budget == 0 {
+            yield_now().await;
+        }
+    }
+    for end in (1..length).rev() {
+        v.swap(0, end);
+        sift_down(&mut v[0..end], 0);
+        steps += 1;
+        if steps % budget == 0 {
+            yield_now().await;
+        }
+    }
+}
+
+// Let the executor run something else before coming back to us.
+pub fn yield_now() -> YieldNow {
+    YieldNow { yielded: false }
+}
+
+pub struct YieldNow {
+    yielded: bool,
+}
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+        if self.yielded {
+            Poll::Ready(())
+        } else {
+            self.yielded = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::task::{Wake, Waker};
+
+    // A tiny executor for the tests: poll the future until it's done,
+    // counting how many times it yielded.
+    fn run<F: Future>(future: F) -> (F::Output, usize) {
+        struct Noop;
+        impl Wake for Noop {
+            fn wake(self: Arc<Self>) {}
+        }
+        let waker = Waker::from(Arc::new(Noop));
+        let mut cx = Context::from_waker(&waker);
+        let mut future = Box::pin(future);
+        let mut yields = 0;
+        loop {
+            match future.as_mut().poll(&mut cx) {
+                Poll::Ready(output) => return (output, yields),
+                Poll::Pending => yields += 1,
+            }
+        }
+    }
+
+    #[test]
+    fn empty() {
+        let mut input: [i32; 0] = [];
+        run(sort_yielding(&mut input, 1));
+        assert_eq!(input, []);
+    }
+
+    #[test]
+    fn ten_items() {
+        let mut input = [3, 2, 0, 5, 8, 9, 6, 3, 2, 0];
+        run(sort_yielding(&mut input, 3));
+        assert_eq!(input, [0, 0, 2, 2, 3, 3, 5, 6, 8, 9]);
+    }
+
+    #[test]
+    fn yields_every_budget_steps() {
+        // 10 elements take 5 sift-downs to heapify and 9 to sort.
+        let mut input = [3, 2, 0, 5, 8, 9, 6, 3, 2, 0];
+        let (_, yields) = run(sort_yielding(&mut input, 2));
+        assert_eq!(7, yields);
+        let (_, yields) = run(sort_yielding(&mut input, 100));
+        assert_eq!(0, yields);
+    }
+}
@@ -0,0 +1,123 @@
+// Runs `sorting-in-rust matrix` the way a grading script would: quietly,
+// with a JSON summary, and looking only at the exit code.
+
+use std::path::PathBuf;
+use std::process::Command;
+
+fn scratch(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("sorting-in-rust-{}-{}", std::process::id(), name))
+}
+
+fn matrix(args: &[&str]) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_sorting-in-rust"))
+        .arg("matrix")
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn quiet_summary_is_one_line() {
+    let output = matrix(&[
+        "--quiet",
+        "--summary-json",
+        "-",
+        "--algorithms",
+        "quicksort,merge",
+        "--sizes",
+        "10,100",
+        "--trials",
+        "2",
+    ]);
+    assert_eq!(Some(0), output.status.code());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(1, stdout.lines().count());
+    assert!(
+        stdout.starts_with("{\"status\": \"ok\", \"exit_code\": 0, \"runs\": 8, \"verified\": 8,")
+    );
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn summary_to_a_file() {
+    let summary = scratch("summary.json");
+    let output = matrix(&[
+        "--summary-json",
+        summary.to_str().unwrap(),
+        "--algorithms",
+        "insertion",
+        "--sizes",
+        "10",
+    ]);
+    assert_eq!(Some(0), output.status.code());
+    // Not quiet, so the usual results too.
+    assert!(String::from_utf8_lossy(&output.stdout).contains("rankings:"));
+    let summary = std::fs::read_to_string(&summary).unwrap();
+    assert!(summary.contains("\"winner\": \"insertion\""));
+}
+
+#[test]
+fn timeout() {
+    let output = matrix(&[
+        "--quiet",
+        "--summary-json",
+        "-",
+        "--algorithms",
+        "insertion",
+        "--sizes",
+        "1000000",
+        "--timeout",
+        "1ms",
+    ]);
+    assert_eq!(Some(4), output.status.code());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with("{\"status\": \"timeout\", \"exit_code\": 4, \"runs\": 0,"));
+}
+
+#[test]
+fn io_error() {
+    let nowhere = scratch("no-such-directory").join("summary.json");
+    let output = matrix(&[
+        "--quiet",
+        "--summary-json",
+        nowhere.to_str().unwrap(),
+        "--algorithms",
+        "insertion",
+        "--sizes",
+        "10",
+    ]);
+    assert_eq!(Some(5), output.status.code());
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("error: couldn't write "));
+}
+
+#[test]
+fn other_errors() {
+    let output = matrix(&["--quiet", "--summary-json", "-", "--max-memory", "1K"]);
+    assert_eq!(Some(1), output.status.code());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with("{\"status\": \"error\", \"exit_code\": 1,"));
+    assert_eq!(Some(2), matrix(&["--timeout"]).status.code());
+}
+
+#[test]
+fn count_only_is_the_same_every_time() {
+    let args = [
+        "--count-only",
+        "--format",
+        "json",
+        "--algorithms",
+        "quicksort,randomized-quicksort,merge",
+        "--sizes",
+        "100",
+    ];
+    let first = matrix(&args);
+    assert_eq!(Some(0), first.status.code());
+    assert_eq!(first.stdout, matrix(&args).stdout);
+    let stdout = String::from_utf8(first.stdout).unwrap();
+    assert!(stdout.contains("\"comparisons\": "));
+    assert!(!stdout.contains("elapsed"));
+    assert_eq!(
+        Some(2),
+        matrix(&["--count-only", "--soak", "1m"]).status.code()
+    );
+}
@@ -0,0 +1,85 @@
+// Runs the `pipeline` binary end to end on a small config.
+
+use std::path::PathBuf;
+use std::process::Command;
+
+fn scratch(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("sorting-in-rust-{}-{}", std::process::id(), name))
+}
+
+#[test]
+fn pipeline_writes_reports() {
+    let config = scratch("pipeline.conf");
+    let json = scratch("results.json");
+    let markdown = scratch("results.md");
+    std::fs::write(
+        &config,
+        "algorithms = quicksort, merge\nsizes = 10, 100\ntrials = 2\n",
+    )
+    .unwrap();
+
+    let status = Command::new(env!("CARGO_BIN_EXE_pipeline"))
+        .arg(&config)
+        .arg("--json")
+        .arg(&json)
+        .arg("--markdown")
+        .arg(&markdown)
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    // 2 algorithms × 2 sizes × 2 trials.
+    let json = std::fs::read_to_string(&json).unwrap();
+    assert_eq!(8, json.matches("\"verified\": true").count());
+    let markdown = std::fs::read_to_string(&markdown).unwrap();
+    // The settings line, a blank line, the header, and a row per run.
+    assert_eq!(2 + 2 + 8, markdown.lines().count());
+    assert!(json.contains("\"parallel\": {\"threads\": "));
+}
+
+#[test]
+fn pipeline_reads_parallel_settings_from_the_environment() {
+    let config = scratch("parallel.conf");
+    let json = scratch("parallel.json");
+    std::fs::write(&config, "algorithms = parallel-merge\nsizes = 100\n").unwrap();
+
+    let status = Command::new(env!("CARGO_BIN_EXE_pipeline"))
+        .arg(&config)
+        .arg("--json")
+        .arg(&json)
+        .env("SORTLAB_THREADS", "3")
+        .env("SORTLAB_PAR_THRESHOLD", "10")
+        .status()
+        .unwrap();
+    assert!(status.success());
+    let json = std::fs::read_to_string(&json).unwrap();
+    assert!(json.contains("{\"threads\": 3, \"chunk_size\": 65536, \"threshold\": 10}"));
+
+    // The config file wins over the environment.
+    std::fs::write(&config, "algorithms = parallel-merge\nthreads = 2\n").unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_pipeline"))
+        .arg(&config)
+        .env("SORTLAB_THREADS", "3")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).starts_with("parallel-merge: 2 threads,"));
+
+    let status = Command::new(env!("CARGO_BIN_EXE_pipeline"))
+        .arg(&config)
+        .env("SORTLAB_PAR_THRESHOLD", "lots")
+        .status()
+        .unwrap();
+    assert_eq!(Some(1), status.code());
+}
+
+#[test]
+fn pipeline_rejects_bad_config() {
+    let config = scratch("bad.conf");
+    std::fs::write(&config, "algorithms = bogosort\n").unwrap();
+    let status = Command::new(env!("CARGO_BIN_EXE_pipeline"))
+        .arg(&config)
+        .status()
+        .unwrap();
+    assert_eq!(Some(1), status.code());
+}
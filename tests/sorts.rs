@@ -0,0 +1,51 @@
+// Use the sorts the way another crate would, through the library's
+// public API, to make sure everything a downstream user needs is
+// exported.
+
+use sorting_in_rust::sorts::{heapsort, insertion_sort, merge_sort, quicksort};
+
+#[test]
+fn sorts_numbers() {
+    let unsorted = vec![3, 2, 0, 5, 8, 9, 6, 3, 2, 0];
+    let sorted = vec![0, 0, 2, 2, 3, 3, 5, 6, 8, 9];
+
+    let mut v = unsorted.clone();
+    insertion_sort(&mut v);
+    assert_eq!(sorted, v);
+
+    let mut v = unsorted.clone();
+    quicksort(&mut v);
+    assert_eq!(sorted, v);
+
+    let mut v = unsorted.clone();
+    heapsort(&mut v);
+    assert_eq!(sorted, v);
+
+    assert_eq!(sorted, merge_sort(&unsorted));
+}
+
+#[test]
+fn sorts_types_of_our_own() {
+    // Anything `PartialOrd`, `Copy`, and `Debug` will do.
+    #[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+    struct Student {
+        last: &'static str,
+        first: &'static str,
+    }
+    let student = |last, first| Student { last, first };
+    let mut v = vec![
+        student("Lovelace", "Ada"),
+        student("Hopper", "Grace"),
+        student("Hopper", "Alan"),
+    ];
+    let merged = merge_sort(&v);
+    let mut heaped = v.clone();
+    heapsort(&mut heaped);
+    quicksort(&mut v);
+    assert_eq!(merged, v);
+    assert_eq!(merged, heaped);
+    assert_eq!(
+        vec!["Alan", "Grace", "Ada"],
+        v.iter().map(|s| s.first).collect::<Vec<_>>()
+    );
+}
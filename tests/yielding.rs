@@ -0,0 +1,41 @@
+// Check that `sort_yielding` really lets other tasks run on a
+// single-threaded tokio runtime while it sorts.
+
+use sorting_in_rust::random::{RandomSource, SeededRandom};
+use sorting_in_rust::yielding::sort_yielding;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+#[tokio::test(flavor = "current_thread")]
+async fn other_tasks_run_during_the_sort() {
+    let mut rng = SeededRandom::new(235);
+    let mut v: Vec<u64> = (0..200_000).map(|_| rng.next_u64()).collect();
+
+    // A task that just counts how often it gets to run until the sort
+    // is done.
+    let done = Arc::new(AtomicBool::new(false));
+    let ticks = Arc::new(AtomicUsize::new(0));
+    let ticker = {
+        let done = done.clone();
+        let ticks = ticks.clone();
+        tokio::spawn(async move {
+            while !done.load(Ordering::SeqCst) {
+                ticks.fetch_add(1, Ordering::SeqCst);
+                tokio::task::yield_now().await;
+            }
+        })
+    };
+
+    sort_yielding(&mut v, 1000).await;
+    let ticks_during_sort = ticks.load(Ordering::SeqCst);
+    done.store(true, Ordering::SeqCst);
+    ticker.await.unwrap();
+
+    assert!(v.windows(2).all(|w| w[0] <= w[1]));
+    // About 300,000 sift-downs with a budget of 1000 is about 300 yields.
+    assert!(
+        ticks_during_sort >= 100,
+        "ticker only ran {} times",
+        ticks_during_sort
+    );
+}